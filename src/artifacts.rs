@@ -0,0 +1,1110 @@
+//! Locating the files a cargo build produces for a target
+
+use crate::cargo::resolve_target_dir;
+use crate::cli::Args;
+use crate::error::{CrossError, Result};
+use crate::metadata::{resolve_cargo_path, CargoMetadata, MetadataPackage, MetadataTarget};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Default `--artifact-name` template: the file name cargo gives the artifact
+pub const DEFAULT_NAME_TEMPLATE: &str = "{file}";
+
+/// Name template without `--artifact-name` when several targets are built, so the same
+/// binary built for each of them lands in its own directory
+pub const PER_TARGET_NAME_TEMPLATE: &str = "{target}/{file}";
+
+/// Kind of a collected build output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// `[[bin]]` executable
+    Bin,
+    /// `[[example]]` executable or library
+    Example,
+    /// Dynamic library for other languages (`crate-type = ["cdylib"]`)
+    Cdylib,
+    /// Static library for other languages (`crate-type = ["staticlib"]`)
+    Staticlib,
+}
+
+impl ArtifactKind {
+    /// Name used by the `{kind}` placeholder of `--artifact-name`
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Bin => "bin",
+            Self::Example => "example",
+            Self::Cdylib => "cdylib",
+            Self::Staticlib => "staticlib",
+        }
+    }
+}
+
+/// A file cargo writes to the profile output directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    /// Package that owns the target
+    pub package: String,
+    /// Target name as written in Cargo.toml
+    pub name: String,
+    /// What the file is
+    pub kind: ArtifactKind,
+    /// Path relative to the profile output directory
+    pub path: PathBuf,
+}
+
+/// A target left out because its `required-features` are not enabled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedTarget {
+    /// Package that owns the target
+    pub package: String,
+    /// Target name
+    pub name: String,
+    /// Required features that are not enabled
+    pub missing_features: Vec<String>,
+}
+
+/// Outputs expected from a build, plus the targets cargo will skip
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArtifactSet {
+    /// Files the build writes
+    pub artifacts: Vec<Artifact>,
+    /// Targets cargo will not build
+    pub skipped: Vec<SkippedTarget>,
+}
+
+/// Output directory name of a cargo profile
+#[must_use]
+pub fn profile_dir(profile: &str) -> &str {
+    match profile {
+        "dev" | "test" => "debug",
+        "bench" => "release",
+        other => other,
+    }
+}
+
+/// Directory cargo writes the final artifacts of `target` to
+#[must_use]
+pub fn output_dir(
+    metadata: &CargoMetadata,
+    target: &str,
+    args: &Args,
+    skip_target_arg: bool,
+) -> PathBuf {
+    let mut dir = match resolve_target_dir(target, args) {
+        Some(dir) => resolve_cargo_path(args, &dir),
+        None => metadata.target_directory.clone(),
+    };
+    if !skip_target_arg && !args.no_cargo_target {
+        dir.push(target);
+    }
+    dir.push(profile_dir(&args.profile));
+    dir
+}
+
+/// Packages cargo builds for the `-p` / `--workspace` / `--exclude` selection
+fn selected_packages<'a>(metadata: &'a CargoMetadata, args: &Args) -> Vec<&'a MetadataPackage> {
+    let matches = |patterns: &[String], name: &str| {
+        patterns.iter().any(|pattern| {
+            globset::Glob::new(pattern).map_or(pattern == name, |glob| {
+                glob.compile_matcher().is_match(name)
+            })
+        })
+    };
+    let members = |ids: &[String]| -> Vec<&'a MetadataPackage> {
+        ids.iter().filter_map(|id| metadata.package(id)).collect()
+    };
+
+    if !args.package.is_empty() {
+        members(&metadata.workspace_members)
+            .into_iter()
+            .filter(|pkg| matches(&args.package, &pkg.name))
+            .collect()
+    } else if args.workspace {
+        members(&metadata.workspace_members)
+            .into_iter()
+            .filter(|pkg| !matches(&args.exclude, &pkg.name))
+            .collect()
+    } else if metadata.workspace_default_members.is_empty() {
+        members(&metadata.workspace_members)
+    } else {
+        members(&metadata.workspace_default_members)
+    }
+}
+
+/// Features enabled for `package`, expanded through its `[features]` table.
+/// `None` means every feature is enabled (`--all-features`).
+fn enabled_features(package: &MetadataPackage, args: &Args) -> Option<BTreeSet<String>> {
+    if args.all_features {
+        return None;
+    }
+
+    let mut pending: Vec<String> = args
+        .features
+        .iter()
+        .flat_map(|features| features.split([',', ' ']))
+        .filter(|feature| !feature.is_empty())
+        .filter_map(|feature| match feature.split_once('/') {
+            // `package/feature` only applies to that package; dependency features are not ours
+            Some((pkg, feature)) => (pkg == package.name).then(|| feature.to_string()),
+            None => Some(feature.to_string()),
+        })
+        .collect();
+    if !args.no_default_features && package.features.contains_key("default") {
+        pending.push("default".to_string());
+    }
+
+    let mut enabled = BTreeSet::new();
+    while let Some(feature) = pending.pop() {
+        if let Some(enables) = package.features.get(&feature) {
+            pending.extend(
+                enables
+                    .iter()
+                    .filter(|f| !f.starts_with("dep:") && !enabled.contains(*f))
+                    .cloned(),
+            );
+        }
+        enabled.insert(feature);
+    }
+    Some(enabled)
+}
+
+/// Whether a target of the given kind is built for the `--lib/--bin/--example` filters.
+/// Without filters cargo builds the libraries and all binaries.
+fn target_selected(target: &MetadataTarget, kind: ArtifactKind, args: &Args) -> bool {
+    let filtered = args.build_lib
+        || args.build_bins
+        || args.bin_target.is_some()
+        || args.build_examples
+        || args.example_target.is_some()
+        || args.build_all_targets;
+    let name_matches = |pattern: &Option<String>| {
+        pattern.as_deref().is_some_and(|pattern| {
+            globset::Glob::new(pattern).map_or(pattern == target.name, |glob| {
+                glob.compile_matcher().is_match(&target.name)
+            })
+        })
+    };
+
+    match kind {
+        ArtifactKind::Cdylib | ArtifactKind::Staticlib => {
+            !filtered || args.build_lib || args.build_all_targets
+        }
+        ArtifactKind::Bin => {
+            !filtered || args.build_bins || args.build_all_targets || name_matches(&args.bin_target)
+        }
+        ArtifactKind::Example => {
+            args.build_examples || args.build_all_targets || name_matches(&args.example_target)
+        }
+    }
+}
+
+/// File name of an executable for `target`
+fn exe_name(name: &str, target: &str) -> String {
+    if target.contains("-windows") {
+        format!("{name}.exe")
+    } else {
+        name.to_string()
+    }
+}
+
+/// File name of a cdylib or staticlib; library crate names use `_` instead of `-`
+fn library_name(name: &str, crate_type: &str, target: &str) -> Option<String> {
+    let name = name.replace('-', "_");
+    let windows = target.contains("-windows");
+    let apple = target.contains("-apple-");
+    Some(match crate_type {
+        "cdylib" if windows => format!("{name}.dll"),
+        "cdylib" if apple => format!("lib{name}.dylib"),
+        "cdylib" => format!("lib{name}.so"),
+        "staticlib" if target.ends_with("-msvc") => format!("{name}.lib"),
+        "staticlib" => format!("lib{name}.a"),
+        _ => return None,
+    })
+}
+
+/// Files a build of `target` produces, relative to [`output_dir`].
+///
+/// Follows cargo's target selection: binaries are enumerated from `[[bin]]` targets rather
+/// than package names, and targets whose `required-features` are not enabled are skipped.
+#[must_use]
+pub fn expected_artifacts(metadata: &CargoMetadata, args: &Args, target: &str) -> ArtifactSet {
+    let mut set = ArtifactSet::default();
+    for package in selected_packages(metadata, args) {
+        let features = enabled_features(package, args);
+        for build_target in &package.targets {
+            let is_example = build_target.kind.iter().any(|kind| kind == "example");
+            let mut outputs = Vec::new();
+            if is_example {
+                for crate_type in &build_target.crate_types {
+                    let file = if crate_type == "bin" {
+                        Some(exe_name(&build_target.name, target))
+                    } else {
+                        library_name(&build_target.name, crate_type, target)
+                    };
+                    if let Some(file) = file {
+                        outputs.push((ArtifactKind::Example, PathBuf::from("examples").join(file)));
+                    }
+                }
+            } else if build_target.kind.iter().any(|kind| kind == "bin") {
+                outputs.push((
+                    ArtifactKind::Bin,
+                    exe_name(&build_target.name, target).into(),
+                ));
+            } else {
+                for crate_type in &build_target.crate_types {
+                    let kind = match crate_type.as_str() {
+                        "cdylib" => ArtifactKind::Cdylib,
+                        "staticlib" => ArtifactKind::Staticlib,
+                        _ => continue,
+                    };
+                    if let Some(file) = library_name(&build_target.name, crate_type, target) {
+                        outputs.push((kind, file.into()));
+                    }
+                }
+            }
+
+            outputs.retain(|(kind, _)| target_selected(build_target, *kind, args));
+            if outputs.is_empty() {
+                continue;
+            }
+
+            let missing_features: Vec<String> =
+                features.as_ref().map_or_else(Vec::new, |enabled| {
+                    build_target
+                        .required_features
+                        .iter()
+                        .filter(|feature| !enabled.contains(*feature))
+                        .cloned()
+                        .collect()
+                });
+            if !missing_features.is_empty() {
+                set.skipped.push(SkippedTarget {
+                    package: package.name.clone(),
+                    name: build_target.name.clone(),
+                    missing_features,
+                });
+                continue;
+            }
+
+            set.artifacts
+                .extend(outputs.into_iter().map(|(kind, path)| Artifact {
+                    package: package.name.clone(),
+                    name: build_target.name.clone(),
+                    kind,
+                    path,
+                }));
+        }
+    }
+    set
+}
+
+/// An artifact of one target and the file name it is collected as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedArtifact {
+    pub target: String,
+    pub artifact: Artifact,
+    /// File name in the artifact directory
+    pub dest: String,
+}
+
+/// File name `template` gives `artifact` built for `target`.
+/// Placeholders: `{package}`, `{name}`, `{target}`, `{kind}` and `{file}` (cargo's file name).
+#[must_use]
+pub fn render_name(template: &str, artifact: &Artifact, target: &str) -> String {
+    let file = artifact
+        .path
+        .file_name()
+        .map(|file| file.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    template
+        .replace("{package}", &artifact.package)
+        .replace("{name}", &artifact.name)
+        .replace("{target}", target)
+        .replace("{kind}", artifact.kind.as_str())
+        .replace("{file}", &file)
+}
+
+/// `package/path (target)`, to name an artifact in messages
+fn describe(target: &str, artifact: &Artifact) -> String {
+    format!(
+        "{}/{} ({target})",
+        artifact.package,
+        artifact.path.to_string_lossy().replace('\\', "/")
+    )
+}
+
+/// Names `template` gives to more than one of `artifacts`, with the artifacts sharing each
+#[must_use]
+pub fn find_collisions(
+    artifacts: &[(String, Artifact)],
+    template: &str,
+) -> BTreeMap<String, Vec<String>> {
+    let mut by_name: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (target, artifact) in artifacts {
+        by_name
+            .entry(render_name(template, artifact, target))
+            .or_default()
+            .push(describe(target, artifact));
+    }
+    by_name.retain(|_, sharing| sharing.len() > 1);
+    by_name
+}
+
+/// Assign the file name every `(target, artifact)` pair is collected as.
+///
+/// When `template` gives artifacts of different packages the same name and does not
+/// mention `{package}` itself, their file names get the package name as a prefix. Names that
+/// still collide are an error. The plan is sorted by target and file name.
+pub fn plan_collection(
+    artifacts: &[(String, Artifact)],
+    template: &str,
+) -> Result<Vec<PlannedArtifact>> {
+    let mut packages: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+    for (target, artifact) in artifacts {
+        packages
+            .entry(render_name(template, artifact, target))
+            .or_default()
+            .insert(&artifact.package);
+    }
+    // The prefix goes on the file name, after any directories in the template
+    let split = template.rfind('/').map_or(0, |slash| slash + 1);
+    let prefixed = format!("{}{{package}}-{}", &template[..split], &template[split..]);
+
+    let mut planned: Vec<PlannedArtifact> = artifacts
+        .iter()
+        .map(|(target, artifact)| {
+            let name = render_name(template, artifact, target);
+            let dest = if packages[&name].len() > 1 && !template.contains("{package}") {
+                render_name(&prefixed, artifact, target)
+            } else {
+                name
+            };
+            PlannedArtifact {
+                target: target.clone(),
+                artifact: artifact.clone(),
+                dest,
+            }
+        })
+        .collect();
+    planned.sort_by(|a, b| (&a.target, &a.dest).cmp(&(&b.target, &b.dest)));
+
+    let mut by_dest: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for entry in &planned {
+        by_dest
+            .entry(&entry.dest)
+            .or_default()
+            .push(describe(&entry.target, &entry.artifact));
+    }
+    if let Some((name, sharing)) = by_dest.into_iter().find(|(_, sharing)| sharing.len() > 1) {
+        return Err(CrossError::ArtifactNameCollision {
+            name: name.to_string(),
+            artifacts: sharing.join(", "),
+        });
+    }
+    Ok(planned)
+}
+
+/// Collect every `(target, artifact)` pair under cargo's own file name, like cargo's
+/// `--artifact-dir` does, in a directory per target when there is more than one target.
+/// Artifacts that would overwrite each other are an error.
+pub fn plan_flat(artifacts: &[(String, Artifact)]) -> Result<Vec<PlannedArtifact>> {
+    let targets: BTreeSet<&str> = artifacts
+        .iter()
+        .map(|(target, _)| target.as_str())
+        .collect();
+    let template = if targets.len() > 1 {
+        PER_TARGET_NAME_TEMPLATE
+    } else {
+        DEFAULT_NAME_TEMPLATE
+    };
+    if let Some((name, sharing)) = find_collisions(artifacts, template).into_iter().next() {
+        return Err(CrossError::ArtifactNameCollision {
+            name,
+            artifacts: sharing.join(", "),
+        });
+    }
+    Ok(artifacts
+        .iter()
+        .map(|(target, artifact)| PlannedArtifact {
+            target: target.clone(),
+            artifact: artifact.clone(),
+            dest: render_name(template, artifact, target),
+        })
+        .collect())
+}
+
+/// Copy the planned artifacts of `target` from `output_dir` into `artifact_dir`.
+/// Artifacts that were not built are left out. Returns the copied sources and destinations.
+pub fn collect(
+    planned: &[PlannedArtifact],
+    target: &str,
+    output_dir: &Path,
+    artifact_dir: &Path,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut copied = Vec::new();
+    for entry in planned.iter().filter(|entry| entry.target == target) {
+        let source = output_dir.join(&entry.artifact.path);
+        if !source.is_file() {
+            continue;
+        }
+        let dest = artifact_dir.join(&entry.dest);
+        let parent = dest.parent().unwrap_or(artifact_dir);
+        std::fs::create_dir_all(parent).map_err(|source| CrossError::IoError {
+            message: format!("Failed to create {}", parent.display()),
+            source,
+        })?;
+        std::fs::copy(&source, &dest).map_err(|e| CrossError::IoError {
+            message: format!("Failed to copy {} to {}", source.display(), dest.display()),
+            source: e,
+        })?;
+        copied.push((source, dest));
+    }
+    Ok(copied)
+}
+
+/// Compile a `--collect-glob` pattern. `*` and `?` stop at `/`; `**` crosses directories.
+pub fn collect_glob(pattern: &str) -> Result<globset::Glob> {
+    if Path::new(pattern).is_absolute()
+        || pattern.starts_with('/')
+        || pattern.split(['/', '\\']).any(|part| part == "..")
+    {
+        return Err(CrossError::InvalidArgument(format!(
+            "collect glob '{pattern}' must stay inside the output directory"
+        )));
+    }
+    globset::GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map_err(|e| CrossError::InvalidArgument(format!("invalid collect glob '{pattern}': {e}")))
+}
+
+/// Copy the files under `output_dir` whose path relative to it matches one of `patterns`
+/// into `artifact_dir`, keeping their relative paths.
+///
+/// Symbolic links are never descended into; a link to a file is copied only when it resolves
+/// inside `output_dir`. `artifact_dir` itself is skipped when it lies in `output_dir`.
+/// Returns the copied sources and destinations, sorted by source.
+pub fn collect_globs(
+    patterns: &[String],
+    output_dir: &Path,
+    artifact_dir: &Path,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    if patterns.is_empty() || !output_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut builder = globset::GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(collect_glob(pattern)?);
+    }
+    let set = builder
+        .build()
+        .map_err(|e| CrossError::InvalidArgument(format!("invalid collect glob: {e}")))?;
+    let io_error = |path: &Path| {
+        let message = format!("Failed to read {}", path.display());
+        move |source| CrossError::IoError { message, source }
+    };
+    let root = output_dir.canonicalize().map_err(io_error(output_dir))?;
+
+    let mut matches = Vec::new();
+    let mut pending = vec![output_dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir).map_err(io_error(&dir))? {
+            let entry = entry.map_err(io_error(&dir))?;
+            let path = entry.path();
+            if path.starts_with(artifact_dir) {
+                continue;
+            }
+            let file_type = entry.file_type().map_err(io_error(&path))?;
+            if file_type.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if file_type.is_symlink()
+                && !path
+                    .canonicalize()
+                    .is_ok_and(|resolved| resolved.starts_with(&root) && resolved.is_file())
+            {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(output_dir) else {
+                continue;
+            };
+            // Only plain names, so the destination cannot leave artifact_dir
+            let parts: Option<Vec<&str>> = relative
+                .components()
+                .map(|component| match component {
+                    std::path::Component::Normal(part) => part.to_str(),
+                    _ => None,
+                })
+                .collect();
+            if parts.is_some_and(|parts| set.is_match(parts.join("/"))) {
+                let dest = artifact_dir.join(relative);
+                matches.push((path, dest));
+            }
+        }
+    }
+    matches.sort();
+
+    for (source, dest) in &matches {
+        let parent = dest.parent().unwrap_or(artifact_dir);
+        std::fs::create_dir_all(parent).map_err(|source| CrossError::IoError {
+            message: format!("Failed to create {}", parent.display()),
+            source,
+        })?;
+        std::fs::copy(source, dest).map_err(|e| CrossError::IoError {
+            message: format!("Failed to copy {} to {}", source.display(), dest.display()),
+            source: e,
+        })?;
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{test_target_args, BuildArgs};
+
+    /// `acme-agent` ships two differently named binaries, a feature-gated one,
+    /// an example sharing a binary's name, and a C library
+    const FIXTURE: &str = r#"{
+        "packages": [
+            {
+                "id": "path+file:///ws/agent#acme-agent@0.1.0",
+                "name": "acme-agent",
+                "features": {"default": ["tls"], "tls": [], "admin": ["dep:clap"]},
+                "targets": [
+                    {"name": "acme_agent", "kind": ["lib"], "crate_types": ["lib"], "required-features": []},
+                    {"name": "acmed", "kind": ["bin"], "crate_types": ["bin"]},
+                    {"name": "acme-cli", "kind": ["bin"], "crate_types": ["bin"], "required-features": ["tls"]},
+                    {"name": "acme-admin", "kind": ["bin"], "crate_types": ["bin"], "required-features": ["admin"]},
+                    {"name": "acmed", "kind": ["example"], "crate_types": ["bin"]},
+                    {"name": "build-script-build", "kind": ["custom-build"], "crate_types": ["bin"]}
+                ]
+            },
+            {
+                "id": "path+file:///ws/ffi#acme-ffi@0.1.0",
+                "name": "acme-ffi",
+                "features": {},
+                "targets": [
+                    {"name": "acme-ffi", "kind": ["cdylib", "staticlib"], "crate_types": ["cdylib", "staticlib"]},
+                    {"name": "ffi-demo", "kind": ["example"], "crate_types": ["cdylib"]}
+                ]
+            }
+        ],
+        "workspace_members": [
+            "path+file:///ws/agent#acme-agent@0.1.0",
+            "path+file:///ws/ffi#acme-ffi@0.1.0"
+        ],
+        "workspace_default_members": ["path+file:///ws/agent#acme-agent@0.1.0"],
+        "target_directory": "/ws/target",
+        "workspace_root": "/ws"
+    }"#;
+
+    fn args(build: BuildArgs) -> Args {
+        test_target_args(
+            "artifacts",
+            &["aarch64-unknown-linux-musl"],
+            BuildArgs {
+                profile: "dev".to_string(),
+                ..build
+            },
+        )
+    }
+
+    fn paths(set: &ArtifactSet) -> Vec<String> {
+        set.artifacts
+            .iter()
+            .map(|artifact| artifact.path.to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn test_expected_artifacts() {
+        let metadata = CargoMetadata::from_json(FIXTURE).unwrap();
+        let cases: &[(&str, BuildArgs, &str, &[&str])] = &[
+            (
+                "default members, default features",
+                BuildArgs::default(),
+                "aarch64-unknown-linux-musl",
+                &["acmed", "acme-cli"],
+            ),
+            (
+                "windows executables get .exe",
+                BuildArgs::default(),
+                "x86_64-pc-windows-gnu",
+                &["acmed.exe", "acme-cli.exe"],
+            ),
+            (
+                "package-qualified feature",
+                BuildArgs {
+                    features: Some("acme-agent/admin".to_string()),
+                    ..BuildArgs::default()
+                },
+                "aarch64-unknown-linux-musl",
+                &["acmed", "acme-cli", "acme-admin"],
+            ),
+            (
+                "no default features skips tls-gated bin",
+                BuildArgs {
+                    no_default_features: true,
+                    ..BuildArgs::default()
+                },
+                "aarch64-unknown-linux-musl",
+                &["acmed"],
+            ),
+            (
+                "--bin glob",
+                BuildArgs {
+                    bin_target: Some("acme-*".to_string()),
+                    all_features: true,
+                    ..BuildArgs::default()
+                },
+                "aarch64-unknown-linux-musl",
+                &["acme-cli", "acme-admin"],
+            ),
+            (
+                "example named like a bin",
+                BuildArgs {
+                    build_examples: true,
+                    ..BuildArgs::default()
+                },
+                "x86_64-pc-windows-msvc",
+                &["examples/acmed.exe"],
+            ),
+            (
+                "cdylib and staticlib names use underscores",
+                BuildArgs {
+                    package: vec!["acme-ffi".to_string()],
+                    ..BuildArgs::default()
+                },
+                "aarch64-unknown-linux-musl",
+                &["libacme_ffi.so", "libacme_ffi.a"],
+            ),
+            (
+                "apple libraries and library examples",
+                BuildArgs {
+                    package: vec!["acme-ffi".to_string()],
+                    build_lib: true,
+                    build_examples: true,
+                    ..BuildArgs::default()
+                },
+                "aarch64-apple-darwin",
+                &[
+                    "libacme_ffi.dylib",
+                    "libacme_ffi.a",
+                    "examples/libffi_demo.dylib",
+                ],
+            ),
+            (
+                "msvc libraries",
+                BuildArgs {
+                    package: vec!["acme-ffi".to_string()],
+                    ..BuildArgs::default()
+                },
+                "x86_64-pc-windows-msvc",
+                &["acme_ffi.dll", "acme_ffi.lib"],
+            ),
+            (
+                "workspace with exclude",
+                BuildArgs {
+                    workspace: true,
+                    exclude: vec!["acme-agent".to_string()],
+                    ..BuildArgs::default()
+                },
+                "aarch64-unknown-linux-musl",
+                &["libacme_ffi.so", "libacme_ffi.a"],
+            ),
+        ];
+
+        for (name, build, target, expected) in cases {
+            let set = expected_artifacts(&metadata, &args(build.clone()), target);
+            assert_eq!(paths(&set), *expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_skipped_targets_report_missing_features() {
+        let metadata = CargoMetadata::from_json(FIXTURE).unwrap();
+        let set = expected_artifacts(
+            &metadata,
+            &args(BuildArgs::default()),
+            "aarch64-unknown-linux-musl",
+        );
+        assert_eq!(
+            set.skipped,
+            vec![SkippedTarget {
+                package: "acme-agent".to_string(),
+                name: "acme-admin".to_string(),
+                missing_features: vec!["admin".to_string()],
+            }]
+        );
+        assert_eq!(set.artifacts[1].kind, ArtifactKind::Bin);
+        assert_eq!(set.artifacts[1].name, "acme-cli");
+    }
+
+    #[test]
+    fn test_output_dir() {
+        let metadata = CargoMetadata::from_json(FIXTURE).unwrap();
+        let mut args = args(BuildArgs::default());
+        assert_eq!(
+            output_dir(&metadata, "aarch64-unknown-linux-musl", &args, false),
+            PathBuf::from("/ws/target/aarch64-unknown-linux-musl/debug")
+        );
+        assert_eq!(
+            output_dir(&metadata, "x86_64-unknown-linux-gnu", &args, true),
+            PathBuf::from("/ws/target/debug")
+        );
+        args.profile = "release".to_string();
+        args.cargo_target_dir = Some(PathBuf::from("/out"));
+        assert_eq!(
+            output_dir(&metadata, "aarch64-unknown-linux-musl", &args, false),
+            PathBuf::from("/out/aarch64-unknown-linux-musl/release")
+        );
+
+        // Relative --target-dir is relative to -C, which is relative to the current directory
+        args.cargo_cwd = Some(PathBuf::from("project"));
+        args.cargo_target_dir = Some(PathBuf::from("build"));
+        assert_eq!(
+            output_dir(&metadata, "aarch64-unknown-linux-musl", &args, false),
+            std::env::current_dir()
+                .unwrap()
+                .join("project/build/aarch64-unknown-linux-musl/release")
+        );
+    }
+    fn bin(package: &str, name: &str, target: &str) -> (String, Artifact) {
+        (
+            target.to_string(),
+            Artifact {
+                package: package.to_string(),
+                name: name.to_string(),
+                kind: ArtifactKind::Bin,
+                path: exe_name(name, target).into(),
+            },
+        )
+    }
+
+    fn mapping(plan: &[PlannedArtifact]) -> Vec<(String, String, String)> {
+        plan.iter()
+            .map(|entry| {
+                (
+                    entry.target.clone(),
+                    entry.artifact.package.clone(),
+                    entry.dest.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_render_name() {
+        let (target, artifact) = bin("acme-agent", "acmed", "x86_64-pc-windows-gnu");
+        assert_eq!(
+            render_name(DEFAULT_NAME_TEMPLATE, &artifact, &target),
+            "acmed.exe"
+        );
+        assert_eq!(
+            render_name("{target}/{package}-{kind}-{name}", &artifact, &target),
+            "x86_64-pc-windows-gnu/acme-agent-bin-acmed"
+        );
+    }
+
+    #[test]
+    fn test_plan_flat_keeps_cargo_file_names() {
+        let gnu = "x86_64-pc-windows-gnu";
+        let musl = "aarch64-unknown-linux-musl";
+        let artifacts = vec![bin("server", "server", musl), bin("client", "cli", musl)];
+        assert_eq!(
+            mapping(&plan_flat(&artifacts).unwrap()),
+            vec![
+                (musl.to_string(), "server".to_string(), "server".to_string()),
+                (musl.to_string(), "client".to_string(), "cli".to_string()),
+            ]
+        );
+
+        // Several targets each get a directory
+        let artifacts = vec![
+            bin("server", "server", musl),
+            bin("client", "cli", musl),
+            bin("client", "client", gnu),
+        ];
+        assert_eq!(
+            mapping(&plan_flat(&artifacts).unwrap()),
+            vec![
+                (
+                    musl.to_string(),
+                    "server".to_string(),
+                    format!("{musl}/server")
+                ),
+                (
+                    musl.to_string(),
+                    "client".to_string(),
+                    format!("{musl}/cli")
+                ),
+                (
+                    gnu.to_string(),
+                    "client".to_string(),
+                    format!("{gnu}/client.exe")
+                ),
+            ]
+        );
+
+        // One package's binary built for several targets
+        let x86 = "x86_64-unknown-linux-musl";
+        let artifacts = vec![bin("acme", "acme", musl), bin("acme", "acme", x86)];
+        assert_eq!(
+            mapping(&plan_flat(&artifacts).unwrap()),
+            vec![
+                (musl.to_string(), "acme".to_string(), format!("{musl}/acme")),
+                (x86.to_string(), "acme".to_string(), format!("{x86}/acme")),
+            ]
+        );
+
+        // Two binaries cargo would write to the same file stop the build
+        let colliding = vec![bin("server", "cli", musl), bin("client", "cli", musl)];
+        let err = plan_flat(&colliding).unwrap_err().to_string();
+        assert!(err.contains("would all be collected as 'cli'"), "{err}");
+        assert!(err.contains("--artifact-name"), "{err}");
+    }
+
+    #[test]
+    fn test_plan_collection_prefixes_colliding_packages() {
+        let musl = "aarch64-unknown-linux-musl";
+        let artifacts = vec![
+            bin("server", "cli", musl),
+            bin("client", "cli", musl),
+            bin("client", "client", musl),
+        ];
+        let collisions = find_collisions(&artifacts, DEFAULT_NAME_TEMPLATE);
+        assert_eq!(
+            collisions.into_iter().collect::<Vec<_>>(),
+            vec![(
+                "cli".to_string(),
+                vec![
+                    format!("server/cli ({musl})"),
+                    format!("client/cli ({musl})")
+                ]
+            )]
+        );
+
+        let plan = plan_collection(&artifacts, DEFAULT_NAME_TEMPLATE).unwrap();
+        let expected = |rows: &[(&str, &str)]| -> Vec<(String, String, String)> {
+            rows.iter()
+                .map(|(package, dest)| {
+                    (
+                        musl.to_string(),
+                        (*package).to_string(),
+                        (*dest).to_string(),
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(
+            mapping(&plan),
+            expected(&[
+                ("client", "client"),
+                ("client", "client-cli"),
+                ("server", "server-cli"),
+            ])
+        );
+        // The input order does not change the result
+        let mut reversed = artifacts.clone();
+        reversed.reverse();
+        assert_eq!(
+            plan_collection(&reversed, DEFAULT_NAME_TEMPLATE).unwrap(),
+            plan
+        );
+    }
+
+    #[test]
+    fn test_plan_collection_errors_when_template_still_collides() {
+        let artifacts = vec![
+            bin("acme-agent", "acmed", "aarch64-unknown-linux-musl"),
+            bin("acme-agent", "acmed", "x86_64-unknown-linux-musl"),
+        ];
+        let err = plan_collection(&artifacts, DEFAULT_NAME_TEMPLATE).unwrap_err();
+        assert!(
+            matches!(err, CrossError::ArtifactNameCollision { ref name, ref artifacts }
+                if name == "acmed" && artifacts.contains("(x86_64-unknown-linux-musl)")),
+            "{err}"
+        );
+        assert!(err.to_string().contains("{target}"), "{err}");
+
+        let plan = plan_collection(&artifacts, "{target}/{file}").unwrap();
+        assert_eq!(
+            plan.iter()
+                .map(|entry| entry.dest.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "aarch64-unknown-linux-musl/acmed",
+                "x86_64-unknown-linux-musl/acmed"
+            ]
+        );
+
+        // Different packages under a template that already names the package
+        let artifacts = vec![
+            bin("a", "b-cli", "aarch64-unknown-linux-musl"),
+            bin("a-b", "cli", "aarch64-unknown-linux-musl"),
+        ];
+        assert!(plan_collection(&artifacts, "{package}-{name}").is_err());
+        // A prefixed name may also run into another artifact's plain name
+        let artifacts = vec![
+            bin("a", "cli", "aarch64-unknown-linux-musl"),
+            bin("b", "cli", "aarch64-unknown-linux-musl"),
+            bin("c", "a-cli", "aarch64-unknown-linux-musl"),
+        ];
+        assert!(plan_collection(&artifacts, DEFAULT_NAME_TEMPLATE).is_err());
+    }
+
+    #[test]
+    fn test_plan_collection_for_workspace() {
+        let metadata = CargoMetadata::from_json(FIXTURE).unwrap();
+        let args = args(BuildArgs {
+            workspace: true,
+            build_examples: true,
+            build_bins: true,
+            ..BuildArgs::default()
+        });
+        let target = "aarch64-unknown-linux-musl";
+        let artifacts: Vec<(String, Artifact)> = expected_artifacts(&metadata, &args, target)
+            .artifacts
+            .into_iter()
+            .map(|artifact| (target.to_string(), artifact))
+            .collect();
+        // The acmed example and binary of one package cannot be told apart by package
+        assert!(plan_collection(&artifacts, DEFAULT_NAME_TEMPLATE).is_err());
+        let plan = plan_collection(&artifacts, "{kind}/{file}").unwrap();
+        assert_eq!(
+            plan.iter()
+                .map(|entry| entry.dest.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "bin/acme-cli",
+                "bin/acmed",
+                "example/acmed",
+                "example/libffi_demo.so"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect() {
+        let root =
+            std::env::temp_dir().join(format!("cargo-cross-collect-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let output = root.join("target/aarch64-unknown-linux-musl/release");
+        std::fs::create_dir_all(&output).unwrap();
+        std::fs::write(output.join("cli"), "cli").unwrap();
+
+        let musl = "aarch64-unknown-linux-musl";
+        let artifacts = vec![
+            bin("server", "cli", musl),
+            bin("client", "cli", musl),
+            bin("server", "cli", "x86_64-unknown-linux-musl"),
+        ];
+        let plan = plan_collection(&artifacts, "{target}/{file}").unwrap();
+        let dist = root.join("dist");
+        let copied = collect(&plan, musl, &output, &dist).unwrap();
+        // Only files of this target are copied
+        assert_eq!(
+            copied,
+            vec![
+                (output.join("cli"), dist.join(musl).join("client-cli")),
+                (output.join("cli"), dist.join(musl).join("server-cli")),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dist.join(musl).join("server-cli")).unwrap(),
+            "cli"
+        );
+        assert!(!dist.join("x86_64-unknown-linux-musl").exists());
+
+        // Artifacts that were not built are left out
+        std::fs::remove_file(output.join("cli")).unwrap();
+        assert!(collect(&plan, musl, &output, &dist).unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_collect_glob_patterns() {
+        assert!(collect_glob("deps/*.ll").is_ok());
+        assert!(collect_glob("**/*.s").is_ok());
+        for pattern in ["../secrets/*", "deps/../../x", "/etc/*", "deps/[a"] {
+            assert!(collect_glob(pattern).is_err(), "{pattern}");
+        }
+        // `*` does not cross directories
+        let glob = collect_glob("*.ll").unwrap().compile_matcher();
+        assert!(glob.is_match("app.ll"));
+        assert!(!glob.is_match("deps/app.ll"));
+    }
+
+    #[test]
+    fn test_collect_globs() {
+        let root =
+            std::env::temp_dir().join(format!("cargo-cross-collect-glob-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let output = root.join("target/aarch64-unknown-linux-musl/release");
+        std::fs::create_dir_all(output.join("deps")).unwrap();
+        std::fs::create_dir_all(output.join("build/app-1234/out")).unwrap();
+        std::fs::write(output.join("deps/app-1234.ll"), "ir").unwrap();
+        std::fs::write(output.join("deps/app-1234.o"), "obj").unwrap();
+        std::fs::write(output.join("build/app-1234/out/gen.ll"), "gen").unwrap();
+        std::fs::write(root.join("outside.ll"), "outside").unwrap();
+
+        let dist = root.join("dist");
+        let patterns = vec!["deps/*.ll".to_string(), "build/**/*.ll".to_string()];
+        let copied = collect_globs(&patterns, &output, &dist).unwrap();
+        assert_eq!(
+            copied,
+            vec![
+                (
+                    output.join("build/app-1234/out/gen.ll"),
+                    dist.join("build/app-1234/out/gen.ll")
+                ),
+                (
+                    output.join("deps/app-1234.ll"),
+                    dist.join("deps/app-1234.ll")
+                ),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dist.join("deps/app-1234.ll")).unwrap(),
+            "ir"
+        );
+
+        // Links are not followed out of the output directory
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.join("outside.ll"), output.join("deps/escape.ll"))
+                .unwrap();
+            std::os::unix::fs::symlink(&root, output.join("deps/up")).unwrap();
+            std::os::unix::fs::symlink(output.join("deps/app-1234.ll"), output.join("app.ll"))
+                .unwrap();
+            let patterns = vec!["**/*.ll".to_string()];
+            let copied: Vec<PathBuf> = collect_globs(&patterns, &output, &dist)
+                .unwrap()
+                .into_iter()
+                .map(|(source, _)| source)
+                .collect();
+            assert_eq!(
+                copied,
+                vec![
+                    output.join("app.ll"),
+                    output.join("build/app-1234/out/gen.ll"),
+                    output.join("deps/app-1234.ll"),
+                ]
+            );
+        }
+
+        // An artifact dir inside the output directory is not collected again
+        let nested = output.join("dist");
+        collect_globs(&["deps/*.ll".to_string()], &output, &nested).unwrap();
+        let copied = collect_globs(&["**/*.ll".to_string()], &output, &nested).unwrap();
+        assert!(copied
+            .iter()
+            .all(|(source, _)| !source.starts_with(&nested)));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
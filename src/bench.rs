@@ -0,0 +1,229 @@
+//! Per-target benchmark results
+//!
+//! Criterion writes its data under `target/criterion` by default, so benchmarking several
+//! targets overwrites the previous target's results. `bench` gives every target its own
+//! `CRITERION_HOME` and afterwards copies the estimates (and `bencher` lines when that output
+//! format was requested) to `<artifact-dir or target-dir>/<triple>/bench/`, together with a
+//! summary recording which runner produced the numbers.
+
+use crate::cargo::effective_target_dir;
+use crate::cli::Args;
+use crate::error::{CrossError, Result};
+use crate::metadata::resolve_cargo_path;
+use crate::plan::RunnerKind;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+/// Summary file written next to the collected results
+pub const SUMMARY_FILE: &str = "summary.json";
+
+/// File holding the `test ... bench:` lines of `--output-format bencher`
+pub const BENCHER_FILE: &str = "bencher.txt";
+
+/// `CRITERION_HOME` for a target: `$CRITERION_HOME/<triple>` if the user set one,
+/// `<target-dir>/criterion/<triple>` otherwise. Always absolute, because criterion resolves
+/// relative paths against each benchmarked package.
+#[must_use]
+pub fn criterion_home(target: &str, args: &Args) -> PathBuf {
+    std::env::var_os("CRITERION_HOME")
+        .map_or_else(
+            || effective_target_dir(target, args).join("criterion"),
+            |home| resolve_cargo_path(args, Path::new(&home)),
+        )
+        .join(target)
+}
+
+/// Directory collected results of a target go to: `<artifact-dir or target-dir>/<triple>/bench`
+#[must_use]
+pub fn results_dir(target: &str, args: &Args) -> PathBuf {
+    args.artifact_dir
+        .as_deref()
+        .map_or_else(
+            || effective_target_dir(target, args),
+            |dir| resolve_cargo_path(args, dir),
+        )
+        .join(target)
+        .join("bench")
+}
+
+/// Whether `--output-format bencher` was passed through to the benchmark harness
+#[must_use]
+pub fn wants_bencher_output(args: &Args) -> bool {
+    args.passthrough_args
+        .iter()
+        .zip(args.passthrough_args.iter().skip(1).map(Some).chain([None]))
+        .any(|(arg, next)| {
+            arg == "--output-format=bencher"
+                || (arg == "--output-format" && next.is_some_and(|next| next == "bencher"))
+        })
+}
+
+/// The result lines of bencher-format output (`test name ... bench: 12 ns/iter (+/- 1)`)
+#[must_use]
+pub fn bencher_lines(stdout: &str) -> Vec<&str> {
+    stdout
+        .lines()
+        .filter(|line| line.starts_with("test ") && line.contains(" bench: "))
+        .collect()
+}
+
+/// Copy every `<benchmark>/new/estimates.json` under `criterion_home` to
+/// `<results>/<benchmark>/estimates.json`, returning the benchmark ids in order
+pub fn collect_estimates(criterion_home: &Path, results: &Path) -> Result<Vec<String>> {
+    fn walk(dir: &Path, root: &Path, results: &Path, found: &mut Vec<String>) -> Result<()> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Ok(());
+        };
+        let mut dirs: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_dir())
+            .collect();
+        dirs.sort();
+        for path in dirs {
+            let estimates = path.join("new").join("estimates.json");
+            if estimates.is_file() {
+                let id = path.strip_prefix(root).unwrap_or(&path);
+                let dest = results.join(id);
+                std::fs::create_dir_all(&dest)?;
+                std::fs::copy(&estimates, dest.join("estimates.json"))?;
+                found.push(id.to_string_lossy().replace('\\', "/"));
+            } else if path.file_name().is_some_and(|name| name != "report") {
+                walk(&path, root, results, found)?;
+            }
+        }
+        Ok(())
+    }
+
+    let mut found = Vec::new();
+    walk(criterion_home, criterion_home, results, &mut found)?;
+    Ok(found)
+}
+
+/// Write `summary.json` describing how a target's results were obtained
+pub fn write_summary(
+    results: &Path,
+    target: &str,
+    runner: RunnerKind,
+    benchmarks: &[String],
+    bencher: bool,
+) -> Result<()> {
+    let summary = json!({
+        "target": target,
+        "runner": runner.as_str(),
+        "emulated": runner.is_emulated(),
+        "benchmarks": benchmarks,
+        "bencher": bencher.then_some(BENCHER_FILE),
+    });
+    std::fs::create_dir_all(results)?;
+    let path = results.join(SUMMARY_FILE);
+    std::fs::write(&path, format!("{summary:#}\n")).map_err(|e| CrossError::IoError {
+        message: format!("Failed to write {}", path.display()),
+        source: e,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{test_target_args, BuildArgs, Command};
+
+    fn bench_args(build: BuildArgs) -> Args {
+        Args {
+            command: Command::bench(),
+            ..test_target_args(
+                "bench",
+                &["aarch64-unknown-linux-musl"],
+                BuildArgs {
+                    cargo_cwd: Some(PathBuf::from("/ws")),
+                    ..build
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_results_dir() {
+        let target = "aarch64-unknown-linux-musl";
+        let mut args = bench_args(BuildArgs::default());
+        assert_eq!(
+            results_dir(target, &args),
+            PathBuf::from("/ws/target/aarch64-unknown-linux-musl/bench")
+        );
+        args.build.artifact_dir = Some(PathBuf::from("/out"));
+        assert_eq!(
+            results_dir(target, &args),
+            PathBuf::from("/out/aarch64-unknown-linux-musl/bench")
+        );
+    }
+
+    #[test]
+    fn test_wants_bencher_output() {
+        for (passthrough, expected) in [
+            (vec!["--output-format", "bencher"], true),
+            (vec!["--output-format=bencher"], true),
+            (vec!["--output-format", "criterion"], false),
+            (vec!["bencher"], false),
+            (vec![], false),
+        ] {
+            let args = bench_args(BuildArgs {
+                passthrough_args: passthrough.iter().map(ToString::to_string).collect(),
+                ..BuildArgs::default()
+            });
+            assert_eq!(wants_bencher_output(&args), expected, "{passthrough:?}");
+        }
+    }
+
+    #[test]
+    fn test_bencher_lines() {
+        let stdout = "\nrunning 2 tests\n\
+            test parse/small ... bench:         120 ns/iter (+/- 3)\n\
+            Gnuplot not found, using plotters backend\n\
+            test parse/large ... bench:       9,876 ns/iter (+/- 45)\n";
+        assert_eq!(
+            bencher_lines(stdout),
+            [
+                "test parse/small ... bench:         120 ns/iter (+/- 3)",
+                "test parse/large ... bench:       9,876 ns/iter (+/- 45)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_estimates_and_summary() {
+        let root = std::env::temp_dir().join(format!("cargo-cross-bench-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let home = root.join("criterion");
+        for id in ["parse/small", "parse/large", "encode"] {
+            let new = home.join(id).join("new");
+            std::fs::create_dir_all(&new).unwrap();
+            std::fs::write(new.join("estimates.json"), id).unwrap();
+        }
+        std::fs::create_dir_all(home.join("report")).unwrap();
+
+        let results = root.join("results");
+        let found = collect_estimates(&home, &results).unwrap();
+        assert_eq!(found, ["encode", "parse/large", "parse/small"]);
+        assert_eq!(
+            std::fs::read_to_string(results.join("parse/small/estimates.json")).unwrap(),
+            "parse/small"
+        );
+
+        write_summary(
+            &results,
+            "aarch64-unknown-linux-musl",
+            RunnerKind::Qemu,
+            &found,
+            false,
+        )
+        .unwrap();
+        let summary: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(results.join(SUMMARY_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(summary["runner"], "qemu");
+        assert_eq!(summary["emulated"], true);
+        assert!(summary["bencher"].is_null());
+        assert_eq!(summary["benchmarks"].as_array().unwrap().len(), 3);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,736 @@
+//! Size analysis for `cargo cross bloat`
+//!
+//! cargo-bloat is used when it is installed. Otherwise the built binary's symbol table is
+//! read directly: the largest functions with the crate each most likely belongs to, or the
+//! total per crate. Crate attribution is guessed from the symbol names, like cargo-bloat's.
+
+use crate::cache::format_size;
+use crate::cli::Args;
+use crate::error::{CrossError, Result};
+use object::{Object, ObjectSection, ObjectSymbol, SectionIndex, SectionKind, SymbolKind};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+
+/// Rows shown when `-n` is not given
+pub const DEFAULT_TOP: usize = 20;
+
+/// Crate of symbols whose names say nothing about one (C code, linker stubs)
+pub const UNKNOWN_CRATE: &str = "[Unknown]";
+
+/// What to report, shared by cargo-bloat and the internal analyzer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloatOptions {
+    /// Total size per crate instead of the largest functions
+    pub crates: bool,
+    /// Only a crate (exact name) or functions matching a regex
+    pub filter: Option<String>,
+    /// Rows to show; 0 shows all
+    pub top: usize,
+}
+
+impl Default for BloatOptions {
+    fn default() -> Self {
+        Self {
+            crates: false,
+            filter: None,
+            top: DEFAULT_TOP,
+        }
+    }
+}
+
+/// Arguments of `cargo bloat` for a target, with the package, profile and feature selection
+/// of the cargo-cross command line
+#[must_use]
+pub fn cargo_bloat_argv(
+    args: &Args,
+    options: &BloatOptions,
+    target: &str,
+    skip_target_arg: bool,
+) -> Vec<String> {
+    let mut argv = vec!["cargo".to_string()];
+    if let Some(ref toolchain) = args.toolchain {
+        argv.push(format!("+{toolchain}"));
+    }
+    argv.push("bloat".to_string());
+    if !skip_target_arg && !args.no_cargo_target {
+        argv.extend(["--target".to_string(), target.to_string()]);
+    }
+    if args.profile == "release" {
+        argv.push("--release".to_string());
+    } else if !matches!(args.profile.as_str(), "dev" | "debug") {
+        argv.extend(["--profile".to_string(), args.profile.clone()]);
+    }
+    if let Some(ref manifest_path) = args.manifest_path {
+        argv.extend([
+            "--manifest-path".to_string(),
+            manifest_path.display().to_string(),
+        ]);
+    }
+    for package in &args.package {
+        argv.extend(["--package".to_string(), package.clone()]);
+    }
+    if let Some(ref bin) = args.bin_target {
+        argv.extend(["--bin".to_string(), bin.clone()]);
+    }
+    if let Some(ref example) = args.example_target {
+        argv.extend(["--example".to_string(), example.clone()]);
+    }
+    if let Some(ref features) = args.features {
+        argv.extend(["--features".to_string(), features.clone()]);
+    }
+    if args.no_default_features {
+        argv.push("--no-default-features".to_string());
+    }
+    if args.all_features {
+        argv.push("--all-features".to_string());
+    }
+    if options.crates {
+        argv.push("--crates".to_string());
+    }
+    if let Some(ref filter) = options.filter {
+        argv.extend(["--filter".to_string(), filter.clone()]);
+    }
+    argv.extend(["-n".to_string(), options.top.to_string()]);
+    argv.extend(args.cargo_args.iter().cloned());
+    argv
+}
+
+/// A function symbol and its size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolSize {
+    /// Demangled name, or the raw name of non-Rust functions
+    pub name: String,
+    /// Crate the function most likely belongs to, [`UNKNOWN_CRATE`] if unknown
+    pub crate_name: String,
+    pub size: u64,
+}
+
+/// Sizes of a binary and of its functions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SizeReport {
+    pub file_size: u64,
+    /// Total size of the code sections
+    pub text_size: u64,
+    /// Functions, largest first
+    pub symbols: Vec<SymbolSize>,
+}
+
+/// A symbol as read from the symbol table, before sizes are filled in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawSymbol {
+    pub name: String,
+    pub address: u64,
+    /// Size from the symbol table; 0 when the format has none (Mach-O)
+    pub size: u64,
+    pub section: usize,
+}
+
+/// Give symbols without a size the distance to the next symbol of their section, or to the
+/// section end for the last one. `section_ends` maps section indexes to their end address.
+pub fn fill_missing_sizes(symbols: &mut [RawSymbol], section_ends: &HashMap<usize, u64>) {
+    let mut starts: HashMap<usize, Vec<u64>> = HashMap::new();
+    for symbol in symbols.iter() {
+        starts
+            .entry(symbol.section)
+            .or_default()
+            .push(symbol.address);
+    }
+    for addresses in starts.values_mut() {
+        addresses.sort_unstable();
+        addresses.dedup();
+    }
+    for symbol in symbols.iter_mut().filter(|symbol| symbol.size == 0) {
+        let next = starts.get(&symbol.section).and_then(|addresses| {
+            addresses
+                .iter()
+                .find(|address| **address > symbol.address)
+                .copied()
+        });
+        if let Some(end) = next.or_else(|| section_ends.get(&symbol.section).copied()) {
+            symbol.size = end.saturating_sub(symbol.address);
+        }
+    }
+}
+
+/// Demangle a Rust symbol (legacy or v0 mangling, with the extra `_` of Mach-O) without
+/// its hash. Other names are returned unchanged.
+#[must_use]
+pub fn demangle(name: &str) -> String {
+    format!("{:#}", rustc_demangle::demangle(name))
+}
+
+/// The crate a function most likely belongs to: the first path segment of its demangled
+/// name, the type's crate for `<Type as Trait>::method`, or [`UNKNOWN_CRATE`]
+#[must_use]
+pub fn crate_of(demangled: &str) -> String {
+    let mut path = demangled.trim_start_matches('<');
+    for prefix in ["&mut ", "&", "*const ", "*mut ", "dyn "] {
+        path = path.strip_prefix(prefix).unwrap_or(path);
+    }
+    let head = path
+        .split(['<', '>', ' ', '[', '(', ','])
+        .next()
+        .unwrap_or_default();
+    match head.split_once("::") {
+        Some((krate, _)) if !krate.is_empty() => krate.to_string(),
+        _ => UNKNOWN_CRATE.to_string(),
+    }
+}
+
+/// Function symbols of an object file, with their section and size
+fn function_symbols(file: &object::File<'_>) -> (Vec<RawSymbol>, HashMap<usize, u64>, u64) {
+    let text_sections: HashMap<usize, u64> = file
+        .sections()
+        .filter(|section| section.kind() == SectionKind::Text)
+        .map(|section| (section.index().0, section.address() + section.size()))
+        .collect();
+    let text_size = file
+        .sections()
+        .filter(|section| section.kind() == SectionKind::Text)
+        .map(|section| section.size())
+        .sum();
+    let mut symbols: Vec<RawSymbol> = file
+        .symbols()
+        .filter(|symbol| symbol.kind() == SymbolKind::Text && symbol.is_definition())
+        .filter_map(|symbol| {
+            let section = symbol.section_index().map(|SectionIndex(index)| index)?;
+            text_sections.contains_key(&section).then_some(())?;
+            Some(RawSymbol {
+                name: symbol.name().ok()?.to_string(),
+                address: symbol.address(),
+                size: symbol.size(),
+                section,
+            })
+        })
+        .collect();
+    // Aliases of one function count once
+    symbols.sort_by(|a, b| (a.section, a.address, &a.name).cmp(&(b.section, b.address, &b.name)));
+    symbols.dedup_by(|b, a| a.section == b.section && a.address == b.address);
+    (symbols, text_sections, text_size)
+}
+
+/// Read the functions and their sizes from an executable or library
+pub fn analyze(data: &[u8]) -> Result<SizeReport> {
+    let file = object::File::parse(data).map_err(|e| {
+        CrossError::InvalidArgument(format!("cannot read the binary for size analysis: {e}"))
+    })?;
+    let (mut raw, section_ends, text_size) = function_symbols(&file);
+    if raw.is_empty() {
+        let reason = if file.format() == object::BinaryFormat::Pe {
+            "MSVC binaries keep their symbols in the .pdb file, which is not read; install \
+             cargo-bloat"
+        } else {
+            "it was probably stripped. Build it with `strip = false` in the profile (or \
+             CARGO_PROFILE_<NAME>_STRIP=false), or install cargo-bloat"
+        };
+        return Err(CrossError::InvalidArgument(format!(
+            "the binary has no function symbols: {reason}"
+        )));
+    }
+    fill_missing_sizes(&mut raw, &section_ends);
+    let mut symbols: Vec<SymbolSize> = raw
+        .into_iter()
+        .filter(|symbol| symbol.size > 0)
+        .map(|symbol| {
+            let name = demangle(&symbol.name);
+            SymbolSize {
+                crate_name: crate_of(&name),
+                name,
+                size: symbol.size,
+            }
+        })
+        .collect();
+    symbols.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    Ok(SizeReport {
+        file_size: data.len() as u64,
+        text_size,
+        symbols,
+    })
+}
+
+/// Keep the functions of the crate named `filter`, or else those whose name matches it as a
+/// regex
+pub fn apply_filter(symbols: &[SymbolSize], filter: &str) -> Result<Vec<SymbolSize>> {
+    if symbols.iter().any(|symbol| symbol.crate_name == filter) {
+        return Ok(symbols
+            .iter()
+            .filter(|symbol| symbol.crate_name == filter)
+            .cloned()
+            .collect());
+    }
+    let regex = regex_lite::Regex::new(filter).map_err(|e| {
+        CrossError::InvalidArgument(format!(
+            "--filter {filter:?} is neither a crate in the binary nor a valid regex: {e}"
+        ))
+    })?;
+    Ok(symbols
+        .iter()
+        .filter(|symbol| regex.is_match(&symbol.name))
+        .cloned()
+        .collect())
+}
+
+/// Size per crate, largest first
+#[must_use]
+pub fn crate_sizes(symbols: &[SymbolSize]) -> Vec<(String, u64)> {
+    let mut totals: BTreeMap<&str, u64> = BTreeMap::new();
+    for symbol in symbols {
+        *totals.entry(&symbol.crate_name).or_default() += symbol.size;
+    }
+    let mut sizes: Vec<(String, u64)> = totals
+        .into_iter()
+        .map(|(name, size)| (name.to_string(), size))
+        .collect();
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sizes
+}
+
+/// Percentage of `part` in `whole`, one decimal
+#[allow(clippy::cast_precision_loss)]
+fn percent(part: u64, whole: u64) -> String {
+    if whole == 0 {
+        return "0.0%".to_string();
+    }
+    format!("{:.1}%", part as f64 * 100.0 / whole as f64)
+}
+
+/// Render the report as cargo-bloat does: one row per function (or crate), a row for the
+/// rest and the `.text` total
+pub fn render(report: &SizeReport, options: &BloatOptions) -> Result<String> {
+    let symbols = match options.filter {
+        Some(ref filter) => apply_filter(&report.symbols, filter)?,
+        None => report.symbols.clone(),
+    };
+    let row = |size: u64, rest: &[&str]| {
+        let mut cells = vec![
+            percent(size, report.file_size),
+            percent(size, report.text_size),
+            format_size(size),
+        ];
+        cells.extend(rest.iter().map(|cell| (*cell).to_string()));
+        cells
+    };
+    let limit = if options.top == 0 {
+        usize::MAX
+    } else {
+        options.top
+    };
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let (shown_size, hidden, unit) = if options.crates {
+        rows.push(
+            ["File", ".text", "Size", "Crate"]
+                .map(String::from)
+                .to_vec(),
+        );
+        let crates = crate_sizes(&symbols);
+        for (name, size) in crates.iter().take(limit) {
+            rows.push(row(*size, &[name]));
+        }
+        let shown: u64 = crates.iter().take(limit).map(|(_, size)| size).sum();
+        (shown, crates.len().saturating_sub(limit), "crates")
+    } else {
+        rows.push(
+            ["File", ".text", "Size", "Crate", "Name"]
+                .map(String::from)
+                .to_vec(),
+        );
+        for symbol in symbols.iter().take(limit) {
+            rows.push(row(symbol.size, &[&symbol.crate_name, &symbol.name]));
+        }
+        let shown: u64 = symbols.iter().take(limit).map(|symbol| symbol.size).sum();
+        (shown, symbols.len().saturating_sub(limit), "functions")
+    };
+    let total: u64 = symbols.iter().map(|symbol| symbol.size).sum();
+    let last_column = rows[0].len() - 3;
+    let mut trailer = |size: u64, text: String| {
+        let mut cells = row(size, &[]);
+        cells.extend(std::iter::repeat_n(String::new(), last_column - 1));
+        cells.push(text);
+        rows.push(cells);
+    };
+    if hidden > 0 {
+        trailer(
+            total - shown_size,
+            format!("And {hidden} smaller {unit}. Use -n N to show more."),
+        );
+    }
+    if options.filter.is_some() {
+        trailer(
+            total,
+            format!(
+                "filtered data size, the file size is {}",
+                format_size(report.file_size)
+            ),
+        );
+    } else {
+        trailer(
+            report.text_size,
+            format!(
+                ".text section size, the file size is {}",
+                format_size(report.file_size)
+            ),
+        );
+    }
+
+    let columns = rows[0].len();
+    let widths: Vec<usize> = (0..columns - 1)
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+        .collect();
+    let mut out = String::new();
+    for row in &rows {
+        let mut line = String::new();
+        for (column, cell) in row.iter().enumerate() {
+            if column + 1 == columns {
+                line.push_str(cell);
+            } else if column < 3 {
+                let _ = write!(line, "{cell:>width$} ", width = widths[column]);
+            } else {
+                let _ = write!(line, "{cell:<width$} ", width = widths[column]);
+            }
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    if options.crates {
+        out.push_str(
+            "\nNote: crates are guessed from symbol names; generic code is often counted \
+             toward the crate that instantiated it.\n",
+        );
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{BuildArgs, Command};
+
+    /// Legacy mangled name of a path, with a hash
+    fn legacy(path: &[&str]) -> String {
+        let mut name = "_ZN".to_string();
+        for part in path.iter().chain(&["h0123456789abcdef"]) {
+            name.push_str(&format!("{}{part}", part.len()));
+        }
+        name.push('E');
+        name
+    }
+
+    /// A little-endian x86_64 ELF executable with a `.text` section at 0x1000 of
+    /// `text_size` bytes and function symbols `(name, address, size)` in it
+    fn elf_with_functions(text_size: u64, functions: &[(&str, u64, u64)]) -> Vec<u8> {
+        let mut strtab = vec![0u8];
+        let mut symtab = vec![0u8; 24];
+        for (name, address, size) in functions {
+            let name_offset = u32::try_from(strtab.len()).unwrap();
+            strtab.extend_from_slice(name.as_bytes());
+            strtab.push(0);
+            symtab.extend_from_slice(&name_offset.to_le_bytes());
+            symtab.push(0x12); // STB_GLOBAL, STT_FUNC
+            symtab.push(0);
+            symtab.extend_from_slice(&1u16.to_le_bytes()); // .text
+            symtab.extend_from_slice(&address.to_le_bytes());
+            symtab.extend_from_slice(&size.to_le_bytes());
+        }
+        let shstrtab = b"\0.text\0.symtab\0.strtab\0.shstrtab\0".to_vec();
+
+        let text_offset = 64u64;
+        let symtab_offset = text_offset + text_size;
+        let strtab_offset = symtab_offset + symtab.len() as u64;
+        let shstrtab_offset = strtab_offset + strtab.len() as u64;
+        let section_headers = shstrtab_offset + shstrtab.len() as u64;
+
+        let mut data = vec![0u8; 64];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2; // 64-bit
+        data[5] = 1; // little endian
+        data[6] = 1; // EV_CURRENT
+        data[16..18].copy_from_slice(&2u16.to_le_bytes()); // ET_EXEC
+        data[18..20].copy_from_slice(&62u16.to_le_bytes()); // EM_X86_64
+        data[20..24].copy_from_slice(&1u32.to_le_bytes());
+        data[40..48].copy_from_slice(&section_headers.to_le_bytes());
+        data[52..54].copy_from_slice(&64u16.to_le_bytes()); // e_ehsize
+        data[58..60].copy_from_slice(&64u16.to_le_bytes()); // e_shentsize
+        data[60..62].copy_from_slice(&5u16.to_le_bytes()); // e_shnum
+        data[62..64].copy_from_slice(&4u16.to_le_bytes()); // e_shstrndx
+        data.resize(usize::try_from(symtab_offset).unwrap(), 0xc3);
+        data.extend_from_slice(&symtab);
+        data.extend_from_slice(&strtab);
+        data.extend_from_slice(&shstrtab);
+
+        let mut header = |name: u32,
+                          kind: u32,
+                          flags: u64,
+                          address: u64,
+                          offset: u64,
+                          size: u64,
+                          link: u32,
+                          entsize: u64| {
+            data.extend_from_slice(&name.to_le_bytes());
+            data.extend_from_slice(&kind.to_le_bytes());
+            data.extend_from_slice(&flags.to_le_bytes());
+            data.extend_from_slice(&address.to_le_bytes());
+            data.extend_from_slice(&offset.to_le_bytes());
+            data.extend_from_slice(&size.to_le_bytes());
+            data.extend_from_slice(&link.to_le_bytes());
+            data.extend_from_slice(&u32::from(kind == 2).to_le_bytes()); // first global symbol
+            data.extend_from_slice(&1u64.to_le_bytes());
+            data.extend_from_slice(&entsize.to_le_bytes());
+        };
+        header(0, 0, 0, 0, 0, 0, 0, 0);
+        header(1, 1, 0x6, 0x1000, text_offset, text_size, 0, 0);
+        header(7, 2, 0, 0, symtab_offset, symtab.len() as u64, 3, 24);
+        header(15, 3, 0, 0, strtab_offset, strtab.len() as u64, 0, 0);
+        header(23, 3, 0, 0, shstrtab_offset, shstrtab.len() as u64, 0, 0);
+        data
+    }
+
+    fn fixture() -> Vec<u8> {
+        let lang_start = legacy(&["std", "rt", "lang_start_internal"]);
+        let main = legacy(&["fixture", "main"]);
+        let debug_fmt = legacy(&["_$LT$fixture..Point$u20$as$u20$core..fmt..Debug$GT$", "fmt"]);
+        elf_with_functions(
+            0x100,
+            &[
+                (&lang_start, 0x1000, 0x40),
+                (&main, 0x1040, 0x20),
+                // An alias of main, counted once
+                ("fixture_main_alias", 0x1040, 0x20),
+                (&debug_fmt, 0x1060, 0x30),
+                // No size: runs to the end of .text
+                ("memcpy", 0x10a0, 0),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_demangle() {
+        assert_eq!(
+            demangle(&legacy(&["std", "rt", "lang_start"])),
+            "std::rt::lang_start"
+        );
+        // Mach-O adds an underscore
+        assert_eq!(
+            demangle(&format!("_{}", legacy(&["core", "fmt", "write"]))),
+            "core::fmt::write"
+        );
+        assert_eq!(
+            demangle(&legacy(&[
+                "_$LT$alloc..vec..Vec$LT$T$GT$$u20$as$u20$core..ops..drop..Drop$GT$",
+                "drop"
+            ])),
+            "<alloc::vec::Vec<T> as core::ops::drop::Drop>::drop"
+        );
+        assert_eq!(demangle("_RNvCs1234_7mycrate3foo"), "mycrate::foo");
+        assert_eq!(
+            demangle("__RNvNtCsaBc_3std2rt10lang_start"),
+            "std::rt::lang_start"
+        );
+        for name in ["memcpy", "__libc_start_main", "_ZN99shortE"] {
+            assert_eq!(demangle(name), name);
+        }
+    }
+
+    #[test]
+    fn test_crate_of() {
+        let crate_of_name = |name: &str| crate_of(&demangle(name));
+        assert_eq!(
+            crate_of_name(&legacy(&["serde_json", "de", "from_str"])),
+            "serde_json"
+        );
+        assert_eq!(
+            crate_of_name(&legacy(&[
+                "_$LT$alloc..vec..Vec$LT$T$GT$$u20$as$u20$core..ops..drop..Drop$GT$",
+                "drop"
+            ])),
+            "alloc"
+        );
+        assert_eq!(
+            crate_of_name(&legacy(&[
+                "_$LT$$RF$mut$u20$W$u20$as$u20$core..fmt..Write$GT$",
+                "write_str"
+            ])),
+            UNKNOWN_CRATE
+        );
+        assert_eq!(crate_of_name("_RNvCs1234_7mycrate3foo"), "mycrate");
+        assert_eq!(crate_of_name("__RNvNtCsaBc_3std2rt10lang_start"), "std");
+        assert_eq!(crate_of_name("memcpy"), UNKNOWN_CRATE);
+        assert_eq!(crate_of_name("__libc_start_main"), UNKNOWN_CRATE);
+    }
+
+    #[test]
+    fn test_fill_missing_sizes() {
+        let symbol = |name: &str, address: u64, size: u64, section: usize| RawSymbol {
+            name: name.to_string(),
+            address,
+            size,
+            section,
+        };
+        let mut symbols = vec![
+            symbol("a", 0x100, 0, 1),
+            symbol("b", 0x180, 0, 1),
+            symbol("b_alias", 0x180, 0, 1),
+            symbol("c", 0x1c0, 0x10, 1),
+            symbol("other", 0x2000, 0, 2),
+            symbol("no_end", 0x3000, 0, 3),
+        ];
+        fill_missing_sizes(&mut symbols, &HashMap::from([(1, 0x200), (2, 0x2040)]));
+        let sizes: Vec<u64> = symbols.iter().map(|symbol| symbol.size).collect();
+        assert_eq!(sizes, [0x80, 0x40, 0x40, 0x10, 0x40, 0]);
+    }
+
+    #[test]
+    fn test_analyze_elf() {
+        let data = fixture();
+        let report = analyze(&data).unwrap();
+        assert_eq!(report.file_size, data.len() as u64);
+        assert_eq!(report.text_size, 0x100);
+        let summary: Vec<(&str, &str, u64)> = report
+            .symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.crate_name.as_str(),
+                    symbol.name.as_str(),
+                    symbol.size,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (UNKNOWN_CRATE, "memcpy", 0x60),
+                ("std", "std::rt::lang_start_internal", 0x40),
+                ("fixture", "<fixture::Point as core::fmt::Debug>::fmt", 0x30),
+                ("fixture", "fixture::main", 0x20),
+            ]
+        );
+
+        assert_eq!(
+            crate_sizes(&report.symbols),
+            [
+                (UNKNOWN_CRATE.to_string(), 0x60),
+                ("fixture".to_string(), 0x50),
+                ("std".to_string(), 0x40),
+            ]
+        );
+
+        // Stripped binaries and non-objects are errors, not empty reports
+        assert!(analyze(&elf_with_functions(0x100, &[])).is_err());
+        assert!(analyze(b"not a binary").is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let report = analyze(&fixture()).unwrap();
+        let options = BloatOptions {
+            top: 2,
+            ..BloatOptions::default()
+        };
+        let text = render(&report, &options).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 5, "{text}");
+        assert!(lines[0].trim_start().starts_with("File"));
+        assert!(lines[0].ends_with("Crate     Name"), "{text}");
+        assert!(lines[1].contains(" 37.5%  96 B [Unknown] memcpy"), "{text}");
+        assert!(
+            lines[2].ends_with("std       std::rt::lang_start_internal"),
+            "{text}"
+        );
+        assert!(lines[3].contains(" 31.2%  80 B "), "{text}");
+        assert!(lines[3].ends_with("And 2 smaller functions. Use -n N to show more."));
+        assert!(lines[4].contains(" 100.0% 256 B "), "{text}");
+        assert!(lines[4].contains(".text section size, the file size is"));
+
+        let crates = BloatOptions {
+            crates: true,
+            top: 0,
+            ..BloatOptions::default()
+        };
+        let text = render(&report, &crates).unwrap();
+        assert!(
+            text.lines().nth(2).unwrap().ends_with("80 B fixture"),
+            "{text}"
+        );
+        assert!(!text.contains("And "));
+        assert!(text.contains("Note: crates are guessed"));
+
+        // A crate name filters by crate, anything else is a regex over the names
+        let filtered = |filter: &str| BloatOptions {
+            filter: Some(filter.to_string()),
+            top: 0,
+            ..BloatOptions::default()
+        };
+        let text = render(&report, &filtered("fixture")).unwrap();
+        assert_eq!(text.lines().count(), 4, "{text}");
+        assert!(text.contains("filtered data size"));
+        let text = render(&report, &filtered("^std::rt")).unwrap();
+        assert_eq!(text.lines().count(), 3, "{text}");
+        assert!(matches!(
+            render(&report, &filtered("(")),
+            Err(CrossError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_cargo_bloat_argv() {
+        let mut args = Args {
+            toolchain: Some("nightly".to_string()),
+            command: Command::new("bloat"),
+            ..crate::cli::test_target_args(
+                "bloat",
+                &["aarch64-unknown-linux-musl"],
+                BuildArgs {
+                    profile: "release".to_string(),
+                    package: vec!["app".to_string()],
+                    bin_target: Some("app".to_string()),
+                    features: Some("simd".to_string()),
+                    cargo_args: vec!["--locked".to_string()],
+                    ..BuildArgs::default()
+                },
+            )
+        };
+        let options = BloatOptions {
+            crates: true,
+            filter: Some("regex".to_string()),
+            top: 10,
+        };
+        assert_eq!(
+            cargo_bloat_argv(&args, &options, "aarch64-unknown-linux-musl", false),
+            [
+                "cargo",
+                "+nightly",
+                "bloat",
+                "--target",
+                "aarch64-unknown-linux-musl",
+                "--release",
+                "--package",
+                "app",
+                "--bin",
+                "app",
+                "--features",
+                "simd",
+                "--crates",
+                "--filter",
+                "regex",
+                "-n",
+                "10",
+                "--locked",
+            ]
+        );
+
+        args.toolchain = None;
+        args.build = BuildArgs {
+            profile: "dev".to_string(),
+            ..BuildArgs::default()
+        };
+        assert_eq!(
+            cargo_bloat_argv(
+                &args,
+                &BloatOptions::default(),
+                "x86_64-unknown-linux-gnu",
+                true
+            ),
+            ["cargo", "bloat", "-n", "20"]
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Spotting slow build scripts
+//!
+//! Huge C dependencies (ffmpeg, aws-lc) are compiled by build scripts that print nothing
+//! for many minutes. With `-v` cargo prints a `Running` line when a build script starts;
+//! the script counts as running until cargo starts rustc for the crate itself or exits.
+//! [`BuildScriptWatch`] follows these lines and names the scripts that exceed a threshold.
+
+use crate::cli::Args;
+use std::time::{Duration, Instant};
+
+/// Threshold used when `--build-script-warn-secs` is not given
+pub const DEFAULT_WARN_SECS: u64 = 300;
+
+/// How long a build script may run before it is reported, if slow scripts are reported
+#[must_use]
+pub fn warn_threshold(args: &Args) -> Option<Duration> {
+    let secs = args.build_script_warn_secs.unwrap_or(DEFAULT_WARN_SECS);
+    (args.verbose_level > 0 && secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Package name of a verbose cargo line starting a build script, e.g.
+/// "Running `/t/debug/build/ring-0a1b2c/build-script-build`" gives `ring`
+#[must_use]
+pub fn started_build_script(line: &str) -> Option<&str> {
+    let command = line.trim_start().strip_prefix("Running `")?;
+    let script = command.rfind("build-script-")?;
+    let dir = command[..script].strip_suffix(['/', '\\'])?;
+    let dir_name = dir.rsplit(['/', '\\']).next()?;
+    dir_name.rsplit_once('-').map(|(name, _hash)| name)
+}
+
+/// Crate name of a verbose cargo line running rustc, skipping build script compilation
+#[must_use]
+pub fn compiled_crate(line: &str) -> Option<&str> {
+    if !line.trim_start().starts_with("Running `") {
+        return None;
+    }
+    let (_, rest) = line.split_once("--crate-name ")?;
+    let name = rest.split_whitespace().next()?;
+    (!name.starts_with("build_script_")).then_some(name)
+}
+
+/// `+mm:ss` since the build started
+#[must_use]
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("+{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// A build script that has started and not yet finished
+#[derive(Debug)]
+struct RunningScript {
+    package: String,
+    since: Instant,
+    reported: bool,
+}
+
+/// Tracks running build scripts from cargo's verbose output
+#[derive(Debug)]
+pub struct BuildScriptWatch {
+    threshold: Duration,
+    build_start: Instant,
+    running: Vec<RunningScript>,
+}
+
+impl BuildScriptWatch {
+    #[must_use]
+    pub const fn new(threshold: Duration, now: Instant) -> Self {
+        Self {
+            threshold,
+            build_start: now,
+            running: Vec::new(),
+        }
+    }
+
+    /// Follow one line of cargo's stderr
+    pub fn observe(&mut self, line: &str, now: Instant) {
+        if let Some(package) = started_build_script(line) {
+            self.running.push(RunningScript {
+                package: package.to_string(),
+                since: now,
+                reported: false,
+            });
+        } else if let Some(name) = compiled_crate(line) {
+            self.running
+                .retain(|script| script.package.replace('-', "_") != name);
+        }
+    }
+
+    /// Warnings for scripts that have run longer than the threshold, each reported once
+    pub fn overdue(&mut self, now: Instant) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for script in &mut self.running {
+            let running_for = now.saturating_duration_since(script.since);
+            if !script.reported && running_for > self.threshold {
+                script.reported = true;
+                warnings.push(format!(
+                    "[{}] build script of {} has been running for {}s (over {}s)",
+                    format_elapsed(now.saturating_duration_since(self.build_start)),
+                    script.package,
+                    running_for.as_secs(),
+                    self.threshold.as_secs()
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{test_args, test_toolchain_dir, BuildArgs};
+
+    #[test]
+    fn test_parse_verbose_lines() {
+        assert_eq!(
+            started_build_script(
+                "     Running `/ws/target/debug/build/aws-lc-sys-0a1b2c3d/build-script-main`"
+            ),
+            Some("aws-lc-sys")
+        );
+        // -vv prefixes the environment
+        assert_eq!(
+            started_build_script(
+                "     Running `CARGO=/bin/cargo OUT_DIR=/t/build/ring-9f/out \
+                 /t/debug/build/ring-0a1b/build-script-build`"
+            ),
+            Some("ring")
+        );
+        assert_eq!(
+            started_build_script(
+                r"     Running `C:\t\debug\build\ffmpeg-sys-next-77\build-script-build`"
+            ),
+            Some("ffmpeg-sys-next")
+        );
+        assert_eq!(
+            started_build_script(
+                "     Running `rustc --crate-name build_script_build --edition=2021 build.rs`"
+            ),
+            None
+        );
+        assert_eq!(started_build_script("   Compiling ring v0.17.8"), None);
+
+        assert_eq!(
+            compiled_crate(
+                "     Running `rustc --crate-name aws_lc_sys --edition=2021 src/lib.rs`"
+            ),
+            Some("aws_lc_sys")
+        );
+        assert_eq!(
+            compiled_crate("     Running `rustc --crate-name build_script_main build.rs`"),
+            None
+        );
+        assert_eq!(compiled_crate("warning: --crate-name in a message"), None);
+    }
+
+    #[test]
+    fn test_watch_reports_slow_scripts_once() {
+        let start = Instant::now();
+        let mut watch = BuildScriptWatch::new(Duration::from_secs(300), start);
+        watch.observe(
+            "     Running `/t/debug/build/aws-lc-sys-0a/build-script-main`",
+            start,
+        );
+        watch.observe(
+            "     Running `/t/debug/build/ring-0b/build-script-build`",
+            start + Duration::from_secs(10),
+        );
+        assert!(watch.overdue(start + Duration::from_secs(200)).is_empty());
+
+        watch.observe(
+            "     Running `rustc --crate-name ring src/lib.rs`",
+            start + Duration::from_secs(20),
+        );
+        let warnings = watch.overdue(start + Duration::from_secs(305));
+        assert_eq!(
+            warnings,
+            ["[+05:05] build script of aws-lc-sys has been running for 305s (over 300s)"]
+        );
+        assert!(watch.overdue(start + Duration::from_secs(400)).is_empty());
+    }
+
+    #[test]
+    fn test_warn_threshold() {
+        let args = |verbose_level, build_script_warn_secs| {
+            test_args(
+                test_toolchain_dir("build-scripts"),
+                BuildArgs {
+                    verbose_level,
+                    build_script_warn_secs,
+                    ..BuildArgs::default()
+                },
+            )
+        };
+        assert_eq!(warn_threshold(&args(0, None)), None);
+        assert_eq!(
+            warn_threshold(&args(1, None)),
+            Some(Duration::from_secs(DEFAULT_WARN_SECS))
+        );
+        assert_eq!(
+            warn_threshold(&args(2, Some(60))),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(warn_threshold(&args(1, Some(0))), None);
+    }
+}
@@ -0,0 +1,279 @@
+//! `--build-std auto`: the standard library crates a target needs
+//!
+//! Whether a crate is `#![no_std]` cannot be told from `cargo metadata`, so the choice is an
+//! approximation. Targets without an operating system (`none` in the triple, such as
+//! `thumbv7em-none-eabihf` or `x86_64-unknown-none`) get `core,alloc` unless a crate in
+//! their dependency graph has its `std` feature enabled; every other target gets the full
+//! set of [`get_build_std_config`]. Per-target overrides take precedence over both.
+
+use crate::cli::{Args, BuildStd};
+use crate::color;
+use crate::config::TargetConfig;
+use crate::env::get_build_std_config;
+use crate::error::{run_command_output, CrossError, Result};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use tokio::process::Command as TokioCommand;
+
+/// Crates built for targets without an operating system
+pub const NO_STD_CRATES: &str = "core,alloc";
+
+/// What the dependency graph of a target says about its use of std
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StdUsage {
+    /// Packages whose `std` feature is enabled, sorted
+    pub std_feature_crates: Vec<String>,
+}
+
+impl StdUsage {
+    /// Summarize the JSON printed by `cargo metadata --format-version 1` with a resolve graph
+    pub fn from_metadata_json(json: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(json)?;
+        let names: HashMap<&str, &str> = value["packages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|pkg| Some((pkg["id"].as_str()?, pkg["name"].as_str()?)))
+            .collect();
+        let std_feature_crates: BTreeSet<String> = value["resolve"]["nodes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|node| {
+                node["features"]
+                    .as_array()
+                    .is_some_and(|features| features.iter().any(|f| f.as_str() == Some("std")))
+            })
+            .filter_map(|node| names.get(node["id"].as_str()?))
+            .map(|name| (*name).to_string())
+            .collect();
+        Ok(Self {
+            std_feature_crates: std_feature_crates.into_iter().collect(),
+        })
+    }
+}
+
+/// Crates chosen for a target and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildStdChoice {
+    /// Value for `-Zbuild-std`
+    pub crates: String,
+    pub reason: String,
+}
+
+/// Whether `target` has no operating system, judging by its triple
+#[must_use]
+pub fn is_bare_metal(target: &str) -> bool {
+    target.split('-').any(|component| component == "none")
+}
+
+/// Crates to build for `target` from its configuration and dependency graph
+#[must_use]
+pub fn choose_build_std(
+    target: &str,
+    config: Option<&TargetConfig>,
+    usage: &StdUsage,
+) -> BuildStdChoice {
+    let full = |reason: String| BuildStdChoice {
+        crates: get_build_std_config().to_string(),
+        reason,
+    };
+    if let Some(config) = config {
+        return full(format!("{} target", config.os.as_str()));
+    }
+    if !is_bare_metal(target) {
+        return full("target with an operating system".to_string());
+    }
+    match usage.std_feature_crates.as_slice() {
+        [] => BuildStdChoice {
+            crates: NO_STD_CRATES.to_string(),
+            reason: "bare-metal target, no crate enables std".to_string(),
+        },
+        crates => full(format!("std feature enabled by {}", crates.join(", "))),
+    }
+}
+
+/// Crates of the last `GLOB=>CRATES` override matching `target`
+#[must_use]
+pub fn override_for<'a>(overrides: &'a [(String, String)], target: &str) -> Option<&'a str> {
+    overrides
+        .iter()
+        .rev()
+        .find(|(pattern, _)| {
+            globset::Glob::new(pattern).is_ok_and(|glob| glob.compile_matcher().is_match(target))
+        })
+        .map(|(_, crates)| crates.as_str())
+}
+
+/// Std usage of the dependency graph of `target`, with the features of the build
+pub async fn std_usage(args: &Args, target: &str) -> Result<StdUsage> {
+    let mut cmd = metadata_command(args, target);
+    let output = run_command_output(&mut cmd, "cargo").await?;
+    if !output.status.success() {
+        return Err(CrossError::CommandFailed {
+            command: format!(
+                "cargo metadata: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+    StdUsage::from_metadata_json(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// The `cargo metadata` command behind [`std_usage`]. It neither updates `Cargo.lock`
+/// nor touches the network; the build itself reports a stale lock file or missing sources.
+fn metadata_command(args: &Args, target: &str) -> TokioCommand {
+    let mut cmd = TokioCommand::new("cargo");
+    if let Some(ref toolchain) = args.toolchain {
+        cmd.arg(format!("+{toolchain}"));
+    }
+    cmd.args([
+        "metadata",
+        "--format-version",
+        "1",
+        "--filter-platform",
+        target,
+        "--locked",
+        "--offline",
+    ]);
+    if let Some(ref manifest) = args.manifest_path {
+        cmd.arg("--manifest-path").arg(manifest);
+    }
+    if let Some(ref features) = args.features {
+        cmd.arg("--features").arg(features);
+    }
+    if args.all_features {
+        cmd.arg("--all-features");
+    }
+    if args.no_default_features {
+        cmd.arg("--no-default-features");
+    }
+    if let Some(ref cwd) = args.cargo_cwd {
+        cmd.current_dir(cwd);
+    }
+    cmd
+}
+
+/// Resolve `--build-std auto` for `target`; `None` unless auto mode is selected
+pub async fn resolve_auto(
+    args: &Args,
+    target: &str,
+    config: Option<&TargetConfig>,
+) -> Option<BuildStdChoice> {
+    let BuildStd::Auto(ref overrides) = args.build_std else {
+        return None;
+    };
+    if let Some(crates) = override_for(overrides, target) {
+        let crates = if crates == "true" {
+            get_build_std_config()
+        } else {
+            crates
+        };
+        return Some(BuildStdChoice {
+            crates: crates.to_string(),
+            reason: "--build-std override".to_string(),
+        });
+    }
+    let usage = if config.is_none() && is_bare_metal(target) {
+        std_usage(args, target).await.unwrap_or_else(|e| {
+            color::log_warning(&format!(
+                "--build-std auto: could not inspect the dependency graph of {target}: {e}"
+            ));
+            StdUsage::default()
+        })
+    } else {
+        StdUsage::default()
+    };
+    Some(choose_build_std(target, config, &usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_target_config;
+
+    #[test]
+    fn test_choose_build_std() {
+        let none = StdUsage::default();
+        let linux = get_target_config("aarch64-unknown-linux-musl");
+        assert_eq!(
+            choose_build_std("aarch64-unknown-linux-musl", linux, &none),
+            BuildStdChoice {
+                crates: get_build_std_config().to_string(),
+                reason: "linux target".to_string(),
+            }
+        );
+
+        let bare = choose_build_std("thumbv7em-none-eabihf", None, &none);
+        assert_eq!(bare.crates, NO_STD_CRATES);
+
+        let with_std = StdUsage {
+            std_feature_crates: vec!["serde".to_string()],
+        };
+        let choice = choose_build_std("x86_64-unknown-none", None, &with_std);
+        assert_eq!(choice.crates, get_build_std_config());
+        assert_eq!(choice.reason, "std feature enabled by serde");
+
+        // Tier-3 targets with an OS that cargo-cross has no configuration for
+        assert_eq!(
+            choose_build_std("riscv64gc-unknown-openbsd", None, &none).crates,
+            get_build_std_config()
+        );
+    }
+
+    #[test]
+    fn test_override_for() {
+        let overrides = vec![
+            ("thumbv*".to_string(), "core".to_string()),
+            ("thumbv7em-*".to_string(), "core,alloc".to_string()),
+        ];
+        assert_eq!(
+            override_for(&overrides, "thumbv7em-none-eabihf"),
+            Some("core,alloc")
+        );
+        assert_eq!(override_for(&overrides, "thumbv6m-none-eabi"), Some("core"));
+        assert_eq!(override_for(&overrides, "x86_64-unknown-none"), None);
+    }
+
+    #[test]
+    fn test_std_usage_from_metadata() {
+        let json = r#"{
+            "packages": [
+                {"id": "app 0.1.0", "name": "app"},
+                {"id": "serde 1.0.0", "name": "serde"},
+                {"id": "heapless 0.8.0", "name": "heapless"}
+            ],
+            "resolve": {"nodes": [
+                {"id": "app 0.1.0", "features": ["default"]},
+                {"id": "serde 1.0.0", "features": ["default", "std"]},
+                {"id": "heapless 0.8.0", "features": []}
+            ]}
+        }"#;
+        assert_eq!(
+            StdUsage::from_metadata_json(json)
+                .unwrap()
+                .std_feature_crates,
+            ["serde"]
+        );
+        assert!(StdUsage::from_metadata_json("{}")
+            .unwrap()
+            .std_feature_crates
+            .is_empty());
+    }
+
+    #[test]
+    fn test_metadata_command_stays_offline() {
+        let args = crate::cli::test_args(
+            crate::cli::test_toolchain_dir("build-std"),
+            crate::cli::BuildArgs::default(),
+        );
+        let cmd = metadata_command(&args, "thumbv7em-none-eabihf");
+        let argv: Vec<_> = cmd
+            .as_std()
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        assert!(argv.contains(&"--locked".to_string()), "{argv:?}");
+        assert!(argv.contains(&"--offline".to_string()), "{argv:?}");
+    }
+}
@@ -0,0 +1,501 @@
+//! High-level library API: prepare and run a cross build for a single target
+//!
+//! ```no_run
+//! use cargo_cross::builder::CrossBuilder;
+//!
+//! # async fn example() -> cargo_cross::Result<()> {
+//! let outcome = CrossBuilder::new("aarch64-unknown-linux-musl")
+//!     .toolchain("stable")
+//!     .configure(|build| {
+//!         build.release = true;
+//!         build.features = Some("tls".to_string());
+//!     })
+//!     .run()
+//!     .await?;
+//! assert!(outcome.success());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::build_std;
+use crate::cargo::{
+    build_cargo_env, clean_target, configured_cargo_command, ensure_rust_src,
+    ensure_target_installed, execute_cargo,
+};
+use crate::cli::{resolve_override, Args, BuildArgs, BuildStd, Command, OverrideSource};
+use crate::color;
+use crate::config::{get_target_config, HostPlatform};
+use crate::download::completed_downloads;
+use crate::env::{target_env_upper, use_sysroot_libs, CrossEnv, EnvMap, EnvSource};
+use crate::error::{CrossError, Result};
+use crate::libclang::setup_libclang;
+use crate::metrics::{self, Phase};
+use crate::platform::{linker_flavor, setup_cross_env, validate_targets};
+use crate::runner::check_runner_available;
+use crate::verify::{should_smoke_test, smoke_test_toolchain};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use tokio::process::Command as TokioCommand;
+
+/// A target whose toolchain has been set up
+#[derive(Debug, Clone)]
+pub struct PreparedTarget {
+    /// Target triple passed to cargo (`host-tuple` resolved to the host triple)
+    pub actual_target: String,
+    /// Whether `--target` is omitted (host builds)
+    pub skip_target_arg: bool,
+    /// Configured cross-compilation environment
+    pub cross_env: CrossEnv,
+}
+
+/// Result of running cargo for a target
+#[derive(Debug)]
+pub struct BuildOutcome {
+    /// Target triple that was built
+    pub target: String,
+    /// Exit status of cargo
+    pub status: ExitStatus,
+    /// Times cargo was re-run after a transient failure (`--retry-transient`)
+    pub retries: u32,
+    /// Environment the build ran with
+    pub cross_env: CrossEnv,
+}
+
+impl BuildOutcome {
+    /// Whether cargo exited successfully
+    #[must_use]
+    pub fn success(&self) -> bool {
+        self.status.success()
+    }
+}
+
+/// Builder for a cross build of one target, usable without going through the CLI
+///
+/// Options start at their CLI defaults; environment variables are not consulted.
+#[derive(Debug, Clone)]
+pub struct CrossBuilder {
+    args: Args,
+    host: HostPlatform,
+}
+
+impl CrossBuilder {
+    /// Create a `cargo build` for `target` with default options
+    #[must_use]
+    pub fn new(target: impl Into<String>) -> Self {
+        let build = BuildArgs::cli_defaults();
+        let args = Args {
+            toolchain: None,
+            command: Command::build(),
+            targets: vec![target.into()],
+            no_cargo_target: false,
+            cross_make_version: build.cross_make_version.clone(),
+            cross_compiler_dir: build
+                .cross_compiler_dir
+                .clone()
+                .unwrap_or_else(|| std::env::temp_dir().join("rust-cross-compiler")),
+            build,
+        };
+        Self::from_args(args, HostPlatform::detect())
+    }
+
+    /// Create a builder from already parsed arguments; the first target is used
+    #[must_use]
+    pub const fn from_args(args: Args, host: HostPlatform) -> Self {
+        Self { args, host }
+    }
+
+    /// Cargo subcommand to run (default: build)
+    #[must_use]
+    pub fn cargo_command(mut self, command: Command) -> Self {
+        self.args.command = command;
+        self
+    }
+
+    /// Rust toolchain (e.g. "nightly")
+    #[must_use]
+    pub fn toolchain(mut self, toolchain: impl Into<String>) -> Self {
+        self.args.toolchain = Some(toolchain.into());
+        self
+    }
+
+    /// Directory for downloaded cross-compiler toolchains
+    #[must_use]
+    pub fn cross_compiler_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        self.args.build.cross_compiler_dir = Some(dir.clone());
+        self.args.cross_compiler_dir = dir;
+        self
+    }
+
+    /// Adjust any build option, as with the corresponding CLI flags
+    #[must_use]
+    pub fn configure(mut self, f: impl FnOnce(&mut BuildArgs)) -> Self {
+        f(&mut self.args.build);
+        if self.args.build.release {
+            self.args.build.profile = "release".to_string();
+        }
+        self.args
+            .cross_make_version
+            .clone_from(&self.args.build.cross_make_version);
+        if let Some(ref dir) = self.args.build.cross_compiler_dir {
+            self.args.cross_compiler_dir.clone_from(dir);
+        }
+        self
+    }
+
+    /// Arguments the builder will use
+    #[must_use]
+    pub const fn args(&self) -> &Args {
+        &self.args
+    }
+
+    fn target(&self) -> &str {
+        self.args
+            .targets
+            .first()
+            .map_or("host-tuple", String::as_str)
+    }
+
+    /// Validate the target for this host and set up its toolchain
+    pub async fn prepare(&self) -> Result<PreparedTarget> {
+        let target = self.target().to_string();
+        validate_targets(std::slice::from_ref(&target), &self.args, &self.host)?;
+        prepare_target(&target, &self.args, &self.host).await
+    }
+
+    /// Environment variables cargo (or any other tool) needs for a prepared target
+    pub fn env(&self, prepared: &PreparedTarget) -> Result<HashMap<String, String>> {
+        build_cargo_env(
+            &prepared.actual_target,
+            &self.args,
+            &prepared.cross_env,
+            &self.host,
+            prepared.skip_target_arg,
+        )
+        .map(EnvMap::into_vars)
+    }
+
+    /// Prepare the target and return the configured cargo command without running it
+    pub async fn command(&self) -> Result<TokioCommand> {
+        let prepared = self.prepare().await?;
+        configured_cargo_command(
+            &prepared.actual_target,
+            &self.args,
+            &prepared.cross_env,
+            &self.host,
+            prepared.skip_target_arg,
+        )
+    }
+
+    /// Prepare the target and run cargo; a failing cargo is reported in the outcome, not as an error
+    pub async fn run(&self) -> Result<BuildOutcome> {
+        let prepared = self.prepare().await?;
+        self.run_prepared(prepared).await
+    }
+
+    /// Run cargo for a target returned by [`CrossBuilder::prepare`]
+    pub async fn run_prepared(&self, prepared: PreparedTarget) -> Result<BuildOutcome> {
+        if self.args.clean_cache {
+            clean_target(
+                &prepared.actual_target,
+                &self.args,
+                prepared.skip_target_arg,
+            )
+            .await;
+        }
+
+        let run = metrics::timed(
+            Phase::Cargo,
+            execute_cargo(
+                &prepared.actual_target,
+                &self.args,
+                &prepared.cross_env,
+                &self.host,
+                prepared.skip_target_arg,
+            ),
+        )
+        .await?;
+
+        Ok(BuildOutcome {
+            target: prepared.actual_target,
+            status: run.status,
+            retries: run.retries,
+            cross_env: prepared.cross_env,
+        })
+    }
+}
+
+/// Install the Rust target, set up the cross toolchain and apply user overrides for one target.
+/// `host-tuple` resolves to the host triple and skips toolchain setup.
+pub async fn prepare_target(
+    target: &str,
+    args: &Args,
+    host: &HostPlatform,
+) -> Result<PreparedTarget> {
+    let is_host_build = target == "host-tuple";
+    let actual_target = if is_host_build { &host.triple } else { target };
+    let target_config = get_target_config(actual_target);
+    // Refuse up front, before rustup or any download, if test/bench would have no runner
+    if let Some(config) = target_config {
+        if !is_host_build && check_preconfigured_env(actual_target, args).is_none() {
+            check_runner_available(config, args, host)?;
+        }
+    }
+    let auto_build_std = metrics::timed(
+        Phase::Rustup,
+        ensure_target_installed(
+            actual_target,
+            args.toolchain.as_deref(),
+            !args.no_target_install,
+        ),
+    )
+    .await?;
+    let downloads_before = completed_downloads();
+    let mut cross_env = if is_host_build {
+        color::log_info(&format!(
+            "Building for host ({}), skipping toolchain setup",
+            color::cyan(actual_target)
+        ));
+        CrossEnv::new()
+    } else if let Some(env) = check_preconfigured_env(actual_target, args) {
+        color::log_success(&format!(
+            "Using pre-configured compiler from environment variables for {}",
+            color::yellow(actual_target)
+        ));
+        env
+    } else if let Some(config) = target_config {
+        setup_cross_env(config, args, host).await?
+    } else {
+        // Unknown target, use default environment
+        color::log_warning_once(
+            "no-toolchain-config",
+            &format!(
+                "No specific toolchain configuration for {}, using default",
+                color::cyan(actual_target)
+            ),
+        );
+        CrossEnv::new()
+    };
+
+    // Apply the CPU baseline to rustc and the C/C++ compilers
+    if let Some(cpu) = args.target_cpu_for(actual_target) {
+        cross_env.add_rustflag(format!("-C target-cpu={cpu}"));
+        if let Some(config) = target_config {
+            let c_flag = config.arch.c_cpu_arg(cpu);
+            cross_env.add_cflag(&c_flag);
+            cross_env.add_cxxflag(c_flag);
+        }
+    }
+
+    // Apply user-provided compiler overrides from CLI arguments
+    // CLI args have highest priority: CLI > env vars > auto-config
+    apply_user_overrides(&mut cross_env, args, actual_target);
+
+    if !args.use_sysroot_libs.is_empty() {
+        for name in use_sysroot_libs(&mut cross_env, &args.use_sysroot_libs, actual_target) {
+            color::log_warning_once(
+                &format!("sysroot-lib-{name}"),
+                &format!(
+                "--use-sysroot-libs: {name} not found in the sysroot of {}, the crate may build its own copy",
+                color::yellow(actual_target)
+            ));
+        }
+    }
+
+    // Enable build-std if auto-detected (target exists in rustc but not in rustup),
+    // unless the user explicitly disabled it
+    if auto_build_std {
+        match args.build_std {
+            BuildStd::Disabled => {
+                return Err(CrossError::BuildStdDisabled {
+                    target: actual_target.to_string(),
+                });
+            }
+            BuildStd::Unset if cross_env.build_std.is_none() => {
+                cross_env.build_std = Some("true".to_string());
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(choice) = build_std::resolve_auto(args, actual_target, target_config).await {
+        color::log_info(&format!(
+            "Build std for {}: {} ({})",
+            color::cyan(actual_target),
+            color::yellow(&choice.crates),
+            choice.reason
+        ));
+        cross_env.build_std = Some(choice.crates);
+    }
+
+    // Handle build-std requirement
+    let needs_build_std = match args.build_std {
+        BuildStd::Enabled(_) | BuildStd::Auto(_) => true,
+        BuildStd::Disabled => args.panic_immediate_abort,
+        BuildStd::Unset => args.panic_immediate_abort || cross_env.build_std.is_some(),
+    };
+
+    if needs_build_std {
+        metrics::timed(
+            Phase::Rustup,
+            ensure_rust_src(
+                actual_target,
+                args.toolchain.as_deref(),
+                !args.no_target_install,
+            ),
+        )
+        .await?;
+    }
+
+    if target_config.is_some() && !is_host_build && !args.skips_toolchain_setup() {
+        setup_libclang(&mut cross_env, actual_target, args, host).await?;
+    }
+
+    // Catch a broken toolchain before spending time on the Rust dependencies
+    if let Some(config) = target_config.filter(|_| !is_host_build) {
+        if should_smoke_test(args, completed_downloads() > downloads_before) {
+            smoke_test_toolchain(config, args, &cross_env, host).await?;
+        }
+    }
+
+    Ok(PreparedTarget {
+        actual_target: actual_target.to_string(),
+        skip_target_arg: args.skip_target_arg(target, &host.triple),
+        cross_env,
+    })
+}
+
+/// Check for pre-configured compiler environment variables
+/// Returns Some(CrossEnv) if CC_<target> or generic CC/CXX are set
+pub(crate) fn check_preconfigured_env(target: &str, args: &Args) -> Option<CrossEnv> {
+    // Skip if user explicitly wants to skip toolchain setup
+    if args.no_toolchain_setup {
+        return None;
+    }
+
+    let target_lower = target.replace('-', "_");
+    let target_upper = target_env_upper(target);
+
+    // Check target-specific CC_<target> first
+    let cc_target_var = format!("CC_{target_lower}");
+    let cxx_target_var = format!("CXX_{target_lower}");
+    let ar_target_var = format!("AR_{target_lower}");
+    let linker_var = format!("CARGO_TARGET_{target_upper}_LINKER");
+    let runner_var = format!("CARGO_TARGET_{target_upper}_RUNNER");
+
+    // Helper to get non-empty env var
+    let get_env = |name: &str| std::env::var(name).ok().filter(|s| !s.is_empty());
+
+    // Check if target-specific CC is set
+    if let Some(cc) = get_env(&cc_target_var) {
+        let mut env = CrossEnv::new();
+        env.set_cc(&cc);
+
+        if let Some(cxx) = get_env(&cxx_target_var) {
+            env.set_cxx(&cxx);
+        }
+        if let Some(ar) = get_env(&ar_target_var) {
+            env.set_ar(&ar);
+        }
+        if let Some(linker) = get_env(&linker_var) {
+            env.set_linker(&linker);
+        }
+        if let Some(runner) = get_env(&runner_var) {
+            env.set_runner(&runner);
+        }
+        mark_inherited(&mut env);
+        return Some(env);
+    }
+
+    // Check generic CC/CXX environment variables
+    // Only use if both CC and CXX are set (matching cross.sh behavior)
+    if let (Some(cc), Some(cxx)) = (get_env("CC"), get_env("CXX")) {
+        let mut env = CrossEnv::new();
+        env.set_cc(&cc);
+        env.set_cxx(&cxx);
+
+        // AR defaults to CC with -gcc suffix replaced by -ar
+        if let Some(ar) = get_env("AR") {
+            env.set_ar(&ar);
+        } else if cc.ends_with("-gcc") {
+            env.set_ar(format!("{}-ar", cc.trim_end_matches("-gcc")));
+        }
+
+        // Linker defaults to CC
+        if let Some(linker) = get_env("LINKER") {
+            env.set_linker(&linker);
+        } else {
+            env.set_linker(&cc);
+        }
+
+        // RUNNER support
+        if let Some(runner) = get_env("RUNNER") {
+            env.set_runner(&runner);
+        }
+
+        mark_inherited(&mut env);
+        return Some(env);
+    }
+
+    None
+}
+
+/// Tag the tools of a preconfigured environment as passed through from the caller
+fn mark_inherited(env: &mut CrossEnv) {
+    for (field, set) in [
+        ("cc", env.cc.is_some()),
+        ("cxx", env.cxx.is_some()),
+        ("ar", env.ar.is_some()),
+        ("linker", env.linker.is_some()),
+        ("runner", env.runner.is_some()),
+    ] {
+        if set {
+            env.set_tool_source(field, EnvSource::Inherited);
+        }
+    }
+}
+
+/// Apply user-provided compiler overrides from CLI arguments
+/// CLI arguments have the highest priority and override both env vars and auto-config;
+/// a `TARGET=PATH` entry wins over a bare PATH
+fn apply_user_overrides(env: &mut CrossEnv, args: &Args, target: &str) {
+    let tool = |entries: &[String]| {
+        resolve_override(entries, target, None)
+            .map(|(path, _)| path.to_string())
+            .filter(|path| !path.is_empty())
+    };
+    if let Some(cc) = tool(&args.cc) {
+        env.set_cc(cc);
+        env.set_tool_source("cc", EnvSource::Cli);
+    }
+    if let Some(cxx) = tool(&args.cxx) {
+        env.set_cxx(cxx);
+        env.set_tool_source("cxx", EnvSource::Cli);
+    }
+    if let Some(ar) = tool(&args.ar) {
+        env.set_ar(ar);
+        env.set_tool_source("ar", EnvSource::Cli);
+    }
+    if let Some((linker, source)) = resolve_override(&args.linker, target, env.linker.as_deref()) {
+        let linker = linker.to_string();
+        if source != OverrideSource::Default {
+            color::log_info(&format!(
+                "Using linker {} for {}",
+                color::cyan(&linker),
+                color::yellow(target)
+            ));
+            if let Some(flavor) = linker_flavor(target, &linker) {
+                env.add_rustflag(format!("-C linker-flavor={flavor}"));
+            }
+            env.set_tool_source("linker", EnvSource::Cli);
+        }
+        env.set_linker(linker);
+    }
+    if let Some(ref runner) = args.runner {
+        if !runner.is_empty() {
+            env.set_runner(runner);
+            env.set_tool_source("runner", EnvSource::Cli);
+        }
+    }
+}
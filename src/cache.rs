@@ -0,0 +1,885 @@
+//! Toolchain cache bookkeeping and size-based pruning
+
+use crate::cli::Args;
+use crate::color;
+use crate::config::{
+    DEFAULT_CROSS_MAKE_VERSION, DEFAULT_FREEBSD_VERSION, DEFAULT_GLIBC_VERSION,
+    DEFAULT_IPHONE_SDK_VERSION, DEFAULT_MACOS_SDK_VERSION, DEFAULT_NDK_VERSION,
+    DEFAULT_QEMU_VERSION,
+};
+use crate::env::CrossEnv;
+use crate::error::{CrossError, Result};
+use crate::layout;
+pub use crate::layout::LAYOUT_VERSION;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Index file (inside the cross compiler directory) recording when each toolchain was last used
+pub const CACHE_INDEX_FILE: &str = ".cargo-cross-index.json";
+
+/// Marker file (inside the cross compiler directory) recording the layout it was written with
+pub const LAYOUT_FILE: &str = "cross-compiler-dir-layout";
+
+/// A toolchain directory in the cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// Entry name relative to the cross compiler directory, e.g. `toolchains/<name>`
+    pub name: String,
+    /// Total size in bytes
+    pub size: u64,
+    /// Last use as seconds since the Unix epoch
+    pub last_used: u64,
+}
+
+/// Parse a size like "20G", "512M", "1.5T" or a plain byte count (binary units)
+pub fn parse_size(s: &str) -> std::result::Result<u64, String> {
+    let trimmed = s.trim();
+    let upper = trimmed.to_ascii_uppercase();
+    let upper = upper
+        .strip_suffix("IB")
+        .or_else(|| upper.strip_suffix('B'))
+        .unwrap_or(&upper);
+    let (number, multiplier) = match upper.chars().last() {
+        Some('K') => (&upper[..upper.len() - 1], 1u64 << 10),
+        Some('M') => (&upper[..upper.len() - 1], 1u64 << 20),
+        Some('G') => (&upper[..upper.len() - 1], 1u64 << 30),
+        Some('T') => (&upper[..upper.len() - 1], 1u64 << 40),
+        _ => (upper, 1),
+    };
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid size: {trimmed} (expected e.g. 20G, 512M)"))?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(format!("invalid size: {trimmed}"));
+    }
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    Ok((value * multiplier as f64) as u64)
+}
+
+/// Format a byte count for display
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// Collect the cache entries referenced by a configured environment
+#[must_use]
+pub fn toolchain_dirs_in_env(env: &CrossEnv, root: &Path) -> BTreeSet<String> {
+    let mut paths: Vec<PathBuf> = env.path.clone();
+    paths.extend(env.library_path.iter().cloned());
+    paths.extend(env.sysroot.iter().cloned());
+    paths.extend(env.sdkroot.iter().cloned());
+    paths.extend(
+        [&env.cc, &env.cxx, &env.ar, &env.linker, &env.runner]
+            .into_iter()
+            .flatten()
+            .map(PathBuf::from),
+    );
+
+    paths
+        .iter()
+        .filter_map(|path| layout::entry_of(root, path))
+        .collect()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn system_time_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Load the last-used index; a missing or corrupt index is treated as empty
+#[must_use]
+pub fn load_index(root: &Path) -> HashMap<String, u64> {
+    std::fs::read_to_string(root.join(CACHE_INDEX_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|value| value.as_object().cloned())
+        .map(|map| {
+            map.into_iter()
+                .filter_map(|(name, ts)| ts.as_u64().map(|ts| (name, ts)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn save_index(root: &Path, index: &HashMap<String, u64>) -> Result<()> {
+    let map: serde_json::Map<String, serde_json::Value> = index
+        .iter()
+        .map(|(name, ts)| (name.clone(), serde_json::Value::from(*ts)))
+        .collect();
+    let content = serde_json::to_string_pretty(&serde_json::Value::Object(map))?;
+    std::fs::write(root.join(CACHE_INDEX_FILE), content).map_err(|e| CrossError::IoError {
+        message: format!("Failed to write {}", root.join(CACHE_INDEX_FILE).display()),
+        source: e,
+    })
+}
+
+/// Mark toolchain directories as used now
+pub fn record_usage<'a>(root: &Path, names: impl IntoIterator<Item = &'a String>) -> Result<()> {
+    if !root.is_dir() {
+        return Ok(());
+    }
+    let mut index = load_index(root);
+    let now = now_secs();
+    for name in names {
+        index.insert(name.clone(), now);
+    }
+    // Forget directories that no longer exist
+    index.retain(|name, _| root.join(name).exists());
+    save_index(root, &index)
+}
+
+/// Total size in bytes of the files below `path`, not following symlinks
+#[must_use]
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(std::result::Result::ok)
+                .map(|entry| dir_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Scan the cache directory, using the index for last-use times and atime/mtime as fallback
+#[must_use]
+pub fn scan_cache(root: &Path) -> Vec<CacheEntry> {
+    let index = load_index(root);
+    layout::entries(root)
+        .into_iter()
+        // Adopted toolchains are links to another entry
+        .filter(|(_, path)| std::fs::symlink_metadata(path).is_ok_and(|m| m.is_dir()))
+        .map(|(name, path)| {
+            let last_used = index.get(&name).copied().unwrap_or_else(|| {
+                std::fs::metadata(&path)
+                    .ok()
+                    .and_then(|m| m.accessed().or_else(|_| m.modified()).ok())
+                    .map_or(0, system_time_secs)
+            });
+            CacheEntry {
+                size: dir_size(&path),
+                name,
+                last_used,
+            }
+        })
+        .collect()
+}
+
+/// Split a cross-make toolchain directory name `<prefix>-cross-<version>` into its prefix and
+/// version, e.g. `x86_64-linux-gnu-2.31-cross-v0.7.7` gives `x86_64-linux-gnu-2.31` and `v0.7.7`.
+/// Directories of other toolchains (NDK, osxcross, iOS, QEMU) give `None`. Entry names
+/// like `toolchains/<name>` are split by their last component.
+#[must_use]
+pub fn split_cross_make_dir(name: &str) -> Option<(&str, &str)> {
+    let name = name.rsplit('/').next().unwrap_or(name);
+    let (prefix, version) = name.rsplit_once("-cross-")?;
+    let mut chars = version.chars();
+    let versioned = chars.next() == Some('v') && chars.next().is_some_and(|c| c.is_ascii_digit());
+    (!prefix.is_empty() && versioned).then_some((prefix, version))
+}
+
+/// Whether `name` is a cross-make toolchain with another version in `in_use`
+fn is_superseded(name: &str, in_use: &HashSet<String>) -> bool {
+    split_cross_make_dir(name).is_some_and(|(prefix, version)| {
+        in_use
+            .iter()
+            .filter_map(|used| split_cross_make_dir(used))
+            .any(|(used_prefix, used_version)| used_prefix == prefix && used_version != version)
+    })
+}
+
+/// Cross-make toolchains cached in more than one version, as prefix and sorted versions
+#[must_use]
+pub fn coexisting_versions<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (prefix, version) in names.into_iter().filter_map(split_cross_make_dir) {
+        versions
+            .entry(prefix.to_string())
+            .or_default()
+            .push(version.to_string());
+    }
+    versions.retain(|_, versions| {
+        versions.sort();
+        versions.dedup();
+        versions.len() > 1
+    });
+    versions
+}
+
+/// Names of the entries in the cache directory, including adopted (symlinked) toolchains
+#[must_use]
+pub fn toolchain_dir_names(root: &Path) -> Vec<String> {
+    layout::entries(root)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Entry an adopted toolchain links to, if the entry `name` is a symlink in the cache
+#[must_use]
+pub fn adopted_from(root: &Path, name: &str) -> Option<String> {
+    let target = std::fs::read_link(root.join(name)).ok()?;
+    let target = target.file_name()?.to_string_lossy();
+    Some(match name.rsplit_once('/') {
+        Some((parent, _)) => layout::entry_name(parent, &target),
+        None => target.into_owned(),
+    })
+}
+
+/// Choose entries to delete until the total fits in `limit`: cross-make toolchains
+/// superseded by another version in use first, then least-recently-used ones.
+/// Entries in `in_use` are never selected.
+#[must_use]
+pub fn select_for_pruning(
+    entries: &[CacheEntry],
+    limit: u64,
+    in_use: &HashSet<String>,
+) -> Vec<CacheEntry> {
+    let mut total: u64 = entries.iter().map(|e| e.size).sum();
+    if total <= limit {
+        return Vec::new();
+    }
+
+    let mut candidates: Vec<&CacheEntry> = entries
+        .iter()
+        .filter(|e| !in_use.contains(&e.name))
+        .collect();
+    candidates.sort_by(|a, b| {
+        is_superseded(&b.name, in_use)
+            .cmp(&is_superseded(&a.name, in_use))
+            .then(a.last_used.cmp(&b.last_used))
+            .then(a.name.cmp(&b.name))
+    });
+
+    let mut selected = Vec::new();
+    for entry in candidates {
+        if total <= limit {
+            break;
+        }
+        total = total.saturating_sub(entry.size);
+        selected.push(entry.clone());
+    }
+    selected
+}
+
+/// Prune the cache down to `limit` bytes, never touching directories in `in_use` or the
+/// directories they were adopted from. Returns the pruned entries.
+pub fn prune_cache(root: &Path, limit: u64, in_use: &HashSet<String>) -> Result<Vec<CacheEntry>> {
+    let mut in_use = in_use.clone();
+    let adopted: Vec<String> = in_use
+        .iter()
+        .filter_map(|name| adopted_from(root, name))
+        .collect();
+    in_use.extend(adopted);
+
+    let entries = scan_cache(root);
+    let selected = select_for_pruning(&entries, limit, &in_use);
+
+    let mut index = load_index(root);
+    for entry in &selected {
+        let path = root.join(&entry.name);
+        std::fs::remove_dir_all(&path).map_err(|e| CrossError::IoError {
+            message: format!("Failed to remove {}", path.display()),
+            source: e,
+        })?;
+        index.remove(&entry.name);
+        color::log_info(&format!(
+            "Pruned toolchain cache entry {} ({})",
+            color::yellow(&entry.name),
+            format_size(entry.size)
+        ));
+    }
+    if !selected.is_empty() {
+        // Adopted toolchains linking to a pruned directory would dangle
+        for parent in layout::ENTRY_DIRS {
+            for entry in std::fs::read_dir(layout::entry_dir(root, parent))
+                .into_iter()
+                .flatten()
+                .flatten()
+            {
+                let name = layout::entry_name(parent, &entry.file_name().to_string_lossy());
+                let dangling = adopted_from(root, &name)
+                    .is_some_and(|target| selected.iter().any(|pruned| pruned.name == target));
+                if dangling {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        index.retain(|name, _| root.join(name).exists());
+        save_index(root, &index)?;
+    }
+
+    Ok(selected)
+}
+
+/// Layout marker contents: the layout version and the default dependency versions,
+/// whose changes rename toolchain folders
+#[must_use]
+pub fn layout_marker() -> String {
+    [
+        ("layout", LAYOUT_VERSION.to_string().as_str()),
+        ("cross-make", DEFAULT_CROSS_MAKE_VERSION),
+        ("glibc", DEFAULT_GLIBC_VERSION),
+        ("qemu", DEFAULT_QEMU_VERSION),
+        ("ndk", DEFAULT_NDK_VERSION),
+        ("macos-sdk", DEFAULT_MACOS_SDK_VERSION),
+        ("iphone-sdk", DEFAULT_IPHONE_SDK_VERSION),
+        ("freebsd", DEFAULT_FREEBSD_VERSION),
+    ]
+    .iter()
+    .map(|(key, value)| format!("{key}={value}\n"))
+    .collect()
+}
+
+/// Entries of `recorded` that differ from `current`, as `key: old -> new`
+#[must_use]
+pub fn layout_changes(recorded: &str, current: &str) -> Vec<String> {
+    let parse = |marker: &str| -> BTreeMap<String, String> {
+        marker
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect()
+    };
+    let (recorded, current) = (parse(recorded), parse(current));
+    current
+        .iter()
+        .filter(|(key, value)| recorded.get(*key) != Some(*value))
+        .map(|(key, value)| {
+            let old = recorded.get(key).map_or("(none)", String::as_str);
+            let display = |v: &str| {
+                if v.is_empty() {
+                    "(default)".to_string()
+                } else {
+                    v.to_string()
+                }
+            };
+            format!("{key}: {} -> {}", display(old), display(value))
+        })
+        .collect()
+}
+
+/// Layout version a marker was written with; markers without one predate versioning
+fn recorded_layout_version(marker: &str) -> u32 {
+    marker
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .find(|(key, _)| key.trim() == "layout")
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Move the entries of an older flat cross compiler directory into the current layout,
+/// carrying their last-use times over
+fn migrate_layout(root: &Path) -> Result<()> {
+    let moved = layout::migrate(root)?;
+    if moved.is_empty() {
+        return Ok(());
+    }
+    let mut index = load_index(root);
+    for (old, new) in &moved {
+        if let Some(last_used) = index.remove(old) {
+            index.insert(new.clone(), last_used);
+        }
+    }
+    save_index(root, &index)?;
+    color::log_info(&format!(
+        "Moved {} cached entries in {} into the layout {LAYOUT_VERSION} subdirectories",
+        color::yellow(&moved.len().to_string()),
+        root.display()
+    ));
+    Ok(())
+}
+
+/// Check the layout marker of the cross compiler directory.
+///
+/// A directory written with an older layout (or without a marker) is migrated to the current
+/// one first, and an empty directory just gets the marker. If cached toolchains were written
+/// by a release with different default versions, warn (or fail with `strict`) since the
+/// cache may hold stale or mixed toolchains, then record the current layout.
+pub fn check_layout(root: &Path, strict: bool) -> Result<()> {
+    if !root.is_dir() {
+        return Ok(());
+    }
+    let marker_path = root.join(LAYOUT_FILE);
+    let current = layout_marker();
+    let recorded = std::fs::read_to_string(&marker_path).ok();
+    let migrated = recorded
+        .as_deref()
+        .is_none_or(|recorded| recorded_layout_version(recorded) < LAYOUT_VERSION);
+    if migrated {
+        migrate_layout(root)?;
+    }
+    let changes = match recorded {
+        Some(recorded) => layout_changes(&recorded, &current)
+            .into_iter()
+            .filter(|change| !(migrated && change.starts_with("layout:")))
+            .collect(),
+        None if scan_cache(root).is_empty() => Vec::new(),
+        None => vec!["layout marker missing (cache written by an older cargo-cross)".to_string()],
+    };
+
+    if !changes.is_empty() {
+        let message = format!(
+            "Cached toolchains in {} were written with a different directory layout:\n  - {}\n\
+             They may be stale or mixed with new downloads. Delete the directory (or limit it with \
+             --toolchain-cache-limit) and key CI caches on `{} cache-key`.",
+            root.display(),
+            changes.join("\n  - "),
+            crate::cli::program_name()
+        );
+        if strict {
+            return Err(CrossError::InvalidArgument(format!(
+                "{message}\nRefusing to continue because of --strict-cache"
+            )));
+        }
+        color::log_warning(&message);
+    }
+
+    if changes.is_empty() && !migrated {
+        return Ok(());
+    }
+    write_layout_marker(root)
+}
+
+/// Record the current layout in the cross compiler directory
+pub fn write_layout_marker(root: &Path) -> Result<()> {
+    let marker_path = root.join(LAYOUT_FILE);
+    std::fs::write(&marker_path, layout_marker()).map_err(|e| CrossError::IoError {
+        message: format!("Failed to write {}", marker_path.display()),
+        source: e,
+    })
+}
+
+/// 64-bit FNV-1a, stable across Rust releases unlike `DefaultHasher`
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Cache key for the cross compiler directory of a run: changes whenever the layout,
+/// the selected dependency versions, the host or the targets change
+#[must_use]
+pub fn cache_key(args: &Args, host_triple: &str) -> String {
+    let mut targets = args.targets.clone();
+    targets.sort_unstable();
+    targets.dedup();
+    let input = format!(
+        "{}host={host_triple}\ntargets={}\ncross-make={}\nglibc={}\nqemu={}\nndk={}\n\
+         macos-sdk={}\niphone-sdk={}\nfreebsd={}\n",
+        layout_marker(),
+        targets.join(","),
+        args.cross_make_version,
+        args.glibc_version,
+        args.qemu_version,
+        args.ndk_version,
+        args.macos_sdk_version,
+        args.iphone_sdk_version,
+        args.freebsd_version,
+    );
+    format!(
+        "cargo-cross-layout{LAYOUT_VERSION}-{:016x}",
+        fnv1a(input.as_bytes())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{BuildArgs, Command};
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "cargo-cross-cache-test-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn entry(name: &str, size: u64, last_used: u64) -> CacheEntry {
+        CacheEntry {
+            name: name.to_string(),
+            size,
+            last_used,
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("2K"), Ok(2048));
+        assert_eq!(parse_size("512M"), Ok(512 << 20));
+        assert_eq!(parse_size("20G"), Ok(20 << 30));
+        assert_eq!(parse_size("20GiB"), Ok(20 << 30));
+        assert_eq!(parse_size("1.5g"), Ok(3 << 29));
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("-1G").is_err());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(10), "10 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(20 << 30), "20.0 GiB");
+    }
+
+    #[test]
+    fn test_select_for_pruning_lru_order() {
+        let entries = vec![
+            entry("new", 100, 300),
+            entry("old", 100, 100),
+            entry("mid", 100, 200),
+        ];
+        let selected = select_for_pruning(&entries, 150, &HashSet::new());
+        let names: Vec<_> = selected.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["old", "mid"]);
+    }
+
+    #[test]
+    fn test_select_for_pruning_under_limit() {
+        let entries = vec![entry("a", 100, 1), entry("b", 100, 2)];
+        assert!(select_for_pruning(&entries, 200, &HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn test_select_for_pruning_skips_in_use() {
+        let entries = vec![entry("old-in-use", 100, 1), entry("newer", 100, 3)];
+        let in_use: HashSet<String> = ["old-in-use".to_string()].into_iter().collect();
+        let selected = select_for_pruning(&entries, 100, &in_use);
+        let names: Vec<_> = selected.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["newer"]);
+    }
+
+    #[test]
+    fn test_toolchain_dirs_in_env() {
+        let root = Path::new("/cache");
+        let mut env = CrossEnv::new();
+        env.add_path("/cache/toolchains/aarch64-linux-musl-cross-v0.7.7/bin");
+        env.add_path("/usr/local/bin");
+        env.set_sysroot("/cache/toolchains/aarch64-linux-musl-cross-v0.7.7/aarch64-linux-musl");
+        env.set_runner("/cache/generated/docker-qemu-runner-aarch64-musl.sh");
+        env.set_cc("aarch64-linux-musl-gcc");
+
+        let dirs: Vec<_> = toolchain_dirs_in_env(&env, root).into_iter().collect();
+        assert_eq!(
+            dirs,
+            vec![
+                "generated".to_string(),
+                "toolchains/aarch64-linux-musl-cross-v0.7.7".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_record_usage_and_prune() {
+        let root = temp_root("prune");
+        for (name, size) in [("old", 300), ("current", 300), ("recent", 300)] {
+            let dir = layout::toolchain_dir(&root, name).join("bin");
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("gcc"), vec![0u8; size]).unwrap();
+        }
+
+        let mut index = HashMap::new();
+        index.insert("toolchains/old".to_string(), 10);
+        index.insert("toolchains/recent".to_string(), 20);
+        save_index(&root, &index).unwrap();
+        record_usage(&root, &["toolchains/current".to_string()]).unwrap();
+
+        let loaded = load_index(&root);
+        assert_eq!(loaded.get("toolchains/old"), Some(&10));
+        assert!(loaded.get("toolchains/current").copied().unwrap() > 20);
+
+        let in_use: HashSet<String> = ["toolchains/current".to_string()].into_iter().collect();
+        let pruned = prune_cache(&root, 650, &in_use).unwrap();
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned[0].name, "toolchains/old");
+        assert!(!root.join("toolchains/old").exists());
+        assert!(root.join("toolchains/current").exists());
+        assert!(root.join("toolchains/recent").exists());
+        assert!(!load_index(&root).contains_key("toolchains/old"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_split_cross_make_dir() {
+        use crate::config::{Abi, Arch, Libc};
+        use crate::platform::get_linux_folder_name;
+
+        let linux = [
+            get_linux_folder_name(Arch::Aarch64, Libc::Musl, None, "2.28", "2.28"),
+            get_linux_folder_name(Arch::Armv7, Libc::Musl, Some(Abi::Eabihf), "", ""),
+            get_linux_folder_name(Arch::X86_64, Libc::Gnu, None, "2.31", "2.28"),
+            get_linux_folder_name(Arch::X86_64, Libc::Gnu, Some(Abi::X32), "2.17", ""),
+        ];
+        let cases = [
+            (format!("{}-v0.7.7", linux[0]), "aarch64-linux-musl"),
+            (format!("{}-v0.7.7", linux[1]), "armv7-linux-musleabihf"),
+            (format!("{}-v0.7.7", linux[2]), "x86_64-linux-gnu-2.31"),
+            (format!("{}-v0.7.7", linux[3]), "x86_64-linux-gnux32-2.17"),
+            (
+                "mips-linux-gnusf-cross-v0.7.7".to_string(),
+                "mips-linux-gnusf",
+            ),
+            (
+                "x86_64-w64-mingw32-cross-v0.7.7".to_string(),
+                "x86_64-w64-mingw32",
+            ),
+            (
+                "x86_64-unknown-freebsd13.5-cross-v0.7.7".to_string(),
+                "x86_64-unknown-freebsd13.5",
+            ),
+            (
+                "x86_64-unknown-netbsd-cross-v0.7.7".to_string(),
+                "x86_64-unknown-netbsd",
+            ),
+        ];
+        for (name, prefix) in &cases {
+            assert_eq!(
+                split_cross_make_dir(name),
+                Some((*prefix, "v0.7.7")),
+                "{name}"
+            );
+        }
+        assert_eq!(
+            split_cross_make_dir("aarch64-linux-musl-cross-v0.8.0-rc1"),
+            Some(("aarch64-linux-musl", "v0.8.0-rc1"))
+        );
+
+        // Toolchains that are not versioned by cross-make
+        for name in [
+            "ios-arm64-cross-1010.6-18.5",
+            "ios-arm64-cross-simulator-1010.6-18.5",
+            "osxcross-14.5-amd64-v1.5",
+            "android-ndk-linux-r27d",
+            "qemu-user-static-v10.0.2-linux-amd64",
+            "docker-qemu-runner-aarch64-musl.sh",
+            "aarch64-linux-musl-cross",
+            "cmake",
+        ] {
+            assert_eq!(split_cross_make_dir(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_coexisting_versions() {
+        let versions = coexisting_versions([
+            "aarch64-linux-musl-cross-v0.7.7",
+            "aarch64-linux-musl-cross-v0.7.3",
+            "x86_64-linux-musl-cross-v0.7.7",
+            "android-ndk-linux-r27d",
+        ]);
+        assert_eq!(
+            versions.into_iter().collect::<Vec<_>>(),
+            [(
+                "aarch64-linux-musl".to_string(),
+                vec!["v0.7.3".to_string(), "v0.7.7".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_select_for_pruning_superseded_first() {
+        let entries = vec![
+            entry("android-ndk-linux-r27d", 100, 1),
+            entry("aarch64-linux-musl-cross-v0.7.3", 100, 5),
+            entry("aarch64-linux-musl-cross-v0.7.7", 100, 6),
+        ];
+        let in_use: HashSet<String> = ["aarch64-linux-musl-cross-v0.7.7".to_string()]
+            .into_iter()
+            .collect();
+        let selected = select_for_pruning(&entries, 200, &in_use);
+        let names: Vec<_> = selected.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["aarch64-linux-musl-cross-v0.7.3"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_prune_keeps_adopted_toolchains() {
+        let root = temp_root("adopted");
+        for name in [
+            "aarch64-linux-musl-cross-v0.7.3",
+            "x86_64-linux-musl-cross-v0.7.3",
+        ] {
+            let dir = layout::toolchain_dir(&root, name);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("gcc"), vec![0u8; 300]).unwrap();
+        }
+        for prefix in ["aarch64", "x86_64"] {
+            std::os::unix::fs::symlink(
+                format!("{prefix}-linux-musl-cross-v0.7.3"),
+                layout::toolchain_dir(&root, &format!("{prefix}-linux-musl-cross-v0.7.7")),
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            adopted_from(&root, "toolchains/aarch64-linux-musl-cross-v0.7.7").as_deref(),
+            Some("toolchains/aarch64-linux-musl-cross-v0.7.3")
+        );
+
+        let in_use: HashSet<String> = ["toolchains/aarch64-linux-musl-cross-v0.7.7".to_string()]
+            .into_iter()
+            .collect();
+        let pruned = prune_cache(&root, 300, &in_use).unwrap();
+        let names: Vec<_> = pruned.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["toolchains/x86_64-linux-musl-cross-v0.7.3"]);
+        assert!(root
+            .join("toolchains/aarch64-linux-musl-cross-v0.7.7/gcc")
+            .exists());
+        // The link to the pruned directory is removed with it
+        assert!(
+            std::fs::symlink_metadata(root.join("toolchains/x86_64-linux-musl-cross-v0.7.7"))
+                .is_err()
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_layout_changes() {
+        let current = layout_marker();
+        assert!(layout_changes(&current, &current).is_empty());
+
+        let recorded = current.replace(
+            &format!("cross-make={DEFAULT_CROSS_MAKE_VERSION}"),
+            "cross-make=v0.1.0",
+        );
+        assert_eq!(
+            layout_changes(&recorded, &current),
+            vec![format!(
+                "cross-make: v0.1.0 -> {DEFAULT_CROSS_MAKE_VERSION}"
+            )]
+        );
+        assert_eq!(
+            layout_changes("layout=1\n", "layout=2\nglibc=\n"),
+            vec!["glibc: (none) -> (default)", "layout: 1 -> 2"]
+        );
+    }
+
+    #[test]
+    fn test_check_layout() {
+        let root = temp_root("layout");
+        // Empty cache: the marker is written silently
+        check_layout(&root, true).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root.join(LAYOUT_FILE)).unwrap(),
+            layout_marker()
+        );
+
+        // Changed default versions: strict mode refuses and keeps the old marker
+        let changed = layout_marker().replace(
+            &format!("cross-make={DEFAULT_CROSS_MAKE_VERSION}"),
+            "cross-make=v0.1.0",
+        );
+        std::fs::write(root.join(LAYOUT_FILE), &changed).unwrap();
+        let err = check_layout(&root, true).unwrap_err().to_string();
+        assert!(err.contains("cross-make: v0.1.0 -> "), "{err}");
+        assert!(err.contains("--strict-cache"), "{err}");
+        assert_eq!(
+            std::fs::read_to_string(root.join(LAYOUT_FILE)).unwrap(),
+            changed
+        );
+
+        // Non-strict mode warns and records the current layout
+        check_layout(&root, false).unwrap();
+        check_layout(&root, true).unwrap();
+
+        // An older flat layout is migrated without a warning
+        let flat = layout_marker().replace(&format!("layout={LAYOUT_VERSION}"), "layout=1");
+        std::fs::write(root.join(LAYOUT_FILE), flat).unwrap();
+        std::fs::create_dir_all(root.join("aarch64-linux-musl-cross-v0.7.7/bin")).unwrap();
+        std::fs::write(
+            root.join("aarch64-linux-musl-cross-v0.7.7/bin/aarch64-linux-musl-gcc"),
+            "",
+        )
+        .unwrap();
+        save_index(
+            &root,
+            &[("aarch64-linux-musl-cross-v0.7.7".to_string(), 42)].into(),
+        )
+        .unwrap();
+        check_layout(&root, true).unwrap();
+        assert!(root
+            .join("toolchains/aarch64-linux-musl-cross-v0.7.7")
+            .is_dir());
+        assert_eq!(
+            load_index(&root).get("toolchains/aarch64-linux-musl-cross-v0.7.7"),
+            Some(&42)
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join(LAYOUT_FILE)).unwrap(),
+            layout_marker()
+        );
+
+        // Toolchains without a marker come from an older release
+        std::fs::remove_file(root.join(LAYOUT_FILE)).unwrap();
+        std::fs::create_dir_all(root.join("aarch64-linux-musl-cross/bin")).unwrap();
+        std::fs::write(
+            root.join("aarch64-linux-musl-cross/bin/aarch64-linux-musl-gcc"),
+            "",
+        )
+        .unwrap();
+        assert!(check_layout(&root, true).is_err());
+        assert!(root.join("toolchains/aarch64-linux-musl-cross").is_dir());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let mut args = Args {
+            command: Command::cache_key(),
+            cross_make_version: DEFAULT_CROSS_MAKE_VERSION.to_string(),
+            ..crate::cli::test_target_args(
+                "cache-key",
+                &["x86_64-unknown-linux-musl", "aarch64-unknown-linux-musl"],
+                BuildArgs::cli_defaults(),
+            )
+        };
+        let key = cache_key(&args, "x86_64-unknown-linux-gnu");
+        assert!(key.starts_with(&format!("cargo-cross-layout{LAYOUT_VERSION}-")));
+        assert_eq!(key.len(), "cargo-cross-layout2-".len() + 16);
+
+        // Target order does not matter, versions and host do
+        args.targets.reverse();
+        assert_eq!(cache_key(&args, "x86_64-unknown-linux-gnu"), key);
+        assert_ne!(cache_key(&args, "aarch64-apple-darwin"), key);
+        args.build.glibc_version = "2.28".to_string();
+        assert_ne!(cache_key(&args, "x86_64-unknown-linux-gnu"), key);
+    }
+}
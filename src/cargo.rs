@@ -1,17 +1,83 @@
 //! Cargo command builder and executor
 
-use crate::cli::Args;
+use crate::bench;
+use crate::build_scripts::{self, BuildScriptWatch};
+use crate::cli::{cleared_clap_env, Args, BuildStd, CrtStatic, SetupStep};
 use crate::color;
 use crate::config::{get_target_config, HostPlatform, Os};
-use crate::env::{get_build_std_config, CMakeToolchain, CrossEnv};
-use crate::error::{run_command, run_command_output, CrossError, Result};
+use crate::diagnose;
+use crate::download::strip_long_path_prefix;
+use crate::env::{
+    display_env_value, get_build_std_config, shell_quote, target_env_upper, CMakeToolchain,
+    CrossEnv, EnvMap, EnvSource,
+};
+use crate::error::{
+    run_command, run_command_output, run_command_prefixed, run_command_tee_stderr,
+    run_command_tee_stdout, run_command_watch_stderr, CrossError, Result,
+};
+use crate::metadata::{effective_cwd, resolve_cargo_path};
+use crate::plan::RunnerKind;
 use crate::platform::{
     cmake_toolchain_env_key, has_preconfigured_cmake_toolchain, prepare_cmake_toolchain_file,
 };
+use crate::report::{self, Event};
+use crate::repro;
+use crate::runner;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::ExitStatus;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
 use tokio::process::Command as TokioCommand;
 
+/// Known transient cargo failures: a description and a regex matched against cargo's stderr.
+/// Only these are re-run by `--retry-transient`; anything else is a real failure.
+pub const TRANSIENT_FAILURES: &[(&str, &str)] = &[
+    (
+        "sccache server startup race",
+        r"(?i)sccache: .*(timed out waiting for server startup|failed to (start|connect to) (the )?server|connection refused)",
+    ),
+    (
+        "crate metadata rename race",
+        r"could not rename crate|failed to rename `[^`]+\.rmeta",
+    ),
+    (
+        "file locked by another process (Access is denied)",
+        r"(?i)access is denied\. \(os error 5\)",
+    ),
+    (
+        "registry index lock timeout",
+        r"(?i)timed out waiting for (the )?(package cache|registry index|file) lock|index\.lock'?: file exists|failed to acquire (the )?(package cache|registry) lock",
+    ),
+];
+
+static TRANSIENT_PATTERNS: LazyLock<Vec<(&'static str, regex_lite::Regex)>> = LazyLock::new(|| {
+    TRANSIENT_FAILURES
+        .iter()
+        .map(|(name, pattern)| {
+            let regex = regex_lite::Regex::new(pattern).expect("invalid transient pattern");
+            (*name, regex)
+        })
+        .collect()
+});
+
+/// Description of the known transient failure in cargo's stderr, if any
+#[must_use]
+pub fn transient_failure(stderr: &str) -> Option<&'static str> {
+    TRANSIENT_PATTERNS
+        .iter()
+        .find(|(_, regex)| regex.is_match(stderr))
+        .map(|(name, _)| *name)
+}
+
+/// Exit status of cargo for a target and how often it was re-run
+#[derive(Debug, Clone, Copy)]
+pub struct CargoRun {
+    pub status: ExitStatus,
+    /// Re-runs after transient failures (`--retry-transient`)
+    pub retries: u32,
+}
+
 /// Build and execute cargo command for a target
 /// If `skip_target_arg` is true, don't pass --target to cargo (for host builds)
 pub async fn execute_cargo(
@@ -20,60 +86,328 @@ pub async fn execute_cargo(
     cross_env: &CrossEnv,
     host: &HostPlatform,
     skip_target_arg: bool,
-) -> Result<ExitStatus> {
-    // Build environment variables
-    let build_env = build_cargo_env(target, args, cross_env, host, skip_target_arg)?;
-
-    // Build cargo command
+) -> Result<CargoRun> {
+    let build_env = cargo_invocation_env(target, args, cross_env, host, skip_target_arg)?;
     let mut cmd = build_cargo_command(target, args, cross_env, skip_target_arg);
-
-    // Set environment variables
-    cmd.envs(&build_env);
+    cmd.envs(build_env.vars());
+    restore_cleared_env(&mut cmd, &build_env);
+    add_script_output_env(&mut cmd, args);
 
     // Print debug info
-    print_env_vars(&build_env);
+    print_env_vars(&build_env, args);
+    let debuginfo = auto_debuginfo_configs(args, target, |name| std::env::var_os(name).is_some());
+    if !debuginfo.is_empty() {
+        color::log_info(&format!(
+            "Keeping debug info for --qemu-gdb: --config {} (--no-auto-debuginfo to disable)",
+            debuginfo.join(" --config ")
+        ));
+    }
     color::print_run_header();
-    println!("{}", color::format_command(&format_command_from_cmd(&cmd)));
+    color::print_command(&format_command_from_cmd(&cmd));
+    if let Some(ref path) = args.emit_repro {
+        let path = repro::script_path(path, target, args.targets.len() > 1);
+        repro::write_script(&path, &cmd, target, &args.secret_env)?;
+        color::log_info(&format!(
+            "Wrote reproduction script: {}",
+            color::cyan(&path.display().to_string())
+        ));
+    }
+
+    report::emit(&Event::CargoStarted {
+        target: target.to_string(),
+        argv: repro::Invocation::from_command(&cmd).argv,
+    });
+
+    let finished = |status: ExitStatus, retries: u32| {
+        report::emit(&Event::CargoFinished {
+            target: target.to_string(),
+            code: status.code(),
+            retries,
+        });
+        Ok(CargoRun { status, retries })
+    };
+    let result = async {
+        let mut retries = 0;
+        loop {
+            let (status, stderr) = run_cargo_once(&mut cmd, target, args).await?;
+            if status.success() || retries >= args.retry_transient {
+                return finished(status, retries);
+            }
+            let Some(failure) = stderr.as_deref().and_then(transient_failure) else {
+                return finished(status, retries);
+            };
+            retries += 1;
+            color::log_warning(&format!(
+                "cargo failed with a transient error ({failure}), retrying {} ({retries}/{})",
+                color::yellow(target),
+                args.retry_transient
+            ));
+        }
+    }
+    .await;
+    // The Docker runner kept one container for all test binaries
+    if let Some(session) = build_env.get(runner::RUNNER_SESSION_ENV) {
+        runner::remove_session_containers(session).await;
+    }
+    result
+}
+
+/// Prefix for each output line of `cargo run` under `--sequential-runs`
+#[must_use]
+pub fn sequential_run_prefix(target: &str, args: &Args) -> Option<String> {
+    (args.sequential_runs && args.command.as_str() == "run").then(|| format!("[{target}] "))
+}
+
+/// Run cargo once, returning its stderr when it had to be captured
+async fn run_cargo_once(
+    cmd: &mut TokioCommand,
+    target: &str,
+    args: &Args,
+) -> Result<(ExitStatus, Option<String>)> {
+    if args.command.as_str() == "bench" && bench::wants_bencher_output(args) {
+        let (status, stdout) = run_command_tee_stdout(cmd, "cargo").await?;
+        let lines = bench::bencher_lines(&stdout);
+        if !lines.is_empty() {
+            let dir = bench::results_dir(target, args);
+            let written = std::fs::create_dir_all(&dir).and_then(|()| {
+                std::fs::write(dir.join(bench::BENCHER_FILE), lines.join("\n") + "\n")
+            });
+            if let Err(e) = written {
+                color::log_warning(&format!("Failed to save bencher output: {e}"));
+            }
+        }
+        return Ok((status, None));
+    }
+    if let Some(prefix) = sequential_run_prefix(target, args) {
+        let (status, stderr) = run_command_prefixed(cmd, "cargo", &prefix).await?;
+        return Ok((status, Some(stderr)));
+    }
+    let slow_build_scripts = build_scripts::warn_threshold(args);
+    let events = report::enabled();
+    if !args.diagnose_link_errors
+        && slow_build_scripts.is_none()
+        && args.retry_transient == 0
+        && !events
+        && !may_run_foreign_binaries(target, args)
+    {
+        return Ok((run_command(cmd, "cargo").await?, None));
+    }
+
+    keep_terminal_style(cmd);
+    let (status, stderr) = if slow_build_scripts.is_some() || events {
+        let mut watch =
+            slow_build_scripts.map(|threshold| BuildScriptWatch::new(threshold, Instant::now()));
+        run_command_watch_stderr(cmd, "cargo", Duration::from_secs(1), |line| {
+            let now = Instant::now();
+            if let Some(line) = line.filter(|_| events) {
+                report::emit(&Event::CargoOutput {
+                    target: target.to_string(),
+                    line: line.to_string(),
+                });
+            }
+            let Some(watch) = watch.as_mut() else {
+                return;
+            };
+            match line {
+                Some(line) => watch.observe(line, now),
+                None => {
+                    for warning in watch.overdue(now) {
+                        color::log_warning(&warning);
+                    }
+                }
+            }
+        })
+        .await?
+    } else {
+        run_command_tee_stderr(cmd, "cargo").await?
+    };
+    if args.diagnose_link_errors && !status.success() && diagnose::is_link_error(&stderr) {
+        report_leaked_artifacts(target, &stderr, &effective_cwd(args));
+    }
+    if !status.success() && !args.runner_for_build_scripts {
+        if let Some(foreign) = diagnose::find_foreign_exec(&stderr, target) {
+            color::log_error(&diagnose::format_foreign_exec(target, &foreign));
+        }
+    }
+    Ok((status, Some(stderr)))
+}
+
+/// Whether a build script could try to run a binary built for `target`, which the host
+/// cannot execute; cargo's stderr is then kept to explain such a failure
+fn may_run_foreign_binaries(target: &str, args: &Args) -> bool {
+    if args.runner_for_build_scripts {
+        return false;
+    }
+    let host = HostPlatform::detect();
+    get_target_config(target)
+        .is_some_and(|config| host.os != config.os.as_str() || !host.can_run_natively(config.arch))
+}
+
+/// Cargo only colours its output and draws a progress bar on a terminal. When its stderr
+/// is piped to be teed, keep both as they would be on the terminal cargo-cross runs in,
+/// unless the user configured them.
+fn keep_terminal_style(cmd: &mut TokioCommand) {
+    let term = console::Term::stderr();
+    if !term.is_term() || std::env::var_os("CARGO_CROSS_SILENT").is_some() {
+        return;
+    }
+    let configured = |cmd: &TokioCommand, name: &str| {
+        std::env::var_os(name).is_some() || cmd.as_std().get_envs().any(|(key, _)| key == name)
+    };
+    if !configured(cmd, "CARGO_TERM_COLOR") {
+        cmd.env("CARGO_TERM_COLOR", "always");
+    }
+    if let Some((_, width)) = term.size_checked() {
+        if !configured(cmd, "CARGO_TERM_PROGRESS_WHEN") {
+            cmd.env("CARGO_TERM_PROGRESS_WHEN", "always");
+            cmd.env("CARGO_TERM_PROGRESS_WIDTH", width.to_string());
+        }
+    }
+}
+
+/// Build the cargo command with its cross-compilation environment, without running it
+pub fn configured_cargo_command(
+    target: &str,
+    args: &Args,
+    cross_env: &CrossEnv,
+    host: &HostPlatform,
+    skip_target_arg: bool,
+) -> Result<TokioCommand> {
+    let build_env = cargo_invocation_env(target, args, cross_env, host, skip_target_arg)?;
+    let mut cmd = build_cargo_command(target, args, cross_env, skip_target_arg);
+    cmd.envs(build_env.vars());
+    restore_cleared_env(&mut cmd, &build_env);
+    add_script_output_env(&mut cmd, args);
+    Ok(cmd)
+}
+
+/// Environment of the cargo command itself: [`build_cargo_env`] plus the unprefixed tool
+/// shims of `--shim-unprefixed-tools`, which nothing else should see
+fn cargo_invocation_env(
+    target: &str,
+    args: &Args,
+    cross_env: &CrossEnv,
+    host: &HostPlatform,
+    skip_target_arg: bool,
+) -> Result<EnvMap> {
+    let mut env = build_cargo_env(target, args, cross_env, host, skip_target_arg)?;
+    if args.shim_unprefixed_tools && !skip_target_arg {
+        crate::shims::apply_shims(&mut env, target, &args.cross_compiler_dir, cross_env, host)?;
+    }
+    Ok(env)
+}
+
+/// The cargo argv (program first) that would run for a target, without its environment
+#[must_use]
+pub fn cargo_argv(
+    target: &str,
+    args: &Args,
+    cross_env: &CrossEnv,
+    skip_target_arg: bool,
+) -> Vec<String> {
+    let cmd = build_cargo_command(target, args, cross_env, skip_target_arg);
+    let cmd = cmd.as_std();
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Give a child process back the empty variables removed only so clap could parse the CLI,
+/// unless the cross environment sets them
+pub fn restore_cleared_env(cmd: &mut TokioCommand, build_env: &EnvMap) {
+    for name in cleared_clap_env() {
+        if !build_env.contains_key(&name) && std::env::var_os(&name).is_none() {
+            cmd.env(name, "");
+        }
+    }
+}
 
-    // Execute
-    let status = run_command(&mut cmd, "cargo").await?;
-    Ok(status)
+/// Print a diagnostic for host-built objects found in the `OUT_DIR`s of a failed link
+fn report_leaked_artifacts(target: &str, stderr: &str, cwd: &Path) {
+    let Some(target_config) = get_target_config(target) else {
+        return;
+    };
+    let expected = diagnose::expected_architecture(target_config.arch);
+    let leaks: Vec<_> = diagnose::extract_out_dirs(stderr)
+        .into_iter()
+        .map(|dir| cwd.join(dir))
+        .flat_map(|dir| diagnose::scan_out_dir(&dir, expected))
+        .collect();
+
+    if leaks.is_empty() {
+        color::log_warning(
+            "Link failed, but no native artifacts with a mismatched architecture were found",
+        );
+    } else {
+        color::log_error(&diagnose::format_diagnostic(target, expected, &leaks));
+    }
 }
 
 /// Format command string from `TokioCommand`
 fn format_command_from_cmd(cmd: &TokioCommand) -> String {
     let std_cmd = cmd.as_std();
-    let mut parts = vec![std_cmd.get_program().to_string_lossy().into_owned()];
-    for arg in std_cmd.get_args() {
-        parts.push(arg.to_string_lossy().into_owned());
-    }
-    parts.join(" ")
+    std::iter::once(std_cmd.get_program())
+        .chain(std_cmd.get_args())
+        .map(|arg| shell_quote(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 /// Build environment variables for cargo execution
+///
+/// Layers are applied from lowest to highest precedence: the toolchain environment,
+/// variables passed through from the caller's environment, then command-line options.
 pub fn build_cargo_env(
     target: &str,
     args: &Args,
     cross_env: &CrossEnv,
     host: &HostPlatform,
     skip_target_arg: bool,
-) -> Result<HashMap<String, String>> {
+) -> Result<EnvMap> {
     let target_lower = target.replace('-', "_");
-    let mut env = cross_env.build_env(target, host);
+    let mut toolchain_env = cross_env.build_env(target, host);
+    if host.is_windows() {
+        // Some tools reject `\\?\` paths, which only file operations need
+        for value in toolchain_env.values_mut() {
+            *value = strip_long_path_prefix(value);
+        }
+    }
+    let mut env = EnvMap::from_layer(toolchain_env, EnvSource::CrossEnv);
+    for (key, source) in cross_env.tool_env_sources(target) {
+        if let Some(value) = env.get(&key).cloned() {
+            env.insert(key, value, source);
+        }
+    }
 
     maybe_add_cmake_toolchain_env(&mut env, target, args, cross_env, host, skip_target_arg)?;
 
     // Handle host config for same-target builds (only when --target is explicitly passed)
     // When skip_target_arg is true, we don't pass --target to cargo, so these aren't needed
-    if !skip_target_arg && !args.no_cargo_target && target == host.triple {
-        add_host_config_env(&mut env);
+    if !skip_target_arg
+        && !args.no_cargo_target
+        && target == host.triple
+        && args.run_step(SetupStep::HostConfig, target)
+    {
+        match host_config_reason(args) {
+            Some(reason) => {
+                color::log_info(&format!(
+                    "Setting CARGO_UNSTABLE_HOST_CONFIG and CARGO_TARGET_APPLIES_TO_HOST=false \
+                     for {target} ({reason}), so build scripts and proc macros do not get the \
+                     target flags"
+                ));
+                add_host_config_env(&mut env);
+            }
+            None => color::log_debug(&format!(
+                "Not setting the host-config variables for {target}: they need a nightly \
+                 toolchain (pass --host-config to set them anyway)"
+            )),
+        }
     }
 
     // Build RUSTFLAGS
-    let rustflags = build_rustflags(args, cross_env);
-    if !rustflags.is_empty() {
-        env.insert("RUSTFLAGS".to_string(), rustflags);
-    }
+    let (rustflags, inherited, source) = build_rustflags(args, cross_env, target);
+    insert_rustflags_env(&mut env, target, &rustflags, inherited, source);
 
     // Add sccache/rustc wrapper
     add_wrapper_env(&mut env, args);
@@ -87,19 +421,117 @@ pub fn build_cargo_env(
     // Add user-provided compiler flags
     add_compiler_flags_env(&mut env, args, &target_lower);
 
+    // Let build scripts run target binaries through cargo's runner
+    if args.runner_for_build_scripts {
+        add_build_script_runner_env(&mut env, target);
+    }
+
+    // Tell build scripts what cargo-cross set up, so they need not guess it from CC
+    add_cross_context_env(&mut env, target, args, cross_env, host);
+
+    // Let tests skip themselves when they run emulated
+    if RunnerKind::from_runner(cross_env.runner.as_deref(), args.runner.is_some()).is_emulated() {
+        env.insert("CARGO_CROSS_EMULATED", "1", EnvSource::CrossEnv);
+    }
+
     // Add other environment variables
     if let Some(ref trim_paths) = args.cargo_trim_paths {
-        env.insert("CARGO_TRIM_PATHS".to_string(), trim_paths.clone());
+        env.insert("CARGO_TRIM_PATHS", trim_paths, EnvSource::Cli);
     }
     if let Some(ref bootstrap) = args.rustc_bootstrap {
-        env.insert("RUSTC_BOOTSTRAP".to_string(), bootstrap.clone());
+        env.insert("RUSTC_BOOTSTRAP", bootstrap, EnvSource::Cli);
+    }
+    // Keep each target's criterion data apart instead of overwriting it
+    if args.command.as_str() == "bench" {
+        env.insert(
+            "CRITERION_HOME",
+            bench::criterion_home(target, args).display().to_string(),
+            EnvSource::CrossEnv,
+        );
     }
 
     Ok(env)
 }
 
+/// `CARGO_CROSS_COLOR` for generated scripts: an explicit `--color`, otherwise whether
+/// cargo-cross itself colors its output
+#[must_use]
+pub fn script_color(color: Option<&str>, colorize: bool) -> &'static str {
+    match color {
+        Some("always") => "always",
+        Some("never") => "never",
+        _ if colorize => "always",
+        _ => "never",
+    }
+}
+
+/// Tell generated runner scripts how cargo-cross prints, through `CARGO_CROSS_COLOR`
+/// (`always` or `never`) and `CARGO_CROSS_QUIET` (`1` with `--quiet`)
+pub fn add_script_output_env(cmd: &mut TokioCommand, args: &Args) {
+    let colorize = colored::control::SHOULD_COLORIZE.should_colorize();
+    cmd.env(
+        "CARGO_CROSS_COLOR",
+        script_color(args.color.as_deref(), colorize),
+    );
+    cmd.env("CARGO_CROSS_QUIET", if args.quiet { "1" } else { "0" });
+}
+
+/// Expose the target's runner to build scripts as `CROSS_RUNNER`
+fn add_build_script_runner_env(env: &mut EnvMap, target: &str) {
+    let runner_var = format!("CARGO_TARGET_{}_RUNNER", target_env_upper(target));
+    if let Some(runner) = env.get(&runner_var).cloned() {
+        env.insert("CROSS_RUNNER", runner, EnvSource::CrossEnv);
+    }
+}
+
+/// Describe the cross setup to build scripts: `CARGO_CROSS=1`, `CARGO_CROSS_TARGET` and
+/// `CARGO_CROSS_RUNNER_KIND` (a [`RunnerKind`] name), plus `CARGO_CROSS_SYSROOT` (the
+/// toolchain's sysroot, else the Apple SDK), `CARGO_CROSS_TOOLCHAIN_DIR` and
+/// `CARGO_CROSS_GLIBC_VERSION` when the toolchain has them
+fn add_cross_context_env(
+    env: &mut EnvMap,
+    target: &str,
+    args: &Args,
+    cross_env: &CrossEnv,
+    host: &HostPlatform,
+) {
+    env.insert("CARGO_CROSS", "1", EnvSource::CrossEnv);
+    env.insert("CARGO_CROSS_TARGET", target, EnvSource::CrossEnv);
+    // The runner is only set up for commands that run binaries; build scripts of a
+    // `build` should see the same kind as those of a `test`
+    let runner = if cross_env.runner.is_some() {
+        RunnerKind::from_runner(cross_env.runner.as_deref(), args.runner.is_some())
+    } else {
+        crate::plan::target_runner_kind(target, args, host)
+    };
+    env.insert(
+        "CARGO_CROSS_RUNNER_KIND",
+        runner.as_str(),
+        EnvSource::CrossEnv,
+    );
+    let paths = [
+        (
+            "CARGO_CROSS_SYSROOT",
+            cross_env.sysroot.as_ref().or(cross_env.sdkroot.as_ref()),
+        ),
+        (
+            "CARGO_CROSS_TOOLCHAIN_DIR",
+            cross_env.toolchain_dir.as_ref(),
+        ),
+    ];
+    for (name, path) in paths {
+        if let Some(path) = path {
+            let value = strip_long_path_prefix(&path.display().to_string());
+            env.insert(name, value, EnvSource::CrossEnv);
+        }
+    }
+    if let Some(ref version) = cross_env.glibc_version {
+        env.insert("CARGO_CROSS_GLIBC_VERSION", version, EnvSource::CrossEnv);
+    }
+}
+
 fn maybe_add_cmake_toolchain_env(
-    env: &mut HashMap<String, String>,
+    env: &mut EnvMap,
     target: &str,
     args: &Args,
     cross_env: &CrossEnv,
@@ -129,6 +561,7 @@ fn maybe_add_cmake_toolchain_env(
         env.insert(
             cmake_toolchain_env_key(target),
             crate::platform::to_cmake_path(&toolchain_path),
+            EnvSource::CrossEnv,
         );
     }
 
@@ -144,114 +577,376 @@ fn infer_generic_cmake_toolchain(os: Os, cross_env: &CrossEnv) -> Option<CMakeTo
     Some(CMakeToolchain::Generic)
 }
 
-/// Build RUSTFLAGS string
-fn build_rustflags(args: &Args, cross_env: &CrossEnv) -> String {
-    let mut rustflags = std::env::var("RUSTFLAGS").unwrap_or_default();
+/// How `--crt-static` links the C runtime of a target, and why
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrtDecision {
+    pub static_crt: bool,
+    pub reason: &'static str,
+}
+
+/// The crt-static target feature passed for `target`, `None` when none is passed. `auto`
+/// spells out the target's default, for targets where rustc lets the choice matter.
+#[must_use]
+pub fn crt_static_decision(target: &str, setting: Option<CrtStatic>) -> Option<CrtDecision> {
+    let decision = |static_crt, reason| Some(CrtDecision { static_crt, reason });
+    match setting? {
+        CrtStatic::Static => decision(true, "--crt-static true"),
+        CrtStatic::Dynamic => decision(false, "--crt-static false"),
+        CrtStatic::Auto if target.contains("-musl") => decision(true, "musl default"),
+        // Bionic has no static libc for executables; rustc links Android dynamically
+        CrtStatic::Auto if target.contains("-android") => decision(false, "Android default"),
+        CrtStatic::Auto if target.contains("-linux-") => decision(false, "glibc default"),
+        CrtStatic::Auto if target.contains("-windows-") => decision(false, "Windows default"),
+        CrtStatic::Auto => None,
+    }
+}
 
-    // Add cross_env rustflags
-    if let Some(ref flags) = cross_env.rustflags_string() {
-        append_flag(&mut rustflags, flags);
+/// Warning for musl binaries linked dynamically: they only start where the musl loader is
+/// installed, while the QEMU runner always finds it in the toolchain sysroot
+#[must_use]
+pub fn dynamic_musl_warning(target: &str, decision: Option<CrtDecision>) -> Option<String> {
+    if !target.contains("-musl") || decision.is_none_or(|decision| decision.static_crt) {
+        return None;
+    }
+    let mut warning = format!(
+        "{target} links musl dynamically: its binaries need the musl loader \
+         (/lib/ld-musl-<arch>.so.1) and libc.so at runtime. Alpine ships them, glibc \
+         distributions do not. Tests and `run` load them from the toolchain sysroot, so they \
+         pass even where the binary would not start."
+    );
+    if ["riscv", "loongarch", "s390x", "powerpc", "mips"]
+        .iter()
+        .any(|arch| target.starts_with(arch))
+    {
+        warning.push_str(
+            " Dynamic linking for this architecture is incomplete in older musl toolchains.",
+        );
     }
+    Some(warning)
+}
+
+/// Build the rustc flags list, one argument per entry
+///
+/// Inherited flags come from `CARGO_ENCODED_RUSTFLAGS` when set (cargo gives it
+/// precedence), otherwise from `RUSTFLAGS`. Returns the arguments, how the flags were
+/// inherited, and the highest-precedence layer that contributed flags.
+fn build_rustflags(
+    args: &Args,
+    cross_env: &CrossEnv,
+    target: &str,
+) -> (Vec<String>, InheritedRustflags, EnvSource) {
+    let encoded = std::env::var("CARGO_ENCODED_RUSTFLAGS").unwrap_or_default();
+    let plain = std::env::var("RUSTFLAGS").unwrap_or_default();
+    let (mut rustflags, inherited): (Vec<String>, _) = if !encoded.is_empty() {
+        (
+            encoded.split('\x1f').map(str::to_string).collect(),
+            InheritedRustflags::Encoded,
+        )
+    } else if !plain.trim().is_empty() {
+        (
+            plain.split_whitespace().map(str::to_string).collect(),
+            InheritedRustflags::Plain,
+        )
+    } else {
+        (Vec::new(), InheritedRustflags::None)
+    };
+
+    // Add cross_env rustflags (already split into arguments, may contain paths with spaces)
+    rustflags.extend(cross_env.rustflags.iter().cloned());
+
+    let mut extra = String::new();
 
     // Add CRT static flag
-    if let Some(crt_static) = args.crt_static {
-        let flag = if crt_static {
+    if let Some(decision) = crt_static_decision(target, args.crt_static)
+        .filter(|_| args.run_step(SetupStep::RustflagsCrt, target))
+    {
+        let flag = if decision.static_crt {
             "-C target-feature=+crt-static"
         } else {
             "-C target-feature=-crt-static"
         };
-        append_flag(&mut rustflags, flag);
+        append_flag(&mut extra, flag);
     }
 
     // Add panic=immediate-abort flag
     if args.panic_immediate_abort {
-        append_flag(&mut rustflags, "-Zunstable-options -Cpanic=immediate-abort");
+        append_flag(
+            &mut extra,
+            panic_immediate_abort_flags(has_immediate_abort_strategy(
+                args.selected_toolchain().as_deref(),
+            )),
+        );
     }
 
     // Add fmt-debug flag
     if let Some(ref fmt_debug) = args.fmt_debug {
-        append_flag(&mut rustflags, &format!("-Zfmt-debug={fmt_debug}"));
+        append_flag(&mut extra, &format!("-Zfmt-debug={fmt_debug}"));
     }
 
     // Add location-detail flag
     if let Some(ref location_detail) = args.location_detail {
-        append_flag(
-            &mut rustflags,
-            &format!("-Zlocation-detail={location_detail}"),
-        );
+        append_flag(&mut extra, &format!("-Zlocation-detail={location_detail}"));
     }
 
     // Add additional rustflags from command line
     for flag in &args.rustflags {
-        append_flag(&mut rustflags, flag);
+        append_flag(&mut extra, flag);
+    }
+
+    let source = if !extra.trim().is_empty() {
+        EnvSource::Cli
+    } else if !cross_env.rustflags.is_empty() {
+        EnvSource::CrossEnv
+    } else {
+        EnvSource::Inherited
+    };
+    rustflags.extend(extra.split_whitespace().map(str::to_string));
+    (rustflags, inherited, source)
+}
+
+/// Rustc flags cargo-cross found in its own environment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InheritedRustflags {
+    None,
+    /// From `RUSTFLAGS`
+    Plain,
+    /// From `CARGO_ENCODED_RUSTFLAGS`
+    Encoded,
+}
+
+/// `CARGO_TARGET_<TRIPLE>_RUSTFLAGS` of `target`
+#[must_use]
+pub fn target_rustflags_var(target: &str) -> String {
+    format!("CARGO_TARGET_{}_RUSTFLAGS", target_env_upper(target))
+}
+
+/// Insert the rustc flags of `target`.
+///
+/// They go to `CARGO_TARGET_<TRIPLE>_RUSTFLAGS`, so the crt-static, panic, fmt-debug and
+/// location-detail flags of one target never reach the units of another, and cargo merges
+/// them with `target.<triple>.rustflags` from its configuration instead of ignoring those.
+/// Cargo reads that variable only while neither `RUSTFLAGS` nor `CARGO_ENCODED_RUSTFLAGS`
+/// is set, so the flags can only be target-scoped as a whole. They stay global when:
+/// - flags were inherited from either variable, which cargo would otherwise keep using
+///   alone: they go back where they came from, with ours appended
+/// - an argument contains whitespace (e.g. a toolchain path with spaces), which only
+///   `CARGO_ENCODED_RUSTFLAGS` can carry
+fn insert_rustflags_env(
+    env: &mut EnvMap,
+    target: &str,
+    flags: &[String],
+    inherited: InheritedRustflags,
+    source: EnvSource,
+) {
+    if flags.is_empty() {
+        return;
+    }
+    if inherited == InheritedRustflags::Encoded
+        || flags.iter().any(|flag| flag.contains(char::is_whitespace))
+    {
+        env.insert("CARGO_ENCODED_RUSTFLAGS", flags.join("\x1f"), source);
+    } else if inherited == InheritedRustflags::Plain {
+        env.insert("RUSTFLAGS", flags.join(" "), source);
+    } else {
+        env.insert(target_rustflags_var(target), flags.join(" "), source);
+    }
+}
+
+/// Whether `rustc -vV` output is from a nightly (or locally built dev) compiler
+fn rustc_is_nightly(version_verbose: &str) -> bool {
+    version_verbose
+        .lines()
+        .find_map(|line| line.strip_prefix("release:"))
+        .is_some_and(|release| release.contains("-nightly") || release.contains("-dev"))
+}
+
+/// Whether the rustup default toolchain is nightly, asked once from `rustc -vV`
+static DEFAULT_TOOLCHAIN_IS_NIGHTLY: LazyLock<bool> = LazyLock::new(|| {
+    std::process::Command::new("rustc")
+        .arg("-vV")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .is_some_and(|output| rustc_is_nightly(&output))
+});
+
+/// Whether the toolchain cargo runs with is nightly: the selected one, or else the rustup
+/// default
+fn runs_on_nightly(args: &Args) -> bool {
+    args.toolchain_is_nightly()
+        .unwrap_or_else(|| *DEFAULT_TOOLCHAIN_IS_NIGHTLY)
+}
+
+/// Whether cargo accepts unstable options: on nightly, or with `RUSTC_BOOTSTRAP=1`
+#[must_use]
+pub fn cargo_unstable_allowed(args: &Args) -> bool {
+    args.rustc_bootstrap.as_deref() == Some("1") || runs_on_nightly(args)
+}
+
+/// Whether `--artifact-dir` is passed on to cargo, which only accepts it when unstable
+/// options are allowed, and only takes it for `build`. Otherwise, and with `--artifact-name`,
+/// `--debuginfo-artifacts` or several targets (whose artifacts go to a directory each),
+/// cargo-cross copies the artifacts itself; `bench` collects its results there instead.
+#[must_use]
+pub fn forwards_artifact_dir(args: &Args) -> bool {
+    args.artifact_dir.is_some()
+        && args.artifact_name.is_none()
+        && !args.debuginfo_artifacts
+        && args.targets.len() <= 1
+        && args.command.as_str() == "build"
+        && cargo_unstable_allowed(args)
+}
+
+/// Whether the command links the binaries and libraries `--artifact-dir` collects
+#[must_use]
+pub fn produces_artifacts(args: &Args) -> bool {
+    matches!(args.command.as_str(), "build" | "rustc")
+}
+
+/// `-Z` flags for cargo: the user's, plus `unstable-options` for a forwarded `--artifact-dir`
+fn cargo_z_flags(args: &Args) -> Vec<&str> {
+    let mut flags: Vec<&str> = args.cargo_z_flags.iter().map(String::as_str).collect();
+    if forwards_artifact_dir(args) && !flags.contains(&"unstable-options") {
+        flags.push("unstable-options");
     }
+    flags
+}
+
+/// `--config` values that keep debug info for `--qemu-gdb`: `debug=true` and `strip="none"`
+/// for profiles that do not have them by default. Keys the user sets with `--config` or
+/// `CARGO_PROFILE_<NAME>_<KEY>` (per `env_set`) win, and `--no-auto-debuginfo` turns it off.
+#[must_use]
+pub fn auto_debuginfo_configs(
+    args: &Args,
+    target: &str,
+    env_set: impl Fn(&str) -> bool,
+) -> Vec<String> {
+    if args.qemu_gdb.is_none()
+        || args.no_auto_debuginfo
+        || matches!(args.profile.as_str(), "dev" | "debug" | "test")
+    {
+        return Vec::new();
+    }
+    let user_keys: Vec<String> = args
+        .cargo_config_for(target)
+        .into_iter()
+        .filter_map(|config| config.split_once('='))
+        .map(|(key, _)| key.chars().filter(|c| !c.is_whitespace()).collect())
+        .collect();
+    let env_prefix = format!(
+        "CARGO_PROFILE_{}_",
+        args.profile.to_ascii_uppercase().replace('-', "_")
+    );
+    [("debug", "true"), ("strip", "\"none\"")]
+        .into_iter()
+        .filter(|(key, _)| {
+            let config_key = format!("profile.{}.{key}", args.profile);
+            !user_keys.contains(&config_key)
+                && !env_set(&format!("{env_prefix}{}", key.to_ascii_uppercase()))
+        })
+        .map(|(key, value)| format!("profile.{}.{key}={value}", args.profile))
+        .collect()
+}
 
-    rustflags
+/// Why the host-config variables are set for a host build, or `None` to leave them out:
+/// they are nightly-only cargo features, which stable cargo ignores or warns about
+fn host_config_reason(args: &Args) -> Option<&'static str> {
+    if args.host_config {
+        Some("--host-config")
+    } else if runs_on_nightly(args) {
+        Some("nightly toolchain")
+    } else {
+        None
+    }
 }
 
 /// Add host config environment variables for same-target builds
 /// These are needed when explicitly passing --target that matches the host
-fn add_host_config_env(env: &mut HashMap<String, String>) {
-    env.insert("CARGO_UNSTABLE_HOST_CONFIG".to_string(), "true".to_string());
-    env.insert(
-        "CARGO_UNSTABLE_TARGET_APPLIES_TO_HOST".to_string(),
-        "true".to_string(),
-    );
+fn add_host_config_env(env: &mut EnvMap) {
+    env.insert("CARGO_UNSTABLE_HOST_CONFIG", "true", EnvSource::CrossEnv);
     env.insert(
-        "CARGO_TARGET_APPLIES_TO_HOST".to_string(),
-        "false".to_string(),
+        "CARGO_UNSTABLE_TARGET_APPLIES_TO_HOST",
+        "true",
+        EnvSource::CrossEnv,
     );
+    env.insert("CARGO_TARGET_APPLIES_TO_HOST", "false", EnvSource::CrossEnv);
 }
 
 /// Add wrapper environment (sccache or `rustc_wrapper`)
-fn add_wrapper_env(env: &mut HashMap<String, String>, args: &Args) {
+fn add_wrapper_env(env: &mut EnvMap, args: &Args) {
     if args.enable_sccache {
-        env.insert("RUSTC_WRAPPER".to_string(), "sccache".to_string());
+        env.insert("RUSTC_WRAPPER", "sccache", EnvSource::Cli);
     } else if let Some(ref wrapper) = args.rustc_wrapper {
-        env.insert("RUSTC_WRAPPER".to_string(), wrapper.display().to_string());
+        env.insert(
+            "RUSTC_WRAPPER",
+            wrapper.display().to_string(),
+            EnvSource::Cli,
+        );
     }
 }
 
 /// Add sccache environment variables
-fn add_sccache_env(env: &mut HashMap<String, String>, args: &Args) {
+pub(crate) fn add_sccache_env(env: &mut EnvMap, args: &Args) {
     // First, pass through all SCCACHE_* environment variables from current environment
     for (key, val) in std::env::vars() {
         if key.starts_with("SCCACHE_") && !val.is_empty() {
-            env.insert(key, val);
+            env.insert(key, val, EnvSource::Inherited);
         }
     }
 
     // Then, override with args-based settings (args have higher priority than env vars)
     if let Some(ref dir) = args.sccache_dir {
-        env.insert("SCCACHE_DIR".to_string(), dir.display().to_string());
+        env.insert("SCCACHE_DIR", dir.display().to_string(), EnvSource::Cli);
     }
     if let Some(ref size) = args.sccache_cache_size {
-        env.insert("SCCACHE_CACHE_SIZE".to_string(), size.clone());
+        env.insert("SCCACHE_CACHE_SIZE", size, EnvSource::Cli);
     }
     if let Some(ref timeout) = args.sccache_idle_timeout {
-        env.insert("SCCACHE_IDLE_TIMEOUT".to_string(), timeout.clone());
+        env.insert("SCCACHE_IDLE_TIMEOUT", timeout, EnvSource::Cli);
     }
     if let Some(ref log) = args.sccache_log {
-        env.insert("SCCACHE_LOG".to_string(), log.clone());
+        env.insert("SCCACHE_LOG", log, EnvSource::Cli);
     }
     if args.sccache_no_daemon {
-        env.insert("SCCACHE_NO_DAEMON".to_string(), "1".to_string());
+        env.insert("SCCACHE_NO_DAEMON", "1", EnvSource::Cli);
     }
     if args.sccache_direct {
-        env.insert("SCCACHE_DIRECT".to_string(), "true".to_string());
+        env.insert("SCCACHE_DIRECT", "true", EnvSource::Cli);
+    }
+    for (key, value) in [
+        ("SCCACHE_BUCKET", &args.sccache_bucket),
+        ("SCCACHE_REGION", &args.sccache_region),
+        ("SCCACHE_ENDPOINT", &args.sccache_endpoint),
+        ("SCCACHE_GCS_BUCKET", &args.sccache_gcs_bucket),
+        ("SCCACHE_REDIS_ENDPOINT", &args.sccache_redis),
+        ("SCCACHE_DIST_SCHEDULER_URL", &args.sccache_dist_scheduler),
+    ] {
+        if let Some(value) = value {
+            env.insert(key, value, EnvSource::Cli);
+        }
     }
 }
 
 /// Add CC crate environment variables
-fn add_cc_crate_env(env: &mut HashMap<String, String>, args: &Args) {
+fn add_cc_crate_env(env: &mut EnvMap, args: &Args) {
     if args.cc_no_defaults {
-        env.insert("CRATE_CC_NO_DEFAULTS".to_string(), "1".to_string());
+        env.insert("CRATE_CC_NO_DEFAULTS", "1", EnvSource::Cli);
     }
     if args.cc_shell_escaped_flags {
-        env.insert("CC_SHELL_ESCAPED_FLAGS".to_string(), "1".to_string());
+        env.insert("CC_SHELL_ESCAPED_FLAGS", "1", EnvSource::Cli);
     }
-    if args.cc_enable_debug || args.verbose_level > 0 {
-        env.insert("CC_ENABLE_DEBUG_OUTPUT".to_string(), "1".to_string());
+    // Not tied to -v: build scripts that read it would rerun whenever verbosity changes
+    if args.cc_enable_debug {
+        env.insert("CC_ENABLE_DEBUG_OUTPUT", "1", EnvSource::Cli);
+    }
+    if let Some(jobs) = args.c_jobs {
+        // Not NUM_JOBS: cargo sets that for every build script from its own -j
+        env.insert("MAKEFLAGS", format!("-j{jobs}"), EnvSource::Cli);
+        env.insert(
+            "CMAKE_BUILD_PARALLEL_LEVEL",
+            jobs.to_string(),
+            EnvSource::Cli,
+        );
     }
 
     // Pass through additional CC crate environment variables
@@ -259,14 +954,14 @@ fn add_cc_crate_env(env: &mut HashMap<String, String>, args: &Args) {
     for var in passthrough_vars {
         if let Ok(val) = std::env::var(var) {
             if !val.is_empty() {
-                env.insert(var.to_string(), val);
+                env.insert(var, val, EnvSource::Inherited);
             }
         }
     }
 }
 
 /// Add user-provided compiler flags
-fn add_compiler_flags_env(env: &mut HashMap<String, String>, args: &Args, target_lower: &str) {
+fn add_compiler_flags_env(env: &mut EnvMap, args: &Args, target_lower: &str) {
     if let Some(ref cflags) = args.cflags {
         let existing = env
             .get(&format!("CFLAGS_{target_lower}"))
@@ -277,8 +972,7 @@ fn add_compiler_flags_env(env: &mut HashMap<String, String>, args: &Args, target
         } else {
             format!("{existing} {cflags}")
         };
-        env.insert(format!("CFLAGS_{target_lower}"), new_flags.clone());
-        env.insert("CFLAGS".to_string(), new_flags);
+        env.insert(format!("CFLAGS_{target_lower}"), new_flags, EnvSource::Cli);
     }
 
     if let Some(ref cxxflags) = args.cxxflags {
@@ -291,8 +985,11 @@ fn add_compiler_flags_env(env: &mut HashMap<String, String>, args: &Args, target
         } else {
             format!("{existing} {cxxflags}")
         };
-        env.insert(format!("CXXFLAGS_{target_lower}"), new_flags.clone());
-        env.insert("CXXFLAGS".to_string(), new_flags);
+        env.insert(
+            format!("CXXFLAGS_{target_lower}"),
+            new_flags,
+            EnvSource::Cli,
+        );
     }
 
     if let Some(ref ldflags) = args.ldflags {
@@ -305,13 +1002,16 @@ fn add_compiler_flags_env(env: &mut HashMap<String, String>, args: &Args, target
         } else {
             format!("{existing} {ldflags}")
         };
-        env.insert(format!("LDFLAGS_{target_lower}"), new_flags.clone());
-        env.insert("LDFLAGS".to_string(), new_flags);
+        env.insert(format!("LDFLAGS_{target_lower}"), new_flags, EnvSource::Cli);
     }
 
     if let Some(ref cxxstdlib) = args.cxxstdlib {
-        env.insert(format!("CXXSTDLIB_{target_lower}"), cxxstdlib.clone());
-        env.insert("CXXSTDLIB".to_string(), cxxstdlib.clone());
+        env.insert(
+            format!("CXXSTDLIB_{target_lower}"),
+            cxxstdlib,
+            EnvSource::Cli,
+        );
+        env.insert("CXXSTDLIB", cxxstdlib, EnvSource::Cli);
     }
 }
 
@@ -338,12 +1038,15 @@ fn build_cargo_command(
     }
 
     // -Z flags
-    for flag in &args.cargo_z_flags {
+    for flag in cargo_z_flags(args) {
         cmd.arg("-Z").arg(flag);
     }
 
-    // --config flags
-    for config in &args.cargo_config {
+    // --config flags, keeping command-line order so later values win
+    for config in auto_debuginfo_configs(args, target, |name| std::env::var_os(name).is_some()) {
+        cmd.arg("--config").arg(config);
+    }
+    for config in args.cargo_config_for(target) {
         cmd.arg("--config").arg(config);
     }
 
@@ -374,19 +1077,18 @@ fn build_cargo_command(
     add_dependency_args(&mut cmd, args);
 
     // Build configuration
-    add_build_config_args(&mut cmd, args);
+    add_build_config_args(&mut cmd, target, args);
 
     // Additional cargo args
     for arg in &args.cargo_args {
         cmd.arg(arg);
     }
 
-    // Passthrough arguments
-    if !args.passthrough_args.is_empty() {
+    // Passthrough arguments, then the `--skip-tests` filters
+    let passthrough = args.passthrough_args_for(target);
+    if !passthrough.is_empty() {
         cmd.arg("--");
-        for arg in &args.passthrough_args {
-            cmd.arg(arg);
-        }
+        cmd.args(passthrough);
     }
 
     cmd
@@ -416,13 +1118,14 @@ fn add_feature_args(cmd: &mut TokioCommand, args: &Args) {
 
 /// Add package and target selection arguments
 fn add_package_args(cmd: &mut TokioCommand, args: &Args) {
-    if let Some(ref package) = args.package {
+    for package in &args.package {
         cmd.arg("--package").arg(package);
     }
-    if args.workspace {
+    // Default members have already been resolved into explicit packages
+    if args.workspace && !args.default_members_only {
         cmd.arg("--workspace");
     }
-    if let Some(ref exclude) = args.exclude {
+    for exclude in &args.exclude {
         cmd.arg("--exclude").arg(exclude);
     }
     if let Some(ref bin) = args.bin_target {
@@ -460,29 +1163,107 @@ fn add_package_args(cmd: &mut TokioCommand, args: &Args) {
     }
 }
 
+/// Crates passed to `-Zbuild-std` for a target, if build-std is used
+#[must_use]
+pub fn build_std_crates(args: &Args, cross_env: &CrossEnv) -> Option<String> {
+    // An explicit BUILD_STD=false wins over any auto-configured value
+    match args.build_std {
+        BuildStd::Disabled => None,
+        BuildStd::Enabled(ref crates) => Some(crates.as_str()),
+        BuildStd::Unset | BuildStd::Auto(_) => cross_env.build_std.as_deref(),
+    }
+    .map(|s| {
+        if s == "true" {
+            get_build_std_config().to_string()
+        } else {
+            s.to_string()
+        }
+    })
+}
+
 /// Add build-std arguments
 fn add_build_std_args(cmd: &mut TokioCommand, args: &Args, cross_env: &CrossEnv) {
-    let build_std_value = args
-        .build_std
-        .as_ref()
-        .or(cross_env.build_std.as_ref())
-        .map(|s| {
-            if s == "true" {
-                get_build_std_config().to_string()
-            } else {
-                s.clone()
-            }
-        });
-
-    if let Some(ref crates) = build_std_value {
+    if let Some(ref crates) = build_std_crates(args, cross_env) {
         cmd.arg(format!("-Zbuild-std={crates}"));
     }
 
-    if let Some(ref features) = args.build_std_features {
+    if let Some(ref features) = build_std_features(args) {
         cmd.arg(format!("-Zbuild-std-features={features}"));
     }
 }
 
+/// Whether rustc has the `immediate-abort` panic strategy, which replaced std's
+/// `panic_immediate_abort` feature; asked once per toolchain
+fn has_immediate_abort_strategy(toolchain: Option<&str>) -> bool {
+    static PROBED: LazyLock<Mutex<HashMap<Option<String>, bool>>> = LazyLock::new(Default::default);
+    let key = toolchain.map(str::to_string);
+    if let Some(&known) = PROBED
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .get(&key)
+    {
+        return known;
+    }
+    let mut cmd = std::process::Command::new("rustc");
+    if let Some(tc) = toolchain {
+        cmd.arg(format!("+{tc}"));
+    }
+    // RUSTC_BOOTSTRAP only lets the probe answer on stable; the build itself needs nightly
+    let supported = cmd
+        .args([
+            "-Zunstable-options",
+            "-Cpanic=immediate-abort",
+            "--print",
+            "cfg",
+        ])
+        .env("RUSTC_BOOTSTRAP", "1")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    PROBED
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .insert(key, supported);
+    supported
+}
+
+/// Rustflags for `--panic-immediate-abort`: the panic strategy where rustc has it, otherwise
+/// panic=abort with std's `panic_immediate_abort` feature
+fn panic_immediate_abort_flags(strategy: bool) -> &'static str {
+    if strategy {
+        "-Zunstable-options -Cpanic=immediate-abort"
+    } else {
+        "-Cpanic=abort"
+    }
+}
+
+/// `--build-std-features` with std's `panic_immediate_abort` feature added for
+/// `--panic-immediate-abort` on toolchains without the panic strategy, and removed on those
+/// with it, whose std rejects the feature
+fn panic_immediate_abort_features(features: Option<&str>, strategy: bool) -> Option<String> {
+    let mut features: Vec<&str> = features
+        .unwrap_or_default()
+        .split([',', ' '])
+        .filter(|feature| !feature.is_empty() && *feature != "panic_immediate_abort")
+        .collect();
+    if !strategy {
+        features.push("panic_immediate_abort");
+    }
+    (!features.is_empty()).then(|| features.join(","))
+}
+
+/// `-Zbuild-std-features` cargo gets
+fn build_std_features(args: &Args) -> Option<String> {
+    if !args.panic_immediate_abort {
+        return args.build_std_features.clone();
+    }
+    panic_immediate_abort_features(
+        args.build_std_features.as_deref(),
+        has_immediate_abort_strategy(args.selected_toolchain().as_deref()),
+    )
+}
+
 /// Add verbosity arguments
 fn add_verbosity_args(cmd: &mut TokioCommand, args: &Args) {
     if args.verbose_level > 0 {
@@ -534,7 +1315,7 @@ fn add_dependency_args(cmd: &mut TokioCommand, args: &Args) {
 }
 
 /// Add build configuration arguments
-fn add_build_config_args(cmd: &mut TokioCommand, args: &Args) {
+fn add_build_config_args(cmd: &mut TokioCommand, target: &str, args: &Args) {
     if let Some(ref jobs) = args.jobs {
         cmd.arg("--jobs").arg(jobs);
     }
@@ -547,14 +1328,85 @@ fn add_build_config_args(cmd: &mut TokioCommand, args: &Args) {
     if args.no_embed_metadata {
         cmd.arg("-Zno-embed-metadata");
     }
-    if let Some(ref target_dir) = args.cargo_target_dir {
+    if let Some(target_dir) = resolve_target_dir(target, args) {
         cmd.arg("--target-dir").arg(target_dir);
     }
-    if let Some(ref artifact_dir) = args.artifact_dir {
+    if let Some(ref artifact_dir) = args
+        .artifact_dir
+        .as_ref()
+        .filter(|_| forwards_artifact_dir(args))
+    {
         cmd.arg("--artifact-dir").arg(artifact_dir);
     }
 }
 
+/// Resolve the `--target-dir` to pass to cargo for a target.
+/// Targets share one directory unless `--per-target-dir` is set, so host artifacts are reused.
+/// Per-target directories live under `--target-dir`, else under the workspace's target
+/// directory, so a build started from a member still lands in the workspace's one.
+#[must_use]
+pub fn resolve_target_dir(target: &str, args: &Args) -> Option<PathBuf> {
+    if args.per_target_dir {
+        let base = args
+            .cargo_target_dir
+            .clone()
+            .or_else(|| {
+                args.workspace_paths
+                    .as_ref()
+                    .map(|workspace| workspace.target_dir.clone())
+            })
+            .unwrap_or_else(|| PathBuf::from("target"));
+        Some(base.join("per-target").join(target))
+    } else {
+        args.cargo_target_dir.clone()
+    }
+}
+
+/// Absolute target directory of a build: `--target-dir` (or the per-target directory)
+/// resolved against the directory cargo runs in, else the workspace's target directory
+#[must_use]
+pub fn effective_target_dir(target: &str, args: &Args) -> PathBuf {
+    match resolve_target_dir(target, args) {
+        Some(dir) => resolve_cargo_path(args, &dir),
+        None => args.workspace_paths.as_ref().map_or_else(
+            || resolve_cargo_path(args, Path::new("target")),
+            |workspace| workspace.target_dir.clone(),
+        ),
+    }
+}
+
+/// Build a `cargo clean` command scoped to one target's artifacts
+fn build_clean_command(target: &str, args: &Args, skip_target_arg: bool) -> TokioCommand {
+    let mut cmd = TokioCommand::new("cargo");
+    if let Some(ref toolchain) = args.toolchain {
+        cmd.arg(format!("+{toolchain}"));
+    }
+    cmd.arg("clean");
+    if let Some(ref cwd) = args.cargo_cwd {
+        cmd.arg("-C").arg(cwd);
+    }
+    if let Some(ref manifest) = args.manifest_path {
+        cmd.arg("--manifest-path").arg(manifest);
+    }
+    // Host builds share the target directory root, so they can only be cleaned as a whole
+    if !skip_target_arg && !args.no_cargo_target {
+        cmd.arg("--target").arg(target);
+    }
+    if let Some(target_dir) = resolve_target_dir(target, args) {
+        cmd.arg("--target-dir").arg(target_dir);
+    }
+    cmd
+}
+
+/// Clean the build artifacts of a single target before building it
+pub async fn clean_target(target: &str, args: &Args, skip_target_arg: bool) {
+    color::log_info(&format!("Cleaning cache for {}...", color::yellow(target)));
+    let mut cmd = build_clean_command(target, args, skip_target_arg);
+    if let Err(e) = run_command(&mut cmd, "cargo").await {
+        color::log_warning(&format!("Failed to clean cache: {e}"));
+    }
+}
+
 /// Helper to append a flag to a space-separated string
 fn append_flag(flags: &mut String, flag: &str) {
     if !flags.is_empty() {
@@ -563,99 +1415,522 @@ fn append_flag(flags: &mut String, flag: &str) {
     flags.push_str(flag);
 }
 
-/// Print environment variables
-fn print_env_vars(env: &HashMap<String, String>) {
-    if env.is_empty() {
+/// Print environment variables, with the source of each one at `-vv`
+pub fn print_env_vars(env: &EnvMap, args: &Args) {
+    if env.is_empty() || !color::log_enabled(color::LogLevel::Debug) {
         return;
     }
 
     color::print_env_header();
-    let mut keys: Vec<_> = env.keys().collect();
-    keys.sort();
-
-    for key in keys {
-        if let Some(value) = env.get(key) {
-            println!("{}", color::format_env(key, value));
+    for (key, entry) in env.sorted() {
+        let value = display_env_value(key, &entry.value, &args.secret_env, args.show_secrets);
+        if args.verbose_level >= 2 {
+            color::print_env_with_source(key, &value, entry.source.as_str());
+        } else {
+            color::print_env(key, &value);
         }
     }
 }
 
-/// Install Rust target if needed
-/// Returns Ok(true) if build-std is required, Ok(false) otherwise
-pub async fn ensure_target_installed(target: &str, toolchain: Option<&str>) -> Result<bool> {
-    // Check if target is installed
-    let mut cmd = TokioCommand::new("rustup");
-    cmd.arg("target").arg("list").arg("--installed");
-
+/// Format a rustup install command for error messages, e.g.
+/// `rustup target add aarch64-unknown-linux-musl --toolchain nightly`
+fn rustup_install_command(args: &[&str], toolchain: Option<&str>) -> String {
+    let mut command = format!("rustup {}", args.join(" "));
     if let Some(tc) = toolchain {
-        cmd.arg("--toolchain").arg(tc);
+        command.push_str(" --toolchain ");
+        command.push_str(tc);
     }
+    command
+}
 
-    let output = run_command_output(&mut cmd, "rustup").await?;
-    let installed = String::from_utf8_lossy(&output.stdout);
+/// Check whether `rustup toolchain list` output contains the given toolchain.
+/// `nightly` matches `nightly-x86_64-unknown-linux-gnu` but not `nightly-2024-01-01-...`
+fn toolchain_listed(list: &str, toolchain: &str) -> bool {
+    list.lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .any(|name| {
+            name == toolchain
+                || name.strip_prefix(toolchain).is_some_and(|rest| {
+                    rest.strip_prefix('-')
+                        .is_some_and(|host| !host.starts_with(|c: char| c.is_ascii_digit()))
+                })
+        })
+}
 
-    if installed.lines().any(|line| line.trim() == target) {
-        return Ok(false);
+/// Whether `name` is a date like `2024-01-01`
+fn is_date(name: &str) -> bool {
+    let parts: Vec<&str> = name.split('-').collect();
+    parts.len() == 3
+        && [4, 2, 2]
+            .iter()
+            .zip(&parts)
+            .all(|(len, part)| part.len() == *len && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Whether a `+toolchain` is a custom toolchain (from `rustup toolchain link`, or a path)
+/// rather than a channel rustup installs: `stable`, `beta`, `nightly` or a version, optionally
+/// followed by a date and a host triple
+#[must_use]
+pub fn is_custom_toolchain(toolchain: &str) -> bool {
+    if toolchain.contains(['/', '\\']) {
+        return true;
+    }
+    let (channel, rest) = toolchain.split_once('-').unwrap_or((toolchain, ""));
+    let is_version = channel.split('.').count() >= 2
+        && channel
+            .split('.')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    if !matches!(channel, "stable" | "beta" | "nightly") && !is_version {
+        return true;
+    }
+    // An optional date, then an optional host triple of at least three parts
+    let rest = match rest.get(..10) {
+        Some(date) if is_date(date) => rest[10..].trim_start_matches('-'),
+        _ => rest,
+    };
+    !rest.is_empty() && rest.split('-').count() < 3
+}
+
+/// Sysroot of a toolchain, from `rustc +<toolchain> --print sysroot`
+async fn toolchain_sysroot(toolchain: &str) -> Result<PathBuf> {
+    let mut cmd = TokioCommand::new("rustc");
+    cmd.arg(format!("+{toolchain}"))
+        .arg("--print")
+        .arg("sysroot");
+    let output = run_command_output(&mut cmd, "rustc").await?;
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+/// Whether a sysroot has a standard library (at least `core`) for `target`
+#[must_use]
+pub fn sysroot_has_std(sysroot: &Path, target: &str) -> bool {
+    let lib_dir = sysroot.join("lib/rustlib").join(target).join("lib");
+    std::fs::read_dir(lib_dir).is_ok_and(|entries| {
+        entries.filter_map(std::result::Result::ok).any(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("libcore-") && name.ends_with(".rlib")
+        })
+    })
+}
+
+/// Whether a sysroot has the standard library sources build-std needs
+#[must_use]
+pub fn sysroot_has_rust_src(sysroot: &Path) -> bool {
+    sysroot.join("lib/rustlib/src/rust/library").is_dir()
+}
+
+/// Status of a target on a custom toolchain, which rustup cannot add targets to: its
+/// sysroot either has the standard library, or it has to be built with build-std
+#[must_use]
+pub const fn custom_target_status(has_std: bool, support: StdSupport) -> RustTargetStatus {
+    if has_std {
+        return RustTargetStatus::Installed;
+    }
+    match support {
+        StdSupport::Prebuilt | StdSupport::BuildStd => RustTargetStatus::BuildStd,
+        StdSupport::Unknown => RustTargetStatus::Unknown,
     }
+}
 
-    // Check if target is available
+/// Install a rustup toolchain if it is missing
+/// If `allow_install` is false, a missing toolchain is an error instead
+pub async fn ensure_toolchain_installed(toolchain: &str, allow_install: bool) -> Result<()> {
+    if is_custom_toolchain(toolchain) && Path::new(toolchain).is_dir() {
+        return Ok(());
+    }
     let mut cmd = TokioCommand::new("rustup");
-    cmd.arg("target").arg("list");
+    cmd.arg("toolchain").arg("list");
+    let output = run_command_output(&mut cmd, "rustup").await?;
+    if toolchain_listed(&String::from_utf8_lossy(&output.stdout), toolchain) {
+        return Ok(());
+    }
+    if is_custom_toolchain(toolchain) {
+        return Err(CrossError::CustomToolchainNotLinked {
+            toolchain: toolchain.to_string(),
+        });
+    }
 
-    if let Some(tc) = toolchain {
-        cmd.arg("--toolchain").arg(tc);
+    if !allow_install {
+        return Err(CrossError::ToolchainNotInstalled {
+            toolchain: toolchain.to_string(),
+        });
     }
 
-    let output = run_command_output(&mut cmd, "rustup").await?;
-    let available = String::from_utf8_lossy(&output.stdout);
+    color::log_info(&format!(
+        "Installing Rust toolchain: {}",
+        color::yellow(toolchain)
+    ));
+    let mut cmd = TokioCommand::new("rustup");
+    cmd.args(["toolchain", "install", "--profile", "minimal", toolchain]);
+    let status = run_command(&mut cmd, "rustup").await?;
+    if !status.success() {
+        return Err(CrossError::CommandFailed {
+            command: format!("rustup toolchain install {toolchain}"),
+        });
+    }
+    Ok(())
+}
 
-    if available
-        .lines()
-        .any(|line| line.trim().starts_with(target))
-    {
-        // Install target
-        color::log_info(&format!(
-            "Installing Rust target: {}",
-            color::yellow(target)
-        ));
+/// How the Rust toolchain can provide a target's standard library
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RustTargetStatus {
+    /// Installed through rustup
+    Installed,
+    /// Available from rustup but not installed yet
+    Installable,
+    /// Only known to rustc, so the standard library is built with build-std
+    BuildStd,
+    /// Neither rustup nor rustc knows the target
+    Unknown,
+}
 
-        let mut cmd = TokioCommand::new("rustup");
-        cmd.arg("target").arg("add").arg(target);
+impl RustTargetStatus {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Installed => "installed",
+            Self::Installable => "installable",
+            Self::BuildStd => "build-std",
+            Self::Unknown => "unknown",
+        }
+    }
+}
 
-        if let Some(tc) = toolchain {
-            cmd.arg("--toolchain").arg(tc);
+impl RustTargetStatus {
+    /// Whether the status means a prebuilt standard library or build-std
+    #[must_use]
+    pub const fn std_support(self) -> StdSupport {
+        match self {
+            Self::Installed | Self::Installable => StdSupport::Prebuilt,
+            Self::BuildStd => StdSupport::BuildStd,
+            Self::Unknown => StdSupport::Unknown,
         }
+    }
+}
 
-        let status = run_command(&mut cmd, "rustup").await?;
-        if !status.success() {
-            return Err(CrossError::TargetInstallFailed {
-                target: target.to_string(),
-            });
+/// Where a target's standard library comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StdSupport {
+    /// rustup ships a prebuilt standard library
+    Prebuilt,
+    /// Only rustc knows the target, so the standard library is built with build-std
+    BuildStd,
+    /// Neither rustup nor rustc knows the target
+    Unknown,
+}
+
+impl StdSupport {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Prebuilt => "prebuilt",
+            Self::BuildStd => "build-std",
+            Self::Unknown => "unknown",
         }
-        return Ok(false);
     }
+}
+
+/// Whether a target list (one target per line, `rustup` may append ` (installed)`) has `target`
+fn list_contains(list: &str, target: &str) -> bool {
+    list.lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .any(|name| name == target)
+}
+
+/// Classify a target from the `rustc --print=target-list` and `rustup target list` outputs
+#[must_use]
+pub fn std_support(target: &str, rustc_target_list: &str, rustup_list: &str) -> StdSupport {
+    if list_contains(rustup_list, target) {
+        StdSupport::Prebuilt
+    } else if list_contains(rustc_target_list, target) {
+        StdSupport::BuildStd
+    } else {
+        StdSupport::Unknown
+    }
+}
+
+/// Why neither rustup nor rustc knows a target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnknownTargetReason {
+    /// A target rustc knows with a version appended, e.g. `x86_64-unknown-freebsd14`
+    VersionedAlias(String),
+    /// Probably misspelled; the closest targets rustc knows
+    Typo(Vec<String>),
+    /// Not a target of this toolchain at all
+    NotInToolchain,
+}
+
+impl std::fmt::Display for UnknownTargetReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::VersionedAlias(base) => write!(
+                f,
+                "rustc knows it as '{base}'; use that target and select the version with \
+                 --glibc-version, --freebsd-version or --ndk-version instead"
+            ),
+            Self::Typo(suggestions) => write!(f, "Did you mean: {}?", suggestions.join(", ")),
+            Self::NotInToolchain => write!(
+                f,
+                "It is not a target of this toolchain; tier-3 targets may need a newer nightly \
+                 (check `rustc +nightly --print=target-list`)"
+            ),
+        }
+    }
+}
+
+/// Levenshtein distance between two ASCII-ish strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            row.push(
+                (prev[j] + usize::from(ca != *cb))
+                    .min(prev[j + 1] + 1)
+                    .min(row[j] + 1),
+            );
+        }
+        prev = row;
+    }
+    prev[b.len()]
+}
+
+/// Explain why `target` is missing from `rustc_target_list`
+#[must_use]
+pub fn diagnose_unknown_target(target: &str, rustc_target_list: &str) -> UnknownTargetReason {
+    // Versioned spellings: glibc-style `.2.17` suffixes or OS versions like `freebsd14`
+    let unversioned = target
+        .split_once('.')
+        .map_or(target, |(base, _)| base)
+        .trim_end_matches(|c: char| c.is_ascii_digit());
+    if unversioned != target && list_contains(rustc_target_list, unversioned) {
+        return UnknownTargetReason::VersionedAlias(unversioned.to_string());
+    }
+
+    let mut candidates: Vec<(usize, &str)> = rustc_target_list
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| (edit_distance(target, name), name))
+        .filter(|(distance, _)| *distance <= 3)
+        .collect();
+    candidates.sort_unstable();
+    if candidates.is_empty() {
+        return UnknownTargetReason::NotInToolchain;
+    }
+    UnknownTargetReason::Typo(
+        candidates
+            .into_iter()
+            .take(3)
+            .map(|(_, name)| name.to_string())
+            .collect(),
+    )
+}
+
+/// Output of the rustup and rustc target probes for one toolchain
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetLists {
+    /// `rustup target list`
+    pub rustup: String,
+    /// `rustc --print=target-list`
+    pub rustc: String,
+}
+
+static TARGET_LISTS: LazyLock<Mutex<HashMap<Option<String>, Arc<TargetLists>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Target lists cached by an earlier [`target_lists`] call for `toolchain`
+fn cached_target_lists(toolchain: Option<&str>) -> Option<Arc<TargetLists>> {
+    TARGET_LISTS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .get(&toolchain.map(str::to_string))
+        .cloned()
+}
+
+/// Query `rustup target list` and `rustc --print=target-list`, once per toolchain and run
+pub async fn target_lists(toolchain: Option<&str>) -> Result<Arc<TargetLists>> {
+    if let Some(cached) = cached_target_lists(toolchain) {
+        return Ok(cached);
+    }
+
+    // rustup has no target list for custom toolchains
+    let rustup = if toolchain.is_some_and(is_custom_toolchain) {
+        String::new()
+    } else {
+        let mut cmd = TokioCommand::new("rustup");
+        cmd.arg("target").arg("list");
+        if let Some(tc) = toolchain {
+            cmd.arg("--toolchain").arg(tc);
+        }
+        let output = run_command_output(&mut cmd, "rustup").await?;
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
 
-    // Check if target exists in rustc (requires build-std)
     let mut cmd = TokioCommand::new("rustc");
-    cmd.args(["--print=target-list"]);
-    let output = run_command_output(&mut cmd, "rustc").await?;
+    if let Some(tc) = toolchain {
+        cmd.arg(format!("+{tc}"));
+    }
+    cmd.arg("--print=target-list");
+    let rustc = run_command_output(&mut cmd, "rustc").await?;
+
+    let lists = Arc::new(TargetLists {
+        rustup,
+        rustc: String::from_utf8_lossy(&rustc.stdout).into_owned(),
+    });
+    TARGET_LISTS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .insert(toolchain.map(str::to_string), Arc::clone(&lists));
+    Ok(lists)
+}
 
-    let targets = String::from_utf8_lossy(&output.stdout);
-    if targets.lines().any(|line| line.trim() == target) {
-        color::log_info(&format!(
-            "Target {} not available in rustup but exists in rustc, using build-std",
-            color::yellow(target)
+/// `BuildStdRequired` error for a target neither rustup nor rustc knows, explained
+/// from the cached rustc target list when it has been probed
+#[must_use]
+pub fn unknown_target_error(target: &str, toolchain: Option<&str>) -> CrossError {
+    let reason = cached_target_lists(toolchain)
+        .map_or(UnknownTargetReason::NotInToolchain, |lists| {
+            diagnose_unknown_target(target, &lists.rustc)
+        });
+    CrossError::BuildStdRequired {
+        target: target.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Query rustup and rustc for a target without installing anything
+pub async fn rust_target_status(target: &str, toolchain: Option<&str>) -> Result<RustTargetStatus> {
+    if let Some(tc) = toolchain.filter(|tc| is_custom_toolchain(tc)) {
+        let has_std = sysroot_has_std(&toolchain_sysroot(tc).await?, target);
+        let lists = target_lists(toolchain).await?;
+        return Ok(custom_target_status(
+            has_std,
+            std_support(target, &lists.rustc, &lists.rustup),
         ));
-        return Ok(true);
     }
 
-    Err(CrossError::BuildStdRequired {
-        target: target.to_string(),
+    // Installed targets change as we install them, so this probe is never cached
+    let mut cmd = TokioCommand::new("rustup");
+    cmd.arg("target").arg("list").arg("--installed");
+
+    if let Some(tc) = toolchain {
+        cmd.arg("--toolchain").arg(tc);
+    }
+
+    let output = run_command_output(&mut cmd, "rustup").await?;
+    let installed = String::from_utf8_lossy(&output.stdout);
+
+    if list_contains(&installed, target) {
+        return Ok(RustTargetStatus::Installed);
+    }
+
+    let lists = target_lists(toolchain).await?;
+    Ok(match std_support(target, &lists.rustc, &lists.rustup) {
+        StdSupport::Prebuilt => RustTargetStatus::Installable,
+        StdSupport::BuildStd => RustTargetStatus::BuildStd,
+        StdSupport::Unknown => RustTargetStatus::Unknown,
     })
 }
 
+/// Install Rust target if needed
+/// Returns Ok(true) if build-std is required, Ok(false) otherwise
+/// If `allow_install` is false, a missing but available target is an error instead
+pub async fn ensure_target_installed(
+    target: &str,
+    toolchain: Option<&str>,
+    allow_install: bool,
+) -> Result<bool> {
+    match rust_target_status(target, toolchain).await? {
+        RustTargetStatus::Installed => Ok(false),
+        RustTargetStatus::Installable => {
+            install_target(target, toolchain, allow_install).await?;
+            Ok(false)
+        }
+        RustTargetStatus::BuildStd => {
+            color::log_info(&format!(
+                "Target {} not available in rustup but exists in rustc, using build-std",
+                color::yellow(target)
+            ));
+            Ok(true)
+        }
+        RustTargetStatus::Unknown => Err(unknown_target_error(target, toolchain)),
+    }
+}
+
+async fn install_target(target: &str, toolchain: Option<&str>, allow_install: bool) -> Result<()> {
+    if !allow_install {
+        return Err(CrossError::RustupInstallDisabled {
+            item: format!("Rust target '{target}'"),
+            command: rustup_install_command(&["target", "add", target], toolchain),
+        });
+    }
+
+    // Install target
+    color::log_info(&format!(
+        "Installing Rust target: {}",
+        color::yellow(target)
+    ));
+
+    let mut cmd = TokioCommand::new("rustup");
+    cmd.arg("target").arg("add").arg(target);
+
+    if let Some(tc) = toolchain {
+        cmd.arg("--toolchain").arg(tc);
+    }
+
+    let status = run_command(&mut cmd, "rustup").await?;
+    if !status.success() {
+        return Err(CrossError::TargetInstallFailed {
+            target: target.to_string(),
+        });
+    }
+    Ok(())
+}
+
 /// Add rust-src component if needed for build-std
-pub async fn ensure_rust_src(target: &str, toolchain: Option<&str>) -> Result<()> {
+/// If `allow_install` is false, only verify that rust-src is already installed
+pub async fn ensure_rust_src(
+    target: &str,
+    toolchain: Option<&str>,
+    allow_install: bool,
+) -> Result<()> {
+    // rustup cannot add components to custom toolchains, so only check the sysroot
+    if let Some(tc) = toolchain.filter(|tc| is_custom_toolchain(tc)) {
+        let sysroot = toolchain_sysroot(tc).await?;
+        if sysroot_has_rust_src(&sysroot) {
+            return Ok(());
+        }
+        return Err(CrossError::CustomToolchainIncomplete {
+            toolchain: tc.to_string(),
+            item: format!("rust-src (needed to build std for {target})"),
+            sysroot,
+        });
+    }
+
+    if !allow_install {
+        let mut cmd = TokioCommand::new("rustup");
+        cmd.arg("component").arg("list").arg("--installed");
+        if let Some(tc) = toolchain {
+            cmd.arg("--toolchain").arg(tc);
+        }
+
+        let output = run_command_output(&mut cmd, "rustup").await?;
+        let installed = String::from_utf8_lossy(&output.stdout);
+        if installed.lines().any(|line| line.trim() == "rust-src") {
+            return Ok(());
+        }
+
+        return Err(CrossError::RustupInstallDisabled {
+            item: "Component 'rust-src'".to_string(),
+            command: rustup_install_command(&["component", "add", "rust-src"], toolchain),
+        });
+    }
+
     let toolchain_info = toolchain
         .map(|t| format!(" and toolchain: {}", color::yellow(t)))
         .unwrap_or_default();
@@ -687,12 +1962,92 @@ pub async fn ensure_rust_src(target: &str, toolchain: Option<&str>) -> Result<()
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cli::{Args, BuildArgs, Command};
+    use crate::cli::{Args, BuildArgs, BuildStd, Command, ExplicitTarget};
     use crate::config::get_target_config;
     use crate::env::CrossEnv;
     use crate::platform::render_cmake_toolchain_file;
     use std::path::{Path, PathBuf};
 
+    #[test]
+    fn test_toolchain_listed() {
+        let list = "stable-x86_64-unknown-linux-gnu (default)\n\
+                    nightly-2024-01-01-x86_64-unknown-linux-gnu\n\
+                    1.75.0-x86_64-unknown-linux-gnu\n";
+        assert!(toolchain_listed(list, "stable"));
+        assert!(toolchain_listed(list, "1.75.0"));
+        assert!(toolchain_listed(list, "nightly-2024-01-01"));
+        assert!(!toolchain_listed(list, "nightly"));
+        assert!(!toolchain_listed(list, "beta"));
+    }
+
+    #[test]
+    fn test_is_custom_toolchain() {
+        // `rustup toolchain list` with a toolchain added by `rustup toolchain link`
+        let list = "stable-x86_64-unknown-linux-gnu (default)\n\
+                    nightly-2024-01-01-x86_64-unknown-linux-gnu\n\
+                    1.75.0-aarch64-apple-darwin\n\
+                    our-llvm\n";
+        for name in list
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+        {
+            assert_eq!(is_custom_toolchain(name), name == "our-llvm", "{name}");
+        }
+        assert!(toolchain_listed(list, "our-llvm"));
+
+        for official in [
+            "stable",
+            "beta",
+            "nightly",
+            "1.75",
+            "1.75.0",
+            "nightly-2024-01-01",
+        ] {
+            assert!(!is_custom_toolchain(official), "{official}");
+        }
+        for custom in [
+            "stage1",
+            "nightly-llvm",
+            "/opt/rust-custom",
+            r"C:\rust\custom",
+        ] {
+            assert!(is_custom_toolchain(custom), "{custom}");
+        }
+    }
+
+    #[test]
+    fn test_custom_target_status() {
+        let sysroot =
+            std::env::temp_dir().join(format!("cargo-cross-custom-sysroot-{}", std::process::id()));
+        let lib_dir = sysroot.join("lib/rustlib/aarch64-unknown-linux-gnu/lib");
+        std::fs::create_dir_all(&lib_dir).unwrap();
+        std::fs::write(lib_dir.join("libcore-0123abcd.rlib"), b"").unwrap();
+
+        assert!(sysroot_has_std(&sysroot, "aarch64-unknown-linux-gnu"));
+        assert!(!sysroot_has_std(&sysroot, "riscv64gc-unknown-linux-gnu"));
+        assert!(!sysroot_has_rust_src(&sysroot));
+
+        // A linked toolchain never reports a target as installable through rustup
+        let rustc_list = "aarch64-unknown-linux-gnu\nriscv64gc-unknown-linux-gnu\n";
+        let status = |target: &str| {
+            custom_target_status(
+                sysroot_has_std(&sysroot, target),
+                std_support(target, rustc_list, ""),
+            )
+        };
+        assert_eq!(
+            status("aarch64-unknown-linux-gnu"),
+            RustTargetStatus::Installed
+        );
+        assert_eq!(
+            status("riscv64gc-unknown-linux-gnu"),
+            RustTargetStatus::BuildStd
+        );
+        assert_eq!(status("mips-unknown-linux-gnu"), RustTargetStatus::Unknown);
+
+        let _ = std::fs::remove_dir_all(&sysroot);
+    }
+
     #[test]
     fn test_append_flag_empty() {
         let mut flags = String::new();
@@ -719,6 +2074,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_insert_rustflags_env_scopes_to_target() {
+        let target = "aarch64-unknown-linux-musl";
+        let flags = vec![
+            "-L".to_string(),
+            "/opt/cross/lib".to_string(),
+            "-C".to_string(),
+            "target-feature=+crt-static".to_string(),
+        ];
+        let insert = |inherited: InheritedRustflags| {
+            let mut env = EnvMap::new();
+            insert_rustflags_env(&mut env, target, &flags, inherited, EnvSource::CrossEnv);
+            env.into_vars()
+        };
+        assert_eq!(
+            insert(InheritedRustflags::None),
+            HashMap::from([(
+                "CARGO_TARGET_AARCH64_UNKNOWN_LINUX_MUSL_RUSTFLAGS".to_string(),
+                "-L /opt/cross/lib -C target-feature=+crt-static".to_string()
+            )])
+        );
+        // Cargo ignores the target variable next to inherited flags
+        assert_eq!(
+            insert(InheritedRustflags::Plain),
+            HashMap::from([(
+                "RUSTFLAGS".to_string(),
+                "-L /opt/cross/lib -C target-feature=+crt-static".to_string()
+            )])
+        );
+        assert_eq!(
+            insert(InheritedRustflags::Encoded),
+            HashMap::from([("CARGO_ENCODED_RUSTFLAGS".to_string(), flags.join("\x1f"))])
+        );
+        assert_eq!(
+            target_rustflags_var("thumbv8m.main-none-eabihf"),
+            "CARGO_TARGET_THUMBV8M_MAIN_NONE_EABIHF_RUSTFLAGS"
+        );
+    }
+
+    #[test]
+    fn test_insert_rustflags_env_encodes_paths_with_spaces() {
+        let root = std::env::temp_dir().join("cargo cross rustflags");
+        let mut cross_env = CrossEnv::new();
+        cross_env.add_rustflag_arg("-L");
+        cross_env.add_rustflag_arg(root.join("lib").display().to_string());
+        cross_env.add_rustflag("-C target-cpu=generic");
+
+        let mut env = EnvMap::new();
+        insert_rustflags_env(
+            &mut env,
+            "aarch64-unknown-linux-musl",
+            &cross_env.rustflags,
+            InheritedRustflags::None,
+            EnvSource::CrossEnv,
+        );
+        assert_eq!(env.len(), 1);
+        let encoded = env.get("CARGO_ENCODED_RUSTFLAGS").unwrap();
+        assert_eq!(
+            encoded.split('\x1f').collect::<Vec<_>>(),
+            vec![
+                "-L",
+                &root.join("lib").display().to_string(),
+                "-C",
+                "target-cpu=generic"
+            ]
+        );
+    }
+
     #[test]
     fn test_render_cmake_toolchain_file_for_freebsd() {
         let mut env = CrossEnv::new();
@@ -805,16 +2228,16 @@ mod tests {
         let _ = std::fs::remove_dir_all(&temp_dir);
 
         let args = Args {
-            toolchain: None,
             command: Command::setup(),
-            targets: vec!["x86_64-unknown-freebsd".to_string()],
-            no_cargo_target: false,
-            cross_make_version: "test".to_string(),
             cross_compiler_dir: temp_dir.join("toolchains"),
-            build: BuildArgs {
-                no_toolchain_setup: true,
-                ..BuildArgs::default()
-            },
+            ..crate::cli::test_target_args(
+                "",
+                &["x86_64-unknown-freebsd"],
+                BuildArgs {
+                    no_toolchain_setup: true,
+                    ..BuildArgs::default()
+                },
+            )
         };
 
         let mut env = CrossEnv::new();
@@ -830,8 +2253,12 @@ mod tests {
 
         let key = "CMAKE_TOOLCHAIN_FILE_x86_64_unknown_freebsd";
         let toolchain_path = Path::new(build_env.get(key).unwrap());
-        assert!(toolchain_path.starts_with(temp_dir.join("toolchains").join("cmake")));
-        assert!(toolchain_path.ends_with("x86_64-unknown-freebsd.cmake"));
+        assert!(toolchain_path.starts_with(temp_dir.join("toolchains/generated/cmake")));
+        let name = toolchain_path.file_name().unwrap().to_string_lossy();
+        assert!(
+            name.starts_with("x86_64-unknown-freebsd-") && name.ends_with(".cmake"),
+            "{name}"
+        );
         assert!(toolchain_path.exists());
 
         let content = std::fs::read_to_string(toolchain_path).unwrap();
@@ -854,16 +2281,16 @@ mod tests {
         std::fs::write(&custom_toolchain, "set(ANDROID_ABI \"arm64-v8a\")\n").unwrap();
 
         let args = Args {
-            toolchain: None,
             command: Command::setup(),
-            targets: vec!["aarch64-linux-android".to_string()],
-            no_cargo_target: false,
-            cross_make_version: "test".to_string(),
             cross_compiler_dir: temp_dir.join("toolchains"),
-            build: BuildArgs {
-                no_toolchain_setup: true,
-                ..BuildArgs::default()
-            },
+            ..crate::cli::test_target_args(
+                "",
+                &["aarch64-linux-android"],
+                BuildArgs {
+                    no_toolchain_setup: true,
+                    ..BuildArgs::default()
+                },
+            )
         };
 
         let mut env = CrossEnv::new();
@@ -880,4 +2307,1016 @@ mod tests {
 
         let _ = std::fs::remove_dir_all(&temp_dir);
     }
+
+    fn build_std_args_for(build_std: BuildStd, cross_env: &CrossEnv) -> Vec<String> {
+        let args = crate::cli::test_target_args(
+            "build-std",
+            &["x86_64-unknown-linux-musl"],
+            BuildArgs {
+                build_std,
+                ..BuildArgs::default()
+            },
+        );
+        let mut cmd = TokioCommand::new("cargo");
+        add_build_std_args(&mut cmd, &args, cross_env);
+        cmd.as_std()
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_add_build_std_args_respects_explicit_disable() {
+        let mut env = CrossEnv::new();
+        env.set_build_std("true");
+
+        assert!(build_std_args_for(BuildStd::Disabled, &env).is_empty());
+        assert_eq!(
+            build_std_args_for(BuildStd::Unset, &env),
+            vec![format!("-Zbuild-std={}", get_build_std_config())]
+        );
+        assert_eq!(
+            build_std_args_for(BuildStd::Enabled("core,alloc".to_string()), &env),
+            vec!["-Zbuild-std=core,alloc".to_string()]
+        );
+        assert!(build_std_args_for(BuildStd::Unset, &CrossEnv::new()).is_empty());
+    }
+
+    const RUSTC_TARGETS: &str = "aarch64-unknown-linux-gnu\n\
+        aarch64-unknown-linux-musl\n\
+        riscv64gc-unknown-linux-musl\n\
+        x86_64-unknown-freebsd\n\
+        x86_64-unknown-linux-musl\n";
+    const RUSTUP_TARGETS: &str = "aarch64-unknown-linux-gnu\n\
+        aarch64-unknown-linux-musl (installed)\n\
+        x86_64-unknown-freebsd\n\
+        x86_64-unknown-linux-musl\n";
+
+    #[test]
+    fn test_std_support() {
+        for (target, expected) in [
+            ("aarch64-unknown-linux-musl", StdSupport::Prebuilt),
+            ("x86_64-unknown-linux-musl", StdSupport::Prebuilt),
+            ("riscv64gc-unknown-linux-musl", StdSupport::BuildStd),
+            ("aarch64-unknown-linux", StdSupport::Unknown),
+            ("mips-unknown-linux-musl", StdSupport::Unknown),
+        ] {
+            assert_eq!(
+                std_support(target, RUSTC_TARGETS, RUSTUP_TARGETS),
+                expected,
+                "{target}"
+            );
+        }
+        assert_eq!(
+            RustTargetStatus::Installable.std_support(),
+            StdSupport::Prebuilt
+        );
+    }
+
+    #[test]
+    fn test_diagnose_unknown_target() {
+        assert_eq!(
+            diagnose_unknown_target("x86_64-unknown-freebsd14", RUSTC_TARGETS),
+            UnknownTargetReason::VersionedAlias("x86_64-unknown-freebsd".to_string())
+        );
+        assert_eq!(
+            diagnose_unknown_target("aarch64-unknown-linux-gnu.2.17", RUSTC_TARGETS),
+            UnknownTargetReason::VersionedAlias("aarch64-unknown-linux-gnu".to_string())
+        );
+        assert_eq!(
+            diagnose_unknown_target("aarch64-unknwon-linux-musl", RUSTC_TARGETS),
+            UnknownTargetReason::Typo(vec!["aarch64-unknown-linux-musl".to_string()])
+        );
+        assert_eq!(
+            diagnose_unknown_target("loongarch64-linux-android", RUSTC_TARGETS),
+            UnknownTargetReason::NotInToolchain
+        );
+        assert!(
+            UnknownTargetReason::Typo(vec!["a".to_string(), "b".to_string()])
+                .to_string()
+                .contains("Did you mean: a, b?")
+        );
+    }
+
+    #[test]
+    fn test_rustup_install_command() {
+        assert_eq!(
+            rustup_install_command(&["target", "add", "aarch64-unknown-linux-musl"], None),
+            "rustup target add aarch64-unknown-linux-musl"
+        );
+        assert_eq!(
+            rustup_install_command(&["component", "add", "rust-src"], Some("nightly")),
+            "rustup component add rust-src --toolchain nightly"
+        );
+    }
+
+    fn command_args(cmd: &TokioCommand) -> Vec<String> {
+        cmd.as_std()
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    fn musl_args(build: BuildArgs) -> Args {
+        crate::cli::test_target_args("cargo", &["aarch64-unknown-linux-musl"], build)
+    }
+
+    #[test]
+    fn test_sequential_run_prefix() {
+        let target = "aarch64-unknown-linux-musl";
+        let mut args = musl_args(BuildArgs {
+            sequential_runs: true,
+            ..BuildArgs::default()
+        });
+        assert_eq!(sequential_run_prefix(target, &args), None);
+        args.command = Command::new("run");
+        assert_eq!(
+            sequential_run_prefix(target, &args).as_deref(),
+            Some("[aarch64-unknown-linux-musl] ")
+        );
+        args.sequential_runs = false;
+        assert_eq!(sequential_run_prefix(target, &args), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_prefixed_run_keeps_stderr_and_status() {
+        let mut cmd = TokioCommand::new("sh");
+        cmd.args(["-c", "echo out; echo err >&2; echo more >&2; exit 3"]);
+        let (status, stderr) = run_command_prefixed(&mut cmd, "sh", "[x86_64-unknown-linux-musl] ")
+            .await
+            .unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert_eq!(stderr, "err\nmore\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_prefixed_run_survives_invalid_utf8() {
+        // The pause lets the invalid line be read before the child writes again
+        let mut cmd = TokioCommand::new("sh");
+        cmd.args([
+            "-c",
+            r"printf 'bad \377\376\n'; printf 'bad \377\n' >&2; sleep 0.2; echo after; echo done >&2",
+        ]);
+        let (status, stderr) = run_command_prefixed(&mut cmd, "sh", "[x86_64-unknown-linux-musl] ")
+            .await
+            .unwrap();
+        assert!(status.success(), "{status}");
+        assert_eq!(stderr, "bad \u{fffd}\ndone\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_tee_runs_survive_invalid_utf8() {
+        let script =
+            r"printf 'bad \377\n'; printf 'bad \377\n' >&2; sleep 0.2; echo done; echo done >&2";
+        let mut cmd = TokioCommand::new("sh");
+        cmd.args(["-c", script]);
+        let (status, stderr) = run_command_tee_stderr(&mut cmd, "sh").await.unwrap();
+        assert!(status.success(), "{status}");
+        assert_eq!(stderr, "bad \u{fffd}\ndone\n");
+
+        let mut cmd = TokioCommand::new("sh");
+        cmd.args(["-c", script]);
+        let (status, stdout) = crate::error::run_command_tee_stdout(&mut cmd, "sh")
+            .await
+            .unwrap();
+        assert!(status.success(), "{status}");
+        assert_eq!(stdout, "bad \u{fffd}\ndone\n");
+    }
+
+    #[test]
+    fn test_may_run_foreign_binaries() {
+        let host = HostPlatform::detect();
+        let args = musl_args(BuildArgs::default());
+        assert!(may_run_foreign_binaries("aarch64-apple-ios", &args));
+        assert!(!may_run_foreign_binaries(&host.triple, &args));
+
+        let args = musl_args(BuildArgs {
+            runner_for_build_scripts: true,
+            ..BuildArgs::default()
+        });
+        assert!(!may_run_foreign_binaries("aarch64-apple-ios", &args));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_tee_keeps_lines_without_progress_and_colour() {
+        let script =
+            r"printf '   Building [==>  ] 1/4\r\033[K\033[1;32m   Compiling\033[0m foo\n' >&2";
+        let mut cmd = TokioCommand::new("sh");
+        cmd.args(["-c", script]);
+        let (status, stderr) = run_command_tee_stderr(&mut cmd, "sh").await.unwrap();
+        assert!(status.success(), "{status}");
+        assert_eq!(stderr, "   Compiling foo\n");
+    }
+
+    #[test]
+    fn test_build_cargo_env_exposes_runner_to_build_scripts() {
+        let target = "aarch64-unknown-linux-musl";
+        let mut cross_env = CrossEnv::new();
+        cross_env.set_runner("qemu-aarch64 -L /sysroot");
+        let host = HostPlatform::detect();
+
+        let env = build_cargo_env(
+            target,
+            &musl_args(BuildArgs::default()),
+            &cross_env,
+            &host,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            env.get("CARGO_TARGET_AARCH64_UNKNOWN_LINUX_MUSL_RUNNER")
+                .map(String::as_str),
+            Some("qemu-aarch64 -L /sysroot")
+        );
+        assert!(!env.contains_key("CROSS_RUNNER"));
+
+        let args = musl_args(BuildArgs {
+            runner_for_build_scripts: true,
+            ..BuildArgs::default()
+        });
+        assert!(args.needs_runner());
+        let env = build_cargo_env(target, &args, &cross_env, &host, false).unwrap();
+        assert_eq!(
+            env.get("CROSS_RUNNER").map(String::as_str),
+            Some("qemu-aarch64 -L /sysroot")
+        );
+
+        // Nothing to expose without a runner
+        let env = build_cargo_env(target, &args, &CrossEnv::new(), &host, false).unwrap();
+        assert!(!env.contains_key("CROSS_RUNNER"));
+    }
+
+    #[test]
+    fn test_build_cargo_env_tags_tool_sources() {
+        let target = "aarch64-unknown-linux-musl";
+        let mut cross_env = CrossEnv::new();
+        cross_env.set_cc("my-gcc");
+        cross_env.set_tool_source("cc", EnvSource::Cli);
+        cross_env.set_runner("qemu-aarch64");
+        cross_env.set_tool_source("runner", EnvSource::Inherited);
+        cross_env.set_ar("aarch64-linux-musl-ar");
+        let host = HostPlatform::detect();
+
+        let env = build_cargo_env(
+            target,
+            &musl_args(BuildArgs::default()),
+            &cross_env,
+            &host,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            env.source("CC_aarch64_unknown_linux_musl"),
+            Some(EnvSource::Cli)
+        );
+        assert_eq!(
+            env.source("CARGO_TARGET_AARCH64_UNKNOWN_LINUX_MUSL_RUNNER"),
+            Some(EnvSource::Inherited)
+        );
+        assert_eq!(
+            env.source("AR_aarch64_unknown_linux_musl"),
+            Some(EnvSource::CrossEnv)
+        );
+    }
+
+    #[test]
+    fn test_explicit_target_modes() {
+        let host = HostPlatform::detect();
+        let cross_env = CrossEnv::new();
+        let run = |mode: ExplicitTarget, target: &str| {
+            let args = musl_args(BuildArgs {
+                explicit_target: mode,
+                host_config: true,
+                ..BuildArgs::default()
+            });
+            let skip = args.skip_target_arg(target, &host.triple);
+            let actual = if target == "host-tuple" {
+                &host.triple
+            } else {
+                target
+            };
+            let cmd = build_cargo_command(actual, &args, &cross_env, skip);
+            let env = build_cargo_env(actual, &args, &cross_env, &host, skip).unwrap();
+            (
+                command_args(&cmd).contains(&"--target".to_string()),
+                env.contains_key("CARGO_UNSTABLE_HOST_CONFIG"),
+            )
+        };
+
+        // (passes --target, sets the host-config env)
+        assert_eq!(run(ExplicitTarget::Auto, "host-tuple"), (false, false));
+        assert_eq!(run(ExplicitTarget::Auto, &host.triple), (true, true));
+        assert_eq!(run(ExplicitTarget::Always, "host-tuple"), (true, true));
+        assert_eq!(run(ExplicitTarget::Always, &host.triple), (true, true));
+        assert_eq!(run(ExplicitTarget::Never, "host-tuple"), (false, false));
+        assert_eq!(run(ExplicitTarget::Never, &host.triple), (false, false));
+        // Cross targets always get --target
+        for mode in [
+            ExplicitTarget::Auto,
+            ExplicitTarget::Always,
+            ExplicitTarget::Never,
+        ] {
+            assert_eq!(run(mode, "riscv64gc-unknown-none-elf"), (true, false));
+        }
+    }
+
+    #[test]
+    fn test_panic_immediate_abort_follows_the_toolchain() {
+        // Nightlies with the panic strategy reject std's feature
+        assert_eq!(
+            panic_immediate_abort_flags(true),
+            "-Zunstable-options -Cpanic=immediate-abort"
+        );
+        assert_eq!(panic_immediate_abort_features(None, true), None);
+        assert_eq!(
+            panic_immediate_abort_features(Some("panic_immediate_abort optimize_for_size"), true),
+            Some("optimize_for_size".to_string())
+        );
+
+        // Older nightlies only have the feature, on top of panic=abort
+        assert_eq!(panic_immediate_abort_flags(false), "-Cpanic=abort");
+        assert_eq!(
+            panic_immediate_abort_features(None, false),
+            Some("panic_immediate_abort".to_string())
+        );
+        assert_eq!(
+            panic_immediate_abort_features(Some("optimize_for_size,panic_immediate_abort"), false),
+            Some("optimize_for_size,panic_immediate_abort".to_string())
+        );
+    }
+
+    #[test]
+    fn test_host_config_needs_nightly() {
+        let host = HostPlatform::detect();
+        let cross_env = CrossEnv::new();
+        let run = |toolchain: &str, host_config: bool, target: &str| {
+            let mut args = musl_args(BuildArgs {
+                host_config,
+                ..BuildArgs::default()
+            });
+            args.toolchain = Some(toolchain.to_string());
+            let env = build_cargo_env(target, &args, &cross_env, &host, false).unwrap();
+            env.contains_key("CARGO_UNSTABLE_HOST_CONFIG")
+                && env.contains_key("CARGO_TARGET_APPLIES_TO_HOST")
+        };
+        let cross = if host.triple == "riscv64gc-unknown-linux-gnu" {
+            "aarch64-unknown-linux-gnu"
+        } else {
+            "riscv64gc-unknown-linux-gnu"
+        };
+
+        assert!(run("nightly", false, &host.triple));
+        assert!(run("nightly-2024-01-01", false, &host.triple));
+        assert!(!run("stable", false, &host.triple));
+        assert!(!run("1.75.0", false, &host.triple));
+        assert!(run("stable", true, &host.triple));
+        for toolchain in ["stable", "nightly"] {
+            for host_config in [false, true] {
+                assert!(
+                    !run(toolchain, host_config, cross),
+                    "{toolchain} {host_config}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rustc_is_nightly() {
+        let vv = |release: &str| {
+            format!("rustc {release} (abc 2024-01-01)\nhost: x\nrelease: {release}\n")
+        };
+        assert!(rustc_is_nightly(&vv("1.80.0-nightly")));
+        assert!(rustc_is_nightly(&vv("1.81.0-dev")));
+        assert!(!rustc_is_nightly(&vv("1.80.0")));
+        assert!(!rustc_is_nightly(&vv("1.80.0-beta.3")));
+        assert!(!rustc_is_nightly(""));
+    }
+
+    #[test]
+    fn test_crt_static_decision_matrix() {
+        let musl = "riscv64gc-unknown-linux-musl";
+        let cases = [
+            ("x86_64-unknown-linux-musl", None, None),
+            (
+                "x86_64-unknown-linux-musl",
+                Some(CrtStatic::Dynamic),
+                Some((false, "--crt-static false")),
+            ),
+            (
+                "x86_64-unknown-linux-gnu",
+                Some(CrtStatic::Static),
+                Some((true, "--crt-static true")),
+            ),
+            (
+                "aarch64-unknown-linux-musl",
+                Some(CrtStatic::Auto),
+                Some((true, "musl default")),
+            ),
+            (
+                "aarch64-unknown-linux-gnu",
+                Some(CrtStatic::Auto),
+                Some((false, "glibc default")),
+            ),
+            (
+                "aarch64-linux-android",
+                Some(CrtStatic::Auto),
+                Some((false, "Android default")),
+            ),
+            (
+                "armv7-linux-androideabi",
+                Some(CrtStatic::Auto),
+                Some((false, "Android default")),
+            ),
+            (
+                "x86_64-pc-windows-msvc",
+                Some(CrtStatic::Auto),
+                Some((false, "Windows default")),
+            ),
+            ("aarch64-apple-darwin", Some(CrtStatic::Auto), None),
+            ("wasm32-unknown-unknown", Some(CrtStatic::Auto), None),
+        ];
+        for (target, setting, expected) in cases {
+            let decision = crt_static_decision(target, setting)
+                .map(|decision| (decision.static_crt, decision.reason));
+            assert_eq!(decision, expected, "{target} {setting:?}");
+        }
+
+        let dynamic = crt_static_decision(musl, Some(CrtStatic::Dynamic));
+        let warning = dynamic_musl_warning(musl, dynamic).unwrap();
+        assert!(warning.contains("ld-musl"), "{warning}");
+        assert!(warning.contains("older musl toolchains"), "{warning}");
+        let warning = dynamic_musl_warning("x86_64-unknown-linux-musl", dynamic).unwrap();
+        assert!(!warning.contains("older musl toolchains"), "{warning}");
+        for (target, setting) in [
+            (musl, Some(CrtStatic::Auto)),
+            (musl, None),
+            ("x86_64-unknown-linux-gnu", Some(CrtStatic::Dynamic)),
+        ] {
+            let decision = crt_static_decision(target, setting);
+            assert_eq!(dynamic_musl_warning(target, decision), None, "{target}");
+        }
+    }
+
+    #[test]
+    fn test_skip_step_host_config_and_crt() {
+        let host = HostPlatform::detect();
+        let cross_env = CrossEnv::new();
+        let run = |skip_step: Vec<SetupStep>| {
+            let args = musl_args(BuildArgs {
+                crt_static: Some(CrtStatic::Static),
+                skip_step,
+                host_config: true,
+                ..BuildArgs::default()
+            });
+            let env = build_cargo_env(&host.triple, &args, &cross_env, &host, false).unwrap();
+            let (rustflags, _, _) = build_rustflags(&args, &cross_env, &host.triple);
+            (
+                env.contains_key("CARGO_UNSTABLE_HOST_CONFIG"),
+                rustflags.join(" ").contains("+crt-static"),
+            )
+        };
+        assert_eq!(run(Vec::new()), (true, true));
+        assert_eq!(run(vec![SetupStep::HostConfig]), (false, true));
+        assert_eq!(run(vec![SetupStep::RustflagsCrt]), (true, false));
+    }
+
+    #[test]
+    fn test_script_color() {
+        assert_eq!(script_color(Some("always"), false), "always");
+        assert_eq!(script_color(Some("never"), true), "never");
+        assert_eq!(script_color(Some("auto"), true), "always");
+        assert_eq!(script_color(None, false), "never");
+
+        let mut cmd = TokioCommand::new("cargo");
+        let mut args = musl_args(BuildArgs {
+            quiet: true,
+            color: Some("never".to_string()),
+            ..BuildArgs::default()
+        });
+        add_script_output_env(&mut cmd, &args);
+        let invocation = crate::repro::Invocation::from_command(&cmd);
+        assert_eq!(
+            invocation.env,
+            vec![
+                ("CARGO_CROSS_COLOR".to_string(), Some("never".to_string())),
+                ("CARGO_CROSS_QUIET".to_string(), Some("1".to_string())),
+            ]
+        );
+        args.build.quiet = false;
+        add_script_output_env(&mut cmd, &args);
+        assert_eq!(
+            cmd.as_std()
+                .get_envs()
+                .find(|(key, _)| *key == "CARGO_CROSS_QUIET")
+                .and_then(|(_, value)| value),
+            Some(std::ffi::OsStr::new("0"))
+        );
+    }
+
+    #[test]
+    fn test_format_command_quotes_arguments() {
+        let mut cmd = TokioCommand::new("cargo");
+        cmd.args([
+            "build",
+            "--config",
+            "target.x.linker='my cc'",
+            "--features",
+            "a b",
+        ]);
+        let line = format_command_from_cmd(&cmd);
+        assert_eq!(
+            line,
+            r#"cargo build --config "target.x.linker='my cc'" --features 'a b'"#
+        );
+        assert_eq!(
+            shlex::split(&line).unwrap(),
+            [
+                "cargo",
+                "build",
+                "--config",
+                "target.x.linker='my cc'",
+                "--features",
+                "a b"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transient_failure() {
+        let cases = [
+            (
+                "sccache: error: Timed out waiting for server startup. Maybe the remote service is unreachable?",
+                Some("sccache server startup race"),
+            ),
+            (
+                "error: failed to execute compile\nsccache: error: Connection refused (os error 111)",
+                Some("sccache server startup race"),
+            ),
+            (
+                "error: could not rename crate \"target/debug/deps/libfoo-1234.rmeta\"",
+                Some("crate metadata rename race"),
+            ),
+            (
+                "error: failed to remove file `C:\\t\\debug\\app.exe`\n\nCaused by:\n  Access is denied. (os error 5)",
+                Some("file locked by another process (Access is denied)"),
+            ),
+            (
+                "error: failed to update registry `crates-io`\nCaused by:\n  timed out waiting for package cache lock",
+                Some("registry index lock timeout"),
+            ),
+            ("error[E0308]: mismatched types", None),
+            ("error: linking with `cc` failed: exit status: 1", None),
+            ("warning: unused variable: `sccache`", None),
+        ];
+        for (stderr, expected) in cases {
+            assert_eq!(transient_failure(stderr), expected, "{stderr}");
+        }
+    }
+
+    #[test]
+    fn test_resolve_target_dir_shared_by_default() {
+        let args = musl_args(BuildArgs::default());
+        assert_eq!(
+            resolve_target_dir("aarch64-unknown-linux-musl", &args),
+            None
+        );
+
+        let args = musl_args(BuildArgs {
+            cargo_target_dir: Some(PathBuf::from("out")),
+            ..BuildArgs::default()
+        });
+        assert_eq!(
+            resolve_target_dir("aarch64-unknown-linux-musl", &args),
+            Some(PathBuf::from("out"))
+        );
+    }
+
+    #[test]
+    fn test_resolve_target_dir_per_target() {
+        let args = musl_args(BuildArgs {
+            per_target_dir: true,
+            ..BuildArgs::default()
+        });
+        assert_eq!(
+            resolve_target_dir("aarch64-unknown-linux-musl", &args),
+            Some(Path::new("target/per-target/aarch64-unknown-linux-musl").to_path_buf())
+        );
+    }
+
+    #[test]
+    fn test_resolve_target_dir_per_target_from_member() {
+        let mut args = musl_args(BuildArgs {
+            per_target_dir: true,
+            ..BuildArgs::default()
+        });
+        args.workspace_paths = Some(crate::metadata::WorkspacePaths {
+            cwd: PathBuf::from("/ws/member"),
+            root: PathBuf::from("/ws"),
+            target_dir: PathBuf::from("/ws/build"),
+        });
+        assert_eq!(
+            resolve_target_dir("aarch64-unknown-linux-musl", &args),
+            Some(PathBuf::from(
+                "/ws/build/per-target/aarch64-unknown-linux-musl"
+            ))
+        );
+        assert_eq!(
+            effective_target_dir("aarch64-unknown-linux-musl", &args),
+            PathBuf::from("/ws/build/per-target/aarch64-unknown-linux-musl")
+        );
+
+        args.cargo_target_dir = Some(PathBuf::from("out"));
+        assert_eq!(
+            resolve_target_dir("aarch64-unknown-linux-musl", &args),
+            Some(PathBuf::from("out/per-target/aarch64-unknown-linux-musl"))
+        );
+    }
+
+    #[test]
+    fn test_build_clean_command_is_target_scoped() {
+        let args = musl_args(BuildArgs::default());
+        let cmd = build_clean_command("aarch64-unknown-linux-musl", &args, false);
+        assert_eq!(
+            command_args(&cmd),
+            vec!["clean", "--target", "aarch64-unknown-linux-musl"]
+        );
+
+        let cmd = build_clean_command("x86_64-unknown-linux-gnu", &args, true);
+        assert_eq!(command_args(&cmd), vec!["clean"]);
+    }
+
+    #[test]
+    fn test_package_args_repeat_package_and_exclude() {
+        let package_args = |build: BuildArgs| {
+            let mut cmd = TokioCommand::new("cargo");
+            add_package_args(&mut cmd, &musl_args(build));
+            command_args(&cmd)
+        };
+
+        let args = package_args(BuildArgs {
+            package: vec!["app".to_string(), "core-*".to_string()],
+            ..BuildArgs::default()
+        });
+        assert_eq!(args, vec!["--package", "app", "--package", "core-*"]);
+
+        let args = package_args(BuildArgs {
+            workspace: true,
+            exclude: vec!["xtask".to_string(), "bench-*".to_string()],
+            ..BuildArgs::default()
+        });
+        assert_eq!(
+            args,
+            vec!["--workspace", "--exclude", "xtask", "--exclude", "bench-*"]
+        );
+
+        // Resolved default members replace --workspace
+        let args = package_args(BuildArgs {
+            workspace: true,
+            default_members_only: true,
+            package: vec!["app".to_string(), "core".to_string()],
+            ..BuildArgs::default()
+        });
+        assert_eq!(args, vec!["--package", "app", "--package", "core"]);
+    }
+
+    #[test]
+    fn test_cargo_args_are_passed_as_separate_arguments() {
+        let args = musl_args(BuildArgs {
+            cargo_args: vec![
+                "--config".to_string(),
+                "build.jobs = 2".to_string(),
+                "--frozen".to_string(),
+            ],
+            ..BuildArgs::default()
+        });
+        let argv = cargo_argv("aarch64-unknown-linux-musl", &args, &CrossEnv::new(), false);
+        assert!(argv.ends_with(&[
+            "--config".to_string(),
+            "build.jobs = 2".to_string(),
+            "--frozen".to_string()
+        ]));
+    }
+
+    #[test]
+    fn test_auto_debuginfo_for_qemu_gdb() {
+        let target = "aarch64-unknown-linux-gnu";
+        let no_env = |_: &str| false;
+        let mut args = musl_args(BuildArgs {
+            profile: "release".to_string(),
+            qemu_gdb: Some(1234),
+            ..BuildArgs::default()
+        });
+        assert_eq!(
+            auto_debuginfo_configs(&args, target, no_env),
+            [
+                "profile.release.debug=true",
+                "profile.release.strip=\"none\""
+            ]
+        );
+        let argv = cargo_argv(target, &args, &CrossEnv::new(), false);
+        assert!(argv
+            .windows(2)
+            .any(|w| w == ["--config", "profile.release.debug=true"]));
+
+        // The user's --config and CARGO_PROFILE_* settings win
+        args.build.cargo_config = vec![
+            "profile.release.debug = 1".to_string(),
+            "x86_64-*=>profile.release.strip=true".to_string(),
+        ];
+        assert_eq!(
+            auto_debuginfo_configs(&args, target, no_env),
+            ["profile.release.strip=\"none\""]
+        );
+        assert!(auto_debuginfo_configs(&args, target, |name| {
+            name == "CARGO_PROFILE_RELEASE_STRIP"
+        })
+        .is_empty());
+
+        // Custom profiles are named in the keys and environment variables
+        args.build.cargo_config.clear();
+        args.build.profile = "dist-lto".to_string();
+        assert_eq!(
+            auto_debuginfo_configs(&args, target, |name| name == "CARGO_PROFILE_DIST_LTO_DEBUG"),
+            ["profile.dist-lto.strip=\"none\""]
+        );
+
+        // Profiles with debug info, --no-auto-debuginfo and runs without gdb are left alone
+        args.build.profile = "dev".to_string();
+        assert!(auto_debuginfo_configs(&args, target, no_env).is_empty());
+        args.build.profile = "release".to_string();
+        args.build.no_auto_debuginfo = true;
+        assert!(auto_debuginfo_configs(&args, target, no_env).is_empty());
+        args.build.no_auto_debuginfo = false;
+        args.build.qemu_gdb = None;
+        assert!(auto_debuginfo_configs(&args, target, no_env).is_empty());
+    }
+
+    #[test]
+    fn test_artifact_dir_needs_unstable_cargo() {
+        let count = |argv: &[String], arg: &str| argv.iter().filter(|a| *a == arg).count();
+        let mut args = musl_args(BuildArgs {
+            artifact_dir: Some(PathBuf::from("dist")),
+            ..BuildArgs::default()
+        });
+        args.toolchain = Some("nightly".to_string());
+        let argv = cargo_argv("aarch64-unknown-linux-musl", &args, &CrossEnv::new(), false);
+        assert!(argv.windows(2).any(|w| w == ["--artifact-dir", "dist"]));
+        assert_eq!(count(&argv, "unstable-options"), 1);
+
+        // Not doubled when the user passes it
+        args.build.cargo_z_flags = vec!["unstable-options".to_string()];
+        let argv = cargo_argv("aarch64-unknown-linux-musl", &args, &CrossEnv::new(), false);
+        assert_eq!(count(&argv, "unstable-options"), 1);
+
+        // On stable cargo-cross copies the artifacts itself
+        args.build.cargo_z_flags.clear();
+        args.toolchain = Some("stable".to_string());
+        assert!(!forwards_artifact_dir(&args));
+        let argv = cargo_argv("aarch64-unknown-linux-musl", &args, &CrossEnv::new(), false);
+        assert_eq!(count(&argv, "--artifact-dir"), 0);
+        assert_eq!(count(&argv, "unstable-options"), 0);
+
+        // Unless RUSTC_BOOTSTRAP=1 unlocks unstable options
+        args.build.rustc_bootstrap = Some("1".to_string());
+        assert!(forwards_artifact_dir(&args));
+
+        // Splitting debug info needs the copies cargo-cross makes
+        args.build.debuginfo_artifacts = true;
+        assert!(!forwards_artifact_dir(&args));
+        args.build.debuginfo_artifacts = false;
+
+        // Several targets are collected into a directory each
+        args.targets.push("x86_64-unknown-linux-musl".to_string());
+        assert!(!forwards_artifact_dir(&args));
+        args.targets.truncate(1);
+
+        // cargo rustc has no --artifact-dir; cargo-cross collects its artifacts
+        args.command = Command::new("rustc");
+        assert!(!forwards_artifact_dir(&args));
+        assert!(produces_artifacts(&args));
+        let argv = cargo_argv("aarch64-unknown-linux-musl", &args, &CrossEnv::new(), false);
+        assert_eq!(count(&argv, "--artifact-dir"), 0);
+    }
+
+    #[test]
+    fn test_skip_tests_follow_passthrough_args() {
+        let mut args = musl_args(BuildArgs {
+            passthrough_args: vec!["--nocapture".to_string()],
+            skip_tests: vec![
+                "timing::".to_string(),
+                "aarch64-unknown-linux-musl=asm".to_string(),
+                "x86_64-unknown-linux-gnu=other".to_string(),
+            ],
+            ..BuildArgs::default()
+        });
+        args.command = Command::test();
+        let argv = cargo_argv("aarch64-unknown-linux-musl", &args, &CrossEnv::new(), false);
+        assert!(argv.ends_with(
+            &["--", "--nocapture", "--skip", "timing::", "--skip", "asm"].map(String::from)
+        ));
+
+        // Filters alone still get their --
+        args.build.passthrough_args.clear();
+        let argv = cargo_argv("x86_64-unknown-linux-gnu", &args, &CrossEnv::new(), false);
+        assert!(argv.ends_with(&["--", "--skip", "timing::", "--skip", "other"].map(String::from)));
+    }
+
+    #[test]
+    fn test_emulated_runs_are_flagged() {
+        let target = "aarch64-unknown-linux-musl";
+        let args = musl_args(BuildArgs::default());
+        let host = HostPlatform::detect();
+        let mut cross_env = CrossEnv::new();
+        cross_env.set_runner("qemu-aarch64 -L /sysroot");
+        let env = build_cargo_env(target, &args, &cross_env, &host, false).unwrap();
+        assert_eq!(
+            env.get("CARGO_CROSS_EMULATED").map(String::as_str),
+            Some("1")
+        );
+
+        let env = build_cargo_env(target, &args, &CrossEnv::new(), &host, false).unwrap();
+        assert!(!env.contains_key("CARGO_CROSS_EMULATED"));
+    }
+
+    #[test]
+    fn test_cross_context_for_build_scripts() {
+        let target = "aarch64-unknown-linux-gnu";
+        let args = musl_args(BuildArgs::default());
+        let host = HostPlatform {
+            os: "linux",
+            arch: "x86_64",
+            triple: "x86_64-unknown-linux-gnu".to_string(),
+        };
+        let toolchain = PathBuf::from("/cache/aarch64-linux-gnu-cross-v0.7.7-glibc2.28");
+        let mut cross_env = CrossEnv::new();
+        cross_env.set_toolchain_dir(&toolchain);
+        cross_env.set_sysroot(toolchain.join("aarch64-linux-gnu"));
+        cross_env.glibc_version = Some("2.28".to_string());
+        cross_env.set_runner("qemu-aarch64 -L /sysroot");
+        let env = build_cargo_env(target, &args, &cross_env, &host, false).unwrap();
+        let get = |name: &str| env.get(name).map(String::as_str);
+        assert_eq!(get("CARGO_CROSS"), Some("1"));
+        assert_eq!(get("CARGO_CROSS_TARGET"), Some(target));
+        assert_eq!(
+            get("CARGO_CROSS_SYSROOT"),
+            Some(toolchain.join("aarch64-linux-gnu").to_str().unwrap())
+        );
+        assert_eq!(get("CARGO_CROSS_TOOLCHAIN_DIR"), toolchain.to_str());
+        assert_eq!(get("CARGO_CROSS_GLIBC_VERSION"), Some("2.28"));
+        assert_eq!(get("CARGO_CROSS_RUNNER_KIND"), Some("qemu"));
+
+        // A build sets up no runner, but its build scripts still see the target's
+        let env = build_cargo_env(target, &args, &CrossEnv::new(), &host, false).unwrap();
+        assert_eq!(
+            env.get("CARGO_CROSS_RUNNER_KIND").map(String::as_str),
+            Some("qemu")
+        );
+        for name in [
+            "CARGO_CROSS_SYSROOT",
+            "CARGO_CROSS_TOOLCHAIN_DIR",
+            "CARGO_CROSS_GLIBC_VERSION",
+        ] {
+            assert!(!env.contains_key(name), "{name}");
+        }
+        let env = build_cargo_env(&host.triple, &args, &CrossEnv::new(), &host, false).unwrap();
+        assert_eq!(
+            env.get("CARGO_CROSS_RUNNER_KIND").map(String::as_str),
+            Some("native")
+        );
+
+        // Nothing is set up with --no-toolchain-setup
+        let args = musl_args(BuildArgs {
+            no_toolchain_setup: true,
+            ..BuildArgs::default()
+        });
+        let env = build_cargo_env(target, &args, &CrossEnv::new(), &host, false).unwrap();
+        assert_eq!(
+            env.get("CARGO_CROSS_RUNNER_KIND").map(String::as_str),
+            Some("none")
+        );
+    }
+
+    #[test]
+    fn test_print_env_vars_only_at_debug_level() {
+        use crate::color::{capture, LogLevel};
+
+        let mut args = musl_args(BuildArgs::default());
+        let mut env = EnvMap::new();
+        env.insert("CC", "aarch64-linux-musl-gcc", EnvSource::CrossEnv);
+        env.insert("API_TOKEN", "hunter2", EnvSource::Inherited);
+
+        assert!(capture::logs(LogLevel::Info, || print_env_vars(&env, &args)).is_empty());
+
+        let lines = capture::logs(LogLevel::Debug, || print_env_vars(&env, &args));
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("Environment variables:"));
+        assert!(lines[1].contains("API_TOKEN") && lines[1].contains(crate::env::REDACTED_VALUE));
+        assert!(!lines[1].contains("hunter2"));
+        assert!(lines[2].contains("aarch64-linux-musl-gcc"));
+        assert!(!lines[2].contains("(cross-env)"));
+
+        // -vv annotates every variable with its source
+        args.build.verbose_level = 2;
+        let lines = capture::logs(LogLevel::Debug, || print_env_vars(&env, &args));
+        assert!(lines[1].contains("(inherited)"));
+        assert!(lines[2].contains("(cross-env)"));
+    }
+
+    #[test]
+    fn test_build_cargo_env_source_precedence() {
+        let target = "aarch64-unknown-linux-musl";
+        let mut cross_env = CrossEnv::new();
+        cross_env.set_cc("aarch64-linux-musl-gcc");
+        cross_env.add_cflag("-O2");
+        cross_env.add_ldflag("-static");
+        cross_env.add_rustflag("-C target-cpu=generic");
+        let host = HostPlatform::detect();
+        let rustflags_source = |env: &EnvMap| {
+            env.source(&target_rustflags_var(target))
+                .or_else(|| env.source("RUSTFLAGS"))
+                .or_else(|| env.source("CARGO_ENCODED_RUSTFLAGS"))
+        };
+
+        let args = musl_args(BuildArgs {
+            no_toolchain_setup: true,
+            ..BuildArgs::default()
+        });
+        let env = build_cargo_env(target, &args, &cross_env, &host, true).unwrap();
+        assert_eq!(
+            env.source("CC_aarch64_unknown_linux_musl"),
+            Some(EnvSource::CrossEnv)
+        );
+        let cflags = "CFLAGS_aarch64_unknown_linux_musl";
+        assert_eq!(env.get(cflags).unwrap(), "-O2");
+        assert_eq!(env.source(cflags), Some(EnvSource::CrossEnv));
+        // Host build scripts never see the target's flags
+        assert!(!env.contains_key("CFLAGS"));
+        assert!(!env.contains_key("LDFLAGS"));
+        assert_eq!(
+            env.get("LDFLAGS_aarch64_unknown_linux_musl").unwrap(),
+            "-static"
+        );
+        assert_eq!(rustflags_source(&env), Some(EnvSource::CrossEnv));
+
+        // Command-line options override the toolchain environment and take over the source
+        let args = musl_args(BuildArgs {
+            no_toolchain_setup: true,
+            cflags: Some("-g".to_string()),
+            ldflags: Some("-s".to_string()),
+            rustflags: vec!["-C debuginfo=1".to_string()],
+            ..BuildArgs::default()
+        });
+        let env = build_cargo_env(target, &args, &cross_env, &host, true).unwrap();
+        assert_eq!(
+            env.source("CC_aarch64_unknown_linux_musl"),
+            Some(EnvSource::CrossEnv)
+        );
+        assert_eq!(env.get(cflags).unwrap(), "-O2 -g");
+        assert_eq!(env.source(cflags), Some(EnvSource::Cli));
+        assert!(!env.contains_key("CFLAGS"));
+        assert_eq!(
+            env.get("LDFLAGS_aarch64_unknown_linux_musl").unwrap(),
+            "-static -s"
+        );
+        assert!(!env.contains_key("LDFLAGS"));
+        assert_eq!(rustflags_source(&env), Some(EnvSource::Cli));
+    }
+
+    #[test]
+    fn test_add_cc_crate_env_c_jobs() {
+        let mut env = EnvMap::new();
+        add_cc_crate_env(&mut env, &musl_args(BuildArgs::default()));
+        assert!(!env.contains_key("MAKEFLAGS"));
+        assert!(!env.contains_key("CC_ENABLE_DEBUG_OUTPUT"));
+
+        let mut env = EnvMap::new();
+        add_cc_crate_env(
+            &mut env,
+            &musl_args(BuildArgs {
+                c_jobs: Some(2),
+                verbose_level: 1,
+                ..BuildArgs::default()
+            }),
+        );
+        assert!(!env.contains_key("NUM_JOBS"));
+        assert_eq!(env.get("MAKEFLAGS").unwrap(), "-j2");
+        assert_eq!(env.get("CMAKE_BUILD_PARALLEL_LEVEL").unwrap(), "2");
+        assert_eq!(env.source("MAKEFLAGS"), Some(EnvSource::Cli));
+        // -v alone leaves host build script fingerprints alone
+        assert!(!env.contains_key("CC_ENABLE_DEBUG_OUTPUT"));
+
+        let mut env = EnvMap::new();
+        add_cc_crate_env(
+            &mut env,
+            &musl_args(BuildArgs {
+                cc_enable_debug: true,
+                ..BuildArgs::default()
+            }),
+        );
+        assert_eq!(env.get("CC_ENABLE_DEBUG_OUTPUT").unwrap(), "1");
+    }
 }
@@ -0,0 +1,352 @@
+//! `.cargo/config.toml` entries written by `setup --write-cargo-config`
+//!
+//! The entries live between two marker comments so running setup again, for the same or
+//! another target, rewrites only that block and leaves the rest of the file alone:
+//!
+//! ```toml
+//! # BEGIN cargo-cross: managed by `cargo cross setup --write-cargo-config`
+//! [target.aarch64-unknown-linux-musl]
+//! linker = "/cross/toolchains/aarch64-linux-musl-cross/bin/aarch64-linux-musl-gcc"
+//!
+//! [env]
+//! CC_aarch64_unknown_linux_musl = "/cross/toolchains/aarch64-linux-musl-cross/bin/aarch64-linux-musl-gcc"
+//! # END cargo-cross
+//! ```
+
+use crate::error::{CrossError, Result};
+use crate::vendor::toml_string;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// First line of the managed block
+pub const BEGIN_MARKER: &str =
+    "# BEGIN cargo-cross: managed by `cargo cross setup --write-cargo-config`";
+
+/// Last line of the managed block
+pub const END_MARKER: &str = "# END cargo-cross";
+
+/// Cargo config of one target: `[target.<triple>]` keys and its `[env]` entries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetSnippet {
+    pub target: String,
+    /// `key = value` lines of the target table, values already in TOML syntax
+    pub table: Vec<String>,
+    /// Variable name to TOML value of the target-specific variables (`CC_<target>`, ...)
+    pub env: BTreeMap<String, String>,
+}
+
+fn target_header(target: &str) -> String {
+    if target.contains('.') {
+        format!("[target.{}]", toml_string(target))
+    } else {
+        format!("[target.{target}]")
+    }
+}
+
+/// Whether `name` is a variable set only for `target` (`CC_aarch64_unknown_linux_musl`)
+fn is_target_var(name: &str, target_lower: &str) -> bool {
+    name.strip_suffix(target_lower)
+        .is_some_and(|prefix| prefix.ends_with('_'))
+}
+
+impl TargetSnippet {
+    /// The entries for `target` in the environment cargo-cross gives cargo: linker, runner
+    /// and rustflags become target keys, variables suffixed with the target go to `[env]`.
+    /// Global variables such as `PATH` are left out.
+    #[must_use]
+    pub fn from_env(target: &str, env: &HashMap<String, String>) -> Self {
+        let target_upper = crate::env::target_env_upper(target);
+        let target_lower = target.replace('-', "_");
+        let mut table = Vec::new();
+        for key in ["LINKER", "RUNNER"] {
+            if let Some(value) = env.get(&format!("CARGO_TARGET_{target_upper}_{key}")) {
+                table.push(format!("{} = {}", key.to_lowercase(), toml_string(value)));
+            }
+        }
+        let rustflags: Vec<String> = match env.get("CARGO_ENCODED_RUSTFLAGS") {
+            Some(encoded) => encoded.split('\x1f').map(toml_string).collect(),
+            None => env
+                .get(&crate::cargo::target_rustflags_var(target))
+                .or_else(|| env.get("RUSTFLAGS"))
+                .map(|flags| flags.split_whitespace().map(toml_string).collect())
+                .unwrap_or_default(),
+        };
+        if !rustflags.is_empty() {
+            table.push(format!("rustflags = [{}]", rustflags.join(", ")));
+        }
+        let env = env
+            .iter()
+            .filter(|(name, _)| is_target_var(name, &target_lower))
+            .map(|(name, value)| (name.clone(), toml_string(value)))
+            .collect();
+        Self {
+            target: target.to_string(),
+            table,
+            env,
+        }
+    }
+}
+
+/// Contents of a managed block: target tables by triple and `[env]` lines by name
+#[derive(Debug, Default)]
+struct ManagedBlock {
+    targets: BTreeMap<String, Vec<String>>,
+    env: BTreeMap<String, String>,
+}
+
+impl ManagedBlock {
+    fn parse(lines: &[&str]) -> Self {
+        let mut block = Self::default();
+        let mut section: Option<String> = None;
+        for line in lines.iter().map(|line| line.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line == "[env]" {
+                section = None;
+            } else if let Some(target) = line
+                .strip_prefix("[target.")
+                .and_then(|rest| rest.strip_suffix(']'))
+            {
+                let target = target.trim_matches('"').to_string();
+                block.targets.entry(target.clone()).or_default();
+                section = Some(target);
+            } else if let Some(ref target) = section {
+                block
+                    .targets
+                    .entry(target.clone())
+                    .or_default()
+                    .push(line.to_string());
+            } else if let Some((name, value)) = line.split_once('=') {
+                block
+                    .env
+                    .insert(name.trim().to_string(), value.trim().to_string());
+            }
+        }
+        block
+    }
+
+    fn apply(&mut self, snippet: &TargetSnippet) {
+        let target_lower = snippet.target.replace('-', "_");
+        self.env
+            .retain(|name, _| !is_target_var(name, &target_lower));
+        self.env.extend(snippet.env.clone());
+        self.targets
+            .insert(snippet.target.clone(), snippet.table.clone());
+    }
+
+    fn render(&self) -> String {
+        let mut out = format!("{BEGIN_MARKER}\n");
+        for (target, lines) in self.targets.iter().filter(|(_, lines)| !lines.is_empty()) {
+            out.push_str(&target_header(target));
+            out.push('\n');
+            for line in lines {
+                out.push_str(line);
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+        if !self.env.is_empty() {
+            out.push_str("[env]\n");
+            for (name, value) in &self.env {
+                out.push_str(&format!("{name} = {value}\n"));
+            }
+        }
+        out.push_str(END_MARKER);
+        out.push('\n');
+        out
+    }
+}
+
+/// `existing` with the managed block updated for `snippet`, or appended if there is none.
+/// Fails if the rest of the file defines `[env]` or the same target table, which cargo
+/// would reject as a duplicate.
+pub fn merge(existing: &str, snippet: &TargetSnippet, path: &Path) -> Result<String> {
+    let lines: Vec<&str> = existing.lines().collect();
+    let begin = lines
+        .iter()
+        .position(|line| line.trim_end() == BEGIN_MARKER);
+    let end = begin.and_then(|begin| {
+        lines[begin..]
+            .iter()
+            .position(|line| line.trim_end() == END_MARKER)
+            .map(|offset| begin + offset)
+    });
+    let (before, block, after) = match (begin, end) {
+        (Some(begin), Some(end)) => (&lines[..begin], &lines[begin + 1..end], &lines[end + 1..]),
+        _ => (&lines[..], &[][..], &[][..]),
+    };
+
+    let header = target_header(&snippet.target);
+    let conflict = before
+        .iter()
+        .chain(after)
+        .map(|line| line.trim())
+        .find(|line| (*line == "[env]" && !snippet.env.is_empty()) || *line == header);
+    if let Some(line) = conflict {
+        return Err(CrossError::InvalidArgument(format!(
+            "--write-cargo-config: {} already has a {line} table outside the cargo-cross block; \
+             move its entries between the '{BEGIN_MARKER}' and '{END_MARKER}' lines or merge the \
+             printed environment by hand",
+            path.display()
+        )));
+    }
+
+    let mut managed = ManagedBlock::parse(block);
+    managed.apply(snippet);
+
+    let mut out = String::new();
+    for line in before {
+        out.push_str(line);
+        out.push('\n');
+    }
+    if begin.is_none() && !out.trim().is_empty() && !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+    out.push_str(&managed.render());
+    for line in after {
+        out.push_str(line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Update the managed block of the cargo config at `path` for `snippet`.
+/// Returns whether the file changed.
+pub fn write(path: &Path, snippet: &TargetSnippet) -> Result<bool> {
+    let existing = std::fs::read_to_string(path).unwrap_or_default();
+    let updated = merge(&existing, snippet, path)?;
+    if updated == existing {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, updated).map_err(|source| CrossError::IoError {
+        message: format!("Failed to write {}", path.display()),
+        source,
+    })?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(target: &str, cc: &str) -> TargetSnippet {
+        let lower = target.replace('-', "_");
+        let upper = crate::env::target_env_upper(target);
+        TargetSnippet::from_env(
+            target,
+            &HashMap::from([
+                (format!("CC_{lower}"), cc.to_string()),
+                (format!("CARGO_TARGET_{upper}_LINKER"), cc.to_string()),
+                (
+                    format!("CARGO_TARGET_{upper}_RUNNER"),
+                    "qemu-aarch64 -L /sysroot".to_string(),
+                ),
+                (
+                    format!("CARGO_TARGET_{upper}_RUSTFLAGS"),
+                    "-C target-feature=+crt-static".to_string(),
+                ),
+                ("PATH".to_string(), "/cross/bin:/usr/bin".to_string()),
+                (
+                    "CC_x86_64_unknown_linux_musl".to_string(),
+                    "other".to_string(),
+                ),
+            ]),
+        )
+    }
+
+    #[test]
+    fn test_snippet_from_env() {
+        let snippet = snippet("aarch64-unknown-linux-musl", "/cross/bin/gcc");
+        assert_eq!(
+            snippet.table,
+            [
+                r#"linker = "/cross/bin/gcc""#,
+                r#"runner = "qemu-aarch64 -L /sysroot""#,
+                r#"rustflags = ["-C", "target-feature=+crt-static"]"#,
+            ]
+        );
+        assert_eq!(
+            snippet.env.into_iter().collect::<Vec<_>>(),
+            [(
+                "CC_aarch64_unknown_linux_musl".to_string(),
+                r#""/cross/bin/gcc""#.to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_snippet_escapes_control_characters() {
+        let target = "aarch64-unknown-linux-musl";
+        let env = HashMap::from([(
+            "CFLAGS_aarch64_unknown_linux_musl".to_string(),
+            "-DA=\"1\"\t-DB\\\n\r\u{1b}[0m\u{7f}".to_string(),
+        )]);
+        let snippet = TargetSnippet::from_env(target, &env);
+        assert_eq!(
+            snippet.env["CFLAGS_aarch64_unknown_linux_musl"],
+            r#""-DA=\"1\"\t-DB\\\n\r\u001B[0m\u007F""#
+        );
+    }
+
+    #[test]
+    fn test_merge_appends_then_replaces() {
+        let path = Path::new(".cargo/config.toml");
+        let user = "[build]\njobs = 4\n";
+        let first = merge(user, &snippet("aarch64-unknown-linux-musl", "gcc-1"), path).unwrap();
+        assert!(first.starts_with("[build]\njobs = 4\n\n# BEGIN cargo-cross"));
+        assert!(first.contains("[target.aarch64-unknown-linux-musl]\nlinker = \"gcc-1\"\n"));
+
+        // Same input again: nothing changes
+        let again = merge(
+            &first,
+            &snippet("aarch64-unknown-linux-musl", "gcc-1"),
+            path,
+        )
+        .unwrap();
+        assert_eq!(again, first);
+
+        // Another target joins the block, a new toolchain replaces the old entries
+        let second = merge(
+            &again,
+            &snippet("armv7-unknown-linux-gnueabihf", "arm-gcc"),
+            path,
+        )
+        .unwrap();
+        let third = merge(
+            &second,
+            &snippet("aarch64-unknown-linux-musl", "gcc-2"),
+            path,
+        )
+        .unwrap();
+        assert_eq!(third.matches(BEGIN_MARKER).count(), 1);
+        assert_eq!(third.matches("[env]").count(), 1);
+        assert!(third.contains("CC_aarch64_unknown_linux_musl = \"gcc-2\"\n"));
+        assert!(third.contains("CC_armv7_unknown_linux_gnueabihf = \"arm-gcc\"\n"));
+        assert!(!third.contains("gcc-1"));
+        assert!(third.starts_with(user));
+    }
+
+    #[test]
+    fn test_merge_rejects_duplicate_tables() {
+        let path = Path::new(".cargo/config.toml");
+        for existing in [
+            "[env]\nFOO = \"1\"\n",
+            "[target.aarch64-unknown-linux-musl]\nlinker = \"cc\"\n",
+        ] {
+            let err = merge(
+                existing,
+                &snippet("aarch64-unknown-linux-musl", "gcc"),
+                path,
+            )
+            .unwrap_err();
+            assert!(
+                err.to_string().contains("outside the cargo-cross block"),
+                "{err}"
+            );
+        }
+    }
+}
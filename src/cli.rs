@@ -1,51 +1,98 @@
 //! Command-line argument parsing for cargo-cross using clap
 
+use crate::cargo::{std_support, TargetLists};
 use crate::config::{
     self, supported_freebsd_versions_str, supported_glibc_versions_str,
     supported_iphone_sdk_versions_str, supported_macos_sdk_versions_str,
     DEFAULT_CROSS_MAKE_VERSION, DEFAULT_FREEBSD_VERSION, DEFAULT_GLIBC_VERSION,
     DEFAULT_IPHONE_SDK_VERSION, DEFAULT_MACOS_SDK_VERSION, DEFAULT_NDK_VERSION,
-    DEFAULT_QEMU_VERSION, SUPPORTED_FREEBSD_VERSIONS, SUPPORTED_GLIBC_VERSIONS,
-    SUPPORTED_IPHONE_SDK_VERSIONS, SUPPORTED_MACOS_SDK_VERSIONS,
+    DEFAULT_QEMU_VERSION, HOST_COMPAT_UBUNTU_VERSIONS, SUPPORTED_FREEBSD_VERSIONS,
+    SUPPORTED_GLIBC_VERSIONS, SUPPORTED_IPHONE_SDK_VERSIONS, SUPPORTED_MACOS_SDK_VERSIONS,
 };
 use crate::error::{CrossError, Result};
+use crate::policy::TargetPolicy;
 use clap::builder::styling::{AnsiColor, Effects, Styles};
 use clap::ArgAction;
 use clap::{Args as ClapArgs, CommandFactory, FromArgMatches, Parser, Subcommand, ValueHint};
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::LazyLock;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
 
 /// Binary name from Cargo.toml (e.g., "cargo-cross")
 const BIN_NAME: &str = env!("CARGO_PKG_NAME");
 
-/// Define subcommand-related constants from a single literal
-macro_rules! define_subcommand {
-    ($name:literal) => {
-        const SUBCOMMAND: &str = $name;
-        const CARGO_DISPLAY_NAME: &str = concat!("cargo ", $name);
-    };
+/// Cargo subcommand name of a binary not named `cargo-<name>`
+const SUBCOMMAND: &str = "cross";
+
+/// How the program was invoked: the name to show, e.g. "cargo cross" or "cargo-cross", and
+/// how many leading arguments precede its own.
+///
+/// Cargo runs `cargo <name> ...` as `cargo-<name> <name> ...` with `CARGO` set, so a binary
+/// renamed to `cargo-xc` shows as `cargo xc`. Run directly, or through a wrapper with another
+/// name, the binary shows under its file name.
+fn invocation(args: &[String], under_cargo: bool) -> (String, usize) {
+    let stem = args
+        .first()
+        .and_then(|arg0| Path::new(arg0).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .filter(|stem| !stem.is_empty())
+        .unwrap_or_else(|| BIN_NAME.to_string());
+    let subcommand = stem.strip_prefix("cargo-").unwrap_or(SUBCOMMAND);
+    if under_cargo && args.get(1).map(String::as_str) == Some(subcommand) {
+        (format!("cargo {subcommand}"), 2)
+    } else {
+        (stem, 1)
+    }
 }
-define_subcommand!("cross");
 
-/// Program name - either "cargo cross" or "cargo-cross" based on invocation style
-static PROGRAM_NAME: LazyLock<&'static str> = LazyLock::new(|| {
+/// Program name shown in help and hints, from how this process was invoked
+static PROGRAM_NAME: LazyLock<String> = LazyLock::new(|| {
     let args: Vec<String> = std::env::args().collect();
-    let is_cargo_subcommand = std::env::var("CARGO").is_ok()
-        && std::env::var("CARGO_HOME").is_ok()
-        && args.get(1).map(String::as_str) == Some(SUBCOMMAND);
-
-    if is_cargo_subcommand {
-        CARGO_DISPLAY_NAME
-    } else {
-        BIN_NAME
-    }
+    invocation(&args, std::env::var_os("CARGO").is_some()).0
 });
 
 /// Get the program name for display
 #[must_use]
 pub fn program_name() -> &'static str {
-    *PROGRAM_NAME
+    PROGRAM_NAME.as_str()
+}
+
+/// Show the `cargo cross` of help examples as the program was invoked, in `cmd` and its
+/// subcommands
+fn with_program_name(mut cmd: clap::Command, prog: &str) -> clap::Command {
+    const EXAMPLE: &str = "cargo cross ";
+    if prog == EXAMPLE.trim_end() {
+        return cmd;
+    }
+    let rename = |text: String| text.replace(EXAMPLE, &format!("{prog} "));
+    if let Some(about) = cmd.get_long_about().map(ToString::to_string) {
+        cmd = cmd.long_about(rename(about));
+    }
+    let ids: Vec<clap::Id> = cmd
+        .get_arguments()
+        .filter(|arg| {
+            arg.get_long_help()
+                .is_some_and(|help| help.to_string().contains(EXAMPLE))
+        })
+        .map(|arg| arg.get_id().clone())
+        .collect();
+    for id in ids {
+        cmd = cmd.mut_arg(id, |arg| {
+            let help = arg
+                .get_long_help()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            arg.long_help(rename(help))
+        });
+    }
+    let names: Vec<String> = cmd
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+    for name in names {
+        cmd = cmd.mut_subcommand(name, |sub| with_program_name(sub, prog));
+    }
+    cmd
 }
 
 /// Custom styles for CLI help output
@@ -158,6 +205,61 @@ This is useful for running custom tooling that should inherit the configured
 compiler, linker, PATH, and cargo target environment variables.")]
     Exec(ExecCliArgs),
 
+    /// Pre-install the Rust toolchain, targets and cross toolchains for the given targets
+    #[command(long_about = "\
+Install the Rust toolchain, rustup targets, rust-src (when build-std is needed),
+cross-compiler toolchains and QEMU runners for the given targets, then print a
+manifest of what is installed.
+
+This is intended for baking CI images, for example:
+    cargo cross +nightly sync -t '*-linux-musl'
+
+With --check nothing is installed or downloaded; the command exits non-zero if
+anything a later build would need is missing.")]
+    Sync(SyncCliArgs),
+
+    /// Print what a cargo command would do for each target, without doing it
+    #[command(long_about = "\
+Expand targets and resolve toolchains, QEMU/NDK bundles, runners, build-std and the
+cargo command line for each target, then print the plan. Nothing is downloaded,
+installed or written.
+
+Takes the same flags as the planned command, for example:
+    cargo cross plan test -t '*-linux-musl' --format table")]
+    Plan(PlanCliArgs),
+
+    /// Print a key identifying the cross compiler directory contents, for CI caches
+    #[command(long_about = "\
+Print a key for caching the cross compiler directory in CI. It changes whenever the
+directory layout, the selected dependency versions, the host or the targets change,
+for example:
+    key: ${{ runner.os }}-$(cargo cross cache-key -t '*-linux-musl')")]
+    CacheKey(CacheKeyCliArgs),
+
+    /// Print what cargo-cross would configure for a target, without setting anything up
+    #[command(long_about = "\
+Print what cargo-cross would configure for each target without downloading or
+installing anything, for build scripts and other tooling, for example:
+    cargo cross query tools -t aarch64-unknown-linux-musl --format json")]
+    Query(QueryCliArgs),
+
+    /// Show what takes space in the binary built for a target
+    #[command(long_about = "\
+Show the largest functions, or with --crates the largest crates, in the binary built for
+one target. Runs `cargo bloat` with the cross-compilation environment when cargo-bloat is
+installed; otherwise builds the target and reads the symbol table of the binary itself,
+for example:
+    cargo cross bloat -t aarch64-unknown-linux-musl --release --crates")]
+    Bloat(BloatCliArgs),
+
+    /// Remove files cargo-cross generated into the cross compiler directory
+    #[command(long_about = "\
+Remove the files cargo-cross generates into the cross compiler directory: Docker
+QEMU runner scripts, generic CMake toolchain files and Android CMake wrappers.
+Downloaded toolchains are kept. The files are written again on the next run, for example:
+    cargo cross clean --generated")]
+    Clean(CleanCliArgs),
+
     /// Display all supported cross-compilation targets
     #[command(long_about = "\
 Display all supported cross-compilation targets.
@@ -216,12 +318,25 @@ pub struct SetupCliArgs {
         help = "Output format (auto, bash, zsh, fish, powershell, cmd, json)"
     )]
     pub format: SetupOutputFormat,
+
+    /// Also write the target's linker, runner, rustflags and variables to .cargo/config.toml
+    #[arg(
+        long,
+        env = "WRITE_CARGO_CONFIG",
+        long_help = "\
+Also write the target's linker, runner, rustflags and target-specific variables (CC_<target>,
+CFLAGS_<target>, ...) to .cargo/config.toml in the working directory, so plain cargo commands
+pick them up. The entries are kept in a block marked as managed by cargo-cross; running setup
+again replaces them and leaves the rest of the file untouched."
+    )]
+    pub write_cargo_config: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct SetupArgs {
     pub args: Args,
     pub format: SetupOutputFormat,
+    pub write_cargo_config: bool,
 }
 
 #[derive(ClapArgs, Debug, Clone)]
@@ -236,6 +351,141 @@ pub struct ExecArgs {
     pub command: Vec<String>,
 }
 
+#[derive(ClapArgs, Debug, Clone)]
+pub struct SyncCliArgs {
+    #[command(flatten)]
+    pub build: BuildArgs,
+
+    /// Only verify that everything is installed; do not install or download
+    #[arg(long = "check")]
+    pub check: bool,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct CacheKeyCliArgs {
+    #[command(flatten)]
+    pub build: BuildArgs,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct QueryCliArgs {
+    #[command(subcommand)]
+    pub query: QueryCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum QueryCommand {
+    /// Print CC, CXX, AR, the linker, the tool prefix and the toolchain directory name
+    Tools(QueryToolsCliArgs),
+}
+
+/// Output format for query commands
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum QueryOutputFormat {
+    /// One block per target
+    #[default]
+    Text,
+    /// JSON array with one object per target
+    Json,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct QueryToolsCliArgs {
+    #[command(flatten)]
+    pub build: BuildArgs,
+
+    /// Output format
+    #[arg(
+        short = 'f',
+        long = "format",
+        value_enum,
+        default_value = "text",
+        help = "Output format (text, json)"
+    )]
+    pub format: QueryOutputFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryToolsArgs {
+    pub args: Args,
+    pub format: QueryOutputFormat,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct BloatCliArgs {
+    #[command(flatten)]
+    pub build: BuildArgs,
+
+    /// Show the size of each crate instead of the largest functions
+    #[arg(long = "crates")]
+    pub crates: bool,
+
+    /// Only a crate, or functions whose name matches a regex
+    #[arg(long = "filter", value_name = "CRATE|REGEX")]
+    pub filter: Option<String>,
+
+    /// Number of rows to show; 0 shows all
+    #[arg(short = 'n', value_name = "NUM", default_value_t = crate::bloat::DEFAULT_TOP)]
+    pub top: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct BloatArgs {
+    pub args: Args,
+    pub options: crate::bloat::BloatOptions,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct CleanCliArgs {
+    #[command(flatten)]
+    pub build: BuildArgs,
+
+    /// Remove generated runner scripts and CMake files
+    #[arg(long = "generated", required = true)]
+    pub generated: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncArgs {
+    pub args: Args,
+    pub check: bool,
+}
+
+/// Output format for plan command
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PlanOutputFormat {
+    /// JSON document with one entry per target
+    #[default]
+    Json,
+    /// Human-readable table
+    Table,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct PlanCliArgs {
+    /// Output format; may also be given after the cargo command
+    #[arg(short = 'f', long = "format", value_enum, default_value = "json")]
+    pub format: PlanOutputFormat,
+
+    /// Cargo command to plan (build, check, run, test, bench, clippy, ...)
+    #[arg(value_name = "COMMAND")]
+    pub command: String,
+
+    /// Flags for the planned command
+    #[arg(
+        value_name = "ARGS",
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlanArgs {
+    pub args: Args,
+    pub format: PlanOutputFormat,
+}
+
 #[derive(ClapArgs, Debug, Clone, Default)]
 pub struct TargetsArgs {
     /// Output format
@@ -247,6 +497,20 @@ pub struct TargetsArgs {
         help = "Output format (text, json, plain)"
     )]
     pub format: OutputFormat,
+
+    /// Also show whether each target's standard library is prebuilt or needs build-std
+    #[arg(
+        long,
+        long_help = "\
+Also show whether each target's standard library is prebuilt (installable with rustup),
+must be built with build-std (only rustc knows the target) or is unknown to the selected
+toolchain. Queries rustup and rustc once."
+    )]
+    pub detailed: bool,
+
+    /// Rust toolchain from `+toolchain`, used by `--detailed`
+    #[arg(skip)]
+    pub toolchain: Option<String>,
 }
 
 #[derive(ClapArgs, Debug, Clone, Default)]
@@ -258,7 +522,6 @@ pub struct BuildArgs {
         short = 't',
         long = "target",
         visible_alias = "targets",
-        value_delimiter = ',',
         env = "TARGETS",
         value_name = "TRIPLE",
         help = "Build for the target triple(s), comma-separated",
@@ -272,6 +535,27 @@ Examples: -t x86_64-unknown-linux-musl, -t '*-linux-musl'"
     )]
     pub targets: Vec<String>,
 
+    /// JSON file with the targets an organization allows or denies
+    #[arg(
+        long,
+        env = "TARGET_POLICY",
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        long_help = "\
+JSON policy checked once the target list is known, e.g.
+  {\"allow\": [\"*-linux-*\"], \"deny\": [{\"pattern\": \"mips*\", \"message\": \"use aarch64\"}]}
+Entries are target globs, or objects with a pattern and the message shown when it rejects a
+target. Deny entries win over allow entries; with an allow list, unlisted targets are rejected.
+An optional top-level \"message\" is shown for entries without their own. Builds without
+-t are checked as the host triple, and host-tuple as the triple it stands for. Set
+TARGET_POLICY in the CI environment to apply it to every build."
+    )]
+    pub target_policy: Option<PathBuf>,
+
+    /// Only warn about targets the --target-policy rejects
+    #[arg(long, env = "POLICY_WARN_ONLY", help_heading = "Target Selection")]
+    pub policy_warn_only: bool,
+
     // ===== Feature Selection =====
     /// Space or comma separated list of features to activate
     #[arg(
@@ -333,12 +617,14 @@ Custom profiles can be defined in Cargo.toml. Default is 'dev'."
         long,
         env = "PACKAGE",
         value_name = "SPEC",
+        value_delimiter = ',',
+        action = clap::ArgAction::Append,
         help_heading = "Package Selection",
         long_help = "\
 Build only the specified packages. This flag may be specified multiple times
 and supports common Unix glob patterns like *, ?, and []."
     )]
-    pub package: Option<String>,
+    pub package: Vec<String>,
 
     /// Build all members in the workspace
     #[arg(
@@ -349,18 +635,32 @@ and supports common Unix glob patterns like *, ?, and []."
     )]
     pub workspace: bool,
 
+    /// Build only the workspace default members
+    #[arg(
+        long,
+        env = "DEFAULT_MEMBERS_ONLY",
+        conflicts_with_all = ["package", "exclude"],
+        help_heading = "Package Selection",
+        long_help = "\
+Build only the workspace's default members. The members are read from
+`cargo metadata` and passed to cargo as -p flags, replacing --workspace."
+    )]
+    pub default_members_only: bool,
+
     /// Exclude packages from the build (must be used with --workspace)
     #[arg(
         long,
         env = "EXCLUDE",
         value_name = "SPEC",
+        value_delimiter = ',',
+        action = clap::ArgAction::Append,
         requires = "workspace",
         help_heading = "Package Selection",
         long_help = "\
 Exclude the specified packages. Must be used in conjunction with the --workspace flag.
 This flag may be specified multiple times and supports common Unix glob patterns."
     )]
-    pub exclude: Option<String>,
+    pub exclude: Vec<String>,
 
     /// Build only the specified binary
     #[arg(
@@ -501,6 +801,19 @@ Override iPhoneSimulator SDK path for simulator targets. Skips version lookup.")
 Override macOS SDK path directly. Skips version lookup.")]
     pub macos_sdk_path: Option<PathBuf>,
 
+    /// Ubuntu build of osxcross/ioscross to download on Linux hosts
+    #[arg(
+        long,
+        env = "HOST_COMPAT_UBUNTU",
+        value_name = "VERSION",
+        help_heading = "Toolchain Versions",
+        long_help = "\
+Ubuntu release whose build of osxcross and ioscross to download on Linux hosts (20.04, 22.04 or
+24.04). By default Ubuntu hosts use their own release; other distributions get the newest
+build whose glibc is not newer than the host's, read from /etc/os-release and getconf or ldd."
+    )]
+    pub host_compat_ubuntu: Option<String>,
+
     /// FreeBSD version for FreeBSD targets
     #[arg(long, default_value = DEFAULT_FREEBSD_VERSION, env = "FREEBSD_VERSION",
           value_name = "VERSION", hide_default_value = true, help_heading = "Toolchain Versions")]
@@ -510,7 +823,8 @@ Override macOS SDK path directly. Skips version lookup.")]
     #[arg(long, default_value = DEFAULT_NDK_VERSION, env = "NDK_VERSION",
           value_name = "VERSION", hide_default_value = true, help_heading = "Toolchain Versions",
           long_help = "\
-Specify Android NDK version for Android targets. Auto-downloaded from Google's official repository.")]
+Specify Android NDK version for Android targets. Auto-downloaded from Google's official repository.
+Takes a release name (r25c, r27d, r29-beta1) or the sdkmanager version (27.2.12479018 is r27c).")]
     pub ndk_version: String,
 
     /// QEMU version for user-mode emulation
@@ -544,42 +858,284 @@ Set this to reuse downloaded toolchains across builds.")]
 Directory for all generated artifacts and intermediate files. Defaults to 'target'.")]
     pub cargo_target_dir: Option<PathBuf>,
 
+    /// Maximum total size of the cross compiler directory
+    #[arg(long, env = "TOOLCHAIN_CACHE_LIMIT", value_name = "SIZE",
+          value_parser = crate::cache::parse_size, help_heading = "Directories",
+          long_help = "\
+Maximum total size of the cross compiler directory (e.g. 20G, 512M). After a successful run,
+least-recently-used toolchain directories are deleted until the total fits, never deleting
+toolchains used by the current run. Last use is tracked in an index file in that directory.")]
+    pub toolchain_cache_limit: Option<u64>,
+
+    /// Reuse an installed older cross-make toolchain when the release is identical
+    #[arg(
+        long,
+        env = "ADOPT_EXISTING",
+        help_heading = "Directories",
+        long_help = "\
+Before downloading a cross-make toolchain, look for the same toolchain with another version in
+the cross compiler directory. If GitHub publishes the same digest for both release assets, the
+installed directory is symlinked under the new name (renamed on Windows) instead of downloading it."
+    )]
+    pub adopt_existing: bool,
+
+    /// Fail instead of warning when cached toolchains use an outdated layout
+    #[arg(
+        long,
+        env = "STRICT_CACHE",
+        help_heading = "Directories",
+        long_help = "\
+Fail instead of warning when the cross compiler directory was written by a cargo-cross release
+with a different directory layout or different default dependency versions. Such caches may hold
+stale toolchains next to fresh downloads; delete the directory or key CI caches on
+`cargo cross cache-key` so they are rebuilt when the layout changes."
+    )]
+    pub strict_cache: bool,
+
+    /// Use a separate target directory per target triple
+    #[arg(
+        long,
+        env = "PER_TARGET_DIR",
+        help_heading = "Directories",
+        long_help = "\
+Give each target triple its own target directory (<target-dir>/per-target/<triple>).
+By default all targets share one target directory so host-side build scripts and proc macros
+are compiled once and reused; cargo already separates target artifacts by triple.
+Use this only when targets must not share any intermediate state."
+    )]
+    pub per_target_dir: bool,
+
     /// Copy final artifacts to this directory (unstable)
     #[arg(long, env = "ARTIFACT_DIR", value_name = "DIR",
           value_hint = ValueHint::DirPath, help_heading = "Directories",
           long_help = "\
-Copy final artifacts to this directory. Unstable, requires nightly toolchain.")]
+Copy final artifacts to this directory. On nightly (or with RUSTC_BOOTSTRAP=1) this is cargo's
+--artifact-dir, with -Z unstable-options added when missing. On stable cargo-cross copies the
+same files after the build, each under its own file name. With several targets cargo-cross
+copies them into <DIR>/<triple>/, so one binary built for each target is kept once per target.
+For bench, collected benchmark results go to <DIR>/<triple>/bench instead.")]
     pub artifact_dir: Option<PathBuf>,
 
+    /// File name template for artifacts collected into --artifact-dir
+    #[arg(
+        long,
+        env = "ARTIFACT_NAME",
+        value_name = "TEMPLATE",
+        requires = "artifact_dir",
+        help_heading = "Directories",
+        long_help = "\
+Collect artifacts into --artifact-dir with cargo-cross instead of cargo, naming each file
+after TEMPLATE. Works on stable. Placeholders: {package}, {name}, {target}, {kind}
+(bin, example, cdylib, staticlib) and {file}, the file name cargo writes. Names shared by
+artifacts of different packages get a '{package}-' prefix; names that still collide, such
+as one binary built for several targets without {target}, are an error.
+Example: --artifact-name '{target}/{file}'"
+    )]
+    pub artifact_name: Option<String>,
+
+    /// Also collect files in the profile output directory matching a glob
+    #[arg(
+        long,
+        env = "COLLECT_GLOB",
+        value_name = "PATTERN",
+        requires = "artifact_dir",
+        value_parser = parse_collect_glob,
+        help_heading = "Directories",
+        long_help = "\
+After the build, copy the files in target/<triple>/<profile> whose path relative to it
+matches PATTERN into --artifact-dir, keeping that relative path. Repeatable. Use it for
+outputs cargo does not list, such as '--emit=llvm-ir' files in deps/. '*' stops at '/',
+'**' matches any number of directories. Symlinks pointing outside the output directory
+are not followed, and patterns may not contain '..'.
+Example: --collect-glob 'deps/*.ll' --collect-glob 'build/*/out/**/*.h'"
+    )]
+    pub collect_glob: Vec<String>,
+
+    /// Split debug info out of collected artifacts into separate files
+    #[arg(
+        long,
+        env = "DEBUGINFO_ARTIFACTS",
+        requires = "artifact_dir",
+        help_heading = "Directories",
+        long_help = "\
+After collecting each executable and cdylib into --artifact-dir, move its debug info into a
+separate file next to it and strip the collected copy; cargo-cross then does the collection
+itself, also on nightly. ELF and MinGW targets get <file>.debug from the toolchain's objcopy
+(--only-keep-debug, then --strip-debug with a .gnu_debuglink); darwin and ios targets get
+<file>.dSYM from dsymutil, skipped with a warning when no dsymutil is available. A file the
+tool fails on keeps its debug info, with a warning. MSVC targets already keep debug info in
+the .pdb. The files in the target directory are left as they are. Build with debug info,
+e.g. CARGO_PROFILE_RELEASE_DEBUG=true."
+    )]
+    pub debuginfo_artifacts: bool,
+
+    /// Compress split debug files with zstd
+    #[arg(
+        long,
+        env = "COMPRESS_DEBUGINFO",
+        requires = "debuginfo_artifacts",
+        help_heading = "Directories",
+        long_help = "\
+Compress the debug sections of <file>.debug files written by --debuginfo-artifacts with
+zstd (objcopy --compress-debug-sections=zstd). gdb, lldb and addr2line read them directly.
+Has no effect on dSYM bundles."
+    )]
+    pub compress_debuginfo: bool,
+
+    /// Convert bare-metal executables into a flashable image
+    #[arg(
+        long,
+        env = "OBJCOPY_FORMAT",
+        value_enum,
+        value_name = "FORMAT",
+        help_heading = "Directories",
+        long_help = "\
+For targets without an OS (*-none-*), convert each executable and cdylib the build links into
+<file>.bin (binary, a raw memory image) or <file>.hex (ihex, Intel HEX) next to it with
+objcopy -O <FORMAT>. Uses rust-objcopy (cargo-binutils), the llvm-objcopy of the llvm-tools
+component, or the toolchain's objcopy. Other targets are left alone. The text/data/bss sizes
+of bare-metal builds are shown in the summary either way."
+    )]
+    pub objcopy_format: Option<ObjcopyFormat>,
+
     // ===== Compiler Options =====
     /// Override C compiler path
-    #[arg(long, env = "CC", value_name = "PATH",
+    /// Note: `CC` env var is handled manually so its commas are not split
+    #[arg(long, value_name = "[TARGET=]PATH", value_delimiter = ',',
           value_hint = ValueHint::ExecutablePath, help_heading = "Compiler Options",
           long_help = "\
-Override the C compiler path. By default, the appropriate cross-compiler is auto-configured.")]
-    pub cc: Option<PathBuf>,
+Override the C compiler path. By default, the appropriate cross-compiler is auto-configured.
+Use 'TARGET=PATH' entries to set it per target; a bare PATH applies to all other targets.
+Without --cc, CC is used as a single entry, so a command like 'gcc -Wl,-rpath,/x' is kept whole.")]
+    pub cc: Vec<String>,
 
     /// Override C++ compiler path
-    #[arg(long, env = "CXX", value_name = "PATH",
+    /// Note: `CXX` env var is handled manually so its commas are not split
+    #[arg(long, value_name = "[TARGET=]PATH", value_delimiter = ',',
           value_hint = ValueHint::ExecutablePath, help_heading = "Compiler Options",
           long_help = "\
-Override the C++ compiler path. By default, the appropriate cross-compiler is auto-configured.")]
-    pub cxx: Option<PathBuf>,
+Override the C++ compiler path. By default, the appropriate cross-compiler is auto-configured.
+Use 'TARGET=PATH' entries to set it per target; a bare PATH applies to all other targets.
+Without --cxx, CXX is used as a single entry.")]
+    pub cxx: Vec<String>,
 
     /// Override archiver (ar) path
-    #[arg(long, env = "AR", value_name = "PATH",
+    /// Note: `AR` env var is handled manually so its commas are not split
+    #[arg(long, value_name = "[TARGET=]PATH", value_delimiter = ',',
           value_hint = ValueHint::ExecutablePath, help_heading = "Compiler Options",
           long_help = "\
-Override the archiver (ar) path. By default, the appropriate archiver is auto-configured.")]
-    pub ar: Option<PathBuf>,
+Override the archiver (ar) path. By default, the appropriate archiver is auto-configured.
+Use 'TARGET=PATH' entries to set it per target; a bare PATH applies to all other targets.
+Without --ar, AR is used as a single entry.")]
+    pub ar: Vec<String>,
 
     /// Override linker path
-    #[arg(long, env = "LINKER", value_name = "PATH",
+    /// Note: `LINKER` env var is handled manually so its commas are not split
+    #[arg(long, value_name = "[TARGET=]PATH", value_delimiter = ',',
           value_hint = ValueHint::ExecutablePath, help_heading = "Compiler Options",
           long_help = "\
 Override the linker path. By default, the cross-compiler is used as linker.
-This option takes precedence over auto-configured linker.")]
-    pub linker: Option<PathBuf>,
+This option takes precedence over auto-configured linker. Use 'TARGET=PATH' entries to set
+it per target; a bare PATH applies to all other targets. lld-link (MSVC targets) and ld64 or
+ld64.lld (Apple targets) get the matching -C linker-flavor. Without --linker, LINKER is used
+as a single entry.
+Example: --linker x86_64-pc-windows-msvc=lld-link,/opt/musl/bin/musl-gcc")]
+    pub linker: Vec<String>,
+
+    /// Put unprefixed gcc, g++ and strip shims for the target first in PATH
+    #[arg(
+        long,
+        env = "SHIM_UNPREFIXED_TOOLS",
+        help_heading = "Compiler Options",
+        long_help = "\
+Run cargo with a directory first in PATH whose gcc, g++ and strip are the target's tools
+(symlinks, or .cmd files on Windows hosts), for build systems that ignore CC and call the
+plain names. Only the cargo invocation sees them. cc, c++, ar and ld are not shimmed, so
+build scripts and proc macros still link for the host. Anything else that runs these names for the host gets
+the target tools, so this is off by default."
+    )]
+    pub shim_unprefixed_tools: bool,
+
+    /// Override the runner used by run/test/bench
+    #[arg(
+        long,
+        env = "RUNNER",
+        value_name = "COMMAND",
+        help_heading = "Compiler Options",
+        long_help = "\
+Override the runner cargo uses to execute target binaries (e.g. 'qemu-aarch64 -L /sysroot').
+Takes precedence over the auto-configured QEMU/Wine/Rosetta runner and also works with
+--no-toolchain-setup, which otherwise configures no runner."
+    )]
+    pub runner: Option<String>,
+
+    /// Library root QEMU loads the target's dynamic linker and libraries from
+    #[arg(long, env = "QEMU_SYSROOT", value_name = "DIR", value_hint = ValueHint::DirPath,
+          help_heading = "Compiler Options",
+          long_help = "\
+Directory passed to QEMU as its library root (-L, or QEMU_LD_PREFIX when the path contains
+spaces) instead of the compiler sysroot, e.g. when the binaries were built against another
+glibc or copied from elsewhere. Applies to the native and the Docker QEMU runner. Should
+contain a lib directory with the target's dynamic linker.")]
+    pub qemu_sysroot: Option<PathBuf>,
+
+    /// QEMU binary (or a directory containing it) to use instead of the downloaded bundle
+    #[arg(long, env = "QEMU_PATH", value_name = "FILE_OR_DIR", value_hint = ValueHint::AnyPath,
+          help_heading = "Compiler Options",
+          long_help = "\
+Use this QEMU user-mode binary as the runner for Linux targets instead of downloading the
+qemu-user-static bundle, e.g. a distribution build with plugins. A file is used as is and
+must be named after the emulator (qemu-aarch64, qemu-aarch64-static) or report it in
+--version; a directory must contain the emulator for the target's architecture (qemu-arm
+for armv7). --qemu-sysroot and --target-cpu still apply. On macOS the binary is copied
+into the Docker container, so it must be a Linux build.")]
+    pub qemu_path: Option<PathBuf>,
+
+    /// Have QEMU wait for a gdb connection on this port before running each binary
+    #[arg(
+        long,
+        env = "QEMU_GDB",
+        value_name = "PORT",
+        help_heading = "Compiler Options",
+        long_help = "\
+Start binaries under the QEMU runner stopped, waiting for gdb on this TCP port (QEMU_GDB),
+e.g. 'target remote :1234' from the toolchain's gdb. Not available with the Docker runner.
+Profiles other than dev and test get '--config profile.<name>.debug=true' and
+'--config profile.<name>.strip=\"none\"' so breakpoints work; keys set with --config or
+CARGO_PROFILE_<NAME>_DEBUG/STRIP are left alone. Pass --no-auto-debuginfo to keep the
+profile as is."
+    )]
+    pub qemu_gdb: Option<u16>,
+
+    /// Do not turn on debug info for --qemu-gdb
+    #[arg(
+        long,
+        env = "NO_AUTO_DEBUGINFO",
+        requires = "qemu_gdb",
+        help_heading = "Compiler Options",
+        long_help = "\
+Build with the selected profile unchanged under --qemu-gdb, even when it has no debug
+info or strips symbols."
+    )]
+    pub no_auto_debuginfo: bool,
+
+    /// How Linux binaries the host can execute are run (auto, chroot)
+    #[arg(
+        long,
+        env = "RUNNER_BACKEND",
+        value_enum,
+        value_name = "BACKEND",
+        default_value = "auto",
+        help_heading = "Compiler Options",
+        long_help = "\
+Backend of the runner for Linux targets the host CPU executes natively.
+  auto    QEMU with the compiler sysroot as its library root; Docker with QEMU on macOS
+  chroot  the sysroot's own dynamic linker inside an unprivileged chroot, without QEMU or
+          Docker: 'unshare -r' when user namespaces are enabled, otherwise proot. Falls back
+          to QEMU with a warning when neither works. Targets of other architectures keep
+          using QEMU, which already loads the sysroot's dynamic linker."
+    )]
+    pub runner_backend: RunnerBackend,
 
     /// Additional flags for C compilation
     #[arg(
@@ -631,15 +1187,54 @@ Specify the C++ standard library to use (libc++, libstdc++, etc)."
     )]
     pub cxxstdlib: Option<String>,
 
+    /// Give built macOS/iOS dylibs an @rpath install name
+    #[arg(long, value_parser = parse_optional_bool, env = "FIX_INSTALL_NAME",
+          value_name = "BOOL", num_args = 0..=1, default_missing_value = "true",
+          help_heading = "Compiler Options",
+          long_help = "\
+After building a darwin or ios target, set the install name of each cdylib to
+@rpath/<file> with the toolchain's install_name_tool, instead of the build path the
+linker recorded. Defaults to true; a dylib that cannot be fixed fails the build.")]
+    pub fix_install_name: Option<bool>,
+
+    /// Rpath entries to add to built macOS/iOS dylibs
+    #[arg(
+        long,
+        env = "RPATH",
+        value_name = "PATH",
+        value_delimiter = ',',
+        help_heading = "Compiler Options",
+        long_help = "\
+Add an LC_RPATH entry to each cdylib built for a darwin or ios target, e.g.
+--rpath @loader_path/../lib. May be given multiple times. Ignored with --fix-install-name=false."
+    )]
+    pub rpath: Vec<String>,
+
+    /// Native libraries to take from the toolchain sysroot
+    #[arg(
+        long,
+        env = "USE_SYSROOT_LIBS",
+        value_name = "LIBS",
+        value_delimiter = ',',
+        value_parser = parse_sysroot_lib,
+        help_heading = "Compiler Options",
+        long_help = "\
+Comma-separated native libraries (zlib, sqlite3, openssl) to link from the toolchain
+sysroot instead of building vendored copies. Sets the variables their -sys crates read in
+the per-target form, e.g. <TARGET>_SQLITE3_LIB_DIR or <TARGET>_OPENSSL_NO_VENDOR, and warns
+about libraries not found."
+    )]
+    pub use_sysroot_libs: Vec<String>,
+
     /// `CMake` generator to use (like cmake -G)
     #[arg(
         long,
         short = 'G',
         env = "CMAKE_GENERATOR",
         value_name = "GENERATOR",
-        help_heading = "Compiler Options",
+        help_heading = "CMake Options",
         long_help = "\
-Specify the CMake generator to use. On Windows, this overrides the auto-detection.
+Specify the CMake generator to use. Any name is passed through to CMake unchanged. On Windows, this overrides the auto-detection.
 Common generators: Ninja, 'MinGW Makefiles', 'Unix Makefiles', 'NMake Makefiles'.
 If not specified, auto-detects: Ninja > MinGW Makefiles > Unix Makefiles."
     )]
@@ -670,36 +1265,170 @@ Specify a rustc wrapper program (sccache, cachepot, etc) for compilation caching
         long_help = "\
 Skip downloading and configuring cross-compilation toolchain.
 Use this when you have pre-configured system compilers or want to use
-only CLI-provided compiler options (--cc, --cxx, --ar, --linker)."
+only CLI-provided compiler options (--cc, --cxx, --ar, --linker).
+Nothing is downloaded: no toolchains, NDK or QEMU. Runners are not
+configured automatically; pass --runner to run binaries for run/test/bench."
     )]
     pub no_toolchain_setup: bool,
 
-    // ===== Sccache Options =====
-    /// Enable sccache for compilation caching
+    /// For `check`, skip the C toolchain and runner unless build scripts compile C
     #[arg(
         long,
-        env = "ENABLE_SCCACHE",
-        conflicts_with = "rustc_wrapper",
-        help_heading = "Sccache Options",
+        env = "NO_LINK_TOOLCHAIN",
+        help_heading = "Compiler Options",
         long_help = "\
-Enable sccache as the rustc wrapper for compilation caching.
-Speeds up compilation by caching previous compilations."
+Skip downloading the C toolchain, libclang and runner for `check`, which
+type-checks without linking. Only done when no package in the crate graph
+has cc, cmake or bindgen among its build-dependencies (per cargo metadata);
+otherwise the full setup runs and a note names the packages. Useful with
+-t all for a quick check of every target."
     )]
-    pub enable_sccache: bool,
-
-    /// Directory for sccache local disk cache
-    #[arg(long, env = "SCCACHE_DIR", value_name = "DIR",
-          value_hint = ValueHint::DirPath, help_heading = "Sccache Options",
-          long_help = "\
-Directory for sccache's local disk cache. Defaults to $HOME/.cache/sccache.")]
-    pub sccache_dir: Option<PathBuf>,
+    pub no_link_toolchain: bool,
 
-    /// Maximum cache size (e.g., '10G', '500M')
+    /// Fail instead of warning when a runner for run/test/bench cannot be configured
     #[arg(
         long,
-        env = "SCCACHE_CACHE_SIZE",
-        value_name = "SIZE",
-        help_heading = "Sccache Options",
+        env = "REQUIRE_RUNNER",
+        help_heading = "Compiler Options",
+        long_help = "\
+Fail before building if the runner needed by run/test/bench (QEMU, Docker, Wine, Rosetta)
+cannot be configured. On by default for test and bench; for other commands a missing runner
+backend only prints a warning and the binaries are run directly."
+    )]
+    pub require_runner: bool,
+
+    /// Build test/bench binaries even when no runner can be configured
+    #[arg(
+        long,
+        env = "ALLOW_NO_RUNNER",
+        conflicts_with = "require_runner",
+        help_heading = "Compiler Options",
+        long_help = "\
+Turn off the runner requirement that test and bench apply by default. A missing runner
+backend then only prints a warning and the binaries are run directly."
+    )]
+    pub allow_no_runner: bool,
+
+    /// Configure the runner for every command so build scripts can run target binaries
+    #[arg(
+        long,
+        env = "RUNNER_FOR_BUILD_SCRIPTS",
+        help_heading = "Compiler Options",
+        long_help = "\
+Set up the QEMU/Wine/Rosetta runner for build and check too, for crates whose build scripts
+run a generator built for the target. Cargo's CARGO_TARGET_<TRIPLE>_RUNNER is exported, and
+the runner command is also exposed as CROSS_RUNNER: a build script spawning a target binary
+through std::process should run `$CROSS_RUNNER <binary> <args>` when the variable is set."
+    )]
+    pub runner_for_build_scripts: bool,
+
+    /// Skip tests matching a filter, for every target or one target
+    #[arg(
+        long,
+        env = "SKIP_TESTS",
+        value_name = "[TARGET=]FILTER",
+        value_delimiter = ',',
+        help_heading = "Compiler Options",
+        long_help = "\
+Pass '--skip FILTER' to the test binaries, after the arguments given after --. Repeatable or
+comma-separated; 'TARGET=FILTER' entries only apply to that target, e.g. tests that are timing
+sensitive under QEMU. Tests can also check CARGO_CROSS_EMULATED, which is 1 when binaries run
+under QEMU, Wine or Rosetta.
+Example: --skip-tests riscv64gc-unknown-linux-gnu=timing::,asm_probe"
+    )]
+    pub skip_tests: Vec<String>,
+
+    /// Never run `rustup target add` / `rustup component add`
+    #[arg(
+        long,
+        env = "NO_TARGET_INSTALL",
+        help_heading = "Compiler Options",
+        long_help = "\
+Never install Rust targets or the rust-src component via rustup. If a required target or
+component is missing, fail immediately and print the rustup command to bake into the image.
+Useful for locked-down CI images with a read-only RUSTUP_HOME or no network access."
+    )]
+    pub no_target_install: bool,
+
+    /// Use the NDK's .cmd clang wrappers on Windows hosts
+    #[arg(
+        long,
+        env = "ANDROID_USE_NDK_WRAPPERS",
+        help_heading = "Compiler Options",
+        long_help = "\
+On Windows hosts, point CC/CXX/linker for Android targets at the NDK's own
+<triple><api>-clang.cmd wrappers instead of the .bat wrappers cargo-cross generates.
+The NDK wrappers mangle arguments containing '=', spaces or quotes (for example
+-DVERSION=\"1.2\" from cc-rs); the generated ones call clang.exe with --target directly."
+    )]
+    pub android_use_ndk_wrappers: bool,
+
+    /// Compile and link a tiny C program with the configured toolchain before building
+    #[arg(long, value_parser = parse_optional_bool, env = "SMOKE_TEST_TOOLCHAIN",
+          value_name = "BOOL", num_args = 0..=1, default_missing_value = "true",
+          help_heading = "Compiler Options",
+          long_help = "\
+Before building, compile and link a hello-world C file (and a C++ one when the crate graph
+builds C++) with exactly the CC/CXX/CFLAGS/LDFLAGS exported to cargo, and check that the
+output has the target's architecture. Fails early with the compiler's stderr instead of
+deep into the build. Defaults to true for toolchains downloaded during this run; passing
+results are cached per toolchain and flags.")]
+    pub smoke_test_toolchain: Option<bool>,
+
+    /// Download the --libclang-url libclang when bindgen is used and the host has none
+    #[arg(
+        long,
+        env = "AUTO_LIBCLANG",
+        requires = "libclang_url",
+        help_heading = "Compiler Options",
+        long_help = "\
+When bindgen is built for the target and libclang cannot be found the way clang-sys looks
+for it (LIBCLANG_PATH, ldconfig, llvm-config, common install directories), download the
+--libclang-url archive into the cross compiler directory and export LIBCLANG_PATH. Without
+this flag such builds fail before cargo runs, naming the missing library."
+    )]
+    pub auto_libclang: bool,
+
+    /// Archive --auto-libclang downloads
+    #[arg(
+        long,
+        env = "LIBCLANG_URL",
+        value_name = "URL",
+        requires = "auto_libclang",
+        help_heading = "Compiler Options",
+        long_help = "Archive (.tar.gz, .tar.xz or .zip) that --auto-libclang downloads, e.g. from an
+internal mirror. It is extracted without a checksum, so there is no default: point it at
+an archive you trust. It must contain lib/libclang.so (lib/libclang.dylib,
+lib/libclang.dll) at its top level."
+    )]
+    pub libclang_url: Option<String>,
+
+    // ===== Sccache Options =====
+    /// Enable sccache for compilation caching
+    #[arg(
+        long,
+        env = "ENABLE_SCCACHE",
+        conflicts_with = "rustc_wrapper",
+        help_heading = "Sccache Options",
+        long_help = "\
+Enable sccache as the rustc wrapper for compilation caching.
+Speeds up compilation by caching previous compilations."
+    )]
+    pub enable_sccache: bool,
+
+    /// Directory for sccache local disk cache
+    #[arg(long, env = "SCCACHE_DIR", value_name = "DIR",
+          value_hint = ValueHint::DirPath, help_heading = "Sccache Options",
+          long_help = "\
+Directory for sccache's local disk cache. Defaults to $HOME/.cache/sccache.")]
+    pub sccache_dir: Option<PathBuf>,
+
+    /// Maximum cache size (e.g., '10G', '500M')
+    #[arg(
+        long,
+        env = "SCCACHE_CACHE_SIZE",
+        value_name = "SIZE",
+        help_heading = "Sccache Options",
         long_help = "\
 Maximum size of the local disk cache (e.g., '10G', '500M'). Default is 10GB."
     )]
@@ -747,6 +1476,75 @@ Enable sccache direct mode. Caches based on source file content directly, bypass
     )]
     pub sccache_direct: bool,
 
+    /// S3 bucket for the sccache cache
+    #[arg(
+        long,
+        env = "SCCACHE_BUCKET",
+        value_name = "BUCKET",
+        help_heading = "Sccache Options",
+        long_help = "\
+Store the sccache cache in this S3 (or S3-compatible) bucket instead of the local disk.
+Credentials are read by sccache from the usual AWS_* variables."
+    )]
+    pub sccache_bucket: Option<String>,
+
+    /// Region of the S3 bucket
+    #[arg(
+        long,
+        env = "SCCACHE_REGION",
+        value_name = "REGION",
+        help_heading = "Sccache Options",
+        long_help = "\
+Region of the S3 bucket given with --sccache-bucket (e.g., 'us-east-1')."
+    )]
+    pub sccache_region: Option<String>,
+
+    /// Endpoint of an S3-compatible storage service
+    #[arg(
+        long,
+        env = "SCCACHE_ENDPOINT",
+        value_name = "URL",
+        help_heading = "Sccache Options",
+        long_help = "\
+Endpoint of an S3-compatible storage service such as MinIO or R2, used with --sccache-bucket."
+    )]
+    pub sccache_endpoint: Option<String>,
+
+    /// Google Cloud Storage bucket for the sccache cache
+    #[arg(
+        long,
+        env = "SCCACHE_GCS_BUCKET",
+        value_name = "BUCKET",
+        help_heading = "Sccache Options",
+        long_help = "\
+Store the sccache cache in this Google Cloud Storage bucket instead of the local disk."
+    )]
+    pub sccache_gcs_bucket: Option<String>,
+
+    /// Redis URL for the sccache cache
+    #[arg(
+        long,
+        env = "SCCACHE_REDIS_ENDPOINT",
+        value_name = "URL",
+        help_heading = "Sccache Options",
+        long_help = "\
+Store the sccache cache in Redis (e.g., 'redis://:password@cache.internal:6379').
+Credentials in the URL are redacted when the environment is printed."
+    )]
+    pub sccache_redis: Option<String>,
+
+    /// Scheduler URL for sccache distributed compilation
+    #[arg(
+        long,
+        env = "SCCACHE_DIST_SCHEDULER_URL",
+        value_name = "URL",
+        help_heading = "Sccache Options",
+        long_help = "\
+Send compilations to the sccache-dist scheduler at this URL.
+Requires an sccache built with distributed compilation support."
+    )]
+    pub sccache_dist_scheduler: Option<String>,
+
     // ===== CC Crate Options =====
     /// Disable CC crate default compiler flags
     #[arg(
@@ -775,15 +1573,42 @@ Enable sccache direct mode. Caches based on source file content directly, bypass
     )]
     pub cc_enable_debug: bool,
 
+    /// Parallel jobs of C/C++ builds run by build scripts
+    #[arg(long, env = "C_JOBS", value_name = "N",
+          value_parser = clap::value_parser!(u32).range(1..),
+          help_heading = "CC Crate Options",
+          long_help = "\
+Limit the parallelism of C/C++ builds inside build scripts independently of cargo's -j.
+Exports MAKEFLAGS=-jN for make and CMAKE_BUILD_PARALLEL_LEVEL=N for cmake. NUM_JOBS is
+left alone, since cargo sets it for build scripts from its own -j.
+Useful on small runners where huge C dependencies (ffmpeg, aws-lc) built in parallel with
+rustc exhaust memory.")]
+    pub c_jobs: Option<u32>,
+
+    /// Warn when a build script runs longer than this many seconds
+    #[arg(
+        long,
+        env = "BUILD_SCRIPT_WARN_SECS",
+        value_name = "SECS",
+        help_heading = "CC Crate Options",
+        long_help = "\
+With -v, print a time-stamped warning naming the crate when a single build script runs
+longer than SECS seconds (default 300). 0 disables the warning."
+    )]
+    pub build_script_warn_secs: Option<u64>,
+
     // ===== Build Options =====
     /// Link the C runtime statically
-    #[arg(long, value_parser = parse_optional_bool, env = "CRT_STATIC",
-          value_name = "BOOL", num_args = 0..=1, default_missing_value = "true",
+    #[arg(long, value_parser = parse_crt_static, env = "CRT_STATIC",
+          value_name = "BOOL|auto", num_args = 0..=1, default_missing_value = "true",
           help_heading = "Build Options",
           long_help = "\
 Control whether the C runtime is statically linked. true=static (larger, portable),
-false=dynamic (smaller, requires libc). Musl defaults to static, glibc to dynamic.")]
-    pub crt_static: Option<bool>,
+false=dynamic (smaller, requires libc). Musl defaults to static, glibc to dynamic.
+auto passes each Linux and Windows target its default explicitly and records the choice
+in --metrics-file. Dynamic musl binaries need the musl loader (/lib/ld-musl-<arch>.so.1)
+at runtime, which glibc distributions do not ship; a warning says so.")]
+    pub crt_static: Option<CrtStatic>,
 
     /// Abort immediately on panic (smaller binary, implies --build-std)
     #[arg(
@@ -792,7 +1617,9 @@ false=dynamic (smaller, requires libc). Musl defaults to static, glibc to dynami
         help_heading = "Build Options",
         long_help = "\
 Use panic=abort and remove panic formatting code for smaller binaries.
-Requires nightly and implies --build-std. Stack traces will not be available."
+Requires nightly and implies --build-std; it cannot be combined with --build-std=false.
+Uses -Cpanic=immediate-abort where rustc has it, and the panic_immediate_abort std
+feature on older nightlies. Stack traces will not be available."
     )]
     pub panic_immediate_abort: bool,
 
@@ -804,14 +1631,34 @@ Requires nightly and implies --build-std. Stack traces will not be available."
     #[arg(long, value_name = "MODE", hide = true, help_heading = "Build Options")]
     pub location_detail: Option<String>,
 
+    /// CPU baseline for rustc and C/C++ code
+    #[arg(
+        long,
+        env = "TARGET_CPU",
+        value_name = "[TARGET=]CPU",
+        value_delimiter = ',',
+        help_heading = "Build Options",
+        long_help = "\
+CPU baseline passed to rustc as '-C target-cpu=<CPU>' and to the C/C++ compiler as -march=/-mcpu=
+(chosen by architecture). The QEMU runner also gets a matching -cpu model when one is known.
+Use 'TARGET=CPU' entries to set it per target; a bare CPU applies to all other targets.
+Example: --target-cpu s390x-unknown-linux-gnu=z14,powerpc64le-unknown-linux-gnu=pwr9"
+    )]
+    pub target_cpu: Vec<String>,
+
     /// Build the standard library from source
     #[arg(long, value_parser = parse_build_std, env = "BUILD_STD",
           value_name = "CRATES", help_heading = "Build Options",
-          num_args = 0..=1, default_missing_value = "true",
+          num_args = 0..=1, default_value = "", hide_default_value = true,
+          default_missing_value = "true",
           long_help = "\
 Build the standard library from source (requires nightly). Without arguments, builds 'std'.
-Use 'true' for full std or specify crates like 'core,alloc'. Required for unsupported targets or panic=abort.")]
-    pub build_std: Option<String>,
+Use 'true' for full std or specify crates like 'core,alloc'. Required for unsupported targets or panic=abort.
+Use 'false' to disable build-std, including the automatic enable for targets without prebuilt std.
+Use 'auto' to pick the crates per target: 'core,alloc' for bare-metal targets whose dependencies
+do not enable std, the full set otherwise. Append ';TARGET_GLOB=>CRATES' entries to override it.
+Example: --build-std 'auto;thumbv6m-*=>core'")]
+    pub build_std: BuildStd,
 
     /// Features to enable when building std
     #[arg(
@@ -867,7 +1714,8 @@ Valid: true, macro, diagnostics, object, all, none (default: false)"
           env = "VERBOSE_LEVEL", conflicts_with = "quiet",
           help_heading = "Output Options",
           long_help = "\
-Use verbose output. -v=commands/warnings, -vv=+deps/build scripts, -vvv=max verbosity")]
+Use verbose output. -v=commands/warnings, -vv=+deps/build scripts, -vvv=max verbosity. \
+Any -v also prints the environment and command cargo-cross runs for each target")]
     pub verbose_level: u8,
 
     /// Do not print cargo log messages
@@ -878,7 +1726,8 @@ Use verbose output. -v=commands/warnings, -vv=+deps/build scripts, -vvv=max verb
         conflicts_with = "verbose_level",
         help_heading = "Output Options",
         long_help = "\
-Do not print cargo log messages. Shows only errors and warnings."
+Do not print cargo log messages. Shows only errors and warnings, for cargo and for \
+cargo-cross's own messages (which always go to stderr)."
     )]
     pub quiet: bool,
 
@@ -947,6 +1796,15 @@ Prevent network access. Uses locally cached data. Run 'cargo fetch' first if nee
     )]
     pub offline: bool,
 
+    /// Build from a `cargo vendor` directory without network access
+    #[arg(long, env = "VENDORED", value_name = "DIR",
+          value_hint = ValueHint::DirPath, help_heading = "Dependency Options",
+          long_help = "\
+Replace crates.io and the git sources in Cargo.lock with the packages in DIR, the output of
+'cargo vendor', through --config flags, and run cargo with --offline.
+Fails before building if Cargo.lock has a package that is not in DIR.")]
+    pub vendored: Option<PathBuf>,
+
     /// Require Cargo.lock and cache are up to date (implies --locked --offline)
     #[arg(
         long,
@@ -1000,10 +1858,24 @@ execution of this command. See 'cargo report' for more information."
     pub future_incompat_report: bool,
 
     // ===== Additional Cargo Arguments =====
-    /// Additional arguments to pass to cargo
+    /// Additional argument to pass to cargo
     /// Note: `CARGO_ARGS` env var is handled manually in cargo.rs to support shell-style parsing
     #[arg(
-        long,
+        long = "cargo-arg",
+        value_name = "ARG",
+        allow_hyphen_values = true,
+        action = clap::ArgAction::Append,
+        help_heading = "Additional Options",
+        long_help = "\
+Pass one extra argument to cargo, unchanged. Repeat it for several arguments:
+--cargo-arg --config --cargo-arg 'build.jobs = 2'
+CARGO_ARGS is split like a shell command line when no --cargo-arg is given."
+    )]
+    pub cargo_args: Vec<String>,
+
+    /// Deprecated: additional cargo arguments as one shell-style string
+    #[arg(
+        long = "cargo-args",
         visible_alias = "args",
         value_name = "ARGS",
         hide = true,
@@ -1011,7 +1883,7 @@ execution of this command. See 'cargo report' for more information."
         action = clap::ArgAction::Append,
         help_heading = "Additional Options"
     )]
-    pub cargo_args: Vec<String>,
+    pub cargo_args_string: Vec<String>,
 
     /// Unstable (nightly-only) flags to Cargo
     #[arg(short = 'Z', value_name = "FLAG",
@@ -1022,12 +1894,14 @@ Common flags: build-std, unstable-options")]
     pub cargo_z_flags: Vec<String>,
 
     /// Override a Cargo configuration value
-    #[arg(long = "config", value_name = "KEY=VALUE",
+    #[arg(long = "config", value_name = "[TARGET_GLOB=>]KEY=VALUE",
           action = clap::ArgAction::Append, help_heading = "Additional Options",
+          value_parser = parse_cargo_config,
           long_help = "\
 Override a Cargo configuration value. The argument should be in TOML syntax of KEY=VALUE.
-This flag may be specified multiple times.
-Example: --config 'build.jobs=4' --config 'profile.release.lto=true'")]
+Prefix it with TARGET_GLOB=> to apply it only to targets matching the glob.
+This flag may be specified multiple times; later values win.
+Example: --config 'build.jobs=4' --config 'aarch64-*=>build.rustflags=[\"-Ctarget-feature=+lse\"]'")]
     pub cargo_config: Vec<String>,
 
     /// Change to directory before doing anything
@@ -1039,6 +1913,10 @@ Changes the current working directory before executing any specified operations.
 This affects where cargo looks for the project manifest (Cargo.toml) and .cargo/config.toml.")]
     pub cargo_cwd: Option<PathBuf>,
 
+    /// Workspace directories resolved with the same `-C`/`--manifest-path` as the build
+    #[arg(skip)]
+    pub workspace_paths: Option<crate::metadata::WorkspacePaths>,
+
     /// Rust toolchain to use (alternative to +toolchain syntax)
     #[arg(
         long = "toolchain",
@@ -1054,23 +1932,181 @@ syntax (e.g., +nightly). Examples: --toolchain nightly, --toolchain stable, --to
     /// GitHub mirror URL for downloading toolchains
     #[arg(long, visible_alias = "github-proxy-mirror", env = "GH_PROXY", value_name = "URL",
           value_hint = ValueHint::Url, hide_env = true,
+          value_parser = crate::download::parse_github_proxy,
           help_heading = "Additional Options",
           long_help = "\
 Specify a GitHub mirror/proxy URL for downloading cross-compiler toolchains.
-Useful in regions where GitHub access is slow or restricted.
-Example: --github-proxy 'https://ghproxy.com/'")]
+Useful in regions where GitHub access is slow or restricted. Three forms are accepted:
+  'https://ghproxy.com/'               prefix (trailing /): the full GitHub URL is appended
+  'https://mirror.example.com'         host replacement: replaces https://github.com
+  'https://m.example.com/{path}?t=X'   template: {url} is the full URL, {path} the part
+                                       after github.com/")]
     pub github_proxy: Option<String>,
 
-    /// Clean the target directory before building
+    /// Extra root certificates (PEM) trusted for toolchain downloads
+    #[arg(long, env = "CROSS_CA_BUNDLE", value_name = "PEM_PATH",
+          value_hint = ValueHint::FilePath, help_heading = "Additional Options",
+          long_help = "\
+PEM file with extra root certificates to trust for toolchain downloads, for proxies that
+intercept TLS. SSL_CERT_FILE is also honored when it points to a file. Proxies themselves are
+taken from HTTPS_PROXY / HTTP_PROXY / NO_PROXY.")]
+    pub download_ca_cert: Option<PathBuf>,
+
+    /// Disable TLS certificate verification for toolchain downloads (insecure)
+    #[arg(
+        long,
+        env = "DOWNLOAD_INSECURE",
+        help_heading = "Additional Options",
+        long_help = "\
+Disable TLS certificate verification for toolchain downloads. Anyone on the network path can
+then replace the downloaded toolchains; prefer --download-ca-cert and use this only as a last
+resort."
+    )]
+    pub download_insecure: bool,
+
+    /// Scan build script output for wrong-architecture objects when linking fails
+    #[arg(
+        long,
+        env = "DIAGNOSE_LINK_ERRORS",
+        help_heading = "Additional Options",
+        long_help = "\
+When cargo fails with a link error, scan the build script OUT_DIRs referenced by the failing
+linker command for object files and static libraries built for the wrong architecture (usually
+by the host compiler) and name the offending crate and file. Captures cargo's stderr."
+    )]
+    pub diagnose_link_errors: bool,
+
+    /// Re-run cargo up to N times when it fails with a known transient error
+    #[arg(
+        long,
+        env = "RETRY_TRANSIENT",
+        value_name = "N",
+        default_value_t = 0,
+        help_heading = "Additional Options",
+        long_help = "\
+When cargo fails and its stderr matches a known transient failure (sccache server startup
+races, 'could not rename crate metadata', 'Access is denied' on Windows, registry index lock
+timeouts), re-run it for the same target up to N times. Other failures, such as compile
+errors, are never retried. Captures cargo's stderr. Default 0 (no retries)."
+    )]
+    pub retry_transient: u32,
+
+    /// Allow `run` with several targets, one after another with labeled output
+    #[arg(
+        long,
+        env = "SEQUENTIAL_RUNS",
+        help_heading = "Additional Options",
+        long_help = "\
+By default `cargo cross run` refuses more than one target, since running several programs back
+to back is rarely intended. With this flag each target's program runs in turn, every line it
+prints is prefixed with '[<target>]', a failing program does not stop the remaining targets, and
+the summary lists the exit code of each target. Captures the program's stdout and stderr."
+    )]
+    pub sequential_runs: bool,
+
+    /// Print secret-looking environment variable values instead of redacting them
+    #[arg(
+        long,
+        env = "SHOW_SECRETS",
+        help_heading = "Additional Options",
+        long_help = "\
+Print the values of environment variables that look like secrets. By default, variables matching
+*TOKEN*, *SECRET*, *KEY*, *PASSWORD* or AWS_* (plus --secret-env patterns) are shown as '****'.
+Values are always passed to cargo unchanged."
+    )]
+    pub show_secrets: bool,
+
+    /// Additional environment variable name patterns to redact when printing
+    #[arg(
+        long,
+        env = "SECRET_ENV",
+        value_name = "PATTERNS",
+        value_delimiter = ',',
+        help_heading = "Additional Options",
+        long_help = "\
+Comma-separated glob patterns (case-insensitive) of environment variable names whose values are
+redacted in printed output, in addition to the defaults. Example: --secret-env 'SCCACHE_BUCKET,*_CREDENTIALS'"
+    )]
+    pub secret_env: Vec<String>,
+
+    /// Clean the target's build artifacts before building
     #[arg(
         long,
         env = "CLEAN_CACHE",
         help_heading = "Additional Options",
         long_help = "\
-Clean the target directory before building. Equivalent to running 'cargo clean' before the build."
+Clean the target's build artifacts before building ('cargo clean --target <triple>').
+Shared host artifacts (build scripts, proc macros) are kept unless building for the host."
     )]
     pub clean_cache: bool,
 
+    /// Write a script that replays the cargo invocation without cargo-cross
+    #[arg(long, env = "EMIT_REPRO", value_name = "PATH", value_hint = ValueHint::FilePath,
+          help_heading = "Additional Options",
+          long_help = "\
+Write a standalone script to PATH that sets the environment variables cargo-cross passes to
+cargo, changes to the same directory and runs the same cargo command line: a bash script,
+or a PowerShell script on Windows. With several targets, the target is appended to the file
+name. Useful to bisect a problem without cargo-cross in the loop. Variables that look like
+secrets (see --secret-env) and URLs with credentials are not written out: the script reads
+them from the environment it runs in. The script is readable by its owner only; run it
+with bash (or pwsh).")]
+    pub emit_repro: Option<PathBuf>,
+
+    /// Whether --target is passed to cargo for host builds (auto, always, never)
+    #[arg(
+        long,
+        env = "EXPLICIT_TARGET",
+        value_enum,
+        value_name = "WHEN",
+        default_value = "auto",
+        help_heading = "Additional Options",
+        long_help = "\
+Control whether '--target <triple>' is passed to cargo when building for the host.
+  auto    omit it when no target is given or for host-tuple; pass it when the host triple
+          is named explicitly, together with the host-config environment (default)
+  always  pass it for every host build, so artifacts stay under target/<triple>/
+  never   omit it whenever the target is the host, so host and target config are shared"
+    )]
+    pub explicit_target: ExplicitTarget,
+
+    /// Skip a toolchain setup step (repeatable, for debugging)
+    #[arg(
+        long = "skip-step",
+        env = "SKIP_STEP",
+        value_enum,
+        value_name = "STEP",
+        value_delimiter = ',',
+        action = ArgAction::Append,
+        help_heading = "Additional Options",
+        long_help = "\
+Disable one piece of the environment cargo-cross sets up, to find out which one breaks a
+build. Can be repeated or comma-separated.
+  gcc-lib-paths         -L flags for the GCC library directories
+  sysroot-env           BINDGEN_EXTRA_CLANG_ARGS and C_INCLUDE_PATH for the sysroot
+  cross-compile-prefix  the CROSS_COMPILE variable
+  cmake                 CMAKE_GENERATOR and the generated CMake toolchain file
+  runner                the QEMU, Docker, Wine or Rosetta runner
+  rustflags-crt         the crt-static target feature from --crt-static
+  host-config           the CARGO_UNSTABLE_HOST_CONFIG variables for host builds"
+    )]
+    pub skip_step: Vec<SetupStep>,
+
+    /// Set the nightly host-config variables for host builds even on a non-nightly toolchain
+    #[arg(
+        long,
+        env = "HOST_CONFIG",
+        help_heading = "Additional Options",
+        long_help = "\
+When the target is the host and --target is passed, cargo-cross keeps the cross flags away from
+build scripts and proc macros by setting CARGO_UNSTABLE_HOST_CONFIG=true,
+CARGO_UNSTABLE_TARGET_APPLIES_TO_HOST=true and CARGO_TARGET_APPLIES_TO_HOST=false. These are
+nightly-only cargo features, so they are set only when the toolchain is nightly. Pass this flag
+to set them anyway, for example with RUSTC_BOOTSTRAP or a default toolchain that is not detected
+as nightly."
+    )]
+    pub host_config: bool,
+
     /// Disable automatic --target appending for `exec` cargo commands
     #[arg(
         long,
@@ -1083,6 +2119,71 @@ will add '--target <triple>' to the cargo command unless it is already present."
     )]
     pub no_append_target: bool,
 
+    /// Export the cross environment to later GitHub Actions steps
+    #[arg(
+        long,
+        env = "EXPORT_GITHUB_ENV",
+        help_heading = "Additional Options",
+        long_help = "\
+After a successful run, append the target's cross environment to the file named by GITHUB_ENV
+so later workflow steps can use it. PATH is not exported; the toolchain directories cargo-cross
+adds are appended to GITHUB_PATH instead. With several targets, pick one with --export-target."
+    )]
+    pub export_github_env: bool,
+
+    /// Target whose environment --export-github-env exports
+    #[arg(
+        long,
+        env = "EXPORT_TARGET",
+        value_name = "TRIPLE",
+        requires = "export_github_env",
+        help_heading = "Additional Options"
+    )]
+    pub export_target: Option<String>,
+
+    /// Write per-target timing and toolchain size metrics to a file
+    #[arg(
+        long,
+        env = "METRICS_FILE",
+        value_name = "PATH",
+        value_hint = ValueHint::FilePath,
+        help_heading = "Additional Options",
+        long_help = "\
+Write time spent downloading, extracting, in rustup and in cargo, and the size of each
+toolchain directory, per target, to PATH when the run ends. The file uses the
+Prometheus/OpenMetrics text format, or JSON if PATH ends in .json."
+    )]
+    pub metrics_file: Option<PathBuf>,
+
+    /// Append a JSON line summarizing the run to a local file
+    #[arg(
+        long,
+        env = "USAGE_LOG",
+        value_name = "PATH",
+        value_hint = ValueHint::FilePath,
+        help_heading = "Additional Options",
+        long_help = "\
+Append one JSON line per run to PATH: the time, the cargo-cross version, the command, the
+targets, how long the run took, whether it succeeded and the per-target entries of the
+--metrics-file JSON. Concurrent runs may share the file; appends are locked. Nothing is
+sent over the network. A path that cannot be written only gives a warning."
+    )]
+    pub usage_log: Option<PathBuf>,
+
+    /// Write a JSON Lines event stream to a file descriptor or file
+    #[arg(
+        long,
+        env = "EVENT_STREAM",
+        value_name = "fd:N|PATH",
+        help_heading = "Additional Options",
+        long_help = "\
+Write newline-delimited JSON events for IDEs and other wrappers: run and target start and
+finish, download progress, the cargo command and each line cargo writes to stderr.
+fd:N writes to an inherited file descriptor (Unix only), anything else is a file path.
+Every event has an \"event\" field naming its kind; the terminal output is unchanged."
+    )]
+    pub event_stream: Option<String>,
+
     /// Arguments passed through to cargo (after --)
     /// Note: `CARGO_PASSTHROUGH_ARGS` env var is handled manually in cargo.rs to support shell-style parsing
     #[arg(
@@ -1099,6 +2200,91 @@ Examples: test -- --nocapture --test-threads=1, run -- --arg1 --arg2"
 }
 
 impl BuildArgs {
+    /// Whether `step` is not disabled by `--skip-step`
+    #[must_use]
+    pub fn step_enabled(&self, step: SetupStep) -> bool {
+        !self.skip_step.contains(&step)
+    }
+
+    /// Like [`Self::step_enabled`], logging when the step is skipped
+    #[must_use]
+    pub fn run_step(&self, step: SetupStep, target: &str) -> bool {
+        let enabled = self.step_enabled(step);
+        if !enabled {
+            crate::color::log_info(&format!(
+                "Skipping setup step {} for {target} (--skip-step)",
+                step.as_str()
+            ));
+        }
+        enabled
+    }
+
+    /// Resolve the `--target-cpu` value for a target.
+    /// A `TARGET=CPU` entry wins over a bare `CPU` that applies to every target.
+    #[must_use]
+    pub fn target_cpu_for(&self, target: &str) -> Option<&str> {
+        resolve_override(&self.target_cpu, target, None).map(|(cpu, _)| cpu)
+    }
+
+    /// `--skip-tests` filters that apply to a target, in command-line order: bare filters
+    /// and `TARGET=FILTER` entries for this target
+    #[must_use]
+    pub fn skip_tests_for(&self, target: &str) -> Vec<&str> {
+        self.skip_tests
+            .iter()
+            .filter_map(|entry| match split_target_entry(entry) {
+                (Some(entry_target), filter) => (entry_target == target).then_some(filter),
+                (None, filter) => Some(filter),
+            })
+            .filter(|filter| !filter.is_empty())
+            .collect()
+    }
+
+    /// Arguments after `--` for a target: the passthrough arguments as given, then a
+    /// `--skip FILTER` pair for each `--skip-tests` filter of the target
+    #[must_use]
+    pub fn passthrough_args_for(&self, target: &str) -> Vec<String> {
+        let mut passthrough = self.passthrough_args.clone();
+        for filter in self.skip_tests_for(target) {
+            passthrough.push("--skip".to_string());
+            passthrough.push(filter.to_string());
+        }
+        passthrough
+    }
+
+    /// `--config` values that apply to a target, in command-line order so later values win.
+    /// Plain values apply to every target; `GLOB=>KEY=VALUE` only to targets matching `GLOB`.
+    #[must_use]
+    pub fn cargo_config_for(&self, target: &str) -> Vec<&str> {
+        self.cargo_config
+            .iter()
+            .filter_map(|entry| match split_target_config(entry) {
+                (Some(pattern), config) => globset::Glob::new(pattern)
+                    .is_ok_and(|glob| glob.compile_matcher().is_match(target))
+                    .then_some(config),
+                (None, config) => Some(config),
+            })
+            .collect()
+    }
+
+    /// Options in use that only work on a nightly toolchain (or with `RUSTC_BOOTSTRAP`)
+    #[must_use]
+    pub fn nightly_requirements(&self) -> Vec<&'static str> {
+        [
+            (self.build_std.is_enabled(), "--build-std"),
+            (self.panic_immediate_abort, "--panic-immediate-abort"),
+            (self.fmt_debug.is_some(), "--fmt-debug"),
+            (self.location_detail.is_some(), "--location-detail"),
+            (self.no_embed_metadata, "--no-embed-metadata"),
+            (self.build_plan, "--build-plan"),
+            (self.lockfile_path.is_some(), "--lockfile-path"),
+            (!self.cargo_z_flags.is_empty(), "-Z"),
+        ]
+        .into_iter()
+        .filter_map(|(used, flag)| used.then_some(flag))
+        .collect()
+    }
+
     /// Create default `BuildArgs` with proper version defaults
     #[must_use]
     pub fn default_for_host() -> Self {
@@ -1125,18 +2311,193 @@ fn parse_optional_bool(s: &str) -> std::result::Result<bool, String> {
     }
 }
 
-/// Parse build-std value (returns empty string for disabled, which is filtered later)
-fn parse_build_std(s: &str) -> std::result::Result<String, String> {
-    match s.to_lowercase().as_str() {
-        "false" | "0" | "no" | "" => Ok(String::new()), // Will be converted to None later
-        "true" | "1" | "yes" => Ok("true".to_string()),
-        _ => Ok(s.to_string()),
-    }
+/// Where the value chosen by [`resolve_override`] came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverrideSource {
+    /// A `TARGET=VALUE` entry for the target
+    Target,
+    /// A bare `VALUE` that applies to every target
+    Global,
+    /// The platform default
+    Default,
 }
 
-/// Cargo or internal command descriptor
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Command(String);
+/// Split a `[TARGET=]VALUE` entry into its target and value. The part before `=` is only
+/// a target if it holds no path separator or whitespace, so paths containing `=` stay whole.
+#[must_use]
+pub fn split_target_entry(entry: &str) -> (Option<&str>, &str) {
+    match entry.split_once('=') {
+        Some((target, value))
+            if !target.trim().is_empty() && !target.trim().contains(['/', '\\', ' ', '\t']) =>
+        {
+            (Some(target.trim()), value.trim())
+        }
+        _ => (None, entry.trim()),
+    }
+}
+
+/// Resolve a `[TARGET=]VALUE` option for `target`: a `TARGET=VALUE` entry wins over a bare
+/// `VALUE`, which wins over `default`. An empty `TARGET=` entry selects the default for
+/// that target. Later bare values replace earlier ones.
+#[must_use]
+pub fn resolve_override<'a>(
+    entries: &'a [String],
+    target: &str,
+    default: Option<&'a str>,
+) -> Option<(&'a str, OverrideSource)> {
+    let default = default.map(|value| (value, OverrideSource::Default));
+    let mut global = None;
+    for entry in entries {
+        match split_target_entry(entry) {
+            (Some(entry_target), value) if entry_target == target => {
+                return if value.is_empty() {
+                    default
+                } else {
+                    Some((value, OverrideSource::Target))
+                };
+            }
+            (Some(_), _) => {}
+            (None, value) if !value.is_empty() => global = Some((value, OverrideSource::Global)),
+            (None, _) => {}
+        }
+    }
+    global.or(default)
+}
+
+fn parse_crt_static(s: &str) -> std::result::Result<CrtStatic, String> {
+    if s.eq_ignore_ascii_case("auto") {
+        return Ok(CrtStatic::Auto);
+    }
+    match parse_optional_bool(s) {
+        Ok(true) => Ok(CrtStatic::Static),
+        Ok(false) => Ok(CrtStatic::Dynamic),
+        Err(_) => Err(format!("invalid value: {s} (expected true, false or auto)")),
+    }
+}
+
+/// Split a `--config` value into its optional target glob and the Cargo config itself.
+/// The part before `=>` is only a glob if it holds no `=` or quotes, so TOML values
+/// that happen to contain `=>` are left alone.
+#[must_use]
+pub fn split_target_config(value: &str) -> (Option<&str>, &str) {
+    match value.split_once("=>") {
+        Some((pattern, config))
+            if !pattern.trim().is_empty() && !pattern.contains(['=', '"', '\'']) =>
+        {
+            (Some(pattern.trim()), config.trim_start())
+        }
+        _ => (None, value),
+    }
+}
+
+/// Parse a `--use-sysroot-libs` name
+fn parse_sysroot_lib(s: &str) -> std::result::Result<String, String> {
+    let name = s.trim();
+    if crate::env::sysroot_lib(name).is_some() {
+        Ok(name.to_string())
+    } else {
+        let known: Vec<&str> = crate::env::SYSROOT_LIBS
+            .iter()
+            .map(|lib| lib.name)
+            .collect();
+        Err(format!(
+            "unknown library '{name}', expected one of: {}",
+            known.join(", ")
+        ))
+    }
+}
+
+/// Validate a `--collect-glob` pattern
+fn parse_collect_glob(s: &str) -> std::result::Result<String, String> {
+    crate::artifacts::collect_glob(s)
+        .map(|_| s.to_string())
+        .map_err(|e| match e {
+            CrossError::InvalidArgument(message) => message,
+            e => e.to_string(),
+        })
+}
+
+/// Parse a `--config` value, validating the target glob if one is given
+fn parse_cargo_config(s: &str) -> std::result::Result<String, String> {
+    if let (Some(pattern), config) = split_target_config(s) {
+        globset::Glob::new(pattern).map_err(|e| format!("invalid target glob '{pattern}': {e}"))?;
+        if config.is_empty() {
+            return Err(format!("missing KEY=VALUE after '{pattern}=>'"));
+        }
+    }
+    Ok(s.to_string())
+}
+
+/// Build-std selection from `--build-std` / `BUILD_STD`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BuildStd {
+    /// Not specified; build-std may still be auto-enabled for targets without prebuilt std
+    #[default]
+    Unset,
+    /// Explicitly disabled ("false", "0", "no"); suppresses auto-enable as well
+    Disabled,
+    /// Enabled with "true" (full std) or a comma-separated crate list
+    Enabled(String),
+    /// Crates chosen per target ("auto"), with `TARGET_GLOB=>CRATES` overrides
+    Auto(Vec<(String, String)>),
+}
+
+impl BuildStd {
+    /// Returns true if build-std was explicitly requested
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        matches!(self, Self::Enabled(_) | Self::Auto(_))
+    }
+
+    /// Returns true if build-std was explicitly disabled
+    #[must_use]
+    pub const fn is_disabled(&self) -> bool {
+        matches!(self, Self::Disabled)
+    }
+
+    /// Returns the raw crate list ("true" or e.g. "core,alloc") when enabled
+    #[must_use]
+    pub fn crates(&self) -> Option<&str> {
+        match self {
+            Self::Enabled(crates) => Some(crates),
+            Self::Unset | Self::Disabled | Self::Auto(_) => None,
+        }
+    }
+}
+
+/// Parse build-std value (empty means unset, "false"/"0"/"no" means explicitly disabled,
+/// "auto" with optional `;TARGET_GLOB=>CRATES` overrides picks crates per target)
+fn parse_build_std(s: &str) -> std::result::Result<BuildStd, String> {
+    let mut entries = s.split(';');
+    let first = entries.next().unwrap_or_default().trim();
+    if first.eq_ignore_ascii_case("auto") {
+        let overrides = entries
+            .filter(|entry| !entry.trim().is_empty())
+            .map(|entry| match split_target_config(entry.trim()) {
+                (Some(pattern), crates) if !crates.trim().is_empty() => {
+                    globset::Glob::new(pattern)
+                        .map_err(|e| format!("invalid target glob '{pattern}': {e}"))?;
+                    Ok((pattern.to_string(), crates.trim().to_string()))
+                }
+                _ => Err(format!(
+                    "invalid --build-std auto override '{}', expected TARGET_GLOB=>CRATES",
+                    entry.trim()
+                )),
+            })
+            .collect::<std::result::Result<_, _>>()?;
+        return Ok(BuildStd::Auto(overrides));
+    }
+    match s.to_lowercase().as_str() {
+        "" => Ok(BuildStd::Unset),
+        "false" | "0" | "no" => Ok(BuildStd::Disabled),
+        "true" | "1" | "yes" => Ok(BuildStd::Enabled("true".to_string())),
+        _ => Ok(BuildStd::Enabled(s.to_string())),
+    }
+}
+
+/// Cargo or internal command descriptor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command(String);
 
 impl Default for Command {
     fn default() -> Self {
@@ -1190,6 +2551,31 @@ impl Command {
         Self::new("exec")
     }
 
+    #[must_use]
+    pub fn sync() -> Self {
+        Self::new("sync")
+    }
+
+    #[must_use]
+    pub fn cache_key() -> Self {
+        Self::new("cache-key")
+    }
+
+    #[must_use]
+    pub fn query() -> Self {
+        Self::new("query")
+    }
+
+    #[must_use]
+    pub fn clean() -> Self {
+        Self::new("clean")
+    }
+
+    #[must_use]
+    pub fn bloat() -> Self {
+        Self::new("bloat")
+    }
+
     #[must_use]
     pub fn as_str(&self) -> &str {
         &self.0
@@ -1197,7 +2583,104 @@ impl Command {
 
     #[must_use]
     pub fn needs_runner(&self) -> bool {
-        matches!(self.as_str(), "run" | "test" | "bench")
+        matches!(self.as_str(), "run" | "test" | "bench" | "sync")
+    }
+}
+
+/// Whether cargo gets `--target` for host builds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExplicitTarget {
+    /// Omit it when no target is given or for host-tuple, pass it for an explicit host triple
+    #[default]
+    Auto,
+    /// Always pass it, keeping host artifacts under target/<triple>/
+    Always,
+    /// Never pass it when building for the host
+    Never,
+}
+
+/// How `--crt-static` links the C runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrtStatic {
+    /// `+crt-static`
+    Static,
+    /// `-crt-static`
+    Dynamic,
+    /// The target's default, passed explicitly
+    Auto,
+}
+
+/// Image format `--objcopy-format` writes for bare-metal executables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ObjcopyFormat {
+    /// Raw memory image, written as `<file>.bin`
+    Binary,
+    /// Intel HEX, written as `<file>.hex`
+    Ihex,
+}
+
+impl ObjcopyFormat {
+    /// Name objcopy knows the format by (`-O <name>`)
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Binary => "binary",
+            Self::Ihex => "ihex",
+        }
+    }
+
+    /// Extension added to the image file name
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Binary => "bin",
+            Self::Ihex => "hex",
+        }
+    }
+}
+
+/// Runner backend for Linux targets the host executes natively
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RunnerBackend {
+    /// QEMU (Docker with QEMU on macOS)
+    #[default]
+    Auto,
+    /// The sysroot's dynamic linker in a user-namespace chroot or proot
+    Chroot,
+}
+
+/// Toolchain setup steps that `--skip-step` can disable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SetupStep {
+    /// Library search paths from GCC passed to rustc
+    GccLibPaths,
+    /// `BINDGEN_EXTRA_CLANG_ARGS` and `C_INCLUDE_PATH` for the sysroot
+    SysrootEnv,
+    /// The `CROSS_COMPILE` prefix
+    CrossCompilePrefix,
+    /// CMake generator and toolchain file
+    Cmake,
+    /// Runner for executing target binaries
+    Runner,
+    /// The crt-static target feature
+    RustflagsCrt,
+    /// Host-config environment for builds targeting the host
+    HostConfig,
+}
+
+impl SetupStep {
+    /// Name as given to `--skip-step`
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::GccLibPaths => "gcc-lib-paths",
+            Self::SysrootEnv => "sysroot-env",
+            Self::CrossCompilePrefix => "cross-compile-prefix",
+            Self::Cmake => "cmake",
+            Self::Runner => "runner",
+            Self::RustflagsCrt => "rustflags-crt",
+            Self::HostConfig => "host-config",
+        }
     }
 }
 
@@ -1220,6 +2703,57 @@ pub struct Args {
     pub build: BuildArgs,
 }
 
+/// Arguments for unit tests: a plain `build` with no targets, keeping
+/// toolchains under `cross_compiler_dir`
+#[cfg(test)]
+pub(crate) fn test_args(cross_compiler_dir: PathBuf, build: BuildArgs) -> Args {
+    Args {
+        cross_compiler_dir,
+        ..test_target_args("", &[], build)
+    }
+}
+
+/// Arguments for tests: a plain `build` of `targets`, keeping toolchains in the
+/// [`test_toolchain_dir`] called `name`. Public for the integration tests.
+#[doc(hidden)]
+#[must_use]
+pub fn test_target_args(name: &str, targets: &[&str], build: BuildArgs) -> Args {
+    Args {
+        toolchain: None,
+        command: Command::build(),
+        targets: targets.iter().map(ToString::to_string).collect(),
+        no_cargo_target: false,
+        cross_make_version: "test".to_string(),
+        cross_compiler_dir: test_toolchain_dir(name),
+        build,
+    }
+}
+
+/// A per-process toolchain directory under the system temp dir, so tests
+/// never write into the working tree
+#[doc(hidden)]
+#[must_use]
+pub fn test_toolchain_dir(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("cargo-cross-{name}-{}", std::process::id()))
+        .join("toolchains")
+}
+
+impl BuildArgs {
+    /// Build arguments with every option at its CLI default.
+    /// Unlike parsing the command line, environment variables are not read.
+    #[must_use]
+    pub fn cli_defaults() -> Self {
+        let matches = ExternalCargoCli::command()
+            .mut_args(|arg| arg.env(None))
+            .try_get_matches_from(["cargo-cross"])
+            .expect("BuildArgs has no required arguments");
+        ExternalCargoCli::from_arg_matches(&matches)
+            .expect("defaults always parse")
+            .build
+    }
+}
+
 impl std::ops::Deref for Args {
     type Target = BuildArgs;
 
@@ -1241,7 +2775,15 @@ impl Args {
             .cross_compiler_dir
             .clone()
             .unwrap_or_else(|| std::env::temp_dir().join("rust-cross-compiler"));
-        let targets = expand_target_list(&b.targets)?;
+        // Toolchain paths end up in environment variables and compiler flags, which are strings
+        if cross_compiler_dir.to_str().is_none() {
+            return Err(CrossError::InvalidArgument(format!(
+                "cross-compiler directory must be valid UTF-8: {}\n\
+                 Use --cross-compiler-dir to choose a different location",
+                cross_compiler_dir.display()
+            )));
+        }
+        let targets = expand_target_list(&b.targets, toolchain.as_deref())?;
 
         Ok(Self {
             toolchain,
@@ -1253,6 +2795,84 @@ impl Args {
             build: b,
         })
     }
+
+    /// Toolchain cargo runs with: `+toolchain` / `--toolchain`, or else `RUSTUP_TOOLCHAIN`.
+    /// `None` for the rustup default toolchain.
+    #[must_use]
+    pub fn selected_toolchain(&self) -> Option<String> {
+        self.toolchain.clone().or_else(|| {
+            std::env::var("RUSTUP_TOOLCHAIN")
+                .ok()
+                .filter(|toolchain| !toolchain.is_empty())
+        })
+    }
+
+    /// Whether [`Self::selected_toolchain`] is a nightly channel, `None` if it is the default
+    #[must_use]
+    pub fn toolchain_is_nightly(&self) -> Option<bool> {
+        self.selected_toolchain()
+            .map(|toolchain| toolchain.starts_with("nightly"))
+    }
+
+    /// Target whose environment `--export-github-env` exports, if the flag is set.
+    /// Without `--export-target` the run must have exactly one target.
+    pub fn github_export_target(&self) -> Result<Option<&str>> {
+        if !self.export_github_env {
+            return Ok(None);
+        }
+        match self.export_target.as_deref() {
+            Some(target) if self.targets.iter().any(|t| t == target) => Ok(Some(target)),
+            Some(target) => Err(CrossError::InvalidArgument(format!(
+                "--export-target {target} is not one of the selected targets"
+            ))),
+            None => match self.targets.as_slice() {
+                [target] => Ok(Some(target)),
+                _ => Err(CrossError::InvalidArgument(
+                    "--export-github-env needs exactly one target or --export-target <TRIPLE>"
+                        .to_string(),
+                )),
+            },
+        }
+    }
+
+    /// Whether toolchain setup is skipped: `--no-toolchain-setup`, or `--no-link-toolchain`
+    /// on `check` once the crate graph was found not to compile C
+    #[must_use]
+    pub fn skips_toolchain_setup(&self) -> bool {
+        self.no_toolchain_setup || (self.no_link_toolchain && self.command.as_str() == "check")
+    }
+
+    /// Whether cargo runs without `--target` for `target` (a triple or `host-tuple`)
+    /// on a host whose triple is `host_triple` (`--explicit-target`)
+    #[must_use]
+    pub fn skip_target_arg(&self, target: &str, host_triple: &str) -> bool {
+        match self.explicit_target {
+            ExplicitTarget::Auto => target == "host-tuple",
+            ExplicitTarget::Always => false,
+            ExplicitTarget::Never => target == "host-tuple" || target == host_triple,
+        }
+    }
+
+    /// Whether a runner is configured: run/test/bench, or any command with
+    /// `--runner-for-build-scripts`
+    #[must_use]
+    pub fn needs_runner(&self) -> bool {
+        self.command.needs_runner() || self.runner_for_build_scripts
+    }
+
+    /// Whether a missing runner is an error rather than a warning.
+    /// test and bench require one unless `--allow-no-runner` is given or nothing will run (`--no-run`).
+    #[must_use]
+    pub fn runner_required(&self) -> bool {
+        if self.allow_no_runner {
+            return false;
+        }
+        let no_run = self
+            .cargo_args
+            .iter()
+            .any(|arg| arg.split_whitespace().any(|word| word == "--no-run"));
+        self.require_runner || (matches!(self.command.as_str(), "test" | "bench") && !no_run)
+    }
 }
 
 /// Result of parsing CLI arguments
@@ -1263,29 +2883,79 @@ pub enum ParseResult {
     Setup(Box<SetupArgs>),
     /// Execute an arbitrary command after environment setup
     Exec(Box<ExecArgs>),
+    /// Pre-install or verify toolchains and targets
+    Sync(Box<SyncArgs>),
+    /// Print the execution plan of a cargo command
+    Plan(Box<PlanArgs>),
+    /// Print the cache key of the cross compiler directory
+    CacheKey(Box<Args>),
+    /// Print the tool names for each target
+    QueryTools(Box<QueryToolsArgs>),
+    /// Show the size breakdown of a target's binary
+    Bloat(Box<BloatArgs>),
+    /// Remove generated files from the cross compiler directory
+    CleanGenerated(Box<Args>),
     /// Show targets command
-    ShowTargets(OutputFormat),
+    ShowTargets(TargetsArgs),
     /// Show version
     ShowVersion,
 }
 
+/// Names of empty environment variables removed by `sanitize_clap_env`
+static CLEARED_CLAP_ENV: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Environment variable names read by clap (`env = "VAR"`) across all subcommands
+fn clap_env_names() -> BTreeSet<String> {
+    fn collect(cmd: &clap::Command, names: &mut BTreeSet<String>) {
+        names.extend(
+            cmd.get_arguments()
+                .filter_map(clap::Arg::get_env)
+                .map(|name| name.to_string_lossy().into_owned()),
+        );
+        for sub in cmd.get_subcommands() {
+            collect(sub, names);
+        }
+    }
+
+    let mut names = BTreeSet::new();
+    collect(&Cli::command(), &mut names);
+    collect(&ExternalCargoCli::command(), &mut names);
+    names
+}
+
 /// Remove empty environment variables that clap would incorrectly treat as having values.
 /// Clap's `env = "VAR"` attribute treats empty strings as valid values, which causes
 /// parsing errors for `PathBuf` and other types that don't accept empty strings.
+/// Only variables clap reads are removed; they are recorded so the cargo child process
+/// can get them back (see [`cleared_clap_env`]).
 fn sanitize_clap_env() {
-    let empty_vars: Vec<_> = std::env::vars()
-        .filter(|(_, v)| v.is_empty())
-        .map(|(k, _)| k)
-        .collect();
-
-    for var in empty_vars {
-        std::env::remove_var(&var);
+    let mut cleared = CLEARED_CLAP_ENV
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    for name in clap_env_names() {
+        if std::env::var_os(&name).is_some_and(|value| value.is_empty()) {
+            std::env::remove_var(&name);
+            if !cleared.contains(&name) {
+                cleared.push(name);
+            }
+        }
     }
 }
 
+/// Empty environment variables removed before CLI parsing, to be restored (as empty)
+/// in child processes so tools that distinguish "empty" from "unset" see the original value
+#[must_use]
+pub fn cleared_clap_env() -> Vec<String> {
+    CLEARED_CLAP_ENV
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone()
+}
+
 /// Parse command-line arguments
 pub fn parse_args() -> Result<ParseResult> {
     let args: Vec<String> = std::env::args().collect();
+    crate::compat::apply_cross_rs_env(args.first().map(String::as_str));
     parse_args_from(args)
 }
 
@@ -1310,11 +2980,7 @@ pub fn parse_args_from(args: Vec<String>) -> Result<ParseResult> {
     // When invoked as `cargo cross`, cargo sets the CARGO env var and passes
     // args as ["cargo-cross", "cross", ...]. We need to skip both.
     // When invoked directly as `cargo-cross`, only skip the program name.
-    let is_cargo_subcommand = env::var("CARGO").is_ok()
-        && env::var("CARGO_HOME").is_ok()
-        && args.get(1).map(String::as_str) == Some(SUBCOMMAND);
-
-    let skip_count = if is_cargo_subcommand { 2 } else { 1 };
+    let (_, skip_count) = invocation(&args, env::var_os("CARGO").is_some());
     let mut args: Vec<String> = args.iter().skip(skip_count).cloned().collect();
 
     // Extract +toolchain from args (can appear at the beginning)
@@ -1445,7 +3111,7 @@ EXAMPLES:\n    \
         });
     }
 
-    cmd
+    with_program_name(cmd.bin_name(prog), prog)
 }
 
 fn should_parse_as_external_cargo_command(command_name: &str) -> bool {
@@ -1727,7 +3393,7 @@ fn should_pair_unknown_value(current: &str, next: Option<&str>) -> bool {
 }
 
 fn push_cargo_arg(processed: &mut Vec<String>, value: &str) {
-    processed.push("--cargo-args".to_string());
+    processed.push("--cargo-arg".to_string());
     processed.push(value.to_string());
 }
 
@@ -1738,7 +3404,7 @@ fn build_external_cargo_command_with_dynamic_help(command_name: &str) -> clap::C
 cross-compilation environment.\n\n\
 EXAMPLES:\n    \
 {prog} {command_name} -t x86_64-unknown-linux-musl\n    \
-{prog} {command_name} -t aarch64-unknown-linux-gnu -- --help"
+{prog} {command_name} -t aarch64-unknown-linux-gnu --release"
     );
 
     ExternalCargoCli::command()
@@ -1779,6 +3445,7 @@ fn process_cli(cli: Cli, toolchain: Option<String>) -> Result<ParseResult> {
             Ok(ParseResult::Setup(Box::new(SetupArgs {
                 args,
                 format: setup.format,
+                write_cargo_config: setup.write_cargo_config,
             })))
         }
         CliCommand::Exec(exec) => {
@@ -1793,58 +3460,280 @@ fn process_cli(cli: Cli, toolchain: Option<String>) -> Result<ParseResult> {
             let args = finalize_args(build, Command::exec(), toolchain)?;
             Ok(ParseResult::Exec(Box::new(ExecArgs { args, command })))
         }
-        CliCommand::Targets(args) => Ok(ParseResult::ShowTargets(args.format)),
+        CliCommand::Sync(sync) => {
+            let args = finalize_args(sync.build, Command::sync(), toolchain)?;
+            Ok(ParseResult::Sync(Box::new(SyncArgs {
+                args,
+                check: sync.check,
+            })))
+        }
+        CliCommand::Plan(plan) => parse_plan_args(plan, toolchain),
+        CliCommand::CacheKey(cache_key) => {
+            let args = finalize_args(cache_key.build, Command::cache_key(), toolchain)?;
+            Ok(ParseResult::CacheKey(Box::new(args)))
+        }
+        CliCommand::Query(query) => match query.query {
+            QueryCommand::Tools(tools) => {
+                let args = finalize_args(tools.build, Command::query(), toolchain)?;
+                Ok(ParseResult::QueryTools(Box::new(QueryToolsArgs {
+                    args,
+                    format: tools.format,
+                })))
+            }
+        },
+        CliCommand::Bloat(bloat) => {
+            let args = finalize_args(bloat.build, Command::bloat(), toolchain)?;
+            if args.targets.len() != 1 {
+                return Err(CrossError::InvalidArgument(format!(
+                    "`{} bloat` analyzes one target at a time, got {} ({})",
+                    program_name(),
+                    args.targets.len(),
+                    args.targets.join(", ")
+                )));
+            }
+            Ok(ParseResult::Bloat(Box::new(BloatArgs {
+                args,
+                options: crate::bloat::BloatOptions {
+                    crates: bloat.crates,
+                    filter: bloat.filter,
+                    top: bloat.top,
+                },
+            })))
+        }
+        CliCommand::Clean(clean) => {
+            let args = finalize_args(clean.build, Command::clean(), toolchain)?;
+            Ok(ParseResult::CleanGenerated(Box::new(args)))
+        }
+        CliCommand::Targets(mut args) => {
+            args.toolchain = toolchain;
+            Ok(ParseResult::ShowTargets(args))
+        }
         CliCommand::Version => Ok(ParseResult::ShowVersion),
     }
 }
 
+fn parse_plan_args(plan: PlanCliArgs, toolchain: Option<String>) -> Result<ParseResult> {
+    let canonical_name = canonical_cargo_command_name(&plan.command)
+        .or_else(|| {
+            is_supported_external_cargo_command(&plan.command).then_some(plan.command.as_str())
+        })
+        .ok_or_else(|| {
+            CrossError::InvalidArgument(format!(
+                "plan expects a cargo command such as build, check or test, got `{}`",
+                plan.command
+            ))
+        })?;
+
+    let mut args = plan.args.clone();
+    let format = take_plan_format(&mut args)?.unwrap_or(plan.format);
+    match parse_cargo_command_args(&plan.command, canonical_name, args, toolchain)? {
+        ParseResult::Build(args) => Ok(ParseResult::Plan(Box::new(PlanArgs {
+            args: *args,
+            format,
+        }))),
+        _ => unreachable!("cargo commands always parse to a build"),
+    }
+}
+
+/// Remove `--format`/`-f` given after the planned command so it is not forwarded to cargo
+fn take_plan_format(args: &mut Vec<String>) -> Result<Option<PlanOutputFormat>> {
+    let mut format = None;
+    let mut index = 0;
+    while index < args.len() {
+        let arg = &args[index];
+        if arg == "--" {
+            break;
+        }
+        let value = if arg == "--format" || arg == "-f" {
+            if index + 1 >= args.len() {
+                return Err(CrossError::InvalidArgument(format!(
+                    "{arg} requires a value (json, table)"
+                )));
+            }
+            let value = args.remove(index + 1);
+            args.remove(index);
+            value
+        } else if let Some(value) = arg.strip_prefix("--format=") {
+            let value = value.to_string();
+            args.remove(index);
+            value
+        } else {
+            index += 1;
+            continue;
+        };
+        format = Some(
+            <PlanOutputFormat as clap::ValueEnum>::from_str(&value, true).map_err(|_| {
+                CrossError::InvalidArgument(format!(
+                    "invalid plan format `{value}` (expected json or table)"
+                ))
+            })?,
+        );
+    }
+    Ok(format)
+}
+
 /// Check if a string is a glob pattern (contains *, ?, or [)
 fn is_glob_pattern(s: &str) -> bool {
     s.contains('*') || s.contains('?') || s.contains('[')
 }
 
-/// Validate that a target triple only contains valid characters (a-z, 0-9, -, _)
-fn validate_target_triple(target: &str) -> Result<()> {
-    for c in target.chars() {
-        if !c.is_ascii_lowercase() && !c.is_ascii_digit() && c != '-' && c != '_' {
-            return Err(CrossError::InvalidTargetTriple {
-                target: target.to_string(),
-                char: c,
-            });
-        }
+/// Whether `c` may appear in a target triple: a-z, 0-9, `.` (`thumbv8m.main-none-eabi`),
+/// `-` and `_` (`x86_64`)
+const fn is_target_triple_char(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '-' | '_')
+}
+
+/// Validate a literal target triple before it reaches environment variable names and
+/// download URLs. Uppercase letters are lowercased with a note, since triples are never
+/// uppercase; any other character outside the allowed set is an error.
+fn normalize_target_triple(target: &str) -> Result<String> {
+    if let Some(c) = target
+        .chars()
+        .find(|c| !is_target_triple_char(c.to_ascii_lowercase()))
+    {
+        return Err(CrossError::InvalidTargetTriple {
+            target: target.to_string(),
+            char: c,
+        });
     }
-    Ok(())
+    if target.chars().any(|c| c.is_ascii_uppercase()) {
+        let lowercase = target.to_ascii_lowercase();
+        crate::color::log_info(&format!(
+            "Using target {lowercase} for {target}: target triples are lowercase"
+        ));
+        return Ok(lowercase);
+    }
+    Ok(target.to_string())
+}
+
+/// Warning for a target given by a name Rust has since replaced
+fn renamed_target_warning(renamed: &config::RenamedTarget) -> String {
+    format!(
+        "Target {} was renamed to {} in Rust {}; building {} instead. Update the target list, \
+         older toolchains only know the former name",
+        renamed.old, renamed.new, renamed.since, renamed.new
+    )
+}
+
+/// Current name of `target`, warning once when a former name was given. A toolchain from
+/// before the rename still knows the former name, so it is kept there.
+fn current_target_name(target: &str, toolchain: Option<&str>) -> String {
+    let Some(renamed) = config::renamed_target(target) else {
+        return target.to_string();
+    };
+    if toolchain_lists_target(toolchain, target) {
+        crate::color::log_debug(&format!(
+            "Keeping target {target}: the toolchain predates its rename to {}",
+            renamed.new
+        ));
+        return target.to_string();
+    }
+    crate::color::log_warning_once(
+        &format!("renamed-target-{}", renamed.old),
+        &renamed_target_warning(renamed),
+    );
+    renamed.new.to_string()
+}
+
+/// Whether `rustc --print target-list` of `toolchain` (the default one for `None`) lists
+/// `target`; false when rustc cannot be asked
+fn toolchain_lists_target(toolchain: Option<&str>, target: &str) -> bool {
+    let mut cmd = std::process::Command::new("rustc");
+    if let Some(toolchain) = toolchain {
+        cmd.arg(format!("+{toolchain}"));
+    }
+    cmd.arg("--print=target-list")
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .is_some_and(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .any(|line| line.trim() == target)
+        })
+}
+
+/// Entries of one `--target`/`TARGETS` value. Besides comma and newline separated lists,
+/// this takes what CI systems pass: a JSON array (`toJSON` in GitHub Actions) and YAML
+/// block scalars with `- ` bullets, and drops quotes around single entries.
+fn split_target_value(value: &str) -> Result<Vec<String>> {
+    let trimmed = value.trim();
+    // A glob class like `[ai]*-linux-musl` also starts with `[`, but is never empty and
+    // never has quotes
+    let empty_array = trimmed
+        .strip_prefix('[')
+        .and_then(|inner| inner.strip_suffix(']'))
+        .is_some_and(|inner| inner.trim().is_empty());
+    if empty_array || (trimmed.starts_with('[') && trimmed.contains('"')) {
+        let invalid = |reason: String| {
+            CrossError::InvalidArgument(format!(
+                "Target list {trimmed} looks like a JSON array but {reason}"
+            ))
+        };
+        let parsed: serde_json::Value =
+            serde_json::from_str(trimmed).map_err(|e| invalid(format!("does not parse: {e}")))?;
+        let entries = parsed
+            .as_array()
+            .ok_or_else(|| invalid("is not an array".to_string()))?;
+        return entries
+            .iter()
+            .map(|entry| {
+                entry
+                    .as_str()
+                    .map(|entry| entry.trim().to_string())
+                    .ok_or_else(|| invalid(format!("holds {entry}, which is not a string")))
+            })
+            .filter(|entry| entry.as_ref().map_or(true, |entry| !entry.is_empty()))
+            .collect();
+    }
+    Ok(trimmed
+        .split([',', '\n'])
+        .map(|part| {
+            let part = part.trim();
+            let part = part
+                .strip_prefix("- ")
+                .or_else(|| part.strip_prefix("-\t"))
+                .map_or(part, str::trim_start);
+            ['"', '\'']
+                .iter()
+                .find_map(|quote| {
+                    part.strip_prefix(*quote)
+                        .and_then(|inner| inner.strip_suffix(*quote))
+                })
+                .unwrap_or(part)
+                .trim()
+                .to_string()
+        })
+        .filter(|part| !part.is_empty() && part != "-")
+        .collect())
 }
 
 /// Expand target list, handling glob patterns
-fn expand_target_list(targets: &[String]) -> Result<Vec<String>> {
+fn expand_target_list(targets: &[String], toolchain: Option<&str>) -> Result<Vec<String>> {
     let mut result = Vec::new();
     for target in targets {
-        // Split by comma or newline to support multiple delimiters
-        for part in target.split([',', '\n']) {
-            let part = part.trim();
-            if part.is_empty() {
+        for part in split_target_value(target)? {
+            let part = part.as_str();
+            if !is_glob_pattern(part) {
+                // Literal triples are validated, then kept whether or not they are built in
+                let target = current_target_name(&normalize_target_triple(part)?, toolchain);
+                if !result.contains(&target) {
+                    result.push(target);
+                }
                 continue;
             }
             let expanded = config::expand_targets(part);
             if expanded.is_empty() {
-                // If it was a glob pattern that matched nothing, error
-                if is_glob_pattern(part) {
-                    return Err(CrossError::NoMatchingTargets {
-                        pattern: part.to_string(),
-                    });
-                }
-                // Not a glob pattern, validate and use as-is
-                validate_target_triple(part)?;
-                if !result.contains(&part.to_string()) {
-                    result.push(part.to_string());
-                }
-            } else {
-                for t in expanded {
-                    let t = t.to_string();
-                    if !result.contains(&t) {
-                        result.push(t);
-                    }
+                return Err(CrossError::NoMatchingTargets {
+                    pattern: part.to_string(),
+                });
+            }
+            for t in expanded {
+                let t = t.to_string();
+                if !result.contains(&t) {
+                    result.push(t);
                 }
             }
         }
@@ -1862,16 +3751,10 @@ fn finalize_args(
         build_args.profile = "release".to_string();
     }
 
-    // Handle build_std: empty string means disabled (from env var "false")
-    if build_args
-        .build_std
-        .as_ref()
-        .is_some_and(std::string::String::is_empty)
-    {
-        build_args.build_std = None;
-    }
-
+    split_cargo_args_strings(&mut build_args)?;
     populate_env_arg_fallbacks(&mut build_args);
+    validate_command_options(&build_args, &command)?;
+    apply_panic_immediate_abort(&mut build_args)?;
 
     // Merge toolchain: +toolchain syntax takes precedence over --toolchain option
     let final_toolchain = toolchain.or_else(|| build_args.toolchain_option.clone());
@@ -1880,19 +3763,94 @@ fn finalize_args(
 
     // Validate versions
     validate_versions(&args)?;
+    normalize_ndk_version(&mut args)?;
 
     // Handle empty targets - default to host
     if args.targets.is_empty() {
         let host = config::HostPlatform::detect();
         args.targets.push(host.triple);
         args.no_toolchain_setup = true;
-        args.no_cargo_target = true;
+        args.no_cargo_target = args.explicit_target != ExplicitTarget::Always;
     }
     // Note: "host-tuple" is handled dynamically in execute_target
+    if let Some(ref path) = args.target_policy {
+        let host = config::HostPlatform::detect();
+        let targets: Vec<String> = args
+            .targets
+            .iter()
+            .map(|target| {
+                if target == "host-tuple" {
+                    host.triple.clone()
+                } else {
+                    target.clone()
+                }
+            })
+            .collect();
+        TargetPolicy::load(path)?.enforce(&targets, args.policy_warn_only)?;
+    }
+    validate_run_targets(&args)?;
 
     Ok(args)
 }
 
+/// `run` with several targets needs `--sequential-runs`
+fn validate_run_targets(args: &Args) -> Result<()> {
+    if args.command.as_str() != "run" || args.targets.len() < 2 || args.sequential_runs {
+        return Ok(());
+    }
+    Err(CrossError::InvalidArgument(format!(
+        "`{} run` got {} targets ({}); run one target at a time, pick the binary with \
+         --bin if that is what you meant, or pass --sequential-runs to run them one after another \
+         with labeled output",
+        program_name(),
+        args.targets.len(),
+        args.targets.join(", ")
+    )))
+}
+
+/// `--panic-immediate-abort` cannot link against the prebuilt std (compiled with unwinding),
+/// so it turns on build-std. Whether std also needs its `panic_immediate_abort` feature
+/// depends on the toolchain and is decided when cargo runs.
+fn apply_panic_immediate_abort(build_args: &mut BuildArgs) -> Result<()> {
+    if !build_args.panic_immediate_abort {
+        return Ok(());
+    }
+
+    match build_args.build_std {
+        BuildStd::Disabled => {
+            return Err(CrossError::InvalidArgument(
+                "--panic-immediate-abort requires build-std, but it was disabled \
+                 (--build-std=false / BUILD_STD=false)\n\
+                 The prebuilt standard library is compiled with panic=unwind and fails to link \
+                 with -Cpanic=immediate-abort. Remove BUILD_STD=false or drop --panic-immediate-abort"
+                    .to_string(),
+            ));
+        }
+        BuildStd::Unset => build_args.build_std = BuildStd::Enabled("true".to_string()),
+        BuildStd::Enabled(_) | BuildStd::Auto(_) => {}
+    }
+    Ok(())
+}
+
+/// Split the deprecated `--cargo-args "<ARGS>"` strings like a shell command line and
+/// add them before the `--cargo-arg` values
+fn split_cargo_args_strings(build_args: &mut BuildArgs) -> Result<()> {
+    let mut split = Vec::new();
+    for value in std::mem::take(&mut build_args.cargo_args_string) {
+        let args = shlex::split(&value).ok_or_else(|| {
+            CrossError::InvalidArgument(format!(
+                "--cargo-args: mismatched quotes in {value:?}; pass each argument with --cargo-arg"
+            ))
+        })?;
+        split.extend(args);
+    }
+    if !split.is_empty() {
+        split.append(&mut build_args.cargo_args);
+        build_args.cargo_args = split;
+    }
+    Ok(())
+}
+
 fn populate_env_arg_fallbacks(build_args: &mut BuildArgs) {
     if build_args.cargo_args.is_empty() {
         if let Some(env_args) = parse_env_args("CARGO_ARGS") {
@@ -1904,6 +3862,23 @@ fn populate_env_arg_fallbacks(build_args: &mut BuildArgs) {
             build_args.passthrough_args = env_args;
         }
     }
+    // A compiler command may have commas of its own (`gcc -Wl,-rpath,/x`); only the
+    // command-line options take comma-separated lists
+    for (entries, name) in [
+        (&mut build_args.cc, "CC"),
+        (&mut build_args.cxx, "CXX"),
+        (&mut build_args.ar, "AR"),
+        (&mut build_args.linker, "LINKER"),
+    ] {
+        if entries.is_empty() {
+            if let Some(value) = std::env::var(name)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+            {
+                entries.push(value);
+            }
+        }
+    }
 }
 
 fn parse_passthrough_env_args(env_name: &str) -> Option<Vec<String>> {
@@ -1936,6 +3911,163 @@ fn parse_env_args(env_name: &str) -> Option<Vec<String>> {
     }
 }
 
+/// Cargo commands `cargo cross` runs; other commands (setup, exec, ...) never pass
+/// the package selection and output options on to cargo
+const CARGO_COMMANDS: &[&str] = &[
+    "build", "check", "run", "test", "bench", "clippy", "doc", "fix", "rustc", "rustdoc",
+];
+
+/// An option that only some cargo commands accept
+struct CommandOption {
+    option: &'static str,
+    used: fn(&BuildArgs) -> bool,
+    /// Cargo commands the option works with
+    commands: &'static [&'static str],
+    /// What to do instead
+    hint: &'static str,
+}
+
+/// Options that would be ignored or rejected by cargo for some commands
+const COMMAND_OPTIONS: &[CommandOption] = &[
+    CommandOption {
+        option: "--artifact-dir",
+        used: |b| b.artifact_dir.is_some(),
+        commands: &["build", "rustc", "bench"],
+        hint: "only `build` and `rustc` produce artifacts to copy, and `bench` exports its \
+               benchmark results there; drop --artifact-dir or use `build`",
+    },
+    CommandOption {
+        option: "--artifact-name",
+        used: |b| b.artifact_name.is_some(),
+        commands: &["build", "rustc"],
+        hint: "only `build` and `rustc` produce artifacts to collect; drop --artifact-name or use `build`",
+    },
+    CommandOption {
+        option: "--qemu-gdb",
+        used: |b| b.qemu_gdb.is_some(),
+        commands: &["run", "test", "bench"],
+        hint: "only `run`, `test` and `bench` start binaries to debug; drop --qemu-gdb",
+    },
+    CommandOption {
+        option: "--objcopy-format",
+        used: |b| b.objcopy_format.is_some(),
+        commands: &["build"],
+        hint: "only `build` links images to convert; drop --objcopy-format or use `build`",
+    },
+    CommandOption {
+        option: "--no-link-toolchain",
+        used: |b| b.no_link_toolchain,
+        commands: &["check"],
+        hint: "only `check` can skip the C toolchain; drop --no-link-toolchain",
+    },
+    CommandOption {
+        option: "--collect-glob",
+        used: |b| !b.collect_glob.is_empty(),
+        commands: &["build", "rustc"],
+        hint: "only `build` and `rustc` collect artifacts; drop --collect-glob or use `build`",
+    },
+    CommandOption {
+        option: "passthrough arguments after `--`",
+        used: |b| !b.passthrough_args.is_empty(),
+        commands: &["run", "test", "bench", "clippy", "rustc", "rustdoc"],
+        hint: "arguments after `--` go to the program run by `run`, `test` or `bench`, or to \
+               the compiler or linter behind `clippy`, `rustc` or `rustdoc`; pass cargo \
+               options with --cargo-arg instead",
+    },
+    CommandOption {
+        option: "--skip-tests",
+        used: |b| !b.skip_tests.is_empty(),
+        commands: &["test"],
+        hint: "only libtest binaries take --skip; drop --skip-tests or use `test`",
+    },
+    CommandOption {
+        option: "--sequential-runs",
+        used: |b| b.sequential_runs,
+        commands: &["run"],
+        hint: "it only changes how `run` handles several targets; drop --sequential-runs",
+    },
+    CommandOption {
+        option: "--lib",
+        used: |b| b.build_lib,
+        commands: &[
+            "build", "check", "test", "bench", "clippy", "doc", "fix", "rustc", "rustdoc",
+        ],
+        hint: "only `build`, `check`, `test`, `bench`, `clippy`, `doc`, `fix`, `rustc` and \
+               `rustdoc` select the library; `run` only runs binaries, use --bin or --example",
+    },
+    CommandOption {
+        option: "--test",
+        used: |b| b.test_target.is_some(),
+        commands: &[
+            "build", "check", "test", "bench", "clippy", "fix", "rustc", "rustdoc",
+        ],
+        hint: "only `build`, `check`, `test`, `bench`, `clippy`, `fix`, `rustc` and `rustdoc` \
+               select test targets; run an integration test with `test --test <NAME>`",
+    },
+    CommandOption {
+        option: "--tests",
+        used: |b| b.build_tests,
+        commands: &[
+            "build", "check", "test", "bench", "clippy", "fix", "rustc", "rustdoc",
+        ],
+        hint: "only `build`, `check`, `test`, `bench`, `clippy`, `fix`, `rustc` and `rustdoc` \
+               select test targets; run the tests with `test`",
+    },
+    CommandOption {
+        option: "--bench",
+        used: |b| b.bench_target.is_some(),
+        commands: &[
+            "build", "check", "test", "bench", "clippy", "fix", "rustc", "rustdoc",
+        ],
+        hint: "only `build`, `check`, `test`, `bench`, `clippy`, `fix`, `rustc` and `rustdoc` \
+               select bench targets; run a benchmark with `bench --bench <NAME>`",
+    },
+    CommandOption {
+        option: "--benches",
+        used: |b| b.build_benches,
+        commands: &[
+            "build", "check", "test", "bench", "clippy", "fix", "rustc", "rustdoc",
+        ],
+        hint: "only `build`, `check`, `test`, `bench`, `clippy`, `fix`, `rustc` and `rustdoc` \
+               select bench targets; run the benchmarks with `bench`",
+    },
+    CommandOption {
+        option: "--all-targets",
+        used: |b| b.build_all_targets,
+        commands: &[
+            "build", "check", "test", "bench", "clippy", "fix", "rustc", "rustdoc",
+        ],
+        hint: "only `build`, `check`, `test`, `bench`, `clippy`, `fix`, `rustc` and `rustdoc` \
+               select every target; choose the one to run with --bin or --example",
+    },
+    CommandOption {
+        option: "--build-plan",
+        used: |b| b.build_plan,
+        commands: &["build"],
+        hint: "only `build` prints a build plan; drop --build-plan or use `build`",
+    },
+];
+
+/// Reject options the selected cargo command does not support
+fn validate_command_options(build_args: &BuildArgs, command: &Command) -> Result<()> {
+    let command = command.as_str();
+    if !CARGO_COMMANDS.contains(&command) {
+        return Ok(());
+    }
+    match COMMAND_OPTIONS
+        .iter()
+        .find(|rule| !rule.commands.contains(&command) && (rule.used)(build_args))
+    {
+        Some(rule) => Err(CrossError::InvalidArgument(format!(
+            "{} cannot be used with `{} {command}`: {}",
+            rule.option,
+            program_name(),
+            rule.hint
+        ))),
+        None => Ok(()),
+    }
+}
+
 /// Validate version options
 fn validate_versions(args: &Args) -> Result<()> {
     // Only validate glibc version if it's specified (non-empty)
@@ -1967,6 +4099,23 @@ fn validate_versions(args: &Args) -> Result<()> {
         });
     }
 
+    if let Some(ref version) = args.host_compat_ubuntu {
+        if !HOST_COMPAT_UBUNTU_VERSIONS
+            .iter()
+            .any(|(supported, _)| supported == version)
+        {
+            return Err(CrossError::InvalidArgument(format!(
+                "--host-compat-ubuntu {version}: no osxcross/ioscross build for it \
+                 (available: {})",
+                HOST_COMPAT_UBUNTU_VERSIONS
+                    .iter()
+                    .map(|(version, _)| *version)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+    }
+
     if !SUPPORTED_FREEBSD_VERSIONS.contains(&args.freebsd_version.as_str()) {
         return Err(CrossError::UnsupportedFreebsdVersion {
             version: args.freebsd_version.clone(),
@@ -1977,26 +4126,75 @@ fn validate_versions(args: &Args) -> Result<()> {
     Ok(())
 }
 
-/// Print all supported targets
-pub fn print_all_targets(format: OutputFormat) {
+/// Accept `--ndk-version` as a release (`r27d`) or an sdkmanager version (`27.3.13750724`),
+/// and use the release name the download URL and install directory are named after
+fn normalize_ndk_version(args: &mut Args) -> Result<()> {
+    let release = crate::platform::android::NdkVersion::parse(&args.ndk_version)?.release_name();
+    if release != args.ndk_version {
+        crate::color::log_debug(&format!(
+            "Using NDK {release} for --ndk-version {}",
+            args.ndk_version
+        ));
+        args.ndk_version = release;
+    }
+    Ok(())
+}
+
+/// Print all supported targets.
+/// With `lists` (from `--detailed`), each target also shows where its standard library comes from.
+pub fn print_all_targets(format: OutputFormat, lists: Option<&TargetLists>) {
     let mut targets: Vec<_> = config::all_targets().collect();
     targets.sort_unstable();
+    let support = |target: &str| lists.map(|l| std_support(target, &l.rustc, &l.rustup).as_str());
+    let formerly = |target: &str| {
+        let names = config::former_names(target);
+        (lists.is_some() && !names.is_empty()).then(|| names.join(", "))
+    };
 
     match format {
         OutputFormat::Text => {
             use colored::Colorize;
             println!("{}", "Supported Rust targets:".bright_green());
+            let width = targets.iter().map(|t| t.len()).max().unwrap_or(0);
             for target in &targets {
-                println!("  {}", target.bright_cyan());
+                match (support(target), formerly(target)) {
+                    (Some(std), Some(names)) => println!(
+                        "  {:<width$}  {}  {}",
+                        target.bright_cyan(),
+                        std.yellow(),
+                        format!("(formerly {names})").dimmed()
+                    ),
+                    (Some(std), None) => {
+                        println!("  {:<width$}  {}", target.bright_cyan(), std.yellow());
+                    }
+                    (None, _) => println!("  {}", target.bright_cyan()),
+                }
             }
         }
+        OutputFormat::Json if lists.is_some() => {
+            let entries: Vec<_> = targets
+                .iter()
+                .map(|target| {
+                    serde_json::json!({
+                        "target": target,
+                        "std": support(target),
+                        "formerly": config::former_names(target),
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::Value::Array(entries));
+        }
         OutputFormat::Json => {
             let json_array = serde_json::to_string(&targets).unwrap_or_else(|_| "[]".to_string());
             println!("{json_array}");
         }
         OutputFormat::Plain => {
             for target in &targets {
-                println!("{target}");
+                match (support(target), formerly(target)) {
+                    (Some(std), Some(names)) => println!("{target}\t{std}\t{names}"),
+                    (Some(std), None) => println!("{target}\t{std}"),
+                    (None, _) => println!("{target}"),
+                }
             }
         }
     }
@@ -2026,14 +4224,28 @@ pub fn print_version() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::RwLock;
+
+    /// Tests setting `CARGO_PASSTHROUGH_ARGS` or a tool variable take this for writing, so
+    /// other parses do not pick up values their command rejects or does not expect
+    static PASSTHROUGH_ENV: RwLock<()> = RwLock::new(());
 
     fn parse(args: &[&str]) -> Result<Args> {
+        let _env = PASSTHROUGH_ENV
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
         let args: Vec<String> = args.iter().map(std::string::ToString::to_string).collect();
         match parse_args_from(args)? {
             ParseResult::Build(args) => Ok(*args),
             ParseResult::ShowTargets(_) => panic!("unexpected ShowTargets"),
             ParseResult::Setup(_) => panic!("unexpected Setup"),
             ParseResult::Exec(_) => panic!("unexpected Exec"),
+            ParseResult::Sync(_) => panic!("unexpected Sync"),
+            ParseResult::Plan(_) => panic!("unexpected Plan"),
+            ParseResult::CacheKey(_) => panic!("unexpected CacheKey"),
+            ParseResult::QueryTools(_) => panic!("unexpected QueryTools"),
+            ParseResult::Bloat(_) => panic!("unexpected Bloat"),
+            ParseResult::CleanGenerated(_) => panic!("unexpected CleanGenerated"),
             ParseResult::ShowVersion => panic!("unexpected ShowVersion"),
         }
     }
@@ -2046,6 +4258,22 @@ mod tests {
         }
     }
 
+    fn parse_plan(args: &[&str]) -> Result<PlanArgs> {
+        let args: Vec<String> = args.iter().map(std::string::ToString::to_string).collect();
+        match parse_args_from(args)? {
+            ParseResult::Plan(args) => Ok(*args),
+            _ => panic!("unexpected parse result"),
+        }
+    }
+
+    fn parse_sync(args: &[&str]) -> Result<SyncArgs> {
+        let args: Vec<String> = args.iter().map(std::string::ToString::to_string).collect();
+        match parse_args_from(args)? {
+            ParseResult::Sync(args) => Ok(*args),
+            _ => panic!("unexpected parse result"),
+        }
+    }
+
     fn parse_exec(args: &[&str]) -> Result<ExecArgs> {
         let args: Vec<String> = args.iter().map(std::string::ToString::to_string).collect();
         match parse_args_from(args)? {
@@ -2149,6 +4377,149 @@ mod tests {
         assert_eq!(args.format, SetupOutputFormat::Fish);
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_cross_compiler_dir_is_rejected() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let build = BuildArgs {
+            cross_compiler_dir: Some(PathBuf::from(std::ffi::OsStr::from_bytes(
+                b"/tmp/cross-\xff",
+            ))),
+            targets: vec!["x86_64-unknown-linux-musl".to_string()],
+            ..BuildArgs::default()
+        };
+        let err = Args::from_build_args(build, Command::build(), None).unwrap_err();
+        assert!(err.to_string().contains("must be valid UTF-8"));
+    }
+
+    #[test]
+    fn test_clap_env_names() {
+        let names = clap_env_names();
+        assert!(names.contains("GLIBC_VERSION"));
+        assert!(names.contains("SCCACHE_IDLE_TIMEOUT"));
+        assert!(!names.contains("PATH"));
+        assert!(!names.contains("GIT_DIR"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sanitize_clap_env_keeps_unrelated_empty_vars() {
+        std::env::set_var("CARGO_CROSS_TEST_UNRELATED_EMPTY", "");
+        std::env::set_var("SCCACHE_IDLE_TIMEOUT", "");
+        sanitize_clap_env();
+
+        assert_eq!(
+            std::env::var_os("CARGO_CROSS_TEST_UNRELATED_EMPTY"),
+            Some(std::ffi::OsString::new())
+        );
+        assert!(std::env::var_os("SCCACHE_IDLE_TIMEOUT").is_none());
+        assert!(cleared_clap_env().contains(&"SCCACHE_IDLE_TIMEOUT".to_string()));
+
+        // The unrelated variable reaches child processes untouched
+        let output = std::process::Command::new("env").output().unwrap();
+        let child_env = String::from_utf8_lossy(&output.stdout);
+        assert!(child_env
+            .lines()
+            .any(|line| line == "CARGO_CROSS_TEST_UNRELATED_EMPTY="));
+
+        // The cleared variable is given back (empty) to the cargo child
+        let mut cmd = tokio::process::Command::new("cargo");
+        crate::cargo::restore_cleared_env(&mut cmd, &crate::env::EnvMap::new());
+        assert!(cmd
+            .as_std()
+            .get_envs()
+            .any(|(key, value)| key == "SCCACHE_IDLE_TIMEOUT"
+                && value == Some(std::ffi::OsStr::new(""))));
+
+        std::env::remove_var("CARGO_CROSS_TEST_UNRELATED_EMPTY");
+    }
+
+    #[test]
+    fn test_build_args_cli_defaults() {
+        let build = BuildArgs::cli_defaults();
+        assert_eq!(build.profile, "dev");
+        assert_eq!(build.glibc_version, DEFAULT_GLIBC_VERSION);
+        assert_eq!(build.cross_make_version, DEFAULT_CROSS_MAKE_VERSION);
+        assert_eq!(build.build_std, BuildStd::Unset);
+        assert!(build.targets.is_empty());
+    }
+
+    #[test]
+    fn test_parse_plan_command() {
+        let plan = parse_plan(&[
+            "cargo-cross",
+            "+nightly",
+            "plan",
+            "test",
+            "-t",
+            "aarch64-unknown-linux-musl",
+            "--format",
+            "table",
+            "--release",
+            "--",
+            "--format",
+            "json",
+        ])
+        .unwrap();
+        assert_eq!(plan.format, PlanOutputFormat::Table);
+        assert_eq!(plan.args.command, Command::test());
+        assert_eq!(plan.args.toolchain.as_deref(), Some("nightly"));
+        assert_eq!(plan.args.targets, vec!["aarch64-unknown-linux-musl"]);
+        assert_eq!(plan.args.profile, "release");
+        assert_eq!(plan.args.passthrough_args, vec!["--format", "json"]);
+        assert!(!plan.args.cargo_args.iter().any(|arg| arg == "--format"));
+
+        let plan = parse_plan(&[
+            "cargo-cross",
+            "plan",
+            "--format=table",
+            "b",
+            "-t",
+            "x86_64-unknown-linux-musl",
+        ])
+        .unwrap();
+        assert_eq!(plan.format, PlanOutputFormat::Table);
+        assert_eq!(plan.args.command, Command::build());
+
+        let plan = parse_plan(&[
+            "cargo-cross",
+            "plan",
+            "doc",
+            "-t",
+            "x86_64-unknown-linux-musl",
+        ])
+        .unwrap();
+        assert_eq!(plan.format, PlanOutputFormat::Json);
+        assert_eq!(plan.args.command.as_str(), "doc");
+
+        assert!(parse(&["cargo-cross", "plan", "setup"]).is_err());
+        let mut args = vec!["--format".to_string(), "yaml".to_string()];
+        assert!(take_plan_format(&mut args).is_err());
+    }
+
+    #[test]
+    fn test_parse_sync_command() {
+        let sync =
+            parse_sync(&["cargo-cross", "sync", "-t", "aarch64-unknown-linux-musl"]).unwrap();
+        assert_eq!(sync.args.command, Command::sync());
+        assert!(sync.args.command.needs_runner());
+        assert_eq!(sync.args.targets, vec!["aarch64-unknown-linux-musl"]);
+        assert!(!sync.check);
+
+        let sync = parse_sync(&[
+            "cargo-cross",
+            "+nightly",
+            "sync",
+            "-t",
+            "x86_64-unknown-linux-musl",
+            "--check",
+        ])
+        .unwrap();
+        assert!(sync.check);
+        assert_eq!(sync.args.toolchain.as_deref(), Some("nightly"));
+    }
+
     #[test]
     fn test_parse_exec_command() {
         let args = parse_exec(&[
@@ -2184,6 +4555,9 @@ mod tests {
 
     #[test]
     fn test_parse_exec_command_from_env_passthrough() {
+        let _env = PASSTHROUGH_ENV
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
         std::env::set_var("CARGO_PASSTHROUGH_ARGS", "-- env FOO=bar");
         let args = parse_exec(&["cargo-cross", "exec", "-t", "x86_64-unknown-linux-musl"]).unwrap();
         assert_eq!(args.args.command, Command::exec());
@@ -2191,6 +4565,38 @@ mod tests {
         std::env::remove_var("CARGO_PASSTHROUGH_ARGS");
     }
 
+    #[test]
+    fn test_tool_env_is_one_entry() {
+        let _env = PASSTHROUGH_ENV
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        std::env::set_var("LINKER", "clang -Wl,-rpath,/opt/lib");
+        let args = parse_args_from(
+            ["cargo-cross", "build"]
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        );
+        let cli = parse_args_from(
+            [
+                "cargo-cross",
+                "build",
+                "--linker",
+                "aarch64-apple-darwin=ld64,clang",
+            ]
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+        );
+        std::env::remove_var("LINKER");
+        let (Ok(ParseResult::Build(args)), Ok(ParseResult::Build(cli))) = (args, cli) else {
+            panic!("build did not parse");
+        };
+        assert_eq!(args.linker, ["clang -Wl,-rpath,/opt/lib"]);
+        // The option still takes a list, and wins over the variable
+        assert_eq!(cli.linker, ["aarch64-apple-darwin=ld64", "clang"]);
+    }
+
     #[test]
     fn test_parse_target() {
         let args = parse(&["cargo-cross", "build", "-t", "x86_64-unknown-linux-musl"]).unwrap();
@@ -2218,23 +4624,51 @@ mod tests {
         assert_eq!(args.verbose_level, 3);
     }
 
+    #[test]
+    fn test_parse_skip_step() {
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "--skip-step",
+            "gcc-lib-paths",
+            "--skip-step=sysroot-env,host-config",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.skip_step,
+            [
+                SetupStep::GccLibPaths,
+                SetupStep::SysrootEnv,
+                SetupStep::HostConfig
+            ]
+        );
+        assert!(!args.step_enabled(SetupStep::SysrootEnv));
+        assert!(args.step_enabled(SetupStep::Runner));
+
+        let err = parse(&["cargo-cross", "build", "--skip-step", "linker"]).unwrap_err();
+        let message = err.to_string();
+        for step in ["gcc-lib-paths", "cross-compile-prefix", "rustflags-crt"] {
+            assert!(message.contains(step), "{message}");
+        }
+    }
+
     #[test]
     fn test_parse_crt_static_flag() {
         let args = parse(&["cargo-cross", "build", "--crt-static", "true"]).unwrap();
-        assert_eq!(args.crt_static, Some(true));
+        assert_eq!(args.crt_static, Some(CrtStatic::Static));
     }
 
     #[test]
     fn test_parse_crt_static_false() {
         let args = parse(&["cargo-cross", "build", "--crt-static", "false"]).unwrap();
-        assert_eq!(args.crt_static, Some(false));
+        assert_eq!(args.crt_static, Some(CrtStatic::Dynamic));
     }
 
     #[test]
     fn test_parse_crt_static_no_value() {
         // --crt-static without value should default to true
         let args = parse(&["cargo-cross", "build", "--crt-static"]).unwrap();
-        assert_eq!(args.crt_static, Some(true));
+        assert_eq!(args.crt_static, Some(CrtStatic::Static));
     }
 
     #[test]
@@ -2244,29 +4678,155 @@ mod tests {
         assert_eq!(args.crt_static, None);
     }
 
+    #[test]
+    fn test_ndk_version_is_normalized() {
+        let args = parse(&["cargo-cross", "build", "--ndk-version", "27.2.12479018"]).unwrap();
+        assert_eq!(args.ndk_version, "r27c");
+        let args = parse(&["cargo-cross", "build", "--ndk-version", "r25c"]).unwrap();
+        assert_eq!(args.ndk_version, "r25c");
+        let err = parse(&["cargo-cross", "build", "--ndk-version", "27-latest!"]).unwrap_err();
+        assert!(matches!(err, CrossError::InvalidArgument(_)), "{err}");
+    }
+
+    #[test]
+    fn test_parse_crt_static_auto() {
+        let args = parse(&["cargo-cross", "build", "--crt-static", "auto"]).unwrap();
+        assert_eq!(args.crt_static, Some(CrtStatic::Auto));
+        let args = parse(&["cargo-cross", "build", "--crt-static=AUTO"]).unwrap();
+        assert_eq!(args.crt_static, Some(CrtStatic::Auto));
+        assert!(parse(&["cargo-cross", "build", "--crt-static", "sometimes"]).is_err());
+    }
+
     #[test]
     fn test_parse_build_std() {
         let args = parse(&["cargo-cross", "build", "--build-std", "true"]).unwrap();
-        assert_eq!(args.build_std, Some("true".to_string()));
+        assert_eq!(args.build_std, BuildStd::Enabled("true".to_string()));
+    }
+
+    #[test]
+    fn test_parse_build_std_auto() {
+        let args = parse(&["cargo-cross", "build", "--build-std", "auto"]).unwrap();
+        assert_eq!(args.build_std, BuildStd::Auto(Vec::new()));
+        assert!(args.build_std.is_enabled());
+
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "--build-std=auto; thumbv6m-*=>core ;x86_64-unknown-none=>core,alloc",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.build_std,
+            BuildStd::Auto(vec![
+                ("thumbv6m-*".to_string(), "core".to_string()),
+                ("x86_64-unknown-none".to_string(), "core,alloc".to_string()),
+            ])
+        );
+        assert!(parse(&["cargo-cross", "build", "--build-std", "auto;core"]).is_err());
+        assert!(parse(&["cargo-cross", "build", "--build-std", "auto;[=>core"]).is_err());
     }
 
     #[test]
     fn test_parse_build_std_crates() {
         let args = parse(&["cargo-cross", "build", "--build-std", "core,alloc"]).unwrap();
-        assert_eq!(args.build_std, Some("core,alloc".to_string()));
+        assert_eq!(args.build_std, BuildStd::Enabled("core,alloc".to_string()));
     }
 
     #[test]
     fn test_parse_build_std_false() {
         let args = parse(&["cargo-cross", "build", "--build-std", "false"]).unwrap();
-        assert_eq!(args.build_std, None);
+        assert_eq!(args.build_std, BuildStd::Disabled);
+        let args = parse(&["cargo-cross", "build", "--build-std", "0"]).unwrap();
+        assert_eq!(args.build_std, BuildStd::Disabled);
+    }
+
+    #[test]
+    fn test_parse_build_std_not_provided() {
+        let args = parse(&["cargo-cross", "build"]).unwrap();
+        assert_eq!(args.build_std, BuildStd::Unset);
+    }
+
+    #[test]
+    fn test_build_std_features_requires_explicit_build_std() {
+        let result = parse(&[
+            "cargo-cross",
+            "build",
+            "--build-std-features",
+            "panic_immediate_abort",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_panic_immediate_abort_implies_build_std() {
+        let args = parse(&["cargo-cross", "build", "--panic-immediate-abort"]).unwrap();
+        assert_eq!(args.build_std, BuildStd::Enabled("true".to_string()));
+        assert_eq!(args.build_std_features, None);
+
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "--panic-immediate-abort",
+            "--build-std",
+            "core,alloc",
+            "--build-std-features",
+            "optimize_for_size",
+        ])
+        .unwrap();
+        assert_eq!(args.build_std, BuildStd::Enabled("core,alloc".to_string()));
+        assert_eq!(
+            args.build_std_features.as_deref(),
+            Some("optimize_for_size")
+        );
+    }
+
+    #[test]
+    fn test_nightly_requirements() {
+        let args = parse(&["cargo-cross", "build"]).unwrap();
+        assert!(args.nightly_requirements().is_empty());
+
+        let args = parse(&["cargo-cross", "build", "--panic-immediate-abort"]).unwrap();
+        assert_eq!(
+            args.nightly_requirements(),
+            vec!["--build-std", "--panic-immediate-abort"]
+        );
+
+        // On stable cargo-cross copies the artifacts itself
+        let args = parse(&["cargo-cross", "build", "--artifact-dir", "dist"]).unwrap();
+        assert!(args.nightly_requirements().is_empty());
+        assert!(parse(&["cargo-cross", "build", "--artifact-name", "{file}"]).is_err());
+
+        // Debug info is split for the default collection too
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "--artifact-dir",
+            "dist",
+            "--debuginfo-artifacts",
+        ])
+        .unwrap();
+        assert!(args.debuginfo_artifacts);
+        assert!(parse(&["cargo-cross", "build", "--debuginfo-artifacts"]).is_err());
+    }
+
+    #[test]
+    fn test_panic_immediate_abort_conflicts_with_disabled_build_std() {
+        let err = parse(&[
+            "cargo-cross",
+            "build",
+            "--panic-immediate-abort",
+            "--build-std=false",
+        ])
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("requires build-std"));
     }
 
     #[test]
     fn test_parse_build_std_no_value() {
         // --build-std without value should default to "true"
         let args = parse(&["cargo-cross", "build", "--build-std"]).unwrap();
-        assert_eq!(args.build_std, Some("true".to_string()));
+        assert_eq!(args.build_std, BuildStd::Enabled("true".to_string()));
     }
 
     #[test]
@@ -2295,16 +4855,107 @@ mod tests {
 
     #[test]
     fn test_parse_passthrough_args() {
-        let args = parse(&["cargo-cross", "build", "--", "--foo", "--bar"]).unwrap();
+        let args = parse(&["cargo-cross", "run", "--", "--foo", "--bar"]).unwrap();
         assert_eq!(args.passthrough_args, vec!["--foo", "--bar"]);
     }
 
+    #[test]
+    fn test_parse_qemu_gdb() {
+        let args = parse(&["cargo-cross", "test", "--release", "--qemu-gdb", "1234"]).unwrap();
+        assert_eq!(args.qemu_gdb, Some(1234));
+        assert!(!args.no_auto_debuginfo);
+        assert!(parse(&["cargo-cross", "test", "--no-auto-debuginfo"]).is_err());
+        assert!(parse(&["cargo-cross", "build", "--qemu-gdb", "1234"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_collect_glob() {
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "--artifact-dir",
+            "dist",
+            "--collect-glob",
+            "deps/*.ll",
+            "--collect-glob",
+            "build/{a,b}/out/*.h",
+        ])
+        .unwrap();
+        assert_eq!(args.collect_glob, ["deps/*.ll", "build/{a,b}/out/*.h"]);
+
+        // rustc takes the flags that emit the extra files after `--`
+        let args = parse(&[
+            "cargo-cross",
+            "rustc",
+            "--artifact-dir",
+            "dist",
+            "--collect-glob",
+            "deps/*.ll",
+            "--",
+            "--emit=llvm-ir,link",
+        ])
+        .unwrap();
+        assert_eq!(args.collect_glob, ["deps/*.ll"]);
+        assert_eq!(args.passthrough_args, ["--emit=llvm-ir,link"]);
+        for argv in [
+            ["cargo-cross", "build", "--collect-glob", "deps/*.ll"].as_slice(),
+            &[
+                "cargo-cross",
+                "build",
+                "--artifact-dir",
+                "dist",
+                "--collect-glob",
+                "../*",
+            ],
+            &[
+                "cargo-cross",
+                "check",
+                "--artifact-dir",
+                "dist",
+                "--collect-glob",
+                "*.s",
+            ],
+        ] {
+            assert!(parse(argv).is_err(), "{argv:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_skip_tests() {
+        let args = parse(&[
+            "cargo-cross",
+            "test",
+            "--skip-tests",
+            "timing::,riscv64gc-unknown-linux-gnu=asm_probe",
+            "--skip-tests",
+            "slow",
+            "--",
+            "--nocapture",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.skip_tests_for("riscv64gc-unknown-linux-gnu"),
+            ["timing::", "asm_probe", "slow"]
+        );
+        assert_eq!(
+            args.passthrough_args_for("aarch64-unknown-linux-gnu"),
+            ["--nocapture", "--skip", "timing::", "--skip", "slow"]
+        );
+        assert!(parse(&["cargo-cross", "build", "--skip-tests", "slow"]).is_err());
+    }
+
     #[test]
     fn test_parse_passthrough_args_from_env_with_legacy_separator() {
+        let _env = PASSTHROUGH_ENV
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
         std::env::set_var("CARGO_PASSTHROUGH_ARGS", "-- --foo --bar");
-        let args = parse(&["cargo-cross", "build"]).unwrap();
-        assert_eq!(args.passthrough_args, vec!["--foo", "--bar"]);
+        let args = parse_args_from(vec!["cargo-cross".to_string(), "run".to_string()]);
         std::env::remove_var("CARGO_PASSTHROUGH_ARGS");
+        let ParseResult::Build(args) = args.unwrap() else {
+            panic!("unexpected parse result");
+        };
+        assert_eq!(args.passthrough_args, vec!["--foo", "--bar"]);
     }
 
     #[test]
@@ -2323,8 +4974,8 @@ mod tests {
     fn test_targets_subcommand() {
         let args: Vec<String> = vec!["cargo-cross".to_string(), "targets".to_string()];
         match parse_args_from(args).unwrap() {
-            ParseResult::ShowTargets(format) => {
-                assert_eq!(format, OutputFormat::Text);
+            ParseResult::ShowTargets(targets) => {
+                assert_eq!(targets.format, OutputFormat::Text);
             }
             _ => panic!("expected ShowTargets"),
         }
@@ -2339,8 +4990,8 @@ mod tests {
             "json".to_string(),
         ];
         match parse_args_from(args).unwrap() {
-            ParseResult::ShowTargets(format) => {
-                assert_eq!(format, OutputFormat::Json);
+            ParseResult::ShowTargets(targets) => {
+                assert_eq!(targets.format, OutputFormat::Json);
             }
             _ => panic!("expected ShowTargets"),
         }
@@ -2355,8 +5006,8 @@ mod tests {
             "plain".to_string(),
         ];
         match parse_args_from(args).unwrap() {
-            ParseResult::ShowTargets(format) => {
-                assert_eq!(format, OutputFormat::Plain);
+            ParseResult::ShowTargets(targets) => {
+                assert_eq!(targets.format, OutputFormat::Plain);
             }
             _ => panic!("expected ShowTargets"),
         }
@@ -2424,13 +5075,13 @@ mod tests {
     #[test]
     fn test_equals_syntax_crt_static() {
         let args = parse(&["cargo-cross", "build", "--crt-static=true"]).unwrap();
-        assert_eq!(args.crt_static, Some(true));
+        assert_eq!(args.crt_static, Some(CrtStatic::Static));
     }
 
     #[test]
     fn test_equals_syntax_build_std() {
         let args = parse(&["cargo-cross", "build", "--build-std=core,alloc"]).unwrap();
-        assert_eq!(args.build_std, Some("core,alloc".to_string()));
+        assert_eq!(args.build_std, BuildStd::Enabled("core,alloc".to_string()));
     }
 
     #[test]
@@ -2453,6 +5104,17 @@ mod tests {
         assert_eq!(args.cross_compiler_dir, PathBuf::from("/opt/cross"));
     }
 
+    #[test]
+    fn test_host_compat_ubuntu() {
+        let args = parse(&["cargo-cross", "build", "--host-compat-ubuntu", "22.04"]).unwrap();
+        assert_eq!(args.host_compat_ubuntu.as_deref(), Some("22.04"));
+        let err = parse(&["cargo-cross", "build", "--host-compat-ubuntu=21.04"]).unwrap_err();
+        assert!(
+            err.to_string().contains("available: 20.04, 22.04, 24.04"),
+            "{err}"
+        );
+    }
+
     #[test]
     fn test_equals_syntax_glibc_version() {
         let args = parse(&["cargo-cross", "build", "--glibc-version=2.31"]).unwrap();
@@ -2469,15 +5131,59 @@ mod tests {
             "--ar=/usr/bin/ar",
         ])
         .unwrap();
-        assert_eq!(args.cc, Some(PathBuf::from("/usr/bin/gcc")));
-        assert_eq!(args.cxx, Some(PathBuf::from("/usr/bin/g++")));
-        assert_eq!(args.ar, Some(PathBuf::from("/usr/bin/ar")));
+        assert_eq!(args.cc, ["/usr/bin/gcc"]);
+        assert_eq!(args.cxx, ["/usr/bin/g++"]);
+        assert_eq!(args.ar, ["/usr/bin/ar"]);
     }
 
     #[test]
     fn test_equals_syntax_linker() {
         let args = parse(&["cargo-cross", "build", "--linker=/usr/bin/ld.lld"]).unwrap();
-        assert_eq!(args.linker, Some(PathBuf::from("/usr/bin/ld.lld")));
+        assert_eq!(args.linker, ["/usr/bin/ld.lld"]);
+    }
+
+    #[test]
+    fn test_tool_override_map() {
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "--linker",
+            "x86_64-pc-windows-msvc=lld-link,/opt/musl/bin/musl-gcc",
+            "--linker=aarch64-apple-darwin=",
+            "--cc",
+            "/opt/a=b/gcc",
+        ])
+        .unwrap();
+        assert_eq!(
+            resolve_override(&args.linker, "x86_64-pc-windows-msvc", Some("cc")),
+            Some(("lld-link", OverrideSource::Target))
+        );
+        assert_eq!(
+            resolve_override(&args.linker, "x86_64-unknown-linux-musl", Some("cc")),
+            Some(("/opt/musl/bin/musl-gcc", OverrideSource::Global))
+        );
+        // An empty per-target entry keeps the platform default for that target
+        assert_eq!(
+            resolve_override(&args.linker, "aarch64-apple-darwin", Some("cc")),
+            Some(("cc", OverrideSource::Default))
+        );
+        assert_eq!(
+            resolve_override(&args.linker, "aarch64-apple-darwin", None),
+            None
+        );
+        // A path with `=` is not a target entry
+        assert_eq!(
+            resolve_override(&args.cc, "x86_64-unknown-linux-gnu", None),
+            Some(("/opt/a=b/gcc", OverrideSource::Global))
+        );
+        assert_eq!(
+            resolve_override(&[], "x86_64-unknown-linux-gnu", Some("gcc")),
+            Some(("gcc", OverrideSource::Default))
+        );
+        assert_eq!(
+            split_target_entry(r"C:\tools=x\lld-link.exe"),
+            (None, r"C:\tools=x\lld-link.exe")
+        );
     }
 
     #[test]
@@ -2550,8 +5256,8 @@ mod tests {
         assert_eq!(args.profile, "release");
         assert_eq!(args.features, Some("serde,json".to_string()));
         assert_eq!(args.jobs, Some("8".to_string()));
-        assert_eq!(args.crt_static, Some(true));
-        assert_eq!(args.build_std, Some("core,alloc".to_string()));
+        assert_eq!(args.crt_static, Some(CrtStatic::Static));
+        assert_eq!(args.build_std, BuildStd::Enabled("core,alloc".to_string()));
     }
 
     #[test]
@@ -2602,7 +5308,7 @@ mod tests {
             "--no-default-features",
         ])
         .unwrap();
-        assert_eq!(args.crt_static, Some(true));
+        assert_eq!(args.crt_static, Some(CrtStatic::Static));
         assert!(args.no_default_features);
     }
 
@@ -2617,7 +5323,7 @@ mod tests {
             "serde",
         ])
         .unwrap();
-        assert_eq!(args.crt_static, Some(false));
+        assert_eq!(args.crt_static, Some(CrtStatic::Dynamic));
         assert_eq!(args.features, Some("serde".to_string()));
     }
 
@@ -2634,7 +5340,7 @@ mod tests {
             "release",
         ])
         .unwrap();
-        assert_eq!(args.crt_static, Some(true));
+        assert_eq!(args.crt_static, Some(CrtStatic::Static));
         assert_eq!(args.targets, vec!["x86_64-unknown-linux-musl"]);
         assert_eq!(args.profile, "release");
     }
@@ -2650,7 +5356,7 @@ mod tests {
         ])
         .unwrap();
         assert!(args.no_default_features);
-        assert_eq!(args.crt_static, Some(true));
+        assert_eq!(args.crt_static, Some(CrtStatic::Static));
     }
 
     #[test]
@@ -2673,7 +5379,7 @@ mod tests {
         assert_eq!(args.targets, vec!["aarch64-unknown-linux-musl"]);
         assert!(args.no_default_features);
         assert_eq!(args.features, Some("serde,json".to_string()));
-        assert_eq!(args.crt_static, Some(true));
+        assert_eq!(args.crt_static, Some(CrtStatic::Static));
         assert_eq!(args.profile, "release");
         assert_eq!(args.verbose_level, 2);
     }
@@ -2905,7 +5611,7 @@ mod tests {
 
     #[test]
     fn test_passthrough_single() {
-        let args = parse(&["cargo-cross", "build", "--", "--nocapture"]).unwrap();
+        let args = parse(&["cargo-cross", "test", "--", "--nocapture"]).unwrap();
         assert_eq!(args.passthrough_args, vec!["--nocapture"]);
     }
 
@@ -2927,7 +5633,7 @@ mod tests {
 
     #[test]
     fn test_passthrough_with_hyphen_values() {
-        let args = parse(&["cargo-cross", "build", "--", "-v", "--foo", "-bar"]).unwrap();
+        let args = parse(&["cargo-cross", "run", "--", "-v", "--foo", "-bar"]).unwrap();
         assert_eq!(args.passthrough_args, vec!["-v", "--foo", "-bar"]);
     }
 
@@ -2935,7 +5641,7 @@ mod tests {
     fn test_passthrough_after_options() {
         let args = parse(&[
             "cargo-cross",
-            "build",
+            "run",
             "-t",
             "x86_64-unknown-linux-musl",
             "--profile",
@@ -3052,7 +5758,41 @@ mod tests {
     fn test_requires_exclude_with_workspace() {
         let args = parse(&["cargo-cross", "build", "--workspace", "--exclude", "foo"]).unwrap();
         assert!(args.workspace);
-        assert_eq!(args.exclude, Some("foo".to_string()));
+        assert_eq!(args.exclude, vec!["foo"]);
+    }
+
+    #[test]
+    fn test_repeated_package_and_exclude() {
+        let args = parse(&["cargo-cross", "build", "-p", "app", "-p", "core-*"]).unwrap();
+        assert_eq!(args.package, vec!["app", "core-*"]);
+
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "--workspace",
+            "--exclude",
+            "xtask",
+            "--exclude",
+            "bench-*",
+        ])
+        .unwrap();
+        assert_eq!(args.exclude, vec!["xtask", "bench-*"]);
+    }
+
+    #[test]
+    fn test_default_members_only_conflicts_with_package() {
+        let args = parse(&["cargo-cross", "build", "--default-members-only"]).unwrap();
+        assert!(args.default_members_only);
+        assert!(args.package.is_empty());
+
+        assert!(parse(&[
+            "cargo-cross",
+            "build",
+            "--default-members-only",
+            "-p",
+            "app"
+        ])
+        .is_err());
     }
 
     #[test]
@@ -3066,7 +5806,7 @@ mod tests {
             "panic_immediate_abort",
         ])
         .unwrap();
-        assert_eq!(args.build_std, Some("core,alloc".to_string()));
+        assert_eq!(args.build_std, BuildStd::Enabled("core,alloc".to_string()));
         assert_eq!(
             args.build_std_features,
             Some("panic_immediate_abort".to_string())
@@ -3106,18 +5846,48 @@ mod tests {
     }
 
     #[test]
-    fn test_linker_with_no_toolchain_setup() {
-        // --linker and --no-toolchain-setup can be used together
+    fn test_cmake_generator() {
+        let args = parse(&["cargo-cross", "build", "--cmake-generator", "Ninja"]).unwrap();
+        assert_eq!(args.cmake_generator.as_deref(), Some("Ninja"));
+
+        let args = parse(&["cargo-cross", "build", "-G", "MinGW Makefiles"]).unwrap();
+        assert_eq!(args.cmake_generator.as_deref(), Some("MinGW Makefiles"));
+
+        // Unknown generators are left for CMake to reject
+        let args = parse(&["cargo-cross", "build", "-G", "Made Up Generator"]).unwrap();
+        assert_eq!(args.cmake_generator.as_deref(), Some("Made Up Generator"));
+
+        let args = parse(&["cargo-cross", "build"]).unwrap();
+        assert_eq!(args.cmake_generator, None);
+    }
+
+    #[test]
+    fn test_runner_with_no_toolchain_setup() {
         let args = parse(&[
             "cargo-cross",
-            "build",
-            "--linker",
+            "test",
+            "--no-toolchain-setup",
+            "--runner",
+            "qemu-aarch64 -L /opt/sysroot",
+        ])
+        .unwrap();
+        assert!(args.no_toolchain_setup);
+        assert_eq!(args.runner.as_deref(), Some("qemu-aarch64 -L /opt/sysroot"));
+    }
+
+    #[test]
+    fn test_linker_with_no_toolchain_setup() {
+        // --linker and --no-toolchain-setup can be used together
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "--linker",
             "/usr/bin/ld",
             "--no-toolchain-setup",
         ])
         .unwrap();
         assert!(args.no_toolchain_setup);
-        assert_eq!(args.linker, Some(PathBuf::from("/usr/bin/ld")));
+        assert_eq!(args.linker, ["/usr/bin/ld"]);
     }
 
     // Complex real-world scenario tests
@@ -3146,7 +5916,7 @@ mod tests {
         assert_eq!(args.command, Command::build());
         assert_eq!(args.targets, vec!["x86_64-unknown-linux-musl"]);
         assert_eq!(args.profile, "release");
-        assert_eq!(args.crt_static, Some(true));
+        assert_eq!(args.crt_static, Some(CrtStatic::Static));
         assert!(args.no_default_features);
         assert_eq!(args.features, Some("serde,json".to_string()));
         assert_eq!(args.jobs, Some("8".to_string()));
@@ -3170,7 +5940,7 @@ mod tests {
         ])
         .unwrap();
         assert_eq!(args.targets.len(), 2);
-        assert_eq!(args.build_std, Some("core,alloc".to_string()));
+        assert_eq!(args.build_std, BuildStd::Enabled("core,alloc".to_string()));
         assert_eq!(
             args.build_std_features,
             Some("panic_immediate_abort".to_string())
@@ -3216,9 +5986,9 @@ mod tests {
         ])
         .unwrap();
         assert_eq!(args.targets, vec!["aarch64-unknown-linux-musl"]);
-        assert!(args.cc.is_some());
-        assert!(args.cxx.is_some());
-        assert!(args.ar.is_some());
+        assert_eq!(args.cc.len(), 1);
+        assert_eq!(args.cxx.len(), 1);
+        assert_eq!(args.ar.len(), 1);
         assert_eq!(args.cflags, Some("-O2 -march=armv8-a".to_string()));
     }
 
@@ -3256,7 +6026,7 @@ mod tests {
         ])
         .unwrap();
         assert!(args.workspace);
-        assert_eq!(args.exclude, Some("test-crate".to_string()));
+        assert_eq!(args.exclude, vec!["test-crate"]);
         assert_eq!(args.targets, vec!["x86_64-unknown-linux-musl"]);
     }
 
@@ -3342,7 +6112,7 @@ mod tests {
         assert_eq!(args.targets, vec!["x86_64-unknown-linux-musl"]);
         assert_eq!(args.profile, "release");
         assert_eq!(args.features, Some("serde".to_string()));
-        assert_eq!(args.crt_static, Some(true));
+        assert_eq!(args.crt_static, Some(CrtStatic::Static));
     }
 
     #[test]
@@ -3527,15 +6297,425 @@ mod tests {
         assert_eq!(args.cargo_args, vec!["--verbose", "--locked"]);
     }
 
+    #[test]
+    fn test_cargo_args_string_is_split() {
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "--cargo-args",
+            "--verbose --frozen",
+            "--cargo-args",
+            "--config 'build.jobs = 2'",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.cargo_args,
+            vec!["--verbose", "--frozen", "--config", "build.jobs = 2"]
+        );
+
+        let result = parse(&[
+            "cargo-cross",
+            "build",
+            "--cargo-args",
+            "--config 'unterminated",
+        ]);
+        assert!(matches!(result, Err(CrossError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn test_command_options_matrix() {
+        // One use of each option in the table, so new rules must add a case here
+        let with_option = |option: &str| {
+            let mut build = BuildArgs::default();
+            match option {
+                "--artifact-dir" => build.artifact_dir = Some(PathBuf::from("dist")),
+                "--artifact-name" => build.artifact_name = Some("{target}/{file}".to_string()),
+                "--collect-glob" => build.collect_glob = vec!["deps/*.ll".to_string()],
+                "--qemu-gdb" => build.qemu_gdb = Some(1234),
+                "--no-link-toolchain" => build.no_link_toolchain = true,
+                "--objcopy-format" => build.objcopy_format = Some(ObjcopyFormat::Binary),
+                "passthrough arguments after `--`" => {
+                    build.passthrough_args = vec!["--nocapture".to_string()];
+                }
+                "--skip-tests" => build.skip_tests = vec!["timing::".to_string()],
+                "--sequential-runs" => build.sequential_runs = true,
+                "--lib" => build.build_lib = true,
+                "--test" => build.test_target = Some("it".to_string()),
+                "--tests" => build.build_tests = true,
+                "--bench" => build.bench_target = Some("speed".to_string()),
+                "--benches" => build.build_benches = true,
+                "--all-targets" => build.build_all_targets = true,
+                "--build-plan" => build.build_plan = true,
+                other => panic!("no example for {other}"),
+            }
+            build
+        };
+
+        for rule in COMMAND_OPTIONS {
+            assert!(
+                rule.commands.iter().all(|c| CARGO_COMMANDS.contains(c)),
+                "{} lists an unknown command",
+                rule.option
+            );
+            for command in rule.commands {
+                assert!(
+                    rule.hint.contains(&format!("`{command}`")),
+                    "the hint of {} does not name `{command}`",
+                    rule.option
+                );
+            }
+            let build = with_option(rule.option);
+            for command in CARGO_COMMANDS {
+                let result = validate_command_options(&build, &Command::new(*command));
+                if rule.commands.contains(command) {
+                    assert!(result.is_ok(), "{} with {command}", rule.option);
+                } else {
+                    let Err(CrossError::InvalidArgument(message)) = result else {
+                        panic!("{} with {command} should be rejected", rule.option);
+                    };
+                    assert!(message.contains(rule.option), "{message}");
+                    assert!(message.contains(&format!("`{} {command}`", program_name())));
+                }
+            }
+            // Commands that do not run cargo share the options without forwarding them
+            for command in [Command::setup(), Command::exec(), Command::cache_key()] {
+                assert!(validate_command_options(&build, &command).is_ok());
+            }
+        }
+        assert!(validate_command_options(&BuildArgs::default(), &Command::new("doc")).is_ok());
+    }
+
+    #[test]
+    fn test_invocation() {
+        let argv = |args: &[&str]| args.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let cases: &[(&[&str], bool, &str, usize)] = &[
+            // `cargo cross build`
+            (
+                &["/home/me/.cargo/bin/cargo-cross", "cross", "build"],
+                true,
+                "cargo cross",
+                2,
+            ),
+            // `cargo-cross build`, also from a cargo build script or `cargo run`
+            (
+                &["/home/me/.cargo/bin/cargo-cross", "build"],
+                false,
+                "cargo-cross",
+                1,
+            ),
+            (&["cargo-cross", "build"], true, "cargo-cross", 1),
+            (&["cargo-cross", "cross", "build"], false, "cargo-cross", 1),
+            // Renamed binaries
+            (&["/opt/bin/cargo-xc", "xc", "build"], true, "cargo xc", 2),
+            (
+                &["/opt/bin/cargo-xc", "cross", "build"],
+                true,
+                "cargo-xc",
+                1,
+            ),
+            (&["xc", "cross", "build"], true, "cargo cross", 2),
+            (&["/usr/local/bin/xc", "build"], false, "xc", 1),
+            (&[], false, "cargo-cross", 1),
+        ];
+        for (args, under_cargo, name, skip) in cases {
+            assert_eq!(
+                invocation(&argv(args), *under_cargo),
+                ((*name).to_string(), *skip),
+                "{args:?} under cargo: {under_cargo}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_help_examples_follow_program_name() {
+        let long_about = |cmd: &clap::Command, name: &str| {
+            cmd.find_subcommand(name)
+                .and_then(clap::Command::get_long_about)
+                .map(ToString::to_string)
+                .unwrap()
+        };
+        let long_help = |cmd: &clap::Command, name: &str, id: &str| {
+            cmd.find_subcommand(name)
+                .and_then(|sub| sub.get_arguments().find(|arg| arg.get_id() == id))
+                .and_then(clap::Arg::get_long_help)
+                .map(ToString::to_string)
+                .unwrap()
+        };
+
+        let cmd = with_program_name(Cli::command(), "cargo-cross");
+        assert!(long_about(&cmd, "setup").contains("eval \"$(cargo-cross setup -t"));
+        assert!(long_about(&cmd, "sync").contains("cargo-cross +nightly sync"));
+        assert!(long_help(&cmd, "run", "sequential_runs").contains("`cargo-cross run`"));
+        assert!(!long_about(&cmd, "clean").contains("cargo cross"));
+
+        let cmd = with_program_name(Cli::command(), "cargo cross");
+        assert!(long_about(&cmd, "setup").contains("eval \"$(cargo cross setup -t"));
+    }
+
+    #[test]
+    fn test_error_hints_name_the_invocation() {
+        let prog = program_name();
+        let hint = format!("Use '{prog} targets' to see available targets");
+        let not_found = CrossError::TargetNotFound {
+            target: "m68k-unknown-linux-gnu".to_string(),
+        };
+        let no_match = CrossError::NoMatchingTargets {
+            pattern: "m68k-*".to_string(),
+        };
+        for err in [not_found, no_match] {
+            let message = err.to_string();
+            assert!(message.ends_with(&hint), "{message}");
+            assert!(!message.contains("--list-targets"), "{message}");
+        }
+
+        let err = parse(&[
+            "cargo-cross",
+            "run",
+            "-t",
+            "x86_64-unknown-linux-musl,aarch64-unknown-linux-musl",
+        ])
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains(&format!("`{prog} run` got 2 targets")),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_incompatible_options_are_rejected() {
+        let err = parse(&["cargo-cross", "check", "--artifact-dir", "dist"]).unwrap_err();
+        assert!(err.to_string().contains(&format!(
+            "--artifact-dir cannot be used with `{} check`",
+            program_name()
+        )));
+        let err = parse(&["cargo-cross", "run", "--bench", "speed"]).unwrap_err();
+        assert!(err.to_string().contains("bench --bench <NAME>"));
+        assert!(parse(&["cargo-cross", "build", "--", "--help"]).is_err());
+        assert!(parse(&["cargo-cross", "run", "--", "--help"]).is_ok());
+    }
+
+    #[test]
+    fn test_target_policy_applies_to_expanded_targets() {
+        let path =
+            std::env::temp_dir().join(format!("cargo-cross-policy-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"deny": [{"pattern": "mips*", "message": "mips is not supported by SecOps, use aarch64 instead"}]}"#,
+        )
+        .unwrap();
+        let policy = path.to_str().unwrap();
+
+        let err = parse(&[
+            "cargo-cross",
+            "build",
+            "-t",
+            "mips*-linux-musl*,aarch64-unknown-linux-musl",
+            "--target-policy",
+            policy,
+        ])
+        .unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.contains("mips-unknown-linux-musl is denied by 'mips*': mips is not supported"),
+            "{message}"
+        );
+        assert!(!message.contains("aarch64-unknown-linux-musl"), "{message}");
+
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "-t",
+            "mips-unknown-linux-musl",
+            "--target-policy",
+            policy,
+            "--policy-warn-only",
+        ])
+        .unwrap();
+        assert_eq!(args.targets, ["mips-unknown-linux-musl"]);
+        assert!(parse(&[
+            "cargo-cross",
+            "build",
+            "-t",
+            "aarch64-unknown-linux-musl",
+            "--target-policy",
+            policy
+        ])
+        .is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_target_policy_checks_the_host_target() {
+        let path = std::env::temp_dir().join(format!(
+            "cargo-cross-policy-host-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(&path, r#"{"allow": ["no-such-arch-*"]}"#).unwrap();
+        let policy = path.to_str().unwrap();
+        let host = config::HostPlatform::detect().triple;
+
+        // Without -t the build is for the host triple, and host-tuple stands for it
+        for extra in [&[][..], &["-t", "host-tuple"][..]] {
+            let mut argv = vec!["cargo-cross", "build", "--target-policy", policy];
+            argv.extend(extra);
+            let message = parse(&argv).unwrap_err().to_string();
+            assert!(message.contains(&host), "{message}");
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_run_with_several_targets_needs_sequential_runs() {
+        let targets = "aarch64-unknown-linux-musl,x86_64-unknown-linux-musl";
+        let err = parse(&["cargo-cross", "run", "-t", targets]).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("got 2 targets"), "{message}");
+        assert!(message.contains("--bin"), "{message}");
+        assert!(message.contains("--sequential-runs"), "{message}");
+
+        let args = parse(&["cargo-cross", "run", "-t", targets, "--sequential-runs"]).unwrap();
+        assert!(args.sequential_runs);
+        assert_eq!(args.targets.len(), 2);
+        assert!(parse(&["cargo-cross", "run", "-t", "aarch64-unknown-linux-musl"]).is_ok());
+        assert!(parse(&["cargo-cross", "build", "-t", targets]).is_ok());
+        assert!(parse(&["cargo-cross", "build", "--sequential-runs"]).is_err());
+    }
+
+    #[test]
+    fn test_cargo_arg_is_kept_whole() {
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "--cargo-arg",
+            "--config",
+            "--cargo-arg",
+            "build.rustflags = [\"-C\", \"debuginfo=1\"]",
+            "--args",
+            "--offline",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.cargo_args,
+            vec![
+                "--offline",
+                "--config",
+                "build.rustflags = [\"-C\", \"debuginfo=1\"]"
+            ]
+        );
+    }
+
     // Target validation tests
 
     #[test]
-    fn test_invalid_target_triple_uppercase() {
-        let result = parse(&["cargo-cross", "build", "-t", "X86_64-unknown-linux-musl"]);
-        assert!(result.is_err());
-        let err = result.unwrap_err();
+    fn test_uppercase_target_triple_is_lowercased() {
+        let args = parse(&["cargo-cross", "build", "-t", "X86_64-Unknown-Linux-MUSL"]).unwrap();
+        assert_eq!(args.targets, vec!["x86_64-unknown-linux-musl"]);
+    }
+
+    #[test]
+    fn test_renamed_target_uses_current_name() {
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "-t",
+            "MIPS64-unknown-linux-musl,mips64-unknown-linux-muslabi64,wasm32-wasi",
+        ])
+        .unwrap();
+        // The former and current name are one target, and unknown targets are renamed too
+        assert_eq!(
+            args.targets,
+            vec!["mips64-unknown-linux-muslabi64", "wasm32-wasip1"]
+        );
+        assert!(config::get_target_config(&args.targets[0]).is_some());
+
+        // Only the toolchain's own list keeps a former name
+        assert!(toolchain_lists_target(None, "x86_64-unknown-linux-gnu"));
+        assert!(!toolchain_lists_target(None, "wasm32-wasi"));
+        assert!(!toolchain_lists_target(
+            Some("cargo-cross-no-such-toolchain"),
+            "x86_64-unknown-linux-gnu"
+        ));
+
+        let warning = renamed_target_warning(config::renamed_target("wasm32-wasi").unwrap());
         assert!(
-            matches!(err, CrossError::InvalidTargetTriple { target, char } if target == "X86_64-unknown-linux-musl" && char == 'X')
+            warning.starts_with("Target wasm32-wasi was renamed to wasm32-wasip1 in Rust 1.78"),
+            "{warning}"
+        );
+    }
+
+    #[test]
+    fn test_invalid_target_triple_shell_metacharacters() {
+        let err = parse(&[
+            "cargo-cross",
+            "build",
+            "-t",
+            "x86_64-unknown-linux-gnu;rm -rf",
+        ])
+        .unwrap_err();
+        assert!(
+            matches!(err, CrossError::InvalidTargetTriple { ref target, char } if target == "x86_64-unknown-linux-gnu;rm -rf" && char == ';'),
+            "{err}"
+        );
+        // Case mistakes are not fixed up when the triple is invalid anyway
+        let err = parse(&["cargo-cross", "build", "-t", "X86_64$(id)"]).unwrap_err();
+        assert!(matches!(
+            err,
+            CrossError::InvalidTargetTriple { char: '$', .. }
+        ));
+    }
+
+    #[test]
+    fn test_split_target_value_ci_formats() {
+        let split = |value: &str| split_target_value(value).unwrap();
+        // YAML block scalar with bullets
+        assert_eq!(
+            split("- x86_64-unknown-linux-musl\n  - aarch64-unknown-linux-musl\n"),
+            ["x86_64-unknown-linux-musl", "aarch64-unknown-linux-musl"]
+        );
+        // JSON array from toJSON/fromJSON
+        assert_eq!(
+            split(r#"["x86_64-unknown-linux-musl", "*-apple-darwin"]"#),
+            ["x86_64-unknown-linux-musl", "*-apple-darwin"]
+        );
+        assert!(split("[]").is_empty());
+        // Mixed commas and newlines, quoted entries, blank lines
+        assert_eq!(
+            split("'x86_64-unknown-linux-musl', \"aarch64-unknown-linux-musl\"\n\n- 'armv7-*'\n-"),
+            [
+                "x86_64-unknown-linux-musl",
+                "aarch64-unknown-linux-musl",
+                "armv7-*"
+            ]
+        );
+        // Glob classes are not JSON
+        assert_eq!(split("[ai]*-linux-musl"), ["[ai]*-linux-musl"]);
+
+        for (value, expected) in [
+            (r#"["x86_64-unknown-linux-musl""#, "does not parse"),
+            (r#"["x86_64-unknown-linux-musl", 3]"#, "holds 3"),
+        ] {
+            let err = split_target_value(value).unwrap_err();
+            assert!(matches!(err, CrossError::InvalidArgument(_)), "{err}");
+            assert!(err.to_string().contains(expected), "{err}");
+        }
+
+        for value in [
+            "--target=- x86_64-unknown-linux-musl\n- aarch64-unknown-linux-musl",
+            r#"--target=["x86_64-unknown-linux-musl", "aarch64-unknown-linux-musl"]"#,
+        ] {
+            let args = parse(&["cargo-cross", "build", value]).unwrap();
+            assert_eq!(
+                args.targets,
+                ["x86_64-unknown-linux-musl", "aarch64-unknown-linux-musl"]
+            );
+        }
+        // An unbalanced quote is still no target triple
+        let err =
+            parse(&["cargo-cross", "build", "-t", "\"x86_64-unknown-linux-musl"]).unwrap_err();
+        assert!(
+            matches!(err, CrossError::InvalidTargetTriple { char: '"', .. }),
+            "{err}"
         );
     }
 
@@ -3560,13 +6740,9 @@ mod tests {
     }
 
     #[test]
-    fn test_invalid_target_triple_dot() {
-        let result = parse(&["cargo-cross", "build", "-t", "x86_64.unknown-linux-musl"]);
-        assert!(result.is_err());
-        let err = result.unwrap_err();
-        assert!(
-            matches!(err, CrossError::InvalidTargetTriple { target, char } if target == "x86_64.unknown-linux-musl" && char == '.')
-        );
+    fn test_target_triple_with_dot() {
+        let args = parse(&["cargo-cross", "build", "-t", "thumbv8m.main-none-eabi"]).unwrap();
+        assert_eq!(args.targets, vec!["thumbv8m.main-none-eabi"]);
     }
 
     #[test]
@@ -3629,16 +6805,28 @@ mod tests {
     }
 
     #[test]
-    fn test_validate_target_triple() {
-        assert!(validate_target_triple("x86_64-unknown-linux-musl").is_ok());
-        assert!(validate_target_triple("aarch64-unknown-linux-gnu").is_ok());
-        assert!(validate_target_triple("armv7-unknown-linux-gnueabihf").is_ok());
-        assert!(validate_target_triple("i686-pc-windows-msvc").is_ok());
-        assert!(validate_target_triple("x86_64-pc-windows-msvc").is_ok());
-        assert!(validate_target_triple("X86_64-unknown-linux-musl").is_err()); // uppercase X
-        assert!(validate_target_triple("x86_64*linux").is_err()); // special char *
-        assert!(validate_target_triple("x86_64.linux").is_err()); // special char .
-        assert!(validate_target_triple("x86_64 linux").is_err()); // space
+    fn test_normalize_target_triple() {
+        for valid in [
+            "x86_64-unknown-linux-musl",
+            "armv7-unknown-linux-gnueabihf",
+            "x86_64-pc-windows-msvc",
+            "thumbv8m.main-none-eabi",
+        ] {
+            assert_eq!(normalize_target_triple(valid).unwrap(), valid);
+        }
+        assert_eq!(
+            normalize_target_triple("AARCH64-unknown-linux-gnu").unwrap(),
+            "aarch64-unknown-linux-gnu"
+        );
+        assert!(normalize_target_triple("x86_64*linux").is_err()); // special char *
+        assert!(normalize_target_triple("x86_64 linux").is_err()); // space
+        assert!(normalize_target_triple("../x86_64").is_err()); // path separator
+        assert!(normalize_target_triple("x86_64-ünknown").is_err()); // non-ASCII
+
+        // Every target a pattern can expand to passes the same check
+        for target in config::all_targets() {
+            assert_eq!(normalize_target_triple(target).unwrap(), target);
+        }
     }
 
     // Short argument concatenation tests (no separator between flag and value)
@@ -3658,7 +6846,7 @@ mod tests {
     #[test]
     fn test_short_concat_package() {
         let args = parse(&["cargo-cross", "build", "-pmypackage"]).unwrap();
-        assert_eq!(args.package, Some("mypackage".to_string()));
+        assert_eq!(args.package, vec!["mypackage"]);
     }
 
     #[test]
@@ -3693,7 +6881,7 @@ mod tests {
         assert_eq!(args.targets, vec!["x86_64-unknown-linux-musl"]);
         assert_eq!(args.features, Some("foo,bar".to_string()));
         assert_eq!(args.jobs, Some("8".to_string()));
-        assert_eq!(args.package, Some("mypkg".to_string()));
+        assert_eq!(args.package, vec!["mypkg"]);
     }
 
     #[test]
@@ -3814,4 +7002,345 @@ mod tests {
         assert_eq!(args.targets, vec!["aarch64_be-unknown-linux-musl"]);
         assert_eq!(args.glibc_version, ""); // default is empty string
     }
+
+    #[test]
+    fn test_parse_no_target_install() {
+        let args = parse(&["cargo-cross", "build", "--no-target-install"]).unwrap();
+        assert!(args.no_target_install);
+        let args = parse(&["cargo-cross", "build"]).unwrap();
+        assert!(!args.no_target_install);
+    }
+
+    #[test]
+    fn test_parse_per_target_dir() {
+        let args = parse(&["cargo-cross", "build", "--per-target-dir"]).unwrap();
+        assert!(args.per_target_dir);
+        let args = parse(&["cargo-cross", "build"]).unwrap();
+        assert!(!args.per_target_dir);
+    }
+
+    #[test]
+    fn test_parse_toolchain_cache_limit() {
+        let args = parse(&["cargo-cross", "build", "--toolchain-cache-limit", "20G"]).unwrap();
+        assert_eq!(args.toolchain_cache_limit, Some(20 << 30));
+        assert!(parse(&["cargo-cross", "build", "--toolchain-cache-limit", "big"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_cache_key() {
+        let args: Vec<String> = [
+            "cargo-cross",
+            "cache-key",
+            "-t",
+            "aarch64-unknown-linux-musl",
+            "--strict-cache",
+        ]
+        .iter()
+        .map(std::string::ToString::to_string)
+        .collect();
+        match parse_args_from(args).unwrap() {
+            ParseResult::CacheKey(args) => {
+                assert_eq!(args.command.as_str(), "cache-key");
+                assert_eq!(args.targets, vec!["aarch64-unknown-linux-musl"]);
+                assert!(args.strict_cache);
+            }
+            _ => panic!("expected CacheKey"),
+        }
+    }
+
+    #[test]
+    fn test_parse_clean_generated() {
+        let parse_clean = |argv: &[&str]| {
+            parse_args_from(argv.iter().map(std::string::ToString::to_string).collect())
+        };
+        match parse_clean(&[
+            "cargo-cross",
+            "clean",
+            "--generated",
+            "--cross-compiler-dir",
+            "/cross",
+        ])
+        .unwrap()
+        {
+            ParseResult::CleanGenerated(args) => {
+                assert_eq!(args.command.as_str(), "clean");
+                assert_eq!(args.cross_compiler_dir, PathBuf::from("/cross"));
+            }
+            _ => panic!("expected CleanGenerated"),
+        }
+        assert!(parse_clean(&["cargo-cross", "clean"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_explicit_target() {
+        let host = config::HostPlatform::detect().triple;
+
+        let auto = parse(&["cargo-cross", "build"]).unwrap();
+        assert_eq!(auto.explicit_target, ExplicitTarget::Auto);
+        assert!(auto.no_cargo_target);
+        assert!(auto.skip_target_arg("host-tuple", &host));
+        assert!(!auto.skip_target_arg(&host, &host));
+
+        let always = parse(&["cargo-cross", "build", "--explicit-target", "always"]).unwrap();
+        assert_eq!(always.targets, vec![host.clone()]);
+        assert!(!always.no_cargo_target);
+        assert!(!always.skip_target_arg("host-tuple", &host));
+
+        let never = parse(&[
+            "cargo-cross",
+            "build",
+            "--explicit-target=never",
+            "-t",
+            &host,
+        ])
+        .unwrap();
+        assert!(!never.no_cargo_target);
+        assert!(never.skip_target_arg(&host, &host));
+        assert!(never.skip_target_arg("host-tuple", &host));
+        assert!(!never.skip_target_arg("riscv64gc-unknown-none-elf", &host));
+
+        assert!(parse(&["cargo-cross", "build", "--explicit-target", "sometimes"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_bloat() {
+        let parse_bloat = |argv: &[&str]| match parse_args_from(
+            argv.iter().map(std::string::ToString::to_string).collect(),
+        )? {
+            ParseResult::Bloat(bloat) => Ok(bloat),
+            _ => panic!("expected Bloat"),
+        };
+        let bloat = parse_bloat(&[
+            "cargo-cross",
+            "bloat",
+            "-t",
+            "aarch64-unknown-linux-musl",
+            "--release",
+            "--crates",
+            "--filter",
+            "serde",
+            "-n",
+            "5",
+        ])
+        .unwrap();
+        assert_eq!(bloat.args.command.as_str(), "bloat");
+        assert_eq!(bloat.args.targets, vec!["aarch64-unknown-linux-musl"]);
+        assert_eq!(bloat.args.profile, "release");
+        assert_eq!(
+            bloat.options,
+            crate::bloat::BloatOptions {
+                crates: true,
+                filter: Some("serde".to_string()),
+                top: 5,
+            }
+        );
+
+        let bloat =
+            parse_bloat(&["cargo-cross", "bloat", "-t", "x86_64-unknown-linux-musl"]).unwrap();
+        assert_eq!(bloat.options, crate::bloat::BloatOptions::default());
+
+        let Err(CrossError::InvalidArgument(message)) = parse_bloat(&[
+            "cargo-cross",
+            "bloat",
+            "-t",
+            "x86_64-unknown-linux-musl,aarch64-unknown-linux-musl",
+        ]) else {
+            panic!("several targets should be rejected");
+        };
+        assert!(message.contains("one target at a time"), "{message}");
+    }
+
+    #[test]
+    fn test_parse_query_tools() {
+        let argv = [
+            "cargo-cross",
+            "query",
+            "tools",
+            "-t",
+            "aarch64-unknown-linux-musl",
+            "--format",
+            "json",
+            "--glibc-version",
+            "2.31",
+        ];
+        match parse_args_from(argv.iter().map(std::string::ToString::to_string).collect()).unwrap()
+        {
+            ParseResult::QueryTools(query) => {
+                assert_eq!(query.args.command.as_str(), "query");
+                assert_eq!(query.args.targets, vec!["aarch64-unknown-linux-musl"]);
+                assert_eq!(query.args.glibc_version, "2.31");
+                assert_eq!(query.format, QueryOutputFormat::Json);
+            }
+            _ => panic!("expected QueryTools"),
+        }
+    }
+
+    #[test]
+    fn test_target_cpu_for() {
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "--target-cpu",
+            "s390x-unknown-linux-gnu=z14,x86-64-v2",
+            "--target-cpu",
+            "powerpc64le-unknown-linux-gnu=pwr9",
+        ])
+        .unwrap();
+        assert_eq!(args.target_cpu_for("s390x-unknown-linux-gnu"), Some("z14"));
+        assert_eq!(
+            args.target_cpu_for("powerpc64le-unknown-linux-gnu"),
+            Some("pwr9")
+        );
+        assert_eq!(
+            args.target_cpu_for("x86_64-unknown-linux-musl"),
+            Some("x86-64-v2")
+        );
+
+        let args = parse(&["cargo-cross", "build"]).unwrap();
+        assert_eq!(args.target_cpu_for("s390x-unknown-linux-gnu"), None);
+    }
+
+    #[test]
+    fn test_github_export_target() {
+        let export = |argv: &[&str]| {
+            parse(argv)
+                .unwrap()
+                .github_export_target()
+                .map(|t| t.map(str::to_string))
+        };
+        assert_eq!(
+            export(&["cargo-cross", "build", "-t", "aarch64-unknown-linux-musl"]).unwrap(),
+            None
+        );
+        assert_eq!(
+            export(&[
+                "cargo-cross",
+                "build",
+                "-t",
+                "aarch64-unknown-linux-musl",
+                "--export-github-env"
+            ])
+            .unwrap()
+            .as_deref(),
+            Some("aarch64-unknown-linux-musl")
+        );
+        let both = "aarch64-unknown-linux-musl,x86_64-unknown-linux-musl";
+        assert!(export(&["cargo-cross", "build", "-t", both, "--export-github-env"]).is_err());
+        assert_eq!(
+            export(&[
+                "cargo-cross",
+                "build",
+                "-t",
+                both,
+                "--export-github-env",
+                "--export-target",
+                "x86_64-unknown-linux-musl",
+            ])
+            .unwrap()
+            .as_deref(),
+            Some("x86_64-unknown-linux-musl")
+        );
+        assert!(export(&[
+            "cargo-cross",
+            "build",
+            "-t",
+            both,
+            "--export-github-env",
+            "--export-target",
+            "riscv64gc-unknown-linux-musl",
+        ])
+        .is_err());
+        assert!(parse(&[
+            "cargo-cross",
+            "build",
+            "--export-target",
+            "x86_64-unknown-linux-musl"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_runner_required_defaults() {
+        let required = |argv: &[&str]| parse(argv).unwrap().runner_required();
+        assert!(required(&["cargo-cross", "test"]));
+        assert!(required(&["cargo-cross", "bench"]));
+        assert!(!required(&["cargo-cross", "run"]));
+        assert!(required(&["cargo-cross", "run", "--require-runner"]));
+        assert!(!required(&["cargo-cross", "test", "--allow-no-runner"]));
+        assert!(!required(&["cargo-cross", "test", "--args", "--no-run"]));
+        assert!(parse(&[
+            "cargo-cross",
+            "test",
+            "--require-runner",
+            "--allow-no-runner"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_split_target_config() {
+        assert_eq!(
+            split_target_config("aarch64-*=>build.jobs=4"),
+            (Some("aarch64-*"), "build.jobs=4")
+        );
+        assert_eq!(
+            split_target_config(" *-musl => profile.release.lto=true"),
+            (Some("*-musl"), "profile.release.lto=true")
+        );
+        assert_eq!(split_target_config("build.jobs=4"), (None, "build.jobs=4"));
+        // `=>` inside a TOML value is not a target selector
+        assert_eq!(
+            split_target_config("alias.x=\"a=>b\""),
+            (None, "alias.x=\"a=>b\"")
+        );
+        assert_eq!(split_target_config("=>x=1"), (None, "=>x=1"));
+    }
+
+    #[test]
+    fn test_parse_cargo_config_validates_target_glob() {
+        assert!(parse_cargo_config("aarch64-*=>build.jobs=4").is_ok());
+        assert!(parse_cargo_config("build.jobs=4").is_ok());
+        assert!(parse_cargo_config("aarch64-[=>build.jobs=4").is_err());
+        assert!(parse_cargo_config("aarch64-*=>").is_err());
+        assert!(parse(&["cargo-cross", "build", "--config", "x86_64-{=>a=1"]).is_err());
+    }
+
+    #[test]
+    fn test_cargo_config_for_target() {
+        let args = parse(&[
+            "cargo-cross",
+            "build",
+            "--config",
+            "build.jobs=4",
+            "--config",
+            "aarch64-*=>build.rustflags=[\"-Ctarget-feature=+lse\"]",
+            "--config",
+            "*-musl=>profile.release.lto=true",
+            "--config",
+            "build.jobs=2",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            args.cargo_config_for("aarch64-unknown-linux-musl"),
+            vec![
+                "build.jobs=4",
+                "build.rustflags=[\"-Ctarget-feature=+lse\"]",
+                "profile.release.lto=true",
+                "build.jobs=2",
+            ]
+        );
+        assert_eq!(
+            args.cargo_config_for("aarch64-unknown-linux-gnu"),
+            vec![
+                "build.jobs=4",
+                "build.rustflags=[\"-Ctarget-feature=+lse\"]",
+                "build.jobs=2",
+            ]
+        );
+        assert_eq!(
+            args.cargo_config_for("x86_64-pc-windows-gnu"),
+            vec!["build.jobs=4", "build.jobs=2"]
+        );
+    }
 }
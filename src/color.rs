@@ -4,7 +4,81 @@
 //! allowing multiple colors within a single log line.
 
 use colored::{ColoredString, Colorize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
 use std::io::{self, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+
+/// Severity of a cargo-cross message, ordered from least to most verbose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    /// Map `--quiet` and the `-v` count to the most verbose level that gets printed
+    #[must_use]
+    pub const fn from_verbosity(quiet: bool, verbose_level: u8) -> Self {
+        if quiet {
+            Self::Warn
+        } else if verbose_level > 0 {
+            Self::Debug
+        } else {
+            Self::Info
+        }
+    }
+
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Error,
+            1 => Self::Warn,
+            2 => Self::Info,
+            _ => Self::Debug,
+        }
+    }
+}
+
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+/// Set the most verbose level that cargo-cross prints
+pub fn set_log_level(level: LogLevel) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Current log level; `CARGO_CROSS_SILENT` limits output to errors
+#[must_use]
+pub fn log_level() -> LogLevel {
+    #[cfg(test)]
+    if let Some(level) = capture::level() {
+        return level;
+    }
+    if std::env::var_os("CARGO_CROSS_SILENT").is_some() {
+        return LogLevel::Error;
+    }
+    LogLevel::from_u8(LOG_LEVEL.load(Ordering::Relaxed))
+}
+
+/// Whether messages at `level` are printed
+#[must_use]
+pub fn log_enabled(level: LogLevel) -> bool {
+    level <= log_level()
+}
+
+/// Print a line to stderr if `level` is enabled, keeping stdout free for cargo's output
+fn emit(level: LogLevel, line: impl Display) {
+    if !log_enabled(level) {
+        return;
+    }
+    #[cfg(test)]
+    if capture::push(&line) {
+        return;
+    }
+    eprintln!("{line}");
+}
 
 #[must_use]
 pub fn cyan(s: &str) -> ColoredString {
@@ -55,37 +129,102 @@ pub fn dim(s: &str) -> ColoredString {
 
 /// Example: `log_info(&format!("Downloading` {} to {}", green(url), green(path)))
 pub fn log_info(msg: &str) {
-    if logs_enabled() {
-        println!("{}", msg.bright_blue().bold());
-    }
+    emit(LogLevel::Info, msg.bright_blue().bold());
 }
 
 /// Log a success message (bold green, supports embedded colors)
 /// Example: `log_success(&format!("Completed` in {}s", `yellow(&secs.to_string())`))
 pub fn log_success(msg: &str) {
-    if logs_enabled() {
-        println!("{}", msg.bright_green().bold());
-    }
+    emit(LogLevel::Info, msg.bright_green().bold());
 }
 
 /// Log a warning message (bold yellow, supports embedded colors)
 pub fn log_warning(msg: &str) {
-    if logs_enabled() {
-        println!("{}", msg.bright_yellow().bold());
+    emit(LogLevel::Warn, msg.bright_yellow().bold());
+}
+
+/// Warnings logged with [`log_warning_once`], counting how often each key repeated
+#[derive(Debug, Default)]
+pub struct WarningRegistry {
+    suppressed: BTreeMap<String, usize>,
+}
+
+impl WarningRegistry {
+    /// Note a warning; true the first time `key` is seen, when the warning is printed
+    pub fn note(&mut self, key: &str) -> bool {
+        match self.suppressed.get_mut(key) {
+            Some(count) => {
+                *count += 1;
+                false
+            }
+            None => {
+                self.suppressed.insert(key.to_string(), 0);
+                true
+            }
+        }
+    }
+
+    /// Line summarizing the repeated warnings, if any were suppressed
+    #[must_use]
+    pub fn summary(&self) -> Option<String> {
+        let repeated: Vec<&str> = self
+            .suppressed
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(key, _)| key.as_str())
+            .collect();
+        let total: usize = self.suppressed.values().sum();
+        (total > 0).then(|| {
+            let noun = if total == 1 { "warning" } else { "warnings" };
+            format!(
+                "{total} similar {noun} suppressed (keys: {})",
+                repeated.join(", ")
+            )
+        })
+    }
+}
+
+static WARNINGS: Mutex<WarningRegistry> = Mutex::new(WarningRegistry {
+    suppressed: BTreeMap::new(),
+});
+
+/// Log a warning that tends to repeat for every target (a missing runner, a fallback)
+/// only the first time its `key` is seen; later ones are counted for
+/// [`log_suppressed_warnings`]
+pub fn log_warning_once(key: &str, msg: &str) {
+    let first = WARNINGS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .note(key);
+    if first {
+        log_warning(msg);
     }
 }
 
-/// Log an error message (bold red, supports embedded colors) to stderr
+/// Print how many repeated warnings [`log_warning_once`] held back, at the end of a run
+pub fn log_suppressed_warnings() {
+    let summary = WARNINGS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .summary();
+    if let Some(summary) = summary {
+        log_warning(&summary);
+    }
+}
+
+/// Log an error message (bold red, supports embedded colors)
 pub fn log_error(msg: &str) {
-    eprintln!("{}", msg.bright_red().bold());
+    emit(LogLevel::Error, msg.bright_red().bold());
+}
+
+/// Log a debug message (dimmed), shown with `-v`
+pub fn log_debug(msg: &str) {
+    emit(LogLevel::Debug, msg.dimmed());
 }
 
 /// Print a separator line
 pub fn print_separator() {
-    if logs_enabled() {
-        let width = terminal_width();
-        println!("{}", "-".repeat(width).dimmed());
-    }
+    emit(LogLevel::Info, "-".repeat(terminal_width()).dimmed());
 }
 
 /// Get terminal width, defaulting to 80 if unavailable
@@ -131,21 +270,176 @@ pub fn print_config_header() {
     log_info("Execution configuration:");
 }
 
-/// Print environment variables header
+/// Print one line of the execution configuration
+pub fn print_config(key: &str, value: &str) {
+    emit(LogLevel::Info, format_config(key, value));
+}
+
+/// Print environment variables header (debug level)
 pub fn print_env_header() {
-    log_info("Environment variables:");
+    emit(
+        LogLevel::Debug,
+        "Environment variables:".bright_blue().bold(),
+    );
+}
+
+/// Print one environment variable (debug level)
+pub fn print_env(key: &str, value: &str) {
+    emit(LogLevel::Debug, format_env(key, value));
 }
 
-/// Print run command header
+/// Print one environment variable followed by where it came from (debug level)
+pub fn print_env_with_source(key: &str, value: &str, source: &str) {
+    emit(
+        LogLevel::Debug,
+        format!(
+            "{}  {}",
+            format_env(key, value),
+            format!("({source})").dimmed()
+        ),
+    );
+}
+
+/// Print run command header (debug level)
 pub fn print_run_header() {
-    log_info("Run command:");
+    emit(LogLevel::Debug, "Run command:".bright_blue().bold());
 }
 
-/// Flush stdout
+/// Print the command about to run (debug level)
+pub fn print_command(cmd: &str) {
+    emit(LogLevel::Debug, format_command(cmd));
+}
+
+/// Flush stdout and stderr
 pub fn flush() {
     let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
 }
 
-fn logs_enabled() -> bool {
-    std::env::var_os("CARGO_CROSS_SILENT").is_none()
+/// Per-thread log capture so tests can assert on output without touching stderr
+#[cfg(test)]
+pub(crate) mod capture {
+    use super::LogLevel;
+    use std::cell::RefCell;
+    use std::fmt::Display;
+
+    thread_local! {
+        static CAPTURE: RefCell<Option<(LogLevel, Vec<String>)>> = const { RefCell::new(None) };
+    }
+
+    /// Run `f` with `level` in effect on this thread and return the lines it logged
+    pub fn logs(level: LogLevel, f: impl FnOnce()) -> Vec<String> {
+        CAPTURE.with(|c| *c.borrow_mut() = Some((level, Vec::new())));
+        f();
+        CAPTURE
+            .with(|c| c.borrow_mut().take())
+            .map(|(_, lines)| lines)
+            .unwrap_or_default()
+    }
+
+    pub(super) fn level() -> Option<LogLevel> {
+        CAPTURE.with(|c| c.borrow().as_ref().map(|(level, _)| *level))
+    }
+
+    pub(super) fn push(line: &dyn Display) -> bool {
+        CAPTURE.with(|c| {
+            c.borrow_mut().as_mut().is_some_and(|(_, lines)| {
+                lines.push(line.to_string());
+                true
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_level_from_verbosity() {
+        assert_eq!(LogLevel::from_verbosity(true, 0), LogLevel::Warn);
+        assert_eq!(LogLevel::from_verbosity(false, 0), LogLevel::Info);
+        assert_eq!(LogLevel::from_verbosity(false, 2), LogLevel::Debug);
+        for level in [
+            LogLevel::Error,
+            LogLevel::Warn,
+            LogLevel::Info,
+            LogLevel::Debug,
+        ] {
+            assert_eq!(LogLevel::from_u8(level as u8), level);
+        }
+    }
+
+    #[test]
+    fn test_warning_registry_dedups_by_key() {
+        let mut registry = WarningRegistry::default();
+        assert!(registry.note("wine-missing"));
+        assert!(registry.note("docker-runner"));
+        assert_eq!(registry.summary(), None);
+
+        assert!(!registry.note("wine-missing"));
+        assert!(!registry.note("wine-missing"));
+        assert!(!registry.note("docker-runner"));
+        assert!(registry.note("qemu-unavailable"));
+        assert_eq!(
+            registry.summary().as_deref(),
+            Some("3 similar warnings suppressed (keys: docker-runner, wine-missing)")
+        );
+    }
+
+    #[test]
+    fn test_log_warning_once_prints_first_occurrence() {
+        let lines = capture::logs(LogLevel::Info, || {
+            log_warning_once("test-once", "Wine not found for a");
+            log_warning_once("test-once", "Wine not found for b");
+        });
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("Wine not found for a"));
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| std::thread::spawn(|| WARNINGS.lock().unwrap().note("test-threads")))
+            .collect();
+        let first = threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .filter(|first| *first)
+            .count();
+        assert_eq!(first, 1);
+    }
+
+    #[test]
+    fn test_quiet_keeps_only_warnings_and_errors() {
+        let lines = capture::logs(LogLevel::Warn, || {
+            log_info("info line");
+            log_success("success line");
+            print_separator();
+            log_warning("warning line");
+            log_error("error line");
+            log_debug("debug line");
+        });
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("warning line"));
+        assert!(lines[1].contains("error line"));
+    }
+
+    #[test]
+    fn test_env_dump_and_command_echo_are_debug() {
+        let chatter = || {
+            print_config_header();
+            print_config("Profile", "dev");
+            print_env_header();
+            print_env("CC", "gcc");
+            print_run_header();
+            print_command("cargo build");
+        };
+
+        let info = capture::logs(LogLevel::Info, chatter);
+        assert_eq!(info.len(), 2);
+        assert!(info[1].contains("Profile"));
+
+        let debug = capture::logs(LogLevel::Debug, chatter);
+        assert_eq!(debug.len(), 6);
+        assert!(debug[3].contains("CC"));
+        assert!(debug[5].contains("cargo build"));
+    }
 }
@@ -0,0 +1,194 @@
+//! Compatibility with cross-rs habits: running cargo-cross as `cross` and the `CROSS_*`
+//! environment variables of cross-rs
+//!
+//! Only settings with a meaning outside a container are bridged. Docker and image settings
+//! are reported as ignored; nothing container-related is emulated.
+
+use crate::color;
+use std::path::Path;
+
+/// cross-rs variables copied to the cargo-cross variable of the same meaning, unless that
+/// one is set already
+const BRIDGED: &[(&str, &str)] = &[("CROSS_BUILD_STD", "BUILD_STD")];
+
+/// cross-rs container and image settings, which have no equivalent without Docker
+const CONTAINER_VARS: &[&str] = &[
+    "CROSS_CONTAINER_ENGINE",
+    "CROSS_CONTAINER_OPTS",
+    "CROSS_CONTAINER_USER_NAMESPACE",
+    "CROSS_CONTAINER_IN_CONTAINER",
+    "CROSS_REMOTE",
+    "CROSS_ROOTLESS_CONTAINER_ENGINE",
+    "CROSS_CUSTOM_TOOLCHAIN",
+    "CROSS_BUILD_DOCKERFILE",
+    "CROSS_BUILD_IMAGE",
+    "CROSS_BUILD_PRE_BUILD",
+];
+
+/// Whether the program was started as `cross` (a symlink or copy named like cross-rs)
+#[must_use]
+pub fn invoked_as_cross(argv0: &str) -> bool {
+    Path::new(argv0)
+        .file_stem()
+        .is_some_and(|stem| stem == "cross")
+}
+
+/// What to do about the cross-rs variables in an environment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrossRsEnv {
+    /// Variables to set, with their values
+    pub set: Vec<(String, String)>,
+    /// One note per cross-rs variable that has no effect, or a different one
+    pub notes: Vec<String>,
+}
+
+/// Map the cross-rs variables among `vars` to cargo-cross settings
+#[must_use]
+pub fn bridge_env(vars: &[(String, String)]) -> CrossRsEnv {
+    let get = |name: &str| {
+        vars.iter()
+            .find(|(key, value)| key == name && !value.trim().is_empty())
+            .map(|(_, value)| value.trim())
+    };
+    let mut bridged = CrossRsEnv::default();
+    for (from, to) in BRIDGED {
+        let Some(value) = get(from) else {
+            continue;
+        };
+        if get(to).is_some() {
+            bridged
+                .notes
+                .push(format!("{from} is ignored because {to} is set"));
+        } else {
+            bridged.set.push(((*to).to_string(), value.to_string()));
+        }
+    }
+    if let Some(path) = get("CROSS_CONFIG") {
+        bridged.notes.push(format!(
+            "CROSS_CONFIG={path} is not read: cargo-cross has no Cross.toml support; use its \
+             command-line options or environment variables instead"
+        ));
+    }
+    let mut names: Vec<&str> = vars
+        .iter()
+        .filter(|(_, value)| !value.trim().is_empty())
+        .map(|(key, _)| key.as_str())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    let passthrough: Vec<&str> = names
+        .iter()
+        .copied()
+        .filter(|name| {
+            *name == "CROSS_BUILD_ENV_PASSTHROUGH"
+                || (name.starts_with("CROSS_TARGET_") && name.ends_with("_ENV_PASSTHROUGH"))
+        })
+        .collect();
+    if !passthrough.is_empty() {
+        bridged.notes.push(format!(
+            "{} not needed: builds run on the host and see its whole environment",
+            passthrough.join(", ")
+        ));
+    }
+    let container: Vec<&str> = names
+        .iter()
+        .copied()
+        .filter(|name| {
+            CONTAINER_VARS.contains(name)
+                || (name.starts_with("CROSS_TARGET_") && name.ends_with("_IMAGE"))
+        })
+        .collect();
+    if !container.is_empty() {
+        bridged.notes.push(format!(
+            "{} ignored: cargo-cross does not use containers",
+            container.join(", ")
+        ));
+    }
+    bridged
+}
+
+/// Apply the cross-rs variables of the process environment before the command line is
+/// parsed, and note once when running as `cross`
+pub fn apply_cross_rs_env(argv0: Option<&str>) {
+    if argv0.is_some_and(invoked_as_cross) {
+        color::log_info(
+            "Running as `cross`: this is cargo-cross, which builds without Docker. Options \
+             work as in cargo-cross; cross-rs settings are bridged where they apply",
+        );
+    }
+    let vars: Vec<(String, String)> = std::env::vars()
+        .filter(|(key, _)| key.starts_with("CROSS_") || BRIDGED.iter().any(|(_, to)| to == key))
+        .collect();
+    let bridged = bridge_env(&vars);
+    for (name, value) in &bridged.set {
+        color::log_debug(&format!("Using {name}={value} from its cross-rs variable"));
+        std::env::set_var(name, value);
+    }
+    for note in &bridged.notes {
+        color::log_info(note);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_invoked_as_cross() {
+        assert!(invoked_as_cross("cross"));
+        assert!(invoked_as_cross("/home/me/.cargo/bin/cross"));
+        assert!(invoked_as_cross("cross.exe"));
+        assert!(!invoked_as_cross("cargo-cross"));
+        assert!(!invoked_as_cross("/usr/bin/crosstool"));
+    }
+
+    #[test]
+    fn test_bridge_env() {
+        assert_eq!(bridge_env(&[]), CrossRsEnv::default());
+
+        let bridged = bridge_env(&vars(&[
+            ("CROSS_BUILD_STD", "core,alloc"),
+            ("CROSS_CONFIG", "ci/Cross.toml"),
+            ("CROSS_BUILD_ENV_PASSTHROUGH", "RUST_LOG"),
+            (
+                "CROSS_TARGET_AARCH64_UNKNOWN_LINUX_MUSL_ENV_PASSTHROUGH",
+                "FOO",
+            ),
+            ("CROSS_CONTAINER_ENGINE", "podman"),
+            ("CROSS_TARGET_AARCH64_UNKNOWN_LINUX_MUSL_IMAGE", "my/image"),
+            ("CROSS_REMOTE", ""),
+        ]));
+        assert_eq!(
+            bridged.set,
+            [("BUILD_STD".to_string(), "core,alloc".to_string())]
+        );
+        assert_eq!(bridged.notes.len(), 3, "{:?}", bridged.notes);
+        assert!(bridged.notes[0].contains("CROSS_CONFIG=ci/Cross.toml is not read"));
+        assert!(bridged.notes[1].starts_with(
+            "CROSS_BUILD_ENV_PASSTHROUGH, CROSS_TARGET_AARCH64_UNKNOWN_LINUX_MUSL_ENV_PASSTHROUGH not needed"
+        ));
+        assert_eq!(
+            bridged.notes[2],
+            "CROSS_CONTAINER_ENGINE, CROSS_TARGET_AARCH64_UNKNOWN_LINUX_MUSL_IMAGE ignored: \
+             cargo-cross does not use containers"
+        );
+
+        // The cargo-cross variable wins
+        let bridged = bridge_env(&vars(&[
+            ("CROSS_BUILD_STD", "true"),
+            ("BUILD_STD", "false"),
+        ]));
+        assert!(bridged.set.is_empty());
+        assert_eq!(
+            bridged.notes,
+            ["CROSS_BUILD_STD is ignored because BUILD_STD is set"]
+        );
+    }
+}
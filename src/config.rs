@@ -33,6 +33,11 @@ pub const SUPPORTED_FREEBSD_VERSIONS: &[&str] = &["13", "14", "15"];
 /// Default FreeBSD version
 pub const DEFAULT_FREEBSD_VERSION: &str = "13";
 
+/// Ubuntu releases the Linux builds of osxcross and ioscross exist for, with the glibc
+/// version each one needs on the host
+pub const HOST_COMPAT_UBUNTU_VERSIONS: &[(&str, (u32, u32))] =
+    &[("20.04", (2, 31)), ("22.04", (2, 35)), ("24.04", (2, 39))];
+
 /// Default cross-compiler make version
 pub const DEFAULT_CROSS_MAKE_VERSION: &str = "v0.7.7";
 
@@ -124,6 +129,9 @@ pub enum Arch {
     X86_64h,
 }
 
+/// Host `(os, arch)` pairs with a published qemu-user-static bundle
+pub const QEMU_BUNDLE_HOSTS: &[(&str, &str)] = &[("linux", "x86_64"), ("linux", "aarch64")];
+
 impl Arch {
     #[must_use]
     pub const fn as_str(&self) -> &'static str {
@@ -176,6 +184,98 @@ impl Arch {
             _ => None,
         }
     }
+
+    /// Check whether a qemu-user-static bundle is published for the given host.
+    /// User-mode QEMU only exists for Linux, so only `linux-x86_64` and `linux-aarch64`
+    /// bundles are released; other hosts must use the Docker backend or WSL.
+    #[must_use]
+    pub fn qemu_bundle_available(host_os: &str, host_arch: &str) -> bool {
+        QEMU_BUNDLE_HOSTS.contains(&(host_os, host_arch))
+    }
+
+    /// Get the C compiler flag that selects a CPU baseline for this architecture.
+    /// x86, s390x, `LoongArch` and MIPS use `-march`; ARM, `PowerPC` and RISC-V use `-mcpu`.
+    #[must_use]
+    pub const fn c_cpu_flag(&self) -> &'static str {
+        match self {
+            Self::I586
+            | Self::I686
+            | Self::X86_64
+            | Self::X86_64h
+            | Self::S390x
+            | Self::Loongarch64
+            | Self::Mips
+            | Self::Mipsel
+            | Self::Mipsisa32r6
+            | Self::Mipsisa32r6el
+            | Self::Mipsisa64r6
+            | Self::Mipsisa64r6el
+            | Self::Mips64
+            | Self::Mips64el => "-march",
+            Self::Aarch64
+            | Self::Aarch64Be
+            | Self::Arm64e
+            | Self::Armv5
+            | Self::Armv6
+            | Self::Armv7
+            | Self::Powerpc64
+            | Self::Powerpc64le
+            | Self::Riscv32
+            | Self::Riscv64 => "-mcpu",
+        }
+    }
+
+    /// Build the C/C++ compiler flag for a rustc `target-cpu` name, e.g.
+    /// `z14` -> `-march=z14` on s390x and `pwr9` -> `-mcpu=power9` on `PowerPC`
+    #[must_use]
+    pub fn c_cpu_arg(&self, cpu: &str) -> String {
+        let c_cpu = match (self, cpu.strip_prefix("pwr")) {
+            (Self::Powerpc64 | Self::Powerpc64le, Some(generation)) => {
+                format!("power{generation}")
+            }
+            _ => cpu.to_string(),
+        };
+        format!("{}={c_cpu}", self.c_cpu_flag())
+    }
+
+    /// Get the QEMU `-cpu` model matching a rustc `target-cpu` name, when one is known
+    #[must_use]
+    pub fn qemu_cpu(&self, cpu: &str) -> Option<&'static str> {
+        let cpu = cpu.to_ascii_lowercase();
+        match self {
+            Self::S390x => match cpu.as_str() {
+                "z13" | "arch11" => Some("z13"),
+                "z14" | "arch12" => Some("z14"),
+                "z15" | "arch13" => Some("z15"),
+                "z16" | "arch14" => Some("z16"),
+                _ => None,
+            },
+            Self::Powerpc64 | Self::Powerpc64le => match cpu.as_str() {
+                "pwr8" | "power8" => Some("power8"),
+                "pwr9" | "power9" => Some("power9"),
+                "pwr10" | "power10" => Some("power10"),
+                _ => None,
+            },
+            Self::Aarch64 | Self::Aarch64Be => match cpu.as_str() {
+                "cortex-a53" => Some("cortex-a53"),
+                "cortex-a57" => Some("cortex-a57"),
+                "cortex-a72" => Some("cortex-a72"),
+                "cortex-a76" => Some("cortex-a76"),
+                "neoverse-n1" => Some("neoverse-n1"),
+                "neoverse-v1" => Some("neoverse-v1"),
+                "neoverse-n2" => Some("neoverse-n2"),
+                _ => None,
+            },
+            Self::X86_64 | Self::X86_64h => match cpu.as_str() {
+                "x86-64-v2" | "nehalem" => Some("Nehalem"),
+                "x86-64-v3" | "haswell" => Some("Haswell"),
+                "x86-64-v4" | "skylake-avx512" => Some("Skylake-Server"),
+                "skylake" => Some("Skylake-Client"),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
 }
 
 /// C library type
@@ -439,6 +539,69 @@ pub static TARGETS: std::sync::LazyLock<HashMap<&'static str, TargetConfig>> =
         configs.into_iter().map(|c| (c.target, c)).collect()
     });
 
+/// A target triple Rust renamed, with the release that introduced the new name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenamedTarget {
+    pub old: &'static str,
+    pub new: &'static str,
+    pub since: &'static str,
+}
+
+/// Former target names still found in CI matrices, mapped to the names rustc knows today
+pub const RENAMED_TARGETS: &[RenamedTarget] = &[
+    RenamedTarget {
+        old: "mips64-unknown-linux-musl",
+        new: "mips64-unknown-linux-muslabi64",
+        since: "1.48",
+    },
+    RenamedTarget {
+        old: "mips64el-unknown-linux-musl",
+        new: "mips64el-unknown-linux-muslabi64",
+        since: "1.48",
+    },
+    RenamedTarget {
+        old: "x86_64-sun-solaris",
+        new: "x86_64-pc-solaris",
+        since: "1.53",
+    },
+    RenamedTarget {
+        old: "x86_64-fuchsia",
+        new: "x86_64-unknown-fuchsia",
+        since: "1.72",
+    },
+    RenamedTarget {
+        old: "aarch64-fuchsia",
+        new: "aarch64-unknown-fuchsia",
+        since: "1.72",
+    },
+    RenamedTarget {
+        old: "wasm32-wasi",
+        new: "wasm32-wasip1",
+        since: "1.78",
+    },
+    RenamedTarget {
+        old: "wasm32-wasi-preview1-threads",
+        new: "wasm32-wasip1-threads",
+        since: "1.78",
+    },
+];
+
+/// Rename entry for a former target name
+#[must_use]
+pub fn renamed_target(target: &str) -> Option<&'static RenamedTarget> {
+    RENAMED_TARGETS.iter().find(|renamed| renamed.old == target)
+}
+
+/// Former names of `target`, oldest rename first
+#[must_use]
+pub fn former_names(target: &str) -> Vec<&'static str> {
+    RENAMED_TARGETS
+        .iter()
+        .filter(|renamed| renamed.new == target)
+        .map(|renamed| renamed.old)
+        .collect()
+}
+
 /// Get target configuration by name
 pub fn get_target_config(target: &str) -> Option<&'static TargetConfig> {
     TARGETS.get(target)
@@ -598,6 +761,16 @@ impl HostPlatform {
         self.os == "windows"
     }
 
+    /// Suffix for executables that run on this host (`.exe` on Windows)
+    #[must_use]
+    pub fn exe_ext(&self) -> &'static str {
+        if self.is_windows() {
+            ".exe"
+        } else {
+            ""
+        }
+    }
+
     /// Check if running on macOS/Darwin
     #[must_use]
     pub fn is_darwin(&self) -> bool {
@@ -653,6 +826,33 @@ mod tests {
         assert_eq!(targets[0], "x86_64-unknown-linux-gnu");
     }
 
+    #[test]
+    fn test_renamed_targets() {
+        let renamed = renamed_target("mips64-unknown-linux-musl").unwrap();
+        assert_eq!(renamed.new, "mips64-unknown-linux-muslabi64");
+        assert!(get_target_config(renamed.new).is_some());
+        assert_eq!(
+            former_names("mips64-unknown-linux-muslabi64"),
+            ["mips64-unknown-linux-musl"]
+        );
+        assert!(renamed_target("mips64-unknown-linux-muslabi64").is_none());
+        assert!(former_names("x86_64-unknown-linux-musl").is_empty());
+
+        for renamed in RENAMED_TARGETS {
+            // Old names are gone from rustc and never renamed twice
+            assert!(get_target_config(renamed.old).is_none(), "{}", renamed.old);
+            assert!(renamed_target(renamed.new).is_none(), "{}", renamed.new);
+            assert_eq!(
+                RENAMED_TARGETS
+                    .iter()
+                    .filter(|r| r.old == renamed.old)
+                    .count(),
+                1
+            );
+            assert!(renamed.since.starts_with("1."), "{}", renamed.since);
+        }
+    }
+
     #[test]
     fn test_glibc_versions() {
         assert!(SUPPORTED_GLIBC_VERSIONS.contains(&"2.28"));
@@ -686,6 +886,44 @@ mod tests {
         assert_eq!(Arch::Riscv64.qemu_binary_name(), Some("qemu-riscv64"));
     }
 
+    #[test]
+    fn test_qemu_bundle_available() {
+        assert!(Arch::qemu_bundle_available("linux", "x86_64"));
+        assert!(Arch::qemu_bundle_available("linux", "aarch64"));
+        assert!(!Arch::qemu_bundle_available("linux", "riscv64"));
+        assert!(!Arch::qemu_bundle_available("darwin", "aarch64"));
+        assert!(!Arch::qemu_bundle_available("windows", "aarch64"));
+        assert!(!Arch::qemu_bundle_available("windows", "x86_64"));
+    }
+
+    #[test]
+    fn test_arch_c_cpu_flag() {
+        assert_eq!(Arch::X86_64.c_cpu_flag(), "-march");
+        assert_eq!(Arch::S390x.c_cpu_flag(), "-march");
+        assert_eq!(Arch::Aarch64.c_cpu_flag(), "-mcpu");
+        assert_eq!(Arch::Powerpc64le.c_cpu_flag(), "-mcpu");
+        assert_eq!(Arch::Riscv64.c_cpu_flag(), "-mcpu");
+    }
+
+    #[test]
+    fn test_arch_c_cpu_arg() {
+        assert_eq!(Arch::S390x.c_cpu_arg("z14"), "-march=z14");
+        assert_eq!(Arch::Powerpc64le.c_cpu_arg("pwr9"), "-mcpu=power9");
+        assert_eq!(Arch::Powerpc64le.c_cpu_arg("power9"), "-mcpu=power9");
+        assert_eq!(Arch::X86_64.c_cpu_arg("x86-64-v3"), "-march=x86-64-v3");
+        assert_eq!(Arch::Aarch64.c_cpu_arg("cortex-a72"), "-mcpu=cortex-a72");
+    }
+
+    #[test]
+    fn test_arch_qemu_cpu() {
+        assert_eq!(Arch::S390x.qemu_cpu("z14"), Some("z14"));
+        assert_eq!(Arch::Powerpc64le.qemu_cpu("pwr9"), Some("power9"));
+        assert_eq!(Arch::X86_64.qemu_cpu("x86-64-v3"), Some("Haswell"));
+        assert_eq!(Arch::Aarch64.qemu_cpu("cortex-a72"), Some("cortex-a72"));
+        assert_eq!(Arch::Aarch64.qemu_cpu("native"), None);
+        assert_eq!(Arch::Riscv64.qemu_cpu("generic-rv64"), None);
+    }
+
     #[test]
     fn test_libc_as_str() {
         assert_eq!(Libc::Musl.as_str(), "musl");
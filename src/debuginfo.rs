@@ -0,0 +1,398 @@
+//! Split debug info of collected artifacts (`--debuginfo-artifacts`)
+//!
+//! Each executable and cdylib copied into the artifact directory keeps its code, and its
+//! debug info moves to a file next to it that debuggers and symbolizers pick up:
+//!
+//! - ELF and MinGW targets: `objcopy --only-keep-debug` writes `<file>.debug`, then
+//!   `objcopy --strip-debug --add-gnu-debuglink` strips the copy and points it at that file.
+//! - darwin and ios targets: `dsymutil` writes the `<file>.dSYM` bundle from the debug map,
+//!   then `strip -S` drops the debug map. Cross builds without a dsymutil are skipped.
+//! - MSVC targets already keep debug info in the `.pdb` the linker writes.
+//!
+//! Only the copies are modified; the files in the target directory stay as cargo wrote them.
+
+use crate::artifacts::ArtifactKind;
+use crate::color;
+use crate::config::HostPlatform;
+use crate::env::CrossEnv;
+use crate::error::{run_command_output, CrossError, Result};
+use std::path::{Path, PathBuf};
+use tokio::process::Command as TokioCommand;
+
+/// How debug info is split out for a target family
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// `objcopy` into a `.debug` file with a `.gnu_debuglink`
+    Objcopy,
+    /// `dsymutil` into a `.dSYM` bundle
+    Dsymutil,
+}
+
+/// Method for `target`, `None` when its debug info is not split (MSVC, wasm, bare metal)
+#[must_use]
+pub fn method(target: &str) -> Option<Method> {
+    if target.contains("-apple-") {
+        Some(Method::Dsymutil)
+    } else if target.ends_with("-msvc")
+        || target.starts_with("wasm")
+        || target.ends_with("-none")
+        || target.contains("-none-")
+        || target.ends_with("-uefi")
+    {
+        None
+    } else {
+        Some(Method::Objcopy)
+    }
+}
+
+/// Whether artifacts of `kind` carry debug info worth splitting. Static libraries are
+/// linked into something else, which is where their debug info ends up.
+#[must_use]
+pub const fn splits(kind: ArtifactKind) -> bool {
+    !matches!(kind, ArtifactKind::Staticlib)
+}
+
+/// The debug file or bundle written next to `artifact`
+#[must_use]
+pub fn debug_path(artifact: &Path, method: Method) -> PathBuf {
+    let suffix = match method {
+        Method::Objcopy => ".debug",
+        Method::Dsymutil => ".dSYM",
+    };
+    let mut path = artifact.as_os_str().to_os_string();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// A program and its arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolCommand {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+}
+
+impl ToolCommand {
+    pub(crate) fn new(program: &Path, args: &[&str]) -> Self {
+        Self {
+            program: program.to_path_buf(),
+            args: args.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    /// The command line, for messages
+    #[must_use]
+    pub fn display(&self) -> String {
+        std::iter::once(self.program.display().to_string())
+            .chain(self.args.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// `objcopy` runs writing `<artifact>.debug`, then stripping `artifact` and linking it to
+/// the debug file
+#[must_use]
+pub fn objcopy_commands(objcopy: &Path, artifact: &Path, compress: bool) -> Vec<ToolCommand> {
+    let artifact_arg = artifact.display().to_string();
+    let debug = debug_path(artifact, Method::Objcopy).display().to_string();
+    let mut keep_debug = vec!["--only-keep-debug"];
+    if compress {
+        keep_debug.push("--compress-debug-sections=zstd");
+    }
+    keep_debug.extend([artifact_arg.as_str(), debug.as_str()]);
+    let debuglink = format!("--add-gnu-debuglink={debug}");
+    vec![
+        ToolCommand::new(objcopy, &keep_debug),
+        ToolCommand::new(objcopy, &["--strip-debug", &debuglink, &artifact_arg]),
+    ]
+}
+
+/// `dsymutil` run writing `<artifact>.dSYM`, then `strip -S` when a strip is available
+#[must_use]
+pub fn dsymutil_commands(
+    dsymutil: &Path,
+    strip: Option<&Path>,
+    artifact: &Path,
+) -> Vec<ToolCommand> {
+    let artifact_arg = artifact.display().to_string();
+    let bundle = debug_path(artifact, Method::Dsymutil).display().to_string();
+    let mut commands = vec![ToolCommand::new(dsymutil, &[&artifact_arg, "-o", &bundle])];
+    if let Some(strip) = strip {
+        commands.push(ToolCommand::new(strip, &["-S", &artifact_arg]));
+    }
+    commands
+}
+
+/// Tool name prefix of the toolchain (`aarch64-linux-musl`, `llvm`), from its archiver or
+/// C compiler
+#[must_use]
+pub fn tool_prefix(cross_env: &CrossEnv, host: &HostPlatform) -> Option<String> {
+    let strip_ext = |tool: &str| {
+        tool.strip_suffix(host.exe_ext())
+            .unwrap_or(tool)
+            .to_string()
+    };
+    let file_name = |tool: &str| {
+        Path::new(tool).file_name().map_or_else(
+            || tool.to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    };
+    let from_ar = cross_env
+        .ar
+        .as_deref()
+        .map(|ar| strip_ext(&file_name(ar)))
+        .and_then(|ar| ar.strip_suffix("-ar").map(str::to_string));
+    from_ar.or_else(|| {
+        let cc = strip_ext(&file_name(cross_env.cc.as_deref()?));
+        ["-gcc", "-clang"]
+            .iter()
+            .find_map(|suffix| cc.strip_suffix(suffix).map(str::to_string))
+    })
+}
+
+/// Names to look for `tool` under, best first: the toolchain's own, the LLVM one, and the
+/// unprefixed one where it understands the target's objects
+#[must_use]
+pub fn tool_candidates(tool: &str, prefix: Option<&str>, host: &HostPlatform) -> Vec<String> {
+    let prefixed = prefix.map(|prefix| format!("{prefix}-{tool}"));
+    let llvm = format!("llvm-{tool}");
+    // The native Apple tools handle every Apple target; elsewhere the plain binutils only
+    // know the host architecture, so LLVM's come first
+    let fallbacks = if host.is_darwin() {
+        [tool.to_string(), llvm]
+    } else {
+        [llvm, tool.to_string()]
+    };
+    let mut names: Vec<String> = Vec::new();
+    for name in prefixed.into_iter().chain(fallbacks) {
+        let name = format!("{name}{}", host.exe_ext());
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// First of `names` found in the toolchain's PATH entries, then on PATH
+pub(crate) fn find_tool(names: &[String], cross_env: &CrossEnv) -> Option<PathBuf> {
+    names.iter().find_map(|name| {
+        cross_env
+            .path
+            .iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .or_else(|| which::which(name).ok())
+    })
+}
+
+async fn run(command: &ToolCommand, artifact: &Path) -> Result<()> {
+    let mut cmd = TokioCommand::new(&command.program);
+    cmd.args(&command.args);
+    let output = run_command_output(&mut cmd, &command.program.display().to_string()).await?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(CrossError::DebuginfoFailed {
+        artifact: artifact.to_path_buf(),
+        command: command.display(),
+        stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    })
+}
+
+/// Split the debug info out of the collected `artifacts` of `target`. Returns each
+/// artifact with the debug file written for it, if any. An artifact the tools fail on
+/// keeps its debug info, with a warning; the build itself succeeded.
+pub async fn split_artifacts(
+    target: &str,
+    artifacts: &[(ArtifactKind, PathBuf)],
+    compress: bool,
+    cross_env: &CrossEnv,
+    host: &HostPlatform,
+) -> Vec<(PathBuf, Option<PathBuf>)> {
+    let unsplit = || -> Vec<(PathBuf, Option<PathBuf>)> {
+        artifacts
+            .iter()
+            .map(|(_, path)| (path.clone(), None))
+            .collect()
+    };
+    let Some(method) = method(target) else {
+        color::log_debug(&format!(
+            "--debuginfo-artifacts: debug info of {target} is not split"
+        ));
+        return unsplit();
+    };
+    let prefix = tool_prefix(cross_env, host);
+    let find = |tool: &str| find_tool(&tool_candidates(tool, prefix.as_deref(), host), cross_env);
+    let tools = match method {
+        Method::Objcopy => find("objcopy").map(|objcopy| (objcopy, None)),
+        Method::Dsymutil => find("dsymutil").map(|dsymutil| (dsymutil, find("strip"))),
+    };
+    let Some((tool, strip)) = tools else {
+        let tool = match method {
+            Method::Objcopy => "objcopy",
+            Method::Dsymutil => "dsymutil",
+        };
+        color::log_warning_once(
+            &format!("debuginfo-{tool}-missing"),
+            &format!(
+                "No {tool} found for {}; collected artifacts keep their debug info",
+                color::yellow(target)
+            ),
+        );
+        return unsplit();
+    };
+
+    let mut split = Vec::new();
+    for (kind, artifact) in artifacts {
+        if !splits(*kind) {
+            split.push((artifact.clone(), None));
+            continue;
+        }
+        let commands = match method {
+            Method::Objcopy => objcopy_commands(&tool, artifact, compress),
+            Method::Dsymutil => dsymutil_commands(&tool, strip.as_deref(), artifact),
+        };
+        let debug = debug_path(artifact, method);
+        let mut failure = None;
+        for command in &commands {
+            if let Err(e) = run(command, artifact).await {
+                failure = Some(e);
+                break;
+            }
+        }
+        if let Some(e) = failure {
+            color::log_warning(&format!(
+                "{e}\nThe collected copy keeps its debug info ({})",
+                color::yellow(target)
+            ));
+            let _ = std::fs::remove_file(&debug).or_else(|_| std::fs::remove_dir_all(&debug));
+            split.push((artifact.clone(), None));
+            continue;
+        }
+        color::log_success(&format!(
+            "Debug info: {}",
+            color::cyan(&debug.display().to_string())
+        ));
+        split.push((artifact.clone(), Some(debug)));
+    }
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(os: &'static str) -> HostPlatform {
+        HostPlatform {
+            os,
+            ..HostPlatform::detect()
+        }
+    }
+
+    #[test]
+    fn test_method_per_target_family() {
+        for (target, expected) in [
+            ("aarch64-unknown-linux-musl", Some(Method::Objcopy)),
+            ("x86_64-unknown-freebsd", Some(Method::Objcopy)),
+            ("aarch64-linux-android", Some(Method::Objcopy)),
+            ("x86_64-pc-windows-gnu", Some(Method::Objcopy)),
+            ("aarch64-apple-darwin", Some(Method::Dsymutil)),
+            ("aarch64-apple-ios", Some(Method::Dsymutil)),
+            ("x86_64-pc-windows-msvc", None),
+            ("wasm32-unknown-unknown", None),
+            ("thumbv7em-none-eabihf", None),
+            ("riscv64gc-unknown-none-elf", None),
+            ("x86_64-unknown-uefi", None),
+        ] {
+            assert_eq!(method(target), expected, "{target}");
+        }
+        assert!(splits(ArtifactKind::Bin));
+        assert!(splits(ArtifactKind::Cdylib));
+        assert!(!splits(ArtifactKind::Staticlib));
+    }
+
+    #[test]
+    fn test_objcopy_commands() {
+        let objcopy = Path::new("/tc/bin/aarch64-linux-musl-objcopy");
+        let artifact = Path::new("dist/acmed");
+        let lines: Vec<String> = objcopy_commands(objcopy, artifact, false)
+            .iter()
+            .map(ToolCommand::display)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "/tc/bin/aarch64-linux-musl-objcopy --only-keep-debug dist/acmed dist/acmed.debug",
+                "/tc/bin/aarch64-linux-musl-objcopy --strip-debug --add-gnu-debuglink=dist/acmed.debug dist/acmed",
+            ]
+        );
+        let compressed = objcopy_commands(objcopy, Path::new("dist/acmed.exe"), true);
+        assert_eq!(
+            compressed[0].args,
+            [
+                "--only-keep-debug",
+                "--compress-debug-sections=zstd",
+                "dist/acmed.exe",
+                "dist/acmed.exe.debug"
+            ]
+        );
+        assert!(!compressed[1].args.iter().any(|arg| arg.contains("zstd")));
+    }
+
+    #[test]
+    fn test_dsymutil_commands() {
+        let artifact = Path::new("dist/libacme_ffi.dylib");
+        let lines: Vec<String> = dsymutil_commands(
+            Path::new("dsymutil"),
+            Some(Path::new("x86_64-apple-darwin25.2-strip")),
+            artifact,
+        )
+        .iter()
+        .map(ToolCommand::display)
+        .collect();
+        assert_eq!(
+            lines,
+            [
+                "dsymutil dist/libacme_ffi.dylib -o dist/libacme_ffi.dylib.dSYM",
+                "x86_64-apple-darwin25.2-strip -S dist/libacme_ffi.dylib",
+            ]
+        );
+        assert_eq!(
+            dsymutil_commands(Path::new("dsymutil"), None, artifact).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_tool_prefix_and_candidates() {
+        let linux = host("linux");
+        let mut env = CrossEnv::new();
+        assert_eq!(tool_prefix(&env, &linux), None);
+        env.cc = Some("aarch64-apple-darwin25.2-clang".to_string());
+        assert_eq!(
+            tool_prefix(&env, &linux).as_deref(),
+            Some("aarch64-apple-darwin25.2")
+        );
+        env.ar = Some("/tc/bin/aarch64-linux-musl-ar".to_string());
+        assert_eq!(
+            tool_prefix(&env, &linux).as_deref(),
+            Some("aarch64-linux-musl")
+        );
+        env.ar = Some("llvm-ar".to_string());
+        assert_eq!(tool_prefix(&env, &linux).as_deref(), Some("llvm"));
+
+        assert_eq!(
+            tool_candidates("objcopy", Some("aarch64-linux-musl"), &linux),
+            ["aarch64-linux-musl-objcopy", "llvm-objcopy", "objcopy"]
+        );
+        assert_eq!(
+            tool_candidates("objcopy", Some("llvm"), &linux),
+            ["llvm-objcopy", "objcopy"]
+        );
+        assert_eq!(
+            tool_candidates("dsymutil", None, &host("darwin")),
+            ["dsymutil", "llvm-dsymutil"]
+        );
+    }
+}
@@ -0,0 +1,380 @@
+//! Build failure diagnostics: host-built objects leaking into target build output, and build
+//! scripts running binaries built for the target
+
+use crate::config::Arch;
+use object::read::archive::ArchiveFile;
+use object::{Architecture, Object};
+use std::path::{Path, PathBuf};
+
+/// A native object or static library whose architecture does not match the target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeakedArtifact {
+    /// Crate whose build script produced the file (from the `build/<crate>-<hash>` dir)
+    pub crate_name: String,
+    /// Offending file
+    pub path: PathBuf,
+    /// Architecture found in the file
+    pub found: Architecture,
+}
+
+/// Map a target architecture to the object file architecture it links against
+#[must_use]
+pub const fn expected_architecture(arch: Arch) -> Architecture {
+    match arch {
+        Arch::Aarch64 | Arch::Aarch64Be | Arch::Arm64e => Architecture::Aarch64,
+        Arch::Armv5 | Arch::Armv6 | Arch::Armv7 => Architecture::Arm,
+        Arch::I586 | Arch::I686 => Architecture::I386,
+        Arch::X86_64 | Arch::X86_64h => Architecture::X86_64,
+        Arch::Loongarch64 => Architecture::LoongArch64,
+        Arch::Mips | Arch::Mipsel | Arch::Mipsisa32r6 | Arch::Mipsisa32r6el => Architecture::Mips,
+        Arch::Mips64 | Arch::Mips64el | Arch::Mipsisa64r6 | Arch::Mipsisa64r6el => {
+            Architecture::Mips64
+        }
+        Arch::Powerpc64 | Arch::Powerpc64le => Architecture::PowerPc64,
+        Arch::Riscv32 => Architecture::Riscv32,
+        Arch::Riscv64 => Architecture::Riscv64,
+        Arch::S390x => Architecture::S390x,
+    }
+}
+
+/// Sniff the architecture of an object file or the first object member of a static library
+#[must_use]
+pub fn sniff_architecture(data: &[u8]) -> Option<Architecture> {
+    if let Ok(archive) = ArchiveFile::parse(data) {
+        return archive
+            .members()
+            .filter_map(std::result::Result::ok)
+            .filter_map(|member| member.data(data).ok())
+            .filter_map(|member_data| object::File::parse(member_data).ok())
+            .map(|file| file.architecture())
+            .find(|arch| *arch != Architecture::Unknown);
+    }
+
+    object::File::parse(data)
+        .ok()
+        .map(|file| file.architecture())
+        .filter(|arch| *arch != Architecture::Unknown)
+}
+
+/// Check whether cargo's stderr contains a linker failure
+#[must_use]
+pub fn is_link_error(stderr: &str) -> bool {
+    stderr.contains("error: linking with")
+        || stderr.contains("file format not recognized")
+        || stderr.contains("file in wrong format")
+        || stderr.contains("incompatible with")
+}
+
+/// Extract build script `OUT_DIR`s referenced by the failing linker invocation
+#[must_use]
+pub fn extract_out_dirs(stderr: &str) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for token in stderr
+        .split(|c: char| c == '"' || c.is_whitespace())
+        .map(|token| {
+            token
+                .trim_start_matches("-Lnative=")
+                .trim_start_matches("-L")
+        })
+    {
+        let normalized = token.replace('\\', "/");
+        let Some(idx) = normalized.find("/build/") else {
+            continue;
+        };
+        let rest = &normalized[idx + "/build/".len()..];
+        let Some((crate_dir, tail)) = rest.split_once('/') else {
+            continue;
+        };
+        if crate_dir.is_empty() || !(tail == "out" || tail.starts_with("out/")) {
+            continue;
+        }
+        let out_dir = PathBuf::from(format!("{}/build/{crate_dir}/out", &normalized[..idx]));
+        if !dirs.contains(&out_dir) {
+            dirs.push(out_dir);
+        }
+    }
+    dirs
+}
+
+/// Get the crate name from an `OUT_DIR` like `target/.../build/foo-sys-1a2b3c4d/out`
+fn crate_name_from_out_dir(out_dir: &Path) -> String {
+    let dir_name = out_dir
+        .parent()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match dir_name.rsplit_once('-') {
+        Some((name, hash)) if hash.chars().all(|c| c.is_ascii_hexdigit()) => name.to_string(),
+        _ => dir_name,
+    }
+}
+
+fn is_native_artifact(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("o" | "obj" | "a" | "lib")
+    )
+}
+
+fn collect_native_artifacts(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(std::result::Result::ok) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_native_artifacts(&path, files);
+        } else if is_native_artifact(&path) {
+            files.push(path);
+        }
+    }
+}
+
+/// Scan an `OUT_DIR` for native artifacts built for the wrong architecture
+#[must_use]
+pub fn scan_out_dir(out_dir: &Path, expected: Architecture) -> Vec<LeakedArtifact> {
+    let crate_name = crate_name_from_out_dir(out_dir);
+    let mut files = Vec::new();
+    collect_native_artifacts(out_dir, &mut files);
+
+    files
+        .into_iter()
+        .filter_map(|path| {
+            let data = std::fs::read(&path).ok()?;
+            let found = sniff_architecture(&data)?;
+            (found != expected).then(|| LeakedArtifact {
+                crate_name: crate_name.clone(),
+                path,
+                found,
+            })
+        })
+        .collect()
+}
+
+/// Format the diagnostic printed after a failed link
+#[must_use]
+pub fn format_diagnostic(target: &str, expected: Architecture, leaks: &[LeakedArtifact]) -> String {
+    let mut message = format!(
+        "Found native artifacts built for the wrong architecture (expected {expected:?} for {target}):\n"
+    );
+    for leak in leaks {
+        message.push_str(&format!(
+            "  - crate '{}': {} is {:?}\n",
+            leak.crate_name,
+            leak.path.display(),
+            leak.found
+        ));
+    }
+    let target_lower = target.replace('-', "_");
+    message.push_str(&format!(
+        "These were likely compiled with the host compiler by a build script.\n\
+         Check that CC_{target_lower} / CXX_{target_lower} are used instead of a global CC/CXX,\n\
+         and try CRATE_CC_NO_DEFAULTS=1 (--cc-no-defaults) if the crate adds host-specific flags."
+    ));
+    message
+}
+
+/// A build script that failed to execute a binary built for the target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignExec {
+    /// Package whose build script failed, from cargo's "failed to run custom build command"
+    pub crate_name: Option<String>,
+    /// Binary under `target/<triple>/` that could not be executed
+    pub binary: PathBuf,
+}
+
+/// Check whether cargo's stderr reports a binary the host cannot execute
+#[must_use]
+pub fn is_exec_format_error(stderr: &str) -> bool {
+    stderr.contains("Exec format error")
+        || stderr.contains("bad CPU type in executable")
+        || stderr.contains("is not a valid Win32 application")
+}
+
+/// Find a build script that tried to run a binary built for `target` on the host
+#[must_use]
+pub fn find_foreign_exec(stderr: &str, target: &str) -> Option<ForeignExec> {
+    if !is_exec_format_error(stderr) {
+        return None;
+    }
+    let target_dir = format!("/{target}/");
+    let binary = stderr
+        .split(|c: char| matches!(c, '"' | '\'' | '`') || c.is_whitespace())
+        .map(|token| token.trim_end_matches([':', ',', ')']).replace('\\', "/"))
+        .find(|token| token.contains(&target_dir) || token.starts_with(&target_dir[1..]))?;
+    let crate_name = stderr
+        .split_once("failed to run custom build command for `")
+        .and_then(|(_, rest)| rest.split([' ', '`']).next())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string);
+    Some(ForeignExec {
+        crate_name,
+        binary: PathBuf::from(binary),
+    })
+}
+
+/// Format the diagnostic printed when a build script ran a binary built for the target
+#[must_use]
+pub fn format_foreign_exec(target: &str, foreign: &ForeignExec) -> String {
+    let culprit = foreign.crate_name.as_ref().map_or_else(
+        || "A build script".to_string(),
+        |name| format!("The build script of '{name}'"),
+    );
+    format!(
+        "{culprit} tried to run {}, which is built for {target} and cannot run on this host.\n\
+         Re-run with --runner-for-build-scripts to set up the target's runner for this command;\n\
+         the build script must then start the binary through $CROSS_RUNNER.",
+        foreign.binary.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal little-endian ELF64 relocatable header for the given machine
+    fn elf_header(machine: u16) -> Vec<u8> {
+        let mut data = vec![0u8; 64];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2; // ELFCLASS64
+        data[5] = 1; // little endian
+        data[6] = 1; // EV_CURRENT
+        data[16..18].copy_from_slice(&1u16.to_le_bytes()); // ET_REL
+        data[18..20].copy_from_slice(&machine.to_le_bytes());
+        data[20..24].copy_from_slice(&1u32.to_le_bytes());
+        data[52..54].copy_from_slice(&64u16.to_le_bytes()); // e_ehsize
+        data[58..60].copy_from_slice(&64u16.to_le_bytes()); // e_shentsize
+        data
+    }
+
+    const EM_X86_64: u16 = 62;
+    const EM_AARCH64: u16 = 183;
+
+    #[test]
+    fn test_sniff_architecture_elf() {
+        assert_eq!(
+            sniff_architecture(&elf_header(EM_X86_64)),
+            Some(Architecture::X86_64)
+        );
+        assert_eq!(
+            sniff_architecture(&elf_header(EM_AARCH64)),
+            Some(Architecture::Aarch64)
+        );
+        assert_eq!(sniff_architecture(b"not an object"), None);
+    }
+
+    #[test]
+    fn test_sniff_architecture_archive() {
+        let member = elf_header(EM_X86_64);
+        let mut archive = b"!<arch>\n".to_vec();
+        archive.extend_from_slice(
+            format!(
+                "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                "foo.o/",
+                0,
+                0,
+                0,
+                644,
+                member.len()
+            )
+            .as_bytes(),
+        );
+        archive.extend_from_slice(&member);
+        assert_eq!(sniff_architecture(&archive), Some(Architecture::X86_64));
+    }
+
+    #[test]
+    fn test_expected_architecture() {
+        assert_eq!(expected_architecture(Arch::Aarch64), Architecture::Aarch64);
+        assert_eq!(expected_architecture(Arch::Armv7), Architecture::Arm);
+        assert_eq!(expected_architecture(Arch::Mips64el), Architecture::Mips64);
+    }
+
+    #[test]
+    fn test_extract_out_dirs_from_linker_invocation() {
+        let stderr = r#"error: linking with `aarch64-linux-musl-gcc` failed: exit status: 1
+  = note: "aarch64-linux-musl-gcc" "/tmp/p/target/aarch64-unknown-linux-musl/debug/deps/app.o" "-L" "/tmp/p/target/aarch64-unknown-linux-musl/debug/build/foo-sys-0123abcd/out" "-Lnative=/tmp/p/target/aarch64-unknown-linux-musl/debug/build/bar-89ef0123/out/lib" "-L" "/tmp/p/target/aarch64-unknown-linux-musl/debug/build/foo-sys-0123abcd/out"
+  = note: ld: foo.o: file format not recognized"#;
+        assert!(is_link_error(stderr));
+        assert_eq!(
+            extract_out_dirs(stderr),
+            vec![
+                PathBuf::from(
+                    "/tmp/p/target/aarch64-unknown-linux-musl/debug/build/foo-sys-0123abcd/out"
+                ),
+                PathBuf::from(
+                    "/tmp/p/target/aarch64-unknown-linux-musl/debug/build/bar-89ef0123/out"
+                ),
+            ]
+        );
+        assert!(!is_link_error("error[E0308]: mismatched types"));
+    }
+
+    #[test]
+    fn test_scan_out_dir_and_format_diagnostic() {
+        let root =
+            std::env::temp_dir().join(format!("cargo-cross-diagnose-test-{}", std::process::id()));
+        let out_dir = root.join("build").join("foo-sys-0123abcd").join("out");
+        std::fs::create_dir_all(&out_dir).unwrap();
+        std::fs::write(out_dir.join("host.o"), elf_header(EM_X86_64)).unwrap();
+        std::fs::write(out_dir.join("target.o"), elf_header(EM_AARCH64)).unwrap();
+        std::fs::write(out_dir.join("notes.txt"), "ignored").unwrap();
+
+        let leaks = scan_out_dir(&out_dir, Architecture::Aarch64);
+        assert_eq!(leaks.len(), 1);
+        assert_eq!(leaks[0].crate_name, "foo-sys");
+        assert!(leaks[0].path.ends_with("host.o"));
+        assert_eq!(leaks[0].found, Architecture::X86_64);
+
+        let message =
+            format_diagnostic("aarch64-unknown-linux-musl", Architecture::Aarch64, &leaks);
+        assert!(message.contains("crate 'foo-sys'"));
+        assert!(message.contains("host.o is X86_64"));
+        assert!(message.contains("CC_aarch64_unknown_linux_musl"));
+        assert!(message.contains("CRATE_CC_NO_DEFAULTS"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_find_foreign_exec() {
+        let stderr = "\
+error: failed to run custom build command for `protogen v0.3.1 (/src/protogen)`
+
+Caused by:
+  process didn't exit successfully: `/src/target/debug/build/protogen-1a2b/build-script-build` (exit status: 101)
+  --- stderr
+  thread 'main' panicked at build.rs:12:10:
+  failed to run \"/src/target/aarch64-unknown-linux-gnu/debug/gen\": Exec format error (os error 8)
+";
+        assert_eq!(
+            find_foreign_exec(stderr, "aarch64-unknown-linux-gnu"),
+            Some(ForeignExec {
+                crate_name: Some("protogen".to_string()),
+                binary: PathBuf::from("/src/target/aarch64-unknown-linux-gnu/debug/gen"),
+            })
+        );
+
+        // Windows paths, relative to the workspace, without the cargo context line
+        let windows = "Error: C:\\src\\target\\aarch64-pc-windows-gnullvm\\release\\gen.exe: \
+                       %1 is not a valid Win32 application. (os error 193)";
+        let found = find_foreign_exec(windows, "aarch64-pc-windows-gnullvm").unwrap();
+        assert_eq!(found.crate_name, None);
+        assert_eq!(
+            found.binary,
+            PathBuf::from("C:/src/target/aarch64-pc-windows-gnullvm/release/gen.exe")
+        );
+
+        // An exec format error that does not involve a target binary is not ours to explain
+        assert!(find_foreign_exec(
+            "sh: /usr/local/bin/protoc: Exec format error",
+            "aarch64-unknown-linux-gnu"
+        )
+        .is_none());
+        assert!(find_foreign_exec(
+            "error: linking with `cc` failed: /t/aarch64-unknown-linux-gnu/debug/app",
+            "aarch64-unknown-linux-gnu"
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,282 @@
+//! Free disk space checks before toolchain downloads
+//!
+//! An Android NDK is a 600 MB zip that extracts to about 4 GB. Running out of space halfway
+//! through extraction leaves a half-written toolchain and an unhelpful "extraction failed",
+//! so each download first estimates what it needs and compares it with the free space of
+//! the filesystem holding the cross compiler directory.
+
+use crate::cache::format_size;
+use crate::color;
+use crate::error::{CrossError, Result};
+use std::path::{Path, PathBuf};
+
+const MIB: u64 = 1 << 20;
+const GIB: u64 = 1 << 30;
+
+/// How many times its archive size a toolchain takes once extracted
+#[must_use]
+pub fn expansion_factor(kind: &str) -> u64 {
+    match kind {
+        "android-ndk" => 7,
+        "cross-make" | "osxcross" | "ioscross" => 4,
+        "qemu-user-static" => 3,
+        _ => 5,
+    }
+}
+
+/// Space a toolchain needs when the server does not send its size, on the high side
+#[must_use]
+pub fn fallback_size(kind: &str) -> u64 {
+    match kind {
+        "android-ndk" => 5 * GIB,
+        "osxcross" => 3 * GIB,
+        "ioscross" => 2 * GIB,
+        "cross-make" => GIB,
+        "qemu-user-static" => 512 * MIB,
+        _ => 2 * GIB,
+    }
+}
+
+/// Bytes a download is expected to need
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpaceEstimate {
+    pub needed: u64,
+    /// Whether the estimate is based on the archive size sent by the server
+    pub from_content_length: bool,
+}
+
+/// Space needed to finish downloading an archive and extract it next to the archive
+#[must_use]
+pub fn estimate(kind: &str, content_length: Option<u64>, already_downloaded: u64) -> SpaceEstimate {
+    match content_length {
+        Some(length) => SpaceEstimate {
+            needed: length.saturating_sub(already_downloaded)
+                + length.saturating_mul(expansion_factor(kind)),
+            from_content_length: true,
+        },
+        None => SpaceEstimate {
+            needed: fallback_size(kind),
+            from_content_length: false,
+        },
+    }
+}
+
+/// Compare an estimate with the free space of `path`. A shortfall is an error when the
+/// estimate comes from the archive size, and a warning (returned) when it is a guess.
+pub fn check_space(
+    path: &Path,
+    estimate: SpaceEstimate,
+    available: Option<u64>,
+) -> Result<Option<String>> {
+    let Some(available) = available else {
+        return Ok(None);
+    };
+    if estimate.needed <= available {
+        return Ok(None);
+    }
+    if estimate.from_content_length {
+        return Err(CrossError::InsufficientDiskSpace {
+            needed: format_size(estimate.needed),
+            available: format_size(available),
+            path: path.to_path_buf(),
+        });
+    }
+    Ok(Some(format!(
+        "{} may not have enough free space: about {} needed, {} available",
+        path.display(),
+        format_size(estimate.needed),
+        format_size(available)
+    )))
+}
+
+/// Nearest existing directory at or above `path`
+fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|dir| dir.is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// Free space in bytes on the filesystem containing `path`, if it can be determined
+#[must_use]
+pub fn available_space(path: &Path) -> Option<u64> {
+    free_space(&existing_ancestor(path)?)
+}
+
+#[cfg(unix)]
+// The field types of statvfs differ between platforms
+#[allow(clippy::useless_conversion)]
+fn free_space(dir: &Path) -> Option<u64> {
+    let stat = nix::sys::statvfs::statvfs(dir).ok()?;
+    u64::from(stat.blocks_available()).checked_mul(u64::from(stat.fragment_size()))
+}
+
+#[cfg(windows)]
+fn free_space(dir: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            free_bytes_available_to_caller: *mut u64,
+            total_bytes: *mut u64,
+            total_free_bytes: *mut u64,
+        ) -> i32;
+    }
+
+    let wide: Vec<u16> = dir
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut available = 0u64;
+    // SAFETY: `wide` is NUL-terminated and outlives the call; the optional outputs are null
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    (ok != 0).then_some(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn free_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+/// Fail before downloading a `kind` toolchain into `dest` if it will not fit
+pub fn preflight(
+    dest: &Path,
+    kind: &str,
+    content_length: Option<u64>,
+    already_downloaded: u64,
+) -> Result<()> {
+    let estimate = estimate(kind, content_length, already_downloaded);
+    if let Some(warning) = check_space(dest, estimate, available_space(dest))? {
+        color::log_warning(&warning);
+    }
+    Ok(())
+}
+
+/// Whether an I/O error means the disk is full (ENOSPC, or `ERROR_DISK_FULL` and
+/// `ERROR_HANDLE_DISK_FULL` on Windows)
+#[must_use]
+pub fn is_disk_full(err: &std::io::Error) -> bool {
+    let code = err.raw_os_error();
+    err.kind() == std::io::ErrorKind::StorageFull
+        || (cfg!(windows) && matches!(code, Some(39 | 112)))
+        || (cfg!(unix) && code == Some(28))
+}
+
+/// Replace an error caused by a full disk with one that says so
+#[must_use]
+pub fn explain_disk_full(err: CrossError, path: &Path) -> CrossError {
+    let disk_full = match err {
+        CrossError::IoError { ref source, .. } => is_disk_full(source),
+        // Extraction errors only keep the message
+        CrossError::ExtractionFailed(ref message) => {
+            message.contains("No space left on device")
+                || message.contains("There is not enough space on the disk")
+        }
+        _ => false,
+    };
+    if !disk_full {
+        return err;
+    }
+    CrossError::DiskFull {
+        path: path.to_path_buf(),
+        available: available_space(path).map_or_else(|| "unknown".to_string(), format_size),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        assert_eq!(
+            estimate("android-ndk", Some(600 * MIB), 0),
+            SpaceEstimate {
+                needed: 600 * MIB * 8,
+                from_content_length: true,
+            }
+        );
+        // A resumed download only needs the rest of the archive
+        assert_eq!(
+            estimate("cross-make", Some(100 * MIB), 40 * MIB).needed,
+            60 * MIB + 400 * MIB
+        );
+        assert_eq!(
+            estimate("osxcross", None, 0),
+            SpaceEstimate {
+                needed: 3 * GIB,
+                from_content_length: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_check_space() {
+        let dir = Path::new("/toolchains");
+        let known = |needed| SpaceEstimate {
+            needed,
+            from_content_length: true,
+        };
+        let guessed = |needed| SpaceEstimate {
+            needed,
+            from_content_length: false,
+        };
+
+        assert!(check_space(dir, known(GIB), Some(2 * GIB))
+            .unwrap()
+            .is_none());
+        assert!(check_space(dir, known(GIB), None).unwrap().is_none());
+
+        let err = check_space(dir, known(5 * GIB), Some(GIB)).unwrap_err();
+        assert!(matches!(
+            err,
+            CrossError::InsufficientDiskSpace { ref needed, ref available, ref path }
+                if needed == "5.0 GiB" && available == "1.0 GiB" && path == dir
+        ));
+
+        let warning = check_space(dir, guessed(5 * GIB), Some(GIB))
+            .unwrap()
+            .unwrap();
+        assert!(
+            warning.contains("about 5.0 GiB needed, 1.0 GiB available"),
+            "{warning}"
+        );
+    }
+
+    #[test]
+    fn test_available_space() {
+        let missing = std::env::temp_dir().join("cargo-cross-disk-missing/toolchains");
+        assert!(available_space(&missing).is_some_and(|bytes| bytes > 0));
+    }
+
+    #[test]
+    fn test_explain_disk_full() {
+        let dir = std::env::temp_dir();
+        let full = std::io::Error::from(std::io::ErrorKind::StorageFull);
+        assert!(matches!(
+            explain_disk_full(full.into(), &dir),
+            CrossError::DiskFull { .. }
+        ));
+        let extraction = CrossError::ExtractionFailed(
+            "failed to unpack `bin/clang`: No space left on device (os error 28)".to_string(),
+        );
+        assert!(matches!(
+            explain_disk_full(extraction, &dir),
+            CrossError::DiskFull { .. }
+        ));
+        let other = CrossError::ExtractionFailed("invalid gzip header".to_string());
+        assert!(matches!(
+            explain_disk_full(other, &dir),
+            CrossError::ExtractionFailed(_)
+        ));
+    }
+}
@@ -1,11 +1,15 @@
 //! Download and archive extraction utilities for cargo-cross
 
 use crate::color;
+use crate::disk;
 use crate::error::{CrossError, Result};
+use crate::metrics::{self, Phase};
+use crate::report;
 use futures_util::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::path::Path;
-use std::sync::LazyLock;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex};
 use std::time::Duration;
 use tokio::fs::{self, File};
 use tokio::io::AsyncWriteExt;
@@ -19,6 +23,148 @@ const MAX_RETRIES: u32 = 3;
 /// Initial retry delay (doubles with each retry)
 const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
 
+/// Longest `--cross-compiler-dir` that leaves room for the deepest files of NDK and mingw
+/// toolchains under the Windows `MAX_PATH` limit of 260 characters
+pub const MAX_CROSS_COMPILER_DIR_LEN: usize = 80;
+
+/// Prefix lifting the `MAX_PATH` limit for Windows file APIs
+const VERBATIM_PREFIX: &str = r"\\?\";
+
+/// Verbatim prefix for UNC paths (`\\server\share` becomes `\\?\UNC\server\share`)
+const VERBATIM_UNC_PREFIX: &str = r"\\?\UNC\";
+
+/// Add the `\\?\` prefix to an absolute Windows path. Relative and already prefixed paths
+/// are returned unchanged, since the prefix turns off all path normalization.
+#[must_use]
+pub fn add_long_path_prefix(path: &str) -> String {
+    if path.starts_with(VERBATIM_PREFIX) {
+        return path.to_string();
+    }
+    let path = path.replace('/', "\\");
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        return format!("{VERBATIM_UNC_PREFIX}{unc}");
+    }
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == b":\\" {
+        return format!("{VERBATIM_PREFIX}{path}");
+    }
+    path
+}
+
+/// Remove `\\?\` prefixes from a value, e.g. a `PATH` list or a `-L` flag, for tools that
+/// do not accept them
+#[must_use]
+pub fn strip_long_path_prefix(value: &str) -> String {
+    value
+        .replace(VERBATIM_UNC_PREFIX, r"\\")
+        .replace(VERBATIM_PREFIX, "")
+}
+
+/// Path usable for file operations beyond `MAX_PATH`: prefixed on Windows, unchanged elsewhere
+#[must_use]
+pub fn long_path(path: &Path) -> PathBuf {
+    if cfg!(windows) && path.is_absolute() {
+        PathBuf::from(add_long_path_prefix(&path.to_string_lossy()))
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Whether toolchains extracted below `dir` risk exceeding the Windows `MAX_PATH` limit
+#[must_use]
+pub fn cross_compiler_dir_too_long(dir: &Path) -> bool {
+    let dir = strip_long_path_prefix(&dir.to_string_lossy());
+    dir.chars().count() > MAX_CROSS_COMPILER_DIR_LEN
+}
+
+/// Warn on Windows when `--cross-compiler-dir` is long enough for toolchain paths to
+/// exceed `MAX_PATH`, which makes some compilers and build scripts fail
+pub fn check_cross_compiler_dir_length(dir: &Path) {
+    if !cfg!(windows) {
+        return;
+    }
+    let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+    if cross_compiler_dir_too_long(&dir) {
+        color::log_warning(&format!(
+            "Cross compiler directory \"{}\" is longer than {MAX_CROSS_COMPILER_DIR_LEN} characters; \
+             files of NDK and mingw toolchains may exceed the Windows path length limit. \
+             Pass a shorter --cross-compiler-dir (e.g. C:\\cross)",
+            dir.display()
+        ));
+    }
+}
+
+/// Temporary extraction directories that must not outlive an interrupted run
+static ACTIVE_TEMP_PATHS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+fn lock_temp_paths() -> std::sync::MutexGuard<'static, Vec<PathBuf>> {
+    ACTIVE_TEMP_PATHS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Registers a temporary path and removes it (if still present) when dropped
+#[derive(Debug)]
+pub struct TempPathGuard(PathBuf);
+
+impl TempPathGuard {
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        lock_temp_paths().push(path.clone());
+        Self(path)
+    }
+}
+
+impl Drop for TempPathGuard {
+    fn drop(&mut self) {
+        remove_temp_path(&self.0);
+        lock_temp_paths().retain(|p| p != &self.0);
+    }
+}
+
+fn remove_temp_path(path: &Path) {
+    if path.is_dir() {
+        let _ = std::fs::remove_dir_all(path);
+    } else if path.exists() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Remove every registered temporary path; called from the interrupt handler
+pub fn cleanup_temp_paths() {
+    for path in lock_temp_paths().drain(..) {
+        remove_temp_path(&path);
+    }
+}
+
+/// Bundles downloaded and extracted by this process
+static COMPLETED_DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of bundles downloaded and extracted so far by this process
+#[must_use]
+pub fn completed_downloads() -> usize {
+    COMPLETED_DOWNLOADS.load(Ordering::Relaxed)
+}
+
+/// Set by `sync --check` so that missing toolchains are reported instead of fetched
+static DOWNLOADS_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Disable (or re-enable) all network downloads for this process
+pub fn set_downloads_disabled(disabled: bool) {
+    DOWNLOADS_DISABLED.store(disabled, Ordering::Relaxed);
+}
+
+fn ensure_downloads_enabled(url: &str, dest: &Path) -> Result<()> {
+    if DOWNLOADS_DISABLED.load(Ordering::Relaxed) {
+        return Err(CrossError::DownloadDisabled {
+            url: url.to_string(),
+            dest: dest.to_path_buf(),
+        });
+    }
+    Ok(())
+}
+
 /// Cached progress styles to avoid repeated template parsing
 static DOWNLOAD_SPINNER_STYLE: LazyLock<ProgressStyle> = LazyLock::new(|| {
     ProgressStyle::default_spinner()
@@ -77,13 +223,112 @@ impl ArchiveFormat {
     }
 }
 
-/// HTTP client wrapper for consistent configuration
-fn create_http_client() -> reqwest::Result<reqwest::Client> {
-    reqwest::Client::builder()
+/// TLS settings for toolchain downloads
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DownloadTls {
+    /// PEM bundles of extra root certificates (`--download-ca-cert`, `SSL_CERT_FILE`)
+    pub ca_certs: Vec<PathBuf>,
+    /// Skip certificate verification entirely (`--download-insecure`)
+    pub insecure: bool,
+}
+
+impl DownloadTls {
+    /// TLS settings from the command line, adding `SSL_CERT_FILE` when it names a file
+    #[must_use]
+    pub fn from_args(ca_cert: Option<&Path>, insecure: bool) -> Self {
+        let ssl_cert_file = std::env::var_os("SSL_CERT_FILE")
+            .map(PathBuf::from)
+            .filter(|path| path.is_file());
+        Self {
+            ca_certs: ca_cert
+                .map(Path::to_path_buf)
+                .into_iter()
+                .chain(ssl_cert_file)
+                .collect(),
+            insecure,
+        }
+    }
+}
+
+/// TLS settings applied to every HTTP client, set once from the command line
+static DOWNLOAD_TLS: Mutex<DownloadTls> = Mutex::new(DownloadTls {
+    ca_certs: Vec::new(),
+    insecure: false,
+});
+
+/// Configure TLS for all downloads of this process
+pub fn set_download_tls(tls: DownloadTls) {
+    *DOWNLOAD_TLS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = tls;
+}
+
+/// Client builder with the shared settings plus `tls`
+fn http_client_builder(tls: &DownloadTls) -> Result<reqwest::ClientBuilder> {
+    let mut builder = reqwest::Client::builder()
         .user_agent("cargo-cross")
         .http1_only()
-        .timeout(Duration::from_mins(5)) // 5 minutes timeout
-        .build()
+        .timeout(Duration::from_mins(5)); // 5 minutes timeout
+
+    for path in &tls.ca_certs {
+        let certs = std::fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|pem| reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| e.to_string()))
+            .and_then(|certs| {
+                if certs.is_empty() {
+                    Err("no PEM certificates found".to_string())
+                } else {
+                    Ok(certs)
+                }
+            })
+            .map_err(|e| {
+                CrossError::InvalidArgument(format!(
+                    "Failed to load CA certificates from {}: {e}",
+                    path.display()
+                ))
+            })?;
+        builder = builder.tls_certs_merge(certs);
+    }
+    if tls.insecure {
+        builder = builder.tls_danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
+}
+
+/// HTTP client wrapper for consistent configuration
+fn create_http_client() -> Result<reqwest::Client> {
+    let tls = DOWNLOAD_TLS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone();
+    Ok(http_client_builder(&tls)?.build()?)
+}
+
+/// Whether an error message (including its sources) reports a failed certificate check
+fn is_tls_verification_error(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    [
+        "certificate",
+        "unknownissuer",
+        "unknown issuer",
+        "self signed",
+        "self-signed",
+        "local issuer",
+    ]
+    .iter()
+    .any(|needle| message.contains(needle))
+}
+
+/// Full error message of `err` including its chain of sources
+fn error_chain(err: &dyn std::error::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
 }
 
 /// Check if an error is retryable (network errors, timeouts, etc.)
@@ -136,6 +381,13 @@ async fn send_request_with_retry_range(
                 )));
             }
             Err(err) => {
+                let message = error_chain(&err);
+                if is_tls_verification_error(&message) {
+                    return Err(CrossError::TlsVerificationFailed {
+                        url: url.to_string(),
+                        message,
+                    });
+                }
                 if !is_retryable_error(&err) || attempt == MAX_RETRIES {
                     // Non-retryable error or max retries reached
                     return Err(err.into());
@@ -152,6 +404,26 @@ async fn send_request_with_retry_range(
     ))
 }
 
+/// What to do with the partial file when a response arrives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResumeAction {
+    /// The server sent the requested range; append to the partial file
+    Append,
+    /// The server sent the whole file; start over from an empty file
+    Restart,
+}
+
+/// Decide how to write a response given the offset a Range was requested from.
+/// An expired redirect signature can lead to a fresh 302 chain whose final server ignores
+/// the Range header and answers 200 with the full content, which must not be appended.
+fn resume_action(requested_from: u64, status: reqwest::StatusCode) -> ResumeAction {
+    if requested_from > 0 && status == reqwest::StatusCode::PARTIAL_CONTENT {
+        ResumeAction::Append
+    } else {
+        ResumeAction::Restart
+    }
+}
+
 /// Download to file with resume support and automatic retry
 async fn download_with_resume(
     client: &reqwest::Client,
@@ -164,21 +436,46 @@ async fn download_with_resume(
     if already_downloaded > 0 {
         pb.set_position(already_downloaded);
     }
+    let mut events = report::DownloadProgress::new(url, pb.length());
 
     let mut downloaded = already_downloaded;
     let mut attempt = 0;
+    let mut last_host = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string));
     'retry: loop {
         let response = send_request_with_retry_range(client, url, Some(downloaded)).await?;
 
-        // Open file in append mode or create if doesn't exist
-        let mut file = if downloaded > 0 {
-            File::options()
-                .append(true)
-                .create(true)
-                .open(file_path)
-                .await?
-        } else {
-            File::create(file_path).await?
+        // Redirect targets (e.g. signed asset URLs) can move between attempts
+        let host = response.url().host_str().map(str::to_string);
+        if host != last_host {
+            color::log_debug(&format!(
+                "Download of {url} redirected to {}",
+                host.as_deref().unwrap_or("<no host>")
+            ));
+            last_host = host;
+        }
+
+        // Append to the partial file only if the server honored the Range
+        let mut file = match resume_action(downloaded, response.status()) {
+            ResumeAction::Append => {
+                File::options()
+                    .append(true)
+                    .create(true)
+                    .open(file_path)
+                    .await?
+            }
+            ResumeAction::Restart => {
+                if downloaded > 0 {
+                    color::log_debug(&format!(
+                        "Server ignored the Range request for {url} (HTTP {}), restarting download",
+                        response.status()
+                    ));
+                    downloaded = 0;
+                    pb.set_position(0);
+                }
+                File::create(file_path).await?
+            }
         };
 
         let mut stream = response.bytes_stream();
@@ -189,6 +486,7 @@ async fn download_with_resume(
                     file.write_all(&chunk).await?;
                     downloaded += chunk.len() as u64;
                     pb.inc(chunk.len() as u64);
+                    events.update(downloaded);
                 }
                 Err(err) => {
                     // Network error during streaming - need to retry
@@ -216,9 +514,42 @@ async fn download_with_resume(
     Ok(())
 }
 
+/// GitHub API URL of the release behind a `https://github.com/<repo>/releases/download/<tag>/<asset>`
+/// URL, and the asset name
+fn release_api_url(url: &str) -> Option<(String, &str)> {
+    let rest = url.strip_prefix("https://github.com/")?;
+    let (repo, rest) = rest.split_once("/releases/download/")?;
+    let (tag, asset) = rest.split_once('/')?;
+    Some((
+        format!("https://api.github.com/repos/{repo}/releases/tags/{tag}"),
+        asset,
+    ))
+}
+
+/// Digest GitHub publishes for a release asset (`sha256:<hex>`), if the release has one.
+/// Returns `None` without a request when downloads are disabled.
+pub async fn release_asset_digest(url: &str) -> Result<Option<String>> {
+    if DOWNLOADS_DISABLED.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+    let Some((api_url, asset)) = release_api_url(url) else {
+        return Ok(None);
+    };
+    let client = create_http_client()?;
+    let response = send_request_with_retry(&client, &api_url).await?;
+    let release: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+    Ok(release["assets"]
+        .as_array()
+        .and_then(|assets| assets.iter().find(|a| a["name"] == asset))
+        .and_then(|a| a["digest"].as_str())
+        .map(str::to_string))
+}
+
 /// Download a file from URL with progress indication, resume support and automatic retry
 pub async fn download_file(url: &str, dest: &Path) -> Result<()> {
+    ensure_downloads_enabled(url, dest)?;
     let client = create_http_client()?;
+    let dest = &long_path(dest);
 
     // Ensure parent directory exists
     if let Some(parent) = dest.parent() {
@@ -268,26 +599,31 @@ pub async fn download_file(url: &str, dest: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Download and extract an archive
+/// Download and extract an archive of a `kind` toolchain (e.g. `cross-make`), whose
+/// extracted size is estimated for the disk space check
 pub async fn download_and_extract(
     url: &str,
     dest: &Path,
     format: Option<ArchiveFormat>,
     github_proxy: Option<&str>,
+    kind: &str,
 ) -> Result<()> {
+    ensure_downloads_enabled(url, dest)?;
     let format = format
         .or_else(|| ArchiveFormat::from_url(url))
         .ok_or_else(|| CrossError::UnsupportedArchiveFormat(url.to_string()))?;
 
     // Apply GitHub proxy if configured
-    let url = apply_github_proxy(url, github_proxy);
+    let url = apply_github_proxy(url, github_proxy)?;
 
     // Get absolute path for destination
-    let dest = if dest.is_absolute() {
+    let display_dest = if dest.is_absolute() {
         dest.to_path_buf()
     } else {
         std::env::current_dir()?.join(dest)
     };
+    // Toolchain files nest deeply; lift MAX_PATH for everything below the destination
+    let dest = long_path(&display_dest);
 
     // Create parent directory
     if let Some(parent) = dest.parent() {
@@ -301,29 +637,31 @@ pub async fn download_and_extract(
         dest.file_name().unwrap().to_string_lossy()
     ));
     cleanup_and_create_dir(&temp_dir).await?;
+    let _temp_guard = TempPathGuard::new(&temp_dir);
 
     color::log_info(&format!(
         "Downloading \"{}\" to \"{}\"",
         color::green(&url),
-        color::green(&dest.display().to_string())
+        color::green(&display_dest.display().to_string())
     ));
 
     let start_time = std::time::Instant::now();
 
     // Download and extract based on format
     let result = match format {
-        ArchiveFormat::TarGz => download_and_extract_tar_gz(&url, &temp_dir).await,
-        ArchiveFormat::Zip => download_and_extract_zip(&url, &temp_dir).await,
+        ArchiveFormat::TarGz => download_and_extract_tar_gz(&url, &temp_dir, kind).await,
+        ArchiveFormat::Zip => download_and_extract_zip(&url, &temp_dir, kind).await,
     };
 
     // Clean up temp directory on failure
-    if result.is_err() {
+    if let Err(e) = result {
         fs::remove_dir_all(&temp_dir).await.ok();
-        return result;
+        return Err(disk::explain_disk_full(e, &display_dest));
     }
 
     // Move extracted content to final destination
-    finalize_extraction(&temp_dir, &dest).await?;
+    metrics::timed(Phase::Extract, finalize_extraction(&temp_dir, &dest)).await?;
+    COMPLETED_DOWNLOADS.fetch_add(1, Ordering::Relaxed);
 
     let elapsed = start_time.elapsed();
     color::log_success(&format!(
@@ -334,8 +672,9 @@ pub async fn download_and_extract(
     Ok(())
 }
 
-/// Download archive file with resume support and progress tracking
-async fn download_archive(url: &str, file_path: &Path) -> Result<()> {
+/// Download archive file with resume support and progress tracking, after checking that
+/// the archive and its extracted contents fit on the disk
+async fn download_archive(url: &str, file_path: &Path, kind: &str) -> Result<()> {
     let client = create_http_client()?;
 
     // Check if partial file exists
@@ -349,6 +688,7 @@ async fn download_archive(url: &str, file_path: &Path) -> Result<()> {
     let response = send_request_with_retry(&client, url).await?;
     let total_size = response.content_length();
     drop(response); // Close the connection
+    disk::preflight(file_path, kind, total_size, already_downloaded)?;
 
     // Create download progress bar
     let download_pb = create_download_progress_bar(total_size);
@@ -362,10 +702,9 @@ async fn download_archive(url: &str, file_path: &Path) -> Result<()> {
 }
 
 /// Download and extract a tar.gz archive with resume support and automatic retry
-async fn download_and_extract_tar_gz(url: &str, dest: &Path) -> Result<()> {
+async fn download_and_extract_tar_gz(url: &str, dest: &Path, kind: &str) -> Result<()> {
     use async_compression::tokio::bufread::GzipDecoder;
     use tokio::io::BufReader;
-    use tokio_tar::ArchiveBuilder;
 
     // Download to {dest}.tar.gz file first (with resume support)
     // Note: Can't use with_extension() because dest may contain dots (e.g., v0.7.7)
@@ -373,7 +712,7 @@ async fn download_and_extract_tar_gz(url: &str, dest: &Path) -> Result<()> {
         "{}.tar.gz",
         dest.file_name().unwrap().to_string_lossy()
     ));
-    download_archive(url, &archive_path).await?;
+    metrics::timed(Phase::Download, download_archive(url, &archive_path, kind)).await?;
 
     // Now extract the downloaded archive
     let extract_pb = create_extract_spinner();
@@ -383,43 +722,160 @@ async fn download_and_extract_tar_gz(url: &str, dest: &Path) -> Result<()> {
 
     // Decompress and extract with permission preservation for executable files
     let decoder = GzipDecoder::new(buf_reader);
-    let mut archive = ArchiveBuilder::new(decoder)
+    let report = metrics::timed(Phase::Extract, extract_tar(decoder, dest, &extract_pb)).await?;
+
+    extract_pb.finish_with_message(format!("{} files extracted", extract_pb.position()));
+    if report.converted > 0 || report.skipped > 0 {
+        color::log_warning(&format!(
+            "{} hard link(s) extracted as copies, {} archive entries skipped",
+            report.converted, report.skipped
+        ));
+    }
+
+    // Clean up archive file after extraction
+    fs::remove_file(&archive_path).await.ok();
+
+    Ok(())
+}
+
+/// Archive entries tokio-tar could not unpack that the fallback handled instead
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct ExtractReport {
+    /// Hard links written as copies of their target
+    converted: usize,
+    /// Entries left out of the extraction
+    skipped: usize,
+}
+
+/// Unpack every entry of a tar stream into `dest`.
+///
+/// An entry tokio-tar fails to unpack is retried conservatively: hard links become
+/// copies and other special entries are skipped. Only regular files and directories
+/// that cannot be written fail the extraction.
+async fn extract_tar<R: tokio::io::AsyncRead + Unpin>(
+    reader: R,
+    dest: &Path,
+    pb: &ProgressBar,
+) -> Result<ExtractReport> {
+    let mut archive = tokio_tar::ArchiveBuilder::new(reader)
         .set_preserve_permissions(true)
         .build();
-
     let mut entries = archive
         .entries()
         .map_err(|e| CrossError::ExtractionFailed(e.to_string()))?;
 
+    let mut report = ExtractReport::default();
     while let Some(entry) = entries.next().await {
         let mut entry = entry.map_err(|e| CrossError::ExtractionFailed(e.to_string()))?;
-        entry
-            .unpack_in(dest)
-            .await
-            .map_err(|e| CrossError::ExtractionFailed(e.to_string()))?;
-        extract_pb.inc(1);
+        if let Err(e) = entry.unpack_in(dest).await {
+            unpack_entry_fallback(&entry, dest, &e, &mut report).await?;
+        }
+        pb.inc(1);
     }
+    Ok(report)
+}
 
-    extract_pb.finish_with_message(format!("{} files extracted", extract_pb.position()));
+/// Path of an archive member below `dest`, or `None` if it would escape it
+fn archive_member_path(dest: &Path, member: &Path) -> Option<PathBuf> {
+    use std::path::Component;
 
-    // Clean up archive file after extraction
-    fs::remove_file(&archive_path).await.ok();
+    let mut path = dest.to_path_buf();
+    for component in member.components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::ParentDir => return None,
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+        }
+    }
+    (path != dest).then_some(path)
+}
+
+/// Whether `path` stays below `dest` once symlinks already extracted are followed.
+/// Checks the deepest part of `path` that exists, so missing directories are fine.
+fn resolves_within(dest: &Path, path: &Path) -> bool {
+    let Ok(root) = dest.canonicalize() else {
+        return false;
+    };
+    let mut existing = path;
+    while existing.symlink_metadata().is_err() {
+        let Some(parent) = existing.parent() else {
+            return false;
+        };
+        existing = parent;
+    }
+    existing
+        .canonicalize()
+        .is_ok_and(|resolved| resolved.starts_with(&root))
+}
+
+/// Handle an entry whose regular unpacking failed with `error`
+async fn unpack_entry_fallback<R: tokio::io::AsyncRead + Unpin>(
+    entry: &tokio_tar::Entry<R>,
+    dest: &Path,
+    error: &std::io::Error,
+    report: &mut ExtractReport,
+) -> Result<()> {
+    let kind = entry.header().entry_type();
+    let name = entry.path().map_or_else(
+        |_| "<invalid path>".to_string(),
+        |path| path.display().to_string(),
+    );
+    if kind.is_file() || kind.is_dir() || kind.is_contiguous() || kind.is_gnu_sparse() {
+        return Err(CrossError::ExtractionFailed(format!("{name}: {error}")));
+    }
 
+    if kind.is_hard_link() {
+        let target = entry
+            .path()
+            .ok()
+            .and_then(|path| archive_member_path(dest, &path));
+        let source = entry
+            .link_name()
+            .ok()
+            .flatten()
+            .and_then(|link| archive_member_path(dest, &link));
+        if let (Some(target), Some(source)) = (target, source) {
+            // The textual paths can still leave `dest` through a symlink the archive
+            // created earlier, which unpack_in would have refused
+            let contained = target
+                .parent()
+                .is_some_and(|parent| resolves_within(dest, parent))
+                && resolves_within(dest, &source);
+            if contained && source != target && source.is_file() {
+                if target.symlink_metadata().is_ok() {
+                    fs::remove_file(&target).await?;
+                }
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent).await?;
+                }
+                fs::copy(&source, &target).await?;
+                color::log_debug(&format!("Extracted hard link {name} as a copy ({error})"));
+                report.converted += 1;
+                return Ok(());
+            }
+        }
+    }
+
+    color::log_debug(&format!("Skipped archive entry {name}: {error}"));
+    report.skipped += 1;
     Ok(())
 }
 
 /// Download and extract a ZIP archive with resume support and automatic retry
-async fn download_and_extract_zip(url: &str, dest: &Path) -> Result<()> {
+async fn download_and_extract_zip(url: &str, dest: &Path, kind: &str) -> Result<()> {
     // Download to {dest}.zip file
     // Note: Can't use with_extension() because dest may contain dots (e.g., v0.7.7)
     let zip_path = dest.parent().unwrap().join(format!(
         "{}.zip",
         dest.file_name().unwrap().to_string_lossy()
     ));
-    download_archive(url, &zip_path).await?;
+    metrics::timed(Phase::Download, download_archive(url, &zip_path, kind)).await?;
 
     // Extract ZIP with progress (creates its own progress bar with known total)
-    extract_zip_archive(&zip_path, dest)?;
+    let extract_start = std::time::Instant::now();
+    let extracted = extract_zip_archive(&zip_path, dest);
+    metrics::record(Phase::Extract, extract_start.elapsed());
+    extracted?;
 
     // Clean up zip file after extraction
     fs::remove_file(&zip_path).await.ok();
@@ -561,8 +1017,13 @@ fn make_writable_dir_all(path: &Path) -> Result<()> {
 
 /// Create a progress bar for download with steady tick
 fn create_download_progress_bar(total_size: Option<u64>) -> ProgressBar {
-    if std::env::var_os("CARGO_CROSS_SILENT").is_some() {
-        return ProgressBar::hidden();
+    if !color::log_enabled(color::LogLevel::Info) {
+        // Hidden, but still sized for the event stream
+        let pb = ProgressBar::hidden();
+        if let Some(size) = total_size {
+            pb.set_length(size);
+        }
+        return pb;
     }
 
     let pb = total_size.map_or_else(
@@ -583,7 +1044,7 @@ fn create_download_progress_bar(total_size: Option<u64>) -> ProgressBar {
 
 /// Create a spinner for extraction progress with steady tick
 fn create_extract_spinner() -> ProgressBar {
-    if std::env::var_os("CARGO_CROSS_SILENT").is_some() {
+    if !color::log_enabled(color::LogLevel::Info) {
         return ProgressBar::hidden();
     }
 
@@ -595,7 +1056,7 @@ fn create_extract_spinner() -> ProgressBar {
 
 /// Create a progress bar for extraction with known total (shows speed and ETA)
 fn create_extract_progress_bar(total: usize) -> ProgressBar {
-    if std::env::var_os("CARGO_CROSS_SILENT").is_some() {
+    if !color::log_enabled(color::LogLevel::Info) {
         return ProgressBar::hidden();
     }
 
@@ -605,12 +1066,42 @@ fn create_extract_progress_bar(total: usize) -> ProgressBar {
     pb
 }
 
-/// Apply GitHub proxy to URL if configured
-fn apply_github_proxy(url: &str, proxy: Option<&str>) -> String {
-    match proxy {
-        Some(proxy) if url.starts_with("https://github.com") => format!("{proxy}{url}"),
-        _ => url.to_string(),
-    }
+/// Apply the GitHub proxy to a `https://github.com/...` URL; other URLs pass through.
+///
+/// The proxy is either a template with `{url}` (the full GitHub URL) or `{path}`
+/// (the part after `github.com/`), a prefix ending in `/` that the full URL is appended to,
+/// or a base URL that replaces `https://github.com`.
+fn apply_github_proxy(url: &str, proxy: Option<&str>) -> Result<String> {
+    let (Some(proxy), Some(path)) = (
+        proxy.filter(|proxy| !proxy.is_empty()),
+        url.strip_prefix("https://github.com/"),
+    ) else {
+        return Ok(url.to_string());
+    };
+
+    let proxied = if proxy.contains("{url}") || proxy.contains("{path}") {
+        proxy.replace("{url}", url).replace("{path}", path)
+    } else if proxy.ends_with('/') {
+        format!("{proxy}{url}")
+    } else {
+        format!("{proxy}/{path}")
+    };
+    reqwest::Url::parse(&proxied).map_err(|e| {
+        CrossError::InvalidArgument(format!(
+            "GitHub proxy '{proxy}' produced an invalid URL '{proxied}': {e}"
+        ))
+    })?;
+    Ok(proxied)
+}
+
+/// Validate a `--github-proxy` value by applying it to a sample release URL
+pub fn parse_github_proxy(proxy: &str) -> std::result::Result<String, String> {
+    apply_github_proxy(
+        "https://github.com/owner/repo/releases/download/v1/file.tgz",
+        Some(proxy),
+    )
+    .map(|_| proxy.to_string())
+    .map_err(|e| e.to_string())
 }
 
 /// Clean up existing directory and create new one
@@ -673,7 +1164,7 @@ pub async fn download_cross_compiler(
     github_proxy: Option<&str>,
 ) -> Result<()> {
     if !dir_exists_and_not_empty(compiler_dir).await {
-        download_and_extract(download_url, compiler_dir, None, github_proxy).await?;
+        download_and_extract(download_url, compiler_dir, None, github_proxy, "cross-make").await?;
     }
     Ok(())
 }
@@ -682,6 +1173,239 @@ pub async fn download_cross_compiler(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_temp_path_guard_and_cleanup() {
+        let base =
+            std::env::temp_dir().join(format!("cargo-cross-temp-guard-{}", std::process::id()));
+        let dropped = base.join("dropped.tmp");
+        let interrupted = base.join("interrupted.tmp");
+        std::fs::create_dir_all(&dropped).unwrap();
+        std::fs::create_dir_all(&interrupted).unwrap();
+
+        drop(TempPathGuard::new(&dropped));
+        assert!(!dropped.exists());
+
+        // The interrupt handler removes registered paths without waiting for the guard
+        let guard = TempPathGuard::new(&interrupted);
+        cleanup_temp_paths();
+        assert!(!interrupted.exists());
+        drop(guard);
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_release_api_url() {
+        assert_eq!(
+            release_api_url(
+                "https://github.com/zijiren233/cross-make/releases/download/v0.7.7-linux-amd64/aarch64-linux-musl-cross.tgz"
+            ),
+            Some((
+                "https://api.github.com/repos/zijiren233/cross-make/releases/tags/v0.7.7-linux-amd64"
+                    .to_string(),
+                "aarch64-linux-musl-cross.tgz"
+            ))
+        );
+        assert_eq!(release_api_url("https://example.com/toolchain.tgz"), None);
+    }
+
+    #[test]
+    fn test_resume_action() {
+        use reqwest::StatusCode;
+
+        assert_eq!(
+            resume_action(1024, StatusCode::PARTIAL_CONTENT),
+            ResumeAction::Append
+        );
+        // Range requested, but the (re-signed) redirect target sent everything
+        assert_eq!(resume_action(1024, StatusCode::OK), ResumeAction::Restart);
+        assert_eq!(resume_action(0, StatusCode::OK), ResumeAction::Restart);
+        // No Range was sent, so a 206 cannot be a continuation of the file
+        assert_eq!(
+            resume_action(0, StatusCode::PARTIAL_CONTENT),
+            ResumeAction::Restart
+        );
+    }
+
+    #[test]
+    fn test_long_path_prefix() {
+        for (path, prefixed) in [
+            (r"C:\cross\ndk", r"\\?\C:\cross\ndk"),
+            ("C:/cross/ndk", r"\\?\C:\cross\ndk"),
+            (r"\\server\share\cross", r"\\?\UNC\server\share\cross"),
+            (r"\\?\C:\cross", r"\\?\C:\cross"),
+            (r"cross\ndk", r"cross\ndk"),
+        ] {
+            assert_eq!(add_long_path_prefix(path), prefixed, "{path}");
+        }
+
+        assert_eq!(strip_long_path_prefix(r"\\?\C:\cross\ndk"), r"C:\cross\ndk");
+        assert_eq!(
+            strip_long_path_prefix(r"\\?\UNC\server\share\cross"),
+            r"\\server\share\cross"
+        );
+        // Every entry of a list or flag is stripped
+        assert_eq!(
+            strip_long_path_prefix(r"\\?\C:\a\bin;\\?\C:\b\bin;C:\Windows"),
+            r"C:\a\bin;C:\b\bin;C:\Windows"
+        );
+        assert_eq!(strip_long_path_prefix(r"-L\\?\C:\a\lib"), r"-LC:\a\lib");
+        assert_eq!(strip_long_path_prefix("/opt/cross/bin"), "/opt/cross/bin");
+    }
+
+    #[test]
+    fn test_cross_compiler_dir_too_long() {
+        assert!(!cross_compiler_dir_too_long(Path::new(r"C:\cross")));
+        assert!(!cross_compiler_dir_too_long(Path::new(
+            r"C:\Users\runneradmin\AppData\Local\cargo-cross"
+        )));
+        let deep = format!(r"C:\{}", ["nested-directory"; 6].join(r"\"));
+        assert!(cross_compiler_dir_too_long(Path::new(&deep)));
+        // The verbatim prefix does not count towards the limit
+        let limit = format!(r"C:\{}", "x".repeat(MAX_CROSS_COMPILER_DIR_LEN - 3));
+        assert!(!cross_compiler_dir_too_long(Path::new(
+            &add_long_path_prefix(&limit)
+        )));
+    }
+
+    /// Tar with a pax global header, a file, a hard link that cannot be created because
+    /// its path already exists, and a hard link to a member that does not exist
+    async fn synthetic_tar() -> Vec<u8> {
+        use tokio_tar::{Builder, EntryType, Header};
+
+        let mut builder = Builder::new(Vec::new());
+        let member = |kind: EntryType, path: &str, link: Option<&str>, data: &[u8]| {
+            let mut header = Header::new_ustar();
+            header.set_entry_type(kind);
+            header.set_path(path).unwrap();
+            if let Some(link) = link {
+                header.set_link_name(link).unwrap();
+            }
+            header.set_mode(0o755);
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            (header, data.to_vec())
+        };
+        let members = [
+            member(
+                EntryType::XGlobalHeader,
+                "pax_global_header",
+                None,
+                b"30 comment=synthetic archive\n",
+            ),
+            member(EntryType::Regular, "tc/bin/gcc", None, b"gcc"),
+            member(EntryType::Regular, "tc/bin/cc", None, b"stale"),
+            member(EntryType::Link, "tc/bin/cc", Some("tc/bin/gcc"), b""),
+            member(EntryType::Link, "tc/bin/c++", Some("tc/bin/missing"), b""),
+        ];
+        for (header, data) in members {
+            builder.append(&header, data.as_slice()).await.unwrap();
+        }
+        builder.into_inner().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_extract_tar_falls_back_for_exotic_members() {
+        let dest = std::env::temp_dir().join(format!("cargo-cross-tar-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dest);
+        std::fs::create_dir_all(&dest).unwrap();
+
+        let tar = synthetic_tar().await;
+        let report = extract_tar(tar.as_slice(), &dest, &ProgressBar::hidden())
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            ExtractReport {
+                converted: 1,
+                skipped: 1
+            }
+        );
+        assert_eq!(
+            std::fs::read_to_string(dest.join("tc/bin/cc")).unwrap(),
+            "gcc"
+        );
+        assert!(!dest.join("tc/bin/c++").exists());
+        assert!(!dest.join("pax_global_header").exists());
+
+        let _ = std::fs::remove_dir_all(&dest);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_extract_tar_fallback_stays_inside_dest() {
+        use tokio_tar::{Builder, EntryType, Header};
+
+        let root =
+            std::env::temp_dir().join(format!("cargo-cross-tar-escape-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let (dest, outside) = (root.join("dest"), root.join("outside"));
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret"), "secret").unwrap();
+
+        let mut builder = Builder::new(Vec::new());
+        let outside_link = outside.to_string_lossy().into_owned();
+        let secret_link = outside.join("secret").to_string_lossy().into_owned();
+        let members = [
+            (EntryType::Regular, "payload", None, &b"payload"[..]),
+            (EntryType::Regular, "copy", None, &b"copy"[..]),
+            // A hard link written through a symlink that points outside `dest`
+            (
+                EntryType::Symlink,
+                "escape",
+                Some(outside_link.as_str()),
+                &b""[..],
+            ),
+            (EntryType::Link, "escape/evil", Some("payload"), &b""[..]),
+            // A hard link whose source is a symlink to a file outside `dest`
+            (
+                EntryType::Symlink,
+                "secret-link",
+                Some(secret_link.as_str()),
+                &b""[..],
+            ),
+            (EntryType::Link, "copy", Some("secret-link"), &b""[..]),
+        ];
+        for (kind, path, link, data) in members {
+            let mut header = Header::new_gnu();
+            header.set_entry_type(kind);
+            header.set_path(path).unwrap();
+            if let Some(link) = link {
+                header.set_link_name(link).unwrap();
+            }
+            header.set_mode(0o644);
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, data).await.unwrap();
+        }
+        let tar = builder.into_inner().await.unwrap();
+
+        let report = extract_tar(tar.as_slice(), &dest, &ProgressBar::hidden())
+            .await
+            .unwrap();
+        assert_eq!(report.converted, 0);
+        assert!(!outside.join("evil").exists());
+        assert_eq!(std::fs::read_to_string(dest.join("copy")).unwrap(), "copy");
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_archive_member_path() {
+        let dest = Path::new("/dest");
+        assert_eq!(
+            archive_member_path(dest, Path::new("./tc/bin/gcc")),
+            Some(PathBuf::from("/dest/tc/bin/gcc"))
+        );
+        assert_eq!(
+            archive_member_path(dest, Path::new("/tc")),
+            Some(PathBuf::from("/dest/tc"))
+        );
+        assert_eq!(archive_member_path(dest, Path::new("../etc/passwd")), None);
+        assert_eq!(archive_member_path(dest, Path::new(".")), None);
+    }
+
     #[test]
     fn test_archive_format_detection() {
         assert_eq!(
@@ -697,19 +1421,125 @@ mod tests {
         assert_eq!(ArchiveFormat::from_url("foo.tar.xz"), None); // Not supported
     }
 
+    /// ISRG Root X2
+    const TEST_CA_PEM: &str = "-----BEGIN CERTIFICATE-----
+MIICGzCCAaGgAwIBAgIQQdKd0XLq7qeAwSxs6S+HUjAKBggqhkjOPQQDAzBPMQsw
+CQYDVQQGEwJVUzEpMCcGA1UEChMgSW50ZXJuZXQgU2VjdXJpdHkgUmVzZWFyY2gg
+R3JvdXAxFTATBgNVBAMTDElTUkcgUm9vdCBYMjAeFw0yMDA5MDQwMDAwMDBaFw00
+MDA5MTcxNjAwMDBaME8xCzAJBgNVBAYTAlVTMSkwJwYDVQQKEyBJbnRlcm5ldCBT
+ZWN1cml0eSBSZXNlYXJjaCBHcm91cDEVMBMGA1UEAxMMSVNSRyBSb290IFgyMHYw
+EAYHKoZIzj0CAQYFK4EEACIDYgAEzZvVn4CDCuwJSvMWSj5cz3es3mcFDR0HttwW
++1qLFNvicWDEukWVEYmO6gbf9yoWHKS5xcUy4APgHoIYOIvXRdgKam7mAHf7AlF9
+ItgKbppbd9/w+kHsOdx1ymgHDB/qo0IwQDAOBgNVHQ8BAf8EBAMCAQYwDwYDVR0T
+AQH/BAUwAwEB/zAdBgNVHQ4EFgQUfEKWrt5LSDv6kviejM9ti6lyN5UwCgYIKoZI
+zj0EAwMDaAAwZQIwe3lORlCEwkSHRhtFcP9Ymd70/aTSVaYgLXTWNLxBo1BfASdW
+tL4ndQavEi51mI38AjEAi/V3bNTIZargCyzuFJ0nN6T5U6VR5CmD1/iQMVtCnwr1
+/q4AaOeMSQ+2b1tbFfLn
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn test_http_client_builder_tls_options() {
+        let dir = std::env::temp_dir().join(format!("cargo-cross-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ca = dir.join("ca.pem");
+        let not_pem = dir.join("not-pem.txt");
+        std::fs::write(&ca, TEST_CA_PEM).unwrap();
+        std::fs::write(&not_pem, "hello").unwrap();
+
+        for (ca_certs, insecure, ok) in [
+            (vec![], false, true),
+            (vec![], true, true),
+            (vec![ca.clone()], false, true),
+            (vec![ca.clone()], true, true),
+            (vec![ca.clone(), not_pem.clone()], false, false),
+            (vec![dir.join("missing.pem")], false, false),
+        ] {
+            let tls = DownloadTls {
+                ca_certs: ca_certs.clone(),
+                insecure,
+            };
+            let client = http_client_builder(&tls).and_then(|b| Ok(b.build()?));
+            assert_eq!(client.is_ok(), ok, "{ca_certs:?} insecure={insecure}");
+        }
+
+        let err = http_client_builder(&DownloadTls {
+            ca_certs: vec![not_pem.clone()],
+            insecure: false,
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("not-pem.txt"), "{err}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_is_tls_verification_error() {
+        assert!(is_tls_verification_error(
+            "error sending request: client error (Connect): invalid peer certificate: UnknownIssuer"
+        ));
+        assert!(is_tls_verification_error(
+            "error:0A000086:SSL routines::certificate verify failed: unable to get local issuer certificate"
+        ));
+        assert!(!is_tls_verification_error(
+            "error sending request: operation timed out"
+        ));
+    }
+
     #[test]
     fn test_github_proxy() {
-        assert_eq!(
-            apply_github_proxy("https://github.com/foo/bar", Some("https://proxy.com/")),
-            "https://proxy.com/https://github.com/foo/bar"
-        );
-        assert_eq!(
-            apply_github_proxy("https://other.com/foo", Some("https://proxy.com/")),
-            "https://other.com/foo"
-        );
-        assert_eq!(
-            apply_github_proxy("https://github.com/foo/bar", None),
-            "https://github.com/foo/bar"
-        );
+        let url = "https://github.com/zijiren233/cross-make/releases/download/v0.7.7/a.tgz";
+        for (proxy, expected) in [
+            // Prefix
+            (
+                Some("https://proxy.com/"),
+                "https://proxy.com/https://github.com/zijiren233/cross-make/releases/download/v0.7.7/a.tgz",
+            ),
+            // Host replacement
+            (
+                Some("https://mirror.example.com"),
+                "https://mirror.example.com/zijiren233/cross-make/releases/download/v0.7.7/a.tgz",
+            ),
+            (
+                Some("https://mirror.example.com/gh"),
+                "https://mirror.example.com/gh/zijiren233/cross-make/releases/download/v0.7.7/a.tgz",
+            ),
+            // Templates
+            (
+                Some("https://mirror.example.com/{path}?token=abc"),
+                "https://mirror.example.com/zijiren233/cross-make/releases/download/v0.7.7/a.tgz?token=abc",
+            ),
+            (
+                Some("https://fetch.example.com/?u={url}"),
+                "https://fetch.example.com/?u=https://github.com/zijiren233/cross-make/releases/download/v0.7.7/a.tgz",
+            ),
+            // Unset or empty
+            (None, url),
+            (Some(""), url),
+        ] {
+            assert_eq!(apply_github_proxy(url, proxy).unwrap(), expected, "{proxy:?}");
+        }
+    }
+
+    #[test]
+    fn test_github_proxy_passes_through_other_urls() {
+        for proxy in [
+            "https://proxy.com/",
+            "https://mirror.example.com",
+            "https://m/{path}",
+        ] {
+            assert_eq!(
+                apply_github_proxy("https://dl.google.com/android/ndk.zip", Some(proxy)).unwrap(),
+                "https://dl.google.com/android/ndk.zip"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_github_proxy() {
+        assert!(parse_github_proxy("https://mirror.example.com").is_ok());
+        assert!(parse_github_proxy("https://proxy.com/").is_ok());
+        assert!(parse_github_proxy("mirror.example.com").is_err());
+        assert!(parse_github_proxy("{path}").is_err());
     }
 }
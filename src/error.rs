@@ -17,7 +17,7 @@ pub enum CrossError {
     #[error("Unknown option: {0}")]
     UnknownOption(String),
 
-    #[error("Target not found: {target}\nUse '--list-targets' to see available targets")]
+    #[error("Target not found: {target}\nUse '{prog} targets' to see available targets", prog = crate::cli::program_name())]
     TargetNotFound { target: String },
 
     #[error("Unsupported target: {0}")]
@@ -35,9 +35,43 @@ pub enum CrossError {
     #[error("Unsupported FreeBSD version '{version}'\nSupported versions: {supported}")]
     UnsupportedFreebsdVersion { version: String, supported: String },
 
+    #[error(
+        "Profile '{profile}' is not defined in the workspace manifest or cargo config\n\
+         Available profiles: {available}"
+    )]
+    UnknownProfile { profile: String, available: String },
+
+    #[error(
+        "Vendor directory {} is missing packages from {}: {missing}\n\
+         Re-run 'cargo vendor' to update it",
+        dir.display(),
+        lockfile.display()
+    )]
+    VendorIncomplete {
+        dir: PathBuf,
+        lockfile: PathBuf,
+        missing: String,
+    },
+
     #[error("Download failed: {0}")]
     DownloadFailed(String),
 
+    #[error("TLS certificate verification failed for {url}: {message}\nIf a proxy intercepts TLS, pass its CA bundle with --download-ca-cert <PEM> (or CROSS_CA_BUNDLE / SSL_CERT_FILE); --download-insecure disables verification as a last resort")]
+    TlsVerificationFailed { url: String, message: String },
+
+    #[error("Missing {dest} and downloads are disabled (sync --check)\nWould download: {url}", dest = dest.display())]
+    DownloadDisabled { url: String, dest: PathBuf },
+
+    #[error("Not enough disk space in {}: {needed} needed, {available} available\nFree up space or point --cross-compiler-dir at a larger disk", path.display())]
+    InsufficientDiskSpace {
+        needed: String,
+        available: String,
+        path: PathBuf,
+    },
+
+    #[error("Ran out of disk space in {} ({available} left)\nFree up space or point --cross-compiler-dir at a larger disk", path.display())]
+    DiskFull { path: PathBuf, available: String },
+
     #[error("HTTP request failed: {0}")]
     HttpError(#[from] reqwest::Error),
 
@@ -71,11 +105,39 @@ pub enum CrossError {
     #[error("Command failed: {command}")]
     CommandFailed { command: String },
 
+    #[error("Rust toolchain '{toolchain}' is not installed\nInstall it with: rustup toolchain install {toolchain}")]
+    ToolchainNotInstalled { toolchain: String },
+
+    #[error("Custom Rust toolchain '{toolchain}' is not linked\nLink it with: rustup toolchain link {toolchain} <path>")]
+    CustomToolchainNotLinked { toolchain: String },
+
+    #[error("Custom Rust toolchain '{toolchain}' has no {item} in {}\nrustup cannot add components to linked toolchains; add it to that sysroot or use a rustup-managed toolchain", sysroot.display())]
+    CustomToolchainIncomplete {
+        toolchain: String,
+        item: String,
+        sysroot: PathBuf,
+    },
+
     #[error("Failed to install Rust target: {target}\nRun 'rustup target add {target}' manually to see details")]
     TargetInstallFailed { target: String },
 
-    #[error("Target '{target}' requires build-std but is not in rustc target list\nUse BUILD_STD=core,alloc or similar to enable build-std")]
-    BuildStdRequired { target: String },
+    #[error("{item} is not installed and automatic installation is disabled (--no-target-install)\nInstall it ahead of time with: {command}")]
+    RustupInstallDisabled { item: String, command: String },
+
+    #[error("Target '{target}' requires build-std but is not in rustc target list\n{reason}")]
+    BuildStdRequired { target: String, reason: String },
+
+    #[error("Target '{target}' has no prebuilt std and requires build-std, but build-std was explicitly disabled (--build-std=false / BUILD_STD=false)\nRemove BUILD_STD=false for this target or set BUILD_STD=true")]
+    BuildStdDisabled { target: String },
+
+    #[error("No runner can be configured for {target}:\n  - {}\nInstall the missing runner, pass --runner, or build anyway with --allow-no-runner", .reasons.join("\n  - "))]
+    RunnerUnavailable {
+        target: String,
+        reasons: Vec<String>,
+    },
+
+    #[error("{count} target(s) cannot be built or run from this host:\n{report}")]
+    ValidationFailed { count: usize, report: String },
 
     #[error("Cross-compilation to {target_os} is not supported from {host_os}")]
     CrossCompilationNotSupported { target_os: String, host_os: String },
@@ -83,13 +145,70 @@ pub enum CrossError {
     #[error("Unsupported architecture '{arch}' for {os}")]
     UnsupportedArchitecture { arch: String, os: String },
 
+    #[error(
+        "Android NDK {requested} does not support {arch}; use --ndk-version {needed} or newer"
+    )]
+    NdkTooOld {
+        arch: String,
+        needed: String,
+        requested: String,
+    },
+
+    #[error("Toolchain smoke test failed for {target}: {reason}\nCommand: {command}\n{stderr}\nPass --smoke-test-toolchain=false to skip this check")]
+    SmokeTestFailed {
+        target: String,
+        reason: String,
+        command: String,
+        stderr: String,
+    },
+
+    #[error("Failed to set the install name of {dylib}\nCommand: {command}\n{stderr}\nPass --fix-install-name=false to keep the linker's install name", dylib = dylib.display())]
+    InstallNameFailed {
+        dylib: PathBuf,
+        command: String,
+        stderr: String,
+    },
+
+    #[error("Failed to split debug info out of {artifact}\nCommand: {command}\n{stderr}\nDrop --debuginfo-artifacts to collect the artifact as built", artifact = artifact.display())]
+    DebuginfoFailed {
+        artifact: PathBuf,
+        command: String,
+        stderr: String,
+    },
+
+    #[error("No objcopy found to convert the images of {target}\nInstall the LLVM tools with `rustup component add llvm-tools` (or cargo-binutils with `cargo install cargo-binutils`), or put the toolchain's objcopy on PATH")]
+    ObjcopyNotFound { target: String },
+
+    #[error("Failed to convert {artifact}\nCommand: {command}\n{stderr}", artifact = artifact.display())]
+    ObjcopyFailed {
+        artifact: PathBuf,
+        command: String,
+        stderr: String,
+    },
+
+    #[error("Artifacts {artifacts} would all be collected as '{name}'\nPass an --artifact-name that includes {{target}}, {{kind}} or {{package}} to tell them apart")]
+    ArtifactNameCollision { name: String, artifacts: String },
+
+    #[error("{target} uses bindgen, but {library} was not found on this host\nInstall libclang (e.g. the libclang-dev or clang package), set LIBCLANG_PATH to the directory containing {library}, or pass --auto-libclang with --libclang-url to download a copy")]
+    LibclangNotFound { target: String, library: String },
+
+    #[error("Failed to download libclang from {url}: {reason}\nSet LIBCLANG_PATH to the directory containing {library}, or pass --libclang-url with an archive that has lib/{library}")]
+    LibclangDownloadFailed {
+        url: String,
+        reason: String,
+        library: String,
+    },
+
+    #[error("Target policy {} rejects:\n{violations}\nPass --policy-warn-only to build anyway", policy.display())]
+    TargetPolicyViolation { policy: PathBuf, violations: String },
+
     #[error("Environment variable error: {0}")]
     EnvError(String),
 
     #[error("No matching targets found for pattern '{pattern}'\nUse '{prog} targets' to see available targets", prog = crate::cli::program_name())]
     NoMatchingTargets { pattern: String },
 
-    #[error("Invalid target triple '{target}': contains invalid character '{char}'\nTarget triples may only contain lowercase letters (a-z), digits (0-9), hyphens (-), and underscores (_)")]
+    #[error("Invalid target triple '{target}': contains invalid character '{char}'\nTarget triples may only contain lowercase letters (a-z), digits (0-9), dots (.), hyphens (-), and underscores (_)")]
     InvalidTargetTriple { target: String, char: char },
 
     #[error("Cargo exited with code {code}")]
@@ -101,6 +220,9 @@ pub enum CrossError {
     #[error("Regex error: {0}")]
     RegexError(#[from] regex_lite::Error),
 
+    #[error("Interrupted")]
+    Interrupted,
+
     #[error("{0}")]
     Other(String),
 
@@ -129,13 +251,9 @@ impl From<std::io::Error> for CrossError {
 /// Result type alias for cargo-cross
 pub type Result<T> = std::result::Result<T, CrossError>;
 
-/// Execute a command and return its status, with improved error messages
-pub async fn run_command(cmd: &mut Command, program: &str) -> Result<std::process::ExitStatus> {
-    if std::env::var_os("CARGO_CROSS_SILENT").is_some() {
-        cmd.stdout(Stdio::null()).stderr(Stdio::null());
-    }
-
-    cmd.status().await.map_err(|e| match e.kind() {
+/// Map a spawn/wait failure to a descriptive error
+fn command_error(e: &std::io::Error, program: &str) -> CrossError {
+    match e.kind() {
         std::io::ErrorKind::NotFound => CrossError::ProgramNotFound {
             program: program.to_string(),
         },
@@ -147,22 +265,269 @@ pub async fn run_command(cmd: &mut Command, program: &str) -> Result<std::proces
             command: program.to_string(),
             reason: e.to_string(),
         },
-    })
+    }
+}
+
+/// Spawn a command and register it so interrupts are forwarded to it. On Unix it leads a
+/// process group of its own (see [`crate::interrupt::own_process_group`]), so the signals
+/// reach everything it starts
+pub(crate) fn spawn_tracked(
+    cmd: &mut Command,
+    program: &str,
+) -> Result<(tokio::process::Child, crate::interrupt::ChildGuard)> {
+    if crate::interrupt::is_interrupted() {
+        return Err(CrossError::Interrupted);
+    }
+    let group = crate::interrupt::own_process_group();
+    #[cfg(unix)]
+    if group {
+        cmd.process_group(0);
+    }
+    let child = cmd.spawn().map_err(|e| command_error(&e, program))?;
+    let guard = crate::interrupt::ChildGuard::register(child.id(), group);
+    Ok((child, guard))
+}
+
+/// Execute a command and return its status, with improved error messages
+pub async fn run_command(cmd: &mut Command, program: &str) -> Result<std::process::ExitStatus> {
+    if std::env::var_os("CARGO_CROSS_SILENT").is_some() {
+        cmd.stdout(Stdio::null()).stderr(Stdio::null());
+    }
+
+    let (mut child, _guard) = spawn_tracked(cmd, program)?;
+    child.wait().await.map_err(|e| command_error(&e, program))
+}
+
+/// Next line of a stream as raw bytes, `None` at EOF. Lines that are not UTF-8 pass
+/// through: ending the stream there would close the pipe while the child still writes.
+/// The buffer lives with the reader, so a line cut short by `select!` is not lost.
+async fn next_line<R: tokio::io::AsyncBufRead + Unpin>(
+    stream: &mut Option<(R, Vec<u8>)>,
+) -> Option<Vec<u8>> {
+    use tokio::io::AsyncBufReadExt;
+
+    let Some((reader, buf)) = stream else {
+        return std::future::pending().await;
+    };
+    let _ = reader.read_until(b'\n', buf).await;
+    (!buf.is_empty()).then(|| std::mem::take(buf))
+}
+
+/// Like [`next_line`], but a segment also ends at a `\r`, so a progress bar redrawn in
+/// place is passed on as it is drawn rather than with the next full line
+async fn next_segment<R: tokio::io::AsyncBufRead + Unpin>(
+    stream: &mut Option<(R, Vec<u8>)>,
+) -> Option<Vec<u8>> {
+    use tokio::io::AsyncBufReadExt;
+
+    let Some((reader, buf)) = stream else {
+        return std::future::pending().await;
+    };
+    while let Ok(available) = reader.fill_buf().await {
+        if available.is_empty() {
+            break;
+        }
+        if let Some(mut end) = available.iter().position(|b| matches!(b, b'\n' | b'\r')) {
+            if available[end] == b'\r' && available.get(end + 1) == Some(&b'\n') {
+                end += 1;
+            }
+            buf.extend_from_slice(&available[..=end]);
+            reader.consume(end + 1);
+            break;
+        }
+        let len = available.len();
+        buf.extend_from_slice(available);
+        reader.consume(len);
+    }
+    (!buf.is_empty()).then(|| std::mem::take(buf))
+}
+
+/// Write a raw output line after `prefix`, ending it with a newline
+fn write_line(mut out: impl std::io::Write, prefix: &str, line: &[u8]) {
+    let newline: &[u8] = if line.ends_with(b"\n") { b"" } else { b"\n" };
+    let _ = out
+        .write_all(prefix.as_bytes())
+        .and_then(|()| out.write_all(line))
+        .and_then(|()| out.write_all(newline))
+        .and_then(|()| out.flush());
+}
+
+/// A raw output line as text for diagnostics, without its line ending and ANSI escapes
+fn line_text(line: &[u8]) -> String {
+    strip_ansi(String::from_utf8_lossy(line).trim_end_matches(['\r', '\n']))
+}
+
+/// Remove ANSI escape sequences (colours, erase-line) from `text`
+#[must_use]
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        if chars.clone().next() == Some('[') {
+            chars.next();
+            for c in chars.by_ref() {
+                if ('\x40'..='\x7e').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Execute a command, streaming its stderr through while keeping a copy for diagnostics
+pub async fn run_command_tee_stderr(
+    cmd: &mut Command,
+    program: &str,
+) -> Result<(std::process::ExitStatus, String)> {
+    run_command_watch_stderr(cmd, program, std::time::Duration::from_secs(3600), |_| {}).await
+}
+
+/// Execute a command, streaming its stderr through while keeping a copy. `watch` sees
+/// every stderr line and is called with `None` once per `tick`, so it can also report on
+/// work that has been silent for a while. Progress bar redraws (text ending in `\r`) are
+/// passed through but neither kept nor watched.
+pub async fn run_command_watch_stderr(
+    cmd: &mut Command,
+    program: &str,
+    tick: std::time::Duration,
+    mut watch: impl FnMut(Option<&str>),
+) -> Result<(std::process::ExitStatus, String)> {
+    use std::io::Write as _;
+    use tokio::io::BufReader;
+
+    if std::env::var_os("CARGO_CROSS_SILENT").is_some() {
+        cmd.stdout(Stdio::null());
+    }
+    cmd.stderr(Stdio::piped());
+
+    let (mut child, _guard) = spawn_tracked(cmd, program)?;
+
+    let silent = std::env::var_os("CARGO_CROSS_SILENT").is_some();
+    let mut stderr = child
+        .stderr
+        .take()
+        .map(|err| (BufReader::new(err), Vec::new()));
+    let mut captured = String::new();
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + tick, tick);
+    while stderr.is_some() {
+        tokio::select! {
+            line = next_segment(&mut stderr) => {
+                let Some(line) = line else {
+                    stderr = None;
+                    continue;
+                };
+                if line.ends_with(b"\r") {
+                    if !silent {
+                        let mut out = std::io::stderr().lock();
+                        let _ = out.write_all(&line).and_then(|()| out.flush());
+                    }
+                    continue;
+                }
+                if !silent {
+                    write_line(std::io::stderr().lock(), "", &line);
+                }
+                let line = line_text(&line);
+                watch(Some(&line));
+                captured.push_str(&line);
+                captured.push('\n');
+            }
+            _ = interval.tick() => watch(None),
+        }
+    }
+
+    let status = child.wait().await.map_err(|e| command_error(&e, program))?;
+    Ok((status, captured))
+}
+
+/// Execute a command, streaming its stdout through while keeping a copy
+pub async fn run_command_tee_stdout(
+    cmd: &mut Command,
+    program: &str,
+) -> Result<(std::process::ExitStatus, String)> {
+    use tokio::io::BufReader;
+
+    cmd.stdout(Stdio::piped());
+
+    let (mut child, _guard) = spawn_tracked(cmd, program)?;
+
+    let silent = std::env::var_os("CARGO_CROSS_SILENT").is_some();
+    let mut stdout = child
+        .stdout
+        .take()
+        .map(|out| (BufReader::new(out), Vec::new()));
+    let mut captured = String::new();
+    while let Some(line) = next_line(&mut stdout).await {
+        if !silent {
+            write_line(std::io::stdout().lock(), "", &line);
+        }
+        captured.push_str(&line_text(&line));
+        captured.push('\n');
+    }
+
+    let status = child.wait().await.map_err(|e| command_error(&e, program))?;
+    Ok((status, captured))
+}
+
+/// Execute a command, streaming its stdout and stderr through with every line prefixed by
+/// `prefix`, and keeping a copy of the unprefixed stderr
+pub async fn run_command_prefixed(
+    cmd: &mut Command,
+    program: &str,
+    prefix: &str,
+) -> Result<(std::process::ExitStatus, String)> {
+    use tokio::io::BufReader;
+
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let (mut child, _guard) = spawn_tracked(cmd, program)?;
+
+    let silent = std::env::var_os("CARGO_CROSS_SILENT").is_some();
+    let mut stdout = child
+        .stdout
+        .take()
+        .map(|out| (BufReader::new(out), Vec::new()));
+    let mut stderr = child
+        .stderr
+        .take()
+        .map(|err| (BufReader::new(err), Vec::new()));
+    let mut captured = String::new();
+    while stdout.is_some() || stderr.is_some() {
+        tokio::select! {
+            line = next_line(&mut stdout) => match line {
+                Some(line) if !silent => write_line(std::io::stdout().lock(), prefix, &line),
+                Some(_) => {}
+                None => stdout = None,
+            },
+            line = next_line(&mut stderr) => match line {
+                Some(line) => {
+                    if !silent {
+                        write_line(std::io::stderr().lock(), prefix, &line);
+                    }
+                    captured.push_str(&line_text(&line));
+                    captured.push('\n');
+                }
+                None => stderr = None,
+            },
+        }
+    }
+
+    let status = child.wait().await.map_err(|e| command_error(&e, program))?;
+    Ok((status, captured))
 }
 
 /// Execute a command and return its output, with improved error messages
 pub async fn run_command_output(cmd: &mut Command, program: &str) -> Result<std::process::Output> {
-    cmd.output().await.map_err(|e| match e.kind() {
-        std::io::ErrorKind::NotFound => CrossError::ProgramNotFound {
-            program: program.to_string(),
-        },
-        std::io::ErrorKind::PermissionDenied => CrossError::CommandExecutionFailed {
-            command: program.to_string(),
-            reason: "Permission denied".to_string(),
-        },
-        _ => CrossError::CommandExecutionFailed {
-            command: program.to_string(),
-            reason: e.to_string(),
-        },
-    })
+    // Same stdio as `Command::output`
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let (child, _guard) = spawn_tracked(cmd, program)?;
+    child
+        .wait_with_output()
+        .await
+        .map_err(|e| command_error(&e, program))
 }
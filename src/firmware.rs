@@ -0,0 +1,340 @@
+//! Firmware images of bare-metal targets (`--objcopy-format`)
+//!
+//! Targets without an OS (`thumbv7em-none-eabihf`, `riscv32imac-unknown-none-elf`) link
+//! ELF files, while flashing tools usually want a raw memory image or Intel HEX. After a
+//! build, the text/data/bss sizes of each executable and cdylib of such a target are read
+//! from its ELF file (as `size` prints them), and with `--objcopy-format` objcopy writes
+//! the image next to it. The ELF file itself is left as it is.
+
+use crate::artifacts::ArtifactKind;
+use crate::cli::{Args, ObjcopyFormat};
+use crate::color;
+use crate::config::HostPlatform;
+use crate::debuginfo::{find_tool, tool_candidates, tool_prefix, ToolCommand};
+use crate::env::CrossEnv;
+use crate::error::{run_command_output, CrossError, Result};
+use object::{Object, ObjectSection, SectionFlags, SectionKind};
+use std::path::{Path, PathBuf};
+use tokio::process::Command as TokioCommand;
+
+/// Section sizes in the Berkeley format of `size`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SectionSizes {
+    /// Code and read-only data
+    pub text: u64,
+    /// Initialized writable data
+    pub data: u64,
+    /// Zero-initialized data, which takes no room in the image
+    pub bss: u64,
+}
+
+impl SectionSizes {
+    #[must_use]
+    pub const fn total(&self) -> u64 {
+        self.text + self.data + self.bss
+    }
+}
+
+/// A bare-metal artifact after the build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Firmware {
+    /// ELF file cargo wrote
+    pub elf: PathBuf,
+    /// Image written by `--objcopy-format`
+    pub image: Option<PathBuf>,
+    pub sizes: SectionSizes,
+}
+
+/// Sizes of the sections an ELF file loads: read-only ones count as text, writable ones
+/// as data, and those without contents in the file as bss
+pub fn section_sizes(data: &[u8]) -> Result<SectionSizes> {
+    let file = object::File::parse(data).map_err(|e| {
+        CrossError::InvalidArgument(format!("cannot read the ELF file for its size: {e}"))
+    })?;
+    let mut sizes = SectionSizes::default();
+    for section in file.sections() {
+        let SectionFlags::Elf { sh_flags } = section.flags() else {
+            continue;
+        };
+        if sh_flags & u64::from(object::elf::SHF_ALLOC) == 0 {
+            continue;
+        }
+        let size = section.size();
+        if matches!(
+            section.kind(),
+            SectionKind::UninitializedData | SectionKind::UninitializedTls
+        ) {
+            sizes.bss += size;
+        } else if sh_flags & u64::from(object::elf::SHF_WRITE) != 0 {
+            sizes.data += size;
+        } else {
+            sizes.text += size;
+        }
+    }
+    Ok(sizes)
+}
+
+/// Whether artifacts of `kind` are linked images. Static libraries are linked into
+/// something else first.
+#[must_use]
+pub const fn is_image(kind: ArtifactKind) -> bool {
+    !matches!(kind, ArtifactKind::Staticlib)
+}
+
+/// The image written next to `elf`
+#[must_use]
+pub fn image_path(elf: &Path, format: ObjcopyFormat) -> PathBuf {
+    let mut path = elf.as_os_str().to_os_string();
+    path.push(".");
+    path.push(format.extension());
+    PathBuf::from(path)
+}
+
+/// `objcopy` run converting `elf` to `format`
+#[must_use]
+pub fn objcopy_command(objcopy: &Path, elf: &Path, format: ObjcopyFormat) -> ToolCommand {
+    let elf_arg = elf.display().to_string();
+    let image = image_path(elf, format).display().to_string();
+    ToolCommand::new(objcopy, &["-O", format.as_str(), &elf_arg, &image])
+}
+
+/// `llvm-objcopy` of the toolchain's `llvm-tools` component, if installed
+async fn llvm_tools_objcopy(args: &Args, host: &HostPlatform) -> Option<PathBuf> {
+    let mut cmd = TokioCommand::new("rustc");
+    if let Some(ref toolchain) = args.toolchain {
+        cmd.arg(format!("+{toolchain}"));
+    }
+    cmd.args(["--print", "sysroot"]);
+    let output = run_command_output(&mut cmd, "rustc").await.ok()?;
+    let sysroot = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    Some(
+        sysroot
+            .join("lib/rustlib")
+            .join(&host.triple)
+            .join("bin")
+            .join(format!("llvm-objcopy{}", host.exe_ext())),
+    )
+    .filter(|path| path.is_file())
+}
+
+/// objcopy for bare-metal ELF files: cargo-binutils' `rust-objcopy`, the `llvm-objcopy`
+/// of the `llvm-tools` component, then the toolchain's, LLVM's or the plain one on PATH
+async fn find_objcopy(args: &Args, cross_env: &CrossEnv, host: &HostPlatform) -> Option<PathBuf> {
+    if let Ok(path) = which::which(format!("rust-objcopy{}", host.exe_ext())) {
+        return Some(path);
+    }
+    if let Some(path) = llvm_tools_objcopy(args, host).await {
+        return Some(path);
+    }
+    let prefix = tool_prefix(cross_env, host);
+    find_tool(
+        &tool_candidates("objcopy", prefix.as_deref(), host),
+        cross_env,
+    )
+}
+
+/// Read the sizes of the bare-metal `artifacts` of `target` and, with `--objcopy-format`,
+/// convert each into an image next to it
+pub async fn process_artifacts(
+    target: &str,
+    artifacts: &[(ArtifactKind, PathBuf)],
+    args: &Args,
+    cross_env: &CrossEnv,
+    host: &HostPlatform,
+) -> Result<Vec<Firmware>> {
+    let objcopy = match args.objcopy_format {
+        Some(_) => Some(find_objcopy(args, cross_env, host).await.ok_or_else(|| {
+            CrossError::ObjcopyNotFound {
+                target: target.to_string(),
+            }
+        })?),
+        None => None,
+    };
+
+    let mut firmware = Vec::new();
+    for (kind, elf) in artifacts {
+        if !is_image(*kind) {
+            continue;
+        }
+        let sizes = section_sizes(&std::fs::read(elf)?)?;
+        let image = match (args.objcopy_format, objcopy.as_deref()) {
+            (Some(format), Some(objcopy)) => {
+                let command = objcopy_command(objcopy, elf, format);
+                let mut cmd = TokioCommand::new(&command.program);
+                cmd.args(&command.args);
+                let output =
+                    run_command_output(&mut cmd, &command.program.display().to_string()).await?;
+                if !output.status.success() {
+                    return Err(CrossError::ObjcopyFailed {
+                        artifact: elf.clone(),
+                        command: command.display(),
+                        stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                    });
+                }
+                let image = image_path(elf, format);
+                color::log_success(&format!(
+                    "Image: {}",
+                    color::cyan(&image.display().to_string())
+                ));
+                Some(image)
+            }
+            _ => None,
+        };
+        firmware.push(Firmware {
+            elf: elf.clone(),
+            image,
+            sizes,
+        });
+    }
+    Ok(firmware)
+}
+
+/// Size table of the bare-metal artifacts of the run, `size`-style, empty without any
+#[must_use]
+pub fn format_size_summary(firmware: &[(String, Firmware)]) -> Vec<String> {
+    if firmware.is_empty() {
+        return Vec::new();
+    }
+    let name = |elf: &Path| {
+        elf.file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+    };
+    let target_width = firmware
+        .iter()
+        .map(|(target, _)| target.len())
+        .max()
+        .unwrap_or(0);
+    let name_width = firmware
+        .iter()
+        .map(|(_, firmware)| name(&firmware.elf).len())
+        .max()
+        .unwrap_or(0);
+    std::iter::once("Firmware sizes (bytes):".to_string())
+        .chain(firmware.iter().map(|(target, firmware)| {
+            let SectionSizes { text, data, bss } = firmware.sizes;
+            format!(
+                "  {target:<target_width$}  {:<name_width$}  text {text:>8}  data {data:>8}  bss {bss:>8}  total {:>8}",
+                name(&firmware.elf),
+                firmware.sizes.total()
+            )
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A little-endian 32-bit ARM ELF executable with `.text` (code), `.rodata`, `.data`,
+    /// `.bss` and a `.comment` section that is not loaded
+    fn fixture_elf() -> Vec<u8> {
+        const SHF_WRITE: u32 = 0x1;
+        const SHF_ALLOC: u32 = 0x2;
+        const SHF_EXECINSTR: u32 = 0x4;
+        let shstrtab = b"\0.text\0.rodata\0.data\0.bss\0.comment\0.shstrtab\0";
+        // (name offset, type, flags, size); SHT_PROGBITS = 1, SHT_NOBITS = 8
+        let sections: [(u32, u32, u32, u32); 5] = [
+            (1, 1, SHF_ALLOC | SHF_EXECINSTR, 0x120),
+            (7, 1, SHF_ALLOC, 0x30),
+            (15, 1, SHF_ALLOC | SHF_WRITE, 0x10),
+            (21, 8, SHF_ALLOC | SHF_WRITE, 0x400),
+            (26, 1, 0, 0x20),
+        ];
+
+        let mut data = vec![0u8; 52];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = 1; // 32-bit
+        data[5] = 1; // little endian
+        data[6] = 1; // EV_CURRENT
+        data[16..18].copy_from_slice(&2u16.to_le_bytes()); // ET_EXEC
+        data[18..20].copy_from_slice(&40u16.to_le_bytes()); // EM_ARM
+        data[20..24].copy_from_slice(&1u32.to_le_bytes());
+        data[40..42].copy_from_slice(&52u16.to_le_bytes()); // e_ehsize
+        data[46..48].copy_from_slice(&40u16.to_le_bytes()); // e_shentsize
+        data[48..50].copy_from_slice(&7u16.to_le_bytes()); // e_shnum
+        data[50..52].copy_from_slice(&6u16.to_le_bytes()); // e_shstrndx
+
+        let mut offsets = Vec::new();
+        for (_, kind, _, size) in sections {
+            offsets.push(u32::try_from(data.len()).unwrap());
+            if kind != 8 {
+                data.resize(data.len() + size as usize, 0);
+            }
+        }
+        let shstrtab_offset = u32::try_from(data.len()).unwrap();
+        data.extend_from_slice(shstrtab);
+        let section_headers = u32::try_from(data.len()).unwrap();
+        data[32..36].copy_from_slice(&section_headers.to_le_bytes());
+
+        let mut header = |name: u32, kind: u32, flags: u32, offset: u32, size: u32| {
+            for field in [name, kind, flags, 0, offset, size, 0, 0, 1, 0] {
+                data.extend_from_slice(&field.to_le_bytes());
+            }
+        };
+        header(0, 0, 0, 0, 0);
+        for ((name, kind, flags, size), offset) in sections.into_iter().zip(offsets) {
+            header(name, kind, flags, offset, size);
+        }
+        header(
+            36,
+            3,
+            0,
+            shstrtab_offset,
+            u32::try_from(shstrtab.len()).unwrap(),
+        );
+        data
+    }
+
+    #[test]
+    fn test_section_sizes() {
+        let sizes = section_sizes(&fixture_elf()).unwrap();
+        assert_eq!(
+            sizes,
+            SectionSizes {
+                text: 0x150,
+                data: 0x10,
+                bss: 0x400
+            }
+        );
+        assert_eq!(sizes.total(), 0x560);
+        assert!(section_sizes(b"not an elf file").is_err());
+    }
+
+    #[test]
+    fn test_objcopy_command() {
+        let objcopy = Path::new("/rust/bin/llvm-objcopy");
+        let elf = Path::new("target/thumbv7em-none-eabihf/release/blinky");
+        assert_eq!(
+            objcopy_command(objcopy, elf, ObjcopyFormat::Binary).display(),
+            "/rust/bin/llvm-objcopy -O binary target/thumbv7em-none-eabihf/release/blinky \
+             target/thumbv7em-none-eabihf/release/blinky.bin"
+        );
+        assert_eq!(
+            image_path(elf, ObjcopyFormat::Ihex),
+            Path::new("target/thumbv7em-none-eabihf/release/blinky.hex")
+        );
+        assert!(is_image(ArtifactKind::Bin));
+        assert!(!is_image(ArtifactKind::Staticlib));
+    }
+
+    #[test]
+    fn test_format_size_summary() {
+        assert!(format_size_summary(&[]).is_empty());
+        let firmware = Firmware {
+            elf: PathBuf::from("target/thumbv7em-none-eabihf/release/blinky"),
+            image: None,
+            sizes: SectionSizes {
+                text: 4096,
+                data: 16,
+                bss: 1024,
+            },
+        };
+        assert_eq!(
+            format_size_summary(&[("thumbv7em-none-eabihf".to_string(), firmware)]),
+            [
+                "Firmware sizes (bytes):",
+                "  thumbv7em-none-eabihf  blinky  text     4096  data       16  bss     1024  total     5136"
+            ]
+        );
+    }
+}
@@ -0,0 +1,447 @@
+//! Files cargo-cross generates into the cross compiler directory
+//!
+//! Docker QEMU runner scripts, CMake toolchain files and the Android clang wrappers of
+//! Windows hosts embed paths and versions of the run that wrote them. Every such file
+//! starts with a header holding the hash of its content, so a file written by an older
+//! cargo-cross or for other settings is detected and regenerated, and
+//! `cargo cross clean --generated` can find them all.
+//!
+//! Runner scripts and CMake files are also named after that hash (see [`write_idempotent`]):
+//! runs for different projects sharing a cross compiler directory then share identical
+//! files and never overwrite each other's differing ones. The clang wrappers keep their
+//! plain names, since compilers are recognized by their file name.
+
+use crate::cache::fnv1a;
+use crate::error::{CrossError, Result};
+use crate::layout;
+use std::path::{Path, PathBuf};
+
+/// Marker of the header line of a generated file
+const HEADER_MARKER: &str = "cargo-cross-generated";
+
+/// Directory inside an NDK holding the clang wrappers generated on Windows hosts
+pub const CLANG_WRAPPER_DIR: &str = "cargo-cross-bin";
+
+/// Comments a header line can start with: `#` for scripts and CMake files, `@REM` for
+/// batch files (the `@` keeps the line from being echoed)
+const HEADER_COMMENTS: &[&str] = &["#", "@REM"];
+
+/// Comment starting the header line of the file at `path`
+fn header_comment(path: &Path) -> &'static str {
+    let batch = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("bat") || ext.eq_ignore_ascii_case("cmd"));
+    HEADER_COMMENTS[usize::from(batch)]
+}
+
+/// Header line for `content`: `# cargo-cross-generated <version> <hash>`
+fn header_line(comment: &str, content: &str) -> String {
+    format!(
+        "{comment} {HEADER_MARKER} {} {:016x}",
+        env!("CARGO_PKG_VERSION"),
+        fnv1a(content.as_bytes())
+    )
+}
+
+/// `content` of the file at `path` with the header inserted, after the shebang line if
+/// there is one
+#[must_use]
+pub fn with_header(path: &Path, content: &str) -> String {
+    let header = header_line(header_comment(path), content);
+    match content.strip_prefix("#!") {
+        Some(rest) => {
+            let (shebang, body) = rest.split_once('\n').unwrap_or((rest, ""));
+            format!("#!{shebang}\n{header}\n{body}")
+        }
+        None => format!("{header}\n{content}"),
+    }
+}
+
+/// Whether `existing` starts with a generated-file header (in its first two lines)
+#[must_use]
+pub fn is_generated(existing: &str) -> bool {
+    existing.lines().take(2).any(|line| {
+        HEADER_COMMENTS.iter().any(|comment| {
+            line.strip_prefix(comment)
+                .and_then(|rest| rest.strip_prefix(' '))
+                .is_some_and(|rest| rest.starts_with(&format!("{HEADER_MARKER} ")))
+        })
+    })
+}
+
+/// Whether the contents `existing` of the file at `path` are what [`with_header`]
+/// produces for `content`
+#[must_use]
+pub fn is_current(path: &Path, existing: &str, content: &str) -> bool {
+    existing == with_header(path, content)
+}
+
+/// Write `contents` to `path` through a temporary file in the same directory and a
+/// rename, so concurrent runs never see a partially written file
+pub fn atomic_write(path: &Path, contents: &[u8], executable: bool) -> Result<()> {
+    atomic_write_mode(path, contents, executable.then_some(0o755))
+}
+
+/// [`atomic_write`] with the file created with Unix permissions `mode` (ignored elsewhere),
+/// so a file only its owner may read is never readable by others, not even briefly
+pub fn atomic_write_mode(path: &Path, contents: &[u8], mode: Option<u32>) -> Result<()> {
+    let io_error = |source| CrossError::IoError {
+        message: format!("Failed to write {}", path.display()),
+        source,
+    };
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+    let written = write_new(&temp, contents, mode).and_then(|()| std::fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    written.map_err(io_error)
+}
+
+/// Create `path` with permissions `mode` on Unix and write `contents` to it
+fn write_new(path: &Path, contents: &[u8], mode: Option<u32>) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(mode);
+    }
+    let mut file = options.open(path)?;
+    // The umask may have taken bits away from `mode`
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(mode))?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    file.write_all(contents)
+}
+
+/// Write a generated file unless an up-to-date copy already exists.
+/// Returns whether the file was (re)written.
+pub fn write_generated(path: &Path, content: &str, executable: bool) -> Result<bool> {
+    if std::fs::read_to_string(path).is_ok_and(|existing| is_current(path, &existing, content)) {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|source| CrossError::IoError {
+            message: format!("Failed to create {}", parent.display()),
+            source,
+        })?;
+    }
+    atomic_write(path, with_header(path, content).as_bytes(), executable)?;
+    Ok(true)
+}
+
+/// Number of hex digits of the content hash in a generated file name
+const NAME_HASH_LEN: usize = 8;
+
+/// Hash naming the generated file for `content`. The version is included so a new
+/// template of an identical rendering never reuses a file written by another release.
+fn name_hash(content: &str) -> String {
+    let hash = fnv1a(format!("{}\n{content}", env!("CARGO_PKG_VERSION")).as_bytes());
+    format!("{hash:016x}")[..NAME_HASH_LEN].to_string()
+}
+
+/// Path of the generated file for `content`: `path_base` with a `-<hash>` suffix before
+/// the extension, e.g. `docker-qemu-runner-aarch64-musl-1a2b3c4d.sh`
+#[must_use]
+pub fn hashed_path(path_base: &Path, content: &str) -> PathBuf {
+    let stem = path_base.file_stem().unwrap_or_default().to_string_lossy();
+    let hash = name_hash(content);
+    let name = match path_base.extension() {
+        Some(ext) => format!("{stem}-{hash}.{}", ext.to_string_lossy()),
+        None => format!("{stem}-{hash}"),
+    };
+    path_base.with_file_name(name)
+}
+
+/// Whether the file name `name` ends with the hash suffix of [`hashed_path`]
+fn is_hashed_name(name: &str) -> bool {
+    let stem = Path::new(name)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    stem.rsplit_once('-').is_some_and(|(_, hash)| {
+        hash.len() == NAME_HASH_LEN && hash.bytes().all(|b| b.is_ascii_hexdigit())
+    })
+}
+
+/// Write the generated file for `content` under the hashed name of `path_base` and return
+/// its path. An existing up-to-date file is reused as is, so concurrent runs rendering the
+/// same content share it and runs rendering other content write other files.
+pub fn write_idempotent(path_base: &Path, content: &str, executable: bool) -> Result<PathBuf> {
+    let path = hashed_path(path_base, content);
+    write_generated(&path, content, executable)?;
+    Ok(path)
+}
+
+/// Files in `dir` (not recursive) that carry a generated-file header
+fn generated_in(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| read_head(path).is_some_and(|head| is_generated(&head)))
+        .collect()
+}
+
+/// Files in `dir` (not recursive) named like a generated file before names were hashed,
+/// with or without a header: a name starting with one of `prefixes` and ending with
+/// `suffix`, without a hash
+fn unhashed_in(dir: &Path, prefixes: &[&str], suffix: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            prefixes.iter().any(|prefix| name.starts_with(prefix))
+                && name.ends_with(suffix)
+                && !is_hashed_name(&name)
+        })
+        .collect()
+}
+
+/// First bytes of a file, enough for the header without reading downloaded archives whole
+fn read_head(path: &Path) -> Option<String> {
+    use std::io::Read;
+
+    let mut head = Vec::with_capacity(512);
+    std::fs::File::open(path)
+        .ok()?
+        .take(512)
+        .read_to_end(&mut head)
+        .ok()?;
+    Some(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// Every generated file below `root`: runner scripts in `generated/`, generic CMake
+/// toolchain files in `generated/cmake/`, the tool shim directories in `generated/shims/`
+/// and the Android CMake and clang wrappers inside NDK directories, including those
+/// written under unhashed names by older releases
+#[must_use]
+pub fn find_generated(root: &Path) -> Vec<PathBuf> {
+    let generated = layout::generated_dir(root);
+    let mut found = generated_in(&generated);
+    found.extend(unhashed_in(
+        &generated,
+        &["docker-qemu-runner-", "chroot-runner-"],
+        ".sh",
+    ));
+    found.extend(generated_in(&generated.join("cmake")));
+    found.extend(unhashed_in(&generated.join("cmake"), &[""], ".cmake"));
+    if let Ok(entries) = std::fs::read_dir(generated.join("shims")) {
+        found.extend(entries.filter_map(|entry| entry.ok().map(|entry| entry.path())));
+    }
+    for (_, dir) in layout::entries(root) {
+        let wrappers = dir.join("build").join("cmake").join("wrappers");
+        found.extend(generated_in(&wrappers));
+        found.extend(unhashed_in(&wrappers, &["android-"], ".cmake"));
+        found.extend(generated_in(&dir.join(CLANG_WRAPPER_DIR)));
+    }
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Remove every generated file below `root`, returning the removed paths
+pub fn clean_generated(root: &Path) -> Result<Vec<PathBuf>> {
+    let found = find_generated(root);
+    for path in &found {
+        let removed = if path.is_dir() {
+            std::fs::remove_dir_all(path)
+        } else {
+            std::fs::remove_file(path)
+        };
+        removed.map_err(|source| CrossError::IoError {
+            message: format!("Failed to remove {}", path.display()),
+            source,
+        })?;
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_and_staleness() {
+        let script_path = Path::new("runner.sh");
+        let script = "#!/bin/sh\nexec qemu-aarch64 \"$@\"\n";
+        let written = with_header(script_path, script);
+        assert!(written.starts_with("#!/bin/sh\n# cargo-cross-generated "));
+        assert!(written.ends_with("exec qemu-aarch64 \"$@\"\n"));
+        assert!(is_generated(&written));
+        assert!(is_current(script_path, &written, script));
+        // Other content, e.g. after switching --qemu-version, makes the file stale
+        assert!(!is_current(
+            script_path,
+            &written,
+            "#!/bin/sh\nexec qemu-aarch64-9 \"$@\"\n"
+        ));
+
+        let cmake_path = Path::new("android-arm64-v8a.cmake");
+        let cmake = "set(ANDROID_ABI \"arm64-v8a\")\n";
+        assert!(with_header(cmake_path, cmake).starts_with("# cargo-cross-generated "));
+        assert!(!is_generated(cmake));
+        // A file written before headers existed is stale
+        assert!(!is_current(cmake_path, cmake, cmake));
+
+        let batch = with_header(Path::new("clang.BAT"), "@echo off\r\n");
+        assert!(batch.starts_with("@REM cargo-cross-generated "));
+        assert!(is_generated(&batch));
+        assert!(!is_generated("@REM cargo-cross-generatedx\n"));
+    }
+
+    #[test]
+    fn test_write_generated_and_clean() {
+        let root =
+            std::env::temp_dir().join(format!("cargo-cross-generated-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let script = layout::generated_dir(&root).join("docker-qemu-runner-aarch64-musl.sh");
+        let wrapper = layout::sdk_dir(&root, "android-ndk-linux-r27d")
+            .join("build/cmake/wrappers/android-arm64-v8a.cmake");
+        let toolchain_file = root.join("toolchains.txt");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(&toolchain_file, "not generated").unwrap();
+
+        assert!(write_generated(&script, "#!/bin/sh\necho 1\n", true).unwrap());
+        assert!(!write_generated(&script, "#!/bin/sh\necho 1\n", true).unwrap());
+        assert!(write_generated(&script, "#!/bin/sh\necho 2\n", true).unwrap());
+        assert!(std::fs::read_to_string(&script)
+            .unwrap()
+            .ends_with("echo 2\n"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&script).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+        assert!(write_generated(&wrapper, "set(ANDROID_ABI \"arm64-v8a\")\n", false).unwrap());
+        let clang = layout::sdk_dir(&root, "android-ndk-linux-r27d")
+            .join(CLANG_WRAPPER_DIR)
+            .join("aarch64-linux-android24-clang.bat");
+        assert!(write_generated(&clang, "@echo off\n", false).unwrap());
+
+        // No temporary files are left behind
+        let leftovers: Vec<_> = std::fs::read_dir(layout::generated_dir(&root))
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+
+        assert_eq!(
+            find_generated(&root),
+            vec![script.clone(), wrapper.clone(), clang.clone()]
+        );
+        let removed = clean_generated(&root).unwrap();
+        assert_eq!(removed.len(), 3);
+        assert!(!script.exists() && !wrapper.exists() && !clang.exists());
+        assert!(toolchain_file.exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_hashed_path() {
+        let base = Path::new("/cache/generated/docker-qemu-runner-aarch64-musl.sh");
+        let one = hashed_path(base, "#!/bin/sh\necho 1\n");
+        let name = one.file_name().unwrap().to_string_lossy().into_owned();
+        assert_eq!(one.parent(), base.parent());
+        assert!(
+            name.starts_with("docker-qemu-runner-aarch64-musl-"),
+            "{name}"
+        );
+        assert!(name.ends_with(".sh"), "{name}");
+        assert_eq!(
+            name.len(),
+            "docker-qemu-runner-aarch64-musl-.sh".len() + NAME_HASH_LEN
+        );
+        assert!(is_hashed_name(&name));
+        // Deterministic, and other content gives another name
+        assert_eq!(hashed_path(base, "#!/bin/sh\necho 1\n"), one);
+        assert_ne!(hashed_path(base, "#!/bin/sh\necho 2\n"), one);
+
+        let cmake = hashed_path(Path::new("aarch64-unknown-linux-gnu.cmake"), "x");
+        assert!(is_hashed_name(&cmake.to_string_lossy()));
+        assert!(hashed_path(Path::new("wrapper"), "x")
+            .to_string_lossy()
+            .starts_with("wrapper-"));
+
+        assert!(!is_hashed_name("docker-qemu-runner-aarch64-musl.sh"));
+        assert!(!is_hashed_name("chroot-runner-x86_64-unknown-linux-gnu.sh"));
+        assert!(!is_hashed_name("android-arm64-v8a.cmake"));
+    }
+
+    #[test]
+    fn test_write_idempotent() {
+        let root =
+            std::env::temp_dir().join(format!("cargo-cross-idempotent-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let base = layout::generated_dir(&root).join("chroot-runner-x86_64-unknown-linux-gnu.sh");
+
+        let first = write_idempotent(&base, "#!/bin/sh\necho 1\n", true).unwrap();
+        let modified = std::fs::metadata(&first).unwrap().modified().unwrap();
+        // Same content: same file, left untouched
+        assert_eq!(
+            write_idempotent(&base, "#!/bin/sh\necho 1\n", true).unwrap(),
+            first
+        );
+        assert_eq!(
+            std::fs::metadata(&first).unwrap().modified().unwrap(),
+            modified
+        );
+        // Other content: another file, the first one stays intact
+        let second = write_idempotent(&base, "#!/bin/sh\necho 2\n", true).unwrap();
+        assert_ne!(second, first);
+        assert!(std::fs::read_to_string(&first)
+            .unwrap()
+            .ends_with("echo 1\n"));
+        assert!(std::fs::read_to_string(&second)
+            .unwrap()
+            .ends_with("echo 2\n"));
+        assert!(!base.exists());
+
+        // Files written under unhashed names by older releases are cleaned too, with or
+        // without a header; unrelated files are kept
+        std::fs::write(&base, "#!/bin/sh\n").unwrap();
+        let legacy_cmake =
+            layout::generated_dir(&root).join("cmake/aarch64-unknown-linux-gnu.cmake");
+        std::fs::create_dir_all(legacy_cmake.parent().unwrap()).unwrap();
+        std::fs::write(&legacy_cmake, "set(CMAKE_SYSTEM_NAME Linux)\n").unwrap();
+        let unrelated = layout::generated_dir(&root).join("notes.txt");
+        std::fs::write(&unrelated, "keep").unwrap();
+        let shims = layout::generated_dir(&root).join("shims/aarch64-unknown-linux-musl-0a1b2c3d");
+        std::fs::create_dir_all(&shims).unwrap();
+        std::fs::write(shims.join("gcc.cmd"), "@\"gcc\" %*\r\n").unwrap();
+        let mut expected = vec![base, first, second, legacy_cmake, shims.clone()];
+        expected.sort();
+        assert_eq!(find_generated(&root), expected);
+        assert_eq!(clean_generated(&root).unwrap().len(), 5);
+        assert!(unrelated.exists() && !shims.exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_atomic_write_replaces_existing_file() {
+        let dir = std::env::temp_dir().join(format!("cargo-cross-atomic-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.cmake");
+        std::fs::write(&path, "old").unwrap();
+        atomic_write(&path, b"new", false).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert!(atomic_write(&dir.join("missing/file"), b"x", false).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
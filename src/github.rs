@@ -0,0 +1,255 @@
+//! Exporting the cross environment to later GitHub Actions steps
+
+use crate::error::{CrossError, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Delimiter for multiline `GITHUB_ENV` values
+const DELIMITER: &str = "__CARGO_CROSS_EOF__";
+
+/// Format one `GITHUB_ENV` entry: `KEY=VALUE`, or the `KEY<<DELIMITER` form for multiline values
+#[must_use]
+pub fn github_env_entry(key: &str, value: &str) -> String {
+    if !value.contains(['\n', '\r']) {
+        return format!("{key}={value}\n");
+    }
+    let mut delimiter = DELIMITER.to_string();
+    while value.lines().any(|line| line == delimiter) {
+        delimiter.push('_');
+    }
+    format!("{key}<<{delimiter}\n{value}\n{delimiter}\n")
+}
+
+/// Values a `GITHUB_ENV` file sets, the last assignment of each name winning
+#[must_use]
+pub fn parse_github_env(contents: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let mut lines = contents.lines();
+    while let Some(line) = lines.next() {
+        // `NAME<<DELIMITER` starts a multiline value; a `=` before `<<` means a plain entry
+        let heredoc = line
+            .split_once("<<")
+            .filter(|(name, _)| !name.contains('='));
+        if let Some((name, delimiter)) = heredoc {
+            let value: Vec<&str> = lines.by_ref().take_while(|l| *l != delimiter).collect();
+            values.insert(name.to_string(), value.join("\n"));
+        } else if let Some((name, value)) = line.split_once('=') {
+            values.insert(name.to_string(), value.to_string());
+        }
+    }
+    values
+}
+
+/// What [`write_github_env`] appended; both empty when the files were already current
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GithubExport {
+    /// Names of the variables written to `GITHUB_ENV`
+    pub variables: Vec<String>,
+    /// Directories written to `GITHUB_PATH`
+    pub paths: Vec<PathBuf>,
+}
+
+/// Render the `GITHUB_ENV` contents for `env`, sorted by name.
+/// `PATH` is left out; its additions go to `GITHUB_PATH` instead.
+#[must_use]
+pub fn render_github_env(env: &HashMap<String, String>) -> String {
+    env.iter()
+        .filter(|(key, _)| key.as_str() != "PATH")
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(key, value)| github_env_entry(key, value))
+        .collect()
+}
+
+/// Render the `GITHUB_PATH` contents for the directories cargo-cross prepends to `PATH`.
+/// Each line is prepended by the runner, so they are written in reverse to keep the order.
+#[must_use]
+pub fn render_github_path(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .rev()
+        .map(|path| format!("{}\n", path.display()))
+        .collect()
+}
+
+fn append(file: &Path, contents: &str) -> Result<()> {
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file)?
+        .write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+/// Append `env` to the `GITHUB_ENV` file and `paths` to the `GITHUB_PATH` file, skipping
+/// variables and directories that are already in effect, so running the same export again
+/// adds nothing. The runner gives every step fresh files and passes what earlier steps
+/// exported as the process environment, so `inherited` (that environment) counts as well.
+pub fn write_github_env(
+    env_file: &Path,
+    path_file: Option<&Path>,
+    env: &HashMap<String, String>,
+    paths: &[PathBuf],
+    inherited: &HashMap<String, String>,
+) -> Result<GithubExport> {
+    let current = parse_github_env(&std::fs::read_to_string(env_file).unwrap_or_default());
+    let changed: HashMap<String, String> = env
+        .iter()
+        .filter(|(key, value)| {
+            current
+                .get(*key)
+                .or_else(|| inherited.get(*key))
+                .is_none_or(|existing| existing != *value)
+        })
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    let rendered = render_github_env(&changed);
+    if !rendered.is_empty() {
+        append(env_file, &rendered)?;
+    }
+    let mut export = GithubExport {
+        variables: changed.into_keys().filter(|key| key != "PATH").collect(),
+        paths: Vec::new(),
+    };
+    export.variables.sort_unstable();
+
+    if let Some(path_file) = path_file {
+        let listed = std::fs::read_to_string(path_file).unwrap_or_default();
+        let search_path = inherited.get("PATH").map_or("", String::as_str);
+        export.paths = paths
+            .iter()
+            .filter(|path| {
+                !listed.lines().any(|line| Path::new(line) == path.as_path())
+                    && !std::env::split_paths(search_path).any(|dir| dir == **path)
+            })
+            .cloned()
+            .collect();
+        if !export.paths.is_empty() {
+            append(path_file, &render_github_path(&export.paths))?;
+        }
+    }
+    Ok(export)
+}
+
+/// Export `env` to the files named by `GITHUB_ENV` and `GITHUB_PATH`
+pub fn export_github_env(env: &HashMap<String, String>, paths: &[PathBuf]) -> Result<GithubExport> {
+    let env_file = std::env::var_os("GITHUB_ENV").ok_or_else(|| {
+        CrossError::InvalidArgument(
+            "--export-github-env requires GITHUB_ENV to be set (run inside GitHub Actions)"
+                .to_string(),
+        )
+    })?;
+    let path_file = std::env::var_os("GITHUB_PATH");
+    write_github_env(
+        Path::new(&env_file),
+        path_file.as_deref().map(Path::new),
+        env,
+        paths,
+        &std::env::vars().collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_env_entry() {
+        assert_eq!(github_env_entry("CC", "clang"), "CC=clang\n");
+        assert_eq!(
+            github_env_entry("FLAGS", "a\nb"),
+            "FLAGS<<__CARGO_CROSS_EOF__\na\nb\n__CARGO_CROSS_EOF__\n"
+        );
+        // The delimiter never appears as a line of the value
+        assert_eq!(
+            github_env_entry("X", "__CARGO_CROSS_EOF__\n1"),
+            "X<<__CARGO_CROSS_EOF___\n__CARGO_CROSS_EOF__\n1\n__CARGO_CROSS_EOF___\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_github_env() {
+        let values = parse_github_env("A=1\nB<<EOF\nx=y\nz\nEOF\nC=a<<b\nA=2\n");
+        assert_eq!(values["A"], "2");
+        assert_eq!(values["B"], "x=y\nz");
+        assert_eq!(values["C"], "a<<b");
+        assert_eq!(values.len(), 3);
+    }
+
+    #[test]
+    fn test_write_github_env_files() {
+        let dir = std::env::temp_dir().join(format!("cargo-cross-github-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let env_file = dir.join("env");
+        let path_file = dir.join("path");
+        std::fs::write(&env_file, "EXISTING=1\n").unwrap();
+
+        let env = HashMap::from([
+            (
+                "CC_aarch64_unknown_linux_musl".to_string(),
+                "aarch64-linux-musl-gcc".to_string(),
+            ),
+            ("PATH".to_string(), "/tc/bin:/qemu:/usr/bin".to_string()),
+            ("NOTE".to_string(), "line 1\nline 2".to_string()),
+        ]);
+        let paths = [PathBuf::from("/tc/bin"), PathBuf::from("/qemu")];
+        let inherited = HashMap::new();
+        write_github_env(&env_file, Some(&path_file), &env, &paths, &inherited).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&env_file).unwrap(),
+            "EXISTING=1\n\
+             CC_aarch64_unknown_linux_musl=aarch64-linux-musl-gcc\n\
+             NOTE<<__CARGO_CROSS_EOF__\nline 1\nline 2\n__CARGO_CROSS_EOF__\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&path_file).unwrap(),
+            "/qemu\n/tc/bin\n"
+        );
+
+        // Exporting the same environment again appends nothing
+        let again =
+            write_github_env(&env_file, Some(&path_file), &env, &paths, &inherited).unwrap();
+        assert_eq!(again, GithubExport::default());
+        assert_eq!(
+            parse_github_env(&std::fs::read_to_string(&env_file).unwrap()).len(),
+            3
+        );
+
+        // A changed value is written again and wins
+        let env = HashMap::from([("EXISTING".to_string(), "2".to_string())]);
+        let changed =
+            write_github_env(&env_file, Some(&path_file), &env, &paths, &inherited).unwrap();
+        assert_eq!(changed.variables, ["EXISTING"]);
+        assert!(changed.paths.is_empty());
+        assert_eq!(
+            parse_github_env(&std::fs::read_to_string(&env_file).unwrap())["EXISTING"],
+            "2"
+        );
+
+        // A later step starts with empty files but inherits what earlier steps exported
+        let (next_env, next_path) = (dir.join("env2"), dir.join("path2"));
+        let inherited = HashMap::from([
+            (
+                "CC_aarch64_unknown_linux_musl".to_string(),
+                "aarch64-linux-musl-gcc".to_string(),
+            ),
+            ("NOTE".to_string(), "old".to_string()),
+            ("PATH".to_string(), "/qemu:/usr/bin".to_string()),
+        ]);
+        let env = HashMap::from([
+            (
+                "CC_aarch64_unknown_linux_musl".to_string(),
+                "aarch64-linux-musl-gcc".to_string(),
+            ),
+            ("NOTE".to_string(), "new".to_string()),
+        ]);
+        let next = write_github_env(&next_env, Some(&next_path), &env, &paths, &inherited).unwrap();
+        assert_eq!(next.variables, ["NOTE"]);
+        assert_eq!(next.paths, [PathBuf::from("/tc/bin")]);
+        assert_eq!(std::fs::read_to_string(&next_path).unwrap(), "/tc/bin\n");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,323 @@
+//! Install names of cross-built Apple dylibs
+//!
+//! ld64 records a dylib's output path as its install name unless `-install_name` is given,
+//! so a cdylib linked by osxcross carries the Linux build path and anything linking it on
+//! macOS looks for the library there. After a build, each cdylib gets `@rpath/<file>` as
+//! its id and the `--rpath` entries, using the toolchain's `install_name_tool`.
+//!
+//! Rewriting load commands invalidates the ad-hoc signature ld64 gives arm64 binaries, and
+//! arm64 macOS refuses to load an unsigned dylib, so those are signed again afterwards.
+
+use crate::artifacts::{self, ArtifactKind};
+use crate::cargo::build_cargo_env;
+use crate::cli::Args;
+use crate::color;
+use crate::config::HostPlatform;
+use crate::debuginfo::find_tool;
+use crate::env::CrossEnv;
+use crate::error::{run_command_output, CrossError, Result};
+use std::path::{Path, PathBuf};
+use tokio::process::Command as TokioCommand;
+
+/// Whether install names are fixed for `target`
+#[must_use]
+pub fn applies(target: &str, args: &Args) -> bool {
+    args.fix_install_name.unwrap_or(true)
+        && (target.contains("-apple-darwin") || target.contains("-apple-ios"))
+}
+
+/// Install name a dylib gets: `@rpath/<file name>`
+#[must_use]
+pub fn install_name(dylib: &Path) -> String {
+    format!(
+        "@rpath/{}",
+        dylib.file_name().unwrap_or_default().to_string_lossy()
+    )
+}
+
+/// `install_name_tool` to use: the native one on macOS, otherwise the toolchain's
+/// `<prefix>-install_name_tool` next to its clang
+#[must_use]
+pub fn install_name_tool(cross_env: &CrossEnv, host: &HostPlatform) -> PathBuf {
+    let name = cross_env
+        .cc
+        .as_deref()
+        .filter(|_| !host.is_darwin())
+        .and_then(|cc| {
+            cc.strip_suffix(host.exe_ext())
+                .unwrap_or(cc)
+                .strip_suffix("-clang")
+        })
+        .map_or_else(
+            || format!("install_name_tool{}", host.exe_ext()),
+            |prefix| format!("{prefix}-install_name_tool{}", host.exe_ext()),
+        );
+    cross_env
+        .path
+        .iter()
+        .map(|dir| dir.join(&name))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+/// Arguments setting the id of a dylib
+#[must_use]
+pub fn id_args(dylib: &Path) -> Vec<String> {
+    vec![
+        "-id".to_string(),
+        install_name(dylib),
+        dylib.display().to_string(),
+    ]
+}
+
+/// Whether `target`'s dylibs must carry a valid signature to load
+#[must_use]
+pub fn needs_signature(target: &str) -> bool {
+    target.starts_with("aarch64-") || target.starts_with("arm64")
+}
+
+/// Tool that ad-hoc signs a dylib: `codesign` on macOS, otherwise `rcodesign` or `ldid`
+#[must_use]
+pub fn signer(cross_env: &CrossEnv, host: &HostPlatform) -> Option<PathBuf> {
+    let names: Vec<String> = host
+        .is_darwin()
+        .then_some("codesign")
+        .into_iter()
+        .chain(["rcodesign", "ldid"])
+        .map(|name| format!("{name}{}", host.exe_ext()))
+        .collect();
+    find_tool(&names, cross_env)
+}
+
+/// Arguments ad-hoc signing `dylib` with `signer`
+#[must_use]
+pub fn sign_args(signer: &Path, dylib: &Path) -> Vec<String> {
+    let name = signer
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let dylib = dylib.display().to_string();
+    match name.as_str() {
+        "rcodesign" => vec!["sign".to_string(), dylib],
+        "ldid" => vec!["-S".to_string(), dylib],
+        _ => vec![
+            "--force".to_string(),
+            "--sign".to_string(),
+            "-".to_string(),
+            dylib,
+        ],
+    }
+}
+
+/// Arguments adding an rpath entry to a dylib
+#[must_use]
+pub fn add_rpath_args(dylib: &Path, rpath: &str) -> Vec<String> {
+    vec![
+        "-add_rpath".to_string(),
+        rpath.to_string(),
+        dylib.display().to_string(),
+    ]
+}
+
+/// Run `install_name_tool`; `tolerate` lists stderr messages that mean there is nothing to do
+async fn run_tool(
+    tool: &Path,
+    args: &[String],
+    env: &[(String, String)],
+    dylib: &Path,
+    tolerate: &[&str],
+) -> Result<()> {
+    let mut cmd = TokioCommand::new(tool);
+    cmd.args(args).envs(env.iter().map(|(k, v)| (k, v)));
+    let program = tool.display().to_string();
+    let output = run_command_output(&mut cmd, &program).await?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() || tolerate.iter().any(|message| stderr.contains(message)) {
+        return Ok(());
+    }
+    Err(CrossError::InstallNameFailed {
+        dylib: dylib.to_path_buf(),
+        command: std::iter::once(program)
+            .chain(args.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(" "),
+        stderr: stderr.trim().to_string(),
+    })
+}
+
+/// Give the cdylibs a build of `target` produced an `@rpath` install name and the `--rpath`
+/// entries, and return the dylibs that were fixed. A dylib that cannot be fixed is an
+/// error: leaving a broken install name would only fail later, on the machine that links it.
+pub async fn fix_install_names(
+    target: &str,
+    args: &Args,
+    cross_env: &CrossEnv,
+    host: &HostPlatform,
+    skip_target_arg: bool,
+) -> Result<Vec<PathBuf>> {
+    let metadata = match crate::metadata::cargo_metadata(args).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            color::log_debug(&format!("Skipping install name fixes: {e}"));
+            return Ok(Vec::new());
+        }
+    };
+    let dir = artifacts::output_dir(&metadata, target, args, skip_target_arg);
+    let dylibs: Vec<PathBuf> = artifacts::expected_artifacts(&metadata, args, target)
+        .artifacts
+        .iter()
+        .filter(|artifact| artifact.kind == ArtifactKind::Cdylib)
+        .map(|artifact| dir.join(&artifact.path))
+        .filter(|path| path.is_file())
+        .collect();
+    if dylibs.is_empty() {
+        return Ok(dylibs);
+    }
+
+    let tool = install_name_tool(cross_env, host);
+    let env: Vec<(String, String)> =
+        build_cargo_env(target, args, cross_env, host, skip_target_arg)?
+            .into_vars()
+            .into_iter()
+            .collect();
+    let signer = if needs_signature(target) {
+        let signer = signer(cross_env, host);
+        if signer.is_none() {
+            color::log_warning_once(
+                "codesign-missing",
+                &format!(
+                    "No codesign, rcodesign or ldid found: the cdylibs of {} lose their ad-hoc \
+                     signature and arm64 macOS will not load them until they are signed again",
+                    color::yellow(target)
+                ),
+            );
+        }
+        signer
+    } else {
+        None
+    };
+    for dylib in &dylibs {
+        run_tool(&tool, &id_args(dylib), &env, dylib, &[]).await?;
+        for rpath in &args.rpath {
+            // Rebuilding without relinking leaves the rpath from the previous run in place
+            run_tool(
+                &tool,
+                &add_rpath_args(dylib, rpath),
+                &env,
+                dylib,
+                &["would duplicate path"],
+            )
+            .await?;
+        }
+        if let Some(ref signer) = signer {
+            run_tool(signer, &sign_args(signer, dylib), &env, dylib, &[]).await?;
+        }
+        color::log_success(&format!(
+            "Set install name of {} to {}{}",
+            color::cyan(&dylib.display().to_string()),
+            install_name(dylib),
+            if args.rpath.is_empty() {
+                String::new()
+            } else {
+                format!(" (rpath {})", args.rpath.join(", "))
+            }
+        ));
+    }
+    Ok(dylibs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_name_command_lines() {
+        let dylib = Path::new("/ws/target/aarch64-apple-darwin/release/libacme_ffi.dylib");
+        assert_eq!(install_name(dylib), "@rpath/libacme_ffi.dylib");
+        assert_eq!(
+            id_args(dylib),
+            [
+                "-id",
+                "@rpath/libacme_ffi.dylib",
+                "/ws/target/aarch64-apple-darwin/release/libacme_ffi.dylib"
+            ]
+        );
+        assert_eq!(
+            add_rpath_args(dylib, "@loader_path/../lib"),
+            [
+                "-add_rpath",
+                "@loader_path/../lib",
+                "/ws/target/aarch64-apple-darwin/release/libacme_ffi.dylib"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resigning_arm64_dylibs() {
+        assert!(needs_signature("aarch64-apple-darwin"));
+        assert!(needs_signature("arm64e-apple-darwin"));
+        assert!(!needs_signature("x86_64-apple-darwin"));
+
+        let dylib = Path::new("libacme_ffi.dylib");
+        assert_eq!(
+            sign_args(Path::new("/usr/bin/codesign"), dylib),
+            ["--force", "--sign", "-", "libacme_ffi.dylib"]
+        );
+        assert_eq!(
+            sign_args(Path::new("/opt/bin/rcodesign"), dylib),
+            ["sign", "libacme_ffi.dylib"]
+        );
+        assert_eq!(
+            sign_args(Path::new("ldid.exe"), dylib),
+            ["-S", "libacme_ffi.dylib"]
+        );
+
+        let bin = std::env::temp_dir().join(format!("cargo-cross-sign-{}", std::process::id()));
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(bin.join("ldid"), "").unwrap();
+        let mut env = CrossEnv::new();
+        env.add_path(&bin);
+        let linux = HostPlatform {
+            os: "linux",
+            ..HostPlatform::detect()
+        };
+        assert_eq!(signer(&env, &linux), Some(bin.join("ldid")));
+        std::fs::write(bin.join("rcodesign"), "").unwrap();
+        assert_eq!(signer(&env, &linux), Some(bin.join("rcodesign")));
+        let _ = std::fs::remove_dir_all(&bin);
+    }
+
+    #[test]
+    fn test_install_name_tool_selection() {
+        let linux = HostPlatform {
+            os: "linux",
+            ..HostPlatform::detect()
+        };
+        let mut env = CrossEnv::new();
+        env.set_cc("aarch64-apple-darwin25.2-clang");
+        assert_eq!(
+            install_name_tool(&env, &linux),
+            PathBuf::from("aarch64-apple-darwin25.2-install_name_tool")
+        );
+
+        let bin = std::env::temp_dir().join(format!("cargo-cross-int-{}", std::process::id()));
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(bin.join("aarch64-apple-darwin25.2-install_name_tool"), "").unwrap();
+        env.add_path(&bin);
+        assert_eq!(
+            install_name_tool(&env, &linux),
+            bin.join("aarch64-apple-darwin25.2-install_name_tool")
+        );
+        let _ = std::fs::remove_dir_all(&bin);
+
+        let macos = HostPlatform {
+            os: "darwin",
+            ..HostPlatform::detect()
+        };
+        assert_eq!(
+            install_name_tool(&env, &macos),
+            PathBuf::from("install_name_tool")
+        );
+    }
+}
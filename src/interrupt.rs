@@ -0,0 +1,206 @@
+//! Interrupt handling: forward SIGINT/SIGTERM to child processes and clean up before exiting
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Child processes spawned through `run_command` and friends that are still running:
+/// pid, and whether the child leads its own process group
+static ACTIVE_CHILDREN: Mutex<Vec<(u32, bool)>> = Mutex::new(Vec::new());
+
+/// Set once an interrupt was received; no new child processes are started afterwards
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Set when children stay in cargo-cross's process group, see [`share_terminal`]
+static SHARE_TERMINAL: AtomicBool = AtomicBool::new(false);
+
+/// How long children get to exit after the forwarded signal before they are killed
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+
+/// Exit code for an interrupt (128 + SIGINT)
+pub const EXIT_SIGINT: i32 = 130;
+
+/// Exit code for a termination request (128 + SIGTERM)
+pub const EXIT_SIGTERM: i32 = 143;
+
+fn lock_children() -> std::sync::MutexGuard<'static, Vec<(u32, bool)>> {
+    ACTIVE_CHILDREN
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Keep children in cargo-cross's process group instead of giving each its own. Meant for
+/// programs that read the terminal (`run` and `exec` with a terminal on stdin), which are
+/// stopped by SIGTTIN when they read it from a background group; Ctrl+C then reaches the
+/// whole foreground group, children included.
+pub fn share_terminal(share: bool) {
+    SHARE_TERMINAL.store(share, Ordering::SeqCst);
+}
+
+/// Whether the next child gets a process group of its own, so signals forwarded to it also
+/// reach the processes it starts (rustc, build scripts, linkers, runners)
+#[must_use]
+pub fn own_process_group() -> bool {
+    cfg!(unix) && !SHARE_TERMINAL.load(Ordering::SeqCst)
+}
+
+/// Keeps a child registered for signal forwarding until dropped
+#[derive(Debug)]
+pub struct ChildGuard(Option<u32>);
+
+impl ChildGuard {
+    /// Register a spawned child by pid (`None` if it already exited); `group` if it leads
+    /// its own process group
+    #[must_use]
+    pub fn register(pid: Option<u32>, group: bool) -> Self {
+        if let Some(pid) = pid {
+            lock_children().push((pid, group));
+        }
+        Self(pid)
+    }
+}
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            lock_children().retain(|&(p, _)| p != pid);
+        }
+    }
+}
+
+/// Pids of children that are still running
+#[must_use]
+pub fn active_children() -> Vec<u32> {
+    lock_children().iter().map(|&(pid, _)| pid).collect()
+}
+
+/// Whether an interrupt was received
+#[must_use]
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Mark the process as interrupted so no further children are spawned
+pub fn set_interrupted() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Send `signal` to a child, or to its whole process group if it leads one
+#[cfg(unix)]
+fn send_signal(pid: u32, group: bool, signal: nix::sys::signal::Signal) {
+    if let Ok(pid) = i32::try_from(pid) {
+        let pid = nix::unistd::Pid::from_raw(pid);
+        let _ = if group {
+            nix::sys::signal::killpg(pid, signal)
+        } else {
+            nix::sys::signal::kill(pid, signal)
+        };
+    }
+}
+
+/// Forward `signal` to every running child and its process group, wait up to `grace` for
+/// them to exit, then kill whatever is left. Groups are killed even when their leader
+/// already exited, so no rustc, build script or runner outlives cargo-cross.
+#[cfg(unix)]
+pub async fn terminate_children(signal: nix::sys::signal::Signal, grace: Duration) {
+    let children = lock_children().clone();
+    for &(pid, group) in &children {
+        send_signal(pid, group, signal);
+    }
+
+    let deadline = std::time::Instant::now() + grace;
+    while !active_children().is_empty() && std::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let remaining = lock_children().clone();
+    for (pid, group) in children
+        .into_iter()
+        .filter(|&(_, group)| group)
+        .chain(remaining)
+    {
+        send_signal(pid, group, nix::sys::signal::Signal::SIGKILL);
+    }
+}
+
+/// Stop children, remove shared runner containers and partial downloads after an interrupt;
+/// returns the exit code
+pub async fn shutdown(exit_code: i32) -> i32 {
+    set_interrupted();
+
+    #[cfg(unix)]
+    {
+        let signal = if exit_code == EXIT_SIGTERM {
+            nix::sys::signal::Signal::SIGTERM
+        } else {
+            nix::sys::signal::Signal::SIGINT
+        };
+        terminate_children(signal, SHUTDOWN_GRACE).await;
+    }
+
+    crate::runner::remove_open_sessions().await;
+    crate::download::cleanup_temp_paths();
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_child_guard_registers_until_dropped() {
+        let guard = ChildGuard::register(Some(u32::MAX - 1), true);
+        assert!(active_children().contains(&(u32::MAX - 1)));
+        drop(guard);
+        assert!(!active_children().contains(&(u32::MAX - 1)));
+
+        let _none = ChildGuard::register(None, false);
+    }
+
+    /// Whether `pid` has exited; zombies count, as nothing may reap them in a container
+    #[cfg(target_os = "linux")]
+    fn exited(pid: &str) -> bool {
+        std::fs::read_to_string(format!("/proc/{pid}/stat")).map_or(true, |stat| {
+            stat.rsplit_once(')')
+                .is_some_and(|(_, rest)| rest.trim_start().starts_with('Z'))
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_kill_reaches_grandchildren() {
+        let pid_file =
+            std::env::temp_dir().join(format!("cargo-cross-grandchild-{}", std::process::id()));
+        let _ = std::fs::remove_file(&pid_file);
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c").arg(format!(
+            "sleep 30 & echo $! > {}.tmp; mv {0}.tmp {0}; wait",
+            pid_file.display()
+        ));
+        let (mut child, guard) = crate::error::spawn_tracked(&mut cmd, "sh").unwrap();
+        let grandchild = loop {
+            if let Ok(pid) = std::fs::read_to_string(&pid_file) {
+                break pid.trim().to_string();
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        };
+        assert!(!exited(&grandchild));
+
+        // What the SIGKILL escalation sends: the sleep goes down with the shell
+        send_signal(
+            child.id().unwrap(),
+            own_process_group(),
+            nix::sys::signal::Signal::SIGKILL,
+        );
+        child.wait().await.unwrap();
+        drop(guard);
+        for _ in 0..100 {
+            if exited(&grandchild) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(exited(&grandchild), "grandchild {grandchild} survived");
+        let _ = std::fs::remove_file(&pid_file);
+    }
+}
@@ -0,0 +1,401 @@
+//! Directory layout of the cross compiler directory
+//!
+//! Everything cargo-cross downloads or writes lives in one of a few subdirectories, so CI
+//! can cache a single toolchain instead of the whole directory:
+//!
+//! ```text
+//! toolchains/<name>/        cross-make GCC toolchains, e.g. aarch64-linux-musl-cross-v0.7.7
+//! sdks/<name>/              Android NDK, osxcross, iOS toolchains and libclang
+//! runners/qemu/<version>/   qemu-user-static bundles
+//! generated/                runner scripts, CMake toolchain files and smoke test markers
+//! ```
+//!
+//! Releases up to layout 1 put everything directly in the cross compiler directory;
+//! [`migrate`] moves such entries into place.
+
+use crate::cache::split_cross_make_dir;
+use crate::error::{CrossError, Result};
+use std::path::{Path, PathBuf};
+
+/// Version of the directory layout and toolchain folder naming scheme.
+/// Bump whenever either changes between releases.
+pub const LAYOUT_VERSION: u32 = 2;
+
+/// Cross-make GCC toolchains
+pub const TOOLCHAINS_DIR: &str = "toolchains";
+
+/// Android NDK, osxcross, iOS toolchains and libclang
+pub const SDKS_DIR: &str = "sdks";
+
+/// Runners downloaded or generated per target
+pub const RUNNERS_DIR: &str = "runners";
+
+/// qemu-user-static bundles, below [`RUNNERS_DIR`]
+pub const QEMU_SUBDIR: &str = "qemu";
+
+/// Cache entry name of the qemu-user-static directory; entry names always use `/`
+pub const QEMU_DIR: &str = "runners/qemu";
+
+/// Files cargo-cross writes itself: runner scripts, CMake toolchain files, smoke test markers
+pub const GENERATED_DIR: &str = "generated";
+
+/// Directories whose subdirectories are cache entries
+pub const ENTRY_DIRS: &[&str] = &[TOOLCHAINS_DIR, SDKS_DIR, QEMU_DIR];
+
+/// Directory of the cross-make toolchain `name`
+#[must_use]
+pub fn toolchain_dir(root: &Path, name: &str) -> PathBuf {
+    root.join(TOOLCHAINS_DIR).join(name)
+}
+
+/// Directory of the SDK `name` (NDK, osxcross, iOS toolchain)
+#[must_use]
+pub fn sdk_dir(root: &Path, name: &str) -> PathBuf {
+    root.join(SDKS_DIR).join(name)
+}
+
+/// Directory of the qemu-user-static bundle `version`, e.g. `v10.0.2-linux-amd64`
+#[must_use]
+pub fn qemu_dir(root: &Path, version: &str) -> PathBuf {
+    root.join(RUNNERS_DIR).join(QEMU_SUBDIR).join(version)
+}
+
+/// Path of the entry directory `parent` (one of [`ENTRY_DIRS`]) below `root`
+#[must_use]
+pub fn entry_dir(root: &Path, parent: &str) -> PathBuf {
+    parent
+        .split('/')
+        .fold(root.to_path_buf(), |dir, component| dir.join(component))
+}
+
+/// Directory of generated files
+#[must_use]
+pub fn generated_dir(root: &Path) -> PathBuf {
+    root.join(GENERATED_DIR)
+}
+
+/// Cache entry name of `name` inside `parent`, e.g. `toolchains/aarch64-linux-musl-cross-v0.7.7`
+#[must_use]
+pub fn entry_name(parent: &str, name: &str) -> String {
+    format!("{parent}/{name}")
+}
+
+/// Cache entry containing `path`, e.g. `toolchains/aarch64-linux-musl-cross-v0.7.7` for a
+/// compiler below it, or the top-level name for paths outside the entry directories
+#[must_use]
+pub fn entry_of(root: &Path, path: &Path) -> Option<String> {
+    let components: Vec<String> = path
+        .strip_prefix(root)
+        .ok()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    for parent in ENTRY_DIRS {
+        let depth = parent.split('/').count();
+        if components.len() > depth && components[..depth].join("/") == *parent {
+            return Some(entry_name(parent, &components[depth]));
+        }
+    }
+    components.into_iter().next()
+}
+
+/// Cache entries below `root` as entry name and path, including adopted (symlinked)
+/// toolchains, sorted by name
+#[must_use]
+pub fn entries(root: &Path) -> Vec<(String, PathBuf)> {
+    let mut found: Vec<(String, PathBuf)> = ENTRY_DIRS
+        .iter()
+        .flat_map(|parent| {
+            std::fs::read_dir(entry_dir(root, parent))
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter(|entry| entry.path().is_dir())
+                .map(move |entry| {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    (entry_name(parent, &name), entry.path())
+                })
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+/// Where a top-level entry written by a layout 1 release belongs, relative to the cross
+/// compiler directory. Unknown entries give `None` and stay where they are.
+#[must_use]
+pub fn legacy_destination(name: &str, is_dir: bool) -> Option<String> {
+    if name == "cmake" || name == ".smoke-tests" {
+        return is_dir.then(|| entry_name(GENERATED_DIR, name));
+    }
+    if name.starts_with("docker-qemu-runner-") {
+        return (!is_dir).then(|| entry_name(GENERATED_DIR, name));
+    }
+    if !is_dir {
+        return None;
+    }
+    if let Some(version) = name.strip_prefix("qemu-user-static-") {
+        return Some(entry_name(QEMU_DIR, version));
+    }
+    if ["android-ndk-", "osxcross-", "ios-"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+    {
+        return Some(entry_name(SDKS_DIR, name));
+    }
+    (split_cross_make_dir(name).is_some() || name.ends_with("-cross"))
+        .then(|| entry_name(TOOLCHAINS_DIR, name))
+}
+
+/// Whether the contents of `path` match what cargo-cross writes for `dest`, so a
+/// directory that merely shares a name (a user's `cmake` or `foo-cross`) stays put
+fn is_cargo_cross_entry(path: &Path, dest: &str) -> bool {
+    let names = |dir: &Path| -> Vec<String> {
+        std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect()
+    };
+    let Some((parent, name)) = dest.rsplit_once('/') else {
+        return false;
+    };
+    match parent {
+        GENERATED_DIR if name == "cmake" => {
+            let files = names(path);
+            !files.is_empty()
+                && files.iter().all(|file| {
+                    Path::new(file)
+                        .extension()
+                        .is_some_and(|ext| ext == "cmake")
+                })
+        }
+        QEMU_DIR => names(path).iter().any(|file| file.starts_with("qemu-")),
+        SDKS_DIR if name.starts_with("android-ndk-") => path.join("source.properties").is_file(),
+        SDKS_DIR => path.join("bin").is_dir(),
+        TOOLCHAINS_DIR => names(&path.join("bin"))
+            .iter()
+            .any(|file| file.ends_with("-gcc") || file.ends_with("-gcc.exe")),
+        _ => true,
+    }
+}
+
+/// Move the top-level entries of a layout 1 cross compiler directory into their
+/// subdirectories. Entries whose destination already exists, or whose contents do not
+/// look like what cargo-cross wrote there, are left in place.
+/// Returns the moved entries as old and new name.
+pub fn migrate(root: &Path) -> Result<Vec<(String, String)>> {
+    let Ok(read_dir) = std::fs::read_dir(root) else {
+        return Ok(Vec::new());
+    };
+    let mut legacy: Vec<(String, String)> = read_dir
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Adopted toolchains are relative symlinks to a sibling and move along with it
+            let is_dir = entry.path().is_dir();
+            legacy_destination(&name, is_dir)
+                .filter(|dest| is_cargo_cross_entry(&entry.path(), dest))
+                .map(|dest| (name, dest))
+        })
+        .collect();
+    legacy.sort();
+
+    let mut moved = Vec::new();
+    for (name, dest) in legacy {
+        let (from, to) = (root.join(&name), root.join(&dest));
+        if std::fs::symlink_metadata(&to).is_ok() {
+            continue;
+        }
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent).map_err(|source| CrossError::IoError {
+                message: format!("Failed to create {}", parent.display()),
+                source,
+            })?;
+        }
+        std::fs::rename(&from, &to).map_err(|source| CrossError::IoError {
+            message: format!("Failed to move {} to {}", from.display(), to.display()),
+            source,
+        })?;
+        moved.push((name, dest));
+    }
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths() {
+        let root = Path::new("/cache");
+        assert_eq!(
+            toolchain_dir(root, "aarch64-linux-musl-cross-v0.7.7"),
+            Path::new("/cache/toolchains/aarch64-linux-musl-cross-v0.7.7")
+        );
+        assert_eq!(
+            sdk_dir(root, "android-ndk-linux-r27d"),
+            Path::new("/cache/sdks/android-ndk-linux-r27d")
+        );
+        assert_eq!(
+            qemu_dir(root, "v10.0.2"),
+            Path::new("/cache/runners/qemu/v10.0.2")
+        );
+        assert_eq!(generated_dir(root), Path::new("/cache/generated"));
+    }
+
+    #[test]
+    fn test_entry_of() {
+        let root = Path::new("/cache");
+        let entry = |path: &str| entry_of(root, Path::new(path));
+        assert_eq!(
+            entry("/cache/toolchains/aarch64-linux-musl-cross-v0.7.7/bin/gcc").as_deref(),
+            Some("toolchains/aarch64-linux-musl-cross-v0.7.7")
+        );
+        assert_eq!(
+            entry("/cache/runners/qemu/v10.0.2/qemu-aarch64").as_deref(),
+            Some("runners/qemu/v10.0.2")
+        );
+        assert_eq!(
+            entry("/cache/sdks/android-ndk-linux-r27d").as_deref(),
+            Some("sdks/android-ndk-linux-r27d")
+        );
+        assert_eq!(
+            entry("/cache/generated/docker-qemu-runner-aarch64-musl.sh").as_deref(),
+            Some("generated")
+        );
+        assert_eq!(entry("/cache/runners"), Some("runners".to_string()));
+        assert_eq!(entry("/usr/bin/gcc"), None);
+    }
+
+    #[test]
+    fn test_legacy_destination() {
+        let cases = [
+            (
+                "aarch64-linux-musl-cross-v0.7.7",
+                true,
+                Some("toolchains/aarch64-linux-musl-cross-v0.7.7"),
+            ),
+            (
+                "x86_64-w64-mingw32-cross",
+                true,
+                Some("toolchains/x86_64-w64-mingw32-cross"),
+            ),
+            (
+                "ios-arm64-cross-1010.6-18.5",
+                true,
+                Some("sdks/ios-arm64-cross-1010.6-18.5"),
+            ),
+            (
+                "osxcross-14-5-amd64-v0.2.6",
+                true,
+                Some("sdks/osxcross-14-5-amd64-v0.2.6"),
+            ),
+            (
+                "android-ndk-linux-r27d",
+                true,
+                Some("sdks/android-ndk-linux-r27d"),
+            ),
+            (
+                "qemu-user-static-v10.0.2-linux-amd64",
+                true,
+                Some("runners/qemu/v10.0.2-linux-amd64"),
+            ),
+            ("cmake", true, Some("generated/cmake")),
+            (".smoke-tests", true, Some("generated/.smoke-tests")),
+            (
+                "docker-qemu-runner-aarch64-musl.sh",
+                false,
+                Some("generated/docker-qemu-runner-aarch64-musl.sh"),
+            ),
+            ("toolchains", true, None),
+            ("cross-compiler-dir-layout", false, None),
+            ("aarch64-linux-musl-cross-v0.7.7.tgz", false, None),
+        ];
+        for (name, is_dir, expected) in cases {
+            assert_eq!(
+                legacy_destination(name, is_dir).as_deref(),
+                expected,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_migrate() {
+        let root =
+            std::env::temp_dir().join(format!("cargo-cross-layout-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in [
+            "aarch64-linux-musl-cross-v0.7.3/bin",
+            "qemu-user-static-v10.0.2",
+            "android-ndk-linux-r27d",
+            "cmake",
+            "unrelated",
+            "notes-cross/bin",
+            "ios-app/src",
+        ] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "aarch64-linux-musl-cross-v0.7.3/bin/aarch64-linux-musl-gcc",
+            "qemu-user-static-v10.0.2/qemu-aarch64",
+            "android-ndk-linux-r27d/source.properties",
+            "cmake/aarch64-unknown-linux-musl.cmake",
+            "notes-cross/bin/notes",
+        ] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+        std::fs::write(
+            root.join("docker-qemu-runner-aarch64-musl.sh"),
+            "#!/bin/sh\n",
+        )
+        .unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(
+            "aarch64-linux-musl-cross-v0.7.3",
+            root.join("aarch64-linux-musl-cross-v0.7.7"),
+        )
+        .unwrap();
+
+        let moved = migrate(&root).unwrap();
+        assert!(moved.contains(&(
+            "qemu-user-static-v10.0.2".to_string(),
+            "runners/qemu/v10.0.2".to_string()
+        )));
+        assert!(root
+            .join("toolchains/aarch64-linux-musl-cross-v0.7.3/bin")
+            .is_dir());
+        assert!(root.join("sdks/android-ndk-linux-r27d").is_dir());
+        assert!(root.join("generated/cmake").is_dir());
+        assert!(root
+            .join("generated/docker-qemu-runner-aarch64-musl.sh")
+            .is_file());
+        assert!(root.join("unrelated").is_dir());
+        // Directories that only share a name with cargo-cross entries stay put
+        assert!(root.join("notes-cross/bin/notes").is_file());
+        assert!(root.join("ios-app/src").is_dir());
+        // Adopted toolchains still resolve after the move
+        #[cfg(unix)]
+        assert!(root
+            .join("toolchains/aarch64-linux-musl-cross-v0.7.7/bin")
+            .is_dir());
+
+        let names: Vec<String> = entries(&root).into_iter().map(|(name, _)| name).collect();
+        assert!(names.contains(&"sdks/android-ndk-linux-r27d".to_string()));
+        assert!(names.contains(&"runners/qemu/v10.0.2".to_string()));
+
+        // A second run has nothing left to move
+        assert!(migrate(&root).unwrap().is_empty());
+
+        // A user's own cmake directory is not taken for generated toolchain files
+        std::fs::remove_dir_all(root.join("generated")).unwrap();
+        std::fs::create_dir_all(root.join("cmake")).unwrap();
+        std::fs::write(root.join("cmake/CMakeLists.txt"), "").unwrap();
+        assert!(migrate(&root).unwrap().is_empty());
+        assert!(root.join("cmake/CMakeLists.txt").is_file());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -6,16 +6,48 @@
 //! Unlike other cross-compilation tools, cargo-cross does not require Docker.
 //! It downloads and manages cross-compilation toolchains automatically.
 
+pub mod artifacts;
+pub mod bench;
+pub mod bloat;
+pub mod build_scripts;
+pub mod build_std;
+pub mod builder;
+pub mod cache;
 pub mod cargo;
+pub mod cargo_config;
 pub mod cli;
 pub mod color;
+pub mod compat;
 pub mod config;
+pub mod debuginfo;
+pub mod diagnose;
+pub mod disk;
 pub mod download;
 pub mod env;
 pub mod error;
+pub mod firmware;
+pub mod generated;
+pub mod github;
+pub mod install_name;
+pub mod interrupt;
+pub mod layout;
+pub mod libclang;
+pub mod metadata;
+pub mod metrics;
+pub mod plan;
 pub mod platform;
+pub mod policy;
+pub mod query;
+pub mod report;
+pub mod repro;
 pub mod runner;
+pub mod sccache;
+pub mod shims;
+pub mod usage;
+pub mod vendor;
+pub mod verify;
 
+pub use builder::{BuildOutcome, CrossBuilder, PreparedTarget};
 pub use cli::{parse_args, Args, Command};
 pub use config::{get_target_config, HostPlatform, TargetConfig};
 pub use env::sanitize_cargo_env;
@@ -0,0 +1,461 @@
+//! libclang for crates that run bindgen at build time
+//!
+//! bindgen loads libclang from the host through clang-sys. Minimal containers often have
+//! no libclang at all, which surfaces as a panic deep inside a build script. When bindgen
+//! is built for the target, the same search clang-sys does runs up front, and a missing
+//! libclang is either downloaded (`--auto-libclang`) or reported before the build starts.
+
+use crate::cache::fnv1a;
+use crate::cli::Args;
+use crate::color;
+use crate::config::HostPlatform;
+use crate::env::CrossEnv;
+use crate::error::{run_command_output, CrossError, Result};
+use crate::layout;
+use crate::platform::ToolchainRelease;
+use std::path::{Path, PathBuf};
+use tokio::process::Command as TokioCommand;
+
+/// Whether a `Cargo.lock` pulls in bindgen
+#[must_use]
+pub fn lockfile_uses_bindgen(lockfile: &str) -> bool {
+    lockfile
+        .lines()
+        .any(|line| line.trim() == r#"name = "bindgen""#)
+}
+
+/// File name clang-sys looks for on the host, as shown in error messages
+#[must_use]
+pub fn library_name(host: &HostPlatform) -> &'static str {
+    if host.is_windows() {
+        "libclang.dll"
+    } else if host.is_darwin() {
+        "libclang.dylib"
+    } else {
+        "libclang.so"
+    }
+}
+
+/// Whether `name` is a libclang clang-sys can load on the host: `libclang.so`,
+/// `libclang-14.so`, `libclang.so.18.1`, `libclang.dylib`, `libclang.dll` or `clang.dll`
+#[must_use]
+pub fn is_libclang_file(name: &str, host: &HostPlatform) -> bool {
+    if host.is_windows() {
+        return name.eq_ignore_ascii_case("libclang.dll") || name.eq_ignore_ascii_case("clang.dll");
+    }
+    if host.is_darwin() {
+        return name == "libclang.dylib";
+    }
+    // `libclang`, an optional `-<version>`, then `.so` and optional version numbers
+    let Some((stem, version)) = name
+        .strip_prefix("libclang")
+        .and_then(|rest| rest.split_once(".so"))
+    else {
+        return false;
+    };
+    let is_version = |part: &str| part.chars().all(|c| c.is_ascii_digit() || c == '.');
+    let stem_ok = stem.is_empty()
+        || stem
+            .strip_prefix('-')
+            .is_some_and(|v| !v.is_empty() && is_version(v));
+    let version_ok = version.is_empty()
+        || version
+            .strip_prefix('.')
+            .is_some_and(|v| !v.is_empty() && is_version(v));
+    stem_ok && version_ok
+}
+
+/// Where to look for libclang, in the order clang-sys does
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LibclangSearch {
+    /// `LIBCLANG_PATH`: a directory or the library itself
+    pub libclang_path: Option<PathBuf>,
+    /// Output of `ldconfig -p`
+    pub ldconfig: Option<String>,
+    /// Directories to scan, `llvm-config --libdir` first
+    pub dirs: Vec<PathBuf>,
+}
+
+impl LibclangSearch {
+    /// Directory containing a loadable libclang, if any
+    #[must_use]
+    pub fn find(&self, host: &HostPlatform) -> Option<PathBuf> {
+        if let Some(ref path) = self.libclang_path {
+            // clang-sys only looks where LIBCLANG_PATH points when it is set
+            return if path.is_file() {
+                path.parent().map(Path::to_path_buf)
+            } else {
+                find_in_dir(path, host)
+            };
+        }
+        let from_ldconfig = self
+            .ldconfig
+            .iter()
+            .flat_map(|output| output.lines())
+            .filter_map(|line| line.split_once("=>"))
+            .map(|(_, path)| PathBuf::from(path.trim()))
+            .find(|path| {
+                path.file_name()
+                    .is_some_and(|name| is_libclang_file(&name.to_string_lossy(), host))
+                    && path.is_file()
+            });
+        from_ldconfig
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+            .or_else(|| self.dirs.iter().find_map(|dir| find_in_dir(dir, host)))
+    }
+}
+
+fn find_in_dir(dir: &Path, host: &HostPlatform) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .any(|entry| {
+            is_libclang_file(&entry.file_name().to_string_lossy(), host) && entry.path().is_file()
+        })
+        .then(|| dir.to_path_buf())
+}
+
+/// Directories clang-sys scans when neither `LIBCLANG_PATH` nor ldconfig find libclang
+fn common_dirs(host: &HostPlatform) -> Vec<PathBuf> {
+    if host.is_windows() {
+        return ["C:\\Program Files\\LLVM\\bin", "C:\\LLVM\\bin"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+    }
+    if host.is_darwin() {
+        return [
+            "/Library/Developer/CommandLineTools/usr/lib",
+            "/Applications/Xcode.app/Contents/Developer/Toolchains/XcodeDefault.xctoolchain/usr/lib",
+            "/opt/homebrew/opt/llvm/lib",
+            "/usr/local/opt/llvm/lib",
+        ]
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    }
+    let mut dirs: Vec<PathBuf> = [
+        "/usr/local/lib",
+        "/usr/local/lib64",
+        "/usr/lib",
+        "/usr/lib64",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .collect();
+    dirs.push(PathBuf::from(format!("/usr/lib/{}-linux-gnu", host.arch)));
+    // Versioned installs such as /usr/lib/llvm-18/lib, newest first
+    let mut llvm: Vec<PathBuf> = std::fs::read_dir("/usr/lib")
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("llvm"))
+        .map(|entry| entry.path().join("lib"))
+        .collect();
+    llvm.sort_unstable_by(|a, b| b.cmp(a));
+    dirs.extend(llvm);
+    dirs
+}
+
+/// Stdout of a successful command, if it ran
+async fn command_stdout(program: &str, args: &[&str]) -> Option<String> {
+    let mut cmd = TokioCommand::new(program);
+    cmd.args(args);
+    run_command_output(&mut cmd, program)
+        .await
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Search locations of the host: `LIBCLANG_PATH`, ldconfig, `llvm-config` and the
+/// common install directories
+pub async fn host_search(host: &HostPlatform) -> LibclangSearch {
+    let libclang_path = std::env::var_os("LIBCLANG_PATH")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from);
+    if libclang_path.is_some() {
+        return LibclangSearch {
+            libclang_path,
+            ..LibclangSearch::default()
+        };
+    }
+    let ldconfig = if host.is_linux() {
+        command_stdout("ldconfig", &["-p"]).await
+    } else {
+        None
+    };
+    let mut dirs: Vec<PathBuf> = command_stdout("llvm-config", &["--libdir"])
+        .await
+        .map(|dir| PathBuf::from(dir.trim()))
+        .into_iter()
+        .collect();
+    dirs.extend(common_dirs(host));
+    LibclangSearch {
+        libclang_path: None,
+        ldconfig,
+        dirs,
+    }
+}
+
+/// What to do about libclang for a build that uses bindgen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibclangSetup {
+    /// libclang is in this directory
+    Found(PathBuf),
+    /// Download the portable bundle (`--auto-libclang`)
+    Download,
+    /// Fail before the build
+    Missing,
+}
+
+/// Decide from the search result and `--auto-libclang`
+#[must_use]
+pub fn decide(found: Option<PathBuf>, auto_libclang: bool) -> LibclangSetup {
+    match found {
+        Some(dir) => LibclangSetup::Found(dir),
+        None if auto_libclang => LibclangSetup::Download,
+        None => LibclangSetup::Missing,
+    }
+}
+
+/// The `--libclang-url` archive `--auto-libclang` downloads. There is no default bundle:
+/// the archive is extracted unverified, so it has to be one the user chose.
+pub fn libclang_release(args: &Args, host: &HostPlatform) -> Result<ToolchainRelease> {
+    let url = args.libclang_url.clone().ok_or_else(|| {
+        CrossError::InvalidArgument(
+            "--auto-libclang needs --libclang-url with the archive to download".to_string(),
+        )
+    })?;
+    let name = format!("libclang-custom-{:08x}", fnv1a(url.as_bytes()) as u32);
+    let dir = layout::sdk_dir(&args.cross_compiler_dir, &name);
+    Ok(ToolchainRelease {
+        kind: "libclang",
+        url,
+        format: None,
+        marker: dir.join("lib").join(library_name(host)),
+        dir,
+    })
+}
+
+/// Whether bindgen is built for `target`, and whether that is certain. The dependency
+/// graph of the target decides; when `cargo metadata` fails, a bindgen entry in the
+/// lockfile only makes it likely, since the target or its features may not pull it in.
+async fn bindgen_use(args: &Args, target: &str) -> (bool, bool) {
+    match crate::metadata::target_package_names(args, target).await {
+        Ok(names) => (names.iter().any(|name| name == "bindgen"), true),
+        Err(e) => {
+            color::log_debug(&format!(
+                "Could not read the dependency graph of {target}, checking Cargo.lock: {e}"
+            ));
+            let in_lockfile = crate::metadata::workspace_lockfile(args)
+                .await
+                .is_some_and(|contents| lockfile_uses_bindgen(&contents));
+            (in_lockfile, false)
+        }
+    }
+}
+
+/// Make sure bindgen can load libclang for `target`, exporting `LIBCLANG_PATH` when the
+/// portable bundle is used. Does nothing if the toolchain already set `LIBCLANG_PATH`
+/// (Android NDK) or bindgen is not built for the target.
+pub async fn setup_libclang(
+    cross_env: &mut CrossEnv,
+    target: &str,
+    args: &Args,
+    host: &HostPlatform,
+) -> Result<()> {
+    if cross_env.extra_env.contains_key("LIBCLANG_PATH") {
+        return Ok(());
+    }
+    let (uses_bindgen, certain) = bindgen_use(args, target).await;
+    if !uses_bindgen {
+        return Ok(());
+    }
+
+    let found = host_search(host).await.find(host);
+    match decide(found, args.auto_libclang) {
+        LibclangSetup::Found(dir) => {
+            color::log_debug(&format!(
+                "bindgen will load libclang from {}",
+                dir.display()
+            ));
+        }
+        LibclangSetup::Download => {
+            let release = libclang_release(args, host)?;
+            release
+                .ensure(args)
+                .await
+                .map_err(|e| CrossError::LibclangDownloadFailed {
+                    url: release.url.clone(),
+                    reason: e.to_string(),
+                    library: library_name(host).to_string(),
+                })?;
+            let dir = release.dir.join("lib");
+            color::print_config("LIBCLANG_PATH", &dir.display().to_string());
+            cross_env.set_env("LIBCLANG_PATH", dir.display().to_string());
+        }
+        LibclangSetup::Missing => {
+            let err = CrossError::LibclangNotFound {
+                target: target.to_string(),
+                library: library_name(host).to_string(),
+            };
+            if certain {
+                return Err(err);
+            }
+            // Only the lockfile mentions bindgen: it may not be built for this target
+            color::log_warning(&err.to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{test_target_args, test_toolchain_dir, BuildArgs};
+
+    fn host(os: &'static str) -> HostPlatform {
+        HostPlatform {
+            os,
+            arch: "x86_64",
+            triple: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_lockfile_uses_bindgen() {
+        let lockfile = "[[package]]\nname = \"bindgen\"\nversion = \"0.70.1\"\n";
+        assert!(lockfile_uses_bindgen(lockfile));
+        assert!(!lockfile_uses_bindgen(
+            "[[package]]\nname = \"bindgen-cli\"\n\n[[package]]\nname = \"cc\"\n"
+        ));
+    }
+
+    #[test]
+    fn test_is_libclang_file() {
+        let linux = host("linux");
+        for name in [
+            "libclang.so",
+            "libclang-14.so",
+            "libclang.so.18",
+            "libclang-14.so.1",
+            "libclang.so.18.1",
+        ] {
+            assert!(is_libclang_file(name, &linux), "{name}");
+        }
+        for name in [
+            "libclang.a",
+            "libclang-cpp.so",
+            "libclangBasic.so",
+            "libclang.so.x",
+            "clang",
+        ] {
+            assert!(!is_libclang_file(name, &linux), "{name}");
+        }
+        assert!(is_libclang_file("libclang.dylib", &host("darwin")));
+        assert!(!is_libclang_file("libclang.so", &host("darwin")));
+        assert!(is_libclang_file("LIBCLANG.DLL", &host("windows")));
+        assert!(is_libclang_file("clang.dll", &host("windows")));
+    }
+
+    #[test]
+    fn test_search_order() {
+        let linux = host("linux");
+        let root =
+            std::env::temp_dir().join(format!("cargo-cross-libclang-{}", std::process::id()));
+        let ldconfig_dir = root.join("ldconfig");
+        let llvm_dir = root.join("llvm-18").join("lib");
+        let empty_dir = root.join("empty");
+        for dir in [&ldconfig_dir, &llvm_dir, &empty_dir] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        std::fs::write(ldconfig_dir.join("libclang-14.so.1"), "").unwrap();
+        std::fs::write(llvm_dir.join("libclang.so"), "").unwrap();
+        let ldconfig = format!(
+            "\tlibclang-cpp.so.14 (libc6,x86-64) => {0}/libclang-cpp.so.14\n\tlibclang-14.so.1 (libc6,x86-64) => {0}/libclang-14.so.1\n",
+            ldconfig_dir.display()
+        );
+
+        let search = LibclangSearch {
+            libclang_path: None,
+            ldconfig: Some(ldconfig.clone()),
+            dirs: vec![empty_dir.clone(), llvm_dir.clone()],
+        };
+        assert_eq!(search.find(&linux), Some(ldconfig_dir.clone()));
+
+        // Without ldconfig the directories are scanned in order
+        let search = LibclangSearch {
+            ldconfig: None,
+            ..search
+        };
+        assert_eq!(search.find(&linux), Some(llvm_dir.clone()));
+
+        // LIBCLANG_PATH is authoritative, as a directory or a file
+        let search = LibclangSearch {
+            libclang_path: Some(empty_dir.clone()),
+            ldconfig: Some(ldconfig),
+            dirs: vec![llvm_dir.clone()],
+        };
+        assert_eq!(search.find(&linux), None);
+        let search = LibclangSearch {
+            libclang_path: Some(llvm_dir.join("libclang.so")),
+            ..search
+        };
+        assert_eq!(search.find(&linux), Some(llvm_dir));
+
+        assert_eq!(LibclangSearch::default().find(&linux), None);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_decide() {
+        let dir = PathBuf::from("/usr/lib/llvm-18/lib");
+        assert_eq!(
+            decide(Some(dir.clone()), true),
+            LibclangSetup::Found(dir.clone())
+        );
+        assert_eq!(decide(Some(dir.clone()), false), LibclangSetup::Found(dir));
+        assert_eq!(decide(None, true), LibclangSetup::Download);
+        assert_eq!(decide(None, false), LibclangSetup::Missing);
+    }
+
+    #[test]
+    fn test_libclang_release() {
+        let dir = test_toolchain_dir("libclang");
+        let args = test_target_args(
+            "libclang",
+            &["x86_64-unknown-linux-gnu"],
+            BuildArgs::default(),
+        );
+        // No default bundle is downloaded
+        assert!(matches!(
+            libclang_release(&args, &host("linux")),
+            Err(CrossError::InvalidArgument(_))
+        ));
+
+        let with_url = |url: &str| Args {
+            build: BuildArgs {
+                libclang_url: Some(url.to_string()),
+                ..BuildArgs::default()
+            },
+            ..args.clone()
+        };
+        let release = libclang_release(
+            &with_url("https://mirror.example/libclang.tar.xz"),
+            &host("linux"),
+        )
+        .unwrap();
+        assert_eq!(release.url, "https://mirror.example/libclang.tar.xz");
+        assert!(release.marker.ends_with("lib/libclang.so"));
+        assert!(release.dir.starts_with(dir.join("sdks")));
+
+        // Another URL gets its own directory, so switching URLs downloads again
+        let other = libclang_release(
+            &with_url("https://other.example/libclang.tgz"),
+            &host("linux"),
+        )
+        .unwrap();
+        assert_ne!(other.dir, release.dir);
+    }
+}
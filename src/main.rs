@@ -1,17 +1,39 @@
 //! cargo-cross: Cross-compilation tool for Rust projects
 
 use cargo_cross::{
-    cargo::{build_cargo_env, ensure_rust_src, ensure_target_installed, execute_cargo},
-    cli::{parse_args, print_all_targets, print_version, ParseResult, SetupOutputFormat},
+    artifacts, bench, bloat,
+    builder::{prepare_target, BuildOutcome, CrossBuilder, PreparedTarget},
+    cache::{self, toolchain_dirs_in_env},
+    cargo::{
+        add_script_output_env, build_cargo_env, build_std_crates, clean_target,
+        crt_static_decision, dynamic_musl_warning, ensure_toolchain_installed, print_env_vars,
+        restore_cleared_env, target_lists, CrtDecision,
+    },
+    cli::{
+        parse_args, print_all_targets, print_version, BuildStd, CrtStatic, ParseResult,
+        PlanOutputFormat, QueryOutputFormat, SetupOutputFormat, SetupStep,
+    },
     color,
-    config::{get_target_config, HostPlatform},
-    error::{run_command, Result},
-    platform::setup_cross_env,
-    sanitize_cargo_env,
+    config::HostPlatform,
+    debuginfo,
+    download::{
+        check_cross_compiler_dir_length, completed_downloads, set_download_tls,
+        set_downloads_disabled, DownloadTls,
+    },
+    env::EnvMap,
+    error::{run_command, run_command_output, Result},
+    firmware, install_name, interrupt,
+    metadata::resolve_default_members,
+    metrics::{self, TargetMetrics},
+    platform::validate_targets,
+    query::{compiler_names, ToolNameOptions},
+    report::{self, Event},
+    sanitize_cargo_env, sccache, usage,
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::ffi::OsString;
-use std::path::Path;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use std::time::Duration;
 use tokio::process::Command as TokioCommand;
@@ -33,29 +55,40 @@ async fn main() -> ExitCode {
     // Sanitize environment variables that could cause cargo errors
     sanitize_cargo_env();
 
-    // Setup signal handlers for Ctrl+C and SIGTERM
+    // Setup signal handlers for Ctrl+C and SIGTERM: forward the signal to running
+    // children, give them a moment to exit, remove partial downloads, then exit
     #[cfg(unix)]
     {
         use tokio::signal::unix::{signal, SignalKind};
 
-        // Handle SIGINT (Ctrl+C)
-        tokio::spawn(async move {
-            if let Ok(mut sigint) = signal(SignalKind::interrupt()) {
-                sigint.recv().await;
-                std::process::exit(130);
-            }
-        });
+        for (kind, code) in [
+            (SignalKind::interrupt(), interrupt::EXIT_SIGINT),
+            (SignalKind::terminate(), interrupt::EXIT_SIGTERM),
+        ] {
+            tokio::spawn(async move {
+                if let Ok(mut stream) = signal(kind) {
+                    stream.recv().await;
+                    std::process::exit(interrupt::shutdown(code).await);
+                }
+            });
+        }
+    }
 
-        // Handle SIGTERM
-        tokio::spawn(async move {
-            if let Ok(mut sigterm) = signal(SignalKind::terminate()) {
-                sigterm.recv().await;
-                std::process::exit(143);
-            }
-        });
+    #[cfg(windows)]
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(interrupt::shutdown(interrupt::EXIT_SIGINT).await);
+        }
+    });
+
+    let result = run().await;
+    if interrupt::is_interrupted() {
+        // The signal handler is shutting down and exits with the conventional code
+        std::future::pending::<()>().await;
     }
+    color::log_suppressed_warnings();
 
-    match run().await {
+    match result {
         Ok(code) => code,
         Err(e) => {
             color::log_error(&format!("Error: {e}"));
@@ -66,11 +99,66 @@ async fn main() -> ExitCode {
 
 async fn run() -> Result<ExitCode> {
     match parse_args()? {
-        ParseResult::Build(args) => run_cargo(*args).await,
-        ParseResult::Setup(args) => run_setup(*args).await,
-        ParseResult::Exec(args) => run_exec(*args).await,
-        ParseResult::ShowTargets(format) => {
-            print_all_targets(format);
+        ParseResult::Build(args) => {
+            set_log_level(&args);
+            configure_downloads(&args);
+            run_cargo(*args).await
+        }
+        ParseResult::Setup(args) => {
+            set_log_level(&args.args);
+            configure_downloads(&args.args);
+            run_setup(*args).await
+        }
+        ParseResult::Exec(args) => {
+            set_log_level(&args.args);
+            configure_downloads(&args.args);
+            run_exec(*args).await
+        }
+        ParseResult::Sync(args) => {
+            set_log_level(&args.args);
+            configure_downloads(&args.args);
+            run_sync(*args).await
+        }
+        ParseResult::Plan(args) => {
+            set_log_level(&args.args);
+            run_plan(*args).await
+        }
+        ParseResult::CacheKey(args) => {
+            println!(
+                "{}",
+                cache::cache_key(&args, &HostPlatform::detect().triple)
+            );
+            Ok(ExitCode::SUCCESS)
+        }
+        ParseResult::QueryTools(query) => run_query_tools(&query),
+        ParseResult::Bloat(bloat) => {
+            set_log_level(&bloat.args);
+            configure_downloads(&bloat.args);
+            run_bloat(*bloat).await
+        }
+        ParseResult::CleanGenerated(args) => {
+            set_log_level(&args);
+            let removed = cargo_cross::generated::clean_generated(&args.cross_compiler_dir)?;
+            for path in &removed {
+                color::log_info(&format!("Removed {}", path.display()));
+            }
+            color::log_success(&format!("Removed {} generated file(s)", removed.len()));
+            Ok(ExitCode::SUCCESS)
+        }
+        ParseResult::ShowTargets(targets) => {
+            let lists = if targets.detailed {
+                // Without rustup/rustc every target is reported as unknown
+                let lists = target_lists(targets.toolchain.as_deref())
+                    .await
+                    .unwrap_or_else(|e| {
+                        color::log_warning(&format!("Failed to query Rust targets: {e}"));
+                        Default::default()
+                    });
+                Some(lists)
+            } else {
+                None
+            };
+            print_all_targets(targets.format, lists.as_deref());
             Ok(ExitCode::SUCCESS)
         }
         ParseResult::ShowVersion => {
@@ -80,17 +168,115 @@ async fn run() -> Result<ExitCode> {
     }
 }
 
-struct PreparedTarget {
-    actual_target: String,
-    skip_target_arg: bool,
-    cross_env: cargo_cross::env::CrossEnv,
+/// Report `target` as failed and finish the run: a failing target stops the remaining ones
+fn fail_run(
+    args: &cargo_cross::Args,
+    target: &str,
+    error: &cargo_cross::CrossError,
+    target_elapsed: std::time::Duration,
+    start_time: std::time::Instant,
+    target_metrics: &[TargetMetrics],
+) -> ExitCode {
+    let command_cap = capitalize_command(args.command.as_str());
+    color::log_error(&format!(
+        "{command_cap} failed for target: {}",
+        color::yellow(target)
+    ));
+    color::log_error(&format!("Error: {}", color::white(&error.to_string())));
+    report::emit(&Event::TargetFinished {
+        target: target.to_string(),
+        status: report::Status::Failed,
+        seconds: target_elapsed.as_secs_f64(),
+        error: Some(error.to_string()),
+    });
+    report::emit(&Event::RunFinished {
+        status: report::Status::Failed,
+        seconds: start_time.elapsed().as_secs_f64(),
+    });
+    write_metrics(args, target_metrics);
+    ExitCode::FAILURE
+}
+
+/// Apply `--download-ca-cert` / `--download-insecure` to every toolchain download
+fn configure_downloads(args: &cargo_cross::Args) {
+    if args.download_insecure {
+        color::log_warning(
+            "TLS certificate verification is DISABLED for toolchain downloads (--download-insecure); \
+             downloaded toolchains can be tampered with",
+        );
+    }
+    set_download_tls(DownloadTls::from_args(
+        args.download_ca_cert.as_deref(),
+        args.download_insecure,
+    ));
+    if !args.no_toolchain_setup {
+        check_cross_compiler_dir_length(&args.cross_compiler_dir);
+    }
+}
+
+/// Apply `--quiet` / `-v` to cargo-cross's own messages
+fn set_log_level(args: &cargo_cross::Args) {
+    color::set_log_level(color::LogLevel::from_verbosity(
+        args.quiet,
+        args.verbose_level,
+    ));
+}
+
+/// What `--usage-log` records about a run besides its arguments
+#[derive(Default)]
+struct RunRecord {
+    metrics: Vec<TargetMetrics>,
+    /// Why the run failed, when it did without returning an error
+    failure: Option<String>,
+}
+
+/// Run the cargo command for every target, then append the run to `--usage-log`, however
+/// it ended
+async fn run_cargo(mut args: cargo_cross::Args) -> Result<ExitCode> {
+    let start_time = std::time::Instant::now();
+    let mut record = RunRecord::default();
+    let result = run_targets(&mut args, &mut record).await;
+    let error = match result {
+        Err(ref e) => Some(e.to_string()),
+        Ok(_) => record.failure,
+    };
+    let status = if error.is_some() {
+        report::Status::Failed
+    } else {
+        report::Status::Success
+    };
+    write_usage_log(&args, status, start_time.elapsed(), &record.metrics, error);
+    result
 }
 
-async fn run_cargo(args: cargo_cross::Args) -> Result<ExitCode> {
+async fn run_targets(args: &mut cargo_cross::Args, record: &mut RunRecord) -> Result<ExitCode> {
     let host = HostPlatform::detect();
-    print_config(&args, &host);
+    if let Some(ref spec) = args.event_stream {
+        report::open(spec)?;
+    }
+    // The program `run` starts may read the terminal
+    interrupt::share_terminal(args.command.as_str() == "run" && std::io::stdin().is_terminal());
+    cargo_cross::metadata::resolve_link_toolchain(args).await;
+    validate_targets(&args.targets, args, &host)?;
+    resolve_default_members(args).await?;
+    cargo_cross::metadata::resolve_workspace(args).await;
+    cargo_cross::metadata::check_profile(args)?;
+    cargo_cross::vendor::apply_vendored(args)?;
+    let export_target = args.github_export_target()?.map(str::to_string);
+    check_cache_layout(args)?;
+    print_config(args, &host);
+    sccache::check_sccache_backends(args).await;
+    let collection = plan_artifact_collection(args, &host).await?;
     let total_targets = args.targets.len();
     let start_time = std::time::Instant::now();
+    let mut used_toolchains = BTreeSet::new();
+    let target_metrics = &mut record.metrics;
+    let mut run_codes = Vec::new();
+    let mut firmware_sizes = Vec::new();
+    report::emit(&Event::RunStarted {
+        command: args.command.as_str().to_string(),
+        targets: args.targets.clone(),
+    });
 
     for (i, target) in args.targets.iter().enumerate() {
         color::log_success(&format!(
@@ -99,30 +285,212 @@ async fn run_cargo(args: cargo_cross::Args) -> Result<ExitCode> {
             color::yellow(&total_targets.to_string()),
             color::cyan(target)
         ));
+        report::emit(&Event::TargetStarted {
+            target: target.clone(),
+            index: i + 1,
+            total: total_targets,
+        });
+
+        let triple = if target == "host-tuple" {
+            host.triple.as_str()
+        } else {
+            target.as_str()
+        };
+        let crt_static = args
+            .run_step(SetupStep::RustflagsCrt, triple)
+            .then(|| crt_static_decision(triple, args.crt_static))
+            .flatten();
+        report_crt_static(triple, args, crt_static);
 
         let target_start = std::time::Instant::now();
-        let result = execute_target(target, &args, &host).await;
+        let phases_before = metrics::phase_totals();
+        let downloads_before = completed_downloads();
+        let sccache_before = sccache::stats_snapshot(args).await;
+        let result = execute_target(target, args, &host).await;
         let target_elapsed = target_start.elapsed();
-
-        if let Err(e) = result {
-            let command_cap = capitalize_command(args.command.as_str());
-            color::log_error(&format!(
-                "{command_cap} failed for target: {}",
+        let sccache_delta = match sccache_before {
+            Some(before) => sccache::stats_snapshot(args)
+                .await
+                .and_then(|after| sccache::diff_stats(&before, &after)),
+            None => None,
+        };
+        if sccache_delta.is_some_and(|delta| delta.reset) {
+            color::log_warning(&format!(
+                "sccache statistics went down during {} (the server restarted?); \
+                 counters that decreased are reported as 0",
                 color::yellow(target)
             ));
-            color::log_error(&format!("Error: {}", color::white(&e.to_string())));
-            return Ok(ExitCode::FAILURE);
+        }
+        let mut metrics = TargetMetrics {
+            target: target.clone(),
+            phases: metrics::phase_totals().since(&phases_before),
+            total: target_elapsed,
+            downloads: completed_downloads() - downloads_before,
+            toolchains: Vec::new(),
+            build_std: None,
+            sccache: sccache_delta,
+            crt_static,
+            linker: None,
+            install_names: Vec::new(),
+            debuginfo: Vec::new(),
+        };
+
+        let retries;
+        match result {
+            Ok(outcome) => {
+                retries = outcome.retries;
+                metrics.linker.clone_from(&outcome.cross_env.linker);
+                if args.sequential_runs {
+                    run_codes.push((target.clone(), outcome.status.code().unwrap_or(0)));
+                }
+                let toolchains =
+                    toolchain_dirs_in_env(&outcome.cross_env, &args.cross_compiler_dir);
+                if args.metrics_file.is_some() {
+                    metrics.measure_toolchains(&args.cross_compiler_dir, &toolchains);
+                    metrics.build_std = build_std_crates(args, &outcome.cross_env);
+                }
+                used_toolchains.extend(toolchains);
+                // A failing post-build step fails the target like a failing build
+                let finished: Result<()> = async {
+                    if export_target.as_deref() == Some(target.as_str()) {
+                        export_target_env(target, args, &outcome.cross_env, &host)?;
+                    }
+                    let skip_target_arg = args.skip_target_arg(target, &host.triple);
+                    let build = args.command.as_str() == "build";
+                    if build && install_name::applies(&outcome.target, args) {
+                        metrics.install_names = install_name::fix_install_names(
+                            &outcome.target,
+                            args,
+                            &outcome.cross_env,
+                            &host,
+                            skip_target_arg,
+                        )
+                        .await?;
+                    }
+                    if cargo_cross::cargo::produces_artifacts(args) {
+                        metrics.debuginfo = report_artifacts(
+                            &outcome.target,
+                            args,
+                            skip_target_arg,
+                            collection.as_deref(),
+                            &outcome.cross_env,
+                            &host,
+                        )
+                        .await?;
+                    }
+                    if build {
+                        let firmware = process_firmware(
+                            &outcome.target,
+                            args,
+                            skip_target_arg,
+                            &outcome.cross_env,
+                            &host,
+                        )
+                        .await?;
+                        firmware_sizes.extend(
+                            firmware
+                                .into_iter()
+                                .map(|firmware| (outcome.target.clone(), firmware)),
+                        );
+                    }
+                    if args.command.as_str() == "bench" {
+                        collect_bench_results(&outcome.target, args, &outcome.cross_env);
+                    }
+                    Ok(())
+                }
+                .await;
+                target_metrics.push(metrics);
+                if let Err(e) = finished {
+                    record.failure = Some(e.to_string());
+                    return Ok(fail_run(
+                        args,
+                        target,
+                        &e,
+                        target_elapsed,
+                        start_time,
+                        target_metrics,
+                    ));
+                }
+            }
+            // Under --sequential-runs a failing program does not stop the other targets
+            Err(cargo_cross::CrossError::CargoFailed { code }) if args.sequential_runs => {
+                color::log_error(&format!(
+                    "Program for {} exited with code {code}",
+                    color::yellow(target)
+                ));
+                report::emit(&Event::TargetFinished {
+                    target: target.clone(),
+                    status: report::Status::Failed,
+                    seconds: target_elapsed.as_secs_f64(),
+                    error: Some(format!("exit code {code}")),
+                });
+                target_metrics.push(metrics);
+                run_codes.push((target.clone(), code));
+                continue;
+            }
+            Err(e) => {
+                target_metrics.push(metrics);
+                record.failure = Some(e.to_string());
+                return Ok(fail_run(
+                    args,
+                    target,
+                    &e,
+                    target_elapsed,
+                    start_time,
+                    target_metrics,
+                ));
+            }
         }
 
+        let mut retry_note = if retries > 0 {
+            format!(", {}", format_retries(retries))
+        } else {
+            String::new()
+        };
+        if let Some(delta) = sccache_delta {
+            retry_note.push_str(&format!(", sccache: {}", delta.describe()));
+        }
         color::log_success(&format!(
-            "Target {} completed (took {})",
+            "Target {} completed (took {}{retry_note})",
             color::yellow(target),
             color::yellow(&format_duration(target_elapsed))
         ));
+        report::emit(&Event::TargetFinished {
+            target: target.clone(),
+            status: report::Status::Success,
+            seconds: target_elapsed.as_secs_f64(),
+            error: None,
+        });
     }
 
     let elapsed = start_time.elapsed();
     color::print_separator();
+    for line in format_sccache_summary(target_metrics) {
+        color::log_info(&line);
+    }
+    for line in firmware::format_size_summary(&firmware_sizes) {
+        color::log_info(&line);
+    }
+    if args.sequential_runs {
+        for line in format_run_codes(&run_codes) {
+            color::log_info(&line);
+        }
+        let failed = run_codes.iter().filter(|(_, code)| *code != 0).count();
+        if failed > 0 {
+            color::log_error(&format!(
+                "{failed} of {} programs exited with an error (took {})",
+                run_codes.len(),
+                format_duration(elapsed)
+            ));
+            report::emit(&Event::RunFinished {
+                status: report::Status::Failed,
+                seconds: elapsed.as_secs_f64(),
+            });
+            write_metrics(args, target_metrics);
+            record.failure = Some(format!("{failed} of {} programs failed", run_codes.len()));
+            return Ok(ExitCode::FAILURE);
+        }
+    }
     color::log_success(&format!(
         "All {} operations completed successfully!",
         color::cyan(args.command.as_str())
@@ -132,7 +500,14 @@ async fn run_cargo(args: cargo_cross::Args) -> Result<ExitCode> {
         color::yellow(&format_duration(elapsed))
     ));
 
-    set_github_output(&args);
+    report::emit(&Event::RunFinished {
+        status: report::Status::Success,
+        seconds: elapsed.as_secs_f64(),
+    });
+
+    set_github_output(args);
+    maintain_toolchain_cache(args, &used_toolchains);
+    write_metrics(args, target_metrics);
 
     Ok(ExitCode::SUCCESS)
 }
@@ -146,6 +521,11 @@ async fn run_setup(setup: cargo_cross::cli::SetupArgs) -> Result<ExitCode> {
 
     let host = HostPlatform::detect();
     let target = &setup.args.targets[0];
+    validate_targets(&setup.args.targets, &setup.args, &host)?;
+    check_cache_layout(&setup.args)?;
+    if let Some(spec) = setup.args.event_stream.as_deref() {
+        report::open(spec)?;
+    }
     let _guard = LogSilenceGuard::new();
     let prepared = prepare_target(target, &setup.args, &host).await?;
     let env = build_cargo_env(
@@ -156,21 +536,58 @@ async fn run_setup(setup: cargo_cross::cli::SetupArgs) -> Result<ExitCode> {
         prepared.skip_target_arg,
     )?;
 
-    write_setup_github_env(&env)?;
-    print_setup_env(&env, setup.format)?;
+    let export = write_setup_github_env(&env, &prepared.cross_env.path)?;
+    let cargo_config = if setup.write_cargo_config {
+        let path = cargo_cross::metadata::effective_cwd(&setup.args)
+            .join(".cargo")
+            .join("config.toml");
+        let snippet = cargo_cross::cargo_config::TargetSnippet::from_env(
+            &prepared.actual_target,
+            &env.clone().into_vars(),
+        );
+        cargo_cross::cargo_config::write(&path, &snippet)?.then_some(path)
+    } else {
+        None
+    };
+    report::emit(&Event::SetupApplied {
+        target: prepared.actual_target.clone(),
+        variables: export
+            .as_ref()
+            .map(|export| export.variables.clone())
+            .unwrap_or_default(),
+        paths: export
+            .as_ref()
+            .map(|export| {
+                export
+                    .paths
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        cargo_config: cargo_config.map(|path| path.display().to_string()),
+    });
+
+    // Inside Actions the environment already reaches later steps through GITHUB_ENV;
+    // exports are printed there only when a format is asked for
+    if export.is_none() || setup.format != SetupOutputFormat::Auto {
+        print_setup_env(&env, setup.format)?;
+    }
     set_github_output(&setup.args);
     Ok(ExitCode::SUCCESS)
 }
 
 async fn run_exec(exec: cargo_cross::cli::ExecArgs) -> Result<ExitCode> {
     let host = HostPlatform::detect();
+    interrupt::share_terminal(std::io::stdin().is_terminal());
+    validate_targets(&exec.args.targets, &exec.args, &host)?;
+    let export_target = exec.args.github_export_target()?.map(str::to_string);
+    check_cache_layout(&exec.args)?;
     print_config(&exec.args, &host);
-    println!(
-        "{}",
-        color::format_config("Exec command", &format_cli_command(&exec.command))
-    );
+    color::print_config("Exec command", &format_cli_command(&exec.command));
 
     let total_targets = exec.args.targets.len();
+    let mut used_toolchains = BTreeSet::new();
     for (i, target) in exec.args.targets.iter().enumerate() {
         color::log_success(&format!(
             "[{}/{}] Processing target: {}",
@@ -179,57 +596,337 @@ async fn run_exec(exec: cargo_cross::cli::ExecArgs) -> Result<ExitCode> {
             color::cyan(target)
         ));
 
-        if let Err(e) = execute_exec_target(target, &exec.args, &exec.command, &host).await {
-            color::log_error(&format!(
-                "Exec failed for target: {}",
-                color::yellow(target)
-            ));
-            color::log_error(&format!("Error: {}", color::white(&e.to_string())));
-            return Ok(ExitCode::FAILURE);
+        match execute_exec_target(target, &exec.args, &exec.command, &host).await {
+            Ok(prepared) => {
+                used_toolchains.extend(toolchain_dirs_in_env(
+                    &prepared.cross_env,
+                    &exec.args.cross_compiler_dir,
+                ));
+                if export_target.as_deref() == Some(target.as_str()) {
+                    export_target_env(target, &exec.args, &prepared.cross_env, &host)?;
+                }
+            }
+            Err(e) => {
+                color::log_error(&format!(
+                    "Exec failed for target: {}",
+                    color::yellow(target)
+                ));
+                color::log_error(&format!("Error: {}", color::white(&e.to_string())));
+                return Ok(ExitCode::FAILURE);
+            }
         }
     }
 
     set_github_output(&exec.args);
+    maintain_toolchain_cache(&exec.args, &used_toolchains);
     Ok(ExitCode::SUCCESS)
 }
 
-async fn execute_target(target: &str, args: &cargo_cross::Args, host: &HostPlatform) -> Result<()> {
+async fn run_sync(sync: cargo_cross::cli::SyncArgs) -> Result<ExitCode> {
+    let mut args = sync.args;
+    if sync.check {
+        // Verify only: rustup installs and toolchain downloads become errors
+        args.no_target_install = true;
+        set_downloads_disabled(true);
+    }
+
+    let host = HostPlatform::detect();
+    validate_targets(&args.targets, &args, &host)?;
+    check_cache_layout(&args)?;
+    print_config(&args, &host);
+
+    let mut missing = Vec::new();
+    if let Some(toolchain) = args.toolchain.as_deref() {
+        if let Err(e) = ensure_toolchain_installed(toolchain, !sync.check).await {
+            if !sync.check {
+                return Err(e);
+            }
+            missing.push((toolchain.to_string(), e.to_string()));
+        }
+    }
+
+    let mut manifest = Vec::new();
+    let mut used_toolchains = BTreeSet::new();
+    if missing.is_empty() {
+        for target in &args.targets {
+            match prepare_target(target, &args, &host).await {
+                Ok(prepared) => {
+                    let dirs = toolchain_dirs_in_env(&prepared.cross_env, &args.cross_compiler_dir);
+                    used_toolchains.extend(dirs.iter().cloned());
+                    manifest.push((prepared.actual_target, dirs));
+                }
+                Err(e) if sync.check => missing.push((target.clone(), e.to_string())),
+                Err(e) => {
+                    color::log_error(&format!(
+                        "Sync failed for target: {}",
+                        color::yellow(target)
+                    ));
+                    color::log_error(&format!("Error: {}", color::white(&e.to_string())));
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+        }
+    }
+
+    print_sync_manifest(&args, &manifest).await;
+
+    if !missing.is_empty() {
+        for (item, reason) in &missing {
+            color::log_error(&format!("Missing for {}: {reason}", color::yellow(item)));
+        }
+        return Ok(ExitCode::FAILURE);
+    }
+
+    if !sync.check {
+        maintain_toolchain_cache(&args, &used_toolchains);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print the installed versions and per-target toolchain directories
+async fn print_sync_manifest(args: &cargo_cross::Args, manifest: &[(String, BTreeSet<String>)]) {
+    let mut cmd = TokioCommand::new("rustc");
+    if let Some(toolchain) = args.toolchain.as_deref() {
+        cmd.arg(format!("+{toolchain}"));
+    }
+    cmd.arg("--version");
+    let rustc_version = run_command_output(&mut cmd, "rustc")
+        .await
+        .ok()
+        .filter(|output| output.status.success())
+        .map_or_else(
+            || "unknown".to_string(),
+            |output| String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        );
+
     color::print_separator();
-    color::log_info(&format!(
-        "Executing {} for {}...",
-        color::magenta(args.command.as_str()),
-        color::magenta(target)
-    ));
+    color::log_success("Sync manifest:");
+    color::print_config("rustc", &rustc_version);
+    color::print_config("Cross-make version", &args.cross_make_version);
+    color::print_config("QEMU version", &args.qemu_version);
+    for (target, dirs) in manifest {
+        let dirs = if dirs.is_empty() {
+            "(no cross toolchain)".to_string()
+        } else {
+            dirs.iter().cloned().collect::<Vec<_>>().join(", ")
+        };
+        color::print_config(target, &dirs);
+    }
+}
 
-    if args.clean_cache {
-        color::log_info("Cleaning cache...");
-        let _ = TokioCommand::new("cargo").arg("clean").status().await;
+/// Print the tool names of each target; exits non-zero if any target has none
+fn run_query_tools(query: &cargo_cross::cli::QueryToolsArgs) -> Result<ExitCode> {
+    let opts = ToolNameOptions::from_args(&query.args, &HostPlatform::detect());
+    let results: Vec<_> = query
+        .args
+        .targets
+        .iter()
+        .map(|target| (target, compiler_names(target, &opts)))
+        .collect();
+
+    match query.format {
+        QueryOutputFormat::Json => {
+            let doc: Vec<serde_json::Value> = results
+                .iter()
+                .map(|(target, result)| {
+                    let mut entry = match result {
+                        Ok(names) => names.to_json(),
+                        Err(e) => serde_json::json!({ "error": e.to_string() }),
+                    };
+                    entry["target"] = serde_json::Value::from(target.as_str());
+                    entry
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&doc)?);
+        }
+        QueryOutputFormat::Text => {
+            for (target, result) in &results {
+                println!("{target}");
+                match result {
+                    Ok(names) => {
+                        for (key, value) in [
+                            ("cc", &names.cc),
+                            ("cxx", &names.cxx),
+                            ("ar", &names.ar),
+                            ("linker", &names.linker),
+                            ("bin_prefix", &names.bin_prefix),
+                            ("folder_name", &names.folder_name),
+                        ] {
+                            println!("  {key:<12}{value}");
+                        }
+                    }
+                    Err(e) => println!("  error       {e}"),
+                }
+            }
+        }
     }
 
-    let prepared = prepare_target(target, args, host).await?;
+    Ok(if results.iter().all(|(_, result)| result.is_ok()) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
 
-    let status = execute_cargo(
+/// Build the target and report the largest functions and crates of its binary
+async fn run_bloat(bloat: cargo_cross::cli::BloatArgs) -> Result<ExitCode> {
+    let host = HostPlatform::detect();
+    let args = &bloat.args;
+    validate_targets(&args.targets, args, &host)?;
+    check_cache_layout(args)?;
+    print_config(args, &host);
+    let target = &args.targets[0];
+
+    if which::which("cargo-bloat").is_err() {
+        color::log_warning(
+            "cargo-bloat is not installed (cargo install cargo-bloat); showing the sizes read \
+             from the symbol table of the built binary instead",
+        );
+        return run_internal_bloat(target, &bloat, &host).await;
+    }
+
+    let prepared = prepare_target(target, args, &host).await?;
+    let build_env = build_cargo_env(
         &prepared.actual_target,
         args,
         &prepared.cross_env,
-        host,
+        &host,
         prepared.skip_target_arg,
-    )
-    .await?;
+    )?;
+    let argv = bloat::cargo_bloat_argv(
+        args,
+        &bloat.options,
+        &prepared.actual_target,
+        prepared.skip_target_arg,
+    );
+    let mut cmd = TokioCommand::new(&argv[0]);
+    cmd.args(&argv[1..]);
+    if let Some(ref cwd) = args.cargo_cwd {
+        cmd.current_dir(cwd);
+    }
+    cmd.envs(build_env.vars());
+    restore_cleared_env(&mut cmd, &build_env);
+    add_script_output_env(&mut cmd, args);
 
+    print_env_vars(&build_env, args);
+    color::print_run_header();
+    color::print_command(&format_cli_command(&argv));
+    let status = run_command(&mut cmd, &argv[0]).await?;
     if !status.success() {
+        return Err(cargo_cross::CrossError::CommandFailed {
+            command: format_cli_command(&argv),
+        });
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Build the target and print the size breakdown of each binary and cdylib it produced
+async fn run_internal_bloat(
+    target: &str,
+    bloat: &cargo_cross::cli::BloatArgs,
+    host: &HostPlatform,
+) -> Result<ExitCode> {
+    let mut args = bloat.args.clone();
+    args.command = cargo_cross::Command::build();
+    let outcome = execute_target(target, &args, host).await?;
+
+    let metadata = cargo_cross::metadata::cargo_metadata(&args).await?;
+    let skip_target_arg = args.skip_target_arg(target, &host.triple);
+    let dir = artifacts::output_dir(&metadata, &outcome.target, &args, skip_target_arg);
+    let binaries: Vec<PathBuf> = artifacts::expected_artifacts(&metadata, &args, &outcome.target)
+        .artifacts
+        .into_iter()
+        .filter(|artifact| artifact.kind != artifacts::ArtifactKind::Staticlib)
+        .map(|artifact| dir.join(artifact.path))
+        .filter(|path| path.is_file())
+        .collect();
+    if binaries.is_empty() {
+        return Err(cargo_cross::CrossError::InvalidArgument(format!(
+            "the build for {} produced no binary or cdylib to analyze in {}",
+            outcome.target,
+            dir.display()
+        )));
+    }
+    for path in &binaries {
+        let data = std::fs::read(path).map_err(|source| cargo_cross::CrossError::IoError {
+            message: format!("Failed to read {}", path.display()),
+            source,
+        })?;
+        let report = bloat::analyze(&data).map_err(|e| {
+            cargo_cross::CrossError::InvalidArgument(format!("{}: {e}", path.display()))
+        })?;
+        color::print_separator();
+        color::log_info(&format!(
+            "Sizes in {}",
+            color::cyan(&path.display().to_string())
+        ));
+        print!("{}", bloat::render(&report, &bloat.options)?);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print the execution plan; exits non-zero if any target would fail
+async fn run_plan(mut plan: cargo_cross::cli::PlanArgs) -> Result<ExitCode> {
+    let host = HostPlatform::detect();
+    cargo_cross::metadata::resolve_link_toolchain(&mut plan.args).await;
+    resolve_default_members(&mut plan.args).await?;
+    cargo_cross::metadata::resolve_workspace(&mut plan.args).await;
+    cargo_cross::metadata::check_profile(&plan.args)?;
+    cargo_cross::vendor::apply_vendored(&mut plan.args)?;
+    let plans = cargo_cross::plan::plan_targets(&plan.args, &host).await;
+
+    match plan.format {
+        PlanOutputFormat::Json => {
+            let doc = cargo_cross::plan::plan_json(&plan.args, &host, &plans);
+            println!("{}", serde_json::to_string_pretty(&doc)?);
+        }
+        PlanOutputFormat::Table => println!("{}", cargo_cross::plan::plan_table(&plans)),
+    }
+
+    if plans.iter().any(|p| !p.errors.is_empty()) {
+        Ok(ExitCode::FAILURE)
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+async fn execute_target(
+    target: &str,
+    args: &cargo_cross::Args,
+    host: &HostPlatform,
+) -> Result<BuildOutcome> {
+    color::print_separator();
+    color::log_info(&format!(
+        "Executing {} for {}...",
+        color::magenta(args.command.as_str()),
+        color::magenta(target)
+    ));
+
+    let mut target_args = args.clone();
+    target_args.targets = vec![target.to_string()];
+    let outcome = CrossBuilder::from_args(target_args, host.clone())
+        .run()
+        .await?;
+
+    if !outcome.success() {
+        if outcome.retries > 0 {
+            color::log_warning(&format!(
+                "Still failing after {}",
+                format_retries(outcome.retries)
+            ));
+        }
         return Err(cargo_cross::CrossError::CargoFailed {
-            code: status.code().unwrap_or(1),
+            code: outcome.status.code().unwrap_or(1),
         });
     }
 
     let command_cap = capitalize_command(args.command.as_str());
     color::log_success(&format!(
         "{command_cap} successful: {}",
-        color::yellow(&prepared.actual_target)
+        color::yellow(&outcome.target)
     ));
 
-    Ok(())
+    Ok(outcome)
 }
 
 async fn execute_exec_target(
@@ -237,19 +934,18 @@ async fn execute_exec_target(
     args: &cargo_cross::Args,
     command: &[String],
     host: &HostPlatform,
-) -> Result<()> {
+) -> Result<PreparedTarget> {
     color::print_separator();
     color::log_info(&format!(
         "Executing custom command for {}...",
         color::magenta(target)
     ));
 
+    let prepared = prepare_target(target, args, host).await?;
+
     if args.clean_cache {
-        color::log_info("Cleaning cache...");
-        let _ = TokioCommand::new("cargo").arg("clean").status().await;
+        clean_target(&prepared.actual_target, args, prepared.skip_target_arg).await;
     }
-
-    let prepared = prepare_target(target, args, host).await?;
     let build_env = build_cargo_env(
         &prepared.actual_target,
         args,
@@ -271,14 +967,13 @@ async fn execute_exec_target(
     if let Some(ref cwd) = args.cargo_cwd {
         cmd.current_dir(cwd);
     }
-    cmd.envs(&build_env);
+    cmd.envs(build_env.vars());
+    restore_cleared_env(&mut cmd, &build_env);
+    add_script_output_env(&mut cmd, args);
 
-    print_env_vars(&build_env);
+    print_env_vars(&build_env, args);
     color::print_run_header();
-    println!(
-        "{}",
-        color::format_command(&format_cli_command(&actual_command))
-    );
+    color::print_command(&format_cli_command(&actual_command));
 
     let status = run_command(&mut cmd, &actual_command[0]).await?;
     if !status.success() {
@@ -292,7 +987,7 @@ async fn execute_exec_target(
         color::yellow(&prepared.actual_target)
     ));
 
-    Ok(())
+    Ok(prepared)
 }
 
 fn prepare_exec_command(command: &[String], target: &str, inject_target: bool) -> Vec<String> {
@@ -408,252 +1103,556 @@ fn has_explicit_exec_target(args: &[String]) -> bool {
     false
 }
 
-async fn prepare_target(
-    target: &str,
-    args: &cargo_cross::Args,
-    host: &HostPlatform,
-) -> Result<PreparedTarget> {
-    let is_host_build = target == "host-tuple";
-    let actual_target = if is_host_build { &host.triple } else { target };
-    let target_config = get_target_config(actual_target);
-    let auto_build_std = ensure_target_installed(actual_target, args.toolchain.as_deref()).await?;
-    let mut cross_env = if is_host_build {
-        color::log_info(&format!(
-            "Building for host ({}), skipping toolchain setup",
-            color::cyan(actual_target)
-        ));
-        cargo_cross::env::CrossEnv::new()
-    } else if let Some(env) = check_preconfigured_env(actual_target, args) {
-        color::log_success(&format!(
-            "Using pre-configured compiler from environment variables for {}",
-            color::yellow(actual_target)
-        ));
-        env
-    } else if let Some(config) = target_config {
-        setup_cross_env(config, args, host).await?
-    } else {
-        // Unknown target, use default environment
-        color::log_warning(&format!(
-            "No specific toolchain configuration for {}, using default",
-            color::cyan(actual_target)
-        ));
-        cargo_cross::env::CrossEnv::new()
-    };
-
-    // Apply user-provided compiler overrides from CLI arguments
-    // CLI args have highest priority: CLI > env vars > auto-config
-    apply_user_overrides(&mut cross_env, args);
-
-    // Enable build-std if auto-detected (target exists in rustc but not in rustup)
-    if auto_build_std && args.build_std.is_none() && cross_env.build_std.is_none() {
-        cross_env.build_std = Some("true".to_string());
+fn print_config(args: &cargo_cross::Args, _host: &HostPlatform) {
+    color::print_config_header();
+    color::print_config("Command", args.command.as_str());
+    color::print_config(
+        "Working directory",
+        &cargo_cross::metadata::effective_cwd(args)
+            .display()
+            .to_string(),
+    );
+    if let Some(ref workspace) = args.workspace_paths {
+        color::print_config("Workspace root", &workspace.root.display().to_string());
     }
 
-    // Handle build-std requirement
-    let needs_build_std =
-        args.build_std.is_some() || args.panic_immediate_abort || cross_env.build_std.is_some();
-
-    if needs_build_std {
-        ensure_rust_src(actual_target, args.toolchain.as_deref()).await?;
+    if !args.package.is_empty() {
+        color::print_config("Package", &args.package.join(", "));
+    }
+    if let Some(ref bin) = args.bin_target {
+        color::print_config("Binary target", bin);
+    }
+    if args.build_bins {
+        color::print_config("Build all binaries", "true");
+    }
+    if args.build_lib {
+        color::print_config("Build library", "true");
+    }
+    if args.build_all_targets {
+        color::print_config("Build all targets", "true");
+    }
+    if args.workspace && !args.default_members_only {
+        color::print_config("Building workspace", "true");
+    }
+    if !args.exclude.is_empty() {
+        color::print_config("Excluded packages", &args.exclude.join(", "));
     }
 
-    Ok(PreparedTarget {
-        actual_target: actual_target.to_string(),
-        skip_target_arg: is_host_build,
-        cross_env,
-    })
-}
+    color::print_config("Profile", &args.profile);
 
-/// Check for pre-configured compiler environment variables
-/// Returns Some(CrossEnv) if CC_<target> or generic CC/CXX are set
-fn check_preconfigured_env(
-    target: &str,
-    args: &cargo_cross::Args,
-) -> Option<cargo_cross::env::CrossEnv> {
-    // Skip if user explicitly wants to skip toolchain setup
-    if args.no_toolchain_setup {
-        return None;
+    if let Some(ref toolchain) = args.toolchain {
+        color::print_config("Toolchain", toolchain);
+    }
+    let nightly = args.nightly_requirements();
+    if !nightly.is_empty() {
+        color::print_config("Requires nightly", &nightly.join(", "));
+        let on_nightly = args.toolchain_is_nightly().unwrap_or(true);
+        if !on_nightly && args.rustc_bootstrap.is_none() {
+            color::log_warning(&format!(
+                "{} require a nightly toolchain, but '{}' was selected",
+                nightly.join(", "),
+                args.selected_toolchain().unwrap_or_default()
+            ));
+        }
     }
 
-    let target_lower = target.replace('-', "_");
-    let target_upper = target.to_uppercase().replace('-', "_");
+    let targets_str = args.targets.join(", ");
+    color::print_config("Targets", &targets_str);
 
-    // Check target-specific CC_<target> first
-    let cc_target_var = format!("CC_{target_lower}");
-    let cxx_target_var = format!("CXX_{target_lower}");
-    let ar_target_var = format!("AR_{target_lower}");
-    let linker_var = format!("CARGO_TARGET_{target_upper}_LINKER");
-    let runner_var = format!("CARGO_TARGET_{target_upper}_RUNNER");
+    if args.glibc_version != cargo_cross::config::DEFAULT_GLIBC_VERSION {
+        color::print_config("Glibc version", &args.glibc_version);
+    }
 
-    // Helper to get non-empty env var
-    let get_env = |name: &str| std::env::var(name).ok().filter(|s| !s.is_empty());
+    if let Some(ref features) = args.features {
+        color::print_config("Features", features);
+    }
+    if args.no_default_features {
+        color::print_config("No default features", "true");
+    }
+    if args.all_features {
+        color::print_config("All features", "true");
+    }
 
-    // Check if target-specific CC is set
-    if let Some(cc) = get_env(&cc_target_var) {
-        let mut env = cargo_cross::env::CrossEnv::new();
-        env.set_cc(&cc);
+    if !args.rustflags.is_empty() {
+        color::print_config("Additional rustflags", &args.rustflags.join(" "));
+    }
 
-        if let Some(cxx) = get_env(&cxx_target_var) {
-            env.set_cxx(&cxx);
+    match args.build_std {
+        BuildStd::Enabled(ref crates) => {
+            color::print_config("Build std", crates);
         }
-        if let Some(ar) = get_env(&ar_target_var) {
-            env.set_ar(&ar);
+        BuildStd::Disabled => {
+            color::print_config("Build std", "false");
         }
-        if let Some(linker) = get_env(&linker_var) {
-            env.set_linker(&linker);
+        BuildStd::Auto(_) => {
+            color::print_config("Build std", "auto (per target)");
         }
-        if let Some(runner) = get_env(&runner_var) {
-            env.set_runner(&runner);
-        }
-        return Some(env);
+        BuildStd::Unset => {}
     }
+}
 
-    // Check generic CC/CXX environment variables
-    // Only use if both CC and CXX are set (matching cross.sh behavior)
-    if let (Some(cc), Some(cxx)) = (get_env("CC"), get_env("CXX")) {
-        let mut env = cargo_cross::env::CrossEnv::new();
-        env.set_cc(&cc);
-        env.set_cxx(&cxx);
+/// Warn (or fail with `--strict-cache`) when cached toolchains were written with another layout
+fn check_cache_layout(args: &cargo_cross::Args) -> Result<()> {
+    if args.no_toolchain_setup {
+        return Ok(());
+    }
+    cache::check_layout(&args.cross_compiler_dir, args.strict_cache)
+}
 
-        // AR defaults to CC with -gcc suffix replaced by -ar
-        if let Some(ar) = get_env("AR") {
-            env.set_ar(&ar);
-        } else if cc.ends_with("-gcc") {
-            env.set_ar(format!("{}-ar", cc.trim_end_matches("-gcc")));
+/// Record toolchain usage and prune the cache if `--toolchain-cache-limit` is set
+fn maintain_toolchain_cache(args: &cargo_cross::Args, used: &BTreeSet<String>) {
+    let root = &args.cross_compiler_dir;
+    if root.is_dir() && !root.join(cache::LAYOUT_FILE).exists() {
+        if let Err(e) = cache::write_layout_marker(root) {
+            color::log_warning(&format!(
+                "Failed to write toolchain cache layout marker: {e}"
+            ));
         }
+    }
+    if let Err(e) = cache::record_usage(root, used) {
+        color::log_warning(&format!("Failed to update toolchain cache index: {e}"));
+    }
+    log_coexisting_versions(root);
 
-        // Linker defaults to CC
-        if let Some(linker) = get_env("LINKER") {
-            env.set_linker(&linker);
-        } else {
-            env.set_linker(&cc);
+    let Some(limit) = args.toolchain_cache_limit else {
+        return;
+    };
+    let in_use: HashSet<String> = used.iter().cloned().collect();
+    match cache::prune_cache(root, limit, &in_use) {
+        Ok(pruned) if !pruned.is_empty() => {
+            let freed: u64 = pruned.iter().map(|e| e.size).sum();
+            color::log_success(&format!(
+                "Pruned {} toolchain cache entries, freed {}",
+                color::yellow(&pruned.len().to_string()),
+                color::yellow(&cache::format_size(freed))
+            ));
         }
+        Ok(_) => {}
+        Err(e) => color::log_warning(&format!("Failed to prune toolchain cache: {e}")),
+    }
+}
 
-        // RUNNER support
-        if let Some(runner) = get_env("RUNNER") {
-            env.set_runner(&runner);
-        }
+/// Point out cross-make toolchains cached in several versions
+fn log_coexisting_versions(root: &Path) {
+    let names = cache::toolchain_dir_names(root);
+    for (prefix, versions) in cache::coexisting_versions(names.iter().map(String::as_str)) {
+        let versions: Vec<String> = versions
+            .iter()
+            .map(|version| {
+                let name = cargo_cross::layout::entry_name(
+                    cargo_cross::layout::TOOLCHAINS_DIR,
+                    &format!("{prefix}-cross-{version}"),
+                );
+                match cache::adopted_from(root, &name) {
+                    Some(target) => format!("{version} (adopted {target})"),
+                    None => version.clone(),
+                }
+            })
+            .collect();
+        color::log_info(&format!(
+            "{} is cached in several versions: {} (--toolchain-cache-limit prunes unused ones first)",
+            color::yellow(&format!("{prefix}-cross")),
+            versions.join(", ")
+        ));
+    }
+}
 
-        return Some(env);
+/// Write `--metrics-file`; a failure to write it does not fail the build
+fn write_metrics(args: &cargo_cross::Args, targets: &[TargetMetrics]) {
+    let Some(ref path) = args.metrics_file else {
+        return;
+    };
+    let path = cargo_cross::metadata::resolve_cargo_path(args, path);
+    match metrics::write_metrics_file(&path, targets) {
+        Ok(()) => color::log_info(&format!(
+            "Metrics written to {}",
+            color::cyan(&path.display().to_string())
+        )),
+        Err(e) => color::log_warning(&format!(
+            "Failed to write metrics to {}: {e}",
+            path.display()
+        )),
     }
+}
 
-    None
+/// Append the run to `--usage-log`; a failure to write it does not fail the build
+fn write_usage_log(
+    args: &cargo_cross::Args,
+    status: report::Status,
+    elapsed: Duration,
+    targets: &[TargetMetrics],
+    error: Option<String>,
+) {
+    let Some(ref path) = args.usage_log else {
+        return;
+    };
+    let path = cargo_cross::metadata::resolve_cargo_path(args, path);
+    let summary = usage::RunSummary {
+        command: args.command.as_str(),
+        targets: &args.targets,
+        status,
+        elapsed,
+        metrics: targets,
+        error,
+    };
+    if let Err(e) = usage::record_run(&path, &summary) {
+        color::log_warning(&format!(
+            "Failed to write the usage log {}: {e}",
+            path.display()
+        ));
+    }
 }
 
-/// Apply user-provided compiler overrides from CLI arguments
-/// CLI arguments have the highest priority and override both env vars and auto-config
-fn apply_user_overrides(env: &mut cargo_cross::env::CrossEnv, args: &cargo_cross::Args) {
-    if let Some(ref cc) = args.cc {
-        let cc_str = cc.display().to_string();
-        if !cc_str.is_empty() {
-            env.set_cc(cc_str);
+fn set_github_output(args: &cargo_cross::Args) {
+    if let Ok(github_output) = std::env::var("GITHUB_OUTPUT") {
+        // Convert targets to JSON array
+        let json_array = serde_json::to_string(&args.targets).unwrap_or_else(|_| "[]".to_string());
+
+        if let Ok(mut file) = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&github_output)
+        {
+            use std::io::Write;
+            let _ = writeln!(file, "targets={json_array}");
         }
     }
-    if let Some(ref cxx) = args.cxx {
-        let cxx_str = cxx.display().to_string();
-        if !cxx_str.is_empty() {
-            env.set_cxx(cxx_str);
-        }
+}
+
+/// Plan the file names of the artifacts collected into `--artifact-dir`, before building.
+/// Returns the plan when cargo-cross collects them itself (`--artifact-name`); otherwise
+/// only warns about files cargo's `--artifact-dir` would overwrite.
+async fn plan_artifact_collection(
+    args: &cargo_cross::Args,
+    host: &HostPlatform,
+) -> Result<Option<Vec<artifacts::PlannedArtifact>>> {
+    if !cargo_cross::cargo::produces_artifacts(args) || args.artifact_dir.is_none() {
+        return Ok(None);
     }
-    if let Some(ref ar) = args.ar {
-        let ar_str = ar.display().to_string();
-        if !ar_str.is_empty() {
-            env.set_ar(ar_str);
+    let metadata = match cargo_cross::metadata::cargo_metadata(args).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            color::log_debug(&format!("Skipping artifact collection planning: {e}"));
+            return Ok(None);
         }
+    };
+    let planned: Vec<(String, artifacts::Artifact)> = args
+        .targets
+        .iter()
+        .map(|target| {
+            if target == "host-tuple" {
+                host.triple.as_str()
+            } else {
+                target.as_str()
+            }
+        })
+        .flat_map(|target| {
+            artifacts::expected_artifacts(&metadata, args, target)
+                .artifacts
+                .into_iter()
+                .map(move |artifact| (target.to_string(), artifact))
+        })
+        .collect();
+
+    let plan = match args.artifact_name {
+        Some(ref template) => artifacts::plan_collection(&planned, template)?,
+        None => artifacts::plan_flat(&planned)?,
+    };
+    for entry in &plan {
+        color::log_info(&format!(
+            "Collect {}/{} ({}) as {}",
+            entry.artifact.package,
+            entry.artifact.path.display(),
+            entry.target,
+            entry.dest
+        ));
     }
-    if let Some(ref linker) = args.linker {
-        let linker_str = linker.display().to_string();
-        if !linker_str.is_empty() {
-            env.set_linker(linker_str);
+    if args.artifact_name.is_none() {
+        if cargo_cross::cargo::forwards_artifact_dir(args) {
+            return Ok(None);
         }
+        let needs_nightly =
+            args.command.as_str() == "build" && !cargo_cross::cargo::cargo_unstable_allowed(args);
+        color::log_info(if needs_nightly {
+            "--artifact-dir needs nightly cargo; cargo-cross copies the artifacts after the build"
+        } else {
+            "cargo-cross copies the artifacts into --artifact-dir after the build"
+        });
     }
+    Ok(Some(plan))
 }
 
-fn print_config(args: &cargo_cross::Args, _host: &HostPlatform) {
-    color::print_config_header();
-    println!("{}", color::format_config("Command", args.command.as_str()));
-    println!(
-        "{}",
-        color::format_config(
-            "Working directory",
-            &std::env::current_dir().map_or_else(|_| ".".to_string(), |p| p.display().to_string())
-        )
-    );
-
-    if let Some(ref package) = args.package {
-        println!("{}", color::format_config("Package", package));
-    }
-    if let Some(ref bin) = args.bin_target {
-        println!("{}", color::format_config("Binary target", bin));
+/// Log the choice `--crt-static auto` made for a target, and warn when musl is linked
+/// dynamically
+fn report_crt_static(target: &str, args: &cargo_cross::Args, decision: Option<CrtDecision>) {
+    if let (Some(CrtStatic::Auto), Some(decision)) = (args.crt_static, decision) {
+        color::log_info(&format!(
+            "C runtime for {}: {} ({}, --crt-static auto)",
+            color::yellow(target),
+            if decision.static_crt {
+                "static"
+            } else {
+                "dynamic"
+            },
+            decision.reason
+        ));
     }
-    if args.build_bins {
-        println!("{}", color::format_config("Build all binaries", "true"));
+    if let Some(warning) = dynamic_musl_warning(target, decision) {
+        color::log_warning_once(&format!("dynamic-musl-{target}"), &warning);
     }
-    if args.build_lib {
-        println!("{}", color::format_config("Build library", "true"));
+}
+
+/// List the binaries and libraries a build produced, noting targets skipped for missing
+/// features, and collect them into `--artifact-dir` when cargo-cross does the collection,
+/// splitting their debug info with `--debuginfo-artifacts`, along with `--collect-glob` matches.
+/// Returns the debug files written.
+async fn report_artifacts(
+    target: &str,
+    args: &cargo_cross::Args,
+    skip_target_arg: bool,
+    collection: Option<&[artifacts::PlannedArtifact]>,
+    cross_env: &cargo_cross::env::CrossEnv,
+    host: &HostPlatform,
+) -> Result<Vec<PathBuf>> {
+    let metadata = match cargo_cross::metadata::cargo_metadata(args).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            color::log_debug(&format!("Skipping artifact collection: {e}"));
+            return Ok(Vec::new());
+        }
+    };
+
+    let set = artifacts::expected_artifacts(&metadata, args, target);
+    for skipped in &set.skipped {
+        color::log_info(&format!(
+            "Skipped {} ({}): requires features {}",
+            color::yellow(&skipped.name),
+            skipped.package,
+            skipped.missing_features.join(", ")
+        ));
     }
-    if args.build_all_targets {
-        println!("{}", color::format_config("Build all targets", "true"));
+    let dir = artifacts::output_dir(&metadata, target, args, skip_target_arg);
+    let artifact_dir = args
+        .artifact_dir
+        .as_ref()
+        .map(|artifact_dir| cargo_cross::metadata::resolve_cargo_path(args, artifact_dir));
+    if let (Some(plan), Some(artifact_dir)) = (collection, artifact_dir.as_ref()) {
+        let mut collected = Vec::new();
+        for (source, dest) in artifacts::collect(plan, target, &dir, artifact_dir)? {
+            color::log_success(&format!(
+                "Artifact: {} -> {}",
+                color::cyan(&source.display().to_string()),
+                color::cyan(&dest.display().to_string())
+            ));
+            let kind = plan
+                .iter()
+                .find(|entry| entry.target == target && artifact_dir.join(&entry.dest) == dest)
+                .map_or(artifacts::ArtifactKind::Bin, |entry| entry.artifact.kind);
+            collected.push((kind, dest));
+        }
+        let mut artifacts = if args.debuginfo_artifacts {
+            debuginfo::split_artifacts(target, &collected, args.compress_debuginfo, cross_env, host)
+                .await
+        } else {
+            collected
+                .into_iter()
+                .map(|(_, dest)| (dest, None))
+                .collect()
+        };
+        let debug_files = artifacts
+            .iter()
+            .filter_map(|(_, debug)| debug.clone())
+            .collect();
+        artifacts.extend(collect_globbed(args, &dir, artifact_dir)?);
+        report::emit(&Event::ArtifactsCollected {
+            target: target.to_string(),
+            artifacts,
+        });
+        return Ok(debug_files);
+    }
+    // cargo copied its artifacts itself; the --collect-glob matches are still ours
+    if let Some(ref artifact_dir) = artifact_dir {
+        let artifacts = collect_globbed(args, &dir, artifact_dir)?;
+        if !artifacts.is_empty() {
+            report::emit(&Event::ArtifactsCollected {
+                target: target.to_string(),
+                artifacts,
+            });
+        }
     }
-    if args.workspace {
-        println!("{}", color::format_config("Building workspace", "true"));
+    for artifact in &set.artifacts {
+        let path = dir.join(&artifact.path);
+        if path.exists() {
+            color::log_success(&format!(
+                "Artifact: {}",
+                color::cyan(&path.display().to_string())
+            ));
+        }
     }
+    Ok(Vec::new())
+}
 
-    println!("{}", color::format_config("Profile", &args.profile));
-
-    if let Some(ref toolchain) = args.toolchain {
-        println!("{}", color::format_config("Toolchain", toolchain));
+/// Read the sizes of the images a bare-metal build linked and convert them with
+/// `--objcopy-format`; nothing for targets with an OS
+async fn process_firmware(
+    target: &str,
+    args: &cargo_cross::Args,
+    skip_target_arg: bool,
+    cross_env: &cargo_cross::env::CrossEnv,
+    host: &HostPlatform,
+) -> Result<Vec<firmware::Firmware>> {
+    if !cargo_cross::build_std::is_bare_metal(target) {
+        if args.objcopy_format.is_some() {
+            color::log_warning_once(
+                "objcopy-format-hosted",
+                "--objcopy-format only converts images of targets without an OS (*-none-*); \
+                 other targets are left alone",
+            );
+        }
+        return Ok(Vec::new());
     }
+    let metadata = match cargo_cross::metadata::cargo_metadata(args).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            color::log_debug(&format!("Skipping firmware sizes: {e}"));
+            return Ok(Vec::new());
+        }
+    };
+    let dir = artifacts::output_dir(&metadata, target, args, skip_target_arg);
+    let built: Vec<_> = artifacts::expected_artifacts(&metadata, args, target)
+        .artifacts
+        .into_iter()
+        .map(|artifact| (artifact.kind, dir.join(&artifact.path)))
+        .filter(|(_, path)| path.is_file())
+        .collect();
+    firmware::process_artifacts(target, &built, args, cross_env, host).await
+}
 
-    let targets_str = args.targets.join(", ");
-    println!("{}", color::format_config("Targets", &targets_str));
-
-    if args.glibc_version != cargo_cross::config::DEFAULT_GLIBC_VERSION {
-        println!(
-            "{}",
-            color::format_config("Glibc version", &args.glibc_version)
-        );
-    }
+/// Copy the `--collect-glob` matches in `output_dir` into `artifact_dir`, logging each copy
+fn collect_globbed(
+    args: &cargo_cross::Args,
+    output_dir: &Path,
+    artifact_dir: &Path,
+) -> Result<Vec<(PathBuf, Option<PathBuf>)>> {
+    let copied = artifacts::collect_globs(&args.collect_glob, output_dir, artifact_dir)?;
+    Ok(copied
+        .into_iter()
+        .map(|(source, dest)| {
+            color::log_success(&format!(
+                "Collected: {} -> {}",
+                color::cyan(&source.display().to_string()),
+                color::cyan(&dest.display().to_string())
+            ));
+            (dest, None)
+        })
+        .collect())
+}
 
-    if let Some(ref features) = args.features {
-        println!("{}", color::format_config("Features", features));
-    }
-    if args.no_default_features {
-        println!("{}", color::format_config("No default features", "true"));
-    }
-    if args.all_features {
-        println!("{}", color::format_config("All features", "true"));
+/// Copy a target's criterion estimates to its results directory and label how they were run
+fn collect_bench_results(
+    target: &str,
+    args: &cargo_cross::Args,
+    cross_env: &cargo_cross::env::CrossEnv,
+) {
+    let runner = cargo_cross::plan::RunnerKind::from_runner(
+        cross_env.runner.as_deref(),
+        args.runner.is_some(),
+    );
+    let results = bench::results_dir(target, args);
+    let collected = bench::collect_estimates(&bench::criterion_home(target, args), &results)
+        .and_then(|benchmarks| {
+            let bencher =
+                bench::wants_bencher_output(args) && results.join(bench::BENCHER_FILE).exists();
+            bench::write_summary(&results, target, runner, &benchmarks, bencher)?;
+            Ok(benchmarks)
+        });
+    match collected {
+        Ok(benchmarks) => {
+            color::log_success(&format!(
+                "Collected {} benchmark results to {}",
+                color::yellow(&benchmarks.len().to_string()),
+                color::cyan(&results.display().to_string())
+            ));
+            if runner.is_emulated() {
+                color::log_warning_once(
+                    "bench-emulated",
+                    &format!(
+                    "Benchmarks for {} ran under {} emulation; timings are not representative of real hardware",
+                    color::yellow(target),
+                    runner.as_str()
+                ));
+            }
+        }
+        Err(e) => color::log_warning(&format!("Failed to collect benchmark results: {e}")),
     }
+}
 
-    if !args.rustflags.is_empty() {
-        println!(
-            "{}",
-            color::format_config("Additional rustflags", &args.rustflags.join(" "))
-        );
-    }
+/// Append a target's cross environment to `GITHUB_ENV` / `GITHUB_PATH` (`--export-github-env`)
+fn export_target_env(
+    target: &str,
+    args: &cargo_cross::Args,
+    cross_env: &cargo_cross::env::CrossEnv,
+    host: &HostPlatform,
+) -> Result<()> {
+    let is_host_build = target == "host-tuple";
+    let actual_target = if is_host_build {
+        host.triple.as_str()
+    } else {
+        target
+    };
+    let skip_target_arg = args.skip_target_arg(target, &host.triple);
+    let env = build_cargo_env(actual_target, args, cross_env, host, skip_target_arg)?.into_vars();
+    cargo_cross::github::export_github_env(&env, &cross_env.path)?;
+    color::log_success(&format!(
+        "Exported environment for {} to GITHUB_ENV",
+        color::yellow(actual_target)
+    ));
+    Ok(())
+}
 
-    if let Some(ref build_std) = args.build_std {
-        let display = if build_std == "true" {
-            "true".to_string()
-        } else {
-            build_std.clone()
-        };
-        println!("{}", color::format_config("Build std", &display));
+/// Summary table of the sccache results per target, empty when sccache was not used
+fn format_sccache_summary(targets: &[TargetMetrics]) -> Vec<String> {
+    let rows: Vec<_> = targets
+        .iter()
+        .filter_map(|metrics| Some((metrics.target.as_str(), metrics.sccache?)))
+        .collect();
+    if rows.is_empty() {
+        return Vec::new();
     }
+    let width = rows
+        .iter()
+        .map(|(target, _)| target.len())
+        .max()
+        .unwrap_or(0);
+    std::iter::once("sccache per target:".to_string())
+        .chain(rows.iter().map(|(target, delta)| {
+            let reset = if delta.reset {
+                " (server restarted)"
+            } else {
+                ""
+            };
+            format!("  {target:<width$}  {}{reset}", delta.describe())
+        }))
+        .collect()
 }
 
-fn set_github_output(args: &cargo_cross::Args) {
-    if let Ok(github_output) = std::env::var("GITHUB_OUTPUT") {
-        // Convert targets to JSON array
-        let json_array = serde_json::to_string(&args.targets).unwrap_or_else(|_| "[]".to_string());
+/// Summary lines of `run --sequential-runs`: each target with its program's exit code
+fn format_run_codes(codes: &[(String, i32)]) -> Vec<String> {
+    let width = codes
+        .iter()
+        .map(|(target, _)| target.len())
+        .max()
+        .unwrap_or(0);
+    codes
+        .iter()
+        .map(|(target, code)| format!("{target:<width$}  exit code {code}"))
+        .collect()
+}
 
-        if let Ok(mut file) = std::fs::OpenOptions::new()
-            .append(true)
-            .open(&github_output)
-        {
-            use std::io::Write;
-            let _ = writeln!(file, "targets={json_array}");
-        }
+fn format_retries(retries: u32) -> String {
+    if retries == 1 {
+        "1 transient retry".to_string()
+    } else {
+        format!("{retries} transient retries")
     }
 }
 
@@ -673,21 +1672,7 @@ fn format_cli_command(command: &[String]) -> String {
         .join(" ")
 }
 
-fn print_env_vars(env: &std::collections::HashMap<String, String>) {
-    if env.is_empty() {
-        return;
-    }
-
-    color::print_env_header();
-    for (key, value) in sorted_env(env) {
-        println!("{}", color::format_env(&key, &value));
-    }
-}
-
-fn print_setup_env(
-    env: &std::collections::HashMap<String, String>,
-    format: SetupOutputFormat,
-) -> Result<()> {
+fn print_setup_env(env: &EnvMap, format: SetupOutputFormat) -> Result<()> {
     let rendered = render_setup_env(env, format)?;
     if !rendered.is_empty() {
         println!("{rendered}");
@@ -695,10 +1680,7 @@ fn print_setup_env(
     Ok(())
 }
 
-fn render_setup_env(
-    env: &std::collections::HashMap<String, String>,
-    format: SetupOutputFormat,
-) -> Result<String> {
+fn render_setup_env(env: &EnvMap, format: SetupOutputFormat) -> Result<String> {
     let mut rendered = Vec::new();
 
     match resolve_setup_output_format(format) {
@@ -724,35 +1706,48 @@ fn render_setup_env(
             }
         }
         SetupOutputFormat::Json => {
-            return Ok(serde_json::to_string_pretty(&sorted_env(env))?);
+            let entries: serde_json::Map<String, serde_json::Value> = env
+                .sorted()
+                .into_iter()
+                .map(|(key, entry)| {
+                    (
+                        key.to_string(),
+                        serde_json::json!({
+                            "value": entry.value,
+                            "source": entry.source.as_str(),
+                        }),
+                    )
+                })
+                .collect();
+            return Ok(serde_json::to_string_pretty(&entries)?);
         }
     }
 
     Ok(rendered.join("\n"))
 }
 
-fn write_setup_github_env(env: &std::collections::HashMap<String, String>) -> Result<()> {
-    let Ok(github_env) = std::env::var("GITHUB_ENV") else {
-        return Ok(());
+/// Write the setup environment to `GITHUB_ENV` and its `PATH` additions to `GITHUB_PATH`,
+/// skipping what an earlier setup already wrote. `None` outside GitHub Actions.
+fn write_setup_github_env(
+    env: &EnvMap,
+    paths: &[PathBuf],
+) -> Result<Option<cargo_cross::github::GithubExport>> {
+    let Some(github_env) = std::env::var_os("GITHUB_ENV") else {
+        return Ok(None);
     };
-
-    let mut file = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(github_env)?;
-
-    use std::io::Write;
-    for (key, value) in sorted_env(env) {
-        writeln!(file, "{key}<<__CARGO_CROSS_EOF__")?;
-        writeln!(file, "{value}")?;
-        writeln!(file, "__CARGO_CROSS_EOF__")?;
-    }
-
-    Ok(())
+    let github_path = std::env::var_os("GITHUB_PATH");
+    cargo_cross::github::write_github_env(
+        Path::new(&github_env),
+        github_path.as_deref().map(Path::new),
+        &env.clone().into_vars(),
+        paths,
+        &std::env::vars().collect(),
+    )
+    .map(Some)
 }
 
-fn sorted_env(env: &std::collections::HashMap<String, String>) -> BTreeMap<String, String> {
-    env.iter()
+fn sorted_env(env: &EnvMap) -> BTreeMap<String, String> {
+    env.vars()
         .map(|(key, value)| (key.clone(), value.clone()))
         .collect()
 }
@@ -870,12 +1865,15 @@ impl Drop for LogSilenceGuard {
 #[cfg(test)]
 mod tests {
     use super::{
-        cargo_subcommand_for_exec, detect_setup_shell, prepare_exec_command, render_setup_env,
-        resolve_setup_output_format_with_shells, write_setup_github_env,
+        cargo_subcommand_for_exec, detect_setup_shell, format_run_codes, format_sccache_summary,
+        prepare_exec_command, render_setup_env, resolve_setup_output_format_with_shells,
+        write_setup_github_env,
     };
     use cargo_cross::cli::SetupOutputFormat;
+    use cargo_cross::env::{EnvMap, EnvSource};
     use std::collections::HashMap;
     use std::ffi::OsString;
+    use std::path::PathBuf;
 
     #[test]
     fn prepare_exec_command_injects_target_for_cargo() {
@@ -1017,6 +2015,54 @@ mod tests {
         assert_eq!(cargo_subcommand_for_exec(&command), Some("clippy"));
     }
 
+    #[test]
+    fn format_sccache_summary_lists_targets_with_stats() {
+        use cargo_cross::metrics::TargetMetrics;
+        use cargo_cross::sccache::{CacheCounters, CacheDelta};
+
+        let with_stats = |target: &str, hits: u64, reset: bool| TargetMetrics {
+            target: target.to_string(),
+            sccache: Some(CacheDelta {
+                counters: CacheCounters {
+                    hits,
+                    misses: 1,
+                    errors: 0,
+                },
+                reset,
+            }),
+            ..TargetMetrics::default()
+        };
+        assert!(format_sccache_summary(&[TargetMetrics::default()]).is_empty());
+        assert_eq!(
+            format_sccache_summary(&[
+                with_stats("aarch64-unknown-linux-musl", 3, false),
+                TargetMetrics::default(),
+                with_stats("x86_64-pc-windows-gnu", 0, true),
+            ]),
+            [
+                "sccache per target:",
+                "  aarch64-unknown-linux-musl  3 hits, 1 misses, 0 errors (75% hit rate)",
+                "  x86_64-pc-windows-gnu       0 hits, 1 misses, 0 errors (0% hit rate) (server restarted)",
+            ]
+        );
+    }
+
+    #[test]
+    fn format_run_codes_aligns_targets() {
+        let codes = [
+            ("aarch64-unknown-linux-musl".to_string(), 0),
+            ("x86_64-pc-windows-gnu".to_string(), 101),
+        ];
+        assert_eq!(
+            format_run_codes(&codes),
+            [
+                "aarch64-unknown-linux-musl  exit code 0",
+                "x86_64-pc-windows-gnu       exit code 101",
+            ]
+        );
+        assert!(format_run_codes(&[]).is_empty());
+    }
+
     #[test]
     fn resolve_setup_output_format_falls_back_to_bash() {
         assert_eq!(
@@ -1050,31 +2096,57 @@ mod tests {
     }
 
     #[test]
-    fn write_setup_github_env_appends_multiline_entries() {
-        let github_env =
-            std::env::temp_dir().join(format!("cargo-cross-github-env-{}.txt", std::process::id()));
-        let original = std::env::var_os("GITHUB_ENV");
+    fn write_setup_github_env_is_idempotent() {
+        let dir =
+            std::env::temp_dir().join(format!("cargo-cross-setup-github-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let github_env = dir.join("env");
+        let github_path = dir.join("path");
+        let original_env = std::env::var_os("GITHUB_ENV");
+        let original_path = std::env::var_os("GITHUB_PATH");
         std::env::set_var("GITHUB_ENV", &github_env);
+        std::env::set_var("GITHUB_PATH", &github_path);
 
         let mut env = HashMap::new();
         env.insert(
             "CC_x86_64_pc_windows_gnu".to_string(),
             "x86_64-w64-mingw32-gcc".to_string(),
         );
+        env.insert(
+            "CFLAGS_x86_64_pc_windows_gnu".to_string(),
+            "-O2\n-g".to_string(),
+        );
         env.insert(
             "PATH".to_string(),
             "/tmp/toolchain/bin:/usr/bin".to_string(),
         );
-        write_setup_github_env(&env).unwrap();
+        let env = EnvMap::from_layer(env, EnvSource::CrossEnv);
+        let paths = [PathBuf::from("/tmp/toolchain/bin")];
+        let export = write_setup_github_env(&env, &paths).unwrap().unwrap();
+        assert_eq!(
+            export.variables,
+            ["CC_x86_64_pc_windows_gnu", "CFLAGS_x86_64_pc_windows_gnu"]
+        );
+        assert_eq!(export.paths, paths);
 
         let contents = std::fs::read_to_string(&github_env).unwrap();
-        assert!(contents.contains("CC_x86_64_pc_windows_gnu<<__CARGO_CROSS_EOF__"));
-        assert!(contents.contains("x86_64-w64-mingw32-gcc"));
-        assert!(contents.contains("PATH<<__CARGO_CROSS_EOF__"));
-        assert!(contents.contains("/tmp/toolchain/bin:/usr/bin"));
+        assert!(contents.contains("CC_x86_64_pc_windows_gnu=x86_64-w64-mingw32-gcc\n"));
+        assert!(contents.contains("CFLAGS_x86_64_pc_windows_gnu<<__CARGO_CROSS_EOF__\n-O2\n-g\n"));
+        // PATH additions go to GITHUB_PATH instead of freezing the whole PATH
+        assert!(!contents.contains("PATH<<") && !contents.contains("\nPATH="));
+        assert_eq!(
+            std::fs::read_to_string(&github_path).unwrap(),
+            "/tmp/toolchain/bin\n"
+        );
 
-        let _ = std::fs::remove_file(&github_env);
-        restore_env_var("GITHUB_ENV", original);
+        // A second setup finds everything in place and writes nothing
+        let again = write_setup_github_env(&env, &paths).unwrap().unwrap();
+        assert!(again.variables.is_empty() && again.paths.is_empty());
+        assert_eq!(std::fs::read_to_string(&github_env).unwrap(), contents);
+
+        let _ = std::fs::remove_dir_all(&dir);
+        restore_env_var("GITHUB_ENV", original_env);
+        restore_env_var("GITHUB_PATH", original_path);
     }
 
     #[test]
@@ -1089,7 +2161,11 @@ mod tests {
             "/tmp/toolchain/bin:/usr/bin".to_string(),
         );
 
-        let rendered = render_setup_env(&env, SetupOutputFormat::Bash).unwrap();
+        let rendered = render_setup_env(
+            &EnvMap::from_layer(env, EnvSource::CrossEnv),
+            SetupOutputFormat::Bash,
+        )
+        .unwrap();
         assert!(rendered.contains("export CC_x86_64_unknown_linux_gnu=clang"));
         assert!(rendered.contains("export PATH=/tmp/toolchain/bin:/usr/bin"));
     }
@@ -1102,7 +2178,11 @@ mod tests {
             "/tmp/toolchain/bin:/usr/bin".to_string(),
         );
 
-        let rendered = render_setup_env(&env, SetupOutputFormat::Fish).unwrap();
+        let rendered = render_setup_env(
+            &EnvMap::from_layer(env, EnvSource::CrossEnv),
+            SetupOutputFormat::Fish,
+        )
+        .unwrap();
         assert_eq!(rendered, "set -gx PATH -- \"/tmp/toolchain/bin:/usr/bin\";");
     }
 
@@ -1114,8 +2194,14 @@ mod tests {
             "/tmp/toolchain/bin:/usr/bin".to_string(),
         );
 
-        let rendered = render_setup_env(&env, SetupOutputFormat::Json).unwrap();
-        assert!(rendered.contains("\"PATH\": \"/tmp/toolchain/bin:/usr/bin\""));
+        let rendered = render_setup_env(
+            &EnvMap::from_layer(env, EnvSource::CrossEnv),
+            SetupOutputFormat::Json,
+        )
+        .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(json["PATH"]["value"], "/tmp/toolchain/bin:/usr/bin");
+        assert_eq!(json["PATH"]["source"], "cross-env");
     }
 
     #[test]
@@ -1123,7 +2209,11 @@ mod tests {
         let mut env = HashMap::new();
         env.insert("PATH".to_string(), "C:\\toolchain\\bin".to_string());
 
-        let rendered = render_setup_env(&env, SetupOutputFormat::Powershell).unwrap();
+        let rendered = render_setup_env(
+            &EnvMap::from_layer(env, EnvSource::CrossEnv),
+            SetupOutputFormat::Powershell,
+        )
+        .unwrap();
         assert_eq!(rendered, "$Env:PATH = 'C:\\toolchain\\bin'");
     }
 
@@ -1135,7 +2225,11 @@ mod tests {
             "C:\\toolchain\\bin;%USERPROFILE%".to_string(),
         );
 
-        let rendered = render_setup_env(&env, SetupOutputFormat::Cmd).unwrap();
+        let rendered = render_setup_env(
+            &EnvMap::from_layer(env, EnvSource::CrossEnv),
+            SetupOutputFormat::Cmd,
+        )
+        .unwrap();
         assert_eq!(rendered, "set \"PATH=C:\\toolchain\\bin;%%USERPROFILE%%\"");
     }
 
@@ -0,0 +1,796 @@
+//! Cached `cargo metadata` queries
+
+use crate::cli::Args;
+use crate::error::{run_command_output, CrossError, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::process::Command as TokioCommand;
+
+/// A build target (`[lib]`, `[[bin]]`, `[[example]]`, ...) of a package
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataTarget {
+    /// Target name as written in Cargo.toml
+    pub name: String,
+    /// Target kinds such as `bin`, `lib`, `cdylib` or `example`
+    pub kind: Vec<String>,
+    /// Crate types produced by the target
+    pub crate_types: Vec<String>,
+    /// Features that must be enabled for the target to be built
+    pub required_features: Vec<String>,
+}
+
+/// A workspace package
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataPackage {
+    /// Package id
+    pub id: String,
+    /// Package name
+    pub name: String,
+    /// Build targets of the package
+    pub targets: Vec<MetadataTarget>,
+    /// `[features]` table: feature name to the features and dependencies it enables
+    pub features: BTreeMap<String, Vec<String>>,
+}
+
+/// The parts of `cargo metadata --no-deps` output that cargo-cross uses
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CargoMetadata {
+    /// Root directory of the workspace
+    pub workspace_root: PathBuf,
+    /// Cargo's resolved target directory
+    pub target_directory: PathBuf,
+    /// Workspace packages
+    pub packages: Vec<MetadataPackage>,
+    /// Package ids of all workspace members
+    pub workspace_members: Vec<String>,
+    /// Package ids of the workspace default members
+    pub workspace_default_members: Vec<String>,
+}
+
+/// Strings of a JSON array, empty if `value` is not an array
+fn string_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl MetadataTarget {
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            name: value["name"].as_str()?.to_string(),
+            kind: string_list(&value["kind"]),
+            crate_types: string_list(&value["crate_types"]),
+            required_features: string_list(&value["required-features"]),
+        })
+    }
+}
+
+impl MetadataPackage {
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            id: value["id"].as_str()?.to_string(),
+            name: value["name"].as_str()?.to_string(),
+            targets: value["targets"]
+                .as_array()
+                .map(|targets| {
+                    targets
+                        .iter()
+                        .filter_map(MetadataTarget::from_json)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            features: value["features"]
+                .as_object()
+                .map(|features| {
+                    features
+                        .iter()
+                        .map(|(name, enables)| (name.clone(), string_list(enables)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl CargoMetadata {
+    /// Parse the JSON printed by `cargo metadata --format-version 1`
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(json)?;
+        let packages = value["packages"]
+            .as_array()
+            .map(|packages| {
+                packages
+                    .iter()
+                    .filter_map(MetadataPackage::from_json)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            workspace_root: value["workspace_root"].as_str().unwrap_or_default().into(),
+            target_directory: value["target_directory"]
+                .as_str()
+                .unwrap_or_default()
+                .into(),
+            packages,
+            workspace_members: string_list(&value["workspace_members"]),
+            workspace_default_members: string_list(&value["workspace_default_members"]),
+        })
+    }
+
+    /// Workspace package with the given id
+    #[must_use]
+    pub fn package(&self, id: &str) -> Option<&MetadataPackage> {
+        self.packages.iter().find(|pkg| pkg.id == id)
+    }
+
+    /// Names of the workspace default members.
+    ///
+    /// Falls back to all workspace members when cargo does not report
+    /// default members (cargo before 1.71).
+    #[must_use]
+    pub fn default_member_names(&self) -> Vec<String> {
+        let ids = if self.workspace_default_members.is_empty() {
+            &self.workspace_members
+        } else {
+            &self.workspace_default_members
+        };
+        ids.iter()
+            .filter_map(|id| self.package(id))
+            .map(|pkg| pkg.name.clone())
+            .collect()
+    }
+}
+
+/// Directories of the workspace a build runs in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspacePaths {
+    /// Absolute directory cargo runs in: `-C` resolved against the current directory
+    pub cwd: PathBuf,
+    /// Workspace root reported by `cargo metadata`
+    pub root: PathBuf,
+    /// Target directory cargo uses when `--target-dir` is not given
+    pub target_dir: PathBuf,
+}
+
+/// Absolute directory cargo runs in: `-C` resolved against the current directory
+#[must_use]
+pub fn effective_cwd(args: &Args) -> PathBuf {
+    if let Some(ref workspace) = args.workspace_paths {
+        return workspace.cwd.clone();
+    }
+    let current = std::env::current_dir().unwrap_or_default();
+    match args.cargo_cwd {
+        Some(ref cwd) => current.join(cwd),
+        None => current,
+    }
+}
+
+/// Make a path given on the command line absolute the way cargo does: relative paths are
+/// relative to the directory cargo runs in, not to where cargo-cross was started
+#[must_use]
+pub fn resolve_cargo_path(args: &Args, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        effective_cwd(args).join(path)
+    }
+}
+
+/// Resolve the workspace directories once, with the same `-C` and `--manifest-path` the
+/// build uses, and store them in `args.workspace_paths`. Leaves it unset when `cargo metadata`
+/// fails, so cargo itself reports the problem.
+pub async fn resolve_workspace(args: &mut Args) {
+    match cargo_metadata(args).await {
+        Ok(metadata) => {
+            args.workspace_paths = Some(WorkspacePaths {
+                cwd: effective_cwd(args),
+                root: metadata.workspace_root.clone(),
+                target_dir: metadata.target_directory.clone(),
+            });
+        }
+        Err(e) => crate::color::log_debug(&format!("Could not resolve the workspace: {e}")),
+    }
+}
+
+/// Profiles cargo always defines
+pub const BUILTIN_PROFILES: &[&str] = &["dev", "release", "test", "bench"];
+
+/// First key of a dotted TOML key, unquoted: `"my-profile".opt-level` gives `my-profile`
+fn first_toml_key(key: &str) -> Option<&str> {
+    let key = key.trim_start();
+    if let Some(quote) = key.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        let rest = &key[1..];
+        return rest.find(quote).map(|end| &rest[..end]);
+    }
+    let end = key
+        .find(|c: char| c == '.' || c == '=' || c == ']' || c.is_whitespace())
+        .unwrap_or(key.len());
+    (end > 0).then(|| &key[..end])
+}
+
+/// Key path after `profile.`, if `key` starts with it
+fn after_profile_key(key: &str) -> Option<&str> {
+    key.trim_start()
+        .strip_prefix("profile")?
+        .trim_start()
+        .strip_prefix('.')
+}
+
+/// Names of the profiles a TOML document (a manifest or a cargo config) defines:
+/// `[profile.NAME]` tables, also nested ones like `[profile.NAME.package.foo]`,
+/// keys of a `[profile]` table and `profile.NAME.key = value` dotted keys.
+/// This is a line-based scan, not a TOML parser.
+#[must_use]
+pub fn profiles_in_toml(content: &str) -> Vec<String> {
+    let mut profiles = Vec::new();
+    let mut table = String::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let name = if let Some(header) = line.strip_prefix('[') {
+            table = header.trim_start_matches('[').to_string();
+            after_profile_key(&table).and_then(first_toml_key)
+        } else if !line.contains('=') {
+            None
+        } else if table.trim_end_matches(']').trim() == "profile" {
+            first_toml_key(line)
+        } else if table.is_empty() {
+            after_profile_key(line).and_then(first_toml_key)
+        } else {
+            None
+        };
+        if let Some(name) = name {
+            if !profiles.iter().any(|p| p == name) {
+                profiles.push(name.to_string());
+            }
+        }
+    }
+    profiles
+}
+
+/// Cargo config files that apply to a build in `cwd`, most specific first
+fn cargo_config_files(cwd: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = cwd.ancestors().map(|dir| dir.join(".cargo")).collect();
+    if let Some(home) = std::env::var_os("CARGO_HOME") {
+        dirs.push(PathBuf::from(home));
+    } else if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))
+    {
+        dirs.push(PathBuf::from(home).join(".cargo"));
+    }
+    dirs.iter()
+        .flat_map(|dir| [dir.join("config.toml"), dir.join("config")])
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Whether `CARGO_PROFILE_<NAME>_*` variables define the profile
+fn profile_in_env(profile: &str) -> bool {
+    let prefix = format!(
+        "CARGO_PROFILE_{}_",
+        profile.to_ascii_uppercase().replace('-', "_")
+    );
+    std::env::vars_os().any(|(name, _)| name.to_string_lossy().starts_with(&prefix))
+}
+
+/// Fail before any toolchain is downloaded if `--profile` names a profile that neither the
+/// workspace manifest, a cargo config nor `--config` defines. Skipped when the workspace
+/// could not be resolved.
+pub fn check_profile(args: &Args) -> Result<()> {
+    let profile = args.profile.as_str();
+    if profile.is_empty() || BUILTIN_PROFILES.contains(&profile) || profile_in_env(profile) {
+        return Ok(());
+    }
+    let Some(ref workspace) = args.workspace_paths else {
+        return Ok(());
+    };
+
+    let mut sources: Vec<String> = std::iter::once(workspace.root.join("Cargo.toml"))
+        .chain(cargo_config_files(&workspace.cwd))
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .collect();
+    for config in &args.cargo_config {
+        let (_, config) = crate::cli::split_target_config(config);
+        if config.contains('=') {
+            sources.push(config.to_string());
+        } else if let Ok(content) = std::fs::read_to_string(workspace.cwd.join(config)) {
+            sources.push(content);
+        }
+    }
+
+    let mut available: Vec<String> = BUILTIN_PROFILES.iter().map(ToString::to_string).collect();
+    for source in &sources {
+        for name in profiles_in_toml(source) {
+            if name == profile {
+                return Ok(());
+            }
+            if !available.contains(&name) {
+                available.push(name);
+            }
+        }
+    }
+    Err(CrossError::UnknownProfile {
+        profile: profile.to_string(),
+        available: available.join(", "),
+    })
+}
+
+/// Workspace identity used as the cache key: toolchain, working directory and manifest
+type MetadataKey = (Option<String>, Option<PathBuf>, Option<PathBuf>);
+
+static METADATA_CACHE: LazyLock<Mutex<HashMap<MetadataKey, Arc<CargoMetadata>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Build the `cargo metadata` invocation for the workspace selected by `args`: the
+/// workspace packages only, or with `with_deps` the dependency graph resolved for the
+/// features the build selects
+fn build_metadata_command(args: &Args, with_deps: bool) -> TokioCommand {
+    let mut cmd = TokioCommand::new("cargo");
+    if let Some(ref toolchain) = args.toolchain {
+        cmd.arg(format!("+{toolchain}"));
+    }
+    cmd.args(["metadata", "--format-version", "1"]);
+    if with_deps {
+        if let Some(ref features) = args.features {
+            cmd.args(["--features", features]);
+        }
+        for (set, flag) in [
+            (args.all_features, "--all-features"),
+            (args.no_default_features, "--no-default-features"),
+            (args.locked, "--locked"),
+            (args.offline, "--offline"),
+            (args.frozen, "--frozen"),
+        ] {
+            if set {
+                cmd.arg(flag);
+            }
+        }
+    } else {
+        cmd.arg("--no-deps");
+    }
+    if let Some(ref manifest) = args.manifest_path {
+        cmd.arg("--manifest-path").arg(manifest);
+    }
+    if let Some(ref cwd) = args.cargo_cwd {
+        cmd.current_dir(cwd);
+    }
+    cmd
+}
+
+/// Run `cargo metadata` for the workspace selected by `args`.
+///
+/// The result is cached per workspace, so every caller within one run
+/// (package selection, artifact collection) shares a single invocation.
+pub async fn cargo_metadata(args: &Args) -> Result<Arc<CargoMetadata>> {
+    let key = (
+        args.toolchain.clone(),
+        args.cargo_cwd.clone(),
+        args.manifest_path.clone(),
+    );
+    if let Some(cached) = METADATA_CACHE
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .get(&key)
+    {
+        return Ok(Arc::clone(cached));
+    }
+
+    let mut cmd = build_metadata_command(args, false);
+    let output = run_command_output(&mut cmd, "cargo").await?;
+    if !output.status.success() {
+        return Err(CrossError::CommandFailed {
+            command: format!(
+                "cargo metadata: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+    let metadata = Arc::new(CargoMetadata::from_json(&String::from_utf8_lossy(
+        &output.stdout,
+    ))?);
+    METADATA_CACHE
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .insert(key, Arc::clone(&metadata));
+    Ok(metadata)
+}
+
+/// Contents of the workspace's `Cargo.lock` (or `--lockfile-path`), if there is one
+pub async fn workspace_lockfile(args: &Args) -> Option<String> {
+    let lockfile = match args.lockfile_path {
+        Some(ref path) => path.clone(),
+        None => cargo_metadata(args)
+            .await
+            .ok()?
+            .workspace_root
+            .join("Cargo.lock"),
+    };
+    tokio::fs::read_to_string(lockfile).await.ok()
+}
+
+/// Build-dependencies that mean a build script compiles C
+pub const C_BUILD_CRATES: &[&str] = &["cc", "cmake", "bindgen"];
+
+/// Names of the packages in the output of `cargo metadata` (with dependencies) that have
+/// one of [`C_BUILD_CRATES`] among their build-dependencies, sorted and deduplicated
+pub fn c_building_packages(json: &str) -> Result<Vec<String>> {
+    let value: Value = serde_json::from_str(json)?;
+    let names: HashMap<&str, &str> = value["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|package| Some((package["id"].as_str()?, package["name"].as_str()?)))
+        .collect();
+    let builds_c = |dep: &Value| {
+        let is_build = dep["dep_kinds"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(|kind| kind["kind"] == "build");
+        is_build
+            && dep["pkg"]
+                .as_str()
+                .and_then(|id| names.get(id))
+                .is_some_and(|name| C_BUILD_CRATES.contains(name))
+    };
+    let mut packages: Vec<String> = value["resolve"]["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|node| node["deps"].as_array().into_iter().flatten().any(builds_c))
+        .filter_map(|node| {
+            names
+                .get(node["id"].as_str()?)
+                .map(|name| (*name).to_string())
+        })
+        .collect();
+    packages.sort();
+    packages.dedup();
+    Ok(packages)
+}
+
+/// Names of the packages in the resolve graph of the output of `cargo metadata` (with
+/// dependencies), sorted and deduplicated
+pub fn resolved_package_names(json: &str) -> Result<Vec<String>> {
+    let value: Value = serde_json::from_str(json)?;
+    let names: HashMap<&str, &str> = value["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|package| Some((package["id"].as_str()?, package["name"].as_str()?)))
+        .collect();
+    let mut packages: Vec<String> = value["resolve"]["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|node| names.get(node["id"].as_str()?))
+        .map(|name| (*name).to_string())
+        .collect();
+    packages.sort();
+    packages.dedup();
+    Ok(packages)
+}
+
+/// Packages built for `target` with the features of the build: the dependency graph
+/// filtered to the target's platform, without optional dependencies that stay off
+pub async fn target_package_names(args: &Args, target: &str) -> Result<Vec<String>> {
+    let mut cmd = build_metadata_command(args, true);
+    cmd.args(["--filter-platform", target]);
+    let output = run_command_output(&mut cmd, "cargo").await?;
+    if !output.status.success() {
+        return Err(CrossError::CommandFailed {
+            command: format!(
+                "cargo metadata: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+    resolved_package_names(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Settle `--no-link-toolchain` for `check`: it stays set only when no build script in the
+/// crate graph compiles C. Otherwise, or when the graph cannot be read, it is cleared with
+/// a note and the full toolchain setup runs.
+pub async fn resolve_link_toolchain(args: &mut Args) {
+    if !args.no_link_toolchain || args.no_toolchain_setup || args.command.as_str() != "check" {
+        return;
+    }
+    let mut cmd = build_metadata_command(args, true);
+    let packages = match run_command_output(&mut cmd, "cargo").await {
+        Ok(output) if output.status.success() => {
+            c_building_packages(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(output) => Err(CrossError::CommandFailed {
+            command: format!(
+                "cargo metadata: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        }),
+        Err(e) => Err(e),
+    };
+    match packages {
+        Ok(packages) if packages.is_empty() => crate::color::log_info(
+            "--no-link-toolchain: no build script compiles C, skipping the C toolchain and runner setup",
+        ),
+        Ok(packages) => {
+            crate::color::log_info(&format!(
+                "--no-link-toolchain: build scripts of {} compile C, setting up the toolchain anyway",
+                packages.join(", ")
+            ));
+            args.no_link_toolchain = false;
+        }
+        Err(e) => {
+            crate::color::log_info(&format!(
+                "--no-link-toolchain: could not read the crate graph ({e}), setting up the toolchain anyway"
+            ));
+            args.no_link_toolchain = false;
+        }
+    }
+}
+
+/// Replace `--default-members-only` with explicit `-p` selections.
+///
+/// Does nothing unless the flag is set; errors if the workspace reports no members.
+pub async fn resolve_default_members(args: &mut Args) -> Result<()> {
+    if !args.default_members_only || !args.package.is_empty() {
+        return Ok(());
+    }
+    let names = cargo_metadata(args).await?.default_member_names();
+    if names.is_empty() {
+        return Err(CrossError::InvalidArgument(
+            "--default-members-only: cargo metadata reported no workspace members".to_string(),
+        ));
+    }
+    args.package = names;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_in_toml() {
+        let manifest = r#"
+[package]
+name = "app"
+
+[profile.release]
+lto = true
+
+[profile.dist]
+inherits = "release"
+
+[profile.dist.package.zlib]
+opt-level = 3
+
+[profile."ci-fast"]
+inherits = "dev"
+
+[profile]
+small = { inherits = "release", opt-level = "z" }
+bench-lto.inherits = "bench"
+
+[dependencies]
+profile = "1"
+"#;
+        assert_eq!(
+            profiles_in_toml(manifest),
+            ["release", "dist", "ci-fast", "small", "bench-lto"]
+        );
+        assert_eq!(
+            profiles_in_toml("profile.quick.inherits = \"dev\"\nprofile.quick.debug = 0"),
+            ["quick"]
+        );
+        assert!(profiles_in_toml("[workspace]\nmembers = [\"profile\"]").is_empty());
+    }
+
+    #[test]
+    fn test_check_profile() {
+        let dir = std::env::temp_dir().join(format!("cargo-cross-profiles-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"app\"\n\n[profile.dist]\ninherits = \"release\"\n",
+        )
+        .unwrap();
+        let args = |profile: &str, cargo_config: Vec<String>| Args {
+            cross_compiler_dir: dir.join("toolchains"),
+            ..crate::cli::test_target_args(
+                "",
+                &["aarch64-unknown-linux-musl"],
+                crate::cli::BuildArgs {
+                    profile: profile.to_string(),
+                    cargo_config,
+                    workspace_paths: Some(WorkspacePaths {
+                        cwd: dir.clone(),
+                        root: dir.clone(),
+                        target_dir: dir.join("target"),
+                    }),
+                    ..crate::cli::BuildArgs::default()
+                },
+            )
+        };
+
+        assert!(check_profile(&args("release", Vec::new())).is_ok());
+        assert!(check_profile(&args("dev", Vec::new())).is_ok());
+        assert!(check_profile(&args("dist", Vec::new())).is_ok());
+        assert!(check_profile(&args(
+            "quick",
+            vec!["profile.quick.inherits=\"dev\"".to_string()]
+        ))
+        .is_ok());
+
+        let err = check_profile(&args("releas", Vec::new())).unwrap_err();
+        assert!(matches!(
+            err,
+            CrossError::UnknownProfile { ref profile, ref available }
+                if profile == "releas" && available == "dev, release, test, bench, dist"
+        ));
+
+        // Without a resolved workspace cargo reports the problem itself
+        let mut unresolved = args("releas", Vec::new());
+        unresolved.workspace_paths = None;
+        assert!(check_profile(&unresolved).is_ok());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    const WORKSPACE_JSON: &str = r#"{
+        "packages": [
+            {"id": "path+file:///ws/app#0.1.0", "name": "app"},
+            {"id": "path+file:///ws/tools#0.1.0", "name": "tools"},
+            {"id": "path+file:///ws/core#0.1.0", "name": "core"}
+        ],
+        "workspace_members": [
+            "path+file:///ws/app#0.1.0",
+            "path+file:///ws/tools#0.1.0",
+            "path+file:///ws/core#0.1.0"
+        ],
+        "workspace_default_members": [
+            "path+file:///ws/app#0.1.0",
+            "path+file:///ws/core#0.1.0"
+        ],
+        "target_directory": "/ws/target",
+        "workspace_root": "/ws"
+    }"#;
+
+    #[test]
+    fn test_default_member_names() {
+        let metadata = CargoMetadata::from_json(WORKSPACE_JSON).unwrap();
+        assert_eq!(metadata.workspace_root, PathBuf::from("/ws"));
+        assert_eq!(metadata.target_directory, PathBuf::from("/ws/target"));
+        assert_eq!(metadata.default_member_names(), vec!["app", "core"]);
+    }
+
+    #[test]
+    fn test_c_building_packages() {
+        let json = r#"{
+            "packages": [
+                {"id": "app 0.1.0", "name": "app"},
+                {"id": "ring 0.17.8", "name": "ring"},
+                {"id": "zstd-sys 2.0.9", "name": "zstd-sys"},
+                {"id": "cc 1.0.90", "name": "cc"},
+                {"id": "cmake 0.1.50", "name": "cmake"},
+                {"id": "serde 1.0.0", "name": "serde"}
+            ],
+            "resolve": {"nodes": [
+                {"id": "app 0.1.0", "deps": [
+                    {"pkg": "ring 0.17.8", "dep_kinds": [{"kind": null}]},
+                    {"pkg": "cc 1.0.90", "dep_kinds": [{"kind": null}]},
+                    {"pkg": "cmake 0.1.50", "dep_kinds": [{"kind": "dev"}]}
+                ]},
+                {"id": "ring 0.17.8", "deps": [
+                    {"pkg": "cc 1.0.90", "dep_kinds": [{"kind": "build"}]}
+                ]},
+                {"id": "zstd-sys 2.0.9", "deps": [
+                    {"pkg": "cc 1.0.90", "dep_kinds": [{"kind": null}, {"kind": "build"}]},
+                    {"pkg": "serde 1.0.0", "dep_kinds": [{"kind": "build"}]}
+                ]},
+                {"id": "cc 1.0.90", "deps": []}
+            ]}
+        }"#;
+        // Only build-dependencies count: app uses cc and cmake at run and test time
+        assert_eq!(c_building_packages(json).unwrap(), ["ring", "zstd-sys"]);
+
+        let no_c = r#"{"packages": [{"id": "app 0.1.0", "name": "app"}],
+            "resolve": {"nodes": [{"id": "app 0.1.0", "deps": []}]}}"#;
+        assert!(c_building_packages(no_c).unwrap().is_empty());
+        assert!(c_building_packages("not json").is_err());
+    }
+
+    #[test]
+    fn test_resolved_package_names() {
+        // bindgen is listed as a package, but the resolve graph for the target (features
+        // and platform applied) leaves it out
+        let json = r#"{
+            "packages": [
+                {"id": "app 0.1.0", "name": "app"},
+                {"id": "bindgen 0.70.1", "name": "bindgen"},
+                {"id": "cc 1.0.90", "name": "cc"}
+            ],
+            "resolve": {"nodes": [
+                {"id": "app 0.1.0", "deps": []},
+                {"id": "cc 1.0.90", "deps": []}
+            ]}
+        }"#;
+        assert_eq!(resolved_package_names(json).unwrap(), ["app", "cc"]);
+        assert!(resolved_package_names("not json").is_err());
+    }
+
+    #[test]
+    fn test_default_member_names_falls_back_to_members() {
+        let mut metadata = CargoMetadata::from_json(WORKSPACE_JSON).unwrap();
+        metadata.workspace_default_members.clear();
+        assert_eq!(
+            metadata.default_member_names(),
+            vec!["app", "tools", "core"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_link_toolchain_on_fixture_crate() {
+        let dir = std::env::temp_dir().join(format!("cargo-cross-link-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        // A local `cc` keeps the fixture offline; only the package name matters
+        for (path, name, build_deps) in [
+            (
+                "app",
+                "app",
+                "[build-dependencies]\ncc = { path = \"../cc\" }\n",
+            ),
+            ("plain", "plain", ""),
+            ("cc", "cc", ""),
+        ] {
+            std::fs::create_dir_all(dir.join(path).join("src")).unwrap();
+            std::fs::write(dir.join(path).join("src/lib.rs"), "").unwrap();
+            std::fs::write(
+                dir.join(path).join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                     [workspace]\n\n{build_deps}"
+                ),
+            )
+            .unwrap();
+        }
+        let check = |crate_dir: &str| {
+            let mut args = crate::cli::test_args(
+                crate::cli::test_toolchain_dir("link"),
+                crate::cli::BuildArgs {
+                    no_link_toolchain: true,
+                    manifest_path: Some(dir.join(crate_dir).join("Cargo.toml")),
+                    ..crate::cli::BuildArgs::default()
+                },
+            );
+            args.command = crate::cli::Command::new("check");
+            args
+        };
+
+        let mut args = check("plain");
+        resolve_link_toolchain(&mut args).await;
+        assert!(args.no_link_toolchain);
+
+        // A build script compiling C needs the toolchain after all
+        let mut args = check("app");
+        resolve_link_toolchain(&mut args).await;
+        assert!(!args.no_link_toolchain);
+
+        // build always sets the toolchain up; the flag is left for it to ignore
+        let mut args = check("plain");
+        args.command = crate::cli::Command::build();
+        resolve_link_toolchain(&mut args).await;
+        assert!(args.no_link_toolchain);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,418 @@
+//! Timing and size metrics for tracking CI warm-up over time
+//!
+//! Phases are timed globally while they run; the per-target values are the difference of
+//! [`phase_totals`] before and after the target. With `--metrics-file` they are written in
+//! the Prometheus/OpenMetrics text format, or as JSON if the file name ends in `.json`.
+//!
+//! The metric names are stable:
+//!
+//! - `cargo_cross_phase_seconds{target, phase}` (gauge): time spent in a phase for a target.
+//!   Phases are `download` (fetching archives), `extract` (unpacking them), `rustup`
+//!   (installing targets, components and checking them) and `cargo` (the cargo command).
+//! - `cargo_cross_target_seconds{target}` (gauge): wall time of the whole target
+//! - `cargo_cross_downloads_total{target}` (counter): toolchain archives downloaded
+//! - `cargo_cross_toolchain_bytes{target, toolchain}` (gauge): size of each toolchain
+//!   directory below `--cross-compiler-dir` the target used
+
+use crate::cargo::CrtDecision;
+use crate::error::Result;
+use crate::sccache::CacheDelta;
+use serde_json::json;
+use std::fmt::Write;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Part of preparing or building a target that is timed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Download,
+    Extract,
+    Rustup,
+    Cargo,
+}
+
+impl Phase {
+    pub const ALL: [Self; 4] = [Self::Download, Self::Extract, Self::Rustup, Self::Cargo];
+
+    /// Value of the `phase` label
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Download => "download",
+            Self::Extract => "extract",
+            Self::Rustup => "rustup",
+            Self::Cargo => "cargo",
+        }
+    }
+}
+
+static PHASE_NANOS: [AtomicU64; 4] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+
+/// Add time spent in a phase
+pub fn record(phase: Phase, elapsed: Duration) {
+    let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+    PHASE_NANOS[phase as usize].fetch_add(nanos, Ordering::Relaxed);
+}
+
+/// Await a future, adding its run time to a phase
+pub async fn timed<T>(phase: Phase, future: impl Future<Output = T>) -> T {
+    let start = Instant::now();
+    let output = future.await;
+    record(phase, start.elapsed());
+    output
+}
+
+/// Time spent in each phase, in the order of [`Phase::ALL`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseTotals([Duration; 4]);
+
+impl PhaseTotals {
+    #[must_use]
+    pub const fn get(&self, phase: Phase) -> Duration {
+        self.0[phase as usize]
+    }
+
+    /// Time spent since an earlier snapshot
+    #[must_use]
+    pub fn since(&self, earlier: &Self) -> Self {
+        Self(std::array::from_fn(|i| {
+            self.0[i].saturating_sub(earlier.0[i])
+        }))
+    }
+}
+
+/// Snapshot of the time spent in each phase so far
+#[must_use]
+pub fn phase_totals() -> PhaseTotals {
+    PhaseTotals(std::array::from_fn(|i| {
+        Duration::from_nanos(PHASE_NANOS[i].load(Ordering::Relaxed))
+    }))
+}
+
+/// Metrics collected for one target
+#[derive(Debug, Clone, Default)]
+pub struct TargetMetrics {
+    pub target: String,
+    pub phases: PhaseTotals,
+    pub total: Duration,
+    pub downloads: usize,
+    /// Toolchain directory names and their sizes in bytes
+    pub toolchains: Vec<(String, u64)>,
+    /// Crates passed to `-Zbuild-std`, if build-std was used
+    pub build_std: Option<String>,
+    /// sccache results of the target, if sccache was the rustc wrapper
+    pub sccache: Option<CacheDelta>,
+    /// How `--crt-static` linked the C runtime, if it passed the target feature
+    pub crt_static: Option<CrtDecision>,
+    /// Linker the target was built with, if one was configured
+    pub linker: Option<String>,
+    /// Apple dylibs whose install name was set after the build
+    pub install_names: Vec<PathBuf>,
+    /// Debug files `--debuginfo-artifacts` split out of the collected artifacts
+    pub debuginfo: Vec<PathBuf>,
+}
+
+impl TargetMetrics {
+    /// Measure the size of the toolchain directories a target used
+    pub fn measure_toolchains<'a>(
+        &mut self,
+        root: &Path,
+        names: impl IntoIterator<Item = &'a String>,
+    ) {
+        self.toolchains = names
+            .into_iter()
+            .map(|name| (name.clone(), crate::cache::dir_size(&root.join(name))))
+            .collect();
+    }
+}
+
+/// Escape a label value for the text exposition format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Metrics in the Prometheus/OpenMetrics text exposition format
+#[must_use]
+pub fn render_openmetrics(targets: &[TargetMetrics]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# TYPE cargo_cross_phase_seconds gauge");
+    let _ = writeln!(
+        out,
+        "# HELP cargo_cross_phase_seconds Time spent in a phase for a target."
+    );
+    for metrics in targets {
+        let target = escape_label(&metrics.target);
+        for phase in Phase::ALL {
+            let _ = writeln!(
+                out,
+                "cargo_cross_phase_seconds{{target=\"{target}\",phase=\"{}\"}} {:.3}",
+                phase.name(),
+                metrics.phases.get(phase).as_secs_f64()
+            );
+        }
+    }
+
+    let _ = writeln!(out, "# TYPE cargo_cross_target_seconds gauge");
+    let _ = writeln!(
+        out,
+        "# HELP cargo_cross_target_seconds Wall time of a target."
+    );
+    for metrics in targets {
+        let _ = writeln!(
+            out,
+            "cargo_cross_target_seconds{{target=\"{}\"}} {:.3}",
+            escape_label(&metrics.target),
+            metrics.total.as_secs_f64()
+        );
+    }
+
+    let _ = writeln!(out, "# TYPE cargo_cross_downloads counter");
+    let _ = writeln!(
+        out,
+        "# HELP cargo_cross_downloads Toolchain archives downloaded for a target."
+    );
+    for metrics in targets {
+        let _ = writeln!(
+            out,
+            "cargo_cross_downloads_total{{target=\"{}\"}} {}",
+            escape_label(&metrics.target),
+            metrics.downloads
+        );
+    }
+
+    let _ = writeln!(out, "# TYPE cargo_cross_toolchain_bytes gauge");
+    let _ = writeln!(
+        out,
+        "# HELP cargo_cross_toolchain_bytes Size of a toolchain directory used by a target."
+    );
+    for metrics in targets {
+        let target = escape_label(&metrics.target);
+        for (toolchain, bytes) in &metrics.toolchains {
+            let _ = writeln!(
+                out,
+                "cargo_cross_toolchain_bytes{{target=\"{target}\",toolchain=\"{}\"}} {bytes}",
+                escape_label(toolchain)
+            );
+        }
+    }
+
+    if targets.iter().any(|metrics| metrics.sccache.is_some()) {
+        let _ = writeln!(out, "# TYPE cargo_cross_sccache counter");
+        let _ = writeln!(
+            out,
+            "# HELP cargo_cross_sccache sccache hits, misses and errors of a target."
+        );
+        for metrics in targets {
+            let Some(delta) = metrics.sccache else {
+                continue;
+            };
+            let target = escape_label(&metrics.target);
+            for (result, count) in [
+                ("hit", delta.counters.hits),
+                ("miss", delta.counters.misses),
+                ("error", delta.counters.errors),
+            ] {
+                let _ = writeln!(
+                    out,
+                    "cargo_cross_sccache_total{{target=\"{target}\",result=\"{result}\"}} {count}"
+                );
+            }
+        }
+    }
+
+    out.push_str("# EOF\n");
+    out
+}
+
+/// Metrics as JSON, one entry per target
+#[must_use]
+pub fn render_json(targets: &[TargetMetrics]) -> serde_json::Value {
+    let entries = targets
+        .iter()
+        .map(|metrics| {
+            let phases: serde_json::Map<String, serde_json::Value> = Phase::ALL
+                .iter()
+                .map(|phase| {
+                    (
+                        phase.name().to_string(),
+                        json!(metrics.phases.get(*phase).as_secs_f64()),
+                    )
+                })
+                .collect();
+            let toolchains: serde_json::Map<String, serde_json::Value> = metrics
+                .toolchains
+                .iter()
+                .map(|(name, bytes)| (name.clone(), json!(bytes)))
+                .collect();
+            json!({
+                "target": metrics.target,
+                "seconds": metrics.total.as_secs_f64(),
+                "phase_seconds": phases,
+                "downloads": metrics.downloads,
+                "toolchain_bytes": toolchains,
+                "build_std": metrics.build_std,
+                "sccache": metrics.sccache.map(|delta| json!({
+                    "hits": delta.counters.hits,
+                    "misses": delta.counters.misses,
+                    "errors": delta.counters.errors,
+                    "reset": delta.reset,
+                })),
+                "crt_static": metrics.crt_static.map(|decision| json!({
+                    "static": decision.static_crt,
+                    "reason": decision.reason,
+                })),
+                "linker": metrics.linker,
+                "install_names": metrics.install_names,
+                "debuginfo": metrics.debuginfo,
+            })
+        })
+        .collect();
+    serde_json::Value::Array(entries)
+}
+
+/// Write the metrics to `--metrics-file`, as JSON for a `.json` path
+pub fn write_metrics_file(path: &Path, targets: &[TargetMetrics]) -> Result<()> {
+    let content = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::to_string_pretty(&render_json(targets))? + "\n"
+    } else {
+        render_openmetrics(targets)
+    };
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> TargetMetrics {
+        let mut phases = PhaseTotals::default();
+        phases.0[Phase::Download as usize] = Duration::from_millis(12_500);
+        phases.0[Phase::Cargo as usize] = Duration::from_secs(90);
+        TargetMetrics {
+            target: "aarch64-unknown-linux-musl".to_string(),
+            phases,
+            total: Duration::from_millis(104_250),
+            downloads: 1,
+            toolchains: vec![("aarch64-linux-musl-cross".to_string(), 123_456)],
+            build_std: None,
+            sccache: None,
+            crt_static: None,
+            linker: Some("aarch64-linux-musl-gcc".to_string()),
+            install_names: Vec::new(),
+            debuginfo: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_render_openmetrics() {
+        assert_eq!(
+            render_openmetrics(&[sample()]),
+            "\
+# TYPE cargo_cross_phase_seconds gauge
+# HELP cargo_cross_phase_seconds Time spent in a phase for a target.
+cargo_cross_phase_seconds{target=\"aarch64-unknown-linux-musl\",phase=\"download\"} 12.500
+cargo_cross_phase_seconds{target=\"aarch64-unknown-linux-musl\",phase=\"extract\"} 0.000
+cargo_cross_phase_seconds{target=\"aarch64-unknown-linux-musl\",phase=\"rustup\"} 0.000
+cargo_cross_phase_seconds{target=\"aarch64-unknown-linux-musl\",phase=\"cargo\"} 90.000
+# TYPE cargo_cross_target_seconds gauge
+# HELP cargo_cross_target_seconds Wall time of a target.
+cargo_cross_target_seconds{target=\"aarch64-unknown-linux-musl\"} 104.250
+# TYPE cargo_cross_downloads counter
+# HELP cargo_cross_downloads Toolchain archives downloaded for a target.
+cargo_cross_downloads_total{target=\"aarch64-unknown-linux-musl\"} 1
+# TYPE cargo_cross_toolchain_bytes gauge
+# HELP cargo_cross_toolchain_bytes Size of a toolchain directory used by a target.
+cargo_cross_toolchain_bytes{target=\"aarch64-unknown-linux-musl\",toolchain=\"aarch64-linux-musl-cross\"} 123456
+# EOF
+"
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        let json = render_json(&[sample()]);
+        assert_eq!(json[0]["target"], "aarch64-unknown-linux-musl");
+        assert_eq!(json[0]["phase_seconds"]["download"], 12.5);
+        assert_eq!(json[0]["phase_seconds"]["rustup"], 0.0);
+        assert_eq!(json[0]["downloads"], 1);
+        assert!(json[0]["build_std"].is_null());
+        assert_eq!(
+            json[0]["toolchain_bytes"]["aarch64-linux-musl-cross"],
+            123_456
+        );
+        assert!(json[0]["sccache"].is_null());
+        assert!(json[0]["crt_static"].is_null());
+        assert_eq!(json[0]["linker"], "aarch64-linux-musl-gcc");
+        assert_eq!(json[0]["install_names"], json!([]));
+        assert_eq!(json[0]["debuginfo"], json!([]));
+
+        let mut metrics = sample();
+        metrics.crt_static = Some(CrtDecision {
+            static_crt: true,
+            reason: "musl default",
+        });
+        let json = render_json(&[metrics]);
+        assert_eq!(json[0]["crt_static"]["static"], true);
+        assert_eq!(json[0]["crt_static"]["reason"], "musl default");
+
+        let mut metrics = sample();
+        metrics.install_names = vec![PathBuf::from("release/libacme_ffi.dylib")];
+        metrics.debuginfo = vec![PathBuf::from("dist/acme.debug")];
+        let json = render_json(&[metrics]);
+        assert_eq!(
+            json[0]["install_names"],
+            json!(["release/libacme_ffi.dylib"])
+        );
+        assert_eq!(json[0]["debuginfo"], json!(["dist/acme.debug"]));
+    }
+
+    #[test]
+    fn test_sccache_metrics() {
+        use crate::sccache::CacheCounters;
+
+        let mut metrics = sample();
+        metrics.sccache = Some(CacheDelta {
+            counters: CacheCounters {
+                hits: 12,
+                misses: 3,
+                errors: 0,
+            },
+            reset: true,
+        });
+        let json = render_json(std::slice::from_ref(&metrics));
+        assert_eq!(json[0]["sccache"]["hits"], 12);
+        assert_eq!(json[0]["sccache"]["misses"], 3);
+        assert_eq!(json[0]["sccache"]["reset"], true);
+
+        let text = render_openmetrics(&[sample(), metrics]);
+        assert!(text.contains(
+            "cargo_cross_sccache_total{target=\"aarch64-unknown-linux-musl\",result=\"hit\"} 12\n"
+        ));
+        assert_eq!(text.matches("cargo_cross_sccache_total").count(), 3);
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn test_label_escaping_and_phase_deltas() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+
+        let before = phase_totals();
+        record(Phase::Extract, Duration::from_millis(250));
+        let delta = phase_totals().since(&before);
+        assert!(delta.get(Phase::Extract) >= Duration::from_millis(250));
+    }
+}
@@ -0,0 +1,759 @@
+//! Dry-run execution plans
+//!
+//! A plan describes what a command would do for each target: which Rust target is used,
+//! which toolchain bundle is installed from where, how binaries would be run, whether
+//! build-std is enabled and the final cargo argv. Planning never downloads anything and
+//! never writes to the filesystem.
+
+use crate::build_std::{self, BuildStdChoice};
+use crate::builder::check_preconfigured_env;
+use crate::cargo::{cargo_argv, rust_target_status, unknown_target_error, RustTargetStatus};
+use crate::cli::{Args, BuildStd, RunnerBackend, SetupStep};
+use crate::config::{get_target_config, Arch, HostPlatform, Libc, Os, TargetConfig};
+use crate::env::CrossEnv;
+use crate::error::CrossError;
+use crate::platform::{self, ToolchainRelease};
+use crate::runner::{check_runner_available, docker_qemu_release, qemu_release};
+use serde_json::{json, Value};
+
+/// How binaries built for a target would be executed by run/test/bench
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunnerKind {
+    /// No runner is configured
+    None,
+    /// Binaries run directly on the host
+    Native,
+    /// User-provided `--runner`
+    Custom,
+    /// qemu-user-static on the host
+    Qemu,
+    /// qemu-user-static inside a Docker container (macOS hosts)
+    DockerQemu,
+    /// The sysroot's dynamic linker in an unprivileged chroot (`--runner-backend chroot`)
+    Chroot,
+    /// Wine for Windows GNU targets
+    Wine,
+    /// Rosetta 2 for `x86_64` macOS targets on Apple Silicon
+    Rosetta,
+}
+
+impl RunnerKind {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Native => "native",
+            Self::Custom => "custom",
+            Self::Qemu => "qemu",
+            Self::DockerQemu => "docker-qemu",
+            Self::Chroot => "chroot",
+            Self::Wine => "wine",
+            Self::Rosetta => "rosetta",
+        }
+    }
+
+    /// Whether binaries run under emulation or translation rather than natively,
+    /// so wall-clock timings do not reflect real hardware
+    #[must_use]
+    pub const fn is_emulated(self) -> bool {
+        matches!(
+            self,
+            Self::Qemu | Self::DockerQemu | Self::Wine | Self::Rosetta
+        )
+    }
+
+    /// Classify the runner configured in a cross environment; `None` means binaries ran natively
+    #[must_use]
+    pub fn from_runner(runner: Option<&str>, custom: bool) -> Self {
+        if custom {
+            return Self::Custom;
+        }
+        let Some(program) = runner.and_then(|runner| runner.split_whitespace().next()) else {
+            return Self::Native;
+        };
+        let name = std::path::Path::new(program)
+            .file_name()
+            .map_or(program.into(), |name| name.to_string_lossy());
+        if name.starts_with("docker-qemu-runner") {
+            Self::DockerQemu
+        } else if name.starts_with("chroot-runner") {
+            Self::Chroot
+        } else if name.starts_with("qemu-") {
+            Self::Qemu
+        } else if name == "wine" {
+            Self::Wine
+        } else if name == "arch" {
+            Self::Rosetta
+        } else {
+            Self::Custom
+        }
+    }
+}
+
+/// Planned work for a single target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetPlan {
+    /// Target as requested (`host-tuple` included)
+    pub target: String,
+    /// Target passed to rustc
+    pub rust_target: String,
+    /// What rustup/rustc report for the target, if they could be queried
+    pub rust_target_status: Option<RustTargetStatus>,
+    /// Cross toolchain bundle, `None` for native or pre-configured toolchains
+    pub toolchain: Option<ToolchainRelease>,
+    /// Runner used by run/test/bench
+    pub runner: RunnerKind,
+    /// Bundle the runner needs, such as qemu-user-static
+    pub runner_bundle: Option<ToolchainRelease>,
+    /// Value passed to `-Zbuild-std`, if build-std is used
+    pub build_std: Option<String>,
+    /// `--config` values forwarded for this target, in order
+    pub cargo_config: Vec<String>,
+    /// Cargo invocation, program first
+    pub cargo_argv: Vec<String>,
+    /// Problems that would make the real run fail
+    pub errors: Vec<String>,
+}
+
+/// Whether planning `targets` needs the Ubuntu build of osxcross/ioscross: only Apple
+/// targets on Linux hosts do, and detecting it logs a line
+fn needs_ubuntu_flavor(targets: &[String], host: &HostPlatform) -> bool {
+    host.is_linux()
+        && targets.iter().any(|target| {
+            get_target_config(target)
+                .is_some_and(|config| matches!(config.os, Os::Darwin | Os::Ios | Os::IosSim))
+        })
+}
+
+/// Plan every target of `args`, querying rustup and rustc but installing nothing
+pub async fn plan_targets(args: &Args, host: &HostPlatform) -> Vec<TargetPlan> {
+    let ubuntu_version = if needs_ubuntu_flavor(&args.targets, host) {
+        platform::host_compat::ubuntu_flavor(args).await.version
+    } else {
+        platform::host_compat::FALLBACK_UBUNTU_VERSION.to_string()
+    };
+
+    let mut plans = Vec::with_capacity(args.targets.len());
+    for target in &args.targets {
+        let rust_target = if target == "host-tuple" {
+            host.triple.as_str()
+        } else {
+            target.as_str()
+        };
+        let status = rust_target_status(rust_target, args.toolchain.as_deref()).await;
+        let choice =
+            build_std::resolve_auto(args, rust_target, get_target_config(rust_target)).await;
+        let mut plan = plan_target(
+            target,
+            args,
+            host,
+            &ubuntu_version,
+            status.as_ref().ok().copied(),
+            choice.as_ref(),
+        );
+        if let Err(e) = status {
+            plan.errors.insert(0, e.to_string());
+        }
+        plans.push(plan);
+    }
+    plans
+}
+
+/// Plan one target given what rustup/rustc report for it
+#[must_use]
+pub fn plan_target(
+    target: &str,
+    args: &Args,
+    host: &HostPlatform,
+    ubuntu_version: &str,
+    status: Option<RustTargetStatus>,
+    auto_build_std_choice: Option<&BuildStdChoice>,
+) -> TargetPlan {
+    let is_host_build = target == "host-tuple";
+    let rust_target = if is_host_build {
+        host.triple.clone()
+    } else {
+        target.to_string()
+    };
+    let target_config = get_target_config(&rust_target);
+    let preconfigured = !is_host_build && check_preconfigured_env(&rust_target, args).is_some();
+    let mut errors = Vec::new();
+
+    let toolchain = match target_config {
+        Some(config) if !is_host_build && !preconfigured => {
+            platform::toolchain_release(config, args, host, ubuntu_version).unwrap_or_else(|e| {
+                errors.push(e.to_string());
+                None
+            })
+        }
+        _ => None,
+    };
+
+    let (mut runner, runner_bundle) =
+        if !args.needs_runner() || !args.step_enabled(SetupStep::Runner) {
+            (RunnerKind::None, None)
+        } else if is_host_build {
+            (RunnerKind::Native, None)
+        } else if preconfigured || args.no_toolchain_setup {
+            (RunnerKind::None, None)
+        } else {
+            target_config.map_or((RunnerKind::None, None), |config| {
+                plan_runner(config, args, host)
+            })
+        };
+    if args.runner.is_some() {
+        runner = RunnerKind::Custom;
+    }
+    if let Some(config) = target_config.filter(|_| !is_host_build && !preconfigured) {
+        if let Err(e) = check_runner_available(config, args, host) {
+            errors.push(e.to_string());
+        }
+    }
+
+    // Mirror prepare_target: a target only rustc knows is built with build-std
+    let auto_build_std = status == Some(RustTargetStatus::BuildStd);
+    match status {
+        Some(RustTargetStatus::Unknown) => {
+            errors.push(unknown_target_error(&rust_target, args.toolchain.as_deref()).to_string());
+        }
+        Some(RustTargetStatus::BuildStd) if args.build_std == BuildStd::Disabled => errors.push(
+            CrossError::BuildStdDisabled {
+                target: rust_target.clone(),
+            }
+            .to_string(),
+        ),
+        _ => {}
+    }
+
+    let mut cross_env = CrossEnv::new();
+    if auto_build_std && args.build_std == BuildStd::Unset {
+        cross_env.set_build_std("true");
+    }
+    if let Some(choice) = auto_build_std_choice {
+        cross_env.set_build_std(&choice.crates);
+    }
+    let build_std = match args.build_std {
+        BuildStd::Enabled(ref crates) => Some(crates.clone()),
+        BuildStd::Disabled => None,
+        BuildStd::Unset | BuildStd::Auto(_) => cross_env.build_std.clone(),
+    };
+    let cargo_config = args
+        .cargo_config_for(&rust_target)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let skip_target_arg = args.skip_target_arg(target, &host.triple);
+    let cargo_argv = cargo_argv(&rust_target, args, &cross_env, skip_target_arg);
+
+    TargetPlan {
+        target: target.to_string(),
+        rust_target,
+        rust_target_status: status,
+        toolchain,
+        runner,
+        runner_bundle,
+        build_std,
+        cargo_config,
+        cargo_argv,
+        errors,
+    }
+}
+
+/// Runner the platform setup configures for `target` when a command needs one, whatever
+/// the current command is
+#[must_use]
+pub fn target_runner_kind(target: &str, args: &Args, host: &HostPlatform) -> RunnerKind {
+    if args.runner.is_some() {
+        RunnerKind::Custom
+    } else if target == host.triple {
+        RunnerKind::Native
+    } else if args.no_toolchain_setup || !args.step_enabled(SetupStep::Runner) {
+        RunnerKind::None
+    } else {
+        get_target_config(target)
+            .map_or(RunnerKind::None, |config| plan_runner(config, args, host).0)
+    }
+}
+
+/// Pick the runner the platform setup would configure, mirroring its availability checks
+fn plan_runner(
+    config: &TargetConfig,
+    args: &Args,
+    host: &HostPlatform,
+) -> (RunnerKind, Option<ToolchainRelease>) {
+    match config.os {
+        Os::Linux => {
+            let Some(qemu_binary) = config.arch.qemu_binary_name() else {
+                return (RunnerKind::None, None);
+            };
+            if host.is_darwin() {
+                if which::which("docker").is_err() {
+                    (RunnerKind::None, None)
+                } else if args.qemu_path.is_some() {
+                    (RunnerKind::DockerQemu, None)
+                } else if Arch::qemu_bundle_available("linux", host.arch) {
+                    (
+                        RunnerKind::DockerQemu,
+                        Some(docker_qemu_release(qemu_binary, args, host)),
+                    )
+                } else {
+                    (RunnerKind::None, None)
+                }
+            } else if args.runner_backend == RunnerBackend::Chroot
+                && host.os == "linux"
+                && host.can_run_natively(config.arch)
+            {
+                // Falls back to QEMU when no chroot tool works, which is only known at setup
+                (RunnerKind::Chroot, None)
+            } else if args.qemu_path.is_some() {
+                (RunnerKind::Qemu, None)
+            } else if Arch::qemu_bundle_available(host.os, host.arch) {
+                (
+                    RunnerKind::Qemu,
+                    Some(qemu_release(qemu_binary, args, host)),
+                )
+            } else {
+                (RunnerKind::None, None)
+            }
+        }
+        Os::Windows if host.is_windows() => (RunnerKind::Native, None),
+        Os::Windows if config.libc != Some(Libc::Msvc) && which::which("wine").is_ok() => {
+            (RunnerKind::Wine, None)
+        }
+        Os::Darwin if host.is_darwin() => {
+            if config.arch == Arch::X86_64 && host.arch == "aarch64" {
+                (RunnerKind::Rosetta, None)
+            } else if host.can_run_natively(config.arch) {
+                (RunnerKind::Native, None)
+            } else {
+                (RunnerKind::None, None)
+            }
+        }
+        _ => (RunnerKind::None, None),
+    }
+}
+
+fn release_json(release: &ToolchainRelease) -> Value {
+    json!({
+        "kind": release.kind,
+        "dir": release.dir.display().to_string(),
+        "url": release.url,
+        "installed": release.is_installed(),
+        "gcc_version": platform::gcc::cached_gcc_major(&release.dir),
+    })
+}
+
+/// Render plans as the JSON document printed by `cargo-cross plan`
+#[must_use]
+pub fn plan_json(args: &Args, host: &HostPlatform, plans: &[TargetPlan]) -> Value {
+    let targets: Vec<Value> = plans
+        .iter()
+        .map(|plan| {
+            json!({
+                "target": plan.target,
+                "rust_target": plan.rust_target,
+                "rust_target_status": plan.rust_target_status.map(RustTargetStatus::as_str),
+                "std_support": plan.rust_target_status.map(|status| status.std_support().as_str()),
+                "toolchain": plan.toolchain.as_ref().map(release_json),
+                "runner": {
+                    "kind": plan.runner.as_str(),
+                    "bundle": plan.runner_bundle.as_ref().map(release_json),
+                },
+                "build_std": plan.build_std,
+                "cargo_config": plan.cargo_config,
+                "cargo_argv": plan.cargo_argv,
+                "errors": plan.errors,
+            })
+        })
+        .collect();
+
+    json!({
+        "command": args.command.as_str(),
+        "host": host.triple,
+        "rust_toolchain": args.toolchain,
+        "cross_make_version": args.cross_make_version,
+        "qemu_version": args.qemu_version,
+        "ndk_version": args.ndk_version,
+        "targets": targets,
+    })
+}
+
+/// Render plans as an aligned table, one row per target followed by any errors
+#[must_use]
+pub fn plan_table(plans: &[TargetPlan]) -> String {
+    let header = [
+        "TARGET",
+        "TOOLCHAIN",
+        "INSTALLED",
+        "RUNNER",
+        "STD",
+        "BUILD-STD",
+    ];
+    let rows: Vec<[String; 6]> = plans
+        .iter()
+        .map(|plan| {
+            [
+                plan.rust_target.clone(),
+                plan.toolchain
+                    .as_ref()
+                    .map_or_else(|| "native".to_string(), |t| t.kind.to_string()),
+                plan.toolchain.as_ref().map_or_else(
+                    || "-".to_string(),
+                    |t| if t.is_installed() { "yes" } else { "no" }.to_string(),
+                ),
+                plan.runner.as_str().to_string(),
+                plan.rust_target_status
+                    .map_or("-", |status| status.std_support().as_str())
+                    .to_string(),
+                plan.build_std.clone().unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let format_row = |cells: &[&str]| {
+        cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut lines = vec![format_row(&header)];
+    for (row, plan) in rows.iter().zip(plans) {
+        lines.push(format_row(&row.each_ref().map(String::as_str)));
+        for config in &plan.cargo_config {
+            lines.push(format!("  config: {config}"));
+        }
+        for error in &plan.errors {
+            // Continuation lines line up with the first, so they do not read as new rows
+            let mut error_lines = error.lines();
+            lines.push(format!(
+                "  error: {}",
+                error_lines.next().unwrap_or_default()
+            ));
+            lines.extend(error_lines.map(|line| format!("         {line}")));
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{BuildArgs, Command};
+    use std::path::PathBuf;
+
+    fn linux_host() -> HostPlatform {
+        HostPlatform {
+            os: "linux",
+            arch: "x86_64",
+            triple: "x86_64-unknown-linux-gnu".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_ubuntu_flavor_only_for_apple_targets() {
+        let targets = |list: &[&str]| list.iter().map(|t| (*t).to_string()).collect::<Vec<_>>();
+        let linux = targets(&["host-tuple", "aarch64-unknown-linux-musl"]);
+        assert!(!needs_ubuntu_flavor(&linux, &linux_host()));
+        let apple = targets(&["aarch64-unknown-linux-musl", "aarch64-apple-ios"]);
+        assert!(needs_ubuntu_flavor(&apple, &linux_host()));
+        let mac = HostPlatform {
+            os: "macos",
+            arch: "aarch64",
+            triple: "aarch64-apple-darwin".to_string(),
+        };
+        assert!(!needs_ubuntu_flavor(&apple, &mac));
+    }
+
+    fn plan_args(command: Command, targets: &[&str]) -> Args {
+        Args {
+            command,
+            cross_make_version: "v0.7.7".to_string(),
+            cross_compiler_dir: PathBuf::from("/nonexistent/cross"),
+            ..crate::cli::test_target_args("", targets, BuildArgs::cli_defaults())
+        }
+    }
+
+    #[test]
+    fn test_plan_linux_target() {
+        let args = plan_args(Command::test(), &["aarch64-unknown-linux-musl"]);
+        let plan = plan_target(
+            "aarch64-unknown-linux-musl",
+            &args,
+            &linux_host(),
+            "20.04",
+            Some(RustTargetStatus::Installed),
+            None,
+        );
+
+        let toolchain = plan.toolchain.as_ref().unwrap();
+        assert_eq!(toolchain.kind, "cross-make");
+        assert!(toolchain.url.ends_with("/aarch64-linux-musl-cross.tgz"));
+        assert!(!toolchain.is_installed());
+        assert_eq!(plan.runner, RunnerKind::Qemu);
+        assert_eq!(
+            plan.runner_bundle.as_ref().unwrap().kind,
+            "qemu-user-static"
+        );
+        assert_eq!(plan.build_std, None);
+        assert_eq!(
+            &plan.cargo_argv[..4],
+            ["cargo", "test", "--target", "aarch64-unknown-linux-musl"]
+        );
+        assert!(plan.errors.is_empty());
+    }
+
+    #[test]
+    fn test_plan_build_std_and_errors() {
+        let args = plan_args(Command::build(), &["aarch64-unknown-linux-musl"]);
+        let plan = plan_target(
+            "aarch64-unknown-linux-musl",
+            &args,
+            &linux_host(),
+            "20.04",
+            Some(RustTargetStatus::BuildStd),
+            None,
+        );
+        assert_eq!(plan.build_std.as_deref(), Some("true"));
+        assert!(plan
+            .cargo_argv
+            .iter()
+            .any(|arg| arg.starts_with("-Zbuild-std")));
+        assert_eq!(plan.runner, RunnerKind::None);
+
+        let plan = plan_target(
+            "aarch64-apple-darwin",
+            &args,
+            &HostPlatform {
+                os: "windows",
+                arch: "x86_64",
+                triple: "x86_64-pc-windows-msvc".to_string(),
+            },
+            "20.04",
+            Some(RustTargetStatus::Installed),
+            None,
+        );
+        assert_eq!(plan.toolchain, None);
+        assert_eq!(plan.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_plan_build_std_auto() {
+        let mut args = plan_args(Command::build(), &["thumbv7em-none-eabihf"]);
+        args.build.build_std = BuildStd::Auto(Vec::new());
+        let choice = BuildStdChoice {
+            crates: "core,alloc".to_string(),
+            reason: "bare-metal target, no crate enables std".to_string(),
+        };
+        let plan = plan_target(
+            "thumbv7em-none-eabihf",
+            &args,
+            &linux_host(),
+            "20.04",
+            Some(RustTargetStatus::BuildStd),
+            Some(&choice),
+        );
+        assert_eq!(plan.build_std.as_deref(), Some("core,alloc"));
+        assert!(plan
+            .cargo_argv
+            .contains(&"-Zbuild-std=core,alloc".to_string()));
+    }
+
+    #[test]
+    fn test_plan_host_tuple() {
+        let mut args = plan_args(Command::run(), &["host-tuple"]);
+        args.runner = Some("valgrind".to_string());
+        let plan = plan_target("host-tuple", &args, &linux_host(), "20.04", None, None);
+        assert_eq!(plan.rust_target, "x86_64-unknown-linux-gnu");
+        assert_eq!(plan.toolchain, None);
+        assert_eq!(plan.runner, RunnerKind::Custom);
+        assert!(!plan.cargo_argv.contains(&"--target".to_string()));
+    }
+
+    #[test]
+    fn test_plan_per_target_cargo_config() {
+        let mut args = plan_args(
+            Command::build(),
+            &["aarch64-unknown-linux-musl", "x86_64-unknown-linux-musl"],
+        );
+        args.cargo_config = vec![
+            "build.jobs=4".to_string(),
+            "aarch64-*=>target.aarch64-unknown-linux-musl.rustflags=[\"-Ctarget-feature=+lse\"]"
+                .to_string(),
+        ];
+
+        let plan = plan_target(
+            "aarch64-unknown-linux-musl",
+            &args,
+            &linux_host(),
+            "20.04",
+            None,
+            None,
+        );
+        assert_eq!(plan.cargo_config.len(), 2);
+        assert!(plan.cargo_argv.contains(
+            &"target.aarch64-unknown-linux-musl.rustflags=[\"-Ctarget-feature=+lse\"]".to_string()
+        ));
+        assert!(plan_table(std::slice::from_ref(&plan)).contains("  config: build.jobs=4"));
+
+        let plan = plan_target(
+            "x86_64-unknown-linux-musl",
+            &args,
+            &linux_host(),
+            "20.04",
+            None,
+            None,
+        );
+        assert_eq!(plan.cargo_config, vec!["build.jobs=4"]);
+        assert!(!plan.cargo_argv.iter().any(|arg| arg.contains("=>")));
+    }
+
+    #[test]
+    fn test_plan_json_schema() {
+        let host = linux_host();
+        let args = plan_args(
+            Command::test(),
+            &["aarch64-unknown-linux-musl", "x86_64-pc-windows-gnu"],
+        );
+        let plans: Vec<_> = args
+            .targets
+            .iter()
+            .map(|target| plan_target(target, &args, &host, "20.04", None, None))
+            .collect();
+        let doc = plan_json(&args, &host, &plans);
+
+        assert_eq!(doc["command"], "test");
+        assert_eq!(doc["host"], "x86_64-unknown-linux-gnu");
+        assert!(doc["rust_toolchain"].is_null());
+        assert!(doc["cross_make_version"].is_string());
+        let targets = doc["targets"].as_array().unwrap();
+        assert_eq!(targets.len(), 2);
+        for target in targets {
+            let object = target.as_object().unwrap();
+            let mut keys: Vec<_> = object.keys().map(String::as_str).collect();
+            keys.sort_unstable();
+            assert_eq!(
+                keys,
+                [
+                    "build_std",
+                    "cargo_argv",
+                    "cargo_config",
+                    "errors",
+                    "runner",
+                    "rust_target",
+                    "rust_target_status",
+                    "std_support",
+                    "target",
+                    "toolchain",
+                ]
+            );
+            assert!(target["cargo_argv"].is_array());
+            assert!(target["runner"]["kind"].is_string());
+            let toolchain = target["toolchain"].as_object().unwrap();
+            for key in ["kind", "dir", "url"] {
+                assert!(toolchain[key].is_string(), "{key}");
+            }
+            assert!(toolchain["installed"].is_boolean());
+        }
+        assert_eq!(targets[0]["runner"]["kind"], "qemu");
+        assert_eq!(targets[0]["runner"]["bundle"]["kind"], "qemu-user-static");
+    }
+
+    #[test]
+    fn test_qemu_path_replaces_bundle() {
+        let mut args = plan_args(Command::test(), &["aarch64-unknown-linux-musl"]);
+        args.qemu_path = Some(PathBuf::from("/opt/qemu/bin"));
+        let plan = plan_target(
+            "aarch64-unknown-linux-musl",
+            &args,
+            &linux_host(),
+            "20.04",
+            None,
+            None,
+        );
+        assert_eq!(plan.runner, RunnerKind::Qemu);
+        assert_eq!(plan.runner_bundle, None);
+        assert!(plan.errors.is_empty(), "{:?}", plan.errors);
+    }
+
+    #[test]
+    fn test_chroot_backend_for_native_targets() {
+        let targets = ["x86_64-unknown-linux-gnu", "aarch64-unknown-linux-gnu"];
+        let mut args = plan_args(Command::test(), &targets);
+        args.runner_backend = RunnerBackend::Chroot;
+        let plan = |target| plan_target(target, &args, &linux_host(), "20.04", None, None);
+
+        let native = plan("x86_64-unknown-linux-gnu");
+        assert_eq!(native.runner, RunnerKind::Chroot);
+        assert_eq!(native.runner_bundle, None);
+        assert!(!native.runner.is_emulated());
+        // Other architectures keep QEMU
+        assert_eq!(plan("aarch64-unknown-linux-gnu").runner, RunnerKind::Qemu);
+    }
+
+    #[test]
+    fn test_runner_kind_from_runner() {
+        for (runner, expected) in [
+            (None, RunnerKind::Native),
+            (
+                Some("qemu-aarch64 -L /x/aarch64-linux-musl"),
+                RunnerKind::Qemu,
+            ),
+            (
+                Some("/cross/docker-qemu-runner-aarch64-musl.sh"),
+                RunnerKind::DockerQemu,
+            ),
+            (
+                Some("/cross/generated/chroot-runner-x86_64-unknown-linux-gnu.sh"),
+                RunnerKind::Chroot,
+            ),
+            (Some("wine"), RunnerKind::Wine),
+            (Some("arch -x86_64"), RunnerKind::Rosetta),
+            (Some("my-runner"), RunnerKind::Custom),
+        ] {
+            assert_eq!(
+                RunnerKind::from_runner(runner, false),
+                expected,
+                "{runner:?}"
+            );
+        }
+        assert_eq!(RunnerKind::from_runner(None, true), RunnerKind::Custom);
+        assert!(RunnerKind::Qemu.is_emulated());
+        assert!(!RunnerKind::Native.is_emulated());
+    }
+
+    #[test]
+    fn test_plan_table() {
+        let host = linux_host();
+        let args = plan_args(Command::build(), &["aarch64-unknown-linux-musl"]);
+        let mut plan = plan_target(
+            "aarch64-unknown-linux-musl",
+            &args,
+            &host,
+            "20.04",
+            None,
+            None,
+        );
+        plan.errors.push("boom".to_string());
+        plan.errors
+            .push("Unknown target 'x'\nIt is not a target of this toolchain".to_string());
+        let table = plan_table(&[plan]);
+        let lines: Vec<_> = table.lines().collect();
+        assert!(lines[0].starts_with("TARGET"));
+        assert!(lines[1].starts_with("aarch64-unknown-linux-musl  cross-make  no"));
+        assert_eq!(lines[2], "  error: boom");
+        assert_eq!(lines[3], "  error: Unknown target 'x'");
+        assert_eq!(lines[4], "         It is not a target of this toolchain");
+        assert_eq!(lines.len(), 5);
+    }
+}
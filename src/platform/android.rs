@@ -1,15 +1,425 @@
 //! Android NDK cross-compilation setup
 
-use crate::cli::Args;
+use crate::cli::{Args, SetupStep};
 use crate::color;
 use crate::config::{Arch, HostPlatform, TargetConfig};
-use crate::download::download_and_extract;
+use crate::download::ArchiveFormat;
 use crate::env::CrossEnv;
 use crate::error::{CrossError, Result};
-use crate::platform::{setup_cmake, to_cmake_path};
-use std::path::PathBuf;
+use crate::generated::{write_generated, write_idempotent, CLANG_WRAPPER_DIR};
+use crate::layout;
+use crate::platform::{setup_cmake, to_cmake_path, CompilerNames, ToolchainRelease};
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
+/// Quote `arg` for a Windows command line so the MSVC runtime parses it back unchanged
+#[must_use]
+pub fn quote_windows_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Backslashes before a quote are escapes, so double them and escape the quote
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    // Trailing backslashes precede the closing quote
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// `arg` as a fixed argument in a batch file: always quoted, so cmd metacharacters in
+/// paths stay literal, and with `%` doubled so it is not expanded as a variable
+fn batch_arg(arg: &str) -> String {
+    let quoted = quote_windows_arg(arg);
+    let quoted = if quoted.starts_with('"') {
+        quoted
+    } else {
+        format!("\"{quoted}\"")
+    };
+    quoted.replace('%', "%%")
+}
+
+/// Contents of a `.bat` wrapper running `clang` for `--target=<clang_prefix>`.
+/// The caller's arguments are forwarded untouched through `%*`; unlike the NDK's .cmd
+/// wrappers nothing inspects `%1`, which is what breaks on `=`, spaces and quotes.
+#[must_use]
+pub fn render_clang_wrapper(clang: &Path, clang_prefix: &str) -> String {
+    format!(
+        "@echo off\r\n{} {} %*\r\n",
+        batch_arg(&clang.display().to_string()),
+        batch_arg(&format!("--target={clang_prefix}"))
+    )
+}
+
+/// Extension of the clang wrappers cargo-cross generates on Windows hosts
+pub const GENERATED_WRAPPER_EXT: &str = ".bat";
+
+/// Extension of the clang wrappers the NDK ships for Windows hosts
+pub const NDK_WRAPPER_EXT: &str = ".cmd";
+
+/// Write the `<clang_prefix>-clang.bat` and `<clang_prefix>-clang++.bat` wrappers into
+/// `wrapper_dir`, returning their paths
+fn write_clang_wrappers(
+    wrapper_dir: &Path,
+    clang_base_dir: &Path,
+    clang_prefix: &str,
+) -> Result<(PathBuf, PathBuf)> {
+    let cc = wrapper_dir.join(format!("{clang_prefix}-clang{GENERATED_WRAPPER_EXT}"));
+    let cxx = wrapper_dir.join(format!("{clang_prefix}-clang++{GENERATED_WRAPPER_EXT}"));
+    write_generated(
+        &cc,
+        &render_clang_wrapper(&clang_base_dir.join("clang.exe"), clang_prefix),
+        false,
+    )?;
+    write_generated(
+        &cxx,
+        &render_clang_wrapper(&clang_base_dir.join("clang++.exe"), clang_prefix),
+        false,
+    )?;
+    Ok((cc, cxx))
+}
+
+/// Point CC/CXX/AR/linker at the NDK clang wrappers and add the prebuilt `bin` dir to PATH.
+/// On Windows hosts with a `wrapper_dir`, generated .bat wrappers are used instead of
+/// the NDK's .cmd files.
+pub(super) fn setup_ndk_tools(
+    env: &mut CrossEnv,
+    clang_base_dir: &Path,
+    clang_prefix: &str,
+    host: &HostPlatform,
+    wrapper_dir: Option<&Path>,
+) -> Result<()> {
+    env.set_ar(format!("llvm-ar{}", host.exe_ext()));
+    env.add_path(clang_base_dir);
+
+    if let Some(wrapper_dir) = wrapper_dir.filter(|_| host.is_windows()) {
+        let (cc, cxx) = write_clang_wrappers(wrapper_dir, clang_base_dir, clang_prefix)?;
+        env.set_cc(cc.display().to_string());
+        env.set_cxx(cxx.display().to_string());
+        env.set_linker(cc.display().to_string());
+        return Ok(());
+    }
+
+    ndk_compiler_names(clang_prefix, host, true).apply(env);
+    Ok(())
+}
+
+/// Tools of the NDK for a clang wrapper prefix such as `aarch64-linux-android21`.
+/// On Windows hosts the clang names are those of the NDK's .cmd wrappers with
+/// `ndk_wrappers`, of the generated .bat wrappers otherwise.
+#[must_use]
+pub fn ndk_compiler_names(
+    clang_prefix: &str,
+    host: &HostPlatform,
+    ndk_wrappers: bool,
+) -> CompilerNames {
+    // Windows won't execute extensionless files, and clang has no .exe per target
+    let clang_ext = match (host.is_windows(), ndk_wrappers) {
+        (false, _) => "",
+        (true, true) => NDK_WRAPPER_EXT,
+        (true, false) => GENERATED_WRAPPER_EXT,
+    };
+    CompilerNames {
+        cc: format!("{clang_prefix}-clang{clang_ext}"),
+        cxx: format!("{clang_prefix}-clang++{clang_ext}"),
+        ar: format!("llvm-ar{}", host.exe_ext()),
+        linker: format!("{clang_prefix}-clang{clang_ext}"),
+    }
+}
+
+/// NDK support for one Android architecture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AndroidArch {
+    pub arch: Arch,
+    /// Clang target triple, completed with the API level to form the wrapper prefix
+    pub clang_triple: &'static str,
+    /// Android ABI name used by the NDK cmake toolchain
+    pub android_abi: &'static str,
+    /// Lowest Android API level the NDK supports for this architecture
+    pub min_api: u32,
+    /// First NDK major release shipping this architecture
+    pub min_ndk: u32,
+}
+
+impl AndroidArch {
+    /// NDK clang wrapper prefix, e.g. `aarch64-linux-android24`
+    #[must_use]
+    pub fn clang_prefix(&self) -> String {
+        format!("{}{}", self.clang_triple, self.min_api)
+    }
+}
+
+/// Architectures supported by the Android NDK
+const ANDROID_ARCHES: &[AndroidArch] = &[
+    AndroidArch {
+        arch: Arch::Armv7,
+        clang_triple: "armv7a-linux-androideabi",
+        android_abi: "armeabi-v7a",
+        min_api: 24,
+        min_ndk: 23,
+    },
+    AndroidArch {
+        arch: Arch::Aarch64,
+        clang_triple: "aarch64-linux-android",
+        android_abi: "arm64-v8a",
+        min_api: 24,
+        min_ndk: 23,
+    },
+    AndroidArch {
+        arch: Arch::I686,
+        clang_triple: "i686-linux-android",
+        android_abi: "x86",
+        min_api: 24,
+        min_ndk: 23,
+    },
+    AndroidArch {
+        arch: Arch::X86_64,
+        clang_triple: "x86_64-linux-android",
+        android_abi: "x86_64",
+        min_api: 24,
+        min_ndk: 23,
+    },
+    AndroidArch {
+        arch: Arch::Riscv64,
+        clang_triple: "riscv64-linux-android",
+        android_abi: "riscv64",
+        min_api: 35,
+        min_ndk: 27,
+    },
+];
+
+/// An NDK version, given as a release name (`r27d`, `r29-beta1`) or as the
+/// `Pkg.Revision` sdkmanager and `source.properties` use (`27.3.13750724`).
+/// Release letters count minor revisions: `r27` is 27.0, `r27b` 27.1, `r27d` 27.3.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NdkVersion {
+    pub major: u32,
+    pub minor: u32,
+    /// Build number, only known from the numeric form
+    pub build: Option<u64>,
+    /// Pre-release tag such as `beta1` or `rc1`
+    pub prerelease: Option<String>,
+}
+
+impl NdkVersion {
+    /// Parse either form
+    pub fn parse(version: &str) -> Result<Self> {
+        Self::parse_inner(version.trim()).ok_or_else(|| {
+            CrossError::InvalidArgument(format!(
+                "Invalid --ndk-version '{version}': expected a release such as r25c, r27d or \
+                 r29-beta1, or an sdkmanager version such as 27.2.12479018"
+            ))
+        })
+    }
+
+    fn parse_inner(version: &str) -> Option<Self> {
+        let (version, prerelease) = match version.split_once('-') {
+            Some((version, tag))
+                if !tag.is_empty() && tag.chars().all(|c| c.is_ascii_alphanumeric()) =>
+            {
+                (version, Some(tag.to_ascii_lowercase()))
+            }
+            Some(_) => return None,
+            None => (version, None),
+        };
+        let number = |part: &str| {
+            (!part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+                .then(|| part.parse().ok())
+                .flatten()
+        };
+        if let Some(release) = version.strip_prefix(['r', 'R']) {
+            let (major, letter) = match release.char_indices().last()? {
+                (i, c) if c.is_ascii_alphabetic() => (&release[..i], Some(c.to_ascii_lowercase())),
+                _ => (release, None),
+            };
+            let minor = match letter {
+                None => 0,
+                // Releases go r27, r27b, r27c: there is no "a"
+                Some('a') => return None,
+                Some(letter) => u32::from(letter) - u32::from('a'),
+            };
+            return Some(Self {
+                major: number(major)?,
+                minor,
+                build: None,
+                prerelease,
+            });
+        }
+        let mut parts = version.split('.');
+        let major = number(parts.next()?)?;
+        let minor = number(parts.next()?)?;
+        let build = match parts.next() {
+            Some(build) => Some(u64::from(number(build)?)),
+            None => None,
+        };
+        if parts.next().is_some() || minor >= 26 {
+            return None;
+        }
+        Some(Self {
+            major,
+            minor,
+            build,
+            prerelease,
+        })
+    }
+
+    /// Release name used by the download URL: `r27d`, `r29-beta1`
+    #[must_use]
+    pub fn release_name(&self) -> String {
+        let mut name = format!("r{}", self.major);
+        if self.minor > 0 {
+            name.push(char::from(b'a' + u8::try_from(self.minor).unwrap_or(25)));
+        }
+        if let Some(ref tag) = self.prerelease {
+            name.push('-');
+            name.push_str(tag);
+        }
+        name
+    }
+
+    /// Numeric form: `27.3`, or `27.3.13750724` when the build is known
+    #[must_use]
+    pub fn numeric(&self) -> String {
+        match self.build {
+            Some(build) => format!("{}.{}.{build}", self.major, self.minor),
+            None => format!("{}.{}", self.major, self.minor),
+        }
+    }
+
+    /// The `Pkg.Revision` of an NDK's `source.properties`
+    #[must_use]
+    pub fn from_source_properties(content: &str) -> Option<Self> {
+        content.lines().find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "Pkg.Revision")
+                .then(|| Self::parse_inner(value.trim()))
+                .flatten()
+        })
+    }
+
+    /// Whether an installed NDK of version `installed` is this version. Builds are only
+    /// compared when both sides know theirs.
+    #[must_use]
+    pub fn matches(&self, installed: &Self) -> bool {
+        self.major == installed.major
+            && self.minor == installed.minor
+            && self.prerelease == installed.prerelease
+            && (self.build.is_none() || installed.build.is_none() || self.build == installed.build)
+    }
+}
+
+/// A pre-release sorts before its release: `r29-beta1` < `r29-rc1` < `r29` < `r29b`
+impl Ord for NdkVersion {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let prerelease = match (&self.prerelease, &other.prerelease) {
+            (None, None) => std::cmp::Ordering::Equal,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (Some(a), Some(b)) => a.cmp(b),
+        };
+        (self.major, self.minor)
+            .cmp(&(other.major, other.minor))
+            .then(prerelease)
+            .then(self.build.cmp(&other.build))
+    }
+}
+
+impl PartialOrd for NdkVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Warn when the `source.properties` of the NDK in `ndk_dir` names another version than
+/// the one requested, e.g. after a directory was replaced by hand
+async fn check_installed_version(ndk_dir: &Path, ndk_version: &str) {
+    let Ok(requested) = NdkVersion::parse(ndk_version) else {
+        return;
+    };
+    let Ok(properties) = fs::read_to_string(ndk_dir.join("source.properties")).await else {
+        return;
+    };
+    if let Some(installed) = NdkVersion::from_source_properties(&properties) {
+        if !requested.matches(&installed) {
+            color::log_warning_once(
+                &format!("ndk-version-{}", ndk_dir.display()),
+                &format!(
+                    "The NDK in {} is {} ({}), not the requested {}",
+                    ndk_dir.display(),
+                    installed.release_name(),
+                    installed.numeric(),
+                    requested.release_name()
+                ),
+            );
+        }
+    }
+}
+
+/// Look up an architecture, checking that `ndk_version` ships it
+pub fn android_arch(arch: Arch, ndk_version: &str) -> Result<&'static AndroidArch> {
+    let entry = ANDROID_ARCHES
+        .iter()
+        .find(|entry| entry.arch == arch)
+        .ok_or_else(|| CrossError::UnsupportedArchitecture {
+            arch: arch.as_str().to_string(),
+            os: "android".to_string(),
+        })?;
+    if NdkVersion::parse(ndk_version)?.major < entry.min_ndk {
+        return Err(CrossError::NdkTooOld {
+            arch: arch.as_str().to_string(),
+            needed: format!("r{}", entry.min_ndk),
+            requested: ndk_version.to_string(),
+        });
+    }
+    Ok(entry)
+}
+
+/// Validate that an Android target can be set up, without touching the network or filesystem
+pub fn validate(target_config: &TargetConfig, args: &Args) -> Result<()> {
+    android_arch(target_config.arch, &args.ndk_version).map(|_| ())
+}
+
+/// Directory the NDK is installed in, e.g. `android-ndk-linux-r27d`
+#[must_use]
+pub fn ndk_dir_name(host: &HostPlatform, ndk_version: &str) -> String {
+    format!("android-ndk-{}-{ndk_version}", host.os)
+}
+
+/// Android NDK release for this host
+#[must_use]
+pub fn ndk_release(args: &Args, host: &HostPlatform) -> ToolchainRelease {
+    let dir = layout::sdk_dir(
+        &args.cross_compiler_dir,
+        &ndk_dir_name(host, &args.ndk_version),
+    );
+    ToolchainRelease {
+        kind: "android-ndk",
+        url: format!(
+            "https://dl.google.com/android/repository/android-ndk-{}-{}.zip",
+            args.ndk_version, host.os
+        ),
+        format: Some(ArchiveFormat::Zip),
+        marker: dir.clone(),
+        dir,
+    }
+}
+
 /// Setup Android cross-compilation environment
 pub async fn setup(
     target_config: &TargetConfig,
@@ -18,28 +428,19 @@ pub async fn setup(
 ) -> Result<CrossEnv> {
     let arch = target_config.arch;
     let rust_target = target_config.target;
+    let android_arch = android_arch(arch, &args.ndk_version)?;
+    let clang_prefix = android_arch.clang_prefix();
+    let android_abi = android_arch.android_abi;
 
-    let ndk_dir = args
-        .cross_compiler_dir
-        .join(format!("android-ndk-{}-{}", host.os, args.ndk_version));
+    let release = ndk_release(args, host);
+    let ndk_dir = release.dir.clone();
 
     // Use nested joins to ensure native path separators on Windows
     let prebuilt_dir = ndk_dir.join("toolchains").join("llvm").join("prebuilt");
 
     // Download NDK if not present
-    if !ndk_dir.exists() {
-        let ndk_url = format!(
-            "https://dl.google.com/android/repository/android-ndk-{}-{}.zip",
-            args.ndk_version, host.os
-        );
-
-        download_and_extract(
-            &ndk_url,
-            &ndk_dir,
-            Some(crate::download::ArchiveFormat::Zip),
-            args.github_proxy.as_deref(),
-        )
-        .await?;
+    if !release.is_installed() {
+        release.ensure(args).await?;
 
         // Move contents from nested directory if present
         let nested_dir = ndk_dir.join(format!("android-ndk-{}", args.ndk_version));
@@ -54,73 +455,55 @@ pub async fn setup(
         }
     }
 
+    check_installed_version(&ndk_dir, &args.ndk_version).await;
+
     // Detect available prebuilt directory after download
     let clang_base_dir = find_prebuilt_bin_dir(&prebuilt_dir, host).await?;
 
-    // Map architecture to Android target prefix
-    let (clang_prefix, android_abi) = match arch {
-        Arch::Armv7 => ("armv7a-linux-androideabi24", "armeabi-v7a"),
-        Arch::Aarch64 => ("aarch64-linux-android24", "arm64-v8a"),
-        Arch::I686 => ("i686-linux-android24", "x86"),
-        Arch::X86_64 => ("x86_64-linux-android24", "x86_64"),
-        Arch::Riscv64 => ("riscv64-linux-android35", "riscv64"),
-        _ => {
-            return Err(CrossError::UnsupportedArchitecture {
-                arch: arch.as_str().to_string(),
-                os: "android".to_string(),
-            });
-        }
-    };
-
     let mut env = CrossEnv::new();
+    env.set_toolchain_dir(&ndk_dir);
 
-    // Set compiler paths
-    // On Windows, Android NDK provides .cmd wrappers (not .exe) for clang
-    // These .cmd scripts set up the environment and call the real clang
-    // We must use .cmd extension because Windows won't execute extensionless files
-    let clang_ext = if host.is_windows() { ".cmd" } else { "" };
-    env.set_cc(format!("{clang_prefix}-clang{clang_ext}"));
-    env.set_cxx(format!("{clang_prefix}-clang++{clang_ext}"));
-    env.set_ar(format!(
-        "llvm-ar{}",
-        if host.is_windows() { ".exe" } else { "" }
-    ));
-    env.set_linker(format!("{clang_prefix}-clang{clang_ext}"));
-    env.add_path(&clang_base_dir);
+    let wrapper_dir = (!args.android_use_ndk_wrappers).then(|| ndk_dir.join(CLANG_WRAPPER_DIR));
+    setup_ndk_tools(
+        &mut env,
+        &clang_base_dir,
+        &clang_prefix,
+        host,
+        wrapper_dir.as_deref(),
+    )?;
 
     // Create wrapper toolchain file for cmake
     // Use nested joins to ensure native path separators on Windows
     let wrapper_toolchain_dir = ndk_dir.join("build").join("cmake").join("wrappers");
-    let wrapper_toolchain_file = wrapper_toolchain_dir.join(format!("android-{android_abi}.cmake"));
+    let wrapper_toolchain_base = wrapper_toolchain_dir.join(format!("android-{android_abi}.cmake"));
     let ndk_toolchain_file = ndk_dir
         .join("build")
         .join("cmake")
         .join("android.toolchain.cmake");
 
-    if !wrapper_toolchain_file.exists() {
-        fs::create_dir_all(&wrapper_toolchain_dir).await?;
-
-        let toolchain_content = format!(
-            r#"# Auto-generated Android toolchain wrapper
+    let toolchain_content = format!(
+        r#"# Auto-generated Android toolchain wrapper
 set(ANDROID_ABI "{}")
-set(ANDROID_PLATFORM "android-24")
+set(ANDROID_PLATFORM "android-{}")
 set(ANDROID_NDK "{}")
 include("{}")
 "#,
-            android_abi,
-            to_cmake_path(&ndk_dir),
-            to_cmake_path(&ndk_toolchain_file)
-        );
-
-        fs::write(&wrapper_toolchain_file, toolchain_content).await?;
-    }
+        android_abi,
+        android_arch.min_api,
+        to_cmake_path(&ndk_dir),
+        to_cmake_path(&ndk_toolchain_file)
+    );
+    let wrapper_toolchain_file =
+        write_idempotent(&wrapper_toolchain_base, &toolchain_content, false)?;
 
-    // Use the Android wrapper toolchain file for CMake-based builds.
-    // This is exported later as a target-specific CMAKE_TOOLCHAIN_FILE_<target>.
-    env.set_custom_cmake_toolchain(&wrapper_toolchain_file);
+    if args.run_step(SetupStep::Cmake, rust_target) {
+        // Use the Android wrapper toolchain file for CMake-based builds.
+        // This is exported later as a target-specific CMAKE_TOOLCHAIN_FILE_<target>.
+        env.set_custom_cmake_toolchain(&wrapper_toolchain_file);
 
-    // Setup CMake generator (auto-detect on Windows, use specified on any platform)
-    setup_cmake(&mut env, args.cmake_generator.as_deref(), host.is_windows());
+        // Setup CMake generator (auto-detect on Windows, use specified on any platform)
+        setup_cmake(&mut env, args.cmake_generator.as_deref(), host.is_windows());
+    }
 
     // Set LIBCLANG_PATH for bindgen
     let ndk_llvm_base = clang_base_dir.parent().unwrap_or(&clang_base_dir);
@@ -192,3 +575,244 @@ async fn find_prebuilt_bin_dir(prebuilt_dir: &PathBuf, host: &HostPlatform) -> R
         path: prebuilt_dir.clone(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ndk_version_forms() {
+        let version = |major, minor, build: Option<u64>, prerelease: Option<&str>| NdkVersion {
+            major,
+            minor,
+            build,
+            prerelease: prerelease.map(str::to_string),
+        };
+        for (input, expected, release, numeric) in [
+            ("r25c", version(25, 2, None, None), "r25c", "25.2"),
+            ("r27d", version(27, 3, None, None), "r27d", "27.3"),
+            ("R26", version(26, 0, None, None), "r26", "26.0"),
+            (
+                "r29-beta1",
+                version(29, 0, None, Some("beta1")),
+                "r29-beta1",
+                "29.0",
+            ),
+            (
+                "27.2.12479018",
+                version(27, 2, Some(12_479_018), None),
+                "r27c",
+                "27.2.12479018",
+            ),
+            (
+                "26.1.10909125",
+                version(26, 1, Some(10_909_125), None),
+                "r26b",
+                "26.1.10909125",
+            ),
+            ("25.2", version(25, 2, None, None), "r25c", "25.2"),
+            (
+                "29.0.13113456-rc1",
+                version(29, 0, Some(13_113_456), Some("rc1")),
+                "r29-rc1",
+                "29.0.13113456",
+            ),
+        ] {
+            let parsed = NdkVersion::parse(input).unwrap();
+            assert_eq!(parsed, expected, "{input}");
+            assert_eq!(parsed.release_name(), release, "{input}");
+            assert_eq!(parsed.numeric(), numeric, "{input}");
+        }
+        for input in [
+            "latest", "", "r", "r27a", "27", "27.x.1", "27.2.1.0", "r27d-", "27.30.1", "r27dd",
+        ] {
+            let err = NdkVersion::parse(input).unwrap_err();
+            assert!(matches!(err, CrossError::InvalidArgument(_)), "{input}");
+            assert!(err.to_string().contains("27.2.12479018"), "{err}");
+        }
+        assert!(NdkVersion::parse("r26b").unwrap() < NdkVersion::parse("r27").unwrap());
+        assert!(NdkVersion::parse("r27c").unwrap() < NdkVersion::parse("r27d").unwrap());
+        let order = ["r28c", "r29-beta1", "r29-beta2", "r29-rc1", "r29", "r29b"];
+        for pair in order.windows(2) {
+            assert!(
+                NdkVersion::parse(pair[0]).unwrap() < NdkVersion::parse(pair[1]).unwrap(),
+                "{pair:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_ndk_source_properties() {
+        let properties = "Pkg.Desc = Android NDK\nPkg.Revision = 27.3.13750724\n";
+        let installed = NdkVersion::from_source_properties(properties).unwrap();
+        assert_eq!(installed.numeric(), "27.3.13750724");
+        assert!(NdkVersion::parse("r27d").unwrap().matches(&installed));
+        assert!(NdkVersion::parse("27.3.13750724")
+            .unwrap()
+            .matches(&installed));
+        assert!(!NdkVersion::parse("27.3.1").unwrap().matches(&installed));
+        assert!(!NdkVersion::parse("r27c").unwrap().matches(&installed));
+
+        let beta =
+            NdkVersion::from_source_properties("Pkg.Revision = 29.0.13113456-beta1\n").unwrap();
+        assert!(NdkVersion::parse("r29-beta1").unwrap().matches(&beta));
+        assert!(!NdkVersion::parse("r29").unwrap().matches(&beta));
+        assert!(!NdkVersion::parse("r29-beta1").unwrap().matches(&installed));
+        assert_eq!(NdkVersion::from_source_properties("Pkg.Desc = x"), None);
+    }
+
+    #[test]
+    fn test_android_arch_table() {
+        for (arch, prefix, abi) in [
+            (Arch::Armv7, "armv7a-linux-androideabi24", "armeabi-v7a"),
+            (Arch::Aarch64, "aarch64-linux-android24", "arm64-v8a"),
+            (Arch::I686, "i686-linux-android24", "x86"),
+            (Arch::X86_64, "x86_64-linux-android24", "x86_64"),
+            (Arch::Riscv64, "riscv64-linux-android35", "riscv64"),
+        ] {
+            let entry = android_arch(arch, "r29").unwrap();
+            assert_eq!(entry.clang_prefix(), prefix);
+            assert_eq!(entry.android_abi, abi);
+        }
+        assert!(matches!(
+            android_arch(Arch::Mips, "r29"),
+            Err(CrossError::UnsupportedArchitecture { .. })
+        ));
+    }
+
+    #[test]
+    fn test_riscv64_floors() {
+        let entry = android_arch(Arch::Riscv64, "r27d").unwrap();
+        assert_eq!(entry.min_api, 35);
+        match android_arch(Arch::Riscv64, "r26b") {
+            Err(CrossError::NdkTooOld {
+                arch,
+                needed,
+                requested,
+            }) => {
+                assert_eq!(arch, "riscv64");
+                assert_eq!(needed, "r27");
+                assert_eq!(requested, "r26b");
+            }
+            other => panic!("expected NdkTooOld, got {other:?}"),
+        }
+        let err = android_arch(Arch::Riscv64, "r26b").unwrap_err();
+        assert!(err.to_string().contains("--ndk-version r27"), "{err}");
+        assert!(matches!(
+            android_arch(Arch::Riscv64, "canary"),
+            Err(CrossError::InvalidArgument(_))
+        ));
+        // The numeric form gates the same way
+        assert!(android_arch(Arch::Riscv64, "27.2.12479018").is_ok());
+        assert!(android_arch(Arch::Riscv64, "26.1.10909125").is_err());
+    }
+
+    /// Split a Windows command line the way the MSVC runtime builds `argv`
+    fn split_command_line(line: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut chars = line.chars().peekable();
+        loop {
+            while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            if chars.peek().is_none() {
+                return args;
+            }
+            let mut arg = String::new();
+            let mut quoted = false;
+            let mut backslashes = 0;
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => backslashes += 1,
+                    '"' => {
+                        arg.extend(std::iter::repeat_n('\\', backslashes / 2));
+                        let escaped =
+                            backslashes % 2 == 1 || (quoted && chars.next_if_eq(&'"').is_some());
+                        if escaped {
+                            arg.push('"');
+                        } else {
+                            quoted = !quoted;
+                        }
+                        backslashes = 0;
+                    }
+                    ' ' | '\t' if !quoted => break,
+                    _ => {
+                        arg.extend(std::iter::repeat_n('\\', backslashes));
+                        backslashes = 0;
+                        arg.push(c);
+                    }
+                }
+            }
+            arg.extend(std::iter::repeat_n('\\', backslashes));
+            args.push(arg);
+        }
+    }
+
+    /// The command line cmd.exe runs for the wrapper's last line when called with `args`
+    fn expand_wrapper(wrapper: &str, args: &[&str]) -> String {
+        let line = wrapper.lines().last().unwrap().trim_end_matches('\r');
+        let forwarded: Vec<_> = args.iter().map(|arg| quote_windows_arg(arg)).collect();
+        line.replace("%*", &forwarded.join(" ")).replace("%%", "%")
+    }
+
+    #[test]
+    fn test_quote_windows_arg() {
+        for (arg, expected) in [
+            ("-O2", "-O2"),
+            ("", "\"\""),
+            ("a b", "\"a b\""),
+            ("-DVERSION=\"1.2\"", "\"-DVERSION=\\\"1.2\\\"\""),
+            ("C:\\dir with space\\", "\"C:\\dir with space\\\\\""),
+        ] {
+            assert_eq!(quote_windows_arg(arg), expected, "{arg}");
+            assert_eq!(split_command_line(&quote_windows_arg(arg)), [arg]);
+        }
+    }
+
+    #[test]
+    fn test_clang_wrapper_forwards_arguments() {
+        let clang = Path::new(r"C:\Program Files\ndk 100%\bin\clang.exe");
+        let wrapper = render_clang_wrapper(clang, "aarch64-linux-android24");
+        assert!(wrapper.starts_with("@echo off\r\n"));
+        assert!(wrapper.contains(r#""C:\Program Files\ndk 100%%\bin\clang.exe""#));
+
+        let args = [
+            "-DVERSION=\"1.2\"",
+            "-DNAME=hello world",
+            "-I",
+            r"C:\src\include dir\",
+            "-DEMPTY=",
+            "--sysroot=C:\\ndk\\sysroot",
+            "-c",
+            "foo.c",
+        ];
+        let argv = split_command_line(&expand_wrapper(&wrapper, &args));
+        assert_eq!(argv[0], r"C:\Program Files\ndk 100%\bin\clang.exe");
+        assert_eq!(argv[1], "--target=aarch64-linux-android24");
+        assert_eq!(argv[2..], args);
+    }
+
+    #[test]
+    fn test_clang_wrapper_escapes_cmd_metacharacters() {
+        let clang = Path::new(r"C:\R&D|tools\^ndk%PATH%\clang.exe");
+        let wrapper = render_clang_wrapper(clang, "aarch64-linux-android24");
+        let line = wrapper.lines().last().unwrap();
+
+        // cmd treats & | ^ as operators and escapes only outside double quotes
+        let mut quoted = false;
+        for c in line.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                '&' | '|' | '^' | '<' | '>' => assert!(quoted, "unquoted {c} in {line}"),
+                _ => {}
+            }
+        }
+        // %PATH% would be expanded even inside quotes
+        assert!(line.contains("%%PATH%%"), "{line}");
+
+        let argv = split_command_line(&expand_wrapper(&wrapper, &["-c", "foo.c"]));
+        assert_eq!(argv[0], r"C:\R&D|tools\^ndk%PATH%\clang.exe");
+        assert_eq!(
+            argv[1..],
+            ["--target=aarch64-linux-android24", "-c", "foo.c"]
+        );
+    }
+}
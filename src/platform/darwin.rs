@@ -1,14 +1,41 @@
 //! Darwin (macOS) cross-compilation setup
 
-use crate::cli::Args;
+use crate::cli::{Args, SetupStep};
 use crate::color;
 use crate::config::{Arch, HostPlatform, TargetConfig};
-use crate::download::download_and_extract;
 use crate::env::CrossEnv;
 use crate::error::{CrossError, Result};
-use crate::platform::{setup_cmake, setup_generic_cmake_toolchain};
+use crate::layout;
+use crate::platform::{setup_apple_cross_tools, setup_cmake_step, ToolchainRelease};
 use crate::runner;
 
+/// Validate that a Darwin target can be set up from this host, without touching the network
+/// or filesystem
+pub fn validate(host: &HostPlatform) -> Result<()> {
+    if host.is_darwin() {
+        Ok(())
+    } else if host.is_linux() {
+        osxcross_host_arch(host).map(|_| ())
+    } else {
+        Err(CrossError::CrossCompilationNotSupported {
+            target_os: "darwin".to_string(),
+            host_os: host.os.to_string(),
+        })
+    }
+}
+
+/// Map the host architecture to the osxcross release name
+fn osxcross_host_arch(host: &HostPlatform) -> Result<&'static str> {
+    match host.arch {
+        "x86_64" | "amd64" => Ok("amd64"),
+        "aarch64" | "arm64" => Ok("aarch64"),
+        _ => Err(CrossError::CrossCompilationNotSupported {
+            target_os: "darwin".to_string(),
+            host_os: format!("{}/{}", host.os, host.arch),
+        }),
+    }
+}
+
 /// Setup Darwin cross-compilation environment
 pub async fn setup(
     target_config: &TargetConfig,
@@ -40,8 +67,8 @@ async fn setup_native(
     let mut env = CrossEnv::new();
 
     // Setup Rosetta runner for x86_64 targets on ARM macOS
-    if args.command.needs_runner() {
-        runner::setup_rosetta_runner(&mut env, arch, rust_target, host);
+    if args.needs_runner() && args.run_step(SetupStep::Runner, rust_target) {
+        runner::setup_rosetta_runner(&mut env, arch, rust_target, args, host)?;
     }
 
     // Priority: MACOS_SDK_PATH > MACOS_SDK_VERSION > system default
@@ -56,7 +83,7 @@ async fn setup_native(
 
     if let Some(ref sdk) = sdk_path {
         env.set_sdkroot(sdk);
-        env.add_rustflag(format!("-C link-arg=--sysroot={}", sdk.display()));
+        env.add_rustflag_arg(format!("-Clink-arg=--sysroot={}", sdk.display()));
         color::log_success(&format!(
             "Using macOS SDK at {}",
             color::cyan(&sdk.display().to_string())
@@ -64,8 +91,7 @@ async fn setup_native(
     }
 
     // Setup CMake generator if specified
-    setup_cmake(&mut env, args.cmake_generator.as_deref(), host.is_windows());
-    setup_generic_cmake_toolchain(&mut env);
+    setup_cmake_step(&mut env, rust_target, args, host);
 
     color::log_success(&format!(
         "Using native macOS toolchain for {}",
@@ -75,56 +101,53 @@ async fn setup_native(
     Ok(env)
 }
 
-/// Setup osxcross for cross-compilation from Linux
-async fn setup_osxcross(
-    arch: Arch,
-    rust_target: &str,
+/// osxcross release for a Linux host; `ubuntu_version` selects the glibc baseline
+pub fn osxcross_release(
     args: &Args,
     host: &HostPlatform,
-) -> Result<CrossEnv> {
+    ubuntu_version: &str,
+) -> Result<ToolchainRelease> {
     // Map host architecture
-    let host_arch_name = match host.arch {
-        "x86_64" | "amd64" => "amd64",
-        "aarch64" | "arm64" => "aarch64",
-        _ => {
-            return Err(CrossError::CrossCompilationNotSupported {
-                target_os: "darwin".to_string(),
-                host_os: format!("{}/{}", host.os, host.arch),
-            });
-        }
-    };
+    let host_arch_name = osxcross_host_arch(host)?;
 
     let osxcross_version = "v0.2.6";
     let macos_sdk_suffix = args.macos_sdk_version.replace('.', "-");
-    let osxcross_dir = args.cross_compiler_dir.join(format!(
-        "osxcross-{macos_sdk_suffix}-{host_arch_name}-{osxcross_version}"
-    ));
+    let dir = layout::sdk_dir(
+        &args.cross_compiler_dir,
+        &format!("osxcross-{macos_sdk_suffix}-{host_arch_name}-{osxcross_version}"),
+    );
+    let url_arch = if host_arch_name == "amd64" {
+        "x86_64"
+    } else {
+        host_arch_name
+    };
 
-    // Download osxcross if not present
-    if !osxcross_dir.join("bin").exists() {
-        let ubuntu_version = super::get_ubuntu_version()
-            .await
-            .unwrap_or_else(|| "20.04".to_string());
-        let url_arch = if host_arch_name == "amd64" {
-            "x86_64"
-        } else {
-            host_arch_name
-        };
-
-        let download_url = format!(
+    Ok(ToolchainRelease {
+        kind: "osxcross",
+        url: format!(
             "https://github.com/zijiren233/osxcross/releases/download/{osxcross_version}/osxcross-{macos_sdk_suffix}-linux-{url_arch}-gnu-ubuntu-{ubuntu_version}.tar.gz"
-        );
-
-        download_and_extract(
-            &download_url,
-            &osxcross_dir,
-            None,
-            args.github_proxy.as_deref(),
-        )
-        .await?;
-    }
+        ),
+        format: None,
+        marker: dir.join("bin"),
+        dir,
+    })
+}
+
+/// Setup osxcross for cross-compilation from Linux
+async fn setup_osxcross(
+    arch: Arch,
+    rust_target: &str,
+    args: &Args,
+    host: &HostPlatform,
+) -> Result<CrossEnv> {
+    // Download osxcross if not present
+    let ubuntu_version = super::host_compat::ubuntu_flavor(args).await.version;
+    let release = osxcross_release(args, host, &ubuntu_version)?;
+    release.ensure(args).await?;
+    let osxcross_dir = release.dir;
 
     let mut env = CrossEnv::new();
+    env.set_toolchain_dir(&osxcross_dir);
 
     // Setup library path for linker to find its shared libraries
     super::setup_darwin_linker_library_path(&mut env, &osxcross_dir);
@@ -139,7 +162,8 @@ async fn setup_osxcross(
     }
 
     // Find the clang binary using wildcard pattern
-    let clang_pattern = format!("{}-apple-darwin*-clang", arch.as_str());
+    let clang_suffix = format!("-clang{}", host.exe_ext());
+    let clang_pattern = format!("{}-apple-darwin*{clang_suffix}", arch.as_str());
     let clang_path = super::find_file_by_pattern(&osxcross_dir.join("bin"), &clang_pattern)
         .await
         .ok_or_else(|| CrossError::CompilerNotFound {
@@ -150,19 +174,14 @@ async fn setup_osxcross(
     let tool_prefix = clang_path
         .file_name()
         .and_then(|n| n.to_str())
-        .and_then(|n| n.strip_suffix("-clang"))
+        .and_then(|n| n.strip_suffix(clang_suffix.as_str()))
         .ok_or_else(|| CrossError::CompilerNotFound {
             path: clang_path.clone(),
         })?
         .to_string();
 
-    // Set compiler paths
-    env.set_cc(format!("{tool_prefix}-clang"));
-    env.set_cxx(format!("{tool_prefix}-clang++"));
-    env.set_ar(format!("{tool_prefix}-ar"));
-    env.set_linker(format!("{tool_prefix}-clang"));
-    env.add_path(osxcross_dir.join("bin"));
-    env.add_path(osxcross_dir.join("clang/bin"));
+    // Set compiler paths and linker flags
+    setup_apple_cross_tools(&mut env, &osxcross_dir, &tool_prefix, host);
 
     // Set COMPILER_PATH for cc crate
     env.set_env(
@@ -170,11 +189,6 @@ async fn setup_osxcross(
         osxcross_dir.join("bin").display().to_string(),
     );
 
-    // Set linker flags
-    let linker_path = osxcross_dir.join("bin").join(format!("{tool_prefix}-ld"));
-    env.add_ldflag(format!("-fuse-ld={}", linker_path.display()));
-    env.add_rustflag(format!("-C link-arg=-fuse-ld={}", linker_path.display()));
-
     // Set SDKROOT from osxcross SDK directory
     let sdk_dir = osxcross_dir.join("SDK");
     if sdk_dir.exists() {
@@ -184,7 +198,7 @@ async fn setup_osxcross(
                 if name.to_string_lossy().starts_with("MacOSX") {
                     let sdk_path = entry.path();
                     env.set_sdkroot(&sdk_path);
-                    env.add_rustflag(format!("-C link-arg=--sysroot={}", sdk_path.display()));
+                    env.add_rustflag_arg(format!("-Clink-arg=--sysroot={}", sdk_path.display()));
                     break;
                 }
             }
@@ -192,8 +206,7 @@ async fn setup_osxcross(
     }
 
     // Setup CMake generator if specified
-    setup_cmake(&mut env, args.cmake_generator.as_deref(), host.is_windows());
-    setup_generic_cmake_toolchain(&mut env);
+    setup_cmake_step(&mut env, rust_target, args, host);
 
     color::log_success(&format!(
         "Configured osxcross toolchain (SDK {}) for {}",
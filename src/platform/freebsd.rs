@@ -3,88 +3,69 @@
 use crate::cli::Args;
 use crate::color;
 use crate::config::{Arch, HostPlatform, TargetConfig};
-use crate::env::{set_gcc_lib_paths, setup_sysroot_env, CrossEnv};
+use crate::env::CrossEnv;
 use crate::error::{CrossError, Result};
-use crate::platform::{setup_cmake, setup_cross_compile_prefix, setup_generic_cmake_toolchain};
+use crate::platform::{
+    cross_make_release, setup_cmake_step, setup_gcc_env, setup_gcc_tools, ToolchainRelease,
+};
 
-/// Setup FreeBSD cross-compilation environment
-pub async fn setup(
-    target_config: &TargetConfig,
-    args: &Args,
-    host: &HostPlatform,
-) -> Result<CrossEnv> {
+/// Validate that a FreeBSD target can be set up, without touching the network or filesystem
+pub fn validate(target_config: &TargetConfig) -> Result<()> {
     let arch = target_config.arch;
-    let rust_target = target_config.target;
-    let freebsd_version = &args.freebsd_version;
-
-    // Validate architecture
-    if !matches!(
+    if matches!(
         arch,
         Arch::X86_64 | Arch::Aarch64 | Arch::Powerpc64 | Arch::Powerpc64le | Arch::Riscv64
     ) {
-        return Err(CrossError::UnsupportedArchitecture {
+        Ok(())
+    } else {
+        Err(CrossError::UnsupportedArchitecture {
             arch: arch.as_str().to_string(),
             os: "freebsd".to_string(),
-        });
+        })
     }
+}
 
-    let bin_prefix = format!("{}-unknown-freebsd{}", arch.as_str(), freebsd_version);
-    let cross_compiler_name = format!("{bin_prefix}-cross");
-
-    // Add .exe extension on Windows
-    let exe_ext = if host.is_windows() { ".exe" } else { "" };
-    let gcc_name = format!("{bin_prefix}-gcc{exe_ext}");
-    let compiler_dir = args.cross_compiler_dir.join(format!(
-        "{}-{}",
-        cross_compiler_name, args.cross_make_version
-    ));
-
-    // Download compiler if not present
-    let gcc_path = compiler_dir.join("bin").join(&gcc_name);
-    if !gcc_path.exists() {
-        let host_platform = host.download_platform();
-
-        // Windows hosts use .zip, others use .tgz
-        let (extension, format_hint) = if host.is_windows() {
-            (".zip", Some(crate::download::ArchiveFormat::Zip))
-        } else {
-            (".tgz", Some(crate::download::ArchiveFormat::TarGz))
-        };
+/// Tool prefix of the FreeBSD toolchain, e.g. `x86_64-unknown-freebsd13.5`
+#[must_use]
+pub fn bin_prefix(arch: Arch, freebsd_version: &str) -> String {
+    format!("{}-unknown-freebsd{freebsd_version}", arch.as_str())
+}
 
-        let download_url = format!(
-            "https://github.com/zijiren233/cross-make/releases/download/{}-{}/{}{}",
-            args.cross_make_version, host_platform, cross_compiler_name, extension
-        );
-        crate::download::download_and_extract(
-            &download_url,
-            &compiler_dir,
-            format_hint,
-            args.github_proxy.as_deref(),
-        )
-        .await?;
-    }
+/// Cross-make release that provides the toolchain for a FreeBSD target
+#[must_use]
+pub fn toolchain_release(
+    target_config: &TargetConfig,
+    args: &Args,
+    host: &HostPlatform,
+) -> ToolchainRelease {
+    let bin_prefix = bin_prefix(target_config.arch, &args.freebsd_version);
+    cross_make_release(&format!("{bin_prefix}-cross"), &bin_prefix, args, host)
+}
 
-    let mut env = CrossEnv::new();
-    let bin_dir = compiler_dir.join("bin");
+/// Setup FreeBSD cross-compilation environment
+pub async fn setup(
+    target_config: &TargetConfig,
+    args: &Args,
+    host: &HostPlatform,
+) -> Result<CrossEnv> {
+    let rust_target = target_config.target;
+    let freebsd_version = &args.freebsd_version;
 
-    env.set_cc(&gcc_name);
-    env.set_cxx(format!("{bin_prefix}-g++{exe_ext}"));
-    env.set_ar(format!("{bin_prefix}-ar{exe_ext}"));
-    env.set_linker(&gcc_name);
-    env.add_path(&bin_dir);
+    // Validate architecture
+    validate(target_config)?;
 
-    // Add library search paths from gcc to rustc
-    set_gcc_lib_paths(&mut env, &compiler_dir, &bin_prefix);
+    let bin_prefix = bin_prefix(target_config.arch, &args.freebsd_version);
 
-    // Set BINDGEN_EXTRA_CLANG_ARGS for cross-compilation
-    setup_sysroot_env(&mut env, &compiler_dir, &bin_prefix, rust_target);
+    let release = toolchain_release(target_config, args, host);
+    release.ensure(args).await?;
+    let compiler_dir = release.dir;
 
-    // Set CROSS_COMPILE prefix for cc crate and other build systems
-    setup_cross_compile_prefix(&mut env, &bin_prefix);
+    let mut env = CrossEnv::new();
+    env.set_toolchain_dir(&compiler_dir);
+    setup_gcc_tools(&mut env, &compiler_dir, &bin_prefix, host);
 
-    // Setup CMake generator (auto-detect on Windows, use specified on any platform)
-    setup_cmake(&mut env, args.cmake_generator.as_deref(), host.is_windows());
-    setup_generic_cmake_toolchain(&mut env);
+    setup_gcc_env(&mut env, &compiler_dir, &bin_prefix, rust_target, args);
+    setup_cmake_step(&mut env, rust_target, args, host);
 
     color::log_success(&format!(
         "Configured FreeBSD {} toolchain for {}",
@@ -0,0 +1,219 @@
+//! GCC version of cross-make toolchains and the C++ support it implies
+
+use crate::cli::Args;
+use crate::color;
+use crate::config::HostPlatform;
+use crate::error::run_command_output;
+use std::path::Path;
+use tokio::process::Command as TokioCommand;
+
+/// File in a toolchain directory caching the output of `gcc -dumpversion`
+const GCC_VERSION_FILE: &str = ".cargo-cross-gcc-version";
+
+/// C++ support of a GCC release series
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CxxSupport {
+    /// Oldest GCC major version in the series
+    pub min_gcc: u32,
+    /// `-std` used when none is given
+    pub default_std: &'static str,
+    /// Newest standard whose language and library support is complete
+    pub complete_std: &'static str,
+}
+
+/// GCC major version to C++ support, newest first
+const CXX_SUPPORT: &[CxxSupport] = &[
+    CxxSupport {
+        min_gcc: 14,
+        default_std: "gnu++17",
+        complete_std: "c++20",
+    },
+    CxxSupport {
+        min_gcc: 11,
+        default_std: "gnu++17",
+        complete_std: "c++17",
+    },
+    CxxSupport {
+        min_gcc: 9,
+        default_std: "gnu++14",
+        complete_std: "c++17",
+    },
+    CxxSupport {
+        min_gcc: 6,
+        default_std: "gnu++14",
+        complete_std: "c++14",
+    },
+    CxxSupport {
+        min_gcc: 5,
+        default_std: "gnu++98",
+        complete_std: "c++14",
+    },
+    CxxSupport {
+        min_gcc: 0,
+        default_std: "gnu++98",
+        complete_std: "c++11",
+    },
+];
+
+/// Major version from `gcc -dumpversion` output ("8", "11.2.0", "13.2.1")
+#[must_use]
+pub fn parse_gcc_major(dumpversion: &str) -> Option<u32> {
+    dumpversion.trim().split('.').next()?.parse().ok()
+}
+
+/// C++ support of a GCC major version
+#[must_use]
+pub fn cxx_support(gcc_major: u32) -> CxxSupport {
+    CXX_SUPPORT
+        .iter()
+        .copied()
+        .find(|support| gcc_major >= support.min_gcc)
+        .unwrap_or(CXX_SUPPORT[CXX_SUPPORT.len() - 1])
+}
+
+/// One line relating the glibc version, the toolchain's GCC and its C++ support
+#[must_use]
+pub fn cxx_pairing_line(glibc_version: &str, gcc_major: u32) -> String {
+    let support = cxx_support(gcc_major);
+    format!(
+        "glibc {glibc_version} toolchain ships GCC {gcc_major}: C++ defaults to -std={}, complete up to {}; \
+         crates needing a newer libstdc++ require a newer --glibc-version",
+        support.default_std, support.complete_std
+    )
+}
+
+/// GCC major version recorded for a toolchain directory, if it was queried before
+#[must_use]
+pub fn cached_gcc_major(compiler_dir: &Path) -> Option<u32> {
+    std::fs::read_to_string(compiler_dir.join(GCC_VERSION_FILE))
+        .ok()
+        .as_deref()
+        .and_then(parse_gcc_major)
+}
+
+/// GCC major version of a toolchain, running `<bin_prefix>-gcc -dumpversion` only the
+/// first time and caching the answer in the toolchain directory
+pub async fn gcc_major_version(
+    compiler_dir: &Path,
+    bin_prefix: &str,
+    host: &HostPlatform,
+) -> Option<u32> {
+    if let Some(major) = cached_gcc_major(compiler_dir) {
+        return Some(major);
+    }
+
+    let gcc = compiler_dir
+        .join("bin")
+        .join(format!("{bin_prefix}-gcc{}", host.exe_ext()));
+    let mut cmd = TokioCommand::new(&gcc);
+    cmd.arg("-dumpversion");
+    let output = run_command_output(&mut cmd, &gcc.display().to_string())
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+    let version = String::from_utf8_lossy(&output.stdout);
+    let major = parse_gcc_major(&version)?;
+    let _ = std::fs::write(compiler_dir.join(GCC_VERSION_FILE), version.trim());
+    Some(major)
+}
+
+/// Whether a `Cargo.lock` pulls in crates that compile C or C++ sources
+#[must_use]
+pub fn lockfile_compiles_cxx(lockfile: &str) -> bool {
+    lockfile.lines().any(|line| {
+        matches!(
+            line.trim(),
+            r#"name = "cc""# | r#"name = "cxx""# | r#"name = "cxx-build""#
+        )
+    })
+}
+
+/// Whether the build involves C++: `--cxxstdlib` or cc/cxx crates in the lockfile
+pub(crate) async fn builds_cxx(args: &Args) -> bool {
+    if args.cxxstdlib.is_some() {
+        return true;
+    }
+    crate::metadata::workspace_lockfile(args)
+        .await
+        .is_some_and(|contents| lockfile_compiles_cxx(&contents))
+}
+
+/// Record the toolchain's GCC version and, for C++ builds, explain what it supports
+pub async fn report_gcc_version(
+    compiler_dir: &Path,
+    bin_prefix: &str,
+    glibc_version: Option<&str>,
+    args: &Args,
+    host: &HostPlatform,
+) -> Option<u32> {
+    let major = gcc_major_version(compiler_dir, bin_prefix, host).await?;
+    color::print_config("GCC", &major.to_string());
+    if let Some(glibc_version) = glibc_version {
+        if builds_cxx(args).await {
+            color::log_info(&cxx_pairing_line(glibc_version, major));
+        }
+    }
+    Some(major)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gcc_major() {
+        for (output, expected) in [
+            ("8\n", Some(8)),
+            ("11.2.0", Some(11)),
+            ("13.2.1\n", Some(13)),
+            ("", None),
+            ("gcc", None),
+        ] {
+            assert_eq!(parse_gcc_major(output), expected, "{output:?}");
+        }
+    }
+
+    #[test]
+    fn test_cxx_support_table() {
+        for (gcc, default_std, complete_std) in [
+            (4, "gnu++98", "c++11"),
+            (5, "gnu++98", "c++14"),
+            (8, "gnu++14", "c++14"),
+            (9, "gnu++14", "c++17"),
+            (11, "gnu++17", "c++17"),
+            (13, "gnu++17", "c++17"),
+            (15, "gnu++17", "c++20"),
+        ] {
+            let support = cxx_support(gcc);
+            assert_eq!(support.default_std, default_std, "GCC {gcc}");
+            assert_eq!(support.complete_std, complete_std, "GCC {gcc}");
+        }
+    }
+
+    #[test]
+    fn test_cxx_pairing_line() {
+        let line = cxx_pairing_line("2.28", 8);
+        assert!(line.starts_with("glibc 2.28 toolchain ships GCC 8"));
+        assert!(line.contains("-std=gnu++14"));
+        assert!(line.contains("complete up to c++14"));
+    }
+
+    #[test]
+    fn test_lockfile_compiles_cxx() {
+        let lock = "[[package]]\nname = \"cxx\"\nversion = \"1.0.0\"\n";
+        assert!(lockfile_compiles_cxx(lock));
+        assert!(!lockfile_compiles_cxx(
+            "[[package]]\nname = \"cxxbridge-macro\"\n"
+        ));
+    }
+
+    #[test]
+    fn test_cached_gcc_major() {
+        let dir = std::env::temp_dir().join(format!("cargo-cross-gcc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(cached_gcc_major(&dir), None);
+        std::fs::write(dir.join(GCC_VERSION_FILE), "11.2.0").unwrap();
+        assert_eq!(cached_gcc_major(&dir), Some(11));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
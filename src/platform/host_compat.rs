@@ -0,0 +1,269 @@
+//! Choice of the Ubuntu build of osxcross and ioscross for a Linux host
+//!
+//! Their Linux releases are built on several Ubuntu versions and need that release's glibc
+//! on the host. Ubuntu hosts get their own build. Other distributions, and Ubuntu releases
+//! without a build, get the newest build whose glibc is not newer than the host's.
+
+use crate::cli::Args;
+use crate::color;
+use crate::config::HOST_COMPAT_UBUNTU_VERSIONS;
+use std::sync::OnceLock;
+use tokio::process::Command;
+
+/// Build used when nothing better is known: the oldest, which needs the oldest glibc
+pub const FALLBACK_UBUNTU_VERSION: &str = "20.04";
+
+/// The fields of `/etc/os-release` that identify the distribution
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OsRelease {
+    /// `ID`, e.g. `ubuntu`, `debian`, `arch` (`linuxmint` on Mint, whose `ID_LIKE` is
+    /// ubuntu but whose versions are its own, so it is matched by glibc)
+    pub id: String,
+    /// `VERSION_ID`, absent on rolling releases
+    pub version_id: Option<String>,
+}
+
+impl OsRelease {
+    /// Parse the `KEY=value` lines of an os-release file
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
+        let mut release = Self::default();
+        for line in content.lines() {
+            let Some((key, value)) = line.trim().split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches(['"', '\'']).to_string();
+            match key.trim() {
+                "ID" => release.id = value.to_ascii_lowercase(),
+                "VERSION_ID" if !value.is_empty() => release.version_id = Some(value),
+                _ => {}
+            }
+        }
+        release
+    }
+
+    /// Name for log messages, e.g. `debian 12` or `arch`
+    fn describe(&self) -> String {
+        match &self.version_id {
+            Some(version) => format!("{} {version}", self.id),
+            None => self.id.clone(),
+        }
+    }
+}
+
+/// The Ubuntu build to download and why it was chosen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UbuntuFlavor {
+    pub version: String,
+    pub reason: String,
+    /// Whether the host is known to have the glibc the build needs
+    pub compatible: bool,
+}
+
+/// Parse the glibc version from `getconf GNU_LIBC_VERSION` (`glibc 2.35`) or the first line
+/// of `ldd --version` (`ldd (Ubuntu GLIBC 2.35-0ubuntu3.8) 2.35`)
+#[must_use]
+pub fn parse_glibc_version(output: &str) -> Option<(u32, u32)> {
+    let line = output.lines().next()?;
+    if !line.contains("libc") && !line.contains("GLIBC") && !line.starts_with("ldd") {
+        return None;
+    }
+    let version = line.split_whitespace().last()?;
+    let (major, rest) = version.split_once('.')?;
+    let minor: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Choose the Ubuntu build for a host running `os` with glibc `glibc`
+#[must_use]
+pub fn choose_ubuntu_flavor(os: Option<&OsRelease>, glibc: Option<(u32, u32)>) -> UbuntuFlavor {
+    let flavor = |version: &str, reason: String, compatible: bool| UbuntuFlavor {
+        version: version.to_string(),
+        reason,
+        compatible,
+    };
+    let host = os.map_or_else(
+        || "host".to_string(),
+        |os| format!("{} host", os.describe()),
+    );
+    if let Some(os) = os.filter(|os| os.id == "ubuntu") {
+        if let Some((version, _)) = HOST_COMPAT_UBUNTU_VERSIONS
+            .iter()
+            .find(|(version, _)| os.version_id.as_deref() == Some(*version))
+        {
+            return flavor(version, host, true);
+        }
+    }
+    let Some((major, minor)) = glibc else {
+        return flavor(
+            FALLBACK_UBUNTU_VERSION,
+            format!(
+                "could not detect the glibc of this {host} (musl or distroless?), using the \
+                 oldest build"
+            ),
+            false,
+        );
+    };
+    match HOST_COMPAT_UBUNTU_VERSIONS
+        .iter()
+        .rev()
+        .find(|(_, needed)| *needed <= (major, minor))
+    {
+        Some((version, (need_major, need_minor))) => flavor(
+            version,
+            format!(
+                "{host} with glibc {major}.{minor}; the Ubuntu {version} build needs \
+                 {need_major}.{need_minor}"
+            ),
+            true,
+        ),
+        None => flavor(
+            FALLBACK_UBUNTU_VERSION,
+            format!(
+                "{host} has glibc {major}.{minor}, older than any build needs; the oldest \
+                 may fail to load"
+            ),
+            false,
+        ),
+    }
+}
+
+/// Read the host's os-release file
+fn read_os_release() -> Option<OsRelease> {
+    ["/etc/os-release", "/usr/lib/os-release"]
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .map(|content| OsRelease::parse(&content))
+}
+
+/// The host glibc version, from `getconf` (which reads `confstr`) or else `ldd`
+async fn host_glibc_version() -> Option<(u32, u32)> {
+    for (program, arg) in [("getconf", "GNU_LIBC_VERSION"), ("ldd", "--version")] {
+        let Ok(output) = Command::new(program).arg(arg).output().await else {
+            continue;
+        };
+        if let Some(version) = output
+            .status
+            .success()
+            .then(|| parse_glibc_version(&String::from_utf8_lossy(&output.stdout)))
+            .flatten()
+        {
+            return Some(version);
+        }
+    }
+    None
+}
+
+/// The Ubuntu build of osxcross and ioscross for this Linux host: `--host-compat-ubuntu`,
+/// or detected once per run and logged with the reason
+pub async fn ubuntu_flavor(args: &Args) -> UbuntuFlavor {
+    static DETECTED: OnceLock<UbuntuFlavor> = OnceLock::new();
+
+    if let Some(ref version) = args.host_compat_ubuntu {
+        return UbuntuFlavor {
+            version: version.clone(),
+            reason: "--host-compat-ubuntu".to_string(),
+            compatible: true,
+        };
+    }
+    if let Some(flavor) = DETECTED.get() {
+        return flavor.clone();
+    }
+    let flavor = choose_ubuntu_flavor(read_os_release().as_ref(), host_glibc_version().await);
+    if DETECTED.set(flavor.clone()).is_ok() {
+        let message = format!(
+            "Using the Ubuntu {} build of osxcross/ioscross ({})",
+            color::cyan(&flavor.version),
+            flavor.reason
+        );
+        if flavor.compatible {
+            color::log_info(&message);
+        } else {
+            color::log_warning(&format!(
+                "{message}\nPass --host-compat-ubuntu to choose another build"
+            ));
+        }
+    }
+    flavor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os(content: &str) -> OsRelease {
+        OsRelease::parse(content)
+    }
+
+    #[test]
+    fn test_parse_os_release() {
+        let mint = os(
+            "NAME=\"Linux Mint\"\nID=linuxmint\nID_LIKE=\"ubuntu debian\"\nVERSION_ID=\"21.3\"\n",
+        );
+        assert_eq!(mint.id, "linuxmint");
+        assert_eq!(mint.version_id.as_deref(), Some("21.3"));
+
+        let arch = os("NAME=\"Arch Linux\"\nID=arch\nBUILD_ID=rolling\n");
+        assert_eq!(arch.id, "arch");
+        assert_eq!(arch.version_id, None);
+    }
+
+    #[test]
+    fn test_parse_glibc_version() {
+        assert_eq!(parse_glibc_version("glibc 2.35\n"), Some((2, 35)));
+        assert_eq!(
+            parse_glibc_version(
+                "ldd (Ubuntu GLIBC 2.35-0ubuntu3.8) 2.35\nCopyright (C) 2022 Free Software"
+            ),
+            Some((2, 35))
+        );
+        assert_eq!(parse_glibc_version("ldd (GNU libc) 2.41\n"), Some((2, 41)));
+        assert_eq!(
+            parse_glibc_version("musl libc (x86_64)\nVersion 1.2.5\n"),
+            None
+        );
+        assert_eq!(parse_glibc_version(""), None);
+    }
+
+    #[test]
+    fn test_choose_ubuntu_flavor() {
+        let ubuntu_22 = os("ID=ubuntu\nVERSION_ID=\"22.04\"\n");
+        let ubuntu_25 = os("ID=ubuntu\nVERSION_ID=\"25.04\"\n");
+        let debian_11 = os("ID=debian\nVERSION_ID=\"11\"\n");
+        let debian_12 = os("ID=debian\nVERSION_ID=\"12\"\n");
+        let fedora_41 = os("ID=fedora\nVERSION_ID=41\n");
+        let arch = os("ID=arch\n");
+        let centos_7 = os("ID=centos\nVERSION_ID=\"7\"\n");
+        let alpine = os("ID=alpine\nVERSION_ID=3.20.3\n");
+
+        for (host, glibc, expected, compatible) in [
+            // Ubuntu releases with a build use it, whatever the glibc reads
+            (Some(&ubuntu_22), Some((2, 35)), "22.04", true),
+            (Some(&ubuntu_22), None, "22.04", true),
+            // Others get the newest build their glibc can load
+            (Some(&ubuntu_25), Some((2, 41)), "24.04", true),
+            (Some(&debian_11), Some((2, 31)), "20.04", true),
+            (Some(&debian_12), Some((2, 36)), "22.04", true),
+            (Some(&fedora_41), Some((2, 40)), "24.04", true),
+            (Some(&arch), Some((2, 42)), "24.04", true),
+            (None, Some((2, 39)), "24.04", true),
+            // Too old or unknown: the oldest build, flagged
+            (Some(&centos_7), Some((2, 17)), "20.04", false),
+            (Some(&alpine), None, "20.04", false),
+            (None, None, "20.04", false),
+        ] {
+            let flavor = choose_ubuntu_flavor(host, glibc);
+            assert_eq!(flavor.version, expected, "{host:?} {glibc:?}");
+            assert_eq!(flavor.compatible, compatible, "{host:?} {glibc:?}");
+        }
+
+        let flavor = choose_ubuntu_flavor(Some(&debian_12), Some((2, 36)));
+        assert_eq!(
+            flavor.reason,
+            "debian 12 host with glibc 2.36; the Ubuntu 22.04 build needs 2.35"
+        );
+        assert!(choose_ubuntu_flavor(Some(&centos_7), Some((2, 17)))
+            .reason
+            .contains("centos 7 host has glibc 2.17"));
+    }
+}
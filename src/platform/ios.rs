@@ -3,10 +3,37 @@
 use crate::cli::Args;
 use crate::color;
 use crate::config::{Arch, HostPlatform, Os, TargetConfig};
-use crate::download::download_and_extract;
 use crate::env::CrossEnv;
 use crate::error::{CrossError, Result};
-use crate::platform::{setup_cmake, setup_generic_cmake_toolchain};
+use crate::layout;
+use crate::platform::{setup_apple_cross_tools, setup_cmake_step, ToolchainRelease};
+
+/// Validate that an iOS target can be set up from this host, without touching the network
+/// or filesystem
+pub fn validate(target_config: &TargetConfig, host: &HostPlatform) -> Result<()> {
+    if host.is_darwin() {
+        Ok(())
+    } else if host.is_linux() {
+        ioscross_arch_prefix(target_config.arch).map(|_| ())
+    } else {
+        Err(CrossError::CrossCompilationNotSupported {
+            target_os: "ios".to_string(),
+            host_os: host.os.to_string(),
+        })
+    }
+}
+
+/// Map an architecture to the ioscross toolchain name
+fn ioscross_arch_prefix(arch: Arch) -> Result<&'static str> {
+    match arch {
+        Arch::Aarch64 => Ok("arm64"),
+        Arch::X86_64 => Ok("x86_64"),
+        _ => Err(CrossError::UnsupportedArchitecture {
+            arch: arch.as_str().to_string(),
+            os: "ios".to_string(),
+        }),
+    }
+}
 
 /// Setup iOS cross-compilation environment
 pub async fn setup(
@@ -16,7 +43,7 @@ pub async fn setup(
 ) -> Result<CrossEnv> {
     let arch = target_config.arch;
     let rust_target = target_config.target;
-    let is_simulator = matches!(target_config.os, Os::IosSim) || arch == Arch::X86_64;
+    let is_simulator = is_simulator(target_config);
 
     if host.is_darwin() {
         setup_native(rust_target, args, host, is_simulator).await
@@ -66,7 +93,7 @@ async fn setup_native(
 
     if let Some(ref sdk) = sdk_path {
         env.set_sdkroot(sdk);
-        env.add_rustflag(format!("-C link-arg=--sysroot={}", sdk.display()));
+        env.add_rustflag_arg(format!("-Clink-arg=--sysroot={}", sdk.display()));
         color::log_success(&format!(
             "Using iPhone SDK at {}",
             color::cyan(&sdk.display().to_string())
@@ -86,8 +113,7 @@ async fn setup_native(
     env.set_env(deployment_target, "12.0");
 
     // Setup CMake generator if specified
-    setup_cmake(&mut env, args.cmake_generator.as_deref(), host.is_windows());
-    setup_generic_cmake_toolchain(&mut env);
+    setup_cmake_step(&mut env, rust_target, args, host);
 
     color::log_success(&format!(
         "Using native macOS toolchain for {}",
@@ -97,25 +123,36 @@ async fn setup_native(
     Ok(env)
 }
 
-/// Setup ioscross for cross-compilation from Linux
-async fn setup_ioscross(
-    arch: Arch,
-    rust_target: &str,
+/// Whether a target builds for the iOS simulator
+fn is_simulator(target_config: &TargetConfig) -> bool {
+    matches!(target_config.os, Os::IosSim) || target_config.arch == Arch::X86_64
+}
+
+/// ioscross release for a Linux host; `ubuntu_version` selects the glibc baseline
+pub fn toolchain_release(
+    target_config: &TargetConfig,
     args: &Args,
     host: &HostPlatform,
+    ubuntu_version: &str,
+) -> Result<ToolchainRelease> {
+    ioscross_release(
+        target_config.arch,
+        is_simulator(target_config),
+        args,
+        host,
+        ubuntu_version,
+    )
+}
+
+fn ioscross_release(
+    arch: Arch,
     is_simulator: bool,
-) -> Result<CrossEnv> {
+    args: &Args,
+    host: &HostPlatform,
+    ubuntu_version: &str,
+) -> Result<ToolchainRelease> {
     // Map architecture
-    let arch_prefix = match arch {
-        Arch::Aarch64 => "arm64",
-        Arch::X86_64 => "x86_64",
-        _ => {
-            return Err(CrossError::UnsupportedArchitecture {
-                arch: arch.as_str().to_string(),
-                os: "ios".to_string(),
-            });
-        }
-    };
+    let arch_prefix = ioscross_arch_prefix(arch)?;
 
     let cctools_version = "v0.1.9";
     let iphone_sdk_suffix = args.iphone_sdk_version.replace('.', "-");
@@ -125,55 +162,52 @@ async fn setup_ioscross(
         cross_compiler_name.push_str("-simulator");
     }
     cross_compiler_name.push_str(&format!("-{cctools_version}-{iphone_sdk_suffix}"));
+    let dir = layout::sdk_dir(&args.cross_compiler_dir, &cross_compiler_name);
 
-    let clang_name = format!("{arch_prefix}-apple-darwin11-clang");
-    let compiler_dir = args.cross_compiler_dir.join(&cross_compiler_name);
-
-    // Download compiler if not present
-    if !compiler_dir.join("bin").join(&clang_name).exists() {
-        let host_platform = host.download_platform();
-        let ubuntu_version = super::get_ubuntu_version()
-            .await
-            .unwrap_or_else(|| "20.04".to_string());
-
-        let ios_sdk_type = if is_simulator {
-            "iPhoneSimulator"
-        } else {
-            "iPhoneOS"
-        };
+    let ios_sdk_type = if is_simulator {
+        "iPhoneSimulator"
+    } else {
+        "iPhoneOS"
+    };
+    let host_platform = host.download_platform();
 
-        let download_url = format!(
+    Ok(ToolchainRelease {
+        kind: "ioscross",
+        url: format!(
             "https://github.com/zijiren233/cctools-port/releases/download/{cctools_version}/ioscross-{ios_sdk_type}{iphone_sdk_suffix}-{arch_prefix}-{host_platform}-gnu-ubuntu-{ubuntu_version}.tar.gz"
-        );
-
-        download_and_extract(
-            &download_url,
-            &compiler_dir,
-            None,
-            args.github_proxy.as_deref(),
-        )
-        .await?;
-    }
+        ),
+        format: None,
+        marker: dir
+            .join("bin")
+            .join(format!("{arch_prefix}-apple-darwin11-clang{}", host.exe_ext())),
+        dir,
+    })
+}
+
+/// Setup ioscross for cross-compilation from Linux
+async fn setup_ioscross(
+    arch: Arch,
+    rust_target: &str,
+    args: &Args,
+    host: &HostPlatform,
+    is_simulator: bool,
+) -> Result<CrossEnv> {
+    let tool_prefix = format!("{}-apple-darwin11", ioscross_arch_prefix(arch)?);
+
+    // Download compiler if not present
+    let ubuntu_version = super::host_compat::ubuntu_flavor(args).await.version;
+    let release = ioscross_release(arch, is_simulator, args, host, &ubuntu_version)?;
+    release.ensure(args).await?;
+    let compiler_dir = release.dir;
 
     let mut env = CrossEnv::new();
+    env.set_toolchain_dir(&compiler_dir);
 
     // Setup library path for linker to find its shared libraries
     super::setup_darwin_linker_library_path(&mut env, &compiler_dir);
 
-    // Set compiler paths
-    env.set_cc(format!("{arch_prefix}-apple-darwin11-clang"));
-    env.set_cxx(format!("{arch_prefix}-apple-darwin11-clang++"));
-    env.set_ar(format!("{arch_prefix}-apple-darwin11-ar"));
-    env.set_linker(format!("{arch_prefix}-apple-darwin11-clang"));
-    env.add_path(compiler_dir.join("bin"));
-    env.add_path(compiler_dir.join("clang/bin"));
-
-    // Set linker flags
-    let linker_path = compiler_dir
-        .join("bin")
-        .join(format!("{arch_prefix}-apple-darwin11-ld"));
-    env.add_ldflag(format!("-fuse-ld={}", linker_path.display()));
-    env.add_rustflag(format!("-C link-arg=-fuse-ld={}", linker_path.display()));
+    // Set compiler paths and linker flags
+    setup_apple_cross_tools(&mut env, &compiler_dir, &tool_prefix, host);
 
     // Set SDKROOT from SDK directory
     let sdk_dir = compiler_dir.join("SDK");
@@ -198,8 +232,7 @@ async fn setup_ioscross(
     env.set_env(deployment_target, "12.0");
 
     // Setup CMake generator if specified
-    setup_cmake(&mut env, args.cmake_generator.as_deref(), host.is_windows());
-    setup_generic_cmake_toolchain(&mut env);
+    setup_cmake_step(&mut env, rust_target, args, host);
 
     color::log_success(&format!(
         "Configured iOS toolchain for {}",
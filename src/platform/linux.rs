@@ -1,105 +1,103 @@
 //! Linux cross-compilation setup
 
-use crate::cli::Args;
+use crate::cli::{Args, RunnerBackend, SetupStep};
 use crate::color;
 use crate::config::{HostPlatform, Libc, TargetConfig, DEFAULT_GLIBC_VERSION};
-use crate::env::{set_gcc_lib_paths, setup_sysroot_env, CrossEnv};
+use crate::env::CrossEnv;
 use crate::error::Result;
 use crate::platform::{
-    get_linux_bin_prefix, get_linux_folder_name, setup_cmake, setup_cross_compile_prefix,
-    setup_generic_cmake_toolchain,
+    cross_make_release, gcc, get_linux_bin_prefix, get_linux_folder_name, setup_cmake_step,
+    setup_gcc_env, setup_gcc_tools, ToolchainRelease,
 };
 use crate::runner;
 
-/// Setup Linux cross-compilation environment
-pub async fn setup(
+/// Cross-make release that provides the toolchain for a Linux target
+#[must_use]
+pub fn toolchain_release(
     target_config: &TargetConfig,
     args: &Args,
     host: &HostPlatform,
-) -> Result<CrossEnv> {
+) -> ToolchainRelease {
     let arch = target_config.arch;
     let libc = target_config.libc.expect("Linux target must have libc");
     let abi = target_config.abi;
-    let rust_target = target_config.target;
-
-    // Binary names never include glibc version (binaries are in separate versioned folders)
     let bin_prefix = get_linux_bin_prefix(arch, libc, abi);
 
     // For gnu libc, folder name includes glibc version suffix (except for default version)
     let cross_compiler_name =
         get_linux_folder_name(arch, libc, abi, &args.glibc_version, DEFAULT_GLIBC_VERSION);
 
-    // Add .exe extension on Windows
-    let exe_ext = if host.is_windows() { ".exe" } else { "" };
-    let gcc_name = format!("{bin_prefix}-gcc{exe_ext}");
-    let compiler_dir = args.cross_compiler_dir.join(format!(
-        "{}-{}",
-        cross_compiler_name, args.cross_make_version
-    ));
+    cross_make_release(&cross_compiler_name, &bin_prefix, args, host)
+}
 
-    // Download compiler if not present
-    let gcc_path = compiler_dir.join("bin").join(&gcc_name);
-    if !gcc_path.exists() {
-        let host_platform = host.download_platform();
+/// Setup Linux cross-compilation environment
+pub async fn setup(
+    target_config: &TargetConfig,
+    args: &Args,
+    host: &HostPlatform,
+) -> Result<CrossEnv> {
+    let arch = target_config.arch;
+    let libc = target_config.libc.expect("Linux target must have libc");
+    let abi = target_config.abi;
+    let rust_target = target_config.target;
 
-        // Windows hosts use .zip, others use .tgz
-        let (extension, format_hint) = if host.is_windows() {
-            (".zip", Some(crate::download::ArchiveFormat::Zip))
-        } else {
-            (".tgz", Some(crate::download::ArchiveFormat::TarGz))
-        };
+    // Binary names never include glibc version (binaries are in separate versioned folders)
+    let bin_prefix = get_linux_bin_prefix(arch, libc, abi);
 
-        let download_url = format!(
-            "https://github.com/zijiren233/cross-make/releases/download/{}-{}/{}{}",
-            args.cross_make_version, host_platform, cross_compiler_name, extension
-        );
-        crate::download::download_and_extract(
-            &download_url,
-            &compiler_dir,
-            format_hint,
-            args.github_proxy.as_deref(),
-        )
-        .await?;
-    }
+    let release = toolchain_release(target_config, args, host);
+    release.ensure(args).await?;
+    let compiler_dir = release.dir;
 
     let mut env = CrossEnv::new();
-    let bin_dir = compiler_dir.join("bin");
-
-    env.set_cc(&gcc_name);
-    env.set_cxx(format!("{bin_prefix}-g++{exe_ext}"));
-    env.set_ar(format!("{bin_prefix}-ar{exe_ext}"));
-    env.set_linker(&gcc_name);
-    env.add_path(&bin_dir);
+    env.set_toolchain_dir(&compiler_dir);
+    setup_gcc_tools(&mut env, &compiler_dir, &bin_prefix, host);
 
-    // Add library search paths from gcc to rustc
-    set_gcc_lib_paths(&mut env, &compiler_dir, &bin_prefix);
-
-    // Set BINDGEN_EXTRA_CLANG_ARGS and C_INCLUDE_PATH for cross-compilation
-    setup_sysroot_env(&mut env, &compiler_dir, &bin_prefix, rust_target);
-
-    // Set CROSS_COMPILE prefix for cc crate and other build systems
-    setup_cross_compile_prefix(&mut env, &bin_prefix);
+    let glibc_version = (libc == Libc::Gnu).then(|| {
+        if args.glibc_version.is_empty() {
+            DEFAULT_GLIBC_VERSION
+        } else {
+            args.glibc_version.as_str()
+        }
+    });
+    gcc::report_gcc_version(&compiler_dir, &bin_prefix, glibc_version, args, host).await;
+    env.glibc_version = glibc_version.map(str::to_string);
 
-    // Setup CMake generator (auto-detect on Windows, use specified on any platform)
-    setup_cmake(&mut env, args.cmake_generator.as_deref(), host.is_windows());
-    setup_generic_cmake_toolchain(&mut env);
+    setup_gcc_env(&mut env, &compiler_dir, &bin_prefix, rust_target, args);
+    setup_cmake_step(&mut env, rust_target, args, host);
 
     // Setup runner only if the command needs to execute binaries
-    if args.command.needs_runner() {
-        if host.is_darwin() {
+    if args.needs_runner() && args.run_step(SetupStep::Runner, rust_target) {
+        if args.runner_backend == RunnerBackend::Chroot && !host.is_darwin() {
+            runner::setup_chroot_runner(
+                &mut env,
+                target_config,
+                &bin_prefix,
+                &compiler_dir,
+                args,
+                host,
+            )
+            .await?;
+        } else if host.is_darwin() {
             runner::setup_docker_qemu_runner(
                 &mut env,
-                arch,
+                target_config,
+                &bin_prefix,
+                &compiler_dir,
+                args,
+                host,
+            )
+            .await?;
+        } else {
+            // Warns (or fails with --require-runner) on hosts without a QEMU bundle
+            runner::setup_qemu_runner(
+                &mut env,
+                target_config,
                 &bin_prefix,
                 &compiler_dir,
-                libc.as_str(),
                 args,
                 host,
             )
             .await?;
-        } else if host.is_linux() {
-            runner::setup_qemu_runner(&mut env, arch, &bin_prefix, &compiler_dir, args, host)
-                .await?;
         }
     }
 
@@ -117,3 +115,66 @@ pub async fn setup(
 
     Ok(env)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{test_target_args, BuildArgs};
+    use crate::env::CMakeToolchain;
+
+    fn args(skip_step: Vec<SetupStep>) -> Args {
+        test_target_args(
+            "linux-steps",
+            &["aarch64-unknown-linux-musl"],
+            BuildArgs {
+                skip_step,
+                ..BuildArgs::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_setup_steps_can_be_skipped() {
+        let compiler_dir = std::env::temp_dir().join(format!(
+            "cargo-cross-linux-steps-{}/aarch64-linux-musl-cross",
+            std::process::id()
+        ));
+        let bin_prefix = "aarch64-linux-musl";
+        let target = "aarch64-unknown-linux-musl";
+        for dir in [
+            "aarch64-linux-musl/lib",
+            "lib/gcc/aarch64-linux-musl/11.2.0",
+        ] {
+            std::fs::create_dir_all(compiler_dir.join(dir)).unwrap();
+        }
+        let host = HostPlatform::detect();
+        let configure = |skip_step: Vec<SetupStep>| {
+            let args = args(skip_step);
+            let mut env = CrossEnv::new();
+            setup_gcc_env(&mut env, &compiler_dir, bin_prefix, target, &args);
+            setup_cmake_step(&mut env, target, &args, &host);
+            env
+        };
+
+        let env = configure(Vec::new());
+        assert!(env.rustflags.contains(&"-L".to_string()));
+        assert_eq!(
+            env.sysroot.as_deref(),
+            Some(compiler_dir.join(bin_prefix).as_path())
+        );
+        assert_eq!(env.extra_env["CROSS_COMPILE"], "aarch64-linux-musl-");
+        assert!(matches!(env.cmake_toolchain, Some(CMakeToolchain::Generic)));
+
+        let env = configure(vec![SetupStep::GccLibPaths, SetupStep::CrossCompilePrefix]);
+        assert!(env.rustflags.is_empty());
+        assert!(env.sysroot.is_some());
+        assert!(!env.extra_env.contains_key("CROSS_COMPILE"));
+
+        let env = configure(vec![SetupStep::SysrootEnv, SetupStep::Cmake]);
+        assert!(!env.rustflags.is_empty());
+        assert!(env.sysroot.is_none());
+        assert!(env.cmake_toolchain.is_none());
+
+        let _ = std::fs::remove_dir_all(compiler_dir.parent().unwrap());
+    }
+}
@@ -3,17 +3,25 @@
 pub mod android;
 pub mod darwin;
 pub mod freebsd;
+pub mod gcc;
+pub mod host_compat;
 pub mod ios;
 pub mod linux;
 pub mod netbsd;
 pub mod windows;
 
-use crate::cli::Args;
+use crate::cache::split_cross_make_dir;
+use crate::cli::{Args, SetupStep};
+use crate::color;
 use crate::config::{Arch, HostPlatform, Libc, Os, TargetConfig};
-use crate::env::{CMakeToolchain, CrossEnv};
+use crate::download::{download_and_extract, release_asset_digest, ArchiveFormat};
+use crate::env::{
+    set_gcc_lib_paths, setup_sysroot_env, shell_quote, CMakeToolchain, CrossEnv, EnvMap,
+};
 use crate::error::{CrossError, Result};
+use crate::generated::write_idempotent;
+use crate::layout;
 use path_slash::PathExt as _;
-use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use tokio::process::Command;
@@ -44,7 +52,7 @@ pub fn cmake_toolchain_env_key(target: &str) -> String {
 
 /// Check if a CMake toolchain file has already been configured externally.
 #[must_use]
-pub fn has_preconfigured_cmake_toolchain(env: &HashMap<String, String>, target: &str) -> bool {
+pub fn has_preconfigured_cmake_toolchain(env: &EnvMap, target: &str) -> bool {
     let target_lower = target.replace('-', "_");
     let target_hyphen = format!("CMAKE_TOOLCHAIN_FILE_{target}");
     let target_underscore = format!("CMAKE_TOOLCHAIN_FILE_{target_lower}");
@@ -56,7 +64,7 @@ pub fn has_preconfigured_cmake_toolchain(env: &HashMap<String, String>, target:
     ];
 
     vars.iter().any(|key| {
-        env.get(*key).is_some_and(|value| !value.is_empty())
+        env.get(key).is_some_and(|value| !value.is_empty())
             || std::env::var_os(key).is_some_and(|value| !value.is_empty())
     })
 }
@@ -96,7 +104,9 @@ fn write_generic_cmake_toolchain_file(
     target_config: &TargetConfig,
     cross_env: &CrossEnv,
 ) -> Result<PathBuf> {
-    let output_dir = resolve_path_from_current_dir(&args.cross_compiler_dir)?.join("cmake");
+    let output_dir =
+        layout::generated_dir(&resolve_path_from_current_dir(&args.cross_compiler_dir)?)
+            .join("cmake");
     fs::create_dir_all(&output_dir).map_err(|source| CrossError::IoError {
         message: format!(
             "Failed to create CMake toolchain directory at {}",
@@ -105,14 +115,12 @@ fn write_generic_cmake_toolchain_file(
         source,
     })?;
 
-    let path = output_dir.join(format!("{}.cmake", target_config.target));
     let content = render_cmake_toolchain_file(target_config, cross_env);
-    fs::write(&path, content).map_err(|source| CrossError::IoError {
-        message: format!("Failed to write CMake toolchain file at {}", path.display()),
-        source,
-    })?;
-
-    Ok(path)
+    write_idempotent(
+        &output_dir.join(format!("{}.cmake", target_config.target)),
+        &content,
+        false,
+    )
 }
 
 /// Render a generic CMake toolchain file that matches `cmake-rs` defaults and adds
@@ -289,14 +297,196 @@ fn resolve_tool_path(tool: &str, extra_path: &[PathBuf]) -> PathBuf {
         .unwrap_or_else(|| tool_path.to_path_buf())
 }
 
+/// A downloadable toolchain bundle: where it is installed and where it comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolchainRelease {
+    /// Bundle family, e.g. `cross-make` or `android-ndk`
+    pub kind: &'static str,
+    /// Installation directory under `--cross-compiler-dir`
+    pub dir: PathBuf,
+    /// Download URL, before `--github-proxy` is applied
+    pub url: String,
+    /// Archive format hint, `None` to detect it from the download
+    pub format: Option<ArchiveFormat>,
+    /// Path whose existence means the bundle is installed
+    pub marker: PathBuf,
+}
+
+impl ToolchainRelease {
+    /// Check whether the bundle is already installed
+    #[must_use]
+    pub fn is_installed(&self) -> bool {
+        self.marker.exists()
+    }
+
+    /// Download and extract the bundle unless it is already installed
+    pub async fn ensure(&self, args: &Args) -> Result<()> {
+        if self.is_installed() {
+            return Ok(());
+        }
+        if args.adopt_existing && self.adopt_existing(args).await {
+            return Ok(());
+        }
+        download_and_extract(
+            &self.url,
+            &self.dir,
+            self.format,
+            args.github_proxy.as_deref(),
+            self.kind,
+        )
+        .await
+    }
+
+    /// Installed cross-make versions of this toolchain other than the requested one,
+    /// newest first
+    fn installed_siblings(&self, args: &Args) -> Vec<String> {
+        let name = self.dir.file_name().unwrap_or_default().to_string_lossy();
+        let (Some((prefix, version)), Ok(marker)) = (
+            split_cross_make_dir(&name),
+            self.marker.strip_prefix(&self.dir),
+        ) else {
+            return Vec::new();
+        };
+        let mut siblings: Vec<String> = crate::cache::toolchain_dir_names(&args.cross_compiler_dir)
+            .iter()
+            .filter_map(|dir| split_cross_make_dir(dir))
+            .filter(|&(p, v)| p == prefix && v != version)
+            .filter(|&(p, v)| {
+                layout::toolchain_dir(&args.cross_compiler_dir, &format!("{p}-cross-{v}"))
+                    .join(marker)
+                    .exists()
+            })
+            .map(|(_, v)| v.to_string())
+            .collect();
+        siblings.sort_unstable_by(|a, b| b.cmp(a));
+        siblings
+    }
+
+    /// Reuse an installed cross-make toolchain of another version when GitHub publishes the
+    /// same digest for both release assets: symlink it under the requested name, or rename
+    /// it where symlinks are unavailable. Returns whether the toolchain was adopted.
+    async fn adopt_existing(&self, args: &Args) -> bool {
+        if self.kind != "cross-make" || fs::symlink_metadata(&self.dir).is_ok() {
+            return false;
+        }
+        let name = self.dir.file_name().unwrap_or_default().to_string_lossy();
+        let Some((prefix, version)) = split_cross_make_dir(&name) else {
+            return false;
+        };
+        let siblings = self.installed_siblings(args);
+        if siblings.is_empty() {
+            return false;
+        }
+        let digest = match release_asset_digest(&self.url).await {
+            Ok(Some(digest)) => digest,
+            Ok(None) => {
+                color::log_info(&format!(
+                    "Not adopting an older {prefix}-cross: no published digest for {version}"
+                ));
+                return false;
+            }
+            Err(e) => {
+                color::log_warning(&format!(
+                    "Not adopting an older {prefix}-cross: failed to look up the {version} digest: {e}"
+                ));
+                return false;
+            }
+        };
+
+        for sibling in siblings {
+            let sibling_url = self.url.replacen(
+                &format!("/download/{version}-"),
+                &format!("/download/{sibling}-"),
+                1,
+            );
+            if release_asset_digest(&sibling_url).await.ok().flatten() != Some(digest.clone()) {
+                continue;
+            }
+            let sibling_dir = format!("{prefix}-cross-{sibling}");
+            match link_or_rename(&sibling_dir, &self.dir) {
+                Ok(()) => {
+                    color::log_info(&format!(
+                        "Adopted {} as {name}: the {sibling} and {version} releases are identical ({digest})",
+                        color::yellow(&sibling_dir)
+                    ));
+                    return true;
+                }
+                Err(e) => {
+                    color::log_warning(&format!("Failed to adopt {sibling_dir} as {name}: {e}"));
+                    return false;
+                }
+            }
+        }
+        color::log_info(&format!(
+            "Not adopting an older {prefix}-cross: the {version} release differs from the installed versions"
+        ));
+        false
+    }
+}
+
+/// Make the sibling directory `existing` of `dest` available as `dest`
+#[cfg(unix)]
+fn link_or_rename(existing: &str, dest: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(existing, dest)
+}
+
+/// Make the sibling directory `existing` of `dest` available as `dest`
+#[cfg(not(unix))]
+fn link_or_rename(existing: &str, dest: &Path) -> std::io::Result<()> {
+    fs::rename(dest.with_file_name(existing), dest)
+}
+
+/// Directory a cross-make toolchain is installed in, e.g. `aarch64-linux-musl-cross-v0.7.7`
+#[must_use]
+pub fn cross_make_dir_name(cross_compiler_name: &str, cross_make_version: &str) -> String {
+    format!("{cross_compiler_name}-{cross_make_version}")
+}
+
+/// Locate the cross-make release that provides `<bin_prefix>-gcc` for this host
+#[must_use]
+pub fn cross_make_release(
+    cross_compiler_name: &str,
+    bin_prefix: &str,
+    args: &Args,
+    host: &HostPlatform,
+) -> ToolchainRelease {
+    let dir = layout::toolchain_dir(
+        &args.cross_compiler_dir,
+        &cross_make_dir_name(cross_compiler_name, &args.cross_make_version),
+    );
+
+    // Windows hosts use .zip, others use .tgz
+    let (extension, format) = if host.is_windows() {
+        (".zip", ArchiveFormat::Zip)
+    } else {
+        (".tgz", ArchiveFormat::TarGz)
+    };
+    let url = format!(
+        "https://github.com/zijiren233/cross-make/releases/download/{}-{}/{cross_compiler_name}{extension}",
+        args.cross_make_version,
+        host.download_platform()
+    );
+    let marker = dir
+        .join("bin")
+        .join(format!("{bin_prefix}-gcc{}", host.exe_ext()));
+
+    ToolchainRelease {
+        kind: "cross-make",
+        dir,
+        url,
+        format: Some(format),
+        marker,
+    }
+}
+
 /// Setup cross-compilation environment for a target
 pub async fn setup_cross_env(
     target_config: &TargetConfig,
     args: &Args,
     host: &HostPlatform,
 ) -> Result<CrossEnv> {
-    // Skip toolchain setup if user wants to skip it
-    if args.no_toolchain_setup {
+    // Skip toolchain setup if user wants to skip it, or `check` needs no C toolchain
+    if args.skips_toolchain_setup() {
         return Ok(CrossEnv::new());
     }
 
@@ -311,6 +501,186 @@ pub async fn setup_cross_env(
     }
 }
 
+/// The toolchain bundle `setup_cross_env` would install for a target, or `None` when the
+/// target uses the host's native toolchain. Never downloads or writes anything.
+pub fn toolchain_release(
+    target_config: &TargetConfig,
+    args: &Args,
+    host: &HostPlatform,
+    ubuntu_version: &str,
+) -> Result<Option<ToolchainRelease>> {
+    if args.skips_toolchain_setup() {
+        return Ok(None);
+    }
+    validate_target(target_config, args, host)?;
+
+    Ok(match target_config.os {
+        Os::Linux => Some(linux::toolchain_release(target_config, args, host)),
+        Os::Windows => windows::toolchain_release(target_config, args, host),
+        Os::FreeBsd => Some(freebsd::toolchain_release(target_config, args, host)),
+        Os::NetBsd => Some(netbsd::toolchain_release(args, host)),
+        Os::Darwin | Os::Ios | Os::IosSim if host.is_darwin() => None,
+        Os::Darwin => Some(darwin::osxcross_release(args, host, ubuntu_version)?),
+        Os::Ios | Os::IosSim => Some(ios::toolchain_release(
+            target_config,
+            args,
+            host,
+            ubuntu_version,
+        )?),
+        Os::Android => Some(android::ndk_release(args, host)),
+    })
+}
+
+/// Validate that a target's toolchain can be set up from this host.
+/// Lightweight: never touches the network or filesystem.
+pub fn validate_target(
+    target_config: &TargetConfig,
+    args: &Args,
+    host: &HostPlatform,
+) -> Result<()> {
+    match target_config.os {
+        Os::Linux => Ok(()),
+        Os::Windows => windows::validate(target_config, host),
+        Os::FreeBsd => freebsd::validate(target_config),
+        Os::NetBsd => netbsd::validate(target_config),
+        Os::Darwin => darwin::validate(host),
+        Os::Ios | Os::IosSim => ios::validate(target_config, host),
+        Os::Android => android::validate(target_config, args),
+    }
+}
+
+/// Validate every target up front and report all problems in a single error,
+/// so a broken matrix fails before any download or build starts
+pub fn validate_targets(targets: &[String], args: &Args, host: &HostPlatform) -> Result<()> {
+    // `host-tuple` builds for the host triple, which is what its entries name
+    let built: Vec<String> = targets
+        .iter()
+        .map(|target| {
+            if target == "host-tuple" {
+                host.triple.clone()
+            } else {
+                target.clone()
+            }
+        })
+        .collect();
+    let mut problems = tool_override_problems(args, &built, |name| which::which(name).ok());
+    if !args.skips_toolchain_setup() {
+        for target in targets {
+            let Some(config) = crate::config::get_target_config(target) else {
+                continue;
+            };
+            if let Err(e) = validate_target(config, args, host) {
+                problems.push(format!("  - {target}: {e}"));
+                continue;
+            }
+            // test/bench would otherwise stop here only after the targets before it ran
+            if crate::builder::check_preconfigured_env(target, args).is_some() {
+                continue;
+            }
+            let reasons = crate::runner::missing_runner_reasons(config, args, host);
+            if !reasons.is_empty() {
+                problems.push(format!(
+                    "  - {target}: no runner for {}: {} (pass --runner, or --allow-no-runner to build anyway)",
+                    args.command.as_str(),
+                    reasons.join("; ")
+                ));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(CrossError::ValidationFailed {
+            count: problems.len(),
+            report: problems.join("\n"),
+        })
+    }
+}
+
+/// Problems with the `--cc`, `--cxx`, `--ar` and `--linker` paths: each must be an existing
+/// executable, or a program `which` finds in PATH. Entries for targets that are not built
+/// only give a warning.
+fn tool_override_problems(
+    args: &Args,
+    targets: &[String],
+    which: impl Fn(&str) -> Option<PathBuf>,
+) -> Vec<String> {
+    let options = [
+        ("--cc", &args.cc),
+        ("--cxx", &args.cxx),
+        ("--ar", &args.ar),
+        ("--linker", &args.linker),
+    ];
+    let mut problems = Vec::new();
+    for (option, entries) in options {
+        for entry in entries {
+            let (target, value) = crate::cli::split_target_entry(entry);
+            if let Some(target) = target.filter(|target| !targets.iter().any(|t| t == target)) {
+                color::log_warning_once(
+                    &format!("tool-override-{option}-{target}"),
+                    &format!("{option} {entry}: {target} is not among the targets being built"),
+                );
+            }
+            // CC and CXX may name a wrapper with arguments, e.g. `ccache gcc`
+            let Some(program) = shlex::split(value).and_then(|words| words.into_iter().next())
+            else {
+                continue;
+            };
+            let reason = if program.contains(['/', '\\']) {
+                let path = Path::new(&program);
+                if !path.is_file() {
+                    Some("does not exist")
+                } else if !is_executable(path) {
+                    Some("is not executable")
+                } else {
+                    None
+                }
+            } else {
+                which(&program).is_none().then_some("was not found in PATH")
+            };
+            if let Some(reason) = reason {
+                problems.push(format!("  - {option} {entry}: {program} {reason}"));
+            }
+        }
+    }
+    problems
+}
+
+/// Whether the file at `path` has an execute permission bit set (always true off Unix)
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).is_ok_and(|meta| meta.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        true
+    }
+}
+
+/// `-C linker-flavor` for a linker that rustc would otherwise drive like a C compiler:
+/// lld-link for MSVC targets, ld64 and ld64.lld for Apple targets. Other linkers and
+/// mismatched combinations give `None`; rustc infers the flavor of the rest from the name.
+#[must_use]
+pub fn linker_flavor(target: &str, linker: &str) -> Option<&'static str> {
+    // Split at either separator so Windows paths work on any host
+    let name = linker.rsplit(['/', '\\']).next()?.to_ascii_lowercase();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+    let apple = target.contains("-apple-");
+    if target.ends_with("-msvc") && (name == "lld-link" || name.ends_with("-lld-link")) {
+        Some("lld-link")
+    } else if apple && (name == "ld64.lld" || name.ends_with("-ld64.lld")) {
+        Some("ld64.lld")
+    } else if apple && (name == "ld64" || name.ends_with("-ld64") || name.ends_with("-ld")) {
+        Some("ld")
+    } else {
+        None
+    }
+}
+
 /// Get the binary prefix for a Linux target
 #[must_use]
 pub fn get_linux_bin_prefix(arch: Arch, libc: Libc, abi: Option<crate::config::Abi>) -> String {
@@ -386,16 +756,57 @@ pub fn setup_cmake(env: &mut CrossEnv, cmake_generator: Option<&str>, is_windows
         return;
     }
 
-    // Auto-detect on Windows: Ninja > MinGW Makefiles > Unix Makefiles
-    let generator = if which::which("ninja").is_ok() {
-        "Ninja"
-    } else if which::which("mingw32-make").is_ok() {
-        "MinGW Makefiles"
+    setup_windows_host_cmake(env);
+}
+
+/// `CMake` settings chosen for a Windows host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowsHostCMake {
+    /// Generator that honors CC/CXX (Visual Studio generators ignore them)
+    pub generator: &'static str,
+    /// Make/ninja binary for `CMAKE_MAKE_PROGRAM`, when one was found
+    pub make_program: Option<PathBuf>,
+    /// Whether `sh.exe` is on PATH, which breaks the "MinGW Makefiles" generator
+    pub sh_on_path: bool,
+}
+
+/// Pick a `CMake` generator for a Windows host: Ninja > MinGW Makefiles > Unix Makefiles.
+/// `find` looks up a program on PATH (normally `which::which`).
+pub fn detect_windows_host_cmake(find: impl Fn(&str) -> Option<PathBuf>) -> WindowsHostCMake {
+    let (generator, make_program) = if let Some(ninja) = find("ninja") {
+        ("Ninja", Some(ninja))
+    } else if let Some(make) = find("mingw32-make") {
+        ("MinGW Makefiles", Some(make))
     } else {
-        "Unix Makefiles"
+        ("Unix Makefiles", find("make"))
     };
-    env.extra_env
-        .insert("CMAKE_GENERATOR".to_string(), generator.to_string());
+
+    WindowsHostCMake {
+        generator,
+        make_program,
+        sh_on_path: generator == "MinGW Makefiles" && find("sh").is_some(),
+    }
+}
+
+/// Force a generator that respects CC/CXX on Windows hosts.
+/// Also exports `CMAKE_MAKE_PROGRAM`, since `CMake` often fails to find `mingw32-make`
+/// on its own, and works around the `sh.exe`-on-PATH check of "MinGW Makefiles"
+/// (Git for Windows puts one there).
+pub fn setup_windows_host_cmake(env: &mut CrossEnv) {
+    apply_windows_host_cmake(
+        env,
+        &detect_windows_host_cmake(|name| which::which(name).ok()),
+    );
+}
+
+fn apply_windows_host_cmake(env: &mut CrossEnv, cmake: &WindowsHostCMake) {
+    env.set_env("CMAKE_GENERATOR", cmake.generator);
+    if let Some(ref program) = cmake.make_program {
+        env.set_env("CMAKE_MAKE_PROGRAM", to_cmake_path(program));
+    }
+    if cmake.sh_on_path {
+        env.set_env("CMAKE_SH", "CMAKE_SH-NOTFOUND");
+    }
 }
 
 /// Setup `CROSS_COMPILE` prefix for cc crate and other build systems
@@ -412,6 +823,104 @@ pub fn setup_cross_compile_prefix(env: &mut CrossEnv, bin_prefix: &str) {
         .insert("CROSS_COMPILE".to_string(), format!("{bin_prefix}-"));
 }
 
+/// Library search paths, sysroot variables and the `CROSS_COMPILE` prefix of a cross-make
+/// GCC toolchain, skipping the steps disabled with `--skip-step`
+pub fn setup_gcc_env(
+    env: &mut CrossEnv,
+    compiler_dir: &Path,
+    bin_prefix: &str,
+    rust_target: &str,
+    args: &Args,
+) {
+    // Add library search paths from gcc to rustc
+    if args.run_step(SetupStep::GccLibPaths, rust_target) {
+        set_gcc_lib_paths(env, compiler_dir, bin_prefix);
+    }
+
+    // Set BINDGEN_EXTRA_CLANG_ARGS and C_INCLUDE_PATH for cross-compilation
+    if args.run_step(SetupStep::SysrootEnv, rust_target) {
+        setup_sysroot_env(env, compiler_dir, bin_prefix, rust_target);
+    }
+
+    // Set CROSS_COMPILE prefix for cc crate and other build systems
+    if args.run_step(SetupStep::CrossCompilePrefix, rust_target) {
+        setup_cross_compile_prefix(env, bin_prefix);
+    }
+}
+
+/// `CMake` generator and generic toolchain file, unless `--skip-step cmake` is given
+pub fn setup_cmake_step(env: &mut CrossEnv, rust_target: &str, args: &Args, host: &HostPlatform) {
+    if args.run_step(SetupStep::Cmake, rust_target) {
+        // Auto-detect on Windows, use the specified generator on any platform
+        setup_cmake(env, args.cmake_generator.as_deref(), host.is_windows());
+        setup_generic_cmake_toolchain(env);
+    }
+}
+
+/// File names of the tools a toolchain provides, as CC/CXX/AR and the linker are set to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompilerNames {
+    pub cc: String,
+    pub cxx: String,
+    pub ar: String,
+    pub linker: String,
+}
+
+impl CompilerNames {
+    /// Tools of a cross-make GCC toolchain, e.g. `aarch64-linux-musl-gcc`
+    #[must_use]
+    pub fn gcc(bin_prefix: &str, host: &HostPlatform) -> Self {
+        let exe_ext = host.exe_ext();
+        Self {
+            cc: format!("{bin_prefix}-gcc{exe_ext}"),
+            cxx: format!("{bin_prefix}-g++{exe_ext}"),
+            ar: format!("{bin_prefix}-ar{exe_ext}"),
+            linker: format!("{bin_prefix}-gcc{exe_ext}"),
+        }
+    }
+
+    /// Set CC/CXX/AR and the linker of `env` to these tools
+    pub fn apply(&self, env: &mut CrossEnv) {
+        env.set_cc(&self.cc);
+        env.set_cxx(&self.cxx);
+        env.set_ar(&self.ar);
+        env.set_linker(&self.linker);
+    }
+}
+
+/// Point CC/CXX/AR/linker at a cross-make GCC toolchain and add its `bin` directory to PATH
+pub fn setup_gcc_tools(
+    env: &mut CrossEnv,
+    compiler_dir: &Path,
+    bin_prefix: &str,
+    host: &HostPlatform,
+) {
+    CompilerNames::gcc(bin_prefix, host).apply(env);
+    env.add_path(compiler_dir.join("bin"));
+}
+
+/// Point CC/CXX/AR/linker at an osxcross or ioscross toolchain, add its `bin` and
+/// `clang/bin` directories to PATH and make clang use the toolchain's `ld`
+pub fn setup_apple_cross_tools(
+    env: &mut CrossEnv,
+    compiler_dir: &Path,
+    tool_prefix: &str,
+    host: &HostPlatform,
+) {
+    let exe_ext = host.exe_ext();
+    let bin_dir = compiler_dir.join("bin");
+    env.set_cc(format!("{tool_prefix}-clang{exe_ext}"));
+    env.set_cxx(format!("{tool_prefix}-clang++{exe_ext}"));
+    env.set_ar(format!("{tool_prefix}-ar{exe_ext}"));
+    env.set_linker(format!("{tool_prefix}-clang{exe_ext}"));
+    env.add_path(&bin_dir);
+    env.add_path(compiler_dir.join("clang").join("bin"));
+
+    let linker_path = bin_dir.join(format!("{tool_prefix}-ld{exe_ext}"));
+    env.add_ldflag(shell_quote(&format!("-fuse-ld={}", linker_path.display())));
+    env.add_rustflag_arg(format!("-Clink-arg=-fuse-ld={}", linker_path.display()));
+}
+
 /// Setup library path for Darwin/iOS linker binaries
 ///
 /// The Darwin/iOS linker binaries from cross-compilation toolchains need to find their
@@ -424,19 +933,6 @@ pub fn setup_darwin_linker_library_path(env: &mut CrossEnv, compiler_dir: &Path)
     }
 }
 
-/// Get Ubuntu version from `lsb_release` (used for Linux cross-compilation downloads)
-pub async fn get_ubuntu_version() -> Option<String> {
-    let output = Command::new("lsb_release").arg("-rs").output().await.ok()?;
-
-    if output.status.success() {
-        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        if version.contains('.') {
-            return Some(version);
-        }
-    }
-    None
-}
-
 /// Find an Apple SDK by version using xcrun and xcode-select
 pub async fn find_apple_sdk(sdk_type: AppleSdkType, version: &str) -> Option<PathBuf> {
     let (sdk_name, platform_name) = sdk_type.names(version);
@@ -563,6 +1059,78 @@ mod tests {
 
     // Tests for CMake path conversion (using path-slash crate)
 
+    fn fake_which(found: &'static [&'static str]) -> impl Fn(&str) -> Option<PathBuf> {
+        move |name| {
+            found
+                .contains(&name)
+                .then(|| PathBuf::from(format!("C:/tools/{name}.exe")))
+        }
+    }
+
+    #[test]
+    fn test_detect_windows_host_cmake_prefers_ninja() {
+        let cmake = detect_windows_host_cmake(fake_which(&["ninja", "mingw32-make", "sh"]));
+        assert_eq!(cmake.generator, "Ninja");
+        assert_eq!(
+            cmake.make_program,
+            Some(PathBuf::from("C:/tools/ninja.exe"))
+        );
+        assert!(!cmake.sh_on_path);
+    }
+
+    #[test]
+    fn test_detect_windows_host_cmake_mingw_with_git_sh() {
+        let cmake = detect_windows_host_cmake(fake_which(&["mingw32-make", "sh"]));
+        assert_eq!(cmake.generator, "MinGW Makefiles");
+        assert_eq!(
+            cmake.make_program,
+            Some(PathBuf::from("C:/tools/mingw32-make.exe"))
+        );
+        assert!(cmake.sh_on_path);
+
+        let mut env = CrossEnv::new();
+        apply_windows_host_cmake(&mut env, &cmake);
+        assert_eq!(env.extra_env["CMAKE_GENERATOR"], "MinGW Makefiles");
+        assert_eq!(
+            env.extra_env["CMAKE_MAKE_PROGRAM"],
+            "C:/tools/mingw32-make.exe"
+        );
+        assert_eq!(env.extra_env["CMAKE_SH"], "CMAKE_SH-NOTFOUND");
+    }
+
+    #[test]
+    fn test_detect_windows_host_cmake_fallback() {
+        let cmake = detect_windows_host_cmake(fake_which(&["make"]));
+        assert_eq!(cmake.generator, "Unix Makefiles");
+        assert_eq!(cmake.make_program, Some(PathBuf::from("C:/tools/make.exe")));
+
+        let cmake = detect_windows_host_cmake(fake_which(&[]));
+        assert_eq!(cmake.generator, "Unix Makefiles");
+        assert_eq!(cmake.make_program, None);
+
+        let mut env = CrossEnv::new();
+        apply_windows_host_cmake(&mut env, &cmake);
+        assert!(!env.extra_env.contains_key("CMAKE_MAKE_PROGRAM"));
+        assert!(!env.extra_env.contains_key("CMAKE_SH"));
+    }
+
+    #[test]
+    fn test_setup_cmake_generator_lands_in_extra_env() {
+        let mut env = CrossEnv::new();
+        setup_cmake(&mut env, Some("Ninja Multi-Config"), false);
+        assert_eq!(env.extra_env["CMAKE_GENERATOR"], "Ninja Multi-Config");
+
+        // An explicit generator wins over Windows auto-detection
+        let mut env = CrossEnv::new();
+        setup_cmake(&mut env, Some("Unix Makefiles"), true);
+        assert_eq!(env.extra_env["CMAKE_GENERATOR"], "Unix Makefiles");
+
+        // Unset on non-Windows hosts leaves CMake's default alone
+        let mut env = CrossEnv::new();
+        setup_cmake(&mut env, None, false);
+        assert!(!env.extra_env.contains_key("CMAKE_GENERATOR"));
+    }
+
     #[test]
     fn test_to_cmake_path_unix() {
         // Unix paths should pass through unchanged
@@ -756,4 +1324,408 @@ mod tests {
         let folder = get_linux_folder_name(Arch::Aarch64Be, Libc::Gnu, None, "2.17", "");
         assert_eq!(folder, "aarch64_be-linux-gnu-2.17-cross");
     }
+
+    fn linux_host() -> HostPlatform {
+        HostPlatform {
+            os: "linux",
+            arch: "x86_64",
+            triple: "x86_64-unknown-linux-gnu".to_string(),
+        }
+    }
+
+    fn validation_args() -> Args {
+        crate::cli::test_args(
+            crate::cli::test_toolchain_dir("validation"),
+            crate::cli::BuildArgs {
+                ndk_version: crate::config::DEFAULT_NDK_VERSION.to_string(),
+                ..crate::cli::BuildArgs::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_tool_override_problems() {
+        let dir = std::env::temp_dir().join(format!("cargo-cross-tools-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let tool = dir.join("musl-gcc");
+        fs::write(&tool, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let plain = dir.join("notes.txt");
+        fs::write(&plain, "").unwrap();
+
+        let mut args = validation_args();
+        let targets = vec!["x86_64-pc-windows-msvc".to_string()];
+        args.linker = vec![
+            "x86_64-pc-windows-msvc=lld-link".to_string(),
+            tool.display().to_string(),
+        ];
+        args.cc = vec!["ccache gcc".to_string()];
+        let which = fake_which(&["lld-link", "ccache"]);
+        assert!(tool_override_problems(&args, &targets, &which).is_empty());
+
+        args.cxx = vec![dir.join("missing-g++").display().to_string()];
+        args.ar = vec!["x86_64-pc-windows-msvc=llvm-lib".to_string()];
+        let problems = tool_override_problems(&args, &targets, &which);
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].contains("--cxx") && problems[0].ends_with("does not exist"));
+        assert!(problems[1].ends_with("llvm-lib was not found in PATH"));
+
+        #[cfg(unix)]
+        {
+            args.cxx = vec![plain.display().to_string()];
+            args.ar.clear();
+            let problems = tool_override_problems(&args, &targets, &which);
+            assert_eq!(problems.len(), 1);
+            assert!(problems[0].ends_with("is not executable"));
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_linker_flavor() {
+        for (target, linker, expected) in [
+            ("x86_64-pc-windows-msvc", "lld-link", Some("lld-link")),
+            (
+                "x86_64-pc-windows-msvc",
+                r"C:\LLVM\bin\LLD-LINK.EXE",
+                Some("lld-link"),
+            ),
+            (
+                "aarch64-apple-darwin",
+                "/usr/bin/ld64.lld",
+                Some("ld64.lld"),
+            ),
+            ("aarch64-apple-darwin", "ld64", Some("ld")),
+            (
+                "x86_64-apple-darwin",
+                "x86_64-apple-darwin20.4-ld",
+                Some("ld"),
+            ),
+            // Mismatched or inferred by rustc
+            ("x86_64-pc-windows-gnu", "lld-link", None),
+            ("x86_64-unknown-linux-musl", "ld64.lld", None),
+            ("x86_64-unknown-linux-musl", "/usr/bin/ld.lld", None),
+            ("x86_64-pc-windows-msvc", "link.exe", None),
+        ] {
+            assert_eq!(linker_flavor(target, linker), expected, "{target} {linker}");
+        }
+    }
+
+    #[test]
+    fn test_validate_targets_reports_every_problem() {
+        let windows_host = HostPlatform {
+            os: "windows",
+            arch: "x86_64",
+            triple: "x86_64-pc-windows-msvc".to_string(),
+        };
+        let targets: Vec<String> = [
+            "x86_64-unknown-linux-musl",
+            "aarch64-apple-darwin",
+            "x86_64-pc-windows-gnu",
+            "aarch64-apple-ios",
+            "aarch64-linux-android",
+            "host-tuple",
+            "not-a-known-target",
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+        let err = validate_targets(&targets, &validation_args(), &windows_host).unwrap_err();
+        let CrossError::ValidationFailed { count, report } = err else {
+            panic!("expected ValidationFailed, got {err:?}");
+        };
+        assert_eq!(count, 2);
+        assert!(report.contains("aarch64-apple-darwin"));
+        assert!(report.contains("aarch64-apple-ios"));
+        assert!(!report.contains("x86_64-unknown-linux-musl"));
+        assert!(!report.contains("x86_64-pc-windows-gnu"));
+        assert!(!report.contains("aarch64-linux-android"));
+    }
+
+    #[test]
+    fn test_validate_target_rejects_unsupported_arch_combinations() {
+        let host = linux_host();
+        let config = |target, os, arch, libc| TargetConfig {
+            target,
+            os,
+            arch,
+            libc,
+            abi: None,
+        };
+        let android_mips = config("mips-linux-android", Os::Android, Arch::Mips, None);
+        let mingw_arm64 = config(
+            "aarch64-pc-windows-gnullvm",
+            Os::Windows,
+            Arch::Aarch64,
+            Some(Libc::Gnu),
+        );
+        let msvc = config(
+            "x86_64-pc-windows-msvc",
+            Os::Windows,
+            Arch::X86_64,
+            Some(Libc::Msvc),
+        );
+        let netbsd_arm = config("aarch64-unknown-netbsd", Os::NetBsd, Arch::Aarch64, None);
+
+        assert!(validate_target(&android_mips, &validation_args(), &host).is_err());
+        assert!(validate_target(&mingw_arm64, &validation_args(), &host).is_err());
+        assert!(validate_target(&msvc, &validation_args(), &host).is_err());
+        assert!(validate_target(&netbsd_arm, &validation_args(), &host).is_err());
+    }
+
+    #[test]
+    fn test_validate_targets_ok_and_skipped_without_toolchain_setup() {
+        let targets = vec!["x86_64-unknown-linux-musl".to_string()];
+        assert!(validate_targets(&targets, &validation_args(), &linux_host()).is_ok());
+
+        let mut args = validation_args();
+        args.no_toolchain_setup = true;
+        let targets = vec!["x86_64-pc-windows-msvc".to_string()];
+        assert!(validate_targets(&targets, &args, &linux_host()).is_ok());
+    }
+
+    #[test]
+    fn test_tool_override_for_host_tuple_is_not_unbuilt() {
+        let mut args = validation_args();
+        args.no_toolchain_setup = true;
+        args.linker = vec!["x86_64-unknown-linux-gnu=sh".to_string()];
+        let lines = crate::color::capture::logs(crate::color::LogLevel::Warn, || {
+            assert!(validate_targets(&["host-tuple".to_string()], &args, &linux_host()).is_ok());
+        });
+        assert!(
+            !lines
+                .iter()
+                .any(|line| line.contains("not among the targets")),
+            "{lines:?}"
+        );
+    }
+
+    #[test]
+    fn test_validate_targets_reports_missing_runners_up_front() {
+        let targets = vec![
+            "host-tuple".to_string(),
+            "x86_64-unknown-freebsd".to_string(),
+        ];
+        let mut args = validation_args();
+        assert!(validate_targets(&targets, &args, &linux_host()).is_ok());
+
+        // Reported before the host target runs its tests
+        args.command = crate::cli::Command::test();
+        match validate_targets(&targets, &args, &linux_host()) {
+            Err(CrossError::ValidationFailed { count, report }) => {
+                assert_eq!(count, 1);
+                assert!(
+                    report.starts_with("  - x86_64-unknown-freebsd: no runner for test: "),
+                    "{report}"
+                );
+            }
+            other => panic!("expected ValidationFailed, got {other:?}"),
+        }
+
+        args.build.allow_no_runner = true;
+        assert!(validate_targets(&targets, &args, &linux_host()).is_ok());
+        args.build.allow_no_runner = false;
+        args.build.runner = Some("my-runner".to_string());
+        assert!(validate_targets(&targets, &args, &linux_host()).is_ok());
+    }
+
+    #[test]
+    fn test_android_glob_validates_with_default_ndk() {
+        // Every Android target in the table must build with the default NDK
+        let targets: Vec<String> = crate::config::expand_targets("*-linux-android*")
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        assert_eq!(targets.len(), 6, "{targets:?}");
+        assert!(validate_targets(&targets, &validation_args(), &linux_host()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_no_link_toolchain_skips_setup_for_check() {
+        let mut args = validation_args();
+        args.build.no_link_toolchain = true;
+        // No osxcross for this host, which check does not need
+        let riscv_host = HostPlatform {
+            os: "linux",
+            arch: "riscv64",
+            triple: "riscv64gc-unknown-linux-gnu".to_string(),
+        };
+        let targets = vec!["aarch64-apple-darwin".to_string()];
+        // build always needs the toolchain, even with the flag
+        assert!(!args.skips_toolchain_setup());
+        assert!(validate_targets(&targets, &args, &riscv_host).is_err());
+
+        args.command = crate::cli::Command::new("check");
+        assert!(args.skips_toolchain_setup());
+        assert!(validate_targets(&targets, &args, &riscv_host).is_ok());
+        let config = crate::config::get_target_config("aarch64-unknown-linux-musl").unwrap();
+        assert!(toolchain_release(config, &args, &linux_host(), "20.04")
+            .unwrap()
+            .is_none());
+        // Returns before looking for a toolchain in the (missing) cache directory
+        let env = setup_cross_env(config, &args, &linux_host()).await.unwrap();
+        assert!(env.cc.is_none() && env.linker.is_none() && env.runner.is_none());
+    }
+
+    #[test]
+    fn test_validate_target_darwin_on_unsupported_host_arch() {
+        let host = HostPlatform {
+            os: "linux",
+            arch: "riscv64",
+            triple: "riscv64gc-unknown-linux-gnu".to_string(),
+        };
+        let config = crate::config::get_target_config("aarch64-apple-darwin").unwrap();
+        assert!(validate_target(config, &validation_args(), &host).is_err());
+        assert!(validate_target(config, &validation_args(), &linux_host()).is_ok());
+    }
+
+    fn windows_host() -> HostPlatform {
+        HostPlatform {
+            os: "windows",
+            arch: "x86_64",
+            triple: "x86_64-pc-windows-msvc".to_string(),
+        }
+    }
+
+    /// Join path components with the separator of the platform running the test, so a
+    /// literal `/` inside a `join` shows up as a mismatch on Windows CI
+    fn native_path(parts: &[&str]) -> String {
+        parts.join(std::path::MAIN_SEPARATOR_STR)
+    }
+
+    #[test]
+    fn test_gcc_tools_on_windows_host() {
+        let host = windows_host();
+        let compiler_dir = PathBuf::from("cross").join("aarch64-linux-musl-cross");
+        let mut env = CrossEnv::new();
+        setup_gcc_tools(&mut env, &compiler_dir, "aarch64-linux-musl", &host);
+
+        assert_eq!(env.cc.as_deref(), Some("aarch64-linux-musl-gcc.exe"));
+        assert_eq!(env.cxx.as_deref(), Some("aarch64-linux-musl-g++.exe"));
+        assert_eq!(env.ar.as_deref(), Some("aarch64-linux-musl-ar.exe"));
+        assert_eq!(env.linker.as_deref(), Some("aarch64-linux-musl-gcc.exe"));
+        assert_eq!(
+            env.path[0].display().to_string(),
+            native_path(&["cross", "aarch64-linux-musl-cross", "bin"])
+        );
+
+        let mut env = CrossEnv::new();
+        setup_gcc_tools(&mut env, &compiler_dir, "aarch64-linux-musl", &linux_host());
+        assert_eq!(env.cc.as_deref(), Some("aarch64-linux-musl-gcc"));
+    }
+
+    #[test]
+    fn test_apple_cross_tools_on_windows_host() {
+        let host = windows_host();
+        let compiler_dir = PathBuf::from("cross").join("osxcross");
+        let mut env = CrossEnv::new();
+        setup_apple_cross_tools(&mut env, &compiler_dir, "aarch64-apple-darwin24", &host);
+
+        assert_eq!(env.cc.as_deref(), Some("aarch64-apple-darwin24-clang.exe"));
+        assert_eq!(
+            env.cxx.as_deref(),
+            Some("aarch64-apple-darwin24-clang++.exe")
+        );
+        assert_eq!(env.ar.as_deref(), Some("aarch64-apple-darwin24-ar.exe"));
+        assert_eq!(
+            env.linker.as_deref(),
+            Some("aarch64-apple-darwin24-clang.exe")
+        );
+
+        let paths: Vec<String> = env.path.iter().map(|p| p.display().to_string()).collect();
+        assert_eq!(
+            paths,
+            [
+                native_path(&["cross", "osxcross", "bin"]),
+                native_path(&["cross", "osxcross", "clang", "bin"]),
+            ]
+        );
+        let linker = native_path(&["cross", "osxcross", "bin", "aarch64-apple-darwin24-ld.exe"]);
+        assert_eq!(env.ldflags, [shell_quote(&format!("-fuse-ld={linker}"))]);
+        assert_eq!(env.rustflags, [format!("-Clink-arg=-fuse-ld={linker}")]);
+    }
+
+    #[test]
+    fn test_ndk_tools_on_windows_host() {
+        let host = windows_host();
+        let bin_dir = PathBuf::from("ndk")
+            .join("toolchains")
+            .join("llvm")
+            .join("prebuilt")
+            .join("windows-x86_64")
+            .join("bin");
+        let mut env = CrossEnv::new();
+        android::setup_ndk_tools(&mut env, &bin_dir, "aarch64-linux-android24", &host, None)
+            .unwrap();
+
+        assert_eq!(env.cc.as_deref(), Some("aarch64-linux-android24-clang.cmd"));
+        assert_eq!(
+            env.cxx.as_deref(),
+            Some("aarch64-linux-android24-clang++.cmd")
+        );
+        assert_eq!(env.ar.as_deref(), Some("llvm-ar.exe"));
+        assert_eq!(
+            env.linker.as_deref(),
+            Some("aarch64-linux-android24-clang.cmd")
+        );
+        assert_eq!(
+            env.path[0].display().to_string(),
+            native_path(&[
+                "ndk",
+                "toolchains",
+                "llvm",
+                "prebuilt",
+                "windows-x86_64",
+                "bin"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_generated_ndk_wrappers_on_windows_host() {
+        let host = windows_host();
+        let wrapper_dir =
+            std::env::temp_dir().join(format!("cargo-cross-ndk-bin-{}", std::process::id()));
+        let mut env = CrossEnv::new();
+        android::setup_ndk_tools(
+            &mut env,
+            Path::new("ndk"),
+            "aarch64-linux-android24",
+            &host,
+            Some(&wrapper_dir),
+        )
+        .unwrap();
+
+        let cc = wrapper_dir.join("aarch64-linux-android24-clang.bat");
+        let cxx = wrapper_dir.join("aarch64-linux-android24-clang++.bat");
+        // compiler-names reports the same files setup writes
+        let names = android::ndk_compiler_names("aarch64-linux-android24", &host, false);
+        assert!(cc.ends_with(&names.cc) && cxx.ends_with(&names.cxx));
+        assert_eq!(env.cc.as_deref(), Some(cc.display().to_string().as_str()));
+        assert_eq!(env.cxx.as_deref(), Some(cxx.display().to_string().as_str()));
+        assert_eq!(env.linker, env.cc);
+        assert_eq!(env.ar.as_deref(), Some("llvm-ar.exe"));
+        let script = std::fs::read_to_string(&cxx).unwrap();
+        assert!(script.starts_with("@REM cargo-cross-generated "));
+        assert!(script.contains("clang++.exe\" \"--target=aarch64-linux-android24\" %*"));
+
+        // Without a Windows host the wrapper dir is ignored
+        let mut env = CrossEnv::new();
+        android::setup_ndk_tools(
+            &mut env,
+            Path::new("ndk"),
+            "aarch64-linux-android24",
+            &linux_host(),
+            Some(&wrapper_dir),
+        )
+        .unwrap();
+        assert_eq!(env.cc.as_deref(), Some("aarch64-linux-android24-clang"));
+
+        let _ = std::fs::remove_dir_all(&wrapper_dir);
+    }
 }
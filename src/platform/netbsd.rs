@@ -3,9 +3,33 @@
 use crate::cli::Args;
 use crate::color;
 use crate::config::{Arch, HostPlatform, TargetConfig};
-use crate::env::{set_gcc_lib_paths, setup_sysroot_env, CrossEnv};
+use crate::env::CrossEnv;
 use crate::error::{CrossError, Result};
-use crate::platform::{setup_cmake, setup_cross_compile_prefix, setup_generic_cmake_toolchain};
+use crate::platform::{
+    cross_make_release, setup_cmake_step, setup_gcc_env, setup_gcc_tools, ToolchainRelease,
+};
+
+/// Validate that a NetBSD target can be set up, without touching the network or filesystem
+pub fn validate(target_config: &TargetConfig) -> Result<()> {
+    // Only x86_64 is supported
+    if target_config.arch == Arch::X86_64 {
+        Ok(())
+    } else {
+        Err(CrossError::UnsupportedArchitecture {
+            arch: target_config.arch.as_str().to_string(),
+            os: "netbsd".to_string(),
+        })
+    }
+}
+
+/// Tool prefix of the NetBSD toolchain
+pub const BIN_PREFIX: &str = "x86_64-unknown-netbsd";
+
+/// Cross-make release that provides the toolchain for a NetBSD target
+#[must_use]
+pub fn toolchain_release(args: &Args, host: &HostPlatform) -> ToolchainRelease {
+    cross_make_release(&format!("{BIN_PREFIX}-cross"), BIN_PREFIX, args, host)
+}
 
 /// Setup NetBSD cross-compilation environment
 pub async fn setup(
@@ -13,74 +37,21 @@ pub async fn setup(
     args: &Args,
     host: &HostPlatform,
 ) -> Result<CrossEnv> {
-    let arch = target_config.arch;
     let rust_target = target_config.target;
 
-    // Validate architecture - only x86_64 is supported
-    if arch != Arch::X86_64 {
-        return Err(CrossError::UnsupportedArchitecture {
-            arch: arch.as_str().to_string(),
-            os: "netbsd".to_string(),
-        });
-    }
-
-    let bin_prefix = "x86_64-unknown-netbsd";
-    let cross_compiler_name = "x86_64-unknown-netbsd-cross";
+    validate(target_config)?;
 
-    // Add .exe extension on Windows
-    let exe_ext = if host.is_windows() { ".exe" } else { "" };
-    let gcc_name = format!("{bin_prefix}-gcc{exe_ext}");
-    let compiler_dir = args.cross_compiler_dir.join(format!(
-        "{}-{}",
-        cross_compiler_name, args.cross_make_version
-    ));
-
-    // Download compiler if not present
-    let gcc_path = compiler_dir.join("bin").join(&gcc_name);
-    if !gcc_path.exists() {
-        let host_platform = host.download_platform();
-
-        // Windows hosts use .zip, others use .tgz
-        let (extension, format_hint) = if host.is_windows() {
-            (".zip", Some(crate::download::ArchiveFormat::Zip))
-        } else {
-            (".tgz", Some(crate::download::ArchiveFormat::TarGz))
-        };
-
-        let download_url = format!(
-            "https://github.com/zijiren233/cross-make/releases/download/{}-{}/{}{}",
-            args.cross_make_version, host_platform, cross_compiler_name, extension
-        );
-        crate::download::download_and_extract(
-            &download_url,
-            &compiler_dir,
-            format_hint,
-            args.github_proxy.as_deref(),
-        )
-        .await?;
-    }
+    let bin_prefix = BIN_PREFIX;
+    let release = toolchain_release(args, host);
+    release.ensure(args).await?;
+    let compiler_dir = release.dir;
 
     let mut env = CrossEnv::new();
-    let bin_dir = compiler_dir.join("bin");
-
-    env.set_cc(&gcc_name);
-    env.set_cxx(format!("{bin_prefix}-g++{exe_ext}"));
-    env.set_ar(format!("{bin_prefix}-ar{exe_ext}"));
-    env.set_linker(&gcc_name);
-    env.add_path(&bin_dir);
-
-    // Add library search paths from gcc to rustc
-    set_gcc_lib_paths(&mut env, &compiler_dir, bin_prefix);
-
-    // Set BINDGEN_EXTRA_CLANG_ARGS for cross-compilation
-    setup_sysroot_env(&mut env, &compiler_dir, bin_prefix, rust_target);
-
-    // Set CROSS_COMPILE prefix for cc crate and other build systems
-    setup_cross_compile_prefix(&mut env, bin_prefix);
+    env.set_toolchain_dir(&compiler_dir);
+    setup_gcc_tools(&mut env, &compiler_dir, bin_prefix, host);
 
-    // Setup CMake generator (auto-detect on Windows, use specified on any platform)
-    setup_cmake(&mut env, args.cmake_generator.as_deref(), host.is_windows());
-    setup_generic_cmake_toolchain(&mut env);
+    setup_gcc_env(&mut env, &compiler_dir, bin_prefix, rust_target, args);
+    setup_cmake_step(&mut env, rust_target, args, host);
 
     color::log_success(&format!(
         "Configured NetBSD toolchain for {}",
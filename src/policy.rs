@@ -0,0 +1,285 @@
+//! Target policy: organization-wide allow and deny lists checked right after the target
+//! list is expanded
+//!
+//! The policy file is JSON. Entries are globs over target triples, optionally with the
+//! message shown when they reject a target:
+//!
+//! ```json
+//! {
+//!   "allow": ["*-linux-*", "*-apple-darwin"],
+//!   "deny": [
+//!     { "pattern": "mips*", "message": "mips is not supported by SecOps, use aarch64 instead" },
+//!     "*-linux-uclibc*"
+//!   ],
+//!   "message": "Ask #platform before adding a target"
+//! }
+//! ```
+//!
+//! A target matching a `deny` entry is rejected even if it is also allowed. With a
+//! non-empty `allow` list, targets matching none of its entries are rejected too. The
+//! top-level `message` applies to rejections whose entry has no message of its own.
+
+use crate::error::{CrossError, Result};
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// One `allow` or `deny` entry
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: String,
+    matcher: globset::GlobMatcher,
+    message: Option<String>,
+}
+
+impl Rule {
+    fn parse(entry: &Value) -> std::result::Result<Self, String> {
+        let (pattern, message) = match entry {
+            Value::String(pattern) => (pattern.as_str(), None),
+            Value::Object(fields) => {
+                let pattern = fields
+                    .get("pattern")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| format!("entry {entry} has no \"pattern\" string"))?;
+                let message = match fields.get("message") {
+                    None => None,
+                    Some(Value::String(message)) => Some(message.clone()),
+                    Some(other) => {
+                        return Err(format!(
+                            "\"message\" of '{pattern}' is not a string: {other}"
+                        ))
+                    }
+                };
+                (pattern, message)
+            }
+            other => return Err(format!("entry {other} is neither a glob nor an object")),
+        };
+        let matcher = globset::Glob::new(pattern)
+            .map_err(|e| format!("invalid glob '{pattern}': {e}"))?
+            .compile_matcher();
+        Ok(Self {
+            pattern: pattern.to_string(),
+            matcher,
+            message,
+        })
+    }
+}
+
+/// Allow and deny lists of a policy file
+#[derive(Debug, Clone)]
+pub struct TargetPolicy {
+    path: PathBuf,
+    allow: Vec<Rule>,
+    deny: Vec<Rule>,
+    message: Option<String>,
+}
+
+/// Why a policy rejects a target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub target: String,
+    /// Deny glob that matched, `None` when the target is missing from the allow list
+    pub denied_by: Option<String>,
+    pub message: Option<String>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.denied_by {
+            Some(ref pattern) => write!(f, "{} is denied by '{pattern}'", self.target)?,
+            None => write!(f, "{} is not in the allow list", self.target)?,
+        }
+        if let Some(ref message) = self.message {
+            write!(f, ": {message}")?;
+        }
+        Ok(())
+    }
+}
+
+impl TargetPolicy {
+    /// Parse the JSON policy read from `path`
+    pub fn parse(json: &str, path: &Path) -> Result<Self> {
+        let invalid = |reason: String| {
+            CrossError::InvalidArgument(format!("target policy {}: {reason}", path.display()))
+        };
+        let value: Value = serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+        let Value::Object(fields) = value else {
+            return Err(invalid("expected a JSON object".to_string()));
+        };
+        if let Some(key) = fields
+            .keys()
+            .find(|key| !matches!(key.as_str(), "allow" | "deny" | "message"))
+        {
+            return Err(invalid(format!(
+                "unknown key \"{key}\" (expected allow, deny or message)"
+            )));
+        }
+        let rules = |key: &str| -> std::result::Result<Vec<Rule>, String> {
+            match fields.get(key) {
+                None => Ok(Vec::new()),
+                Some(Value::Array(entries)) => entries.iter().map(Rule::parse).collect(),
+                Some(other) => Err(format!("\"{key}\" must be a list, got {other}")),
+            }
+        };
+        let message = match fields.get("message") {
+            None => None,
+            Some(Value::String(message)) => Some(message.clone()),
+            Some(other) => {
+                return Err(invalid(format!(
+                    "\"message\" must be a string, got {other}"
+                )))
+            }
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            allow: rules("allow").map_err(invalid)?,
+            deny: rules("deny").map_err(invalid)?,
+            message,
+        })
+    }
+
+    /// Read and parse the policy file at `path`
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(|source| CrossError::IoError {
+            message: format!("Failed to read target policy {}", path.display()),
+            source,
+        })?;
+        Self::parse(&json, path)
+    }
+
+    /// Why `target` is rejected, if it is: deny entries win over allow entries
+    #[must_use]
+    pub fn check(&self, target: &str) -> Option<Violation> {
+        if let Some(rule) = self.deny.iter().find(|rule| rule.matcher.is_match(target)) {
+            return Some(Violation {
+                target: target.to_string(),
+                denied_by: Some(rule.pattern.clone()),
+                message: rule.message.clone().or_else(|| self.message.clone()),
+            });
+        }
+        if self.allow.is_empty() || self.allow.iter().any(|rule| rule.matcher.is_match(target)) {
+            return None;
+        }
+        Some(Violation {
+            target: target.to_string(),
+            denied_by: None,
+            message: self.message.clone(),
+        })
+    }
+
+    /// Reject the targets the policy does not permit, or only warn about them with
+    /// `warn_only`
+    pub fn enforce(&self, targets: &[String], warn_only: bool) -> Result<()> {
+        let violations: Vec<Violation> = targets
+            .iter()
+            .filter_map(|target| self.check(target))
+            .collect();
+        if violations.is_empty() {
+            return Ok(());
+        }
+        if warn_only {
+            for violation in &violations {
+                crate::color::log_warning(&format!(
+                    "Target policy {}: {violation} (--policy-warn-only)",
+                    self.path.display()
+                ));
+            }
+            return Ok(());
+        }
+        Err(CrossError::TargetPolicyViolation {
+            policy: self.path.clone(),
+            violations: violations
+                .iter()
+                .map(|violation| format!("  {violation}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(json: &str) -> TargetPolicy {
+        TargetPolicy::parse(json, Path::new("policy.json")).unwrap()
+    }
+
+    #[test]
+    fn test_parse_entries_and_errors() {
+        let parsed = policy(
+            r#"{"allow": ["*-linux-*"],
+                "deny": [{"pattern": "mips*", "message": "use aarch64"}, "*-uclibc*"],
+                "message": "ask platform"}"#,
+        );
+        assert_eq!(parsed.allow.len(), 1);
+        assert_eq!(parsed.deny[0].message.as_deref(), Some("use aarch64"));
+        assert_eq!(parsed.deny[1].message, None);
+        assert_eq!(parsed.message.as_deref(), Some("ask platform"));
+        assert!(policy("{}").check("riscv64gc-unknown-none-elf").is_none());
+
+        for (json, expected) in [
+            ("[]", "expected a JSON object"),
+            (r#"{"allowed": []}"#, "unknown key \"allowed\""),
+            (r#"{"deny": "mips*"}"#, "\"deny\" must be a list"),
+            (r#"{"deny": [42]}"#, "neither a glob nor an object"),
+            (r#"{"deny": [{"message": "x"}]}"#, "no \"pattern\" string"),
+            (r#"{"allow": ["["]}"#, "invalid glob '['"),
+            ("{", "EOF"),
+        ] {
+            let err = TargetPolicy::parse(json, Path::new("policy.json")).unwrap_err();
+            let message = err.to_string();
+            assert!(message.contains("target policy policy.json"), "{message}");
+            assert!(message.contains(expected), "{json}: {message}");
+        }
+    }
+
+    #[test]
+    fn test_deny_wins_over_allow() {
+        let parsed = policy(
+            r#"{"allow": ["*-linux-*", "mipsel-unknown-linux-musl"],
+                "deny": [{"pattern": "mips*", "message": "mips is not supported by SecOps, use aarch64 instead"},
+                         "*-linux-uclibc*"],
+                "message": "ask #platform"}"#,
+        );
+        assert_eq!(parsed.check("aarch64-unknown-linux-musl"), None);
+        assert_eq!(
+            parsed.check("mipsel-unknown-linux-musl"),
+            Some(Violation {
+                target: "mipsel-unknown-linux-musl".to_string(),
+                denied_by: Some("mips*".to_string()),
+                message: Some("mips is not supported by SecOps, use aarch64 instead".to_string()),
+            })
+        );
+        // The top-level message stands in for entries without one
+        assert_eq!(
+            parsed
+                .check("armv7-unknown-linux-uclibceabihf")
+                .unwrap()
+                .to_string(),
+            "armv7-unknown-linux-uclibceabihf is denied by '*-linux-uclibc*': ask #platform"
+        );
+        assert_eq!(
+            parsed.check("x86_64-pc-windows-gnu").unwrap().to_string(),
+            "x86_64-pc-windows-gnu is not in the allow list: ask #platform"
+        );
+    }
+
+    #[test]
+    fn test_enforce() {
+        let parsed = policy(r#"{"deny": [{"pattern": "mips*", "message": "use aarch64"}]}"#);
+        let targets = [
+            "aarch64-unknown-linux-musl".to_string(),
+            "mips-unknown-linux-musl".to_string(),
+        ];
+        let err = parsed.enforce(&targets, false).unwrap_err().to_string();
+        assert!(err.contains("policy.json"), "{err}");
+        assert!(
+            err.contains("mips-unknown-linux-musl is denied by 'mips*': use aarch64"),
+            "{err}"
+        );
+        assert!(!err.contains("aarch64-unknown-linux-musl is"), "{err}");
+        assert!(parsed.enforce(&targets, true).is_ok());
+        assert!(parsed.enforce(&targets[..1], false).is_ok());
+    }
+}
@@ -0,0 +1,289 @@
+//! Tool names for a target without setting anything up
+//!
+//! Build scripts and other tooling can ask which compiler, archiver and linker cargo-cross
+//! would configure for a target, and which toolchain directory it would install, without
+//! downloading anything. The names come from the same functions the platform setup uses.
+//!
+//! Apple targets and MSVC are not covered: their tool names depend on the installed SDK
+//! or on Visual Studio.
+
+use crate::cli::Args;
+use crate::config::{get_target_config, HostPlatform, Libc, Os, DEFAULT_GLIBC_VERSION};
+use crate::error::{CrossError, Result};
+use crate::layout::{entry_name, SDKS_DIR, TOOLCHAINS_DIR};
+use crate::platform::{
+    android, cross_make_dir_name, freebsd, get_linux_bin_prefix, get_linux_folder_name, netbsd,
+    windows, CompilerNames,
+};
+
+/// Versions and host that tool and directory names depend on
+#[derive(Debug, Clone)]
+pub struct ToolNameOptions {
+    pub host: HostPlatform,
+    pub cross_make_version: String,
+    pub glibc_version: String,
+    pub freebsd_version: String,
+    pub ndk_version: String,
+    /// Name the NDK's own clang wrappers on Windows hosts instead of the generated ones
+    pub android_use_ndk_wrappers: bool,
+}
+
+impl ToolNameOptions {
+    /// Options of a parsed command line
+    #[must_use]
+    pub fn from_args(args: &Args, host: &HostPlatform) -> Self {
+        Self {
+            host: host.clone(),
+            cross_make_version: args.cross_make_version.clone(),
+            glibc_version: args.glibc_version.clone(),
+            freebsd_version: args.freebsd_version.clone(),
+            ndk_version: args.ndk_version.clone(),
+            android_use_ndk_wrappers: args.android_use_ndk_wrappers,
+        }
+    }
+}
+
+/// Tools cargo-cross would configure for a target, and where they are installed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolNames {
+    pub cc: String,
+    pub cxx: String,
+    pub ar: String,
+    pub linker: String,
+    /// Prefix of the tool names, e.g. `aarch64-linux-musl` or `aarch64-linux-android24`
+    pub bin_prefix: String,
+    /// Toolchain directory relative to the cross compiler directory, e.g.
+    /// `toolchains/aarch64-linux-musl-cross-v0.7.7`
+    pub folder_name: String,
+}
+
+impl ToolNames {
+    fn new(compilers: CompilerNames, bin_prefix: String, folder_name: String) -> Self {
+        Self {
+            cc: compilers.cc,
+            cxx: compilers.cxx,
+            ar: compilers.ar,
+            linker: compilers.linker,
+            bin_prefix,
+            folder_name,
+        }
+    }
+
+    /// JSON object with one key per field
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "cc": self.cc,
+            "cxx": self.cxx,
+            "ar": self.ar,
+            "linker": self.linker,
+            "bin_prefix": self.bin_prefix,
+            "folder_name": self.folder_name,
+        })
+    }
+}
+
+/// Names of a cross-make GCC toolchain
+fn cross_make_names(
+    bin_prefix: String,
+    cross_compiler_name: &str,
+    opts: &ToolNameOptions,
+) -> ToolNames {
+    ToolNames::new(
+        CompilerNames::gcc(&bin_prefix, &opts.host),
+        bin_prefix,
+        entry_name(
+            TOOLCHAINS_DIR,
+            &cross_make_dir_name(cross_compiler_name, &opts.cross_make_version),
+        ),
+    )
+}
+
+/// Compiler, archiver and linker names cargo-cross would use for `target`
+///
+/// # Errors
+///
+/// Returns an error for unknown targets, architectures a platform does not support, and
+/// targets whose tool names are only known after setup (Apple targets, MSVC)
+pub fn compiler_names(target: &str, opts: &ToolNameOptions) -> Result<ToolNames> {
+    let config = get_target_config(target).ok_or_else(|| CrossError::TargetNotFound {
+        target: target.to_string(),
+    })?;
+    let arch = config.arch;
+    match (config.os, config.libc) {
+        (Os::Linux, Some(libc)) => {
+            let bin_prefix = get_linux_bin_prefix(arch, libc, config.abi);
+            let name = get_linux_folder_name(
+                arch,
+                libc,
+                config.abi,
+                &opts.glibc_version,
+                DEFAULT_GLIBC_VERSION,
+            );
+            Ok(cross_make_names(bin_prefix, &name, opts))
+        }
+        (Os::Windows, libc) if libc != Some(Libc::Msvc) => {
+            windows::validate(config, &opts.host)?;
+            let bin_prefix = windows::mingw_bin_prefix(arch);
+            let name = format!("{bin_prefix}-cross");
+            Ok(cross_make_names(bin_prefix, &name, opts))
+        }
+        (Os::FreeBsd, _) => {
+            freebsd::validate(config)?;
+            let bin_prefix = freebsd::bin_prefix(arch, &opts.freebsd_version);
+            let name = format!("{bin_prefix}-cross");
+            Ok(cross_make_names(bin_prefix, &name, opts))
+        }
+        (Os::NetBsd, _) => {
+            netbsd::validate(config)?;
+            let name = format!("{}-cross", netbsd::BIN_PREFIX);
+            Ok(cross_make_names(
+                netbsd::BIN_PREFIX.to_string(),
+                &name,
+                opts,
+            ))
+        }
+        (Os::Android, _) => {
+            let android_arch = android::android_arch(arch, &opts.ndk_version)?;
+            let clang_prefix = android_arch.clang_prefix();
+            Ok(ToolNames::new(
+                android::ndk_compiler_names(
+                    &clang_prefix,
+                    &opts.host,
+                    opts.android_use_ndk_wrappers,
+                ),
+                clang_prefix,
+                entry_name(
+                    SDKS_DIR,
+                    &android::ndk_dir_name(&opts.host, &opts.ndk_version),
+                ),
+            ))
+        }
+        _ => Err(CrossError::UnsupportedTarget(format!(
+            "{target}: tool names are only known after setup for this platform"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opts(os: &'static str) -> ToolNameOptions {
+        ToolNameOptions {
+            host: HostPlatform {
+                os,
+                arch: "amd64",
+                triple: format!("x86_64-unknown-{os}"),
+            },
+            cross_make_version: "v0.7.7".to_string(),
+            glibc_version: String::new(),
+            freebsd_version: "13.5".to_string(),
+            ndk_version: "r27d".to_string(),
+            android_use_ndk_wrappers: false,
+        }
+    }
+
+    fn names(target: &str, opts: &ToolNameOptions) -> ToolNames {
+        compiler_names(target, opts).unwrap()
+    }
+
+    #[test]
+    fn test_linux_names() {
+        let linux = opts("linux");
+        assert_eq!(
+            names("aarch64-unknown-linux-musl", &linux),
+            ToolNames {
+                cc: "aarch64-linux-musl-gcc".to_string(),
+                cxx: "aarch64-linux-musl-g++".to_string(),
+                ar: "aarch64-linux-musl-ar".to_string(),
+                linker: "aarch64-linux-musl-gcc".to_string(),
+                bin_prefix: "aarch64-linux-musl".to_string(),
+                folder_name: "toolchains/aarch64-linux-musl-cross-v0.7.7".to_string(),
+            }
+        );
+        let armv7 = names("armv7-unknown-linux-gnueabihf", &linux);
+        assert_eq!(armv7.cc, "armv7-linux-gnueabihf-gcc");
+        assert_eq!(
+            armv7.folder_name,
+            "toolchains/armv7-linux-gnueabihf-cross-v0.7.7"
+        );
+
+        // A non-default glibc gets its own directory but keeps the tool names
+        let mut glibc = linux.clone();
+        glibc.glibc_version = "2.31".to_string();
+        let gnu = names("x86_64-unknown-linux-gnu", &glibc);
+        assert_eq!(gnu.cc, "x86_64-linux-gnu-gcc");
+        assert_eq!(
+            gnu.folder_name,
+            "toolchains/x86_64-linux-gnu-2.31-cross-v0.7.7"
+        );
+    }
+
+    #[test]
+    fn test_windows_names() {
+        let mingw = names("x86_64-pc-windows-gnu", &opts("linux"));
+        assert_eq!(mingw.cc, "x86_64-w64-mingw32-gcc");
+        assert_eq!(mingw.ar, "x86_64-w64-mingw32-ar");
+        assert_eq!(
+            mingw.folder_name,
+            "toolchains/x86_64-w64-mingw32-cross-v0.7.7"
+        );
+
+        // Tools on a Windows host carry .exe
+        let on_windows = names("i686-pc-windows-gnu", &opts("windows"));
+        assert_eq!(on_windows.linker, "i686-w64-mingw32-gcc.exe");
+
+        assert!(compiler_names("x86_64-pc-windows-msvc", &opts("windows")).is_err());
+    }
+
+    #[test]
+    fn test_bsd_names() {
+        let freebsd = names("x86_64-unknown-freebsd", &opts("linux"));
+        assert_eq!(freebsd.cc, "x86_64-unknown-freebsd13.5-gcc");
+        assert_eq!(
+            freebsd.folder_name,
+            "toolchains/x86_64-unknown-freebsd13.5-cross-v0.7.7"
+        );
+        assert!(compiler_names("i686-unknown-freebsd", &opts("linux")).is_err());
+
+        let netbsd = names("x86_64-unknown-netbsd", &opts("linux"));
+        assert_eq!(netbsd.cxx, "x86_64-unknown-netbsd-g++");
+        assert_eq!(
+            netbsd.folder_name,
+            "toolchains/x86_64-unknown-netbsd-cross-v0.7.7"
+        );
+    }
+
+    #[test]
+    fn test_android_names() {
+        let android = names("aarch64-linux-android", &opts("linux"));
+        assert_eq!(android.cc, "aarch64-linux-android24-clang");
+        assert_eq!(android.cxx, "aarch64-linux-android24-clang++");
+        assert_eq!(android.ar, "llvm-ar");
+        assert_eq!(android.bin_prefix, "aarch64-linux-android24");
+        assert_eq!(android.folder_name, "sdks/android-ndk-linux-r27d");
+
+        // Setup generates .bat wrappers on Windows unless told to use the NDK's .cmd ones
+        let mut windows = opts("windows");
+        let on_windows = names("armv7-linux-androideabi", &windows);
+        assert_eq!(on_windows.cc, "armv7a-linux-androideabi24-clang.bat");
+        assert_eq!(on_windows.ar, "llvm-ar.exe");
+        assert_eq!(on_windows.folder_name, "sdks/android-ndk-windows-r27d");
+        windows.android_use_ndk_wrappers = true;
+        let ndk_wrappers = names("armv7-linux-androideabi", &windows);
+        assert_eq!(ndk_wrappers.cc, "armv7a-linux-androideabi24-clang.cmd");
+    }
+
+    #[test]
+    fn test_unsupported_targets() {
+        assert!(matches!(
+            compiler_names("aarch64-apple-darwin", &opts("linux")),
+            Err(CrossError::UnsupportedTarget(_))
+        ));
+        assert!(matches!(
+            compiler_names("not-a-target", &opts("linux")),
+            Err(CrossError::TargetNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,459 @@
+//! Machine-readable event stream for IDEs and other wrappers (`--event-stream`)
+//!
+//! Each event is one JSON object per line with an `event` field naming its kind. The
+//! schema is stable: existing fields keep their name and meaning, new ones may be added.
+//!
+//! | `event`             | fields                                                  |
+//! |---------------------|---------------------------------------------------------|
+//! | `run_started`       | `command`, `targets`                                    |
+//! | `target_started`    | `target`, `index` (1-based), `total`                    |
+//! | `download_progress` | `url`, `downloaded`, `total` and `pct` (null if unknown) |
+//! | `cargo_started`     | `target`, `argv`                                        |
+//! | `cargo_output`      | `target`, `line` (a line cargo wrote to stderr)         |
+//! | `cargo_finished`    | `target`, `code` (null if killed by a signal), `retries` |
+//! | `target_finished`   | `target`, `status` (`success`/`failed`), `seconds`, `error` |
+//! | `run_finished`      | `status`, `seconds`                                     |
+//! | `artifacts_collected` | `target`, `artifacts`: `path` and `debuginfo` (split debug file or null) of each file collected with `--artifact-name` |
+//! | `setup_applied`     | `target`, `variables`, `paths`, `cargo_config` (what `setup` wrote; empty when nothing changed) |
+//!
+//! The human-readable output is not affected.
+
+use crate::error::{CrossError, Result};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Outcome of a target or the whole run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    Failed,
+}
+
+impl Status {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// An event of the stream
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    RunStarted {
+        command: String,
+        targets: Vec<String>,
+    },
+    TargetStarted {
+        target: String,
+        index: usize,
+        total: usize,
+    },
+    DownloadProgress {
+        url: String,
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    CargoStarted {
+        target: String,
+        argv: Vec<String>,
+    },
+    CargoOutput {
+        target: String,
+        line: String,
+    },
+    CargoFinished {
+        target: String,
+        code: Option<i32>,
+        retries: u32,
+    },
+    TargetFinished {
+        target: String,
+        status: Status,
+        seconds: f64,
+        error: Option<String>,
+    },
+    RunFinished {
+        status: Status,
+        seconds: f64,
+    },
+    SetupApplied {
+        target: String,
+        variables: Vec<String>,
+        paths: Vec<String>,
+        cargo_config: Option<String>,
+    },
+    /// Files collected into the artifact directory, with their split debug info
+    ArtifactsCollected {
+        target: String,
+        artifacts: Vec<(PathBuf, Option<PathBuf>)>,
+    },
+}
+
+/// Whole percent of `total` that `downloaded` is, if the total is known
+#[must_use]
+pub fn percent(downloaded: u64, total: Option<u64>) -> Option<u64> {
+    total
+        .filter(|total| *total > 0)
+        .map(|total| (u128::from(downloaded.min(total)) * 100 / u128::from(total)) as u64)
+}
+
+impl Event {
+    /// Value of the `event` field
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::RunStarted { .. } => "run_started",
+            Self::TargetStarted { .. } => "target_started",
+            Self::DownloadProgress { .. } => "download_progress",
+            Self::CargoStarted { .. } => "cargo_started",
+            Self::CargoOutput { .. } => "cargo_output",
+            Self::CargoFinished { .. } => "cargo_finished",
+            Self::TargetFinished { .. } => "target_finished",
+            Self::RunFinished { .. } => "run_finished",
+            Self::SetupApplied { .. } => "setup_applied",
+            Self::ArtifactsCollected { .. } => "artifacts_collected",
+        }
+    }
+
+    /// The event as a JSON object
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        let mut value = match self {
+            Self::RunStarted { command, targets } => json!({
+                "command": command,
+                "targets": targets,
+            }),
+            Self::TargetStarted {
+                target,
+                index,
+                total,
+            } => json!({ "target": target, "index": index, "total": total }),
+            Self::DownloadProgress {
+                url,
+                downloaded,
+                total,
+            } => json!({
+                "url": url,
+                "downloaded": downloaded,
+                "total": total,
+                "pct": percent(*downloaded, *total),
+            }),
+            Self::CargoStarted { target, argv } => json!({ "target": target, "argv": argv }),
+            Self::CargoOutput { target, line } => json!({ "target": target, "line": line }),
+            Self::CargoFinished {
+                target,
+                code,
+                retries,
+            } => json!({ "target": target, "code": code, "retries": retries }),
+            Self::TargetFinished {
+                target,
+                status,
+                seconds,
+                error,
+            } => json!({
+                "target": target,
+                "status": status.as_str(),
+                "seconds": seconds,
+                "error": error,
+            }),
+            Self::RunFinished { status, seconds } => json!({
+                "status": status.as_str(),
+                "seconds": seconds,
+            }),
+            Self::SetupApplied {
+                target,
+                variables,
+                paths,
+                cargo_config,
+            } => json!({
+                "target": target,
+                "variables": variables,
+                "paths": paths,
+                "cargo_config": cargo_config,
+            }),
+            Self::ArtifactsCollected { target, artifacts } => json!({
+                "target": target,
+                "artifacts": artifacts
+                    .iter()
+                    .map(|(path, debuginfo)| json!({
+                        "path": path.display().to_string(),
+                        "debuginfo": debuginfo.as_ref().map(|path| path.display().to_string()),
+                    }))
+                    .collect::<Vec<_>>(),
+            }),
+        };
+        value["event"] = json!(self.name());
+        value
+    }
+}
+
+/// Where `--event-stream` writes to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Destination {
+    /// An inherited file descriptor, `fd:N`
+    Fd(u32),
+    File(PathBuf),
+}
+
+impl Destination {
+    /// Parse `fd:N` or a path
+    pub fn parse(spec: &str) -> Result<Self> {
+        match spec.strip_prefix("fd:") {
+            Some(fd) => fd.parse().map(Self::Fd).map_err(|_| {
+                CrossError::InvalidArgument(format!(
+                    "--event-stream: invalid file descriptor '{fd}', expected fd:N or a path"
+                ))
+            }),
+            None if spec.is_empty() => Err(CrossError::InvalidArgument(
+                "--event-stream: expected fd:N or a path".to_string(),
+            )),
+            None => Ok(Self::File(PathBuf::from(spec))),
+        }
+    }
+
+    fn open(&self) -> Result<std::fs::File> {
+        let path = match self {
+            Self::File(path) => path.clone(),
+            // Reopening through /dev/fd needs no unsafe fd ownership
+            Self::Fd(fd) if cfg!(unix) => PathBuf::from(format!("/dev/fd/{fd}")),
+            Self::Fd(_) => {
+                return Err(CrossError::InvalidArgument(
+                    "--event-stream fd:N is only supported on Unix; pass a path".to_string(),
+                ))
+            }
+        };
+        let mut options = std::fs::File::options();
+        options.write(true);
+        if matches!(self, Self::File(_)) {
+            options.create(true).truncate(true);
+        }
+        options.open(&path).map_err(|source| CrossError::IoError {
+            message: format!("Failed to open event stream {}", path.display()),
+            source,
+        })
+    }
+}
+
+static SINK: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+
+/// Send events to `spec` (`fd:N` or a path) from now on
+pub fn open(spec: &str) -> Result<()> {
+    let file = Destination::parse(spec)?.open()?;
+    set_sink(Box::new(file));
+    Ok(())
+}
+
+/// Send events to `sink` from now on
+pub fn set_sink(sink: Box<dyn Write + Send>) {
+    *SINK
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(sink);
+}
+
+/// Whether an event stream is open
+#[must_use]
+pub fn enabled() -> bool {
+    SINK.lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .is_some()
+}
+
+/// Write an event line. A reader that went away closes the stream instead of failing
+/// the build.
+pub fn emit(event: &Event) {
+    let mut sink = SINK
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let Some(writer) = sink.as_mut() else {
+        return;
+    };
+    let written = writeln!(writer, "{}", event.to_json()).and_then(|()| writer.flush());
+    if written.is_err() {
+        *sink = None;
+    }
+}
+
+/// Turns byte counts of one download into `download_progress` events, one per whole
+/// percent (or per MiB when the size is unknown)
+#[derive(Debug)]
+pub struct DownloadProgress {
+    url: String,
+    total: Option<u64>,
+    last: Option<u64>,
+}
+
+impl DownloadProgress {
+    #[must_use]
+    pub fn new(url: &str, total: Option<u64>) -> Self {
+        Self {
+            url: url.to_string(),
+            total,
+            last: None,
+        }
+    }
+
+    /// Event for `downloaded` bytes, if it moved past the last reported step
+    pub fn event(&mut self, downloaded: u64) -> Option<Event> {
+        let step = percent(downloaded, self.total).unwrap_or(downloaded >> 20);
+        if self.last == Some(step) {
+            return None;
+        }
+        self.last = Some(step);
+        Some(Event::DownloadProgress {
+            url: self.url.clone(),
+            downloaded,
+            total: self.total,
+        })
+    }
+
+    /// Emit the event for `downloaded` bytes, if any
+    pub fn update(&mut self, downloaded: u64) {
+        if enabled() {
+            if let Some(event) = self.event(downloaded) {
+                emit(&event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json() {
+        let cases = [
+            (
+                Event::RunStarted {
+                    command: "build".to_string(),
+                    targets: vec!["aarch64-unknown-linux-musl".to_string()],
+                },
+                r#"{"command":"build","event":"run_started","targets":["aarch64-unknown-linux-musl"]}"#,
+            ),
+            (
+                Event::DownloadProgress {
+                    url: "https://example.com/gcc.tgz".to_string(),
+                    downloaded: 512,
+                    total: Some(2048),
+                },
+                r#"{"downloaded":512,"event":"download_progress","pct":25,"total":2048,"url":"https://example.com/gcc.tgz"}"#,
+            ),
+            (
+                Event::DownloadProgress {
+                    url: "u".to_string(),
+                    downloaded: 512,
+                    total: None,
+                },
+                r#"{"downloaded":512,"event":"download_progress","pct":null,"total":null,"url":"u"}"#,
+            ),
+            (
+                Event::CargoFinished {
+                    target: "x86_64-pc-windows-gnu".to_string(),
+                    code: None,
+                    retries: 1,
+                },
+                r#"{"code":null,"event":"cargo_finished","retries":1,"target":"x86_64-pc-windows-gnu"}"#,
+            ),
+            (
+                Event::TargetFinished {
+                    target: "aarch64-unknown-linux-musl".to_string(),
+                    status: Status::Failed,
+                    seconds: 1.5,
+                    error: Some("Cargo exited with code 101".to_string()),
+                },
+                r#"{"error":"Cargo exited with code 101","event":"target_finished","seconds":1.5,"status":"failed","target":"aarch64-unknown-linux-musl"}"#,
+            ),
+            (
+                Event::RunFinished {
+                    status: Status::Success,
+                    seconds: 3.0,
+                },
+                r#"{"event":"run_finished","seconds":3.0,"status":"success"}"#,
+            ),
+            (
+                Event::ArtifactsCollected {
+                    target: "aarch64-unknown-linux-musl".to_string(),
+                    artifacts: vec![
+                        (
+                            PathBuf::from("dist/acmed"),
+                            Some(PathBuf::from("dist/acmed.debug")),
+                        ),
+                        (PathBuf::from("dist/libacme_ffi.a"), None),
+                    ],
+                },
+                r#"{"artifacts":[{"debuginfo":"dist/acmed.debug","path":"dist/acmed"},{"debuginfo":null,"path":"dist/libacme_ffi.a"}],"event":"artifacts_collected","target":"aarch64-unknown-linux-musl"}"#,
+            ),
+        ];
+        for (event, expected) in cases {
+            assert_eq!(event.to_json().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_every_event_has_its_name() {
+        let target = || "t".to_string();
+        for event in [
+            Event::TargetStarted {
+                target: target(),
+                index: 1,
+                total: 2,
+            },
+            Event::CargoStarted {
+                target: target(),
+                argv: vec!["cargo".to_string(), "build".to_string()],
+            },
+            Event::CargoOutput {
+                target: target(),
+                line: "warning: unused variable".to_string(),
+            },
+            Event::SetupApplied {
+                target: target(),
+                variables: vec!["CC_t".to_string()],
+                paths: Vec::new(),
+                cargo_config: None,
+            },
+        ] {
+            let line = event.to_json().to_string();
+            let parsed: Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(parsed["event"], event.name());
+            assert_eq!(parsed["target"], "t");
+        }
+    }
+
+    #[test]
+    fn test_destination_parse() {
+        assert_eq!(Destination::parse("fd:3").unwrap(), Destination::Fd(3));
+        assert_eq!(
+            Destination::parse("events.jsonl").unwrap(),
+            Destination::File(PathBuf::from("events.jsonl"))
+        );
+        assert!(Destination::parse("fd:three").is_err());
+        assert!(Destination::parse("").is_err());
+    }
+
+    #[test]
+    fn test_download_progress_steps() {
+        let mut progress = DownloadProgress::new("u", Some(1000));
+        let pcts: Vec<Option<u64>> = [0, 3, 9, 10, 15, 1000, 1000]
+            .into_iter()
+            .filter_map(|downloaded| progress.event(downloaded))
+            .map(|event| match event {
+                Event::DownloadProgress {
+                    downloaded, total, ..
+                } => percent(downloaded, total),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(pcts, [Some(0), Some(1), Some(100)]);
+
+        let mut unknown = DownloadProgress::new("u", None);
+        assert!(unknown.event(10).is_some());
+        assert!(unknown.event(1 << 19).is_none());
+        assert!(unknown.event(1 << 20).is_some());
+    }
+}
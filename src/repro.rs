@@ -0,0 +1,329 @@
+//! Reproduction scripts for cargo invocations (`--emit-repro`)
+//!
+//! The script sets exactly the variables cargo-cross sets on the cargo command, changes to
+//! its working directory and runs the same argv, so a failing build can be replayed and
+//! bisected without cargo-cross. Secrets are not written out: the script takes them from
+//! the environment it runs in.
+
+use crate::env::{is_secret_env, redact_url_credentials, shell_quote};
+use crate::error::{CrossError, Result};
+use crate::generated::atomic_write_mode;
+use std::path::{Path, PathBuf};
+use tokio::process::Command as TokioCommand;
+
+/// Script language of a reproduction script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptKind {
+    Bash,
+    PowerShell,
+}
+
+impl ScriptKind {
+    /// Language for the host: `PowerShell` on Windows, bash elsewhere
+    #[must_use]
+    pub const fn for_host() -> Self {
+        if cfg!(windows) {
+            Self::PowerShell
+        } else {
+            Self::Bash
+        }
+    }
+}
+
+/// What a cargo command changes relative to the caller's environment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Invocation {
+    pub argv: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    /// Variables set (`Some`) or removed (`None`), sorted by name
+    pub env: Vec<(String, Option<String>)>,
+}
+
+impl Invocation {
+    /// Capture the argv, working directory and environment delta of a command
+    #[must_use]
+    pub fn from_command(cmd: &TokioCommand) -> Self {
+        let cmd = cmd.as_std();
+        let argv = std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        let mut env: Vec<_> = cmd
+            .get_envs()
+            .map(|(key, value)| {
+                (
+                    key.to_string_lossy().into_owned(),
+                    value.map(|value| value.to_string_lossy().into_owned()),
+                )
+            })
+            .collect();
+        env.sort();
+        Self {
+            argv,
+            cwd: cmd.get_current_dir().map(Path::to_path_buf),
+            env,
+        }
+    }
+}
+
+/// Quote a `PowerShell` string literal; single-quoted strings only interpret `'`
+#[must_use]
+pub fn powershell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Whether the value of `key` is left out of the script: secret-looking names (the
+/// `--secret-env` patterns included) and URLs with credentials
+fn is_secret(key: &str, value: &str, secret_patterns: &[String]) -> bool {
+    is_secret_env(key, secret_patterns) || redact_url_credentials(value).is_some()
+}
+
+/// Render a standalone script that replays `invocation`. Secret values are read from the
+/// environment the script runs in, which has to set them.
+#[must_use]
+pub fn render_script(
+    invocation: &Invocation,
+    kind: ScriptKind,
+    target: &str,
+    secret_patterns: &[String],
+) -> String {
+    let mut script = String::new();
+    match kind {
+        ScriptKind::Bash => {
+            script.push_str("#!/usr/bin/env bash\n");
+            script.push_str(&format!(
+                "# Reproduces the cargo invocation of cargo-cross for {target}\nset -euo pipefail\n\n"
+            ));
+            if let Some(cwd) = &invocation.cwd {
+                script.push_str(&format!("cd {}\n", shell_quote(&cwd.to_string_lossy())));
+            }
+            for (key, value) in &invocation.env {
+                match value {
+                    Some(value) if is_secret(key, value, secret_patterns) => {
+                        script.push_str(&format!(
+                            "export {key}=\"${{{key}:?set {key}, left out of this script as a secret}}\"\n"
+                        ));
+                    }
+                    Some(value) => {
+                        script.push_str(&format!("export {key}={}\n", shell_quote(value)));
+                    }
+                    None => script.push_str(&format!("unset {key}\n")),
+                }
+            }
+            let argv: Vec<String> = invocation.argv.iter().map(|arg| shell_quote(arg)).collect();
+            script.push_str(&format!("\nexec {}\n", argv.join(" ")));
+        }
+        ScriptKind::PowerShell => {
+            script.push_str(&format!(
+                "# Reproduces the cargo invocation of cargo-cross for {target}\n\
+                 $ErrorActionPreference = 'Stop'\n\n"
+            ));
+            if let Some(cwd) = &invocation.cwd {
+                script.push_str(&format!(
+                    "Set-Location -LiteralPath {}\n",
+                    powershell_quote(&cwd.to_string_lossy())
+                ));
+            }
+            for (key, value) in &invocation.env {
+                match value {
+                    Some(value) if is_secret(key, value, secret_patterns) => {
+                        script.push_str(&format!(
+                            "if ($null -eq ${{env:{key}}}) {{ throw {} }}\n",
+                            powershell_quote(&format!(
+                                "Set {key}, left out of this script as a secret"
+                            ))
+                        ));
+                    }
+                    Some(value) => {
+                        script.push_str(&format!("${{env:{key}}} = {}\n", powershell_quote(value)))
+                    }
+                    None => script.push_str(&format!(
+                        "Remove-Item -ErrorAction SilentlyContinue -LiteralPath {}\n",
+                        powershell_quote(&format!("Env:{key}"))
+                    )),
+                }
+            }
+            let argv: Vec<String> = invocation
+                .argv
+                .iter()
+                .map(|arg| powershell_quote(arg))
+                .collect();
+            script.push_str(&format!("\n& {}\nexit $LASTEXITCODE\n", argv.join(" ")));
+        }
+    }
+    script
+}
+
+/// Script path for `target`: `path` itself for a single target, otherwise with the
+/// target appended to the file stem
+#[must_use]
+pub fn script_path(path: &Path, target: &str, multiple_targets: bool) -> PathBuf {
+    if !multiple_targets {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}-{target}.{}", ext.to_string_lossy()),
+        None => format!("{stem}-{target}"),
+    };
+    path.with_file_name(name)
+}
+
+/// Write the reproduction script of `cmd` for `target`, readable by its owner only
+pub fn write_script(
+    path: &Path,
+    cmd: &TokioCommand,
+    target: &str,
+    secret_patterns: &[String],
+) -> Result<()> {
+    let script = render_script(
+        &Invocation::from_command(cmd),
+        ScriptKind::for_host(),
+        target,
+        secret_patterns,
+    );
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|source| CrossError::IoError {
+            message: format!("Failed to create {}", parent.display()),
+            source,
+        })?;
+    }
+    atomic_write_mode(path, script.as_bytes(), Some(0o600))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invocation() -> Invocation {
+        Invocation {
+            argv: vec![
+                "cargo".to_string(),
+                "build".to_string(),
+                "--config".to_string(),
+                "build.rustflags=[\"-C\", \"target-cpu=native\"]".to_string(),
+            ],
+            cwd: Some(PathBuf::from("/src/my app")),
+            env: vec![
+                ("CARGO_INCREMENTAL".to_string(), None),
+                (
+                    "CFLAGS_aarch64".to_string(),
+                    Some("-DNAME='x' -O2\n-g".to_string()),
+                ),
+                (
+                    "SCCACHE_REDIS_ENDPOINT".to_string(),
+                    Some("redis://:hunter2@cache:6379".to_string()),
+                ),
+                ("SCCACHE_S3_TOKEN".to_string(), Some("hunter2".to_string())),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_render_bash_script() {
+        let script = render_script(
+            &invocation(),
+            ScriptKind::Bash,
+            "aarch64-unknown-linux-gnu",
+            &[],
+        );
+        assert!(script.starts_with("#!/usr/bin/env bash\n"));
+        assert!(script.contains("cd '/src/my app'\n"));
+        assert!(script.contains("unset CARGO_INCREMENTAL\n"));
+        let expected_env = format!(
+            "export CFLAGS_aarch64={}\n",
+            shell_quote("-DNAME='x' -O2\n-g")
+        );
+        assert!(script.contains(&expected_env), "{script}");
+        // Secrets come from the caller's environment
+        assert!(!script.contains("hunter2"), "{script}");
+        assert!(script.contains(
+            "export SCCACHE_S3_TOKEN=\"${SCCACHE_S3_TOKEN:?set SCCACHE_S3_TOKEN, left out of this script as a secret}\"\n"
+        ));
+        assert!(script.contains("export SCCACHE_REDIS_ENDPOINT=\"${SCCACHE_REDIS_ENDPOINT:?"));
+        assert!(script.contains(
+            "exec cargo build --config 'build.rustflags=[\"-C\", \"target-cpu=native\"]'\n"
+        ));
+
+        // Every quoted value reads back unchanged
+        let values: Vec<String> = shlex::split(script.lines().last().unwrap()).unwrap();
+        assert_eq!(values[1..], invocation().argv[..]);
+    }
+
+    #[test]
+    fn test_render_powershell_script() {
+        let script = render_script(
+            &invocation(),
+            ScriptKind::PowerShell,
+            "x86_64-pc-windows-gnu",
+            &[],
+        );
+        assert!(script.contains("Set-Location -LiteralPath '/src/my app'\n"));
+        assert!(script.contains(
+            "Remove-Item -ErrorAction SilentlyContinue -LiteralPath 'Env:CARGO_INCREMENTAL'\n"
+        ));
+        assert!(script.contains("${env:CFLAGS_aarch64} = '-DNAME=''x'' -O2\n-g'\n"));
+        assert!(!script.contains("hunter2"), "{script}");
+        assert!(script.contains(
+            "if ($null -eq ${env:SCCACHE_S3_TOKEN}) { throw 'Set SCCACHE_S3_TOKEN, left out of this script as a secret' }\n"
+        ));
+        assert!(script.contains(
+            "& 'cargo' 'build' '--config' 'build.rustflags=[\"-C\", \"target-cpu=native\"]'\n"
+        ));
+        assert!(script.ends_with("exit $LASTEXITCODE\n"));
+    }
+
+    #[test]
+    fn test_invocation_from_command() {
+        let mut cmd = TokioCommand::new("cargo");
+        cmd.args(["build", "--target", "aarch64-unknown-linux-musl"])
+            .env("CC_aarch64_unknown_linux_musl", "aarch64-linux-musl-gcc")
+            .env_remove("RUSTC_WRAPPER")
+            .current_dir("/src");
+        let invocation = Invocation::from_command(&cmd);
+        assert_eq!(
+            invocation.argv,
+            ["cargo", "build", "--target", "aarch64-unknown-linux-musl"]
+        );
+        assert_eq!(invocation.cwd, Some(PathBuf::from("/src")));
+        assert_eq!(
+            invocation.env,
+            vec![
+                (
+                    "CC_aarch64_unknown_linux_musl".to_string(),
+                    Some("aarch64-linux-musl-gcc".to_string())
+                ),
+                ("RUSTC_WRAPPER".to_string(), None),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_script_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("cargo-cross-repro-{}", std::process::id()));
+        let path = dir.join("repro.sh");
+        let mut cmd = TokioCommand::new("cargo");
+        cmd.arg("build").env("SCCACHE_S3_TOKEN", "hunter2");
+        write_script(&path, &cmd, "aarch64-unknown-linux-musl", &[]).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("hunter2"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_script_path() {
+        let path = Path::new("out/repro.sh");
+        assert_eq!(script_path(path, "aarch64-unknown-linux-musl", false), path);
+        assert_eq!(
+            script_path(path, "aarch64-unknown-linux-musl", true),
+            Path::new("out/repro-aarch64-unknown-linux-musl.sh")
+        );
+        assert_eq!(
+            script_path(Path::new("repro"), "x86_64-pc-windows-gnu", true),
+            Path::new("repro-x86_64-pc-windows-gnu")
+        );
+    }
+}
@@ -2,58 +2,318 @@
 
 use crate::cli::Args;
 use crate::color;
-use crate::config::{Arch, HostPlatform};
-use crate::download::download_and_extract;
-use crate::env::CrossEnv;
-use crate::error::Result;
-use std::path::Path;
-use tokio::fs;
+use crate::config::{Arch, HostPlatform, Libc, Os, TargetConfig};
+use crate::env::{shell_quote, CrossEnv};
+use crate::error::{run_command_output, CrossError, Result};
+use crate::generated::write_idempotent;
+use crate::layout;
+use crate::platform::ToolchainRelease;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+/// QEMU `-cpu` model for the target's `--target-cpu`, when one is known
+fn qemu_cpu_model(arch: Arch, rust_target: &str, args: &Args) -> Option<&'static str> {
+    args.target_cpu_for(rust_target)
+        .and_then(|cpu| arch.qemu_cpu(cpu))
+}
+
+/// Suggest an alternative when the host has no qemu-user-static bundle
+fn qemu_unsupported_hint(host: &HostPlatform) -> &'static str {
+    if host.is_windows() {
+        "Run the build inside WSL to execute Linux binaries with QEMU"
+    } else if host.is_darwin() {
+        "The Docker QEMU backend is used on macOS; ensure Docker is installed"
+    } else {
+        "Bundles are only published for linux-x86_64 and linux-aarch64 hosts"
+    }
+}
+
+/// qemu-user-static bundle for this host, located by the emulator binary it provides
+#[must_use]
+pub fn qemu_release(qemu_binary: &str, args: &Args, host: &HostPlatform) -> ToolchainRelease {
+    let dir = layout::qemu_dir(&args.cross_compiler_dir, &args.qemu_version);
+    ToolchainRelease {
+        kind: "qemu-user-static",
+        url: format!(
+            "https://github.com/zijiren233/qemu-user-static/releases/download/{}/qemu-user-static-{}-musl.tgz",
+            args.qemu_version,
+            host.download_platform()
+        ),
+        format: None,
+        marker: dir.join(qemu_binary),
+        dir,
+    }
+}
+
+/// Linux qemu-user-static bundle that the Docker runner mounts into the container
+#[must_use]
+pub fn docker_qemu_release(
+    qemu_binary: &str,
+    args: &Args,
+    host: &HostPlatform,
+) -> ToolchainRelease {
+    let dir = layout::qemu_dir(
+        &args.cross_compiler_dir,
+        &format!("{}-linux-{}", args.qemu_version, host.arch),
+    );
+    ToolchainRelease {
+        kind: "qemu-user-static",
+        url: format!(
+            "https://github.com/zijiren233/qemu-user-static/releases/download/{}/qemu-user-static-linux-{}-musl.tgz",
+            args.qemu_version,
+            host.arch
+        ),
+        format: None,
+        marker: dir.join(qemu_binary),
+        dir,
+    }
+}
+
+/// Library root for QEMU: `--qemu-sysroot`, else the compiler sysroot configured in `env`,
+/// else `<compiler_dir>/<bin_prefix>`
+#[must_use]
+pub fn qemu_sysroot(args: &Args, env: &CrossEnv, compiler_dir: &Path, bin_prefix: &str) -> PathBuf {
+    args.qemu_sysroot
+        .clone()
+        .or_else(|| env.sysroot.clone())
+        .unwrap_or_else(|| compiler_dir.join(bin_prefix))
+}
+
+/// Warn when a `--qemu-sysroot` has no `lib` directory for QEMU to load libraries from
+fn check_qemu_sysroot(args: &Args, sysroot: &Path) {
+    if args.qemu_sysroot.is_some() && !sysroot.join("lib").is_dir() {
+        color::log_warning_once(
+            "qemu-sysroot-lib",
+            &format!(
+                "--qemu-sysroot {} has no lib directory; QEMU may not find the dynamic linker",
+                sysroot.display()
+            ),
+        );
+    }
+}
+
+/// Whether `sysroot` has the musl dynamic linker (`lib/ld-musl-<arch>.so.1`)
+fn has_musl_loader(sysroot: &Path) -> bool {
+    find_loader(sysroot).is_some_and(|loader| {
+        loader
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with("ld-musl-"))
+    })
+}
+
+/// Warn when binaries linked dynamically against musl (`--crt-static false`) cannot find
+/// the musl loader in the sysroot QEMU is pointed at
+fn check_musl_loader(target_config: &TargetConfig, args: &Args, sysroot: &Path) {
+    let dynamic = crate::cargo::crt_static_decision(target_config.target, args.crt_static)
+        .is_some_and(|decision| !decision.static_crt);
+    if target_config.libc == Some(Libc::Musl) && dynamic && !has_musl_loader(sysroot) {
+        color::log_warning_once(
+            &format!("musl-loader-{}", target_config.target),
+            &format!(
+                "{} has no ld-musl-*.so.1 in {}/lib; QEMU cannot start the dynamically linked \
+                 binaries of {}. Pass --qemu-sysroot with a musl root filesystem",
+                sysroot.display(),
+                sysroot.display(),
+                color::yellow(target_config.target)
+            ),
+        );
+    }
+}
+
+/// Where the QEMU runner binary comes from, in order of precedence: `--qemu-path`, then
+/// the downloaded qemu-user-static bundle. Without either no runner is configured (the
+/// host may still run the binaries through `binfmt_misc`), or `--require-runner` fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QemuSource {
+    /// `--qemu-path`: the binary itself or a directory containing it
+    Path(PathBuf),
+    /// The qemu-user-static bundle for the host
+    Bundle,
+    /// No QEMU available, continue without a runner
+    Skip,
+    /// No QEMU available and `--require-runner` was given
+    Fail,
+}
+
+impl QemuSource {
+    /// Decide from `--qemu-path`, bundle availability and `--require-runner`
+    #[must_use]
+    pub fn new(qemu_path: Option<&Path>, bundle_available: bool, require_runner: bool) -> Self {
+        if let Some(path) = qemu_path {
+            return Self::Path(path.to_path_buf());
+        }
+        match RunnerDecision::new(bundle_available, require_runner) {
+            RunnerDecision::Configure => Self::Bundle,
+            RunnerDecision::Skip => Self::Skip,
+            RunnerDecision::Fail => Self::Fail,
+        }
+    }
+}
+
+/// Whether a QEMU binary emulates the architecture of `qemu_binary` (e.g. `qemu-aarch64`),
+/// judged by its file name (`qemu-aarch64`, `qemu-aarch64-static`) or the first word of
+/// its `--version` output
+#[must_use]
+pub fn qemu_binary_matches(
+    file_name: &str,
+    version_output: Option<&str>,
+    qemu_binary: &str,
+) -> bool {
+    let matches = |name: &str| {
+        name.strip_prefix(qemu_binary)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(['-', '.']))
+    };
+    matches(file_name)
+        || version_output
+            .and_then(|output| output.split_whitespace().next())
+            .is_some_and(|first| matches(first.rsplit('/').next().unwrap_or(first)))
+}
+
+/// Resolve `--qemu-path` to the QEMU binary for `qemu_binary`, checking that it is an
+/// executable file for the right architecture. `probe` runs it with `--version` when the
+/// file name does not tell the architecture; the Docker runner's Linux binary cannot run
+/// on the host, so only its name is checked.
+pub async fn resolve_qemu_path(path: &Path, qemu_binary: &str, probe: bool) -> Result<PathBuf> {
+    let invalid = |reason: String| {
+        CrossError::InvalidArgument(format!("--qemu-path {}: {reason}", path.display()))
+    };
+    let binary = if path.is_dir() {
+        let binary = path.join(qemu_binary);
+        if !binary.is_file() {
+            return Err(invalid(format!("directory has no {qemu_binary}")));
+        }
+        binary
+    } else if path.is_file() {
+        path.to_path_buf()
+    } else {
+        return Err(invalid("no such file or directory".to_string()));
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&binary)?.permissions().mode();
+        if mode & 0o111 == 0 {
+            return Err(invalid(format!("{} is not executable", binary.display())));
+        }
+    }
+
+    let file_name = binary
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if qemu_binary_matches(&file_name, None, qemu_binary) {
+        return Ok(binary);
+    }
+    let version = if probe {
+        let mut cmd = tokio::process::Command::new(&binary);
+        cmd.arg("--version");
+        run_command_output(&mut cmd, &binary.display().to_string())
+            .await
+            .ok()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        None
+    };
+    if qemu_binary_matches(&file_name, version.as_deref(), qemu_binary) {
+        Ok(binary)
+    } else {
+        Err(invalid(format!(
+            "{} does not look like {qemu_binary}; name it {qemu_binary} or point at a directory containing it",
+            binary.display()
+        )))
+    }
+}
 
 /// Setup QEMU runner for cross-compiled Linux binaries
 pub async fn setup_qemu_runner(
     env: &mut CrossEnv,
-    arch: Arch,
+    target_config: &TargetConfig,
     bin_prefix: &str,
     compiler_dir: &Path,
     args: &Args,
     host: &HostPlatform,
 ) -> Result<()> {
+    let arch = target_config.arch;
     let Some(qemu_binary) = arch.qemu_binary_name() else {
         return Ok(());
     };
 
-    let qemu_dir = args
-        .cross_compiler_dir
-        .join(format!("qemu-user-static-{}", args.qemu_version));
-
-    let qemu_path = qemu_dir.join(qemu_binary);
-
-    // Download QEMU if not present
-    if !qemu_path.exists() {
-        let host_platform = host.download_platform();
-        let download_url = format!(
-            "https://github.com/zijiren233/qemu-user-static/releases/download/{}/qemu-user-static-{}-musl.tgz",
-            args.qemu_version,
-            host_platform
-        );
-        download_and_extract(&download_url, &qemu_dir, None, args.github_proxy.as_deref()).await?;
-    }
+    // Check the host against the published bundles before attempting a download
+    let available = Arch::qemu_bundle_available(host.os, host.arch);
+    let qemu_path =
+        match QemuSource::new(args.qemu_path.as_deref(), available, args.runner_required()) {
+            QemuSource::Path(path) => resolve_qemu_path(&path, qemu_binary, true).await?,
+            QemuSource::Bundle => {
+                // Download QEMU if not present
+                let release = qemu_release(qemu_binary, args, host);
+                release.ensure(args).await?;
+                release.marker
+            }
+            QemuSource::Skip => {
+                color::log_warning_once(
+                    "qemu-unavailable",
+                    &format!(
+                "QEMU user-mode emulation is not available on {}, skipping runner setup for {}\n{}",
+                host.download_platform(),
+                color::yellow(target_config.target),
+                qemu_unsupported_hint(host)
+            ),
+                );
+                return Ok(());
+            }
+            QemuSource::Fail => {
+                return Err(CrossError::RunnerUnavailable {
+                    target: target_config.target.to_string(),
+                    reasons: vec![qemu_unavailable_reason(host)],
+                });
+            }
+        };
 
     if qemu_path.exists() {
-        // Add QEMU directory to PATH
-        env.add_path(&qemu_dir);
-
-        // Set runner using command name (relies on PATH) with sysroot
-        let sysroot = compiler_dir.join(bin_prefix);
-        if sysroot.join("lib").exists() {
-            env.set_runner(format!("{} -L {}", qemu_binary, sysroot.display()));
+        let display_path = qemu_path.display().to_string();
+        let mut runner = if args.qemu_path.is_some() && !display_path.contains(char::is_whitespace)
+        {
+            // A user-provided binary may live next to unrelated tools, keep it off PATH
+            display_path
         } else {
-            env.set_runner(qemu_binary);
+            // Set runner using command name (relies on PATH) with sysroot
+            if let Some(qemu_dir) = qemu_path.parent() {
+                env.add_path(qemu_dir);
+            }
+            qemu_path.file_name().map_or_else(
+                || qemu_binary.to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            )
+        };
+        if let Some(cpu) = qemu_cpu_model(arch, target_config.target, args) {
+            runner.push_str(&format!(" -cpu {cpu}"));
+        }
+        let sysroot = qemu_sysroot(args, env, compiler_dir, bin_prefix);
+        check_qemu_sysroot(args, &sysroot);
+        check_musl_loader(target_config, args, &sysroot);
+        if args.qemu_sysroot.is_some() || sysroot.join("lib").exists() {
+            let sysroot = sysroot.display().to_string();
+            if sysroot.contains(char::is_whitespace) {
+                // Cargo splits the runner on whitespace, so pass the sysroot via the environment
+                env.set_env("QEMU_LD_PREFIX", sysroot);
+            } else {
+                runner.push_str(&format!(" -L {sysroot}"));
+            }
+        }
+        if let Some(port) = args.qemu_gdb {
+            env.set_env("QEMU_GDB", port.to_string());
         }
+        env.set_runner(runner);
 
+        let shown = if args.qemu_path.is_some() {
+            qemu_path.display().to_string()
+        } else {
+            qemu_binary.to_string()
+        };
         color::log_success(&format!(
             "Configured QEMU runner: {} for {}",
-            color::yellow(qemu_binary),
+            color::yellow(&shown),
             color::yellow(arch.as_str())
         ));
     }
@@ -64,40 +324,57 @@ pub async fn setup_qemu_runner(
 /// Setup Docker QEMU runner for cross-compiled Linux binaries (for macOS host)
 pub async fn setup_docker_qemu_runner(
     env: &mut CrossEnv,
-    arch: Arch,
+    target_config: &TargetConfig,
     bin_prefix: &str,
     compiler_dir: &Path,
-    libc: &str,
     args: &Args,
     host: &HostPlatform,
 ) -> Result<()> {
-    // Check if Docker is available
-    if which::which("docker").is_err() {
-        color::log_warning("Docker not found, skipping Docker QEMU runner setup");
-        return Ok(());
-    }
+    let arch = target_config.arch;
+    let libc = target_config.libc.map_or("gnu", |libc| libc.as_str());
 
     let Some(qemu_binary) = arch.qemu_binary_name() else {
         return Ok(());
     };
-
-    // Download QEMU for Linux (to run inside Docker container)
-    let qemu_dir = args.cross_compiler_dir.join(format!(
-        "qemu-user-static-{}-linux-{}",
-        args.qemu_version, host.arch
-    ));
-
-    let qemu_path = qemu_dir.join(qemu_binary);
-
-    if !qemu_path.exists() {
-        let download_url = format!(
-            "https://github.com/zijiren233/qemu-user-static/releases/download/{}/qemu-user-static-linux-{}-musl.tgz",
-            args.qemu_version,
-            host.arch
+    if args.qemu_gdb.is_some() {
+        color::log_warning_once(
+            "docker-qemu-gdb",
+            "--qemu-gdb is not supported by the Docker QEMU runner; binaries run without gdb",
         );
-        download_and_extract(&download_url, &qemu_dir, None, args.github_proxy.as_deref()).await?;
     }
 
+    // Docker and a Linux QEMU (bundle or --qemu-path, run inside the container) are needed
+    let reasons = docker_unavailable_reasons(host, args.qemu_path.is_some(), |program| {
+        which::which(program).is_ok()
+    });
+    match RunnerDecision::new(reasons.is_empty(), args.runner_required()) {
+        RunnerDecision::Configure => {}
+        RunnerDecision::Skip => {
+            color::log_warning_once(
+                "docker-runner-unavailable",
+                &format!(
+                    "Skipping Docker QEMU runner setup for {}: {}",
+                    color::yellow(target_config.target),
+                    reasons.join("; ")
+                ),
+            );
+            return Ok(());
+        }
+        RunnerDecision::Fail => {
+            return Err(CrossError::RunnerUnavailable {
+                target: target_config.target.to_string(),
+                reasons,
+            });
+        }
+    }
+    let qemu_path = if let Some(ref path) = args.qemu_path {
+        resolve_qemu_path(path, qemu_binary, false).await?
+    } else {
+        let release = docker_qemu_release(qemu_binary, args, host);
+        release.ensure(args).await?;
+        release.marker
+    };
+
     if !qemu_path.exists() {
         return Ok(());
     }
@@ -110,24 +387,200 @@ pub async fn setup_docker_qemu_runner(
     };
 
     // Create runner script
-    let runner_script =
-        args.cross_compiler_dir
-            .join(format!("docker-qemu-runner-{}-{}.sh", arch.as_str(), libc));
+    let runner_base = layout::generated_dir(&args.cross_compiler_dir).join(format!(
+        "docker-qemu-runner-{}-{}.sh",
+        arch.as_str(),
+        libc
+    ));
+
+    let sysroot = qemu_sysroot(args, env, compiler_dir, bin_prefix);
+    check_qemu_sysroot(args, &sysroot);
 
-    let sysroot = compiler_dir.join(bin_prefix);
+    let script_content = render_docker_runner_script(
+        &qemu_path,
+        qemu_binary,
+        &sysroot,
+        docker_image,
+        qemu_cpu_model(arch, target_config.target, args),
+    );
 
-    let script_content = format!(
+    // Named after its content, so another template or QEMU selection gets its own file
+    let runner_script = write_idempotent(&runner_base, &script_content, true)?;
+
+    let runner_path = runner_script.display().to_string();
+    if runner_path.contains(char::is_whitespace) {
+        color::log_warning_once(
+            "docker-runner-spaces",
+            &format!(
+                "Docker QEMU runner path contains spaces and cargo will split it: {runner_path}\n\
+             Use --cross-compiler-dir with a path without spaces to run binaries"
+            ),
+        );
+    }
+    env.set_runner(runner_path);
+    // Test and bench run many binaries; let them share a container
+    if matches!(args.command.as_str(), "test" | "bench") {
+        env.set_env(RUNNER_SESSION_ENV, runner_session(target_config.target));
+    }
+
+    color::log_success(&format!(
+        "Configured Docker QEMU runner: {} for {} (image: {})",
+        color::yellow(qemu_binary),
+        color::yellow(arch.as_str()),
+        color::cyan(docker_image)
+    ));
+
+    Ok(())
+}
+
+/// Environment variable naming the runner session of a cargo-cross run for one target.
+/// Docker QEMU runner scripts that see it share one container per script.
+pub const RUNNER_SESSION_ENV: &str = "CARGO_CROSS_RUNNER_SESSION";
+
+/// Docker label holding the session of a shared runner container
+pub const RUNNER_SESSION_LABEL: &str = "cargo-cross.session";
+
+/// Sessions handed out by [`runner_session`] whose containers were not removed yet
+static SESSIONS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn lock_sessions() -> std::sync::MutexGuard<'static, Vec<String>> {
+    SESSIONS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Session id for `target` in this run: unique per process and target, and usable in
+/// container names
+#[must_use]
+pub fn runner_session(target: &str) -> String {
+    static RUN: LazyLock<String> = LazyLock::new(|| {
+        let started = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        format!("{}-{started}", std::process::id())
+    });
+    let session = format!(
+        "{}-{}",
+        *RUN,
+        target.replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+    );
+    let mut sessions = lock_sessions();
+    if !sessions.contains(&session) {
+        sessions.push(session.clone());
+    }
+    session
+}
+
+/// `docker <args>` with its output captured. Not tracked like other children: cleanup runs
+/// after an interrupt, when tracked commands are no longer started.
+async fn docker_output(args: &[&str]) -> std::io::Result<std::process::Output> {
+    tokio::process::Command::new("docker")
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+}
+
+/// Remove the shared runner containers of `session`. Failures only warn: the containers
+/// carry the [`RUNNER_SESSION_LABEL`] label, so they can be removed by hand.
+pub async fn remove_session_containers(session: &str) {
+    lock_sessions().retain(|s| s != session);
+    let filter = format!("label={RUNNER_SESSION_LABEL}={session}");
+    let ids: Vec<String> = match docker_output(&["ps", "-aq", "--filter", &filter]).await {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .map(String::from)
+            .collect(),
+        Err(e) => {
+            color::log_debug(&format!("Failed to list runner containers: {e}"));
+            return;
+        }
+    };
+    if ids.is_empty() {
+        return;
+    }
+    let remove: Vec<&str> = ["rm", "-f"]
+        .into_iter()
+        .chain(ids.iter().map(String::as_str))
+        .collect();
+    match docker_output(&remove).await {
+        Ok(output) if output.status.success() => color::log_debug(&format!(
+            "Removed {} runner container(s) of session {session}",
+            ids.len()
+        )),
+        Ok(output) => color::log_warning(&format!(
+            "Failed to remove runner containers {}: {}",
+            ids.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(e) => color::log_warning(&format!("Failed to remove runner containers: {e}")),
+    }
+}
+
+/// Remove the shared runner containers of every session still open. An interrupt calls
+/// this: cargo-cross exits without returning to the code that removes them after cargo.
+pub async fn remove_open_sessions() {
+    let sessions = lock_sessions().clone();
+    for session in sessions {
+        remove_session_containers(&session).await;
+    }
+}
+
+/// Render the Docker QEMU runner script; every interpolated path, image and argument is
+/// shell-quoted.
+/// With [`RUNNER_SESSION_ENV`] set, binaries reuse one container named after the session
+/// and the script's file name; otherwise, or when that fails, each gets its own container.
+#[must_use]
+pub fn render_docker_runner_script(
+    qemu_path: &Path,
+    qemu_binary: &str,
+    sysroot: &Path,
+    docker_image: &str,
+    qemu_cpu: Option<&str>,
+) -> String {
+    format!(
         r#"#!/bin/bash
 set -e
 
 # Docker QEMU Runner Script
-QEMU_PATH="{qemu_path}"
-QEMU_BINARY="{qemu_binary}"
-SYSROOT="{sysroot}"
-DOCKER_IMAGE="{docker_image}"
+QEMU_PATH={qemu_path}
+QEMU_BINARY={qemu_binary}
+SYSROOT={sysroot}
+DOCKER_IMAGE={docker_image}
+QEMU_CPU_ARGS={qemu_cpu_args}
+
+# Output settings of cargo-cross: CARGO_CROSS_COLOR=always|never|auto, CARGO_CROSS_QUIET=1
+COLOR="${{CARGO_CROSS_COLOR:-auto}}"
+if [[ "$COLOR" == "auto" ]]; then
+    if [[ -t 2 ]]; then COLOR=always; else COLOR=never; fi
+fi
+QUIET="${{CARGO_CROSS_QUIET:-0}}"
+
+info() {{
+    if [[ "$QUIET" == "1" ]]; then return 0; fi
+    if [[ "$COLOR" == "always" ]]; then
+        printf '\033[36m%s\033[0m\n' "$*" >&2
+    else
+        printf '%s\n' "$*" >&2
+    fi
+}}
+
+error() {{
+    if [[ "$COLOR" == "always" ]]; then
+        printf '\033[31mError:\033[0m %s\n' "$*" >&2
+    else
+        printf 'Error: %s\n' "$*" >&2
+    fi
+}}
+
+CREATE_ARGS=()
+if [[ "$QUIET" == "1" ]]; then CREATE_ARGS=(--quiet); fi
+EXEC_ENV=()
+if [[ "$COLOR" == "never" ]]; then EXEC_ENV=(-e NO_COLOR=1 -e TERM=dumb); fi
 
 if [[ $# -lt 1 ]]; then
-    echo "Usage: $0 <binary> [args...]" >&2
+    error "Usage: $0 <binary> [args...]"
     exit 1
 fi
 
@@ -135,77 +588,600 @@ BINARY="$1"
 shift
 
 if [[ ! -f "$BINARY" ]]; then
-    echo "Error: Binary not found: $BINARY" >&2
+    error "Binary not found: $BINARY"
     exit 1
 fi
 
 BINARY_NAME=$(basename "$BINARY")
 
-# Create container
-CONTAINER_ID=$(docker create --rm -i "$DOCKER_IMAGE" /bin/sh -c "sleep infinity")
+if ! docker image inspect "$DOCKER_IMAGE" >/dev/null 2>&1; then
+    info "Pulling $DOCKER_IMAGE for the QEMU runner"
+fi
 
-cleanup() {{
-    docker rm -f "$CONTAINER_ID" >/dev/null 2>&1 || true
+# Start a created container and copy QEMU and the sysroot into it
+prepare_container() {{
+    docker start "$1" >/dev/null &&
+        docker cp "$QEMU_PATH" "$1:/usr/bin/$QEMU_BINARY" >/dev/null &&
+        docker exec "$1" chmod +x "/usr/bin/$QEMU_BINARY" || return 1
+    if [[ -d "$SYSROOT/lib" ]]; then
+        docker cp "$SYSROOT" "$1:/sysroot" >/dev/null || return 1
+    fi
 }}
-trap cleanup EXIT
 
-# Start the container
-docker start "$CONTAINER_ID" >/dev/null
+# Within a cargo-cross session, binaries share one container per runner script, prepared
+# once and removed by cargo-cross after cargo exits
+CONTAINER_ID=""
+RUN_DIR=/tmp
+SESSION="${{{session_env}:-}}"
+if [[ -n "$SESSION" ]]; then
+    RUNNER_KEY=$(basename "$0" .sh)
+    CONTAINER_NAME="cargo-cross-${{SESSION//[^a-zA-Z0-9_.-]/_}}-${{RUNNER_KEY//[^a-zA-Z0-9_.-]/_}}"
+    if docker create "${{CREATE_ARGS[@]}}" --name "$CONTAINER_NAME" --label {session_label}="$SESSION" \
+        -i "$DOCKER_IMAGE" /bin/sh -c "sleep infinity" >/dev/null 2>&1; then
+        if prepare_container "$CONTAINER_NAME" &&
+            docker exec "$CONTAINER_NAME" touch /.cargo-cross-ready; then
+            CONTAINER_ID="$CONTAINER_NAME"
+        else
+            docker rm -f "$CONTAINER_NAME" >/dev/null 2>&1 || true
+        fi
+    else
+        # Another binary created it first; wait up to 60s until it is prepared
+        for _ in $(seq 1 600); do
+            if docker exec "$CONTAINER_NAME" test -f /.cargo-cross-ready >/dev/null 2>&1; then
+                CONTAINER_ID="$CONTAINER_NAME"
+                break
+            fi
+            if ! docker container inspect "$CONTAINER_NAME" >/dev/null 2>&1; then
+                break
+            fi
+            sleep 0.1
+        done
+        if [[ -z "$CONTAINER_ID" ]] && docker container inspect "$CONTAINER_NAME" >/dev/null 2>&1; then
+            error "Runner container $CONTAINER_NAME was not ready after 60s; preparing it may have hung (docker logs $CONTAINER_NAME)"
+        fi
+    fi
+    if [[ -n "$CONTAINER_ID" ]]; then
+        RUN_DIR=$(docker exec "$CONTAINER_ID" mktemp -d) || CONTAINER_ID=""
+    fi
+    if [[ -z "$CONTAINER_ID" ]]; then
+        info "Could not share runner container $CONTAINER_NAME, starting one for $BINARY_NAME"
+        RUN_DIR=/tmp
+    fi
+fi
+
+if [[ -n "$CONTAINER_ID" ]]; then
+    cleanup() {{
+        docker exec "$CONTAINER_ID" rm -rf "$RUN_DIR" >/dev/null 2>&1 || true
+    }}
+    trap cleanup EXIT
+else
+    # A container for this binary only
+    CONTAINER_ID=$(docker create "${{CREATE_ARGS[@]}}" --rm -i "$DOCKER_IMAGE" /bin/sh -c "sleep infinity")
 
-# Copy QEMU binary to container
-docker cp "$QEMU_PATH" "$CONTAINER_ID:/usr/bin/$QEMU_BINARY" >/dev/null
-docker exec "$CONTAINER_ID" chmod +x "/usr/bin/$QEMU_BINARY"
+    cleanup() {{
+        docker rm -f "$CONTAINER_ID" >/dev/null 2>&1 || true
+    }}
+    trap cleanup EXIT
 
-# Copy sysroot lib to container
-if [[ -d "$SYSROOT/lib" ]]; then
-    docker cp "$SYSROOT" "$CONTAINER_ID:/sysroot" >/dev/null
+    prepare_container "$CONTAINER_ID"
 fi
 
 # Copy the binary to execute
-docker cp "$BINARY" "$CONTAINER_ID:/tmp/$BINARY_NAME" >/dev/null
-docker exec "$CONTAINER_ID" chmod +x "/tmp/$BINARY_NAME"
+docker cp "$BINARY" "$CONTAINER_ID:$RUN_DIR/$BINARY_NAME" >/dev/null
+docker exec "$CONTAINER_ID" chmod +x "$RUN_DIR/$BINARY_NAME"
 
 # Run the binary with QEMU
-docker exec "$CONTAINER_ID" /usr/bin/$QEMU_BINARY -L /sysroot /tmp/$BINARY_NAME "$@"
+docker exec "${{EXEC_ENV[@]}}" "$CONTAINER_ID" "/usr/bin/$QEMU_BINARY" $QEMU_CPU_ARGS -L /sysroot "$RUN_DIR/$BINARY_NAME" "$@"
 "#,
-        qemu_path = qemu_path.display(),
-        qemu_binary = qemu_binary,
-        sysroot = sysroot.display(),
-        docker_image = docker_image,
-    );
+        session_env = RUNNER_SESSION_ENV,
+        session_label = RUNNER_SESSION_LABEL,
+        qemu_path = shell_quote(&qemu_path.display().to_string()),
+        qemu_binary = shell_quote(qemu_binary),
+        sysroot = shell_quote(&sysroot.display().to_string()),
+        docker_image = shell_quote(docker_image),
+        qemu_cpu_args = shell_quote(
+            &qemu_cpu
+                .map(|cpu| format!("-cpu {cpu}"))
+                .unwrap_or_default()
+        ),
+    )
+}
 
-    fs::write(&runner_script, &script_content).await?;
+/// Tool that runs binaries inside the sysroot without privileges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChrootTool {
+    /// `unshare --user --map-root-user --mount` with bind mounts and a chroot
+    Unshare,
+    /// `proot -r`, which needs no kernel support
+    Proot,
+}
 
-    // Make executable
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata(&runner_script).await?.permissions();
-        perms.set_mode(0o755);
-        fs::set_permissions(&runner_script, perms).await?;
+impl ChrootTool {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Unshare => "unshare",
+            Self::Proot => "proot",
+        }
     }
+}
 
-    env.set_runner(runner_script.display().to_string());
+/// Pick `unshare` when user namespaces work, else `proot`. The error explains why neither
+/// can be used; `userns_disabled` is only asked when `unshare` exists but fails.
+fn select_chroot_tool(
+    has_program: impl Fn(&str) -> bool,
+    userns_works: impl Fn() -> bool,
+    userns_disabled: impl Fn() -> String,
+) -> std::result::Result<ChrootTool, String> {
+    let unshare_reason = if !has_program("unshare") {
+        "unshare (util-linux) is not installed".to_string()
+    } else if userns_works() {
+        return Ok(ChrootTool::Unshare);
+    } else {
+        userns_disabled()
+    };
+    if has_program("proot") {
+        Ok(ChrootTool::Proot)
+    } else {
+        Err(format!("{unshare_reason}, and proot is not installed"))
+    }
+}
 
-    color::log_success(&format!(
-        "Configured Docker QEMU runner: {} for {} (image: {})",
-        color::yellow(qemu_binary),
-        color::yellow(arch.as_str()),
-        color::cyan(docker_image)
+/// Probe whether unprivileged user and mount namespaces can be created and chrooted
+/// into: the runner's own setup of `sysroot` is run, then `unshare --root` starts a
+/// program that does not exist. Only a working chroot gets as far as `unshare`'s
+/// "not found" exit status 127; failing mounts or a refused chroot exit with 1 or 32.
+fn chroot_works(sysroot: &Path) -> bool {
+    let Ok(run_root) = tempfile_dir("cargo-cross-chroot-probe") else {
+        return false;
+    };
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+    let status = std::process::Command::new("unshare")
+        .args(["--user", "--map-root-user", "--mount", "/bin/sh", "-c"])
+        .arg(format!(
+            "{CHROOT_SETUP}exec unshare --root=\"$RUN_ROOT\" --wd=/ /.cargo-cross-probe"
+        ))
+        .env("SYSROOT", sysroot)
+        .env("RUN_ROOT", &run_root)
+        .env("MOUNT", CHROOT_MOUNT)
+        .env("WORKDIR", &cwd)
+        .env("BINARY_DIR", &cwd)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status();
+    let _ = std::fs::remove_dir(&run_root);
+    status.is_ok_and(|status| status.code() == Some(127))
+}
+
+/// A new empty directory below the system temp dir
+fn tempfile_dir(prefix: &str) -> std::io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!(
+        "{prefix}-{}-{}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos())
     ));
+    std::fs::create_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Why `unshare --user` failed, from the sysctls (read as `/proc/sys/<name>`) that
+/// commonly disable unprivileged user namespaces
+fn userns_disabled_reason(read_sysctl: impl Fn(&str) -> Option<String>) -> String {
+    let is = |name: &str, value: &str| read_sysctl(name).is_some_and(|v| v.trim() == value);
+    if is("kernel/unprivileged_userns_clone", "0") {
+        "unprivileged user namespaces are disabled (sysctl kernel.unprivileged_userns_clone=0)"
+            .to_string()
+    } else if is("user/max_user_namespaces", "0") {
+        "user namespaces are disabled (sysctl user.max_user_namespaces=0)".to_string()
+    } else if is("kernel/apparmor_restrict_unprivileged_userns", "1") {
+        "AppArmor restricts unprivileged user namespaces \
+         (sysctl kernel.apparmor_restrict_unprivileged_userns=1)"
+            .to_string()
+    } else {
+        "'unshare --user --map-root-user' failed; the container's seccomp profile may block \
+         user namespaces"
+            .to_string()
+    }
+}
+
+/// Dynamic linker of a sysroot, relative to it (`lib/ld-linux-aarch64.so.1`, `lib/ld-musl-x86_64.so.1`)
+fn find_loader(sysroot: &Path) -> Option<PathBuf> {
+    ["lib", "lib64", "lib32", "usr/lib"].iter().find_map(|dir| {
+        let mut names: Vec<String> = std::fs::read_dir(sysroot.join(dir))
+            .ok()?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| {
+                (name.starts_with("ld-")
+                    || name.starts_with("ld64.so")
+                    || name.starts_with("ld.so"))
+                    && name.contains(".so")
+            })
+            .collect();
+        names.sort();
+        names.first().map(|name| Path::new(dir).join(name))
+    })
+}
+
+/// Where the binary's directory is mounted inside the chroot
+const CHROOT_MOUNT: &str = "/.cargo-cross-run";
+
+/// Shell commands, run as root of a new user and mount namespace, that assemble the chroot
+/// on a tmpfs at `$RUN_ROOT`: the sysroot's top-level entries are bind-mounted or copied
+/// (symlinks), then `/dev`, `/proc`, `/sys`, `/tmp`, the working directory and the binary's
+/// directory. Nothing is created inside the shared sysroot. Holds no single quotes, so the
+/// runner script can pass it to `sh -c '...'`.
+const CHROOT_SETUP: &str = r#"set -e
+mount -t tmpfs cargo-cross "$RUN_ROOT"
+for entry in "$SYSROOT"/* "$SYSROOT"/.[!.]*; do
+    [ -e "$entry" ] || [ -L "$entry" ] || continue
+    name=${entry##*/}
+    if [ -L "$entry" ]; then
+        cp -P "$entry" "$RUN_ROOT/$name"
+    elif [ -d "$entry" ]; then
+        mkdir "$RUN_ROOT/$name"
+        mount --rbind "$entry" "$RUN_ROOT/$name"
+    else
+        : > "$RUN_ROOT/$name"
+        mount --bind "$entry" "$RUN_ROOT/$name"
+    fi
+done
+for dir in /dev /proc /sys /tmp; do
+    mkdir -p "$RUN_ROOT$dir"
+    mount --rbind "$dir" "$RUN_ROOT$dir"
+done
+mkdir -p "$RUN_ROOT$WORKDIR" "$RUN_ROOT$MOUNT"
+mount --bind "$WORKDIR" "$RUN_ROOT$WORKDIR"
+mount --bind "$BINARY_DIR" "$RUN_ROOT$MOUNT"
+"#;
+
+/// Render the chroot runner script; every interpolated value is shell-quoted
+fn render_chroot_runner_script(sysroot: &Path, loader: &Path, tool: ChrootTool) -> String {
+    format!(
+        r#"#!/bin/bash
+set -e
+
+# Chroot Runner Script: runs a binary through the sysroot's dynamic linker
+SYSROOT={sysroot}
+LOADER={loader}
+TOOL={tool}
+MOUNT={mount}
+
+# Output settings of cargo-cross: CARGO_CROSS_COLOR=always|never|auto
+COLOR="${{CARGO_CROSS_COLOR:-auto}}"
+if [[ "$COLOR" == "auto" ]]; then
+    if [[ -t 2 ]]; then COLOR=always; else COLOR=never; fi
+fi
+if [[ "$COLOR" == "never" ]]; then export NO_COLOR=1 TERM=dumb; fi
+
+error() {{
+    if [[ "$COLOR" == "always" ]]; then
+        printf '\033[31mError:\033[0m %s\n' "$*" >&2
+    else
+        printf 'Error: %s\n' "$*" >&2
+    fi
+}}
+
+if [[ $# -lt 1 ]]; then
+    error "Usage: $0 <binary> [args...]"
+    exit 1
+fi
+
+if [[ ! -f "$1" ]]; then
+    error "Binary not found: $1"
+    exit 1
+fi
+
+BINARY=$(readlink -f "$1")
+shift
+
+BINARY_DIR=$(dirname "$BINARY")
+BINARY_NAME=$(basename "$BINARY")
+WORKDIR=$(pwd -P)
 
+if [[ "$TOOL" == "proot" ]]; then
+    exec proot -r "$SYSROOT" -b /dev -b /proc -b /sys -b /tmp -b "$WORKDIR" \
+        -b "$BINARY_DIR:$MOUNT" -w "$WORKDIR" "/$LOADER" "$MOUNT/$BINARY_NAME" "$@"
+fi
+
+# A working directory below a sysroot entry could only be created inside the sysroot
+TOP=${{WORKDIR#/}}
+TOP=/${{TOP%%/*}}
+if [[ "$TOP" != "/" && -e "$SYSROOT$TOP" && ! -d "$SYSROOT$WORKDIR" ]]; then
+    error "Cannot run in $WORKDIR: $TOP is part of the sysroot $SYSROOT"
+    exit 1
+fi
+
+# The chroot is assembled on a tmpfs over this per-run directory
+RUN_ROOT=$(mktemp -d "${{TMPDIR:-/tmp}}/cargo-cross-chroot.XXXXXX")
+trap 'rmdir "$RUN_ROOT" 2>/dev/null || true' EXIT
+
+export SYSROOT LOADER MOUNT BINARY_DIR BINARY_NAME WORKDIR RUN_ROOT
+unshare --user --map-root-user --mount /bin/sh -c '{setup}exec unshare --root="$RUN_ROOT" --wd="$WORKDIR" "/$LOADER" "$MOUNT/$BINARY_NAME" "$@"
+' cargo-cross-chroot "$@"
+"#,
+        sysroot = shell_quote(&sysroot.display().to_string()),
+        loader = shell_quote(&loader.display().to_string()),
+        tool = tool.as_str(),
+        mount = CHROOT_MOUNT,
+        setup = CHROOT_SETUP,
+    )
+}
+
+/// Setup the `--runner-backend chroot` runner: binaries the host CPU executes run through
+/// the sysroot's dynamic linker in an unprivileged chroot. Other architectures, and hosts
+/// where no chroot tool works, get the QEMU runner instead.
+pub async fn setup_chroot_runner(
+    env: &mut CrossEnv,
+    target_config: &TargetConfig,
+    bin_prefix: &str,
+    compiler_dir: &Path,
+    args: &Args,
+    host: &HostPlatform,
+) -> Result<()> {
+    let rust_target = target_config.target;
+    if host.os != "linux" || !host.can_run_natively(target_config.arch) {
+        color::log_info(&format!(
+            "--runner-backend chroot only applies to targets this host executes natively; \
+             using QEMU for {}",
+            color::yellow(rust_target)
+        ));
+        return setup_qemu_runner(env, target_config, bin_prefix, compiler_dir, args, host).await;
+    }
+
+    let sysroot = qemu_sysroot(args, env, compiler_dir, bin_prefix);
+    let selected = find_loader(&sysroot)
+        .ok_or_else(|| format!("no dynamic linker found under {}/lib", sysroot.display()))
+        .and_then(|loader| {
+            select_chroot_tool(
+                |program| which::which(program).is_ok(),
+                || chroot_works(&sysroot),
+                || {
+                    userns_disabled_reason(|name| {
+                        std::fs::read_to_string(format!("/proc/sys/{name}")).ok()
+                    })
+                },
+            )
+            .map(|tool| (loader, tool))
+        });
+    let (loader, tool) = match selected {
+        Ok(selected) => selected,
+        Err(reason) => {
+            color::log_warning_once(
+                "chroot-runner-unavailable",
+                &format!(
+                    "--runner-backend chroot is unavailable for {}: {reason}\n\
+                     Falling back to the QEMU runner",
+                    color::yellow(rust_target)
+                ),
+            );
+            return setup_qemu_runner(env, target_config, bin_prefix, compiler_dir, args, host)
+                .await;
+        }
+    };
+
+    let runner_script = write_idempotent(
+        &layout::generated_dir(&args.cross_compiler_dir)
+            .join(format!("chroot-runner-{rust_target}.sh")),
+        &render_chroot_runner_script(&sysroot, &loader, tool),
+        true,
+    )?;
+
+    let runner_path = runner_script.display().to_string();
+    if runner_path.contains(char::is_whitespace) {
+        color::log_warning_once(
+            "chroot-runner-spaces",
+            &format!(
+                "Chroot runner path contains spaces and cargo will split it: {runner_path}\n\
+             Use --cross-compiler-dir with a path without spaces to run binaries"
+            ),
+        );
+    }
+    env.set_runner(runner_path);
+
+    color::log_success(&format!(
+        "Configured chroot runner: {} with {} for {}",
+        color::yellow(&loader.display().to_string()),
+        color::yellow(tool.as_str()),
+        color::yellow(rust_target)
+    ));
     Ok(())
 }
 
 /// Setup Wine runner for Windows targets
-pub fn setup_wine_runner(env: &mut CrossEnv, rust_target: &str) {
-    if which::which("wine").is_ok() {
-        env.set_runner("wine");
-        color::log_success(&format!(
-            "Configured Wine runner for {}",
-            color::yellow(rust_target)
+pub fn setup_wine_runner(env: &mut CrossEnv, rust_target: &str, args: &Args) -> Result<()> {
+    match RunnerDecision::new(which::which("wine").is_ok(), args.runner_required()) {
+        RunnerDecision::Configure => {
+            env.set_runner("wine");
+            color::log_success(&format!(
+                "Configured Wine runner for {}",
+                color::yellow(rust_target)
+            ));
+        }
+        RunnerDecision::Skip => {
+            color::log_warning_once(
+                "wine-missing",
+                &format!(
+                    "Wine not found, skipping runner setup for {}",
+                    color::yellow(rust_target)
+                ),
+            );
+        }
+        RunnerDecision::Fail => {
+            return Err(CrossError::RunnerUnavailable {
+                target: rust_target.to_string(),
+                reasons: vec![WINE_MISSING.to_string()],
+            });
+        }
+    }
+    Ok(())
+}
+
+const WINE_MISSING: &str = "wine is not installed";
+
+/// Why a host without a usable qemu-user-static bundle cannot emulate Linux binaries
+fn qemu_unavailable_reason(host: &HostPlatform) -> String {
+    format!(
+        "no qemu-user-static bundle for {}. {}",
+        host.download_platform(),
+        qemu_unsupported_hint(host)
+    )
+}
+
+/// What the Docker QEMU runner (used on macOS) is missing; `custom_qemu` is set when
+/// `--qemu-path` replaces the bundle
+fn docker_unavailable_reasons(
+    host: &HostPlatform,
+    custom_qemu: bool,
+    has_program: impl Fn(&str) -> bool,
+) -> Vec<String> {
+    let mut reasons = Vec::new();
+    if !has_program("docker") {
+        reasons.push(
+            "docker is not installed (Linux binaries run through Docker QEMU on macOS)".to_string(),
+        );
+    }
+    if !custom_qemu && !Arch::qemu_bundle_available("linux", host.arch) {
+        reasons.push(format!(
+            "no Linux qemu-user-static bundle for {} hosts",
+            host.arch
         ));
     }
+    reasons
+}
+
+/// Everything missing for running `config` binaries on `host`; empty when a runner
+/// can be configured or none is needed
+fn runner_unavailable_reasons(
+    config: &TargetConfig,
+    host: &HostPlatform,
+    custom_qemu: bool,
+    has_program: impl Fn(&str) -> bool,
+    rosetta_installed: impl Fn() -> bool,
+) -> Vec<String> {
+    let arch = config.arch;
+    let native = host.os == config.os.as_str() && host.can_run_natively(arch);
+    match config.os {
+        Os::Linux if arch.qemu_binary_name().is_none() => {
+            if native {
+                Vec::new()
+            } else {
+                vec![format!(
+                    "QEMU has no user-mode emulator for {}",
+                    arch.as_str()
+                )]
+            }
+        }
+        Os::Linux if host.is_darwin() => docker_unavailable_reasons(host, custom_qemu, has_program),
+        Os::Linux if custom_qemu || Arch::qemu_bundle_available(host.os, host.arch) => Vec::new(),
+        Os::Linux => vec![qemu_unavailable_reason(host)],
+        Os::Windows if host.is_windows() => Vec::new(),
+        Os::Windows if config.libc == Some(Libc::Msvc) => {
+            vec!["MSVC targets only run on Windows hosts".to_string()]
+        }
+        Os::Windows if has_program("wine") => Vec::new(),
+        Os::Windows => vec![WINE_MISSING.to_string()],
+        Os::Darwin if host.is_darwin() && arch == Arch::X86_64 && host.arch == "aarch64" => {
+            if rosetta_installed() {
+                Vec::new()
+            } else {
+                vec![format!(
+                    "Rosetta 2 is not installed. Install it with: {ROSETTA_INSTALL_COMMAND}"
+                )]
+            }
+        }
+        _ if native => Vec::new(),
+        os => vec![format!(
+            "no runner exists for {} {} binaries on a {}-{} host",
+            os.as_str(),
+            arch.as_str(),
+            host.os,
+            host.arch
+        )],
+    }
+}
+
+/// Everything missing for running `config` binaries when `args` requires a runner
+/// (see [`Args::runner_required`]); empty when one can be configured, `--runner` is
+/// given or cargo-cross does not set up the toolchain
+#[must_use]
+pub fn missing_runner_reasons(
+    config: &TargetConfig,
+    args: &Args,
+    host: &HostPlatform,
+) -> Vec<String> {
+    let custom_runner = args.runner.as_deref().is_some_and(|r| !r.is_empty());
+    if !args.needs_runner()
+        || !args.runner_required()
+        || custom_runner
+        || args.skips_toolchain_setup()
+    {
+        return Vec::new();
+    }
+    runner_unavailable_reasons(
+        config,
+        host,
+        args.qemu_path.is_some(),
+        |program| which::which(program).is_ok(),
+        rosetta_available,
+    )
+}
+
+/// Fail before any download or build when `args` requires a runner
+/// (see [`Args::runner_required`]) that cannot be configured for `config`
+pub fn check_runner_available(
+    config: &TargetConfig,
+    args: &Args,
+    host: &HostPlatform,
+) -> Result<()> {
+    let reasons = missing_runner_reasons(config, args, host);
+    if reasons.is_empty() {
+        Ok(())
+    } else {
+        Err(CrossError::RunnerUnavailable {
+            target: config.target.to_string(),
+            reasons,
+        })
+    }
+}
+
+/// What to do when a runner's backend may be unavailable on the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunnerDecision {
+    /// Backend is available, configure the runner
+    Configure,
+    /// Backend is missing, warn and continue without a runner
+    Skip,
+    /// Backend is missing and `--require-runner` was given
+    Fail,
+}
+
+impl RunnerDecision {
+    /// Decide based on backend availability and `--require-runner`
+    #[must_use]
+    pub const fn new(available: bool, require_runner: bool) -> Self {
+        if available {
+            Self::Configure
+        } else if require_runner {
+            Self::Fail
+        } else {
+            Self::Skip
+        }
+    }
+}
+
+/// Command that installs Rosetta 2 on Apple Silicon
+const ROSETTA_INSTALL_COMMAND: &str = "softwareupdate --install-rosetta --agree-to-license";
+
+/// Probe whether Rosetta 2 is installed by running a trivial `x86_64` process
+fn rosetta_available() -> bool {
+    std::process::Command::new("arch")
+        .args(["-x86_64", "/usr/bin/true"])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
 }
 
 /// Setup Rosetta runner for `x86_64` Darwin binaries on ARM Darwin hosts
@@ -213,30 +1189,517 @@ pub fn setup_rosetta_runner(
     env: &mut CrossEnv,
     arch: Arch,
     rust_target: &str,
+    args: &Args,
     host: &HostPlatform,
-) {
+) -> Result<()> {
     // Only setup Rosetta on Darwin hosts
     if !host.is_darwin() {
-        return;
+        return Ok(());
     }
 
     // Only for x86_64 Darwin targets
     if arch != Arch::X86_64 {
-        return;
+        return Ok(());
     }
 
     if !rust_target.contains("-apple-darwin") {
-        return;
+        return Ok(());
     }
 
     // Check if host is ARM
     if host.arch != "aarch64" {
-        return;
+        return Ok(());
     }
 
-    env.set_runner("arch -x86_64");
-    color::log_success(&format!(
-        "Configured Rosetta runner for {}",
-        color::yellow(rust_target)
-    ));
+    match RunnerDecision::new(rosetta_available(), args.runner_required()) {
+        RunnerDecision::Configure => {
+            env.set_runner("arch -x86_64");
+            color::log_success(&format!(
+                "Configured Rosetta runner for {}",
+                color::yellow(rust_target)
+            ));
+        }
+        RunnerDecision::Skip => {
+            color::log_warning(&format!(
+                "Rosetta 2 is not installed, skipping runner for {}. Install it with: {}",
+                color::yellow(rust_target),
+                color::cyan(ROSETTA_INSTALL_COMMAND)
+            ));
+        }
+        RunnerDecision::Fail => {
+            return Err(CrossError::RunnerUnavailable {
+                target: rust_target.to_string(),
+                reasons: vec![format!(
+                    "Rosetta 2 is not installed. Install it with: {ROSETTA_INSTALL_COMMAND}"
+                )],
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runner_decision() {
+        assert_eq!(RunnerDecision::new(true, false), RunnerDecision::Configure);
+        assert_eq!(RunnerDecision::new(true, true), RunnerDecision::Configure);
+        assert_eq!(RunnerDecision::new(false, false), RunnerDecision::Skip);
+        assert_eq!(RunnerDecision::new(false, true), RunnerDecision::Fail);
+    }
+
+    #[test]
+    fn test_qemu_source_precedence() {
+        let custom = Path::new("/opt/qemu/bin");
+        for (available, required) in [(true, false), (false, false), (false, true)] {
+            assert_eq!(
+                QemuSource::new(Some(custom), available, required),
+                QemuSource::Path(custom.to_path_buf())
+            );
+        }
+        assert_eq!(QemuSource::new(None, true, true), QemuSource::Bundle);
+        assert_eq!(QemuSource::new(None, false, false), QemuSource::Skip);
+        assert_eq!(QemuSource::new(None, false, true), QemuSource::Fail);
+    }
+
+    #[test]
+    fn test_qemu_binary_matches() {
+        assert!(qemu_binary_matches("qemu-aarch64", None, "qemu-aarch64"));
+        assert!(qemu_binary_matches(
+            "qemu-aarch64-static",
+            None,
+            "qemu-aarch64"
+        ));
+        assert!(!qemu_binary_matches(
+            "qemu-aarch64_be",
+            None,
+            "qemu-aarch64"
+        ));
+        assert!(!qemu_binary_matches("qemu-mips64el", None, "qemu-mips64"));
+        assert!(!qemu_binary_matches("qemu-armeb", None, "qemu-arm"));
+        assert!(!qemu_binary_matches("qemu", None, "qemu-arm"));
+        let version =
+            "qemu-aarch64 version 8.2.2 (Debian 1:8.2.2+ds-0ubuntu1)\nCopyright (c) 2003-2023\n";
+        assert!(qemu_binary_matches("qemu", Some(version), "qemu-aarch64"));
+        assert!(!qemu_binary_matches("qemu", Some(version), "qemu-riscv64"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_qemu_path() {
+        let root =
+            std::env::temp_dir().join(format!("cargo-cross-qemu-path-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let qemu = root.join("qemu-aarch64");
+        let renamed = root.join("tuned-qemu");
+        for file in [&qemu, &renamed] {
+            std::fs::write(file, "#!/bin/sh\n").unwrap();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(file, std::fs::Permissions::from_mode(0o755)).unwrap();
+            }
+        }
+
+        assert_eq!(
+            resolve_qemu_path(&root, "qemu-aarch64", false)
+                .await
+                .unwrap(),
+            qemu
+        );
+        assert_eq!(
+            resolve_qemu_path(&qemu, "qemu-aarch64", false)
+                .await
+                .unwrap(),
+            qemu
+        );
+        let err = resolve_qemu_path(&root, "qemu-riscv64", false)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("directory has no qemu-riscv64"),
+            "{err}"
+        );
+        let err = resolve_qemu_path(&renamed, "qemu-aarch64", false)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("does not look like qemu-aarch64"),
+            "{err}"
+        );
+        assert!(
+            resolve_qemu_path(&root.join("missing"), "qemu-aarch64", false)
+                .await
+                .is_err()
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&qemu, std::fs::Permissions::from_mode(0o644)).unwrap();
+            let err = resolve_qemu_path(&qemu, "qemu-aarch64", false)
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("not executable"), "{err}");
+        }
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    fn host(os: &'static str, arch: &'static str) -> HostPlatform {
+        HostPlatform {
+            os,
+            arch,
+            triple: String::new(),
+        }
+    }
+
+    fn reasons(target: &str, host: &HostPlatform, programs: &[&str], rosetta: bool) -> Vec<String> {
+        let config = crate::config::get_target_config(target).unwrap();
+        runner_unavailable_reasons(config, host, false, |p| programs.contains(&p), || rosetta)
+    }
+
+    #[test]
+    fn test_runner_unavailable_reasons_linux_targets() {
+        let linux = host("linux", "x86_64");
+        assert!(reasons("aarch64-unknown-linux-musl", &linux, &[], false).is_empty());
+
+        let macos = host("darwin", "aarch64");
+        assert!(reasons("aarch64-unknown-linux-musl", &macos, &["docker"], false).is_empty());
+        let missing = reasons("aarch64-unknown-linux-musl", &macos, &[], false);
+        assert_eq!(missing.len(), 1);
+        assert!(missing[0].contains("docker is not installed"));
+
+        let windows = host("windows", "x86_64");
+        let missing = reasons("aarch64-unknown-linux-musl", &windows, &[], false);
+        assert!(missing[0].contains("no qemu-user-static bundle"));
+
+        // --qemu-path stands in for the bundle
+        let config = crate::config::get_target_config("aarch64-unknown-linux-musl").unwrap();
+        let freebsd = host("freebsd", "x86_64");
+        assert!(runner_unavailable_reasons(config, &freebsd, true, |_| false, || false).is_empty());
+        let intel_mac = host("darwin", "x86_64");
+        assert_eq!(
+            runner_unavailable_reasons(config, &intel_mac, true, |p| p == "docker", || false),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            runner_unavailable_reasons(config, &intel_mac, true, |_| false, || false).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_runner_unavailable_reasons_other_targets() {
+        let linux = host("linux", "x86_64");
+        assert!(reasons("x86_64-pc-windows-gnu", &linux, &["wine"], false).is_empty());
+        assert_eq!(
+            reasons("x86_64-pc-windows-gnu", &linux, &[], false),
+            vec![WINE_MISSING]
+        );
+        assert_eq!(
+            reasons("aarch64-linux-android", &linux, &[], false).len(),
+            1
+        );
+
+        let macos = host("darwin", "aarch64");
+        assert!(reasons("x86_64-apple-darwin", &macos, &[], true).is_empty());
+        assert!(reasons("x86_64-apple-darwin", &macos, &[], false)[0].contains("Rosetta"));
+        assert!(reasons("aarch64-apple-darwin", &macos, &[], false).is_empty());
+    }
+
+    #[test]
+    fn test_qemu_unsupported_hint() {
+        let host = |os| HostPlatform {
+            os,
+            arch: "aarch64",
+            triple: String::new(),
+        };
+        assert!(qemu_unsupported_hint(&host("windows")).contains("WSL"));
+        assert!(qemu_unsupported_hint(&host("darwin")).contains("Docker"));
+        assert!(qemu_unsupported_hint(&host("freebsd")).contains("linux-aarch64"));
+    }
+
+    #[test]
+    fn test_docker_runner_script_quotes_paths_with_spaces() {
+        let root = Path::new("/Users/me/Library/Caches/cargo cross");
+        let script = render_docker_runner_script(
+            &root.join("qemu-user-static-v1/qemu-aarch64"),
+            "qemu-aarch64",
+            &root.join("aarch64-linux-musl"),
+            "alpine:latest",
+            Some("neoverse-n1"),
+        );
+
+        let assignment = |name: &str| {
+            let line = script
+                .lines()
+                .find(|line| line.starts_with(&format!("{name}=")))
+                .unwrap();
+            shlex::split(&line[name.len() + 1..]).unwrap()
+        };
+        assert_eq!(
+            assignment("QEMU_PATH"),
+            vec!["/Users/me/Library/Caches/cargo cross/qemu-user-static-v1/qemu-aarch64"]
+        );
+        assert_eq!(
+            assignment("SYSROOT"),
+            vec!["/Users/me/Library/Caches/cargo cross/aarch64-linux-musl"]
+        );
+        assert_eq!(assignment("QEMU_CPU_ARGS"), vec!["-cpu neoverse-n1"]);
+        assert_eq!(assignment("DOCKER_IMAGE"), vec!["alpine:latest"]);
+    }
+
+    #[test]
+    fn test_docker_runner_session() {
+        let session = runner_session("aarch64-unknown-linux-gnu");
+        assert!(session.ends_with("-aarch64_unknown_linux_gnu"), "{session}");
+        assert!(session.starts_with(&format!("{}-", std::process::id())));
+        assert_eq!(runner_session("aarch64-unknown-linux-gnu"), session);
+        assert_ne!(runner_session("aarch64-unknown-linux-musl"), session);
+        // An interrupt removes the containers of the sessions handed out
+        assert!(lock_sessions().contains(&session));
+
+        let script = render_docker_runner_script(
+            Path::new("/cache/qemu-aarch64"),
+            "qemu-aarch64",
+            Path::new("/cache/sysroot"),
+            "alpine:latest",
+            None,
+        );
+        assert!(script.contains("SESSION=\"${CARGO_CROSS_RUNNER_SESSION:-}\"\n"));
+        assert!(script.contains("--label cargo-cross.session=\"$SESSION\""));
+        // Shared containers outlive the script; per-binary ones are removed with it
+        assert_eq!(script.matches("--rm").count(), 1);
+        assert!(script.contains("touch /.cargo-cross-ready"));
+        assert!(script.contains("was not ready after 60s"));
+        assert!(script.contains("\"$RUN_DIR/$BINARY_NAME\" \"$@\"\n"));
+    }
+
+    #[test]
+    fn test_docker_runner_script_output_settings() {
+        let script = render_docker_runner_script(
+            Path::new("/cache/qemu-aarch64"),
+            "qemu-aarch64",
+            Path::new("/cache/sysroot"),
+            "alpine:latest",
+            None,
+        );
+        assert!(script.contains("COLOR=\"${CARGO_CROSS_COLOR:-auto}\"\n"));
+        assert!(script.contains("QUIET=\"${CARGO_CROSS_QUIET:-0}\"\n"));
+        assert!(script.contains("if [[ \"$QUIET\" == \"1\" ]]; then CREATE_ARGS=(--quiet); fi\n"));
+        assert!(script.contains(
+            "if [[ \"$COLOR\" == \"never\" ]]; then EXEC_ENV=(-e NO_COLOR=1 -e TERM=dumb); fi\n"
+        ));
+        assert!(script.contains("docker exec \"${EXEC_ENV[@]}\" \"$CONTAINER_ID\""));
+
+        // Errors are printed before docker is needed; color follows CARGO_CROSS_COLOR
+        #[cfg(unix)]
+        {
+            let run = |color: &str| {
+                std::process::Command::new("bash")
+                    .args(["-c", &script, "runner", "/nonexistent/binary"])
+                    .env("CARGO_CROSS_COLOR", color)
+                    .output()
+                    .ok()
+                    .map(|output| String::from_utf8_lossy(&output.stderr).into_owned())
+            };
+            if let (Some(plain), Some(colored)) = (run("never"), run("always")) {
+                assert_eq!(plain, "Error: Binary not found: /nonexistent/binary\n");
+                assert!(colored.starts_with("\x1b[31mError:\x1b[0m"), "{colored:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_select_chroot_tool() {
+        let select = |programs: &[&str], userns: bool| {
+            select_chroot_tool(
+                |program| programs.contains(&program),
+                || userns,
+                || "user namespaces are disabled".to_string(),
+            )
+        };
+        assert_eq!(select(&["unshare", "proot"], true), Ok(ChrootTool::Unshare));
+        assert_eq!(select(&["unshare", "proot"], false), Ok(ChrootTool::Proot));
+        assert_eq!(select(&["proot"], true), Ok(ChrootTool::Proot));
+        assert_eq!(
+            select(&["unshare"], false),
+            Err("user namespaces are disabled, and proot is not installed".to_string())
+        );
+        assert_eq!(
+            select(&[], true),
+            Err("unshare (util-linux) is not installed, and proot is not installed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_userns_disabled_reason() {
+        let reason = |sysctls: &[(&str, &str)]| {
+            userns_disabled_reason(|name| {
+                sysctls
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| format!("{value}\n"))
+            })
+        };
+        assert!(reason(&[("kernel/unprivileged_userns_clone", "0")])
+            .contains("kernel.unprivileged_userns_clone=0"));
+        assert!(reason(&[
+            ("kernel/unprivileged_userns_clone", "1"),
+            ("user/max_user_namespaces", "0")
+        ])
+        .contains("user.max_user_namespaces=0"));
+        assert!(
+            reason(&[("kernel/apparmor_restrict_unprivileged_userns", "1")]).contains("AppArmor")
+        );
+        assert!(reason(&[]).contains("seccomp"));
+    }
+
+    #[test]
+    fn test_find_loader() {
+        let root =
+            std::env::temp_dir().join(format!("cargo-cross-chroot-loader-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        assert_eq!(find_loader(&root), None);
+
+        std::fs::create_dir_all(root.join("lib")).unwrap();
+        std::fs::create_dir_all(root.join("lib64")).unwrap();
+        for file in [
+            "lib/libc.so.6",
+            "lib/ldscripts",
+            "lib64/ld-linux-x86-64.so.2",
+        ] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+        assert_eq!(
+            find_loader(&root),
+            Some(PathBuf::from("lib64/ld-linux-x86-64.so.2"))
+        );
+        std::fs::write(root.join("lib/ld-linux-x86-64.so.2"), "").unwrap();
+        assert_eq!(
+            find_loader(&root),
+            Some(PathBuf::from("lib/ld-linux-x86-64.so.2"))
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_has_musl_loader() {
+        let root =
+            std::env::temp_dir().join(format!("cargo-cross-musl-loader-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("lib")).unwrap();
+        std::fs::write(root.join("lib/libc.so"), "").unwrap();
+        assert!(!has_musl_loader(&root));
+        std::fs::write(root.join("lib/ld-linux-aarch64.so.1"), "").unwrap();
+        assert!(!has_musl_loader(&root));
+        std::fs::remove_file(root.join("lib/ld-linux-aarch64.so.1")).unwrap();
+        std::fs::write(root.join("lib/ld-musl-aarch64.so.1"), "").unwrap();
+        assert!(has_musl_loader(&root));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_chroot_runner_script() {
+        let sysroot = Path::new("/cache/cargo cross/x86_64-linux-gnu");
+        let loader = Path::new("lib/ld-linux-x86-64.so.2");
+        let script = render_chroot_runner_script(sysroot, loader, ChrootTool::Unshare);
+
+        let assignment = |name: &str| {
+            let line = script
+                .lines()
+                .find(|line| line.starts_with(&format!("{name}=")))
+                .unwrap();
+            shlex::split(&line[name.len() + 1..]).unwrap()
+        };
+        assert_eq!(
+            assignment("SYSROOT"),
+            vec!["/cache/cargo cross/x86_64-linux-gnu"]
+        );
+        assert_eq!(assignment("LOADER"), vec!["lib/ld-linux-x86-64.so.2"]);
+        assert_eq!(assignment("TOOL"), vec!["unshare"]);
+        assert!(script.contains("unshare --user --map-root-user --mount /bin/sh -c 'set -e\n"));
+        assert!(script.contains("mount --bind \"$BINARY_DIR\" \"$RUN_ROOT$MOUNT\"\n"));
+        assert!(script.contains(
+            "exec unshare --root=\"$RUN_ROOT\" --wd=\"$WORKDIR\" \"/$LOADER\" \"$MOUNT/$BINARY_NAME\" \"$@\"\n"
+        ));
+        // The shared sysroot is only read, and the setup fits in single quotes
+        assert!(!script.contains("mkdir -p \"$SYSROOT"));
+        assert!(!CHROOT_SETUP.contains('\''));
+        assert!(script.contains("COLOR=\"${CARGO_CROSS_COLOR:-auto}\"\n"));
+        assert!(script.contains("\"$WORKDIR\" \\\n        -b \"$BINARY_DIR:$MOUNT\""));
+
+        let proot = render_chroot_runner_script(sysroot, loader, ChrootTool::Proot);
+        assert_eq!(
+            proot.replace("TOOL=proot", "TOOL=unshare"),
+            script,
+            "only the tool differs"
+        );
+        assert!(proot.contains("exec proot -r \"$SYSROOT\""));
+
+        #[cfg(unix)]
+        {
+            let output = std::process::Command::new("bash")
+                .args(["-c", &script, "runner", "/nonexistent/binary"])
+                .output();
+            if let Ok(output) = output {
+                assert!(!output.status.success());
+                assert_eq!(
+                    String::from_utf8_lossy(&output.stderr),
+                    "Error: Binary not found: /nonexistent/binary\n"
+                );
+            }
+
+            // Runs for real where the host allows user namespaces, with the host as sysroot
+            let root = Path::new("/");
+            if let Some(loader) = find_loader(root).filter(|_| chroot_works(root)) {
+                let script = render_chroot_runner_script(root, &loader, ChrootTool::Unshare);
+                let output = std::process::Command::new("bash")
+                    .args(["-c", &script, "runner", "/bin/echo", "hello"])
+                    .output()
+                    .unwrap();
+                assert!(output.status.success(), "{output:?}");
+                assert_eq!(String::from_utf8_lossy(&output.stdout), "hello\n");
+            }
+        }
+    }
+
+    #[test]
+    fn test_qemu_sysroot_precedence() {
+        use crate::cli::{test_target_args, BuildArgs, Command};
+
+        let args = |qemu_sysroot: Option<&str>| Args {
+            command: Command::test(),
+            ..test_target_args(
+                "qemu-sysroot",
+                &["aarch64-unknown-linux-gnu"],
+                BuildArgs {
+                    qemu_sysroot: qemu_sysroot.map(PathBuf::from),
+                    ..BuildArgs::default()
+                },
+            )
+        };
+        let compiler_dir = Path::new("/cross/aarch64-linux-gnu-cross");
+        let bin_prefix = "aarch64-linux-gnu";
+        let mut env = CrossEnv::new();
+
+        // Without a configured sysroot: the toolchain's target directory
+        assert_eq!(
+            qemu_sysroot(&args(None), &env, compiler_dir, bin_prefix),
+            compiler_dir.join(bin_prefix)
+        );
+
+        env.set_sysroot("/opt/sysroot");
+        assert_eq!(
+            qemu_sysroot(&args(None), &env, compiler_dir, bin_prefix),
+            PathBuf::from("/opt/sysroot")
+        );
+
+        assert_eq!(
+            qemu_sysroot(&args(Some("/srv/rootfs")), &env, compiler_dir, bin_prefix),
+            PathBuf::from("/srv/rootfs")
+        );
+    }
 }
@@ -0,0 +1,409 @@
+//! Checking that sccache can use the remote cache selected with `--sccache-*` options
+//!
+//! Remote backends and distributed compilation are cargo features of sccache, so a binary
+//! built without them silently falls back to the local disk cache. The check asks the
+//! binary itself: `--version` confirms it runs, `--show-stats` reports the cache location
+//! it actually uses and `--dist-status` whether distributed compilation is compiled in.
+//!
+//! The same server also reports how well the cache worked: the JSON statistics taken before
+//! and after each target are diffed into per-target hits, misses and errors.
+
+use crate::cargo::add_sccache_env;
+use crate::cli::Args;
+use crate::color;
+use crate::env::EnvMap;
+use crate::error::run_command_output;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tokio::process::Command as TokioCommand;
+
+/// Remote cache backends that can be selected on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SccacheBackend {
+    S3,
+    Gcs,
+    Redis,
+}
+
+impl SccacheBackend {
+    /// Name as it starts sccache's `Cache location` line
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::S3 => "s3",
+            Self::Gcs => "gcs",
+            Self::Redis => "redis",
+        }
+    }
+}
+
+/// Remote cache backends configured by `args`
+#[must_use]
+pub fn requested_backends(args: &Args) -> Vec<SccacheBackend> {
+    [
+        (args.sccache_bucket.is_some(), SccacheBackend::S3),
+        (args.sccache_gcs_bucket.is_some(), SccacheBackend::Gcs),
+        (args.sccache_redis.is_some(), SccacheBackend::Redis),
+    ]
+    .into_iter()
+    .filter_map(|(set, backend)| set.then_some(backend))
+    .collect()
+}
+
+/// Whether the rustc wrapper of the build is sccache
+#[must_use]
+pub fn uses_sccache(args: &Args) -> bool {
+    args.enable_sccache
+        || args
+            .rustc_wrapper
+            .as_deref()
+            .and_then(Path::file_stem)
+            .is_some_and(|stem| stem == "sccache")
+}
+
+/// Version from `sccache --version` output ("sccache 0.8.2")
+#[must_use]
+pub fn parse_sccache_version(output: &str) -> Option<&str> {
+    output
+        .trim()
+        .strip_prefix("sccache")?
+        .split_whitespace()
+        .next()
+}
+
+/// The `Cache location` reported by `sccache --show-stats`
+#[must_use]
+pub fn cache_location(stats: &str) -> Option<&str> {
+    stats
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Cache location"))
+        .map(str::trim)
+}
+
+/// Whether a `Cache location` value is one of `backends`.
+/// sccache prints e.g. "s3, name: ci-cache, prefix: /" or "Local disk: \"...\"".
+#[must_use]
+pub fn location_matches(location: &str, backends: &[SccacheBackend]) -> bool {
+    let location = location.to_ascii_lowercase();
+    backends
+        .iter()
+        .any(|backend| location.starts_with(backend.as_str()))
+}
+
+/// Whether `sccache --dist-status` output says distributed compilation is unavailable
+#[must_use]
+pub fn dist_disabled(status: &str) -> bool {
+    status.contains("Disabled")
+}
+
+/// Run `sccache <arg>` with the sccache environment of the build, returning its output
+async fn sccache_output(program: &Path, arg: &str, env: &EnvMap) -> Option<String> {
+    let mut cmd = TokioCommand::new(program);
+    cmd.arg(arg).envs(env.vars());
+    let output = run_command_output(&mut cmd, &program.display().to_string())
+        .await
+        .ok()?;
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Some(text)
+}
+
+/// Cache results counted by the sccache server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheCounters {
+    pub hits: u64,
+    pub misses: u64,
+    pub errors: u64,
+}
+
+impl CacheCounters {
+    /// Counters from `sccache --show-stats --stats-format json` output. Hits, misses and
+    /// cache errors are counted per language and summed; read and write errors add to errors.
+    #[must_use]
+    pub fn from_stats_json(json: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(json.trim()).ok()?;
+        // Wrapped in "stats" by every release that has --stats-format, bare in hand-made files
+        let stats = value.get("stats").unwrap_or(&value);
+        if !stats.is_object() {
+            return None;
+        }
+        let number = |key: &str| stats.get(key).and_then(Value::as_u64).unwrap_or(0);
+        let per_language = |key: &str| {
+            stats
+                .get(key)
+                .and_then(|counts| counts.get("counts"))
+                .and_then(Value::as_object)
+                .map_or(0, |counts| counts.values().filter_map(Value::as_u64).sum())
+        };
+        Some(Self {
+            hits: per_language("cache_hits"),
+            misses: per_language("cache_misses"),
+            errors: per_language("cache_errors")
+                + number("cache_read_errors")
+                + number("cache_write_errors"),
+        })
+    }
+}
+
+/// Cache results of one target: the difference of the statistics around its build
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheDelta {
+    pub counters: CacheCounters,
+    /// A counter went down, so the server restarted (or was zeroed) during the target and
+    /// the negative differences were counted as 0
+    pub reset: bool,
+}
+
+impl CacheDelta {
+    /// Share of cacheable compilations served from the cache, `None` without any
+    #[must_use]
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.counters.hits + self.counters.misses;
+        #[allow(clippy::cast_precision_loss)]
+        (total > 0).then(|| self.counters.hits as f64 / total as f64)
+    }
+
+    /// "12 hits, 3 misses, 0 errors (80% hit rate)"
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let counters = self.counters;
+        let mut text = format!(
+            "{} hits, {} misses, {} errors",
+            counters.hits, counters.misses, counters.errors
+        );
+        if let Some(rate) = self.hit_rate() {
+            text.push_str(&format!(" ({:.0}% hit rate)", rate * 100.0));
+        }
+        text
+    }
+}
+
+/// Diff two `--stats-format json` snapshots taken before and after a target
+#[must_use]
+pub fn diff_stats(before: &str, after: &str) -> Option<CacheDelta> {
+    let before = CacheCounters::from_stats_json(before)?;
+    let after = CacheCounters::from_stats_json(after)?;
+    let diff = |before: u64, after: u64| after.checked_sub(before);
+    let counts = [
+        diff(before.hits, after.hits),
+        diff(before.misses, after.misses),
+        diff(before.errors, after.errors),
+    ];
+    Some(CacheDelta {
+        counters: CacheCounters {
+            hits: counts[0].unwrap_or(0),
+            misses: counts[1].unwrap_or(0),
+            errors: counts[2].unwrap_or(0),
+        },
+        reset: counts.iter().any(Option::is_none),
+    })
+}
+
+/// The sccache binary used as the rustc wrapper
+fn sccache_program(args: &Args) -> PathBuf {
+    args.rustc_wrapper
+        .clone()
+        .unwrap_or_else(|| "sccache".into())
+}
+
+/// Statistics of the sccache server the build uses, as JSON. `None` when sccache is not the
+/// rustc wrapper or the server could not be asked.
+pub async fn stats_snapshot(args: &Args) -> Option<String> {
+    if !uses_sccache(args) {
+        return None;
+    }
+    let program = sccache_program(args);
+    let mut env = EnvMap::new();
+    add_sccache_env(&mut env, args);
+    let mut cmd = TokioCommand::new(&program);
+    cmd.args(["--show-stats", "--stats-format", "json"])
+        .envs(env.vars());
+    let output = run_command_output(&mut cmd, &program.display().to_string())
+        .await
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Warn when the sccache binary cannot use the remote cache or dist scheduler requested
+/// with `--sccache-*` options. Does nothing unless one of them is set.
+pub async fn check_sccache_backends(args: &Args) {
+    let backends = requested_backends(args);
+    if !uses_sccache(args) || (backends.is_empty() && args.sccache_dist_scheduler.is_none()) {
+        return;
+    }
+    let program = sccache_program(args);
+    let mut env = EnvMap::new();
+    add_sccache_env(&mut env, args);
+
+    let Some(version) = sccache_output(&program, "--version", &env).await else {
+        color::log_warning(&format!(
+            "Could not run {} to check the configured sccache backend",
+            program.display()
+        ));
+        return;
+    };
+    if let Some(version) = parse_sccache_version(&version) {
+        color::log_debug(&format!("sccache {version}"));
+    }
+
+    if !backends.is_empty() {
+        let stats = sccache_output(&program, "--show-stats", &env).await;
+        if let Some(location) = stats.as_deref().and_then(cache_location) {
+            if !location_matches(location, &backends) {
+                let names: Vec<_> = backends.iter().map(|b| b.as_str()).collect();
+                color::log_warning(&format!(
+                    "sccache reports cache location \"{location}\" instead of {}: the binary may be \
+                     built without that backend, or a server started with other settings is still \
+                     running (stop it with `sccache --stop-server`)",
+                    names.join("/")
+                ));
+            }
+        }
+    }
+
+    if args.sccache_dist_scheduler.is_some() {
+        let status = sccache_output(&program, "--dist-status", &env).await;
+        if status.as_deref().is_some_and(dist_disabled) {
+            color::log_warning(
+                "--sccache-dist-scheduler is set, but sccache reports distributed compilation \
+                 as disabled (it needs an sccache built with the dist-client feature)",
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{test_target_args, BuildArgs};
+
+    fn sccache_args(build: BuildArgs) -> Args {
+        test_target_args("sccache", &["aarch64-unknown-linux-musl"], build)
+    }
+
+    #[test]
+    fn test_requested_backends_and_wrapper() {
+        let args = sccache_args(BuildArgs {
+            sccache_bucket: Some("ci-cache".to_string()),
+            sccache_redis: Some("redis://cache:6379".to_string()),
+            rustc_wrapper: Some(PathBuf::from("/usr/local/bin/sccache")),
+            ..BuildArgs::default()
+        });
+        assert_eq!(
+            requested_backends(&args),
+            [SccacheBackend::S3, SccacheBackend::Redis]
+        );
+        assert!(uses_sccache(&args));
+
+        let args = sccache_args(BuildArgs {
+            rustc_wrapper: Some(PathBuf::from("cachepot")),
+            ..BuildArgs::default()
+        });
+        assert!(requested_backends(&args).is_empty());
+        assert!(!uses_sccache(&args));
+    }
+
+    #[test]
+    fn test_parse_sccache_output() {
+        assert_eq!(parse_sccache_version("sccache 0.8.2\n"), Some("0.8.2"));
+        assert_eq!(parse_sccache_version("cachepot 0.1"), None);
+
+        let local = "Compile requests                      0\n\
+                     Cache location                  Local disk: \"/home/ci/.cache/sccache\"\n\
+                     Max cache size                       10 GiB\n";
+        let s3 = "Cache location                  s3, name: ci-cache, prefix: /\n";
+        let location = cache_location(local).unwrap();
+        assert!(location.starts_with("Local disk"));
+        assert!(!location_matches(location, &[SccacheBackend::S3]));
+        assert!(location_matches(
+            cache_location(s3).unwrap(),
+            &[SccacheBackend::S3]
+        ));
+        // Older releases capitalize the backend
+        assert!(location_matches(
+            "Redis: redis://cache",
+            &[SccacheBackend::Redis]
+        ));
+        assert_eq!(cache_location("no stats"), None);
+
+        assert!(dist_disabled(
+            r#"{"Disabled":"dist-client feature not selected"}"#
+        ));
+        assert!(!dist_disabled(
+            r#"{"SchedulerStatus":["http://sched:10600/",{}]}"#
+        ));
+    }
+
+    /// `sccache --show-stats --stats-format json` of sccache 0.8, trimmed
+    fn stats_json(hits: u64, c_hits: u64, misses: u64, errors: u64) -> String {
+        format!(
+            r#"{{"stats":{{"compile_requests":40,"requests_executed":38,
+            "cache_errors":{{"counts":{{"Rust":{errors}}},"adv_counts":{{}}}},
+            "cache_hits":{{"counts":{{"Rust":{hits},"C/C++":{c_hits}}},"adv_counts":{{}}}},
+            "cache_misses":{{"counts":{{"Rust":{misses}}},"adv_counts":{{}}}},
+            "cache_timeouts":0,"cache_read_errors":1,"cache_write_errors":0,
+            "non_cacheable_compilations":2,"compile_fails":0}},
+            "cache_location":"Local disk: \"/home/ci/.cache/sccache\"","cache_size":1024}}"#
+        )
+    }
+
+    #[test]
+    fn test_cache_counters_from_stats_json() {
+        assert_eq!(
+            CacheCounters::from_stats_json(&stats_json(10, 2, 5, 1)),
+            Some(CacheCounters {
+                hits: 12,
+                misses: 5,
+                errors: 2,
+            })
+        );
+        // A server that has not compiled anything yet
+        let empty = r#"{"stats":{"cache_hits":{"counts":{}},"cache_misses":{"counts":{}}}}"#;
+        assert_eq!(
+            CacheCounters::from_stats_json(empty),
+            Some(CacheCounters::default())
+        );
+        assert_eq!(CacheCounters::from_stats_json("Compile requests 0"), None);
+        assert_eq!(CacheCounters::from_stats_json("[]"), None);
+    }
+
+    #[test]
+    fn test_diff_stats() {
+        let delta = diff_stats(&stats_json(10, 2, 5, 0), &stats_json(25, 2, 8, 1)).unwrap();
+        assert_eq!(
+            delta,
+            CacheDelta {
+                counters: CacheCounters {
+                    hits: 15,
+                    misses: 3,
+                    errors: 1,
+                },
+                reset: false,
+            }
+        );
+        assert_eq!(
+            delta.describe(),
+            "15 hits, 3 misses, 1 errors (83% hit rate)"
+        );
+
+        // The server restarted between the snapshots: negative differences become 0
+        let delta = diff_stats(&stats_json(10, 2, 5, 0), &stats_json(4, 0, 6, 0)).unwrap();
+        assert!(delta.reset);
+        assert_eq!(
+            delta.counters,
+            CacheCounters {
+                hits: 0,
+                misses: 1,
+                errors: 0,
+            }
+        );
+
+        let unchanged = diff_stats(&stats_json(1, 0, 1, 0), &stats_json(1, 0, 1, 0)).unwrap();
+        assert_eq!(unchanged.hit_rate(), None);
+        assert_eq!(unchanged.describe(), "0 hits, 0 misses, 0 errors");
+        assert_eq!(diff_stats("", &stats_json(1, 0, 1, 0)), None);
+    }
+}
@@ -0,0 +1,335 @@
+//! Unprefixed tool shims (`--shim-unprefixed-tools`)
+//!
+//! Some build systems run by build scripts, notably Makefiles of `-sys` crates, call plain
+//! `gcc` or `strip` and ignore `CC`, so a cross build silently uses the host tools. With
+//! the option, cargo runs with a directory first in PATH whose `gcc`, `g++` and `strip`
+//! are the target's tools: symlinks on Unix, `.cmd` files calling them on Windows (copies
+//! would not find the files installed next to the real tools).
+//!
+//! `cc`, `c++`, `ld` and `ar` are left alone: rustc links build scripts and proc macros
+//! with the host `cc`, which finds `ld` through PATH, and cc-rs archives host code with
+//! the `ar` it finds there.
+
+use crate::cache::fnv1a;
+use crate::config::HostPlatform;
+use crate::env::{CrossEnv, EnvMap, EnvSource};
+use crate::error::{CrossError, Result};
+use crate::{color, layout};
+use std::path::{Path, PathBuf};
+
+/// Shimmed names and the tool each one runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Cc,
+    Cxx,
+    /// `<prefix>-<name>` of the toolchain
+    Prefixed,
+}
+
+const SHIMS: &[(&str, Source)] = &[
+    ("gcc", Source::Cc),
+    ("g++", Source::Cxx),
+    ("strip", Source::Prefixed),
+];
+
+/// The shims for a toolchain: each unprefixed name with the path of the target tool it
+/// runs. `resolve` finds a tool name or path; names it cannot find, tools given with
+/// arguments (`ccache gcc`) and tools that already have the unprefixed name are skipped.
+#[must_use]
+pub fn shim_plan(
+    cross_env: &CrossEnv,
+    prefix: Option<&str>,
+    resolve: impl Fn(&str) -> Option<PathBuf>,
+) -> Vec<(&'static str, PathBuf)> {
+    SHIMS
+        .iter()
+        .filter_map(|(name, source)| {
+            let tool = match source {
+                Source::Cc => cross_env.cc.clone(),
+                Source::Cxx => cross_env.cxx.clone(),
+                Source::Prefixed => prefix.map(|prefix| format!("{prefix}-{name}")),
+            }?;
+            if tool.trim().contains(char::is_whitespace) {
+                return None;
+            }
+            let path = resolve(tool.trim())?;
+            let stem = path.file_stem()?.to_string_lossy().into_owned();
+            (stem != *name).then_some((*name, path))
+        })
+        .collect()
+}
+
+/// Find `tool` like the cargo child would: a path as is, a name in the toolchain's PATH
+/// entries and then on PATH
+fn resolve_tool(tool: &str, cross_env: &CrossEnv, host: &HostPlatform) -> Option<PathBuf> {
+    if tool.contains(['/', '\\']) {
+        return Some(PathBuf::from(tool)).filter(|path| path.is_file());
+    }
+    let names = [tool.to_string(), format!("{tool}{}", host.exe_ext())];
+    cross_env
+        .path
+        .iter()
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+        .or_else(|| which::which(tool).ok())
+}
+
+/// Directory for the shims of `target`, named after what they point at so runs with other
+/// toolchains never share one
+#[must_use]
+pub fn shim_dir(root: &Path, target: &str, shims: &[(&str, PathBuf)]) -> PathBuf {
+    let key: String = shims
+        .iter()
+        .map(|(name, path)| format!("{name}={}\n", path.display()))
+        .collect();
+    layout::generated_dir(root)
+        .join("shims")
+        .join(format!("{target}-{:08x}", fnv1a(key.as_bytes()) as u32))
+}
+
+/// Content of the `.cmd` shim that runs `tool` with the shim's arguments
+#[must_use]
+pub fn cmd_shim(tool: &Path) -> String {
+    format!("@\"{}\" %*\r\n", tool.display())
+}
+
+/// Create the shims in `dir` unless it exists already. They are written to a temporary
+/// directory first, so concurrent runs never see a partial set.
+pub fn write_shims(dir: &Path, shims: &[(&str, PathBuf)], windows: bool) -> Result<()> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+    let io_error = |path: &Path| {
+        let message = format!("Failed to create the tool shim {}", path.display());
+        move |source| CrossError::IoError { message, source }
+    };
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    let temp = dir.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+    let _ = std::fs::remove_dir_all(&temp);
+    std::fs::create_dir_all(&temp).map_err(io_error(&temp))?;
+    for (shim, tool) in shims {
+        let written = if windows {
+            let path = temp.join(format!("{shim}.cmd"));
+            std::fs::write(&path, cmd_shim(tool)).map_err(io_error(&path))
+        } else {
+            link(tool, &temp.join(shim))
+        };
+        if let Err(e) = written {
+            let _ = std::fs::remove_dir_all(&temp);
+            return Err(e);
+        }
+    }
+    if let Err(source) = std::fs::rename(&temp, dir) {
+        let _ = std::fs::remove_dir_all(&temp);
+        // Another run created the same shims in the meantime
+        if !dir.is_dir() {
+            return Err(io_error(dir)(source));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn link(tool: &Path, shim: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(tool, shim).map_err(|source| CrossError::IoError {
+        message: format!("Failed to create the tool shim {}", shim.display()),
+        source,
+    })
+}
+
+#[cfg(not(unix))]
+fn link(tool: &Path, shim: &Path) -> Result<()> {
+    let path = shim.with_extension("cmd");
+    std::fs::write(&path, cmd_shim(tool)).map_err(|source| CrossError::IoError {
+        message: format!("Failed to create the tool shim {}", path.display()),
+        source,
+    })
+}
+
+/// Put `dir` first in the PATH of `env`, before the toolchain directories and the
+/// inherited PATH
+pub fn prepend_path(env: &mut EnvMap, dir: &Path, host: &HostPlatform) {
+    let current = env
+        .get("PATH")
+        .cloned()
+        .unwrap_or_else(|| std::env::var("PATH").unwrap_or_default());
+    let sep = host.path_separator();
+    let path = if current.is_empty() {
+        dir.display().to_string()
+    } else {
+        format!("{}{sep}{current}", dir.display())
+    };
+    env.insert("PATH", path, EnvSource::CrossEnv);
+}
+
+/// Create the shims of `target` and put them first in the PATH of the cargo environment
+pub fn apply_shims(
+    env: &mut EnvMap,
+    target: &str,
+    root: &Path,
+    cross_env: &CrossEnv,
+    host: &HostPlatform,
+) -> Result<()> {
+    let prefix = crate::debuginfo::tool_prefix(cross_env, host);
+    let shims = shim_plan(cross_env, prefix.as_deref(), |tool| {
+        resolve_tool(tool, cross_env, host)
+    });
+    if shims.is_empty() {
+        color::log_warning(&format!(
+            "--shim-unprefixed-tools: no target tools to shim for {}",
+            color::yellow(target)
+        ));
+        return Ok(());
+    }
+    let dir = shim_dir(root, target, &shims);
+    write_shims(&dir, &shims, host.is_windows())?;
+    prepend_path(env, &dir, host);
+    let names: Vec<String> = shims
+        .iter()
+        .map(|(name, tool)| {
+            let tool = tool.file_name().unwrap_or_default().to_string_lossy();
+            format!("{name} -> {tool}")
+        })
+        .collect();
+    color::log_warning(&format!(
+        "--shim-unprefixed-tools: for this cargo invocation, {} in PATH run the {} tools \
+         ({}); anything that calls them for the host gets the target tools too",
+        shims
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(", "),
+        color::yellow(target),
+        names.join(", ")
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toolchain_env() -> CrossEnv {
+        let mut env = CrossEnv::new();
+        env.set_cc("aarch64-linux-musl-gcc");
+        env.set_cxx("ccache aarch64-linux-musl-g++");
+        env.set_ar("/opt/cross/bin/aarch64-linux-musl-ar");
+        env
+    }
+
+    fn fake_resolve(tool: &str) -> Option<PathBuf> {
+        let name = Path::new(tool).file_name()?.to_str()?;
+        Some(PathBuf::from("/opt/cross/bin").join(name))
+    }
+
+    #[test]
+    fn test_shim_plan() {
+        let plan = shim_plan(&toolchain_env(), Some("aarch64-linux-musl"), fake_resolve);
+        assert_eq!(
+            plan,
+            vec![
+                (
+                    "gcc",
+                    PathBuf::from("/opt/cross/bin/aarch64-linux-musl-gcc")
+                ),
+                (
+                    "strip",
+                    PathBuf::from("/opt/cross/bin/aarch64-linux-musl-strip")
+                ),
+            ]
+        );
+        // Tools that cannot be found are skipped
+        assert!(shim_plan(&toolchain_env(), Some("aarch64-linux-musl"), |_| None).is_empty());
+
+        // Without a prefix only the configured compilers are shimmed, and a compiler that
+        // is already called gcc needs no shim. ar and ld never are: host links need them.
+        let mut env = CrossEnv::new();
+        env.set_cc("gcc");
+        env.set_cxx("clang++");
+        env.set_ar("llvm-ar");
+        assert_eq!(
+            shim_plan(&env, None, fake_resolve),
+            vec![("g++", PathBuf::from("/opt/cross/bin/clang++"))]
+        );
+    }
+
+    #[test]
+    fn test_shim_dir_and_cmd() {
+        let root = Path::new("/cache");
+        let shims = vec![("gcc", PathBuf::from("/opt/a-gcc"))];
+        let dir = shim_dir(root, "aarch64-unknown-linux-musl", &shims);
+        assert!(dir.starts_with("/cache/generated/shims"));
+        assert!(dir
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("aarch64-unknown-linux-musl-"));
+        assert_eq!(dir, shim_dir(root, "aarch64-unknown-linux-musl", &shims));
+        let other = vec![("gcc", PathBuf::from("/opt/b-gcc"))];
+        assert_ne!(dir, shim_dir(root, "aarch64-unknown-linux-musl", &other));
+
+        assert_eq!(
+            cmd_shim(Path::new(r"C:\cross\bin\x86_64-w64-mingw32-gcc.exe")),
+            "@\"C:\\cross\\bin\\x86_64-w64-mingw32-gcc.exe\" %*\r\n"
+        );
+    }
+
+    #[test]
+    fn test_write_shims() {
+        let root = std::env::temp_dir().join(format!("cargo-cross-shims-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let tool = root.join("bin/aarch64-linux-musl-gcc");
+        std::fs::create_dir_all(tool.parent().unwrap()).unwrap();
+        std::fs::write(&tool, "#!/bin/sh\n").unwrap();
+        let shims = vec![("gcc", tool.clone())];
+        let dir = shim_dir(&root, "aarch64-unknown-linux-musl", &shims);
+
+        write_shims(&dir, &shims, false).unwrap();
+        #[cfg(unix)]
+        assert_eq!(std::fs::read_link(dir.join("gcc")).unwrap(), tool);
+        // Existing shims are reused
+        write_shims(&dir, &shims, false).unwrap();
+
+        let windows_dir = dir.with_file_name("windows");
+        write_shims(&windows_dir, &shims, true).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(windows_dir.join("gcc.cmd")).unwrap(),
+            cmd_shim(&tool)
+        );
+        // No temporary directories are left behind
+        let leftovers = std::fs::read_dir(dir.parent().unwrap())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .count();
+        assert_eq!(leftovers, 0);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_prepend_path() {
+        let unix = HostPlatform {
+            os: "linux",
+            arch: "x86_64",
+            triple: "x86_64-unknown-linux-gnu".to_string(),
+        };
+        let mut env = EnvMap::new();
+        env.insert("PATH", "/cross/bin:/usr/bin", EnvSource::CrossEnv);
+        prepend_path(&mut env, Path::new("/cache/generated/shims/x"), &unix);
+        assert_eq!(
+            env.get("PATH").unwrap(),
+            "/cache/generated/shims/x:/cross/bin:/usr/bin"
+        );
+
+        let windows = HostPlatform {
+            os: "windows",
+            arch: "x86_64",
+            triple: "x86_64-pc-windows-msvc".to_string(),
+        };
+        let mut env = EnvMap::new();
+        env.insert("PATH", r"C:\cross\bin", EnvSource::CrossEnv);
+        prepend_path(&mut env, Path::new(r"C:\shims"), &windows);
+        assert_eq!(env.get("PATH").unwrap(), r"C:\shims;C:\cross\bin");
+    }
+}
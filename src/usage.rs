@@ -0,0 +1,195 @@
+//! Local usage log (`--usage-log`): one JSON line per run, appended to a file
+//!
+//! Nothing is sent anywhere. Teams point the option at a shared file and aggregate the
+//! lines themselves. Each line holds the time, the cargo-cross version, the command, the
+//! targets, how long the run took and whether it succeeded, plus the per-target entries
+//! of the `--metrics-file` JSON.
+
+use crate::error::{CrossError, Result};
+use crate::metrics::{self, TargetMetrics};
+use crate::report::Status;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Longest line written, newline included. Longer records lose their per-target entries
+/// first, then the target list and the error.
+pub const MAX_LINE_BYTES: usize = 16 * 1024;
+
+/// What a run did, for one line of the usage log
+#[derive(Debug, Clone)]
+pub struct RunSummary<'a> {
+    pub command: &'a str,
+    pub targets: &'a [String],
+    pub status: Status,
+    pub elapsed: Duration,
+    /// Metrics of the targets that ran, in order
+    pub metrics: &'a [TargetMetrics],
+    /// Error that stopped the run, if any
+    pub error: Option<String>,
+}
+
+/// The line for `summary` at `timestamp` (seconds since the Unix epoch), without the
+/// newline and at most [`MAX_LINE_BYTES`] - 1 bytes long
+#[must_use]
+pub fn usage_line(summary: &RunSummary<'_>, timestamp: u64) -> String {
+    let mut record = json!({
+        "timestamp": timestamp,
+        "version": env!("CARGO_PKG_VERSION"),
+        "command": summary.command,
+        "targets": summary.targets,
+        "target_count": summary.targets.len(),
+        "status": summary.status.as_str(),
+        "seconds": summary.elapsed.as_secs_f64(),
+        "error": summary.error,
+        "target_metrics": metrics::render_json(summary.metrics),
+    });
+    let fits = |record: &Value| record.to_string().len() < MAX_LINE_BYTES;
+    if !fits(&record) {
+        record["target_metrics"] = Value::Null;
+        record["truncated"] = json!(true);
+    }
+    if !fits(&record) {
+        record["targets"] = Value::Null;
+        record["error"] = json!(summary
+            .error
+            .as_ref()
+            .map(|error| error.chars().take(1024).collect::<String>()));
+    }
+    record.to_string()
+}
+
+/// Append `line` and a newline to `path` while holding an exclusive advisory lock, so
+/// concurrent runs never interleave their lines
+pub fn append_line(path: &Path, line: &str) -> Result<()> {
+    let io_error = |source| CrossError::IoError {
+        message: format!("Failed to append to {}", path.display()),
+        source,
+    };
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(io_error)?;
+    file.lock().map_err(io_error)?;
+    let written = file.write_all(format!("{line}\n").as_bytes());
+    let _ = file.unlock();
+    written.map_err(io_error)
+}
+
+/// Append the line for `summary` to the usage log at `path`
+pub fn record_run(path: &Path, summary: &RunSummary<'_>) -> Result<()> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    append_line(path, &usage_line(summary, timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary<'a>(targets: &'a [String], metrics: &'a [TargetMetrics]) -> RunSummary<'a> {
+        RunSummary {
+            command: "build",
+            targets,
+            status: Status::Failed,
+            elapsed: Duration::from_millis(1500),
+            metrics,
+            error: Some("Cargo build failed".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_usage_line() {
+        let targets = vec![
+            "aarch64-unknown-linux-musl".to_string(),
+            "x86_64-pc-windows-gnu".to_string(),
+        ];
+        let metrics = vec![TargetMetrics {
+            target: targets[0].clone(),
+            total: Duration::from_secs(1),
+            ..TargetMetrics::default()
+        }];
+        let line = usage_line(&summary(&targets, &metrics), 1_700_000_000);
+        assert!(!line.contains('\n'));
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["timestamp"], 1_700_000_000);
+        assert_eq!(value["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(value["command"], "build");
+        assert_eq!(value["targets"], json!(targets));
+        assert_eq!(value["target_count"], 2);
+        assert_eq!(value["status"], "failed");
+        assert_eq!(value["seconds"], 1.5);
+        assert_eq!(value["error"], "Cargo build failed");
+        assert_eq!(value["target_metrics"][0]["target"], targets[0]);
+        assert_eq!(value["target_metrics"][0]["seconds"], 1.0);
+        assert!(value.get("truncated").is_none());
+    }
+
+    #[test]
+    fn test_usage_line_is_capped() {
+        let targets: Vec<String> = (0..2000).map(|i| format!("target-{i:05}")).collect();
+        let metrics: Vec<TargetMetrics> = targets
+            .iter()
+            .map(|target| TargetMetrics {
+                target: target.clone(),
+                ..TargetMetrics::default()
+            })
+            .collect();
+        let mut run = summary(&targets, &metrics);
+        run.error = Some("x".repeat(100_000));
+        let line = usage_line(&run, 0);
+        assert!(line.len() < MAX_LINE_BYTES, "{}", line.len());
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["truncated"], true);
+        assert_eq!(value["target_count"], 2000);
+        assert!(value["targets"].is_null());
+        assert!(value["target_metrics"].is_null());
+        assert_eq!(value["error"].as_str().unwrap().len(), 1024);
+
+        // Only the per-target entries go when that is enough
+        let targets = &targets[..200];
+        let line = usage_line(&summary(targets, &metrics), 0);
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["truncated"], true);
+        assert_eq!(value["targets"].as_array().unwrap().len(), 200);
+    }
+
+    #[test]
+    fn test_append_line_concurrently() {
+        let dir = std::env::temp_dir().join(format!("cargo-cross-usage-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("logs/usage.jsonl");
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for j in 0..25 {
+                        let line = json!({"thread": i, "line": j, "pad": "y".repeat(4096)});
+                        append_line(&path, &line.to_string()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 200);
+        for line in lines {
+            serde_json::from_str::<Value>(line).unwrap();
+        }
+
+        // A path that cannot be written is an error, not a panic
+        std::fs::write(dir.join("file"), "").unwrap();
+        assert!(append_line(&dir.join("file/usage.jsonl"), "{}").is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,400 @@
+//! Building from a `cargo vendor` directory
+//!
+//! `--vendored DIR` replaces crates.io (and the git sources in Cargo.lock) with the vendored
+//! copies through `--config` flags, so no `.cargo/config.toml` edit is needed, and runs cargo
+//! with `--offline`. Cargo.lock is checked against the directory first, so a stale vendor
+//! directory fails with the list of missing packages instead of a resolver error.
+
+use crate::cli::Args;
+use crate::error::{CrossError, Result};
+use crate::metadata::{effective_cwd, resolve_cargo_path};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Name of the source that replaces crates.io, as `cargo vendor` prints it
+pub const VENDORED_SOURCE: &str = "vendored-sources";
+
+/// File `cargo vendor` writes into every vendored package
+const CHECKSUM_FILE: &str = ".cargo-checksum.json";
+
+/// A package from Cargo.lock that is fetched from a registry or a git repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    pub source: String,
+}
+
+/// Value of a `key = "value"` line
+fn string_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(key)?.trim_start().strip_prefix('=')?;
+    rest.trim().strip_prefix('"')?.strip_suffix('"')
+}
+
+/// Packages in a Cargo.lock that have a source; path dependencies have none
+#[must_use]
+pub fn locked_packages(lockfile: &str) -> Vec<LockedPackage> {
+    let mut packages = Vec::new();
+    let mut current: Option<(String, String, Option<String>)> = None;
+    let mut flush = |current: &mut Option<(String, String, Option<String>)>| {
+        if let Some((name, version, Some(source))) = current.take() {
+            packages.push(LockedPackage {
+                name,
+                version,
+                source,
+            });
+        }
+    };
+    for line in lockfile.lines().map(str::trim) {
+        if line.starts_with('[') {
+            flush(&mut current);
+            if line == "[[package]]" {
+                current = Some((String::new(), String::new(), None));
+            }
+        } else if let Some(ref mut package) = current {
+            if let Some(name) = string_value(line, "name") {
+                package.0 = name.to_string();
+            } else if let Some(version) = string_value(line, "version") {
+                package.1 = version.to_string();
+            } else if let Some(source) = string_value(line, "source") {
+                package.2 = Some(source.to_string());
+            }
+        }
+    }
+    flush(&mut current);
+    packages
+}
+
+/// `version` of the `[package]` table of a vendored Cargo.toml
+fn manifest_version(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package {
+            if let Some(version) = string_value(line, "version") {
+                return Some(version.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Check that `dir` looks like `cargo vendor` output: a directory whose packages all
+/// carry `.cargo-checksum.json`
+///
+/// # Errors
+///
+/// Returns an error if `dir` is not a directory or holds a package without a checksum file
+pub fn check_vendor_dir(dir: &Path) -> Result<()> {
+    let invalid = |reason: String| {
+        CrossError::InvalidArgument(format!(
+            "--vendored {}: {reason}\nCreate it with 'cargo vendor {}'",
+            dir.display(),
+            dir.display()
+        ))
+    };
+    let entries = std::fs::read_dir(dir).map_err(|e| invalid(e.to_string()))?;
+    let mut packages = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if hidden || !path.is_dir() {
+            continue;
+        }
+        if !path.join(CHECKSUM_FILE).is_file() {
+            return Err(invalid(format!(
+                "{} has no {CHECKSUM_FILE}, this is not a cargo vendor directory",
+                path.display()
+            )));
+        }
+        packages += 1;
+    }
+    if packages == 0 {
+        return Err(invalid("no vendored packages found".to_string()));
+    }
+    Ok(())
+}
+
+/// Whether `dir` holds `package`, either as `name-version` (`--versioned-dirs` or several
+/// versions of one crate) or as `name` with the locked version in its Cargo.toml
+#[must_use]
+pub fn is_vendored(dir: &Path, package: &LockedPackage) -> bool {
+    let versioned = dir.join(format!("{}-{}", package.name, package.version));
+    if versioned.join(CHECKSUM_FILE).is_file() {
+        return true;
+    }
+    let plain = dir.join(&package.name);
+    plain.join(CHECKSUM_FILE).is_file()
+        && std::fs::read_to_string(plain.join("Cargo.toml"))
+            .ok()
+            .and_then(|manifest| manifest_version(&manifest))
+            .is_some_and(|version| version == package.version)
+}
+
+/// Packages of the lockfile that are not in the vendor directory, as `name@version`
+#[must_use]
+pub fn missing_packages(dir: &Path, lockfile: &str) -> Vec<String> {
+    locked_packages(lockfile)
+        .iter()
+        .filter(|package| !is_vendored(dir, package))
+        .map(|package| format!("{}@{}", package.name, package.version))
+        .collect()
+}
+
+/// Quote a string as a TOML basic string, escaping quotes, backslashes and control characters
+pub(crate) fn toml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\u{8}' => quoted.push_str("\\b"),
+            '\t' => quoted.push_str("\\t"),
+            '\n' => quoted.push_str("\\n"),
+            '\u{c}' => quoted.push_str("\\f"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04X}", u32::from(c));
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `--config` values for one git source of Cargo.lock, e.g.
+/// `git+https://github.com/o/r?branch=main#<sha>`
+fn git_source_config(source: &str) -> Option<Vec<String>> {
+    let url = source.strip_prefix("git+")?;
+    let url = url.split_once('#').map_or(url, |(url, _commit)| url);
+    let (repo, reference) = url.split_once('?').unwrap_or((url, ""));
+    let key = format!("source.{}", toml_string(&format!("git+{url}")));
+
+    let mut config = vec![format!("{key}.git={}", toml_string(repo))];
+    for (name, value) in reference.split('&').filter_map(|kv| kv.split_once('=')) {
+        if matches!(name, "branch" | "tag" | "rev") {
+            config.push(format!("{key}.{name}={}", toml_string(value)));
+        }
+    }
+    config.push(format!(
+        "{key}.replace-with={}",
+        toml_string(VENDORED_SOURCE)
+    ));
+    Some(config)
+}
+
+/// `--config` values that make cargo use the vendor directory, as in the snippet
+/// `cargo vendor` prints
+#[must_use]
+pub fn vendor_config_args(dir: &Path, lockfile: &str) -> Vec<String> {
+    let mut config = vec![
+        format!(
+            "source.crates-io.replace-with={}",
+            toml_string(VENDORED_SOURCE)
+        ),
+        format!(
+            "source.{VENDORED_SOURCE}.directory={}",
+            toml_string(&dir.to_string_lossy())
+        ),
+    ];
+    let mut git_sources: Vec<String> = locked_packages(lockfile)
+        .into_iter()
+        .map(|package| package.source)
+        .filter(|source| source.starts_with("git+"))
+        .map(|source| {
+            source
+                .split_once('#')
+                .map_or(source.clone(), |(url, _)| url.to_string())
+        })
+        .collect();
+    git_sources.sort();
+    git_sources.dedup();
+    for source in git_sources {
+        config.extend(git_source_config(&source).unwrap_or_default());
+    }
+    config
+}
+
+/// Cargo.lock cargo will use: `--lockfile-path`, the workspace root's, or the nearest one
+/// above the manifest or directory
+#[must_use]
+pub fn find_lockfile(args: &Args) -> Option<PathBuf> {
+    if let Some(ref path) = args.lockfile_path {
+        return Some(resolve_cargo_path(args, path));
+    }
+    if let Some(ref workspace) = args.workspace_paths {
+        let lockfile = workspace.root.join("Cargo.lock");
+        if lockfile.is_file() {
+            return Some(lockfile);
+        }
+    }
+    let start = args.manifest_path.as_ref().map_or_else(
+        || effective_cwd(args),
+        |manifest| {
+            let manifest = resolve_cargo_path(args, manifest);
+            manifest.parent().map(Path::to_path_buf).unwrap_or(manifest)
+        },
+    );
+    start
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|lockfile| lockfile.is_file())
+}
+
+/// Point cargo at the `--vendored` directory and force `--offline`
+///
+/// # Errors
+///
+/// Returns an error if the directory is not `cargo vendor` output, Cargo.lock is missing,
+/// or a locked package is not vendored
+pub fn apply_vendored(args: &mut Args) -> Result<()> {
+    let Some(ref dir) = args.vendored else {
+        return Ok(());
+    };
+    let dir = resolve_cargo_path(args, dir);
+    check_vendor_dir(&dir)?;
+
+    let lockfile_path = find_lockfile(args).ok_or_else(|| {
+        CrossError::InvalidArgument(
+            "--vendored needs a Cargo.lock; run 'cargo generate-lockfile' and 'cargo vendor' first"
+                .to_string(),
+        )
+    })?;
+    let lockfile = std::fs::read_to_string(&lockfile_path).map_err(|e| CrossError::IoError {
+        message: format!("Failed to read {}", lockfile_path.display()),
+        source: e,
+    })?;
+    let missing = missing_packages(&dir, &lockfile);
+    if !missing.is_empty() {
+        return Err(CrossError::VendorIncomplete {
+            dir,
+            lockfile: lockfile_path,
+            missing: missing.join(", "),
+        });
+    }
+
+    // Ahead of the user's --config values so theirs still win
+    let config = vendor_config_args(&dir, &lockfile);
+    args.cargo_config.splice(0..0, config);
+    args.offline = true;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCKFILE: &str = r#"# This file is automatically @generated by Cargo.
+version = 4
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "libc",
+ "patched",
+]
+
+[[package]]
+name = "libc"
+version = "0.2.170"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "0000"
+
+[[package]]
+name = "patched"
+version = "1.2.0"
+source = "git+https://github.com/example/patched?branch=fix#0123456789abcdef"
+"#;
+
+    fn vendor_package(dir: &Path, dir_name: &str, version: &str) {
+        let package = dir.join(dir_name);
+        std::fs::create_dir_all(&package).unwrap();
+        std::fs::write(package.join(CHECKSUM_FILE), "{\"files\":{}}").unwrap();
+        std::fs::write(
+            package.join("Cargo.toml"),
+            format!("[package]\nname = \"x\"\nversion = \"{version}\"\n\n[dependencies.y]\nversion = \"9\"\n"),
+        )
+        .unwrap();
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("cargo-cross-vendor-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_locked_packages() {
+        let packages = locked_packages(LOCKFILE);
+        assert_eq!(packages.len(), 2);
+        assert_eq!(
+            packages[0],
+            LockedPackage {
+                name: "libc".to_string(),
+                version: "0.2.170".to_string(),
+                source: "registry+https://github.com/rust-lang/crates.io-index".to_string(),
+            }
+        );
+        assert_eq!(packages[1].name, "patched");
+    }
+
+    #[test]
+    fn test_vendor_config_args() {
+        let config = vendor_config_args(Path::new("/ci/vendor"), LOCKFILE);
+        assert_eq!(
+            config,
+            [
+                r#"source.crates-io.replace-with="vendored-sources""#,
+                r#"source.vendored-sources.directory="/ci/vendor""#,
+                r#"source."git+https://github.com/example/patched?branch=fix".git="https://github.com/example/patched""#,
+                r#"source."git+https://github.com/example/patched?branch=fix".branch="fix""#,
+                r#"source."git+https://github.com/example/patched?branch=fix".replace-with="vendored-sources""#,
+            ]
+        );
+        let windows = vendor_config_args(Path::new(r"C:\ci\vendor"), "");
+        assert_eq!(
+            windows[1],
+            r#"source.vendored-sources.directory="C:\\ci\\vendor""#
+        );
+    }
+
+    #[test]
+    fn test_check_vendor_dir() {
+        let dir = fixture("check");
+        assert!(check_vendor_dir(&dir).is_err());
+        assert!(check_vendor_dir(&dir.join("absent")).is_err());
+
+        vendor_package(&dir, "libc", "0.2.170");
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        assert!(check_vendor_dir(&dir).is_ok());
+
+        std::fs::create_dir_all(dir.join("not-vendored")).unwrap();
+        let err = check_vendor_dir(&dir).unwrap_err().to_string();
+        assert!(err.contains("not-vendored"), "{err}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_missing_packages() {
+        let dir = fixture("missing");
+        vendor_package(&dir, "libc", "0.2.169");
+        assert_eq!(
+            missing_packages(&dir, LOCKFILE),
+            ["libc@0.2.170", "patched@1.2.0"]
+        );
+
+        // Plain directory with the locked version, and a versioned directory
+        vendor_package(&dir, "libc", "0.2.170");
+        vendor_package(&dir, "patched-1.2.0", "1.2.0");
+        assert!(missing_packages(&dir, LOCKFILE).is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
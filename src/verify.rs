@@ -0,0 +1,427 @@
+//! Smoke test of a configured C/C++ toolchain
+//!
+//! A missing sysroot header or a broken archiver otherwise only shows up when the first
+//! build script runs the C compiler, often after many minutes of compiling Rust
+//! dependencies. The smoke test compiles and links a hello-world program with exactly the
+//! compiler and flags exported to cargo and checks the architecture of the result. Passing
+//! results are remembered in the cross compiler directory, keyed by a hash of the inputs.
+
+use crate::cache::fnv1a;
+use crate::cargo::build_cargo_env;
+use crate::cli::Args;
+use crate::color;
+use crate::config::{HostPlatform, TargetConfig};
+use crate::diagnose::{expected_architecture, sniff_architecture};
+use crate::env::{CrossEnv, EnvMap};
+use crate::error::{run_command_output, CrossError, Result};
+use crate::layout;
+use crate::platform::gcc::builds_cxx;
+use object::Architecture;
+use std::path::Path;
+use tokio::process::Command as TokioCommand;
+
+/// Directory in the cross compiler directory with a marker file per passed smoke test
+pub const SMOKE_CACHE_DIR: &str = ".smoke-tests";
+
+const C_SOURCE: &str = r#"#include <stdio.h>
+
+int main(void) {
+    puts("hello");
+    return 0;
+}
+"#;
+
+const CXX_SOURCE: &str = r#"#include <iostream>
+#include <string>
+
+int main() {
+    std::string greeting("hello");
+    std::cout << greeting << std::endl;
+    return 0;
+}
+"#;
+
+/// Language of a smoke test program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    C,
+    Cxx,
+}
+
+impl Language {
+    const fn compiler_var(self) -> &'static str {
+        match self {
+            Self::C => "CC",
+            Self::Cxx => "CXX",
+        }
+    }
+
+    const fn flags_var(self) -> &'static str {
+        match self {
+            Self::C => "CFLAGS",
+            Self::Cxx => "CXXFLAGS",
+        }
+    }
+
+    const fn source(self) -> &'static str {
+        match self {
+            Self::C => C_SOURCE,
+            Self::Cxx => CXX_SOURCE,
+        }
+    }
+
+    /// Source file name and output name. The output has an extension so MinGW does not
+    /// append `.exe`.
+    const fn file_names(self) -> (&'static str, &'static str) {
+        match self {
+            Self::C => ("smoke.c", "smoke-c.out"),
+            Self::Cxx => ("smoke.cpp", "smoke-cxx.out"),
+        }
+    }
+}
+
+/// Compiler and flags of one language as exported to cargo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmokeCompiler {
+    pub language: Language,
+    /// Compiler program followed by any arguments embedded in `CC`/`CXX`
+    pub compiler: Vec<String>,
+    pub flags: Vec<String>,
+    pub ldflags: Vec<String>,
+}
+
+impl SmokeCompiler {
+    /// Command line compiling and linking `source` into `output`
+    #[must_use]
+    pub fn command_line(&self, source: &Path, output: &Path) -> Vec<String> {
+        let mut line = self.compiler.clone();
+        line.extend(self.flags.iter().cloned());
+        line.push(source.display().to_string());
+        line.push("-o".to_string());
+        line.push(output.display().to_string());
+        line.extend(self.ldflags.iter().cloned());
+        line
+    }
+}
+
+/// The target-specific variable (`CC_<target>`) if set, the generic one otherwise
+fn target_var<'a>(env: &'a EnvMap, name: &str, target: &str) -> Option<&'a String> {
+    env.get(&format!("{name}_{}", target.replace('-', "_")))
+        .or_else(|| env.get(name))
+}
+
+fn split_words(value: Option<&String>) -> Vec<String> {
+    value
+        .and_then(|value| shlex::split(value))
+        .unwrap_or_default()
+}
+
+/// The `language` compiler the build of `target` would use, `None` if none is set
+#[must_use]
+pub fn smoke_compiler(env: &EnvMap, target: &str, language: Language) -> Option<SmokeCompiler> {
+    let compiler = split_words(target_var(env, language.compiler_var(), target));
+    if compiler.is_empty() {
+        return None;
+    }
+    Some(SmokeCompiler {
+        language,
+        compiler,
+        flags: split_words(target_var(env, language.flags_var(), target)),
+        ldflags: split_words(target_var(env, "LDFLAGS", target)),
+    })
+}
+
+/// Cache key of a smoke test: the target, every compiler with its flags and the PATH the
+/// compilers are looked up in
+#[must_use]
+pub fn smoke_key(target: &str, compilers: &[SmokeCompiler], path: Option<&str>) -> String {
+    let mut input = String::from(target);
+    for compiler in compilers {
+        for word in compiler
+            .compiler
+            .iter()
+            .chain(&compiler.flags)
+            .chain(&compiler.ldflags)
+        {
+            input.push('\x1f');
+            input.push_str(word);
+        }
+        input.push('\x1e');
+    }
+    input.push_str(path.unwrap_or_default());
+    format!("{target}-{:016x}", fnv1a(input.as_bytes()))
+}
+
+/// Whether to smoke test a target: `--smoke-test-toolchain` if given, otherwise only when
+/// its toolchain was downloaded during this run
+#[must_use]
+pub fn should_smoke_test(args: &Args, downloaded: bool) -> bool {
+    !args.skips_toolchain_setup() && args.smoke_test_toolchain.unwrap_or(downloaded)
+}
+
+/// Compile and link the program of one language in `dir` and check its architecture
+async fn compile_and_check(
+    target: &str,
+    compiler: &SmokeCompiler,
+    dir: &Path,
+    env: &EnvMap,
+    expected: Architecture,
+) -> Result<()> {
+    let (source_name, output_name) = compiler.language.file_names();
+    let source = dir.join(source_name);
+    let output = dir.join(output_name);
+    std::fs::write(&source, compiler.language.source()).map_err(|e| CrossError::IoError {
+        message: format!("Failed to write {}", source.display()),
+        source: e,
+    })?;
+
+    let line = compiler.command_line(&source, &output);
+    let failed = |reason: String, stderr: String| CrossError::SmokeTestFailed {
+        target: target.to_string(),
+        reason,
+        command: line.join(" "),
+        stderr,
+    };
+    let mut cmd = TokioCommand::new(&line[0]);
+    cmd.args(&line[1..]).envs(env.vars());
+    let result = run_command_output(&mut cmd, &line[0]).await?;
+    if !result.status.success() {
+        return Err(failed(
+            format!("{} exited with {}", line[0], result.status),
+            String::from_utf8_lossy(&result.stderr).into_owned(),
+        ));
+    }
+
+    let data = std::fs::read(&output).unwrap_or_default();
+    match sniff_architecture(&data) {
+        Some(found) if found == expected => Ok(()),
+        found => Err(failed(
+            format!("output has architecture {found:?}, expected {expected:?}"),
+            String::new(),
+        )),
+    }
+}
+
+/// Compile and link hello-world programs with the toolchain configured for a target,
+/// unless an identical setup passed before
+pub async fn smoke_test_toolchain(
+    target_config: &TargetConfig,
+    args: &Args,
+    cross_env: &CrossEnv,
+    host: &HostPlatform,
+) -> Result<()> {
+    let target = target_config.target;
+    let env = build_cargo_env(target, args, cross_env, host, false)?;
+    let mut compilers: Vec<_> = smoke_compiler(&env, target, Language::C)
+        .into_iter()
+        .collect();
+    if builds_cxx(args).await {
+        compilers.extend(smoke_compiler(&env, target, Language::Cxx));
+    }
+    if compilers.is_empty() {
+        return Ok(());
+    }
+
+    let key = smoke_key(target, &compilers, env.get("PATH").map(String::as_str));
+    let marker = layout::generated_dir(&args.cross_compiler_dir)
+        .join(SMOKE_CACHE_DIR)
+        .join(&key);
+    if marker.exists() {
+        color::log_debug(&format!("Toolchain smoke test for {target} passed before"));
+        return Ok(());
+    }
+
+    let dir = std::env::temp_dir().join(format!("cargo-cross-smoke-{key}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let expected = expected_architecture(target_config.arch);
+    let mut result = Ok(());
+    for compiler in &compilers {
+        result = compile_and_check(target, compiler, &dir, &env, expected).await;
+        if result.is_err() {
+            break;
+        }
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+    result?;
+
+    // A marker that cannot be written only means the test runs again next time
+    if let Some(parent) = marker.parent() {
+        let _ = std::fs::create_dir_all(parent).and_then(|()| std::fs::write(&marker, ""));
+    }
+    color::log_success(&format!(
+        "Toolchain smoke test passed for {}",
+        color::yellow(target)
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{test_target_args, test_toolchain_dir, BuildArgs};
+    use crate::config::get_target_config;
+    use crate::env::EnvSource;
+    use std::path::PathBuf;
+
+    fn smoke_args(build: BuildArgs, cross_compiler_dir: PathBuf) -> Args {
+        Args {
+            cross_compiler_dir,
+            ..test_target_args("smoke", &["aarch64-unknown-linux-musl"], build)
+        }
+    }
+
+    /// The host C compiler, if the machine running the tests has one
+    fn host_cc() -> Option<&'static str> {
+        which::which("cc").ok().map(|_| "cc")
+    }
+
+    #[test]
+    fn test_smoke_compiler_from_env() {
+        let target = "aarch64-unknown-linux-musl";
+        let mut env = EnvMap::new();
+        env.insert(
+            "CC_aarch64_unknown_linux_musl",
+            "zig cc -target aarch64-linux-musl",
+            EnvSource::CrossEnv,
+        );
+        env.insert("CFLAGS", "-O2 -DNAME='a b'", EnvSource::Cli);
+        env.insert(
+            "LDFLAGS_aarch64_unknown_linux_musl",
+            "-static",
+            EnvSource::CrossEnv,
+        );
+
+        let compiler = smoke_compiler(&env, target, Language::C).unwrap();
+        assert_eq!(
+            compiler.compiler,
+            ["zig", "cc", "-target", "aarch64-linux-musl"]
+        );
+        assert_eq!(compiler.flags, ["-O2", "-DNAME=a b"]);
+        assert_eq!(
+            compiler.command_line(Path::new("smoke.c"), Path::new("smoke-c.out")),
+            [
+                "zig",
+                "cc",
+                "-target",
+                "aarch64-linux-musl",
+                "-O2",
+                "-DNAME=a b",
+                "smoke.c",
+                "-o",
+                "smoke-c.out",
+                "-static"
+            ]
+        );
+        assert_eq!(smoke_compiler(&env, target, Language::Cxx), None);
+
+        let key = smoke_key(target, std::slice::from_ref(&compiler), Some("/bin"));
+        assert!(key.starts_with("aarch64-unknown-linux-musl-"));
+        assert_eq!(
+            key,
+            smoke_key(target, std::slice::from_ref(&compiler), Some("/bin"))
+        );
+        let mut other = compiler;
+        other.flags.push("-g".to_string());
+        assert_ne!(key, smoke_key(target, &[other], Some("/bin")));
+    }
+
+    #[test]
+    fn test_should_smoke_test() {
+        let dir = test_toolchain_dir("should-smoke");
+        let args = smoke_args(BuildArgs::default(), dir.clone());
+        assert!(should_smoke_test(&args, true));
+        assert!(!should_smoke_test(&args, false));
+        let args = smoke_args(
+            BuildArgs {
+                smoke_test_toolchain: Some(false),
+                ..BuildArgs::default()
+            },
+            dir.clone(),
+        );
+        assert!(!should_smoke_test(&args, true));
+        let args = smoke_args(
+            BuildArgs {
+                smoke_test_toolchain: Some(true),
+                no_toolchain_setup: true,
+                ..BuildArgs::default()
+            },
+            dir,
+        );
+        assert!(!should_smoke_test(&args, true));
+    }
+
+    #[tokio::test]
+    async fn test_smoke_test_with_host_compiler() {
+        let Some(cc) = host_cc() else {
+            return;
+        };
+        let host = HostPlatform::detect();
+        let Some(config) = get_target_config(&host.triple) else {
+            return;
+        };
+        let root = std::env::temp_dir().join(format!("cargo-cross-verify-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let args = smoke_args(
+            BuildArgs {
+                lockfile_path: Some(root.join("missing.lock")),
+                ..BuildArgs::default()
+            },
+            root.clone(),
+        );
+
+        let mut cross_env = CrossEnv::new();
+        cross_env.set_cc(cc);
+        smoke_test_toolchain(config, &args, &cross_env, &host)
+            .await
+            .unwrap();
+        let markers: Vec<_> = std::fs::read_dir(layout::generated_dir(&root).join(SMOKE_CACHE_DIR))
+            .unwrap()
+            .collect();
+        assert_eq!(markers.len(), 1);
+
+        // A broken sysroot include fails with the compiler's stderr
+        cross_env.add_cflag("-include cargo-cross-missing-header.h");
+        match smoke_test_toolchain(config, &args, &cross_env, &host).await {
+            Err(CrossError::SmokeTestFailed {
+                command, stderr, ..
+            }) => {
+                assert!(
+                    command.contains("cargo-cross-missing-header.h"),
+                    "{command}"
+                );
+                assert!(stderr.contains("cargo-cross-missing-header.h"), "{stderr}");
+            }
+            other => panic!("expected SmokeTestFailed, got {other:?}"),
+        }
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_smoke_test_detects_wrong_architecture() {
+        let Some(cc) = host_cc() else {
+            return;
+        };
+        let dir =
+            std::env::temp_dir().join(format!("cargo-cross-verify-arch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let compiler = SmokeCompiler {
+            language: Language::C,
+            compiler: vec![cc.to_string()],
+            flags: Vec::new(),
+            ldflags: Vec::new(),
+        };
+        // No host this runs on compiles for s390x by default
+        let err = compile_and_check(
+            "s390x-unknown-linux-gnu",
+            &compiler,
+            &dir,
+            &EnvMap::new(),
+            Architecture::S390x,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("expected S390x"), "{err}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
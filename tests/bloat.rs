@@ -0,0 +1,61 @@
+//! Reads the symbol sizes of a binary built for the host with symbols kept. MSVC binaries
+//! keep their symbols in the `.pdb`, so this runs on Unix hosts only.
+#![cfg(unix)]
+
+use cargo_cross::bloat::{analyze, render, BloatOptions};
+use std::process::Command;
+
+#[test]
+fn analyzes_host_binary() {
+    let dir = std::env::temp_dir().join(format!("cargo-cross-bloat-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"bloat-fixture\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+         [workspace]\n\n[profile.dev]\nstrip = false\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("src/main.rs"),
+        "#[inline(never)]\nfn checksum(data: &[u8]) -> u64 {\n    \
+         data.iter().fold(17, |acc, b| acc.wrapping_mul(31).wrapping_add(u64::from(*b)))\n}\n\n\
+         fn main() {\n    println!(\"{}\", checksum(std::env::args().next().unwrap().as_bytes()));\n}\n",
+    )
+    .unwrap();
+    let status = Command::new("cargo")
+        .args(["build", "--quiet", "--target-dir", "target"])
+        .current_dir(&dir)
+        .env_remove("CARGO_TARGET_DIR")
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success());
+
+    let binary = dir
+        .join("target/debug")
+        .join(format!("bloat-fixture{}", std::env::consts::EXE_SUFFIX));
+    let data = std::fs::read(&binary).unwrap();
+    let report = analyze(&data).unwrap();
+    assert!(report.text_size > 0);
+    let checksum = report
+        .symbols
+        .iter()
+        .find(|symbol| symbol.name == "bloat_fixture::checksum")
+        .expect("checksum function in the report");
+    assert_eq!(checksum.crate_name, "bloat_fixture");
+    assert!(checksum.size > 0);
+    assert!(report
+        .symbols
+        .iter()
+        .any(|symbol| symbol.crate_name == "std"));
+
+    let options = BloatOptions {
+        filter: Some("bloat_fixture".to_string()),
+        ..BloatOptions::default()
+    };
+    let text = render(&report, &options).unwrap();
+    assert!(text.contains("bloat_fixture::checksum"), "{text}");
+    assert!(!text.contains("std::"), "{text}");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
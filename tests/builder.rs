@@ -0,0 +1,89 @@
+//! Drives a host build through the `CrossBuilder` library API
+
+use cargo_cross::{Command, CrossBuilder};
+use std::path::{Path, PathBuf};
+
+fn write_fixture(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("cargo-cross-builder-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"builder-fixture\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+    dir
+}
+
+fn host_builder(dir: &Path) -> CrossBuilder {
+    let manifest = dir.join("Cargo.toml");
+    CrossBuilder::new("host-tuple").configure(move |build| build.manifest_path = Some(manifest))
+}
+
+#[tokio::test]
+async fn command_is_configured_without_running() {
+    let dir = write_fixture("command");
+    let cmd = host_builder(&dir)
+        .cargo_command(Command::check())
+        .command()
+        .await
+        .unwrap();
+
+    let std_cmd = cmd.as_std();
+    assert_eq!(std_cmd.get_program(), "cargo");
+    let args: Vec<_> = std_cmd
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(args[0], "check");
+    assert!(args
+        .windows(2)
+        .any(|w| w[0] == "--manifest-path" && Path::new(&w[1]) == dir.join("Cargo.toml")));
+    assert!(!args.contains(&"--target".to_string()));
+    assert!(!dir.join("target").exists());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn run_builds_host_target() {
+    let dir = write_fixture("run");
+    let outcome = host_builder(&dir).run().await.unwrap();
+
+    assert!(outcome.success());
+    assert!(
+        dir.join("target/debug/builder-fixture").exists()
+            || dir.join("target/debug/builder-fixture.exe").exists()
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn no_toolchain_setup_downloads_nothing_and_keeps_explicit_runner() {
+    // Any download attempt now fails instead of touching the network
+    cargo_cross::download::set_downloads_disabled(true);
+
+    let toolchains = std::env::temp_dir().join(format!(
+        "cargo-cross-builder-no-setup-{}",
+        std::process::id()
+    ));
+    let host = cargo_cross::HostPlatform::detect();
+    let prepared = CrossBuilder::new(host.triple.clone())
+        .cargo_command(Command::test())
+        .cross_compiler_dir(&toolchains)
+        .configure(|build| {
+            build.no_toolchain_setup = true;
+            build.runner = Some("echo".to_string());
+        })
+        .prepare()
+        .await
+        .unwrap();
+
+    assert_eq!(prepared.cross_env.runner.as_deref(), Some("echo"));
+    assert!(prepared.cross_env.cc.is_none());
+    assert!(prepared.cross_env.path.is_empty());
+    assert!(!toolchains.exists());
+}
@@ -0,0 +1,95 @@
+//! Runs two binaries through the Docker QEMU runner script within one session and checks
+//! that they share a container, which is gone after the session is cleaned up. QEMU is
+//! replaced by a shell script, so any Linux image works. Run with:
+//! `cargo test --test docker_runner -- --ignored --nocapture`
+//! Requires a running Docker daemon and pulls alpine:latest.
+#![cfg(unix)]
+
+use cargo_cross::runner::{
+    remove_session_containers, render_docker_runner_script, RUNNER_SESSION_ENV,
+    RUNNER_SESSION_LABEL,
+};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+fn write_executable(path: &Path, content: &str) {
+    std::fs::write(path, content).unwrap();
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// Run `binary` through the runner script and return its output
+fn run(script: &Path, binary: &Path, session: Option<&str>) -> String {
+    let mut cmd = Command::new(script);
+    cmd.arg(binary).env("CARGO_CROSS_QUIET", "1");
+    match session {
+        Some(session) => cmd.env(RUNNER_SESSION_ENV, session),
+        None => cmd.env_remove(RUNNER_SESSION_ENV),
+    };
+    let output = cmd.output().expect("failed to run the runner script");
+    assert!(
+        output.status.success(),
+        "runner failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+fn session_containers(session: &str) -> Vec<String> {
+    let output = Command::new("docker")
+        .args(["ps", "-aq", "--filter"])
+        .arg(format!("label={RUNNER_SESSION_LABEL}={session}"))
+        .output()
+        .expect("failed to run docker");
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .map(String::from)
+        .collect()
+}
+
+#[tokio::test]
+#[ignore = "needs a running Docker daemon"]
+async fn session_shares_one_container() {
+    let dir =
+        std::env::temp_dir().join(format!("cargo-cross-docker-runner-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("sysroot/lib")).unwrap();
+    std::fs::write(dir.join("sysroot/lib/marker"), "").unwrap();
+    // Stands in for QEMU: drops its options and runs the binary
+    write_executable(
+        &dir.join("qemu-fake"),
+        "#!/bin/sh\nwhile [ \"$1\" = -L ] || [ \"$1\" = -cpu ]; do shift 2; done\nexec \"$@\"\n",
+    );
+    // Prints the container it runs in, and whether the sysroot was copied
+    let binary = dir.join("probe");
+    write_executable(
+        &binary,
+        "#!/bin/sh\necho \"$(hostname) $(ls /sysroot/lib)\"\n",
+    );
+    let script = dir.join("docker-qemu-runner-test-gnu-0123abcd.sh");
+    write_executable(
+        &script,
+        &render_docker_runner_script(
+            &dir.join("qemu-fake"),
+            "qemu-fake",
+            &dir.join("sysroot"),
+            "alpine:latest",
+            None,
+        ),
+    );
+
+    let session = format!("test-{}", std::process::id());
+    let first = run(&script, &binary, Some(&session));
+    assert!(first.ends_with(" marker"), "{first}");
+    assert_eq!(run(&script, &binary, Some(&session)), first);
+    assert_eq!(session_containers(&session).len(), 1);
+
+    // Without a session every binary gets its own container, removed afterwards
+    assert_ne!(run(&script, &binary, None), first);
+    assert_eq!(session_containers(&session).len(), 1);
+
+    remove_session_containers(&session).await;
+    assert!(session_containers(&session).is_empty());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
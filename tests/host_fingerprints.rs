@@ -0,0 +1,179 @@
+//! Builds a fixture using serde_derive and a local proc macro with a build script for
+//! one target, then another, and checks that the host artifacts (proc macros, their
+//! dependencies and build scripts) are reused instead of rebuilt. Run with:
+//! `cargo test --test host_fingerprints -- --ignored --nocapture`
+//! Requires network access to download the toolchains for both targets.
+
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+
+const FIRST_TARGET: &str = "x86_64-unknown-linux-musl";
+const SECOND_TARGET: &str = "aarch64-unknown-linux-musl";
+
+fn write_fixture(dir: &Path) {
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::create_dir_all(dir.join("macros/src")).unwrap();
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        r#"[package]
+name = "host-fingerprints-fixture"
+version = "0.1.0"
+edition = "2021"
+
+[workspace]
+
+[dependencies]
+fixture-macros = { path = "macros" }
+serde = { version = "1", features = ["derive"] }
+serde_json = "1"
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("src/main.rs"),
+        r#"#[derive(serde::Serialize, serde::Deserialize, fixture_macros::Named)]
+struct Point { x: i32, y: i32 }
+
+fn main() {
+    println!("{} {}", Point::NAME, serde_json::to_string(&Point { x: 1, y: 2 }).unwrap());
+}
+"#,
+    )
+    .unwrap();
+    // A proc macro whose build script tracks the variables the cc crate reads, like a
+    // proc macro that compiles C code for the host
+    std::fs::write(
+        dir.join("macros/Cargo.toml"),
+        r#"[package]
+name = "fixture-macros"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+proc-macro = true
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("macros/build.rs"),
+        r#"fn main() {
+    for var in ["CFLAGS", "CXXFLAGS", "HOST_CFLAGS", "CC_ENABLE_DEBUG_OUTPUT"] {
+        println!("cargo:rerun-if-env-changed={var}");
+    }
+}
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("macros/src/lib.rs"),
+        r#"use proc_macro::TokenStream;
+
+#[proc_macro_derive(Named)]
+pub fn derive_named(input: TokenStream) -> TokenStream {
+    let name = input
+        .into_iter()
+        .skip_while(|token| token.to_string() != "struct")
+        .nth(1)
+        .unwrap()
+        .to_string();
+    format!("impl {name} {{ const NAME: &'static str = {name:?}; }}")
+        .parse()
+        .unwrap()
+}
+"#,
+    )
+    .unwrap();
+}
+
+/// Build `target` and return cargo's `compiler-artifact` messages
+fn build(dir: &Path, target: &str, extra: &[&str]) -> Vec<Value> {
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-cross"))
+        .args(["build", "-t", target, "--message-format", "json"])
+        .args(extra)
+        .current_dir(dir)
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("failed to run cargo-cross");
+    assert!(
+        output.status.success(),
+        "build for {target} failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|message| message["reason"] == "compiler-artifact")
+        .collect()
+}
+
+/// Whether an artifact was built for the host: its files are outside `target/<triple>/`
+fn is_host_artifact(message: &Value, target: &str) -> bool {
+    message["filenames"].as_array().is_some_and(|files| {
+        files.iter().filter_map(Value::as_str).all(|file| {
+            !Path::new(file)
+                .components()
+                .any(|component| component.as_os_str() == target)
+        })
+    })
+}
+
+#[test]
+#[ignore = "downloads toolchains and builds crates from crates.io"]
+fn second_target_reuses_host_artifacts() {
+    let dir = std::env::temp_dir().join(format!(
+        "cargo-cross-host-fingerprints-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    write_fixture(&dir);
+
+    let first = build(&dir, FIRST_TARGET, &["--cflags", "-O2"]);
+    assert!(
+        first
+            .iter()
+            .any(|message| message["target"]["kind"][0] == "proc-macro"),
+        "the fixture should build serde_derive"
+    );
+
+    // Another target, other C flags and -v: none of them may reach host fingerprints
+    let second = build(&dir, SECOND_TARGET, &["--cflags", "-O1", "-v"]);
+    let host: Vec<&Value> = second
+        .iter()
+        .filter(|message| is_host_artifact(message, SECOND_TARGET))
+        .collect();
+    assert!(!host.is_empty(), "no host artifacts reported");
+    let rebuilt: Vec<&str> = host
+        .iter()
+        .filter(|message| message["fresh"] != true)
+        .filter_map(|message| message["package_id"].as_str())
+        .collect();
+    assert!(rebuilt.is_empty(), "host artifacts rebuilt: {rebuilt:#?}");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+#[ignore = "downloads toolchains and builds crates from crates.io"]
+fn crt_static_stays_off_host_units() {
+    let dir = std::env::temp_dir().join(format!(
+        "cargo-cross-host-fingerprints-crt-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    write_fixture(&dir);
+
+    build(&dir, FIRST_TARGET, &[]);
+
+    // The crt-static flag is scoped to the target, so host units keep their fingerprints
+    let gnu = "x86_64-unknown-linux-gnu";
+    let second = build(&dir, gnu, &["--crt-static", "false"]);
+    let rebuilt: Vec<&str> = second
+        .iter()
+        .filter(|message| is_host_artifact(message, gnu) && message["fresh"] != true)
+        .filter_map(|message| message["package_id"].as_str())
+        .collect();
+    assert!(rebuilt.is_empty(), "host artifacts rebuilt: {rebuilt:#?}");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
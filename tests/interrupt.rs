@@ -0,0 +1,76 @@
+//! Signals sent to cargo-cross reach the child it is running
+#![cfg(unix)]
+
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+fn wait_for_pid(pidfile: &Path) -> Pid {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    loop {
+        if let Ok(contents) = std::fs::read_to_string(pidfile) {
+            if let Ok(pid) = contents.trim().parse() {
+                return Pid::from_raw(pid);
+            }
+        }
+        assert!(Instant::now() < deadline, "child never wrote its pid");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn assert_exits(pid: Pid) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while kill(pid, None).is_ok() {
+        assert!(
+            Instant::now() < deadline,
+            "child {pid} outlived cargo-cross"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn interrupt_exec(signal: Signal, expected_code: i32) {
+    let dir = std::env::temp_dir().join(format!(
+        "cargo-cross-interrupt-{signal}-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let pidfile = dir.join("child.pid");
+
+    let mut cargo_cross = Command::new(env!("CARGO_BIN_EXE_cargo-cross"))
+        .current_dir(&dir)
+        .args([
+            "exec",
+            "--",
+            "sh",
+            "-c",
+            "echo $$ > child.pid; exec sleep 30",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let child = wait_for_pid(&pidfile);
+    let parent = Pid::from_raw(i32::try_from(cargo_cross.id()).unwrap());
+    kill(parent, signal).unwrap();
+
+    let status = cargo_cross.wait().unwrap();
+    assert_eq!(status.code(), Some(expected_code));
+    assert_exits(child);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn sigterm_is_forwarded_to_exec_child() {
+    interrupt_exec(Signal::SIGTERM, 143);
+}
+
+#[test]
+fn sigint_is_forwarded_to_exec_child() {
+    interrupt_exec(Signal::SIGINT, 130);
+}
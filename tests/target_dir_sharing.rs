@@ -0,0 +1,77 @@
+//! Compares the cold build time of a second target with a shared target directory
+//! versus `--per-target-dir`. Run with:
+//! `cargo test --test target_dir_sharing -- --ignored --nocapture`
+//! Requires network access to download the toolchains for both targets.
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const FIRST_TARGET: &str = "x86_64-unknown-linux-musl";
+const SECOND_TARGET: &str = "aarch64-unknown-linux-musl";
+
+fn write_fixture(dir: &Path) {
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        r#"[package]
+name = "target-dir-sharing-fixture"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+serde = { version = "1", features = ["derive"] }
+serde_json = "1"
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("src/main.rs"),
+        r#"#[derive(serde::Serialize)]
+struct Point { x: i32, y: i32 }
+
+fn main() {
+    println!("{}", serde_json::to_string(&Point { x: 1, y: 2 }).unwrap());
+}
+"#,
+    )
+    .unwrap();
+}
+
+fn build(dir: &Path, target: &str, extra: &[&str]) -> Duration {
+    let start = Instant::now();
+    let status = Command::new(env!("CARGO_BIN_EXE_cargo-cross"))
+        .args(["build", "-t", target])
+        .args(extra)
+        .current_dir(dir)
+        .env("NO_COLOR", "1")
+        .status()
+        .expect("failed to run cargo-cross");
+    assert!(status.success(), "build for {target} failed");
+    start.elapsed()
+}
+
+fn second_target_time(name: &str, extra: &[&str]) -> Duration {
+    let dir = std::env::temp_dir().join(format!(
+        "cargo-cross-target-dir-{name}-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    write_fixture(&dir);
+
+    build(&dir, FIRST_TARGET, extra);
+    let elapsed = build(&dir, SECOND_TARGET, extra);
+
+    let _ = std::fs::remove_dir_all(&dir);
+    elapsed
+}
+
+#[test]
+#[ignore = "benchmark: downloads toolchains and builds crates from crates.io"]
+fn bench_second_target_shared_vs_per_target_dir() {
+    let shared = second_target_time("shared", &[]);
+    let per_target = second_target_time("per-target", &["--per-target-dir"]);
+
+    println!("second target with shared target dir: {shared:?}");
+    println!("second target with --per-target-dir:  {per_target:?}");
+}
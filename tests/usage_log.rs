@@ -0,0 +1,43 @@
+//! `--usage-log` records runs that stop before any target is built
+
+use std::process::Command;
+
+#[test]
+fn run_failing_before_the_targets_is_logged() {
+    let dir = std::env::temp_dir().join(format!("cargo-cross-usage-log-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"usage-log\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    let log = dir.join("usage.jsonl");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-cross"))
+        .args([
+            "build",
+            "--no-toolchain-setup",
+            "--profile",
+            "no-such-profile",
+        ])
+        .arg("--usage-log")
+        .arg(&log)
+        .current_dir(&dir)
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("failed to run cargo-cross");
+    assert!(!output.status.success());
+
+    let content = std::fs::read_to_string(&log).expect("usage log was not written");
+    let line: serde_json::Value = serde_json::from_str(content.trim()).unwrap();
+    assert_eq!(line["command"], "build");
+    assert_eq!(line["status"], "failed");
+    assert!(
+        line["error"].as_str().unwrap().contains("no-such-profile"),
+        "{line}"
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
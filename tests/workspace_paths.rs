@@ -0,0 +1,133 @@
+//! Resolves workspace-derived paths with `-C` and `--manifest-path` against a temporary
+//! workspace, the way `cargo cross build` does before building
+
+use cargo_cross::cargo::effective_target_dir;
+use cargo_cross::cli::{test_target_args, BuildArgs};
+use cargo_cross::metadata::{effective_cwd, resolve_workspace};
+use cargo_cross::{bench, Args, Command};
+use std::path::{Path, PathBuf};
+
+const TARGET: &str = "aarch64-unknown-linux-musl";
+
+/// A workspace with a root package and a `member` package below it
+fn write_workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "cargo-cross-workspace-{name}-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    for package in [&dir, &dir.join("member")] {
+        std::fs::create_dir_all(package.join("src")).unwrap();
+        std::fs::write(package.join("src/main.rs"), "fn main() {}\n").unwrap();
+    }
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"ws-root\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+         [workspace]\nmembers = [\"member\"]\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("member/Cargo.toml"),
+        "[package]\nname = \"ws-member\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    dir.canonicalize().unwrap()
+}
+
+fn args(build: BuildArgs) -> Args {
+    Args {
+        command: Command::bench(),
+        ..test_target_args("workspace", &[TARGET], build)
+    }
+}
+
+async fn resolved(build: BuildArgs) -> Args {
+    let mut args = args(build);
+    resolve_workspace(&mut args).await;
+    assert!(args.workspace_paths.is_some(), "cargo metadata failed");
+    args
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[tokio::test]
+async fn directory_of_a_member_uses_the_workspace_target_dir() {
+    let ws = write_workspace("member");
+    let args = resolved(BuildArgs {
+        cargo_cwd: Some(ws.join("member")),
+        ..BuildArgs::default()
+    })
+    .await;
+
+    let workspace = args.workspace_paths.as_ref().unwrap();
+    assert_eq!(canonical(&workspace.root), ws);
+    assert_eq!(effective_cwd(&args), ws.join("member"));
+    assert_eq!(effective_target_dir(TARGET, &args), ws.join("target"));
+    assert_eq!(
+        bench::results_dir(TARGET, &args),
+        ws.join("target").join(TARGET).join("bench")
+    );
+
+    let _ = std::fs::remove_dir_all(&ws);
+}
+
+#[tokio::test]
+async fn manifest_path_without_directory() {
+    let ws = write_workspace("manifest");
+    let args = resolved(BuildArgs {
+        manifest_path: Some(ws.join("member/Cargo.toml")),
+        ..BuildArgs::default()
+    })
+    .await;
+
+    assert_eq!(canonical(&args.workspace_paths.as_ref().unwrap().root), ws);
+    assert_eq!(effective_cwd(&args), std::env::current_dir().unwrap());
+    assert_eq!(effective_target_dir(TARGET, &args), ws.join("target"));
+
+    let _ = std::fs::remove_dir_all(&ws);
+}
+
+#[tokio::test]
+async fn relative_manifest_path_and_target_dir_follow_the_directory() {
+    let ws = write_workspace("relative");
+    let parent = ws.parent().unwrap().to_path_buf();
+    let name = ws.file_name().unwrap();
+    let args = resolved(BuildArgs {
+        cargo_cwd: Some(parent.clone()),
+        manifest_path: Some(Path::new(name).join("Cargo.toml")),
+        cargo_target_dir: Some(PathBuf::from("out")),
+        artifact_dir: Some(PathBuf::from("dist")),
+        ..BuildArgs::default()
+    })
+    .await;
+
+    assert_eq!(canonical(&args.workspace_paths.as_ref().unwrap().root), ws);
+    // Relative command-line paths are relative to -C, as they are for cargo
+    assert_eq!(effective_target_dir(TARGET, &args), parent.join("out"));
+    assert_eq!(
+        bench::results_dir(TARGET, &args),
+        parent.join("dist").join(TARGET).join("bench")
+    );
+
+    let _ = std::fs::remove_dir_all(&ws);
+}
+
+#[tokio::test]
+async fn per_target_dir_is_relative_to_the_directory() {
+    let ws = write_workspace("per-target");
+    let args = resolved(BuildArgs {
+        cargo_cwd: Some(ws.clone()),
+        per_target_dir: true,
+        ..BuildArgs::default()
+    })
+    .await;
+
+    assert_eq!(
+        effective_target_dir(TARGET, &args),
+        ws.join("target").join("per-target").join(TARGET)
+    );
+
+    let _ = std::fs::remove_dir_all(&ws);
+}